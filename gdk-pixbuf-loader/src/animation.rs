@@ -0,0 +1,241 @@
+//! Animated SVGs as a `GdkPixbufAnimation`.
+//!
+//! Frames are rendered when an iterator advances to them, instead of all of them up
+//! front, so that only a couple of frames are in memory at any time.
+
+use std::cell::{Cell, OnceCell, RefCell};
+use std::time::{Duration, SystemTime};
+
+use gdk_pixbuf::subclass::prelude::*;
+use gdk_pixbuf::{Pixbuf, PixbufAnimation, PixbufAnimationIter};
+use glib::prelude::*;
+
+use libc::c_int;
+
+use rsvg::SvgHandle;
+
+use crate::options::LoaderOptions;
+
+/// Samples the timeline of an animated document into frames.
+pub struct Frames {
+    pub handle: SvgHandle,
+    pub options: LoaderOptions,
+
+    /// Size of the document, and size of the frames that it gets scaled to.
+    pub document_size: (f64, f64),
+    pub size: (c_int, c_int),
+
+    /// Time at which the animations are finished.
+    pub end: Duration,
+
+    /// Frames per second, and number of frames in the animation.
+    pub rate: f64,
+    pub num_frames: u32,
+
+    /// Whether the animation starts over after the last frame.
+    pub loops: bool,
+}
+
+impl Frames {
+    /// Returns the index of the frame to show at `elapsed` time since the start.
+    fn index_at(&self, elapsed: Duration) -> u32 {
+        let index = (elapsed.as_secs_f64() * self.rate).floor();
+        let num_frames = f64::from(self.num_frames);
+
+        if self.loops {
+            (index % num_frames) as u32
+        } else {
+            index.min(num_frames - 1.0) as u32
+        }
+    }
+
+    fn is_last(&self, index: u32) -> bool {
+        !self.loops && index + 1 >= self.num_frames
+    }
+
+    /// Renders the frame at `index`.
+    pub fn render(&mut self, index: u32) -> Result<Pixbuf, String> {
+        // Rounding can push the last sample past the end; clamp it to the duration
+        // itself rather than risk a value that does not fit in a Duration.
+        let time = Duration::try_from_secs_f64(f64::from(index) / self.rate)
+            .map_or(self.end, |t| t.min(self.end));
+        self.handle.set_animation_time(time);
+
+        crate::render(&self.handle, &self.options, self.document_size, self.size)
+    }
+}
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct SvgAnimation {
+        pub frames: RefCell<Option<Frames>>,
+        pub first_frame: OnceCell<Pixbuf>,
+
+        /// Last frame that got rendered, shared by all the iterators.
+        pub current: RefCell<Option<(u32, Pixbuf)>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SvgAnimation {
+        const NAME: &'static str = "RsvgPixbufAnimation";
+
+        type Type = super::SvgAnimation;
+        type ParentType = PixbufAnimation;
+    }
+
+    impl ObjectImpl for SvgAnimation {}
+
+    impl PixbufAnimationImpl for SvgAnimation {
+        fn is_static_image(&self) -> bool {
+            false
+        }
+
+        fn static_image(&self) -> Option<Pixbuf> {
+            self.first_frame.get().cloned()
+        }
+
+        fn size(&self) -> (i32, i32) {
+            self.frames.borrow().as_ref().map_or((0, 0), |f| f.size)
+        }
+
+        fn iter(&self, start_time: SystemTime) -> PixbufAnimationIter {
+            super::SvgAnimationIter::new(&self.obj(), start_time).upcast()
+        }
+    }
+
+    #[derive(Default)]
+    pub struct SvgAnimationIter {
+        pub animation: OnceCell<super::SvgAnimation>,
+        pub start_time: Cell<Option<SystemTime>>,
+        pub index: Cell<u32>,
+        pub pixbuf: RefCell<Option<Pixbuf>>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SvgAnimationIter {
+        const NAME: &'static str = "RsvgPixbufAnimationIter";
+
+        type Type = super::SvgAnimationIter;
+        type ParentType = PixbufAnimationIter;
+    }
+
+    impl ObjectImpl for SvgAnimationIter {}
+
+    impl PixbufAnimationIterImpl for SvgAnimationIter {
+        fn delay_time(&self) -> Option<Duration> {
+            let animation = self.animation.get()?;
+            let frames = animation.imp().frames.borrow();
+            let frames = frames.as_ref()?;
+
+            if frames.is_last(self.index.get()) {
+                None
+            } else {
+                Some(Duration::from_secs_f64(1.0 / frames.rate))
+            }
+        }
+
+        fn pixbuf(&self) -> Pixbuf {
+            self.pixbuf
+                .borrow()
+                .clone()
+                .expect("iterator is created with the first frame")
+        }
+
+        fn on_currently_loading_frame(&self) -> bool {
+            false
+        }
+
+        fn advance(&self, current_time: SystemTime) -> bool {
+            let (Some(animation), Some(start_time)) = (self.animation.get(), self.start_time.get())
+            else {
+                return false;
+            };
+
+            let elapsed = current_time.duration_since(start_time).unwrap_or_default();
+
+            let Some(index) = animation
+                .imp()
+                .frames
+                .borrow()
+                .as_ref()
+                .map(|f| f.index_at(elapsed))
+            else {
+                return false;
+            };
+
+            if index == self.index.get() {
+                return false;
+            }
+
+            match animation.frame(index) {
+                Ok(pixbuf) => {
+                    self.index.set(index);
+                    *self.pixbuf.borrow_mut() = Some(pixbuf);
+                    true
+                }
+
+                Err(e) => {
+                    glib::g_warning!("librsvg", "could not render animation frame: {e}");
+                    false
+                }
+            }
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct SvgAnimation(ObjectSubclass<imp::SvgAnimation>) @extends PixbufAnimation;
+}
+
+glib::wrapper! {
+    pub struct SvgAnimationIter(ObjectSubclass<imp::SvgAnimationIter>) @extends PixbufAnimationIter;
+}
+
+impl SvgAnimation {
+    pub fn new(frames: Frames, first_frame: Pixbuf) -> SvgAnimation {
+        let animation: SvgAnimation = glib::Object::new();
+
+        let imp = animation.imp();
+        *imp.frames.borrow_mut() = Some(frames);
+        *imp.current.borrow_mut() = Some((0, first_frame.clone()));
+        let _ = imp.first_frame.set(first_frame);
+
+        animation
+    }
+
+    /// Returns the frame at `index`, rendering it if it is not the current one.
+    fn frame(&self, index: u32) -> Result<Pixbuf, String> {
+        let imp = self.imp();
+
+        if let Some((current, ref pixbuf)) = *imp.current.borrow() {
+            if current == index {
+                return Ok(pixbuf.clone());
+            }
+        }
+
+        let pixbuf = imp
+            .frames
+            .borrow_mut()
+            .as_mut()
+            .ok_or_else(|| String::from("animation has no frames"))?
+            .render(index)?;
+
+        *imp.current.borrow_mut() = Some((index, pixbuf.clone()));
+        Ok(pixbuf)
+    }
+}
+
+impl SvgAnimationIter {
+    fn new(animation: &SvgAnimation, start_time: SystemTime) -> SvgAnimationIter {
+        let iter: SvgAnimationIter = glib::Object::new();
+
+        let imp = iter.imp();
+        let _ = imp.animation.set(animation.clone());
+        imp.start_time.set(Some(start_time));
+        *imp.pixbuf.borrow_mut() = animation.imp().first_frame.get().cloned();
+
+        iter
+    }
+}
@@ -1,7 +1,14 @@
+//! Loader module for gdk-pixbuf, written in Rust.
+//!
+//! This is not installed yet; the module that gets built and installed is the one in
+//! `io-svg.c`, which uses the C API.  Features that only exist here, like animated SVGs and
+//! the `RSVG_PIXBUF_OPTIONS` environment variable, are not available to applications until
+//! this module replaces it.
+
 use std::ptr::null_mut;
 
 use gdk_pixbuf::ffi::{
-    GdkPixbuf, GdkPixbufFormat, GdkPixbufModule, GdkPixbufModulePattern,
+    GdkPixbuf, GdkPixbufAnimation, GdkPixbufFormat, GdkPixbufModule, GdkPixbufModulePattern,
     GdkPixbufModulePreparedFunc, GdkPixbufModuleSizeFunc, GdkPixbufModuleUpdatedFunc,
     GDK_PIXBUF_FORMAT_SCALABLE, GDK_PIXBUF_FORMAT_THREADSAFE,
 };
@@ -12,15 +19,34 @@ use glib::ffi::{gboolean, gpointer, GError};
 use glib::translate::*;
 use glib::Bytes;
 
+use gdk_pixbuf::{Pixbuf, PixbufAnimation};
 use gio::prelude::MemoryInputStreamExt;
 use gio::MemoryInputStream;
 use glib::gobject_ffi::GObject;
+use glib::prelude::*;
 
 use librsvg_c::sizing::LegacySize;
-use rsvg::Loader;
+use rsvg::SvgHandle;
 
 use cstr::cstr;
 
+mod animation;
+use animation::{Frames, SvgAnimation};
+
+mod options;
+use options::LoaderOptions;
+
+/// Frames per second at which SMIL animations are sampled.
+const FRAME_RATE: f64 = 20.0;
+
+/// Maximum number of frames for an animation; longer animations are sampled at a lower rate.
+const MAX_FRAMES: f64 = 600.0;
+
+/// Maximum number of pixels in all the frames of an animation together; bigger animations
+/// are sampled at a lower rate, and ones where even two frames would exceed this are loaded
+/// as a static image.
+const MAX_ANIMATION_PIXELS: f64 = (1u64 << 30) as f64;
+
 struct SvgContext {
     size_func: GdkPixbufModuleSizeFunc,
     prep_func: GdkPixbufModulePreparedFunc,
@@ -71,6 +97,28 @@ unsafe extern "C" fn load_increment(
     true.into_glib()
 }
 
+/// Renders the document scaled to `size`.
+///
+/// The document gets scaled to the size that the caller asked for, instead of being
+/// rendered at its own size into a viewport of the requested size; the latter would leave
+/// blank space around it if the aspect ratio changes.
+fn render(
+    handle: &SvgHandle,
+    options: &LoaderOptions,
+    (document_width, document_height): (f64, f64),
+    (w, h): (c_int, c_int),
+) -> Result<Pixbuf, String> {
+    let renderer = options.renderer(handle);
+    librsvg_c::pixbuf_utils::render_to_pixbuf_at_size(
+        &renderer,
+        document_width,
+        document_height,
+        w as f64,
+        h as f64,
+    )
+    .map_err(|e| e.to_string())
+}
+
 #[no_mangle]
 unsafe extern "C" fn stop_load(user_data: gpointer, error: *mut *mut GError) -> gboolean {
     let ctx = Box::from_raw(user_data as *mut SvgContext);
@@ -78,15 +126,21 @@ unsafe extern "C" fn stop_load(user_data: gpointer, error: *mut *mut GError) ->
         *error = null_mut();
     }
 
-    fn _inner_stop_load(ctx: &SvgContext) -> Result<gdk_pixbuf::Pixbuf, String> {
-        let handle = Loader::new()
+    fn _inner_stop_load(ctx: &SvgContext) -> Result<(Pixbuf, Option<SvgAnimation>), String> {
+        let options = LoaderOptions::from_env();
+
+        let mut handle = options
+            .loader()
             .read_stream::<_, gio::File, gio::Cancellable>(&ctx.stream, None, None)
             .map_err(|e| e.to_string())?;
 
-        let renderer = rsvg::CairoRenderer::new(&handle);
-        let (w, h) = renderer.legacy_document_size().map_err(|e| e.to_string())?;
-        let mut w = w.ceil() as c_int;
-        let mut h = h.ceil() as c_int;
+        options.apply_to_handle(&mut handle)?;
+
+        let renderer = options.renderer(&handle);
+        let (document_width, document_height) =
+            renderer.legacy_document_size().map_err(|e| e.to_string())?;
+        let mut w = document_width.ceil() as c_int;
+        let mut h = document_height.ceil() as c_int;
 
         if let Some(size_func) = ctx.size_func {
             let mut tmp_w: c_int = w;
@@ -104,15 +158,51 @@ unsafe extern "C" fn stop_load(user_data: gpointer, error: *mut *mut GError) ->
             }
         }
 
-        let pb = librsvg_c::pixbuf_utils::render_to_pixbuf_at_size(
-            &renderer, w as f64, h as f64, w as f64, h as f64,
-        )
-        .map_err(|e| e.to_string())?;
+        let document_size = (document_width, document_height);
+        let size = (w, h);
+
+        let info = handle.animation_info();
+        let max_frames =
+            MAX_FRAMES.min((MAX_ANIMATION_PIXELS / (f64::from(w) * f64::from(h))).floor());
 
-        Ok(pb)
+        let end = match info.duration {
+            Some(end) if max_frames >= 2.0 => end,
+            _ => return Ok((render(&handle, &options, document_size, size)?, None)),
+        };
+        let duration = end.as_secs_f64();
+
+        // Animations that loop are sampled for one iteration; otherwise, the last frame
+        // shows the final state of the animation.
+        let rate = if info.loops {
+            FRAME_RATE.min(max_frames / duration)
+        } else {
+            FRAME_RATE.min((max_frames - 1.0) / duration)
+        };
+        let num_frames = (duration * rate).ceil() as u32;
+        let num_frames = if info.loops {
+            num_frames.max(1)
+        } else {
+            num_frames + 1
+        };
+
+        let mut frames = Frames {
+            handle,
+            options,
+            document_size,
+            size,
+            end,
+            rate,
+            num_frames,
+            loops: info.loops,
+        };
+
+        let first_frame = frames.render(0)?;
+        let anim = SvgAnimation::new(frames, first_frame.clone());
+
+        Ok((first_frame, Some(anim)))
     }
 
-    let pixbuf = match _inner_stop_load(&ctx) {
+    let (pixbuf, anim) = match _inner_stop_load(&ctx) {
         Ok(r) => r,
         Err(e) => {
             if !error.is_null() {
@@ -127,8 +217,15 @@ unsafe extern "C" fn stop_load(user_data: gpointer, error: *mut *mut GError) ->
     let h = pixbuf.height();
     let pixbuf: *mut GdkPixbuf = pixbuf.to_glib_full();
 
+    // For animated documents, the loader shows the first frame until the animation is
+    // picked up by the caller.
+    let anim: *mut GdkPixbufAnimation = anim
+        .as_ref()
+        .map(|a| a.upcast_ref::<PixbufAnimation>().to_glib_none().0)
+        .unwrap_or(null_mut());
+
     if let Some(prep_func) = ctx.prep_func {
-        prep_func(pixbuf, null_mut(), ctx.user_data);
+        prep_func(pixbuf, anim, ctx.user_data);
     }
     if let Some(update_func) = ctx.update_func {
         update_func(pixbuf, 0, 0, w, h, ctx.user_data);
@@ -310,6 +407,191 @@ mod tests {
                                     </svg>
     "#;
 
+    const ANIMATED_SVG_DATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+  <rect width="10" height="10" fill="lime">
+    <animate attributeName="x" from="0" to="10" dur="1s" repeatCount="indefinite"/>
+  </rect>
+</svg>
+"#;
+
+    #[test]
+    fn animated_svg() {
+        unsafe extern "C" fn prep_cb(
+            pb: *mut gdk_pixbuf::ffi::GdkPixbuf,
+            pba: *mut gdk_pixbuf::ffi::GdkPixbufAnimation,
+            user_data: *mut libc::c_void,
+        ) {
+            assert_eq!(user_data, null_mut());
+            assert_ne!(pba, null_mut());
+
+            assert_eq!(
+                gdk_pixbuf::ffi::gdk_pixbuf_animation_is_static_image(pba),
+                0
+            );
+            assert_eq!(gdk_pixbuf::ffi::gdk_pixbuf_animation_get_width(pba), 20);
+            assert_eq!(gdk_pixbuf::ffi::gdk_pixbuf_animation_get_height(pba), 20);
+
+            // The first frame has the rect at its starting position
+            let pixels = gdk_pixbuf::ffi::gdk_pixbuf_get_pixels(pb);
+            assert_eq!(*pixels.offset(1), 0xff);
+            assert_eq!(*pixels.offset(3), 0xff);
+        }
+
+        unsafe {
+            let ctx = crate::begin_load(None, Some(prep_cb), None, null_mut(), null_mut());
+            assert_ne!(ctx, null_mut());
+
+            let inc = crate::load_increment(
+                ctx,
+                ANIMATED_SVG_DATA.as_ptr(),
+                ANIMATED_SVG_DATA.len() as u32,
+                null_mut(),
+            );
+            assert_ne!(inc, 0);
+
+            assert_ne!(crate::stop_load(ctx, null_mut()), 0);
+        }
+    }
+
+    #[test]
+    fn animation_frames_are_rendered_on_demand() {
+        unsafe extern "C" fn prep_cb(
+            _pb: *mut gdk_pixbuf::ffi::GdkPixbuf,
+            pba: *mut gdk_pixbuf::ffi::GdkPixbufAnimation,
+            _user_data: *mut libc::c_void,
+        ) {
+            use gdk_pixbuf::prelude::*;
+            use glib::translate::from_glib_none;
+            use std::time::{Duration, SystemTime};
+
+            let anim: gdk_pixbuf::PixbufAnimation = from_glib_none(pba);
+
+            let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+            let iter = anim.iter(Some(start));
+            assert_eq!(iter.delay_time(), Some(Duration::from_millis(50)));
+
+            // Halfway through, the rect has moved by 5 pixels
+            assert!(iter.advance(start + Duration::from_millis(500)));
+            assert!(!iter.advance(start + Duration::from_millis(520)));
+
+            let pb = iter.pixbuf();
+            let stride = pb.rowstride() as usize;
+            let pixels = pb.read_pixel_bytes();
+            let alpha = |x: usize, y: usize| pixels[y * stride + x * 4 + 3];
+
+            assert_eq!(alpha(2, 5), 0);
+            assert_eq!(alpha(12, 5), 0xff);
+        }
+
+        load_animated(ANIMATED_SVG_DATA, Some(prep_cb));
+    }
+
+    fn load_animated(data: &str, prep_cb: gdk_pixbuf::ffi::GdkPixbufModulePreparedFunc) {
+        unsafe {
+            let ctx = crate::begin_load(None, prep_cb, None, null_mut(), null_mut());
+            assert_ne!(ctx, null_mut());
+
+            let inc = crate::load_increment(ctx, data.as_ptr(), data.len() as u32, null_mut());
+            assert_ne!(inc, 0);
+
+            assert_ne!(crate::stop_load(ctx, null_mut()), 0);
+        }
+    }
+
+    #[test]
+    fn huge_animation_durations() {
+        unsafe extern "C" fn animated_cb(
+            _pb: *mut gdk_pixbuf::ffi::GdkPixbuf,
+            pba: *mut gdk_pixbuf::ffi::GdkPixbufAnimation,
+            _user_data: *mut libc::c_void,
+        ) {
+            assert_ne!(pba, null_mut());
+        }
+
+        unsafe extern "C" fn static_cb(
+            _pb: *mut gdk_pixbuf::ffi::GdkPixbuf,
+            pba: *mut gdk_pixbuf::ffi::GdkPixbufAnimation,
+            _user_data: *mut libc::c_void,
+        ) {
+            assert_eq!(pba, null_mut());
+        }
+
+        let svg = |dur: &str| {
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+  <rect width="10" height="10" fill="lime">
+    <animate attributeName="x" from="0" to="10" dur="{dur}"/>
+  </rect>
+</svg>
+"#
+            )
+        };
+
+        // Still fits in a Duration, so it gets sampled into a capped number of frames
+        load_animated(&svg("10000000000000000000s"), Some(animated_cb));
+
+        // Too long for a Duration, so the document loads as a static image
+        load_animated(&svg("100000000000000000000s"), Some(static_cb));
+        load_animated(&svg(&format!("{}h", "9".repeat(400))), Some(static_cb));
+    }
+
+    const HALVES_SVG_DATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+  <rect width="10" height="10" fill="red"/>
+  <rect x="10" width="10" height="10" fill="blue"/>
+</svg>
+"#;
+
+    #[test]
+    fn requested_size_scales_document() {
+        unsafe extern "C" fn size_cb(
+            width: *mut libc::c_int,
+            height: *mut libc::c_int,
+            _user_data: *mut libc::c_void,
+        ) {
+            assert_eq!((*width, *height), (20, 10));
+
+            *width = 40;
+            *height = 40;
+        }
+
+        unsafe extern "C" fn prep_cb(
+            pb: *mut gdk_pixbuf::ffi::GdkPixbuf,
+            _pba: *mut gdk_pixbuf::ffi::GdkPixbufAnimation,
+            _user_data: *mut libc::c_void,
+        ) {
+            assert_eq!(gdk_pixbuf::ffi::gdk_pixbuf_get_width(pb), 40);
+            assert_eq!(gdk_pixbuf::ffi::gdk_pixbuf_get_height(pb), 40);
+
+            let stride = gdk_pixbuf::ffi::gdk_pixbuf_get_rowstride(pb) as isize;
+            let pixels = gdk_pixbuf::ffi::gdk_pixbuf_get_pixels(pb);
+            let pixel = |x: isize, y: isize| {
+                let p = pixels.offset(y * stride + x * 4);
+                (*p, *p.offset(1), *p.offset(2), *p.offset(3))
+            };
+
+            // Each half gets stretched to the new aspect ratio
+            assert_eq!(pixel(5, 35), (0xff, 0, 0, 0xff));
+            assert_eq!(pixel(35, 35), (0, 0, 0xff, 0xff));
+        }
+
+        unsafe {
+            let ctx = crate::begin_load(Some(size_cb), Some(prep_cb), None, null_mut(), null_mut());
+            assert_ne!(ctx, null_mut());
+
+            let inc = crate::load_increment(
+                ctx,
+                HALVES_SVG_DATA.as_ptr(),
+                HALVES_SVG_DATA.len() as u32,
+                null_mut(),
+            );
+            assert_ne!(inc, 0);
+
+            assert_ne!(crate::stop_load(ctx, null_mut()), 0);
+        }
+    }
+
     #[test]
     fn minimal_svg() {
         unsafe extern "C" fn prep_cb(
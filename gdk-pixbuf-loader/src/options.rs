@@ -0,0 +1,138 @@
+//! Options for tuning the loader from outside the application.
+//!
+//! Programs that load SVGs through gdk-pixbuf don't know about librsvg, and gdk-pixbuf has no
+//! way to pass options down to a loader module.  So, the loader reads them from the
+//! `RSVG_PIXBUF_OPTIONS` environment variable instead, as a comma-separated list of
+//! `key=value` pairs, for example `dpi=192,max-nodes=100000`.  The recognized keys are:
+//!
+//! * `dpi`: pixel density for resolving physical units like `cm`; the default is 96.
+//!
+//! * `stylesheet`: path to a CSS file to apply to every document, as a user stylesheet.
+//!
+//! * `max-nodes`: maximum number of elements that a document can have.
+//!
+//! Unknown keys and invalid values are ignored with a warning.  There is deliberately no
+//! option to disable the XML parser's safety limits, since anything that can set an
+//! environment variable for a program would then be able to turn them off.
+//!
+//! Only this Rust loader module reads `RSVG_PIXBUF_OPTIONS`; the `io-svg.c` module, which
+//! is the one that gets installed, ignores it.
+
+use std::path::PathBuf;
+
+use rsvg::{CairoRenderer, Loader, SvgHandle};
+
+const ENV_VAR: &str = "RSVG_PIXBUF_OPTIONS";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct LoaderOptions {
+    pub dpi: Option<f64>,
+    pub stylesheet: Option<PathBuf>,
+    pub max_nodes: Option<usize>,
+}
+
+impl LoaderOptions {
+    pub fn from_env() -> LoaderOptions {
+        std::env::var(ENV_VAR)
+            .map(|s| LoaderOptions::parse(&s))
+            .unwrap_or_default()
+    }
+
+    fn parse(s: &str) -> LoaderOptions {
+        let mut options = LoaderOptions::default();
+
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            if !options.set(item) {
+                glib::g_warning!("librsvg", "ignoring invalid option in {ENV_VAR}: {item}");
+            }
+        }
+
+        options
+    }
+
+    /// Sets an option from a `key=value` pair; returns whether it was valid.
+    fn set(&mut self, item: &str) -> bool {
+        let Some((key, value)) = item.split_once('=') else {
+            return false;
+        };
+
+        let value = value.trim();
+
+        match key.trim() {
+            "dpi" => match value.parse::<f64>() {
+                Ok(dpi) if dpi.is_finite() && dpi > 0.0 => self.dpi = Some(dpi),
+                _ => return false,
+            },
+
+            "stylesheet" if !value.is_empty() => self.stylesheet = Some(PathBuf::from(value)),
+
+            "max-nodes" => match value.parse::<usize>() {
+                Ok(max) if max > 0 => self.max_nodes = Some(max),
+                _ => return false,
+            },
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn loader(&self) -> Loader {
+        let loader = Loader::new();
+
+        match self.max_nodes {
+            Some(max) => loader.with_max_elements(max),
+            None => loader,
+        }
+    }
+
+    /// Applies the options that take effect after the document is loaded.
+    pub fn apply_to_handle(&self, handle: &mut SvgHandle) -> Result<(), String> {
+        if let Some(ref path) = self.stylesheet {
+            handle
+                .set_stylesheet_from_file(&gio::File::for_path(path))
+                .map_err(|e| format!("could not load stylesheet {}: {e}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn renderer<'a>(&self, handle: &'a SvgHandle) -> CairoRenderer<'a> {
+        let renderer = CairoRenderer::new(handle);
+
+        match self.dpi {
+            Some(dpi) => renderer.with_dpi(dpi, dpi),
+            None => renderer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_options() {
+        assert_eq!(
+            LoaderOptions::parse("dpi=192, stylesheet=/tmp/style.css,max-nodes=1000"),
+            LoaderOptions {
+                dpi: Some(192.0),
+                stylesheet: Some(PathBuf::from("/tmp/style.css")),
+                max_nodes: Some(1000),
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_options() {
+        assert_eq!(
+            LoaderOptions::parse(
+                "dpi=-5,max-nodes=lots,frobnicate=yes,unlimited-size=true,,dpi=72"
+            ),
+            LoaderOptions {
+                dpi: Some(72.0),
+                ..LoaderOptions::default()
+            }
+        );
+    }
+}
@@ -187,6 +187,61 @@ gboolean rsvg_handle_render_document (RsvgHandle           *handle,
                                       const RsvgRectangle  *viewport,
                                       GError              **error);
 
+/**
+ * rsvg_handle_render_document_async:
+ * @handle: An [class@Rsvg.Handle]
+ * @cr: A Cairo context
+ * @viewport: Viewport size at which the whole SVG would be fitted.
+ * @cancellable: (nullable): A `GCancellable`, or `NULL`
+ * @callback: (scope async): A `GAsyncReadyCallback` to call when rendering is done
+ * @user_data: User data to pass to @callback
+ *
+ * Asynchronously renders the whole SVG document fitted to a viewport, like
+ * [method@Rsvg.Handle.render_document], but does the rendering in a worker thread.
+ *
+ * This function takes a reference to @cr until the operation is done.  You must not
+ * use @handle or @cr, nor the surface that @cr draws on, until @callback has been
+ * called; usually you want @cr to draw on an image surface that is not shared with
+ * anything else.  Call [method@Rsvg.Handle.render_document_finish] from @callback
+ * to obtain the result.
+ *
+ * Note that a resource loader set with [method@Rsvg.Handle.set_resource_loader] may be
+ * called from the worker thread.
+ *
+ * The @cancellable is checked before rendering starts; once rendering is underway
+ * it will run to completion.
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+void rsvg_handle_render_document_async (RsvgHandle          *handle,
+                                        cairo_t             *cr,
+                                        const RsvgRectangle *viewport,
+                                        GCancellable        *cancellable,
+                                        GAsyncReadyCallback  callback,
+                                        gpointer             user_data);
+
+/**
+ * rsvg_handle_render_document_finish:
+ * @handle: An [class@Rsvg.Handle]
+ * @result: The `GAsyncResult` passed to the callback of [method@Rsvg.Handle.render_document_async]
+ * @error: return location for a `GError`
+ *
+ * Finishes an operation started with [method@Rsvg.Handle.render_document_async].
+ *
+ * Returns: `TRUE` on success, `FALSE` on error or if the operation was cancelled.
+ * Errors are returned in the @error argument.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gboolean rsvg_handle_render_document_finish (RsvgHandle    *handle,
+                                             GAsyncResult  *result,
+                                             GError       **error);
+
 /**
  * rsvg_handle_get_geometry_for_layer:
  * @handle: An [class@Rsvg.Handle]
@@ -240,6 +295,80 @@ gboolean rsvg_handle_get_geometry_for_layer (RsvgHandle     *handle,
                                              RsvgRectangle  *out_logical_rect,
                                              GError        **error);
 
+/**
+ * rsvg_handle_get_geometries:
+ * @handle: An [class@Rsvg.Handle]
+ * @ids: (array zero-terminated=1): A `NULL`-terminated array of element ids, each
+ * starting with "#" (a single hash character), for example, `#layer1`.
+ * @viewport: Viewport size at which the whole SVG would be fitted.
+ * @error: return location for a `GError`
+ *
+ * Computes the ink rectangle and logical rectangle of several SVG elements at once, as
+ * if the whole SVG were rendered to a specific viewport.
+ *
+ * This returns one [struct@Rsvg.ElementGeometry] for each element in @ids, in the same
+ * order.  The SVG is measured a single time for all the elements, so this is much faster
+ * than calling [method@Rsvg.Handle.get_geometry_for_layer] for each of them.
+ *
+ * Unlike with [method@Rsvg.Handle.get_geometry_for_layer], effects from the elements'
+ * ancestors, like filters, are not taken into account.  Elements which are not rendered
+ * in their place in the document, for example because they are inside a `<defs>`, get
+ * empty rectangles.
+ *
+ * Returns: (transfer full) (element-type RsvgElementGeometry) (nullable): An array with
+ * the geometries of the elements, or `NULL` on error, for example if one of the @ids
+ * does not exist.  Errors are returned in the @error argument.  Free the array with
+ * [func@GLib.Array.unref].
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+GArray *rsvg_handle_get_geometries (RsvgHandle          *handle,
+                                    const char * const  *ids,
+                                    const RsvgRectangle *viewport,
+                                    GError             **error);
+
+/**
+ * rsvg_handle_get_element_at_point:
+ * @handle: An [class@Rsvg.Handle]
+ * @viewport: Viewport size at which the whole SVG would be fitted.
+ * @x: Horizontal coordinate of the point, in the same coordinates as the @viewport.
+ * @y: Vertical coordinate of the point, in the same coordinates as the @viewport.
+ * @error: return location for a `GError`
+ *
+ * Finds the topmost element that is painted at a point, as if the whole SVG were
+ * rendered to a specific viewport.  Viewers can use this to find out which element
+ * the user clicked on, for example.
+ *
+ * Shapes are hit where their fill or stroke gets painted, and text and images anywhere
+ * within their bounding box.  Content drawn through a `<use>` element counts as the
+ * `<use>` itself.  If the element under the point does not have an `id`, this returns
+ * the id of its closest ancestor that has one.  Clipping paths, masks, and filters are
+ * not taken into account.
+ *
+ * The id is returned without a leading "#" character, as with
+ * [method@Rsvg.Handle.get_element_ids]; prepend one to pass it to functions like
+ * [method@Rsvg.Handle.render_layer].
+ *
+ * Returns: (transfer full) (nullable): The id of the element at the point, or `NULL` if
+ * there is no element with an id there, or on error.  Errors are returned in the @error
+ * argument.  Free the id with [func@GLib.free].
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+char *rsvg_handle_get_element_at_point (RsvgHandle          *handle,
+                                        const RsvgRectangle *viewport,
+                                        double               x,
+                                        double               y,
+                                        GError             **error);
+
 /**
  * rsvg_handle_render_layer:
  * @handle: An [class@Rsvg.Handle]
@@ -390,6 +519,29 @@ gboolean rsvg_handle_render_element (RsvgHandle           *handle,
                                      const RsvgRectangle  *element_viewport,
                                      GError              **error);
 
+/**
+ * rsvg_handle_set_font_options:
+ * @handle: A [class@Rsvg.Handle]
+ * @options: (nullable): Font options to use for text, or `NULL` to use librsvg's defaults.
+ *
+ * Sets the Cairo font options to use for the text in the SVG document.
+ *
+ * By default librsvg turns off font hinting, so that text looks the same at any scale,
+ * and uses Cairo's defaults for everything else.  The options that are set in @options,
+ * like the antialiasing mode, hint style, hint metrics, or subpixel order, override
+ * those choices; the ones that are left at their default values don't.  Elements with
+ * `text-rendering="optimizeSpeed"` are still drawn without antialiasing.
+ *
+ * The options are used for all the functions that render or measure the document.  This
+ * function can be called before or after the @handle is loaded; librsvg keeps a copy of
+ * @options.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+void rsvg_handle_set_font_options (RsvgHandle                 *handle,
+                                   const cairo_font_options_t *options);
+
 G_END_DECLS
 
 #endif
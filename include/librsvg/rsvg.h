@@ -54,12 +54,34 @@ G_BEGIN_DECLS
 
 /**
  * RsvgError:
- * @RSVG_ERROR_FAILED: the request failed
- *
- * An enumeration representing possible errors
+ * @RSVG_ERROR_FAILED: the request failed, for a reason not covered by the other codes.
+ * @RSVG_ERROR_XML_PARSE: the data is not well-formed XML, or it does not have an `<svg>`
+ *   root element.  Since: 2.58
+ * @RSVG_ERROR_CSS: a stylesheet could not be parsed.  Since: 2.58
+ * @RSVG_ERROR_INVALID_REFERENCE: an element or URL that was referenced does not exist,
+ *   is malformed, or is not allowed to be loaded.  Since: 2.58
+ * @RSVG_ERROR_LIMIT_EXCEEDED: one of the limits that librsvg imposes to protect against
+ *   malicious documents was exceeded, for example, the number of elements in the
+ *   document.  Since: 2.58
+ * @RSVG_ERROR_CAIRO: Cairo could not create a surface or render to it; for example, the
+ *   requested size is too big.  Since: 2.58
+ * @RSVG_ERROR_INVALID: the document has a problem that librsvg would normally ignore,
+ *   and it was loaded with `RSVG_HANDLE_FLAG_STRICT`.  Since: 2.58
+ *
+ * An enumeration representing possible errors.
+ *
+ * Before librsvg 2.58, all errors used `RSVG_ERROR_FAILED`.  Calling programs should
+ * handle unknown codes like `RSVG_ERROR_FAILED`, since more codes may be added in the
+ * future.
  */
 typedef enum {
-    RSVG_ERROR_FAILED
+    RSVG_ERROR_FAILED,
+    RSVG_ERROR_XML_PARSE,
+    RSVG_ERROR_CSS,
+    RSVG_ERROR_INVALID_REFERENCE,
+    RSVG_ERROR_LIMIT_EXCEEDED,
+    RSVG_ERROR_CAIRO,
+    RSVG_ERROR_INVALID
 } RsvgError;
 
 #define RSVG_ERROR (rsvg_error_quark ())
@@ -323,31 +345,101 @@ GType rsvg_error_get_type (void);
  */
 
 /**
- * RsvgHandle:title:
+ * RsvgHandle:title: (attributes org.gtk.Property.get=rsvg_handle_get_title)
+ *
+ * SVG's title, from the `<title>` element of the toplevel `<svg>`.
+ *
+ * Before version 2.58, reading this property always returned `NULL`.
+ */
+
+/**
+ * RsvgHandle:desc: (attributes org.gtk.Property.get=rsvg_handle_get_desc)
  *
- * SVG's title.
+ * SVG's description, from the `<desc>` element of the toplevel `<svg>`.
  *
- * Deprecated: 2.36.  Reading this property always returns `NULL`.
+ * Before version 2.58, reading this property always returned `NULL`.
  */
 
 /**
- * RsvgHandle:desc:
+ * RsvgHandle:metadata: (attributes org.gtk.Property.get=rsvg_handle_get_metadata)
  *
- * SVG's description.
+ * SVG's metadata, from the `<metadata>` element of the toplevel `<svg>`, serialized as XML.
  *
- * Deprecated: 2.36.  Reading this property always returns `NULL`.
+ * Before version 2.58, reading this property always returned `NULL`.
  */
 
 /**
- * RsvgHandle:metadata:
+ * RsvgHandle:loaded:
  *
- * SVG's metadata
+ * Whether the handle has finished loading an SVG document successfully.
  *
- * Deprecated: 2.36.  Reading this property always returns `NULL`.
+ * This becomes `TRUE` when [method@Rsvg.Handle.close] or
+ * [method@Rsvg.Handle.read_stream_sync] succeed, and you can connect to its
+ * `notify::loaded` signal to find out when the handle is ready for rendering.  Checking
+ * this property is also a way to avoid the "API ordering" warnings that librsvg emits
+ * when a handle that is not loaded gets rendered.
+ *
+ * Since: 2.58
+ */
+
+/**
+ * RsvgHandle:intrinsic-width:
+ *
+ * Width of the SVG document in pixels, as computed by
+ * [method@Rsvg.Handle.get_intrinsic_size_in_pixels].
+ *
+ * This is `0.0` if the handle is not loaded, or if the document's width cannot be
+ * converted to pixels, for example when it is a percentage.  Unlike the `width`
+ * property, this value is not rounded and it does not go through the size callback.
+ *
+ * Since: 2.58
+ */
+
+/**
+ * RsvgHandle:intrinsic-height:
+ *
+ * Height of the SVG document in pixels, as computed by
+ * [method@Rsvg.Handle.get_intrinsic_size_in_pixels].
+ *
+ * This is `0.0` if the handle is not loaded, or if the document's height cannot be
+ * converted to pixels, for example when it is a percentage.  Unlike the `height`
+ * property, this value is not rounded and it does not go through the size callback.
+ *
+ * Since: 2.58
+ */
+
+/**
+ * RsvgHandle:viewbox:
+ *
+ * The `viewBox` attribute of the toplevel `<svg>` element, as a `GVariant` of type
+ * `(dddd)` with the x, y, width, and height of the box.
+ *
+ * This is `NULL` if the handle is not loaded, or if the document does not have a
+ * `viewBox`.
+ *
+ * Since: 2.58
  */
 
 /***** End documentation for RsvgHandle properties *****/
 
+/***** Begin documentation for RsvgHandle signals *****/
+
+/**
+ * RsvgHandle::load-progress:
+ * @handle: the [class@Rsvg.Handle] which is loading data
+ * @bytes_consumed: total number of bytes that the handle has received so far
+ *
+ * Emitted while an SVG document is being loaded, each time that [method@Rsvg.Handle.write]
+ * or [method@Rsvg.Handle.read_stream_sync] receive more data.  This can be used to
+ * show the progress of loading a large document.
+ *
+ * For compressed SVGZ documents, @bytes_consumed counts the compressed data.
+ *
+ * Since: 2.58
+ */
+
+/***** End documentation for RsvgHandle signals *****/
+
 #define RSVG_TYPE_HANDLE                  (rsvg_handle_get_type ())
 #define RSVG_HANDLE(obj)                  (G_TYPE_CHECK_INSTANCE_CAST ((obj), RSVG_TYPE_HANDLE, RsvgHandle))
 #define RSVG_HANDLE_CLASS(klass)          (G_TYPE_CHECK_CLASS_CAST ((klass), RSVG_TYPE_HANDLE, RsvgHandleClass))
@@ -363,6 +455,7 @@ typedef struct _RsvgHandleClass RsvgHandleClass;
 typedef struct _RsvgDimensionData RsvgDimensionData;
 typedef struct _RsvgPositionData RsvgPositionData;
 typedef struct _RsvgRectangle RsvgRectangle;
+typedef struct _RsvgElementGeometry RsvgElementGeometry;
 
 /**
  * RsvgHandleClass:
@@ -439,6 +532,20 @@ struct _RsvgRectangle {
     double height;
 };
 
+/**
+ * RsvgElementGeometry:
+ * @ink_rect: ink rectangle of the element
+ * @logical_rect: logical rectangle of the element
+ *
+ * The geometry of one element, as returned by [method@Rsvg.Handle.get_geometries].
+ *
+ * Since: 2.58
+ */
+struct _RsvgElementGeometry {
+    RsvgRectangle ink_rect;
+    RsvgRectangle logical_rect;
+};
+
 /**
  * rsvg_cleanup:
  *
@@ -450,6 +557,23 @@ struct _RsvgRectangle {
 RSVG_DEPRECATED
 void rsvg_cleanup (void);
 
+/**
+ * rsvg_get_features:
+ *
+ * Returns the list of features that this build of librsvg supports.  Each element
+ * is a keyword: `"filters"`, `"text"`, and `"animation"` for the corresponding
+ * features; the MIME types of the raster image formats that can be decoded, like
+ * `"image/png"`; the output backends prefixed with `backend:`, like `"backend:pdf"`;
+ * and capability keywords like `"svgz"` or `"mix-blend-mode"`.
+ *
+ * Returns: (transfer full) (array zero-terminated=1): a newly-allocated,
+ * `NULL`-terminated array of strings.  Free it with `g_strfreev()`.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gchar **rsvg_get_features (void);
+
 /**
  * rsvg_set_default_dpi:
  * @dpi: Dots Per Inch (aka Pixels Per Inch)
@@ -520,6 +644,30 @@ void rsvg_handle_set_dpi (RsvgHandle *handle, double dpi);
 RSVG_API
 void rsvg_handle_set_dpi_x_y (RsvgHandle *handle, double dpi_x, double dpi_y);
 
+/**
+ * rsvg_handle_set_physical_units_dpi:
+ * @handle: An [class@Rsvg.Handle]
+ * @dpi_x: Dots Per Inch (i.e. Pixels Per Inch)
+ * @dpi_y: Dots Per Inch (i.e. Pixels Per Inch)
+ *
+ * Sets the DPI to use for documents whose size is specified in physical units.
+ *
+ * If the `width` and `height` of the toplevel `<svg>` element are both in physical
+ * units, like `width="210mm" height="297mm"`, then @dpi_x and @dpi_y are used instead
+ * of the values from [method@Rsvg.Handle.set_dpi_x_y] to compute the document's size in
+ * pixels and to render it.  Documents sized in pixels, font-based units, or percentages
+ * keep using the handle's normal DPI.
+ *
+ * This is useful for print previews: you can call this function with the printer's
+ * resolution before rendering, without having to look at the document's units first.
+ *
+ * Passing a number <= 0 for either @dpi_x or @dpi_y turns this off.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+void rsvg_handle_set_physical_units_dpi (RsvgHandle *handle, double dpi_x, double dpi_y);
+
 /**
  * rsvg_handle_new:
  *
@@ -558,7 +706,12 @@ RsvgHandle *rsvg_handle_new (void);
  *
  * Loads the next @count bytes of the image.  You can call this function multiple
  * times until the whole document is consumed; then you must call [method@Rsvg.Handle.close]
- * to actually parse the document.
+ * to finish loading the document.
+ *
+ * The data is parsed as it arrives, so if it is not well-formed XML, this function
+ * returns an error as soon as it finds the problem.  After that, the handle cannot be
+ * used anymore.  Compressed SVGZ data is kept in memory until
+ * [method@Rsvg.Handle.close] gets called, and it is parsed then.
  *
  * Before calling this function for the first time, you may need to call
  * [method@Rsvg.Handle.set_base_uri] or [method@Rsvg.Handle.set_base_gfile] to set the "base
@@ -570,10 +723,10 @@ RsvgHandle *rsvg_handle_new (void);
  *
  * Deprecated: 2.46.  Use [method@Rsvg.Handle.read_stream_sync] or the constructor
  * functions [ctor@Rsvg.Handle.new_from_gfile_sync] or
- * [ctor@Rsvg.Handle.new_from_stream_sync].  This function is deprecated because it
- * will accumulate data from the @buf in memory until [method@Rsvg.Handle.close] gets
- * called.  To avoid a big temporary buffer, use the suggested functions, which
- * take a `GFile` or a `GInputStream` and do not require a temporary buffer.
+ * [ctor@Rsvg.Handle.new_from_stream_sync].  This function was deprecated because it
+ * used to accumulate data from the @buf in memory until [method@Rsvg.Handle.close] got
+ * called.  The suggested functions, which take a `GFile` or a `GInputStream`, are
+ * still simpler to use.
  **/
 RSVG_DEPRECATED_FOR(rsvg_handle_read_stream_sync)
 gboolean rsvg_handle_write (RsvgHandle   *handle,
@@ -660,6 +813,175 @@ GdkPixbuf *rsvg_handle_get_pixbuf (RsvgHandle *handle);
 RSVG_API
 GdkPixbuf *rsvg_handle_get_pixbuf_sub (RsvgHandle *handle, const char *id);
 
+/**
+ * rsvg_handle_get_pixbuf_for_size:
+ * @handle: An [class@Rsvg.Handle]
+ * @width: Desired width in pixels; must be greater than zero.
+ * @height: Desired height in pixels; must be greater than zero.
+ * @preserve_aspect_ratio: Whether to keep the document's aspect ratio.
+ * @error: return location for a `GError`
+ *
+ * Renders the whole SVG document directly into a `GdkPixbuf` of the requested size.
+ *
+ * If @preserve_aspect_ratio is `TRUE`, the document is scaled uniformly so that
+ * it fits within @width and @height, and the returned pixbuf may be smaller than
+ * the requested size along one of its dimensions.  Otherwise, the document is
+ * stretched to exactly @width by @height pixels.
+ *
+ * Unlike calling [method@Rsvg.Handle.get_pixbuf] and then scaling the resulting
+ * pixbuf, this function rasterizes the vector data at the final size, so the
+ * result stays sharp.
+ *
+ * This function depends on the [class@Rsvg.Handle]'s dots-per-inch value (DPI) to compute the
+ * "natural size" of the document, which is used to compute the aspect ratio.
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Returns: (transfer full) (nullable): a pixbuf, or `NULL` if an error occurs
+ * during rendering.
+ *
+ * Since: 2.58
+ **/
+RSVG_API
+GdkPixbuf *rsvg_handle_get_pixbuf_for_size (RsvgHandle *handle,
+                                            int         width,
+                                            int         height,
+                                            gboolean    preserve_aspect_ratio,
+                                            GError    **error);
+
+/**
+ * rsvg_handle_render_element_to_pixbuf:
+ * @handle: An [class@Rsvg.Handle]
+ * @id: (nullable): An element's id within the SVG, starting with "#" (a single
+ * hash character), for example, `#layer1`.  This notation corresponds to a
+ * URL's fragment ID.  Alternatively, pass `NULL` to render the whole SVG.
+ * @error: return location for a `GError`
+ *
+ * Renders a single SVG element into a new `GdkPixbuf` that is just big enough for it.
+ *
+ * The pixbuf has the size of the element's ink rectangle, as returned by
+ * [method@Rsvg.Handle.get_geometry_for_element], rounded up to whole pixels.  Like in
+ * [method@Rsvg.Handle.render_element], the element is rendered by itself, without the
+ * transformations of its ancestors.
+ *
+ * This is a convenience function that does the geometry query, the surface creation,
+ * and the call to [method@Rsvg.Handle.render_element] for you.
+ *
+ * Element IDs should look like an URL fragment identifier; for example, pass
+ * `#foo` (hash `foo`) to render the element that has an `id="foo"` attribute.
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Returns: (transfer full) (nullable): a pixbuf, or `NULL` if an error occurs
+ * during rendering, for example if there is no element with the specified @id.
+ *
+ * Since: 2.58
+ **/
+RSVG_API
+GdkPixbuf *rsvg_handle_render_element_to_pixbuf (RsvgHandle  *handle,
+                                                 const char  *id,
+                                                 GError     **error);
+
+/**
+ * rsvg_handle_set_pixbuf_matte_color:
+ * @handle: An [class@Rsvg.Handle]
+ * @rgba: Color of the matte, in `0xRRGGBBAA` format.
+ *
+ * Sets a solid color over which the functions that return a `GdkPixbuf` composite
+ * the SVG, like [method@Rsvg.Handle.get_pixbuf].  The resulting pixbufs have no
+ * transparency if the color is opaque.
+ *
+ * By default there is no matte, and areas that the SVG does not paint are left
+ * transparent.  Pass a fully transparent color, like 0, to go back to that.
+ *
+ * This does not affect rendering to a Cairo context.
+ *
+ * Since: 2.58
+ **/
+RSVG_API
+void rsvg_handle_set_pixbuf_matte_color (RsvgHandle *handle, guint32 rgba);
+
+/**
+ * rsvg_handle_set_pixbuf_matte_checkerboard:
+ * @handle: An [class@Rsvg.Handle]
+ * @light_rgba: Color of the square at the top-left corner and every other one, in
+ * `0xRRGGBBAA` format.
+ * @dark_rgba: Color of the rest of the squares, in `0xRRGGBBAA` format.
+ * @square_size: Size of each square in pixels; must be greater than zero.
+ *
+ * Sets a checkerboard over which the functions that return a `GdkPixbuf` composite
+ * the SVG, like [method@Rsvg.Handle.get_pixbuf].  Image viewers commonly show such
+ * a matte to make it clear which parts of an image are transparent; this lets them
+ * do it without compositing the pixbuf themselves.
+ *
+ * The squares are aligned to the pixbuf's pixels, so they keep the same size
+ * regardless of the size at which the SVG is rendered.
+ *
+ * Use [method@Rsvg.Handle.set_pixbuf_matte_color] with a transparent color to
+ * remove the matte.  This does not affect rendering to a Cairo context.
+ *
+ * Since: 2.58
+ **/
+RSVG_API
+void rsvg_handle_set_pixbuf_matte_checkerboard (RsvgHandle *handle,
+                                                guint32     light_rgba,
+                                                guint32     dark_rgba,
+                                                guint       square_size);
+
+/**
+ * rsvg_handle_get_pixbuf_async:
+ * @handle: An [class@Rsvg.Handle]
+ * @cancellable: (nullable): A `GCancellable`, or `NULL`
+ * @callback: (scope async): A `GAsyncReadyCallback` to call when the pixbuf is ready
+ * @user_data: User data to pass to @callback
+ *
+ * Asynchronously renders the whole SVG into a pixbuf, like [method@Rsvg.Handle.get_pixbuf].
+ * The rendering happens in a worker thread, so your main loop keeps running while a large
+ * document is rasterized.
+ *
+ * When the pixbuf is ready, @callback will be called in the thread-default main context
+ * of the thread that called this function.  Call [method@Rsvg.Handle.get_pixbuf_finish]
+ * from @callback to obtain the result.
+ *
+ * You must not use @handle from any other function until @callback has been called.
+ * Note that a size callback set with [method@Rsvg.Handle.set_size_callback], and a
+ * resource loader set with [method@Rsvg.Handle.set_resource_loader], may be called
+ * from the worker thread.
+ *
+ * The @cancellable is checked before rendering starts; once rendering is underway
+ * it will run to completion.
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Since: 2.58
+ **/
+RSVG_API
+void rsvg_handle_get_pixbuf_async (RsvgHandle          *handle,
+                                   GCancellable        *cancellable,
+                                   GAsyncReadyCallback  callback,
+                                   gpointer             user_data);
+
+/**
+ * rsvg_handle_get_pixbuf_finish:
+ * @handle: An [class@Rsvg.Handle]
+ * @result: The `GAsyncResult` passed to the callback of [method@Rsvg.Handle.get_pixbuf_async]
+ * @error: return location for a `GError`
+ *
+ * Finishes an operation started with [method@Rsvg.Handle.get_pixbuf_async].
+ *
+ * Returns: (transfer full) (nullable): a pixbuf, or `NULL` if an error occurs
+ * during rendering or if the operation was cancelled.
+ *
+ * Since: 2.58
+ **/
+RSVG_API
+GdkPixbuf *rsvg_handle_get_pixbuf_finish (RsvgHandle    *handle,
+                                          GAsyncResult  *result,
+                                          GError       **error);
+
 /**
  * rsvg_handle_get_base_uri: (attributes org.gtk.Method.get_property=base-uri)
  * @handle: A [class@Rsvg.Handle]
@@ -672,6 +994,61 @@ GdkPixbuf *rsvg_handle_get_pixbuf_sub (RsvgHandle *handle, const char *id);
 RSVG_API
 const char *rsvg_handle_get_base_uri (RsvgHandle *handle);
 
+/**
+ * rsvg_handle_get_title: (attributes org.gtk.Method.get_property=title)
+ * @handle: An [class@Rsvg.Handle]
+ *
+ * Gets the title of the SVG document, which is the text of the `<title>` element that
+ * is a child of the toplevel `<svg>` element.
+ *
+ * Before version 2.58 this function was deprecated and always returned `NULL`.
+ *
+ * Returns: (nullable): the document's title, or `NULL` if the handle is not loaded or
+ * the document does not have a title.  The string is owned by the handle.
+ *
+ * Since: 2.4
+ */
+RSVG_API
+const char *rsvg_handle_get_title (RsvgHandle *handle);
+
+/**
+ * rsvg_handle_get_desc: (attributes org.gtk.Method.get_property=desc)
+ * @handle: An [class@Rsvg.Handle]
+ *
+ * Gets the description of the SVG document, which is the text of the `<desc>` element
+ * that is a child of the toplevel `<svg>` element.
+ *
+ * Before version 2.58 this function was deprecated and always returned `NULL`.
+ *
+ * Returns: (nullable): the document's description, or `NULL` if the handle is not
+ * loaded or the document does not have a description.  The string is owned by the handle.
+ *
+ * Since: 2.4
+ */
+RSVG_API
+const char *rsvg_handle_get_desc (RsvgHandle *handle);
+
+/**
+ * rsvg_handle_get_metadata: (attributes org.gtk.Method.get_property=metadata)
+ * @handle: An [class@Rsvg.Handle]
+ *
+ * Gets the metadata of the SVG document, which is the contents of the `<metadata>`
+ * element that is a child of the toplevel `<svg>` element.  This is usually RDF with
+ * licensing and authorship information.
+ *
+ * The contents are returned as an XML fragment; each of its toplevel elements declares
+ * the namespaces that it uses, so it can be parsed on its own.
+ *
+ * Before version 2.58 this function was deprecated and always returned `NULL`.
+ *
+ * Returns: (nullable): the document's metadata, or `NULL` if the handle is not loaded or
+ * the document does not have a `<metadata>` element.  The string is owned by the handle.
+ *
+ * Since: 2.9
+ */
+RSVG_API
+const char *rsvg_handle_get_metadata (RsvgHandle *handle);
+
 /**
  * rsvg_handle_set_base_uri: (attributes org.gtk.Method.set_property=base-uri)
  * @handle: A [class@Rsvg.Handle]
@@ -787,6 +1164,49 @@ gboolean rsvg_handle_get_position_sub (RsvgHandle       *handle,
 RSVG_API
 gboolean rsvg_handle_has_sub (RsvgHandle *handle, const char *id);
 
+/**
+ * rsvg_handle_get_element_ids:
+ * @handle: a [class@Rsvg.Handle]
+ *
+ * Gets the `id` attributes of all the elements in the SVG document, in document order.
+ *
+ * The ids are returned without a leading "#" character; prepend one to pass them to
+ * functions like [method@Rsvg.Handle.has_sub], [method@Rsvg.Handle.render_layer], or
+ * [method@Rsvg.Handle.render_element].  This lets you find out which fragments of a
+ * document can be rendered on their own.
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Returns: (transfer full) (array zero-terminated=1): A `NULL`-terminated array of
+ * ids, which may be empty.  Free it with [func@GLib.strfreev].
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gchar **rsvg_handle_get_element_ids (RsvgHandle *handle);
+
+/**
+ * rsvg_handle_get_links:
+ * @handle: a [class@Rsvg.Handle]
+ *
+ * Gets the targets of the hyperlinks in the SVG document, that is, the `href` or
+ * `xlink:href` attributes of its `<a>` elements, in document order.
+ *
+ * The links are returned exactly as they appear in the document; relative URLs are
+ * not resolved.
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Returns: (transfer full) (element-type utf8): An array of links, which may be
+ * empty.  Free it with [func@GLib.PtrArray.unref].
+ *
+ * Since: 2.58
+ */
+RSVG_API
+GPtrArray *rsvg_handle_get_links (RsvgHandle *handle);
+
 /**
  * RsvgUnit:
  * @RSVG_UNIT_PERCENT: percentage values; where <literal>1.0</literal> means 100%.
@@ -903,6 +1323,71 @@ void rsvg_handle_get_intrinsic_dimensions (RsvgHandle *handle,
                                            gboolean   *out_has_viewbox,
                                            RsvgRectangle *out_viewbox);
 
+/**
+ * RsvgLengthKind:
+ * @RSVG_LENGTH_KIND_MISSING: the property is not specified, so its value is 100%.
+ * @RSVG_LENGTH_KIND_PERCENTAGE: the property is a percentage, which needs a viewport to be resolved.
+ * @RSVG_LENGTH_KIND_ABSOLUTE: the property is in any other unit, including the font-relative `em` and `ex`.
+ *
+ * Kind of value of the `width` or `height` of the toplevel `<svg>` element, as returned
+ * by [method@Rsvg.Handle.get_intrinsic_dimensions_full].
+ *
+ * Since: 2.58
+ */
+typedef enum {
+    RSVG_LENGTH_KIND_MISSING,
+    RSVG_LENGTH_KIND_PERCENTAGE,
+    RSVG_LENGTH_KIND_ABSOLUTE
+} RsvgLengthKind;
+
+/**
+ * rsvg_handle_get_intrinsic_dimensions_full:
+ * @handle: An [class@Rsvg.Handle]
+ * @out_width_kind: (out)(optional): Will be set to the kind of value of the `width` property in the toplevel SVG.
+ * @out_width: (out)(optional): Will be set to the computed value of the `width` property in the toplevel SVG.
+ * @out_height_kind: (out)(optional): Will be set to the kind of value of the `height` property in the toplevel SVG.
+ * @out_height: (out)(optional): Will be set to the computed value of the `height` property in the toplevel SVG.
+ * @out_has_viewbox: (out)(optional): Will be set to `TRUE` if the toplevel SVG has a `viewBox` attribute
+ * @out_viewbox: (out)(optional): Will be set to the value of the `viewBox` attribute in the toplevel SVG
+ *
+ * Like [method@Rsvg.Handle.get_intrinsic_dimensions], but also tells whether the `width`
+ * and `height` were specified at all, and whether they are percentages.
+ *
+ * Since SVG2, a missing `width` or `height` has a computed value of `100%`, so
+ * [method@Rsvg.Handle.get_intrinsic_dimensions] cannot distinguish these two documents:
+ *
+ * ```
+ * <svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 400">
+ *
+ * <svg xmlns="http://www.w3.org/2000/svg" width="100%" height="100%" viewBox="0 0 100 400">
+ * ```
+ *
+ * For the first one, this function sets `out_width_kind` and `out_height_kind` to
+ * `RSVG_LENGTH_KIND_MISSING`; for the second one, it sets them to
+ * `RSVG_LENGTH_KIND_PERCENTAGE`.  In both cases `out_width` and `out_height` are
+ * returned as `100%`.  Applications that negotiate the size of an SVG with their
+ * layout, as CSS does for replaced elements, need this distinction: a missing size
+ * means that the SVG only has an intrinsic aspect ratio given by its `viewBox`.
+ *
+ * Lengths of kind `RSVG_LENGTH_KIND_ABSOLUTE` can be converted to pixels without a
+ * viewport; see [method@Rsvg.Handle.get_intrinsic_size_in_pixels].
+ *
+ * API ordering: This function must be called on a fully-loaded @handle.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Panics: this function will panic if the @handle is not fully-loaded.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+void rsvg_handle_get_intrinsic_dimensions_full (RsvgHandle     *handle,
+                                                RsvgLengthKind *out_width_kind,
+                                                RsvgLength     *out_width,
+                                                RsvgLengthKind *out_height_kind,
+                                                RsvgLength     *out_height,
+                                                gboolean       *out_has_viewbox,
+                                                RsvgRectangle  *out_viewbox);
+
 /**
  * rsvg_handle_get_intrinsic_size_in_pixels:
  * @handle: An [class@Rsvg.Handle]
@@ -986,6 +1471,17 @@ gboolean rsvg_handle_get_intrinsic_size_in_pixels (RsvgHandle *handle,
  * smaller and faster.  Please see [the Cairo
  * documentation](https://www.cairographics.org/manual/cairo-cairo-surface-t.html#cairo-surface-set-mime-data)
  * for details.
+ * @RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES: Refuse to load anything outside of the SVG
+ * document itself: images, stylesheets, XInclude, and references to elements in other
+ * files, like `<use href="other.svg#foo"/>`, are treated as if the files did not exist.
+ * Only `data:` URLs can be used.  This also applies to URLs that would be loaded with
+ * the callback from [method@Rsvg.Handle.set_resource_loader].  Use this to render
+ * untrusted documents.  Since: 2.58
+ * @RSVG_HANDLE_FLAG_STRICT: Make loading fail with `RSVG_ERROR_INVALID` on problems that
+ * librsvg would normally ignore, like invalid attribute values, invalid or unknown CSS
+ * properties, and references to elements that do not exist.  The error message includes
+ * the line and column of the problem.  Use this to validate documents instead of
+ * rendering them on a best-effort basis.  Since: 2.58
  *
  * Configuration flags for an [class@Rsvg.Handle].  Note that not all of [class@Rsvg.Handle]'s
  * constructors let you specify flags.  For this reason, [ctor@Rsvg.Handle.new_from_gfile_sync]
@@ -995,9 +1491,11 @@ gboolean rsvg_handle_get_intrinsic_size_in_pixels (RsvgHandle *handle,
  */
 typedef enum /*< flags >*/
 {
-    RSVG_HANDLE_FLAGS_NONE           = 0,
-    RSVG_HANDLE_FLAG_UNLIMITED       = 1 << 0,
-    RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA = 1 << 1
+    RSVG_HANDLE_FLAGS_NONE                 = 0,
+    RSVG_HANDLE_FLAG_UNLIMITED             = 1 << 0,
+    RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA       = 1 << 1,
+    RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES = 1 << 2,
+    RSVG_HANDLE_FLAG_STRICT                = 1 << 3
 } RsvgHandleFlags;
 
 RSVG_API
@@ -1035,6 +1533,69 @@ RSVG_API
 void rsvg_handle_set_base_gfile (RsvgHandle *handle,
                                  GFile      *base_file);
 
+/**
+ * RsvgResourceLoaderFunc:
+ * @url: absolute URL of the file that the SVG document references
+ * @user_data: user data
+ * @error: return location for a `GError`
+ *
+ * Function to supply the contents of files referenced by an SVG document; see
+ * [method@Rsvg.Handle.set_resource_loader].
+ *
+ * Return the contents of @url to serve it.  Return `NULL` and set @error to deny access
+ * to @url, or return `NULL` without setting @error to let librsvg read it as usual.
+ *
+ * This function may be called from a worker thread, not just from the thread that set
+ * it; see [method@Rsvg.Handle.set_resource_loader].
+ *
+ * Returns: (transfer full) (nullable): the contents of @url, or `NULL`.
+ *
+ * Since: 2.58
+ */
+typedef GBytes *(*RsvgResourceLoaderFunc) (const char *url,
+                                           gpointer    user_data,
+                                           GError    **error);
+
+/**
+ * rsvg_handle_set_resource_loader:
+ * @handle: a [class@Rsvg.Handle]
+ * @loader_func: (nullable): A function to supply the contents of referenced files, or `NULL`
+ * @user_data: User data to pass to @loader_func, or `NULL`
+ * @user_data_destroy: Function to be called to destroy the data passed in @user_data,
+ *   or `NULL`.
+ *
+ * Sets a function that librsvg calls before reading any file that the SVG document
+ * references, like images, stylesheets, and XInclude files.  Applications can use this
+ * to serve those files from somewhere else, like their own `GResource` bundle, or to
+ * deny access to them.
+ *
+ * The @loader_func gets called with absolute URLs, only for the files that librsvg's
+ * security policy allows the document to reference; see the section "[Security and
+ * locations of referenced files](class.Handle.html#security-and-locations-of-referenced-files)".
+ * `data:` URLs are always decoded by librsvg.  Note that referenced files may also be
+ * read while rendering, so @loader_func may be called from the rendering functions.
+ *
+ * The asynchronous rendering functions, like [method@Rsvg.Handle.get_pixbuf_async] and
+ * [method@Rsvg.Handle.render_document_async], render in a worker thread, so
+ * @loader_func may get called from that thread.  It is never called from two threads at
+ * the same time for the same @handle.  If @handle is freed while one of those is running,
+ * @user_data_destroy may also be called from the worker thread.
+ *
+ * The @user_data is destroyed when the loader is replaced and the documents loaded
+ * with it are freed, or when @handle is finalized.  If @loader_func is `NULL`, the
+ * @user_data is destroyed right away.
+ *
+ * Note: This function may only be called before [method@Rsvg.Handle.write] or
+ * [method@Rsvg.Handle.read_stream_sync] have been called.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+void rsvg_handle_set_resource_loader (RsvgHandle            *handle,
+                                      RsvgResourceLoaderFunc loader_func,
+                                      gpointer               user_data,
+                                      GDestroyNotify         user_data_destroy);
+
 /**
  * rsvg_handle_read_stream_sync:
  * @handle: a [class@Rsvg.Handle]
@@ -1066,6 +1627,57 @@ gboolean rsvg_handle_read_stream_sync (RsvgHandle   *handle,
                                        GCancellable *cancellable,
                                        GError      **error);
 
+/**
+ * rsvg_handle_read_stream_async:
+ * @handle: a [class@Rsvg.Handle]
+ * @stream: a `GInputStream`
+ * @cancellable: (nullable): a `GCancellable`, or `NULL`
+ * @callback: (scope async): A `GAsyncReadyCallback` to call when the document is loaded
+ * @user_data: User data to pass to @callback
+ *
+ * Asynchronously reads @stream and loads the SVG document from it, like
+ * [method@Rsvg.Handle.read_stream_sync], but without blocking.  This is useful for
+ * streams that may be slow, like the ones for network resources or files from portals.
+ *
+ * The data is read in the thread-default main context of the caller, which must be
+ * running for the operation to make progress, and the [signal@Rsvg.Handle::load-progress]
+ * signal is emitted as data arrives.  Call [method@Rsvg.Handle.read_stream_finish] from
+ * @callback to obtain the result.  You must not use @handle until @callback has been
+ * called.
+ *
+ * If @cancellable is triggered, or if reading @stream fails, the error from the
+ * stream will be returned; for cancellation this is `G_IO_ERROR_CANCELLED`.
+ *
+ * API ordering: This function must be called on a handle that has not been loaded.  See
+ * the section "[API ordering](class.Handle.html#api-ordering)" for details.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+void rsvg_handle_read_stream_async (RsvgHandle          *handle,
+                                    GInputStream        *stream,
+                                    GCancellable        *cancellable,
+                                    GAsyncReadyCallback  callback,
+                                    gpointer             user_data);
+
+/**
+ * rsvg_handle_read_stream_finish:
+ * @handle: a [class@Rsvg.Handle]
+ * @result: The `GAsyncResult` passed to the callback
+ * @error: return location for a `GError`
+ *
+ * Finishes an operation started with [method@Rsvg.Handle.read_stream_async].
+ *
+ * Returns: `TRUE` if the document was loaded successfully, or `FALSE` otherwise
+ *   with @error filled in
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gboolean rsvg_handle_read_stream_finish (RsvgHandle    *handle,
+                                         GAsyncResult  *result,
+                                         GError       **error);
+
 /**
  * rsvg_handle_new_from_gfile_sync:
  * @file: a `GFile`
@@ -1132,8 +1744,7 @@ RsvgHandle *rsvg_handle_new_from_stream_sync (GInputStream   *input_stream,
  *
  * Loads the SVG specified by @data.  Note that this function creates an
  * [class@Rsvg.Handle] without a base URL, and without any [flags@Rsvg.HandleFlags].  If you
- * need these, use [ctor@Rsvg.Handle.new_from_stream_sync] instead by creating
- * a [class@Gio.MemoryInputStream] from your data.
+ * need these, use [ctor@Rsvg.Handle.new_from_bytes] instead.
  *
  * Returns: (transfer full) (nullable): A [class@Rsvg.Handle] or `NULL` if an error occurs.
  * Since: 2.14
@@ -1141,6 +1752,31 @@ RsvgHandle *rsvg_handle_new_from_stream_sync (GInputStream   *input_stream,
 RSVG_API
 RsvgHandle *rsvg_handle_new_from_data (const guint8 *data, gsize data_len, GError **error);
 
+/**
+ * rsvg_handle_new_from_bytes:
+ * @bytes: a `GBytes` with the SVG data
+ * @flags: flags from [flags@Rsvg.HandleFlags]
+ * @base_file: (nullable): a `GFile`, or `NULL`
+ * @error: return location for a `GError`
+ *
+ * Loads the SVG specified by @bytes.  The handle takes a reference to @bytes
+ * instead of copying its contents, so the data does not need to be kept alive
+ * by the caller.
+ *
+ * This function sets the "base file" of the handle to be @base_file if
+ * provided.  SVG elements like `<image>` which reference
+ * external resources will be resolved relative to the location of @base_file.
+ *
+ * Returns: (transfer full) (nullable): a new [class@Rsvg.Handle] on success, or `NULL` with @error filled in
+ *
+ * Since: 2.58
+ */
+RSVG_API
+RsvgHandle *rsvg_handle_new_from_bytes (GBytes         *bytes,
+                                        RsvgHandleFlags flags,
+                                        GFile          *base_file,
+                                        GError        **error);
+
 /**
  * rsvg_handle_new_from_file:
  * @filename: The file name to load, or a URI.
@@ -1186,6 +1822,87 @@ gboolean rsvg_handle_set_stylesheet (RsvgHandle   *handle,
                                      gsize         css_len,
                                      GError      **error);
 
+/**
+ * rsvg_handle_set_stylesheet_file:
+ * @handle: A [class@Rsvg.Handle].
+ * @file: A `GFile` with CSS data; must be valid UTF-8.
+ * @error: return location for a `GError`
+ *
+ * Sets a CSS stylesheet to use for an SVG document, loading it from @file.
+ *
+ * This is like [method@Rsvg.Handle.set_stylesheet], but `@import` rules and relative
+ * references in `url()` values are resolved with respect to the location of @file,
+ * instead of the location of the SVG document.  The files they refer to are subject to
+ * the same restrictions as @file itself: they must be in the same directory as @file,
+ * or in a subdirectory of it.
+ *
+ * During the CSS cascade, the specified stylesheet will be used with a "User"
+ * [origin](https://drafts.csswg.org/css-cascade-3/#cascading-origins).
+ *
+ * If the handle has a resource loader (see [method@Rsvg.Handle.set_resource_loader]),
+ * it is used to fetch the stylesheet and the ones it imports.
+ *
+ * Returns: `TRUE` on success, `FALSE` on error.  Errors are returned
+ * in the @error argument.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gboolean rsvg_handle_set_stylesheet_file (RsvgHandle *handle,
+                                          GFile      *file,
+                                          GError    **error);
+
+/**
+ * rsvg_handle_add_font_file:
+ * @handle: A [class@Rsvg.Handle].
+ * @filename: (type filename): Path to a TrueType or OpenType font file.
+ * @error: return location for a `GError`
+ *
+ * Registers a font file to use for the text in the SVG document.
+ *
+ * Normally librsvg uses the fonts installed in the system.  With this function, a
+ * program can supply fonts that are not installed, for example from a sandboxed
+ * application, or from a test suite that needs predictable rendering.  The families in
+ * the font file become available to the document's `font-family` properties, in
+ * addition to the system's fonts.
+ *
+ * The file must remain in place while the document is being rendered.
+ *
+ * This is only supported on platforms where Pango uses Fontconfig; elsewhere this
+ * function returns an error.
+ *
+ * Returns: `TRUE` on success, `FALSE` on error.  Errors are returned
+ * in the @error argument.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gboolean rsvg_handle_add_font_file (RsvgHandle  *handle,
+                                    const char  *filename,
+                                    GError     **error);
+
+/**
+ * rsvg_handle_add_font_bytes:
+ * @handle: A [class@Rsvg.Handle].
+ * @bytes: Contents of a TrueType or OpenType font file.
+ * @error: return location for a `GError`
+ *
+ * Registers a font to use for the text in the SVG document, from the contents of a
+ * font file.
+ *
+ * This is like [method@Rsvg.Handle.add_font_file], but for fonts that are already in
+ * memory, for example from a `GResource`.
+ *
+ * Returns: `TRUE` on success, `FALSE` on error.  Errors are returned
+ * in the @error argument.
+ *
+ * Since: 2.58
+ */
+RSVG_API
+gboolean rsvg_handle_add_font_bytes (RsvgHandle  *handle,
+                                     GBytes      *bytes,
+                                     GError     **error);
+
 #ifndef __GTK_DOC_IGNORE__
 /**
  * rsvg_handle_internal_set_testing:
@@ -1401,48 +2118,6 @@ GdkPixbuf *rsvg_pixbuf_from_file_at_zoom_with_max (const gchar *filename,
                                                    gint         max_height,
                                                    GError     **error);
 
-/**
- * rsvg_handle_get_title:
- * @handle: An [class@Rsvg.Handle]
- *
- * Returns: (nullable): This function always returns `NULL`.
- *
- * Since: 2.4
- *
- * Deprecated: 2.36.  Librsvg does not read the metadata/desc/title elements;
- * this function always returns `NULL`.
- */
-RSVG_DEPRECATED
-const char *rsvg_handle_get_title (RsvgHandle *handle);
-
-/**
- * rsvg_handle_get_desc:
- * @handle: An [class@Rsvg.Handle]
- *
- * Returns: (nullable): This function always returns `NULL`.
- *
- * Since: 2.4
- *
- * Deprecated: 2.36.  Librsvg does not read the metadata/desc/title elements;
- * this function always returns `NULL`.
- */
-RSVG_DEPRECATED
-const char *rsvg_handle_get_desc (RsvgHandle *handle);
-
-/**
- * rsvg_handle_get_metadata:
- * @handle: An [class@Rsvg.Handle]
- *
- * Returns: (nullable): This function always returns `NULL`.
- *
- * Since: 2.9
- *
- * Deprecated: 2.36.  Librsvg does not read the metadata/desc/title elements;
- * this function always returns `NULL`.
- */
-RSVG_DEPRECATED
-const char *rsvg_handle_get_metadata (RsvgHandle *handle);
-
 /* END deprecated APIs. */
 
 G_END_DECLS
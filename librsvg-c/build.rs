@@ -1,12 +1,14 @@
 use regex::Regex;
+use std::collections::BTreeSet;
 use std::env;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::prelude::*;
 use std::io::{BufReader, Write};
 use std::path::Path;
 
 fn main() {
     write_version();
+    check_c_api();
 }
 
 fn write_version() {
@@ -69,3 +71,102 @@ pub static rsvg_micro_version: c_uint = {};
     )
     .expect("write version.rs");
 }
+
+/// Fails the build if the functions exported from Rust drift from the public C API.
+///
+/// Every `#[no_mangle]` function in `src/` must be declared in one of the public headers,
+/// and listed in `win32/librsvg.symbols` so that it gets exported from the DLL.  Conversely,
+/// everything in the headers and in the symbols file must be implemented in Rust.
+///
+/// The headers are not generated from the Rust code; they are written by hand, since they
+/// carry the gi-docgen documentation for the C API.  This check is what keeps them in sync.
+fn check_c_api() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=../configure.ac");
+    println!("cargo:rerun-if-changed=../include/librsvg");
+    println!("cargo:rerun-if-changed=../win32/librsvg.symbols");
+
+    let exported = exported_functions("src");
+    let declared = declared_functions("../include/librsvg");
+    let listed = listed_symbols("../win32/librsvg.symbols");
+
+    let mut problems = Vec::new();
+
+    for name in exported.difference(&declared) {
+        problems.push(format!(
+            "{name} is exported from Rust but not declared in a public header"
+        ));
+    }
+
+    for name in declared.difference(&exported) {
+        problems.push(format!(
+            "{name} is declared in a public header but not implemented in Rust"
+        ));
+    }
+
+    for name in exported.difference(&listed) {
+        problems.push(format!("{name} is missing from win32/librsvg.symbols"));
+    }
+
+    for name in listed.difference(&exported) {
+        // The version numbers are variables generated by write_version(), not functions.
+        if !name.ends_with("_version") {
+            problems.push(format!(
+                "{name} is in win32/librsvg.symbols but not implemented in Rust"
+            ));
+        }
+    }
+
+    if !problems.is_empty() {
+        panic!(
+            "the C API is out of sync with the Rust code:\n{}",
+            problems.join("\n")
+        );
+    }
+}
+
+fn exported_functions(dir: &str) -> BTreeSet<String> {
+    let regex = Regex::new(r#"#\[no_mangle\]\s*pub (?:unsafe )?extern "C" fn (\w+)"#).unwrap();
+
+    let mut names = BTreeSet::new();
+
+    for entry in fs::read_dir(dir).expect("read the source directory") {
+        let path = entry.expect("read a source directory entry").path();
+        if path.extension().is_some_and(|ext| ext == "rs") {
+            let source = fs::read_to_string(&path).expect("read a source file");
+            names.extend(regex.captures_iter(&source).map(|c| c[1].to_string()));
+        }
+    }
+
+    names
+}
+
+fn declared_functions(dir: &str) -> BTreeSet<String> {
+    // Comments are stripped first, since the doc comments mention functions all the time.
+    let comment = Regex::new(r"(?s)/\*.*?\*/").unwrap();
+    let declaration = Regex::new(r"\b(rsvg_\w+)\s*\(").unwrap();
+
+    let mut names = BTreeSet::new();
+
+    for entry in fs::read_dir(dir).expect("read the include directory") {
+        let path = entry.expect("read an include directory entry").path();
+        if path.extension().is_some_and(|ext| ext == "h") {
+            let header = fs::read_to_string(&path).expect("read a header file");
+            let header = comment.replace_all(&header, "");
+            names.extend(declaration.captures_iter(&header).map(|c| c[1].to_string()));
+        }
+    }
+
+    names
+}
+
+fn listed_symbols(path: &str) -> BTreeSet<String> {
+    fs::read_to_string(path)
+        .expect("read the symbols file")
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("rsvg_"))
+        .map(String::from)
+        .collect()
+}
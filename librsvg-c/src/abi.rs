@@ -0,0 +1,102 @@
+//! Compile-time checks for the layout of the structs in the public C API.
+//!
+//! The structs in `rsvg.h` are written by hand, so nothing stops them from drifting from
+//! their `#[repr(C)]` counterparts in Rust.  The assertions here pin the Rust side to the
+//! C layout rules for what the header declares; `tests-c/api.c` has the same assertions
+//! with `G_STATIC_ASSERT` for the C side.  If you change one of these structs, update both.
+
+use glib::ffi::gpointer;
+use glib::gobject_ffi;
+use libc::c_int;
+use std::mem::{align_of, size_of};
+
+use rsvg::Length as RsvgLength;
+
+use crate::handle::{
+    RsvgDimensionData, RsvgElementGeometry, RsvgHandle, RsvgHandleClass, RsvgPositionData,
+    RsvgRectangle,
+};
+
+/// Offset of a field within a struct, usable in constant expressions.
+macro_rules! offset_of {
+    ($ty:ty, $field:ident) => {{
+        let uninit = std::mem::MaybeUninit::<$ty>::uninit();
+        let base = uninit.as_ptr();
+        // SAFETY: the pointer is only used to compute an address, never dereferenced.
+        unsafe {
+            let field = std::ptr::addr_of!((*base).$field);
+            (field as *const u8).offset_from(base as *const u8) as usize
+        }
+    }};
+}
+
+/// Asserts the size and alignment of a struct, and the offsets of its fields.
+macro_rules! assert_layout {
+    ($ty:ty, size = $size:expr, align = $align:expr $(, $field:ident = $offset:expr)* $(,)?) => {
+        const _: () = assert!(size_of::<$ty>() == $size);
+        const _: () = assert!(align_of::<$ty>() == $align);
+        $(const _: () = assert!(offset_of!($ty, $field) == $offset);)*
+    };
+}
+
+/// Rounds up `size` to a multiple of `align`, like a C compiler does with trailing padding.
+const fn padded(size: usize, align: usize) -> usize {
+    (size + align - 1) / align * align
+}
+
+assert_layout!(
+    RsvgHandle,
+    size = size_of::<gobject_ffi::GObject>() + 16 * size_of::<gpointer>(),
+    align = align_of::<gobject_ffi::GObject>(),
+);
+
+assert_layout!(
+    RsvgHandleClass,
+    size = size_of::<gobject_ffi::GObjectClass>() + 15 * size_of::<gpointer>(),
+    align = align_of::<gobject_ffi::GObjectClass>(),
+);
+
+assert_layout!(
+    RsvgRectangle,
+    size = 4 * size_of::<f64>(),
+    align = align_of::<f64>(),
+    x = 0,
+    y = size_of::<f64>(),
+    width = 2 * size_of::<f64>(),
+    height = 3 * size_of::<f64>(),
+);
+
+assert_layout!(
+    RsvgElementGeometry,
+    size = 2 * size_of::<RsvgRectangle>(),
+    align = align_of::<RsvgRectangle>(),
+    ink_rect = 0,
+    logical_rect = size_of::<RsvgRectangle>(),
+);
+
+assert_layout!(
+    RsvgPositionData,
+    size = 2 * size_of::<c_int>(),
+    align = align_of::<c_int>(),
+    x = 0,
+    y = size_of::<c_int>(),
+);
+
+assert_layout!(
+    RsvgDimensionData,
+    size = 2 * size_of::<c_int>() + 2 * size_of::<f64>(),
+    align = align_of::<f64>(),
+    width = 0,
+    height = size_of::<c_int>(),
+    em = 2 * size_of::<c_int>(),
+    ex = 2 * size_of::<c_int>() + size_of::<f64>(),
+);
+
+// RsvgUnit is a plain C enum, so it has the size of an int.
+assert_layout!(
+    RsvgLength,
+    size = padded(size_of::<f64>() + size_of::<c_int>(), align_of::<f64>()),
+    align = align_of::<f64>(),
+    length = 0,
+    unit = size_of::<f64>(),
+);
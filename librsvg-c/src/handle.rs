@@ -21,10 +21,12 @@
 use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::ffi::{CStr, CString, OsStr};
 use std::fmt;
-use std::path::PathBuf;
+use std::mem;
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::slice;
 use std::str;
+use std::sync::Arc;
 use std::{f64, i32};
 
 use gdk_pixbuf::Pixbuf;
@@ -39,11 +41,15 @@ use glib::{ffi::gpointer, gobject_ffi};
 use glib::{Bytes, Cast, StaticType, ToValue};
 
 use rsvg::c_api_only::{rsvg_log, Session, SharedImageSurface, SurfaceType};
-use rsvg::{CairoRenderer, IntrinsicDimensions, Length, Loader, LoadingError, SvgHandle};
+use rsvg::{
+    CairoRenderer, IncrementalLoader, IntrinsicDimensions, IntrinsicLengthKind, Length, Loader,
+    LoadingError, Matte, ResourceLoader, SvgHandle,
+};
 
 use super::dpi::Dpi;
 use super::messages::{rsvg_g_critical, rsvg_g_warning};
-use super::pixbuf_utils::{empty_pixbuf, pixbuf_from_surface};
+use super::pixbuf_utils::{empty_pixbuf, pixbuf_from_surface, render_to_pixbuf_at_size};
+use super::progress::ProgressInputStream;
 use super::sizing::LegacySize;
 
 // The C API exports global variables that contain the library's version number;
@@ -88,6 +94,15 @@ pub enum HandleFlags {
         nick = "flag-keep-image-data"
     )]
     KEEP_IMAGE_DATA = 1 << 1,
+
+    #[flags_value(
+        name = "RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES",
+        nick = "flag-no-external-resources"
+    )]
+    NO_EXTERNAL_RESOURCES = 1 << 2,
+
+    #[flags_value(name = "RSVG_HANDLE_FLAG_STRICT", nick = "flag-strict")]
+    STRICT = 1 << 3,
 }
 
 /// Type alias used to pass flags in the C API functions.
@@ -98,6 +113,8 @@ pub type RsvgHandleFlags = u32;
 struct LoadFlags {
     unlimited_size: bool,
     keep_image_data: bool,
+    no_external_resources: bool,
+    strict: bool,
 }
 
 impl From<HandleFlags> for LoadFlags {
@@ -105,6 +122,8 @@ impl From<HandleFlags> for LoadFlags {
         LoadFlags {
             unlimited_size: flags.contains(HandleFlags::UNLIMITED),
             keep_image_data: flags.contains(HandleFlags::KEEP_IMAGE_DATA),
+            no_external_resources: flags.contains(HandleFlags::NO_EXTERNAL_RESOURCES),
+            strict: flags.contains(HandleFlags::STRICT),
         }
     }
 }
@@ -121,6 +140,14 @@ impl From<LoadFlags> for HandleFlags {
             hflags.insert(HandleFlags::KEEP_IMAGE_DATA);
         }
 
+        if lflags.no_external_resources {
+            hflags.insert(HandleFlags::NO_EXTERNAL_RESOURCES);
+        }
+
+        if lflags.strict {
+            hflags.insert(HandleFlags::STRICT);
+        }
+
         hflags
     }
 }
@@ -173,9 +200,15 @@ enum LoadState {
 
     /// Being loaded using the legacy write()/close() API.
     ///
-    /// We buffer all the data from `write()` calls until the time `close()` is called;
-    /// then we run the buffer through a decompressor in case this is an SVGZ file.
-    Loading { buffer: Vec<u8> },
+    /// The data from `write()` calls gets parsed as it arrives, and `close()` finishes
+    /// loading the document.  This is also used by `rsvg_handle_read_stream_async()`.
+    Loading {
+        loader: IncrementalLoader,
+        bytes_written: usize,
+    },
+
+    /// Being parsed from a stream by `rsvg_handle_read_stream_sync()`.
+    Reading,
 
     /// Loading finished successfully; the document is in the `SvgHandle`.
     ClosedOk { handle: SvgHandle },
@@ -274,9 +307,20 @@ impl From<RsvgRectangle> for cairo::Rectangle {
     }
 }
 
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct RsvgElementGeometry {
+    pub ink_rect: RsvgRectangle,
+    pub logical_rect: RsvgRectangle,
+}
+
 mod imp {
     use super::*;
-    use glib::{ParamSpec, ParamSpecDouble, ParamSpecFlags, ParamSpecInt, ParamSpecString};
+    use glib::subclass::Signal;
+    use glib::{
+        ParamSpec, ParamSpecBoolean, ParamSpecDouble, ParamSpecFlags, ParamSpecInt,
+        ParamSpecString, ParamSpecVariant, VariantTy,
+    };
     use once_cell::sync::Lazy;
 
     /// Contains all the interior mutability for a RsvgHandle to be called
@@ -291,10 +335,29 @@ mod imp {
     #[derive(Default)]
     pub(super) struct CHandleInner {
         pub(super) dpi: Dpi,
+
+        /// DPI from `rsvg_handle_set_physical_units_dpi()`, for documents sized in
+        /// physical units.
+        pub(super) physical_units_dpi: Option<(f64, f64)>,
+
         pub(super) load_flags: LoadFlags,
         pub(super) base_url: BaseUrl,
         pub(super) size_callback: SizeCallback,
+        pub(super) resource_loader: Option<CResourceLoader>,
         pub(super) is_testing: bool,
+
+        /// Font options from `rsvg_handle_set_font_options()`, which are kept here so
+        /// they can be applied to the document once it is loaded.
+        pub(super) font_options: Option<cairo::FontOptions>,
+
+        /// Matte for the `rsvg_handle_get_pixbuf*()` family of functions.
+        pub(super) pixbuf_matte: Option<Matte>,
+
+        /// Strings returned by `rsvg_handle_get_title()`, `rsvg_handle_get_desc()`, and
+        /// `rsvg_handle_get_metadata()`, which are owned by the handle.
+        pub(super) title: Option<CString>,
+        pub(super) desc: Option<CString>,
+        pub(super) metadata: Option<CString>,
     }
 
     #[glib::object_subclass]
@@ -321,16 +384,17 @@ mod imp {
                     ParamSpecInt::builder("height").read_only().build(),
                     ParamSpecDouble::builder("em").read_only().build(),
                     ParamSpecDouble::builder("ex").read_only().build(),
-                    ParamSpecString::builder("title")
-                        .deprecated()
+                    ParamSpecString::builder("title").read_only().build(),
+                    ParamSpecString::builder("desc").read_only().build(),
+                    ParamSpecString::builder("metadata").read_only().build(),
+                    ParamSpecBoolean::builder("loaded").read_only().build(),
+                    ParamSpecDouble::builder("intrinsic-width")
                         .read_only()
                         .build(),
-                    ParamSpecString::builder("desc")
-                        .deprecated()
+                    ParamSpecDouble::builder("intrinsic-height")
                         .read_only()
                         .build(),
-                    ParamSpecString::builder("metadata")
-                        .deprecated()
+                    ParamSpecVariant::builder("viewbox", VariantTy::new("(dddd)").unwrap())
                         .read_only()
                         .build(),
                 ]
@@ -338,6 +402,15 @@ mod imp {
             PROPERTIES.as_ref()
         }
 
+        fn signals() -> &'static [Signal] {
+            static SIGNALS: Lazy<Vec<Signal>> = Lazy::new(|| {
+                vec![Signal::builder("load-progress")
+                    .param_types([u64::static_type()])
+                    .build()]
+            });
+            SIGNALS.as_ref()
+        }
+
         fn set_property(&self, id: usize, value: &glib::Value, pspec: &ParamSpec) {
             let obj = self.obj();
             match pspec.name() {
@@ -384,10 +457,16 @@ mod imp {
                 "em" => obj.get_dimensions_or_empty().em.to_value(),
                 "ex" => obj.get_dimensions_or_empty().ex.to_value(),
 
-                // the following three are deprecated
-                "title" => None::<String>.to_value(),
-                "desc" => None::<String>.to_value(),
-                "metadata" => None::<String>.to_value(),
+                "title" => obj.get_title().to_value(),
+                "desc" => obj.get_desc().to_value(),
+
+                "metadata" => obj.get_metadata().to_value(),
+
+                "loaded" => obj.is_loaded().to_value(),
+
+                "intrinsic-width" => obj.get_intrinsic_size_or_zero().0.to_value(),
+                "intrinsic-height" => obj.get_intrinsic_size_or_zero().1.to_value(),
+                "viewbox" => obj.get_viewbox_variant().to_value(),
 
                 _ => unreachable!("invalid property id={} for RsvgHandle", id),
             }
@@ -515,6 +594,72 @@ impl Drop for SizeCallback {
         }
     }
 }
+
+// Keep in sync with rsvg.h:RsvgResourceLoaderFunc
+pub type RsvgResourceLoaderFunc = Option<
+    unsafe extern "C" fn(
+        url: *const libc::c_char,
+        user_data: gpointer,
+        error: *mut *mut glib::ffi::GError,
+    ) -> *mut glib::ffi::GBytes,
+>;
+
+struct ResourceLoaderCallback {
+    loader_func: unsafe extern "C" fn(
+        *const libc::c_char,
+        gpointer,
+        *mut *mut glib::ffi::GError,
+    ) -> *mut glib::ffi::GBytes,
+    user_data: gpointer,
+    destroy_notify: glib::ffi::GDestroyNotify,
+}
+
+// The loader is only called while loading or rendering with the RsvgHandle.  The async
+// rendering functions do that in a GTask worker thread, so the C function and the destroy
+// notify may run in a thread other than the one that set them; rsvg.h documents this.
+// Callers must not use the RsvgHandle while an async operation is running, so the
+// callback is still never used from more than one thread at a time.
+unsafe impl Send for ResourceLoaderCallback {}
+unsafe impl Sync for ResourceLoaderCallback {}
+
+impl Drop for ResourceLoaderCallback {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(ref f) = self.destroy_notify {
+                f(self.user_data);
+            };
+        }
+    }
+}
+
+/// The resource loader from `rsvg_handle_set_resource_loader()`.
+///
+/// The `Loader` for the handle and the documents it loads may keep the loader alive after
+/// the callback is replaced, so the user data is destroyed when all of them are done.
+#[derive(Clone)]
+pub(crate) struct CResourceLoader(Arc<ResourceLoaderCallback>);
+
+impl ResourceLoader for CResourceLoader {
+    fn load(&self, url: &str) -> Option<Result<Vec<u8>, glib::Error>> {
+        let callback = &self.0;
+        let url = CString::new(url).ok()?;
+
+        unsafe {
+            let mut error = ptr::null_mut();
+            let bytes = (callback.loader_func)(url.as_ptr(), callback.user_data, &mut error);
+
+            if !bytes.is_null() {
+                let bytes: Bytes = from_glib_full(bytes);
+                Some(Ok(bytes.to_vec()))
+            } else if !error.is_null() {
+                Some(Err(from_glib_full(error)))
+            } else {
+                None
+            }
+        }
+    }
+}
+
 pub trait CairoRectangleExt {
     fn from_size(width: f64, height: f64) -> Self;
 }
@@ -585,6 +730,16 @@ impl CHandle {
         inner.dpi = Dpi::new(dpi.x(), dpi_y);
     }
 
+    fn set_physical_units_dpi(&self, dpi_x: f64, dpi_y: f64) {
+        let mut inner = self.imp().inner.borrow_mut();
+
+        inner.physical_units_dpi = if dpi_x > 0.0 && dpi_y > 0.0 {
+            Some((dpi_x, dpi_y))
+        } else {
+            None
+        };
+    }
+
     fn get_dpi_x(&self) -> f64 {
         let inner = self.imp().inner.borrow();
         inner.dpi.x()
@@ -615,30 +770,74 @@ impl CHandle {
         inner.size_callback = SizeCallback::new(size_func, user_data, destroy_notify);
     }
 
-    fn write(&self, buf: &[u8]) {
-        let mut state = self.imp().load_state.borrow_mut();
+    fn set_resource_loader(
+        &self,
+        loader_func: RsvgResourceLoaderFunc,
+        user_data: gpointer,
+        destroy_notify: glib::ffi::GDestroyNotify,
+    ) {
+        let Some(loader_func) = loader_func else {
+            self.imp().inner.borrow_mut().resource_loader = None;
 
-        match *state {
-            LoadState::Start => {
-                *state = LoadState::Loading {
-                    buffer: Vec::from(buf),
+            // Nothing will use the user data, so it can be freed right away.
+            if let Some(destroy_notify) = destroy_notify {
+                unsafe {
+                    destroy_notify(user_data);
                 }
             }
 
-            LoadState::Loading { ref mut buffer } => {
-                buffer.extend_from_slice(buf);
+            return;
+        };
+
+        let mut inner = self.imp().inner.borrow_mut();
+        inner.resource_loader = Some(CResourceLoader(Arc::new(ResourceLoaderCallback {
+            loader_func,
+            user_data,
+            destroy_notify,
+        })));
+    }
+
+    fn write(&self, buf: &[u8]) -> Result<(), LoadingError> {
+        let bytes_consumed = {
+            let mut state = self.imp().load_state.borrow_mut();
+
+            if let LoadState::Start = *state {
+                self.begin_loading(&mut state)?;
             }
 
-            _ => {
-                rsvg_g_critical("Handle must not be closed in order to write to it");
+            match *state {
+                LoadState::Loading {
+                    ref mut loader,
+                    ref mut bytes_written,
+                } => {
+                    if let Err(e) = loader.write(buf) {
+                        *state = LoadState::ClosedError;
+                        return Err(e);
+                    }
+
+                    *bytes_written += buf.len();
+                    *bytes_written
+                }
+
+                _ => {
+                    rsvg_g_critical("Handle must not be closed in order to write to it");
+                    return Err(LoadingError::Other(String::from("API ordering")));
+                }
             }
-        }
+        };
+
+        self.emit_load_progress(bytes_consumed);
+
+        Ok(())
     }
 
     fn close(&self) -> Result<(), LoadingError> {
         let imp = self.imp();
 
-        let inner = imp.inner.borrow();
+        // Declared before the borrows below so that "notify::loaded" gets emitted after
+        // they are released; handlers may want to call methods on the handle.
+        let _notify_guard = self.freeze_notify();
+
         let mut state = imp.load_state.borrow_mut();
 
         match *state {
@@ -649,12 +848,19 @@ impl CHandle {
                 )))
             }
 
-            LoadState::Loading { ref buffer } => {
-                let bytes = Bytes::from(buffer);
-                let stream = gio::MemoryInputStream::from_bytes(&bytes);
+            LoadState::Loading { .. } => {
+                let LoadState::Loading { loader, .. } =
+                    std::mem::replace(&mut *state, LoadState::ClosedError)
+                else {
+                    unreachable!();
+                };
+
+                self.finish_loading(state, loader.close())
+            }
 
-                let base_file = inner.base_url.get_gfile();
-                self.read_stream(state, &stream.upcast(), base_file.as_ref(), None)
+            LoadState::Reading => {
+                rsvg_g_critical("Handle is being read from a stream; it cannot be closed");
+                Err(LoadingError::Other(String::from("API ordering")))
             }
 
             // Closing is idempotent
@@ -670,35 +876,115 @@ impl CHandle {
     ) -> Result<(), LoadingError> {
         let imp = self.imp();
 
-        let state = imp.load_state.borrow_mut();
-        let inner = imp.inner.borrow();
+        {
+            let mut state = imp.load_state.borrow_mut();
 
-        match *state {
-            LoadState::Start => {
-                let base_file = inner.base_url.get_gfile();
-                self.read_stream(state, stream, base_file.as_ref(), cancellable)
+            if !matches!(*state, LoadState::Start) {
+                rsvg_g_critical(
+                    "handle must not be already loaded in order to call \
+                     rsvg_handle_read_stream_sync()",
+                );
+                return Err(LoadingError::Other(String::from("API ordering")));
+            }
+
+            // Marks the handle as loading, so it can't be rendered or loaded again from a
+            // "load-progress" handler.
+            *state = LoadState::Reading;
+        }
+
+        // The document gets parsed as it is read from the stream.  No borrows are held
+        // meanwhile, so that handlers of the "load-progress" signal can query the handle.
+        let obj = self.clone();
+        let stream =
+            ProgressInputStream::new(stream, move |bytes_read| obj.emit_load_progress(bytes_read));
+
+        let base_file = imp.inner.borrow().base_url.get_gfile();
+        let result =
+            self.make_loader()
+                .read_stream(&stream.upcast(), base_file.as_ref(), cancellable);
+
+        let _notify_guard = self.freeze_notify();
+
+        self.finish_loading(imp.load_state.borrow_mut(), result)
+    }
+
+    /// Creates the loader for the data from `write()`, when the handle is first written to.
+    fn begin_loading(&self, state: &mut LoadState) -> Result<(), LoadingError> {
+        let base_file = self.imp().inner.borrow().base_url.get_gfile();
+
+        match self.make_loader().begin_incremental(base_file.as_ref()) {
+            Ok(loader) => {
+                *state = LoadState::Loading {
+                    loader,
+                    bytes_written: 0,
+                };
+                Ok(())
+            }
+
+            Err(e) => {
+                *state = LoadState::ClosedError;
+                Err(e)
             }
+        }
+    }
+
+    /// Starts an asynchronous read for `rsvg_handle_read_stream_async()`.
+    ///
+    /// Returns an error if the handle was already loaded or loading.
+    fn start_async_read(&self) -> Result<(), LoadingError> {
+        let mut state = self.imp().load_state.borrow_mut();
+
+        match *state {
+            // Marks the handle as loading, so it can't be rendered or loaded again while
+            // the stream is being read.
+            LoadState::Start => self.begin_loading(&mut state),
 
-            LoadState::Loading { .. } | LoadState::ClosedOk { .. } | LoadState::ClosedError => {
+            _ => {
                 rsvg_g_critical(
                     "handle must not be already loaded in order to call \
-                     rsvg_handle_read_stream_sync()",
+                     rsvg_handle_read_stream_async()",
                 );
                 Err(LoadingError::Other(String::from("API ordering")))
             }
         }
     }
 
-    fn read_stream(
+    /// Marks the handle as failed, when an asynchronous read could not read its stream.
+    fn set_load_error(&self) {
+        *self.imp().load_state.borrow_mut() = LoadState::ClosedError;
+    }
+
+    fn emit_load_progress(&self, bytes_consumed: usize) {
+        self.emit_by_name::<()>("load-progress", &[&(bytes_consumed as u64)]);
+    }
+
+    fn is_loaded(&self) -> bool {
+        matches!(*self.imp().load_state.borrow(), LoadState::ClosedOk { .. })
+    }
+
+    /// Stores the result of loading the document, and notifies that the handle is loaded.
+    fn finish_loading(
         &self,
         mut load_state: RefMut<'_, LoadState>,
-        stream: &gio::InputStream,
-        base_file: Option<&gio::File>,
-        cancellable: Option<&gio::Cancellable>,
+        result: Result<SvgHandle, LoadingError>,
     ) -> Result<(), LoadingError> {
-        let loader = self.make_loader();
+        let font_options = self.imp().inner.borrow().font_options.clone();
 
-        load_state.set_from_loading_result(loader.read_stream(stream, base_file, cancellable))
+        let result = result.map(|mut handle| {
+            handle.set_font_options(font_options.as_ref());
+            handle
+        });
+
+        load_state.set_from_loading_result(result)?;
+
+        // Callers freeze notifications while they hold borrows, so this gets emitted
+        // when they are done.
+        self.notify("loaded");
+        self.notify("intrinsic-width");
+        self.notify("intrinsic-height");
+        self.notify("viewbox");
+
+        Ok(())
     }
 
     fn get_handle_ref(&self) -> Result<Ref<'_, SvgHandle>, RenderingError> {
@@ -715,6 +1001,11 @@ impl CHandle {
                 Err(RenderingError::HandleIsNotLoaded)
             }
 
+            LoadState::Reading => {
+                rsvg_g_critical("Handle is still being read from a stream");
+                Err(RenderingError::HandleIsNotLoaded)
+            }
+
             LoadState::ClosedError => {
                 rsvg_g_critical(
                     "Handle could not read or parse the SVG; did you check for errors during the \
@@ -735,9 +1026,16 @@ impl CHandle {
         let inner = imp.inner.borrow();
         let session = imp.session.clone();
 
-        Loader::new_with_session(session)
+        let loader = Loader::new_with_session(session)
             .with_unlimited_size(inner.load_flags.unlimited_size)
             .keep_image_data(inner.load_flags.keep_image_data)
+            .enable_external_resources(!inner.load_flags.no_external_resources)
+            .strict(inner.load_flags.strict);
+
+        match inner.resource_loader {
+            Some(ref resource_loader) => loader.with_resource_loader(resource_loader.clone()),
+            None => loader,
+        }
     }
 
     fn has_sub(&self, id: &str) -> Result<bool, RenderingError> {
@@ -745,6 +1043,66 @@ impl CHandle {
         Ok(handle.has_element_with_id(id)?)
     }
 
+    fn get_element_ids(&self) -> Result<Vec<String>, RenderingError> {
+        let handle = self.get_handle_ref()?;
+
+        Ok(handle
+            .root_element()
+            .descendants()
+            .filter_map(|e| e.id())
+            .collect())
+    }
+
+    fn get_links(&self) -> Result<Vec<String>, RenderingError> {
+        let handle = self.get_handle_ref()?;
+
+        Ok(handle
+            .root_element()
+            .descendants()
+            .filter(|e| e.name() == "a" && e.namespace() == "http://www.w3.org/2000/svg")
+            .filter_map(|e| {
+                e.attribute("", "href")
+                    .or_else(|| e.attribute("http://www.w3.org/1999/xlink", "href"))
+            })
+            .collect())
+    }
+
+    fn get_title(&self) -> Option<String> {
+        match *self.imp().load_state.borrow() {
+            LoadState::ClosedOk { ref handle } => handle.document_title(),
+            _ => None,
+        }
+    }
+
+    fn get_title_ptr(&self) -> *const libc::c_char {
+        let mut inner = self.imp().inner.borrow_mut();
+        cached_cstring_ptr(&mut inner.title, || self.get_title())
+    }
+
+    fn get_desc(&self) -> Option<String> {
+        match *self.imp().load_state.borrow() {
+            LoadState::ClosedOk { ref handle } => handle.document_description(),
+            _ => None,
+        }
+    }
+
+    fn get_desc_ptr(&self) -> *const libc::c_char {
+        let mut inner = self.imp().inner.borrow_mut();
+        cached_cstring_ptr(&mut inner.desc, || self.get_desc())
+    }
+
+    fn get_metadata(&self) -> Option<String> {
+        match *self.imp().load_state.borrow() {
+            LoadState::ClosedOk { ref handle } => handle.document_metadata(),
+            _ => None,
+        }
+    }
+
+    fn get_metadata_ptr(&self) -> *const libc::c_char {
+        let mut inner = self.imp().inner.borrow_mut();
+        cached_cstring_ptr(&mut inner.metadata, || self.get_metadata())
+    }
+
     fn get_dimensions_or_empty(&self) -> RsvgDimensionData {
         self.get_dimensions_sub(None)
             .unwrap_or_else(|_| RsvgDimensionData::empty())
@@ -820,9 +1178,24 @@ impl CHandle {
     fn make_renderer<'a>(&self, handle_ref: &'a Ref<'_, SvgHandle>) -> CairoRenderer<'a> {
         let inner = self.imp().inner.borrow();
 
-        CairoRenderer::new(handle_ref)
+        let renderer = CairoRenderer::new(handle_ref)
             .with_dpi(inner.dpi.x(), inner.dpi.y())
-            .test_mode(inner.is_testing)
+            .test_mode(inner.is_testing);
+
+        match inner.physical_units_dpi {
+            Some((dpi_x, dpi_y)) => renderer.with_physical_units_dpi(dpi_x, dpi_y),
+            None => renderer,
+        }
+    }
+
+    /// Makes a renderer for the functions that return a pixbuf, which paint the matte.
+    fn make_pixbuf_renderer<'a>(&self, handle_ref: &'a Ref<'_, SvgHandle>) -> CairoRenderer<'a> {
+        let renderer = self.make_renderer(handle_ref);
+
+        match self.imp().inner.borrow().pixbuf_matte {
+            Some(matte) => renderer.with_matte(matte),
+            None => renderer,
+        }
     }
 
     fn get_geometry_sub(
@@ -849,6 +1222,48 @@ impl CHandle {
         }
     }
 
+    fn set_stylesheet_file(&self, file: &gio::File) -> Result<(), LoadingError> {
+        match *self.imp().load_state.borrow_mut() {
+            LoadState::ClosedOk { ref mut handle } => handle.set_stylesheet_from_file(file),
+
+            _ => {
+                rsvg_g_critical(
+                    "handle must already be loaded in order to call \
+                     rsvg_handle_set_stylesheet_file()",
+                );
+                Err(LoadingError::Other(String::from("API ordering")))
+            }
+        }
+    }
+
+    fn add_font_file(&self, path: &Path) -> Result<(), LoadingError> {
+        match *self.imp().load_state.borrow_mut() {
+            LoadState::ClosedOk { ref mut handle } => handle.add_font_file(path),
+
+            _ => {
+                rsvg_g_critical(
+                    "handle must already be loaded in order to call \
+                     rsvg_handle_add_font_file()",
+                );
+                Err(LoadingError::Other(String::from("API ordering")))
+            }
+        }
+    }
+
+    fn add_font_bytes(&self, data: &[u8]) -> Result<(), LoadingError> {
+        match *self.imp().load_state.borrow_mut() {
+            LoadState::ClosedOk { ref mut handle } => handle.add_font_data(data),
+
+            _ => {
+                rsvg_g_critical(
+                    "handle must already be loaded in order to call \
+                     rsvg_handle_add_font_bytes()",
+                );
+                Err(LoadingError::Other(String::from("API ordering")))
+            }
+        }
+    }
+
     fn render_cairo_sub(
         &self,
         cr: *mut cairo::ffi::cairo_t,
@@ -885,6 +1300,11 @@ impl CHandle {
 
         {
             let cr = cairo::Context::new(&surface)?;
+
+            if let Some(matte) = self.imp().inner.borrow().pixbuf_matte {
+                matte.paint(&cr)?;
+            }
+
             let cr_raw = cr.to_raw_none();
             self.render_cairo_sub(cr_raw, id)?;
         }
@@ -894,6 +1314,38 @@ impl CHandle {
         Ok(pixbuf_from_surface(&surface)?)
     }
 
+    fn get_pixbuf_for_size(
+        &self,
+        width: i32,
+        height: i32,
+        preserve_aspect_ratio: bool,
+    ) -> Result<Pixbuf, RenderingError> {
+        let handle = self.get_handle_ref()?;
+        let renderer = self.make_pixbuf_renderer(&handle);
+
+        let (document_width, document_height) = renderer.legacy_document_size()?;
+
+        let (desired_width, desired_height) =
+            if preserve_aspect_ratio && document_width > 0.0 && document_height > 0.0 {
+                let zoom =
+                    (f64::from(width) / document_width).min(f64::from(height) / document_height);
+                (
+                    (zoom * document_width).round(),
+                    (zoom * document_height).round(),
+                )
+            } else {
+                (f64::from(width), f64::from(height))
+            };
+
+        Ok(render_to_pixbuf_at_size(
+            &renderer,
+            document_width,
+            document_height,
+            desired_width,
+            desired_height,
+        )?)
+    }
+
     fn render_document(
         &self,
         cr: *mut cairo::ffi::cairo_t,
@@ -920,6 +1372,38 @@ impl CHandle {
             .map(|(i, l)| (RsvgRectangle::from(i), RsvgRectangle::from(l)))?)
     }
 
+    fn get_geometries(
+        &self,
+        ids: &[&str],
+        viewport: &cairo::Rectangle,
+    ) -> Result<Vec<RsvgElementGeometry>, RenderingError> {
+        let handle = self.get_handle_ref()?;
+        let renderer = self.make_renderer(&handle);
+
+        Ok(renderer
+            .geometry_for_elements(ids, viewport)?
+            .into_iter()
+            .map(|(i, l)| RsvgElementGeometry {
+                ink_rect: RsvgRectangle::from(i),
+                logical_rect: RsvgRectangle::from(l),
+            })
+            .collect())
+    }
+
+    fn get_element_at_point(
+        &self,
+        viewport: &cairo::Rectangle,
+        x: f64,
+        y: f64,
+    ) -> Result<Option<String>, RenderingError> {
+        let handle = self.get_handle_ref()?;
+        let renderer = self.make_renderer(&handle);
+
+        Ok(renderer
+            .element_at_point(viewport, x, y)?
+            .and_then(|element| element.id()))
+    }
+
     fn render_layer(
         &self,
         cr: *mut cairo::ffi::cairo_t,
@@ -963,19 +1447,86 @@ impl CHandle {
         Ok(renderer.render_element(&cr, id, element_viewport)?)
     }
 
+    fn render_element_to_pixbuf(&self, id: Option<&str>) -> Result<Pixbuf, RenderingError> {
+        let handle = self.get_handle_ref()?;
+        let renderer = self.make_pixbuf_renderer(&handle);
+
+        let (ink_rect, _) = renderer.geometry_for_element(id)?;
+
+        if ink_rect.width() == 0.0 || ink_rect.height() == 0.0 {
+            return Ok(empty_pixbuf()?);
+        }
+
+        let surface = cairo::ImageSurface::create(
+            cairo::Format::ARgb32,
+            checked_i32(ink_rect.width().ceil())?,
+            checked_i32(ink_rect.height().ceil())?,
+        )?;
+
+        {
+            let cr = cairo::Context::new(&surface)?;
+            let viewport = cairo::Rectangle::new(0.0, 0.0, ink_rect.width(), ink_rect.height());
+            renderer.render_element(&cr, id, &viewport)?;
+        }
+
+        let surface = SharedImageSurface::wrap(surface, SurfaceType::SRgb)?;
+
+        Ok(pixbuf_from_surface(&surface)?)
+    }
+
     fn get_intrinsic_dimensions(&self) -> Result<IntrinsicDimensions, RenderingError> {
         let handle = self.get_handle_ref()?;
         let renderer = self.make_renderer(&handle);
         Ok(renderer.intrinsic_dimensions())
     }
 
-    fn get_intrinsic_size_in_pixels(&self) -> Result<Option<(f64, f64)>, RenderingError> {
+    fn get_intrinsic_length_kinds(
+        &self,
+    ) -> Result<(IntrinsicLengthKind, IntrinsicLengthKind), RenderingError> {
         let handle = self.get_handle_ref()?;
         let renderer = self.make_renderer(&handle);
-        Ok(renderer.intrinsic_size_in_pixels())
+        Ok(renderer.intrinsic_length_kinds())
     }
 
-    fn set_testing(&self, is_testing: bool) {
+    fn get_intrinsic_size_in_pixels(&self) -> Result<Option<(f64, f64)>, RenderingError> {
+        let handle = self.get_handle_ref()?;
+        let renderer = self.make_renderer(&handle);
+        Ok(renderer.intrinsic_size_in_pixels())
+    }
+
+    fn get_intrinsic_size_or_zero(&self) -> (f64, f64) {
+        if !self.is_loaded() {
+            return (0.0, 0.0);
+        }
+
+        self.get_intrinsic_size_in_pixels()
+            .ok()
+            .flatten()
+            .unwrap_or((0.0, 0.0))
+    }
+
+    fn get_viewbox_variant(&self) -> Option<glib::Variant> {
+        if !self.is_loaded() {
+            return None;
+        }
+
+        let vbox = self.get_intrinsic_dimensions().ok()?.vbox?;
+        Some((vbox.x(), vbox.y(), vbox.width(), vbox.height()).to_variant())
+    }
+
+    fn set_font_options(&self, options: Option<cairo::FontOptions>) {
+        if let LoadState::ClosedOk { ref mut handle } = *self.imp().load_state.borrow_mut() {
+            handle.set_font_options(options.as_ref());
+        }
+
+        self.imp().inner.borrow_mut().font_options = options;
+    }
+
+    fn set_pixbuf_matte(&self, matte: Option<Matte>) {
+        self.imp().inner.borrow_mut().pixbuf_matte = matte;
+    }
+
+    fn set_testing(&self, is_testing: bool) {
         let mut inner = self.imp().inner.borrow_mut();
         inner.is_testing = is_testing;
     }
@@ -1103,6 +1654,98 @@ pub unsafe extern "C" fn rsvg_handle_set_dpi_x_y(
     rhandle.set_dpi_y(dpi_y);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_set_physical_units_dpi(
+    handle: *const RsvgHandle,
+    dpi_x: libc::c_double,
+    dpi_y: libc::c_double,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_set_physical_units_dpi;
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    rhandle.set_physical_units_dpi(dpi_x, dpi_y);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_set_font_options(
+    handle: *const RsvgHandle,
+    options: *const cairo::ffi::cairo_font_options_t,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_set_font_options;
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    let options = if options.is_null() {
+        None
+    } else {
+        Some(cairo::FontOptions::from_raw_full(
+            cairo::ffi::cairo_font_options_copy(options),
+        ))
+    };
+
+    rhandle.set_font_options(options);
+}
+
+/// Converts a color in `0xRRGGBBAA` format, or `None` if it is fully transparent.
+fn rgba_from_u32(rgba: u32) -> Option<cssparser::RGBA> {
+    let [r, g, b, a] = rgba.to_be_bytes();
+
+    if a == 0 {
+        None
+    } else {
+        Some(cssparser::RGBA::new(
+            Some(r),
+            Some(g),
+            Some(b),
+            Some(f32::from(a) / 255.0),
+        ))
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_set_pixbuf_matte_color(handle: *const RsvgHandle, rgba: u32) {
+    rsvg_return_if_fail! {
+        rsvg_handle_set_pixbuf_matte_color;
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    rhandle.set_pixbuf_matte(rgba_from_u32(rgba).map(Matte::Solid));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_set_pixbuf_matte_checkerboard(
+    handle: *const RsvgHandle,
+    light_rgba: u32,
+    dark_rgba: u32,
+    square_size: libc::c_uint,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_set_pixbuf_matte_checkerboard;
+
+        is_rsvg_handle(handle),
+        square_size > 0,
+    }
+
+    let transparent = cssparser::RGBA::new(Some(0), Some(0), Some(0), Some(0.0));
+
+    let rhandle = get_rust_handle(handle);
+    rhandle.set_pixbuf_matte(Some(Matte::Checkerboard {
+        light: rgba_from_u32(light_rgba).unwrap_or(transparent),
+        dark: rgba_from_u32(dark_rgba).unwrap_or(transparent),
+        square_size,
+    }));
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_set_size_callback(
     handle: *const RsvgHandle,
@@ -1121,6 +1764,24 @@ pub unsafe extern "C" fn rsvg_handle_set_size_callback(
     rhandle.set_size_callback(size_func, user_data, destroy_notify);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_set_resource_loader(
+    handle: *const RsvgHandle,
+    loader_func: RsvgResourceLoaderFunc,
+    user_data: gpointer,
+    destroy_notify: glib::ffi::GDestroyNotify,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_set_resource_loader;
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    rhandle.set_resource_loader(loader_func, user_data, destroy_notify);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_internal_set_testing(
     handle: *const RsvgHandle,
@@ -1146,7 +1807,10 @@ trait IntoGError {
     fn into_g_warning(self) -> Self::GlibResult;
 }
 
-impl<E: fmt::Display> IntoGError for Result<(), E> {
+impl<E: fmt::Display> IntoGError for Result<(), E>
+where
+    for<'a> Error: From<&'a E>,
+{
     type GlibResult = glib::ffi::gboolean;
 
     /// Use this one when the public API actually uses a GError.
@@ -1159,7 +1823,7 @@ impl<E: fmt::Display> IntoGError for Result<(), E> {
             Ok(()) => true.into_glib(),
 
             Err(e) => {
-                set_gerror(session, error, 0, &format!("{e}"));
+                set_gerror(session, error, Error::from(&e), &format!("{e}"));
                 false.into_glib()
             }
         }
@@ -1205,6 +1869,115 @@ pub unsafe extern "C" fn rsvg_handle_read_stream_sync(
         .into_gerror(&session, error)
 }
 
+/// Reads the next chunk of the stream for `rsvg_handle_read_stream_async()`, and finishes
+/// `task` when the stream is exhausted or there is an error.
+///
+/// Each chunk is fed through the same path as `rsvg_handle_write()`, and the document is
+/// parsed like in `rsvg_handle_close()`.
+fn read_stream_async_chunk(
+    rhandle: CHandle,
+    stream: gio::InputStream,
+    cancellable: Option<gio::Cancellable>,
+    task: *mut gio::ffi::GTask,
+) {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    stream.clone().read_bytes_async(
+        CHUNK_SIZE,
+        glib::Priority::DEFAULT,
+        cancellable.clone().as_ref(),
+        move |result| unsafe {
+            match result {
+                Ok(bytes) if !bytes.is_empty() => {
+                    let session = rhandle.imp().session.clone();
+
+                    match rhandle.write(&bytes) {
+                        Ok(()) => {
+                            read_stream_async_chunk(rhandle, stream, cancellable, task);
+                            return;
+                        }
+
+                        Err(e) => {
+                            return_task_error(task, &session, Error::from(&e), &format!("{e}"))
+                        }
+                    }
+                }
+
+                Ok(_) => {
+                    let session = rhandle.imp().session.clone();
+
+                    match rhandle.close() {
+                        Ok(()) => gio::ffi::g_task_return_boolean(task, true.into_glib()),
+                        Err(e) => {
+                            return_task_error(task, &session, Error::from(&e), &format!("{e}"))
+                        }
+                    }
+                }
+
+                // Errors from the stream, including G_IO_ERROR_CANCELLED, are returned as is.
+                Err(e) => {
+                    rhandle.set_load_error();
+                    gio::ffi::g_task_return_error(task, e.into_glib_ptr());
+                }
+            }
+
+            gobject_ffi::g_object_unref(task as *mut _);
+        },
+    );
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_read_stream_async(
+    handle: *const RsvgHandle,
+    stream: *mut gio::ffi::GInputStream,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: gio::ffi::GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_read_stream_async;
+
+        is_rsvg_handle(handle),
+        is_input_stream(stream),
+        cancellable.is_null() || is_cancellable(cancellable),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
+
+    let task = gio::ffi::g_task_new(handle as *mut _, cancellable, callback, user_data);
+    gio::ffi::g_task_set_source_tag(task, rsvg_handle_read_stream_async as gpointer);
+
+    if let Err(e) = rhandle.start_async_read() {
+        return_task_error(task, &session, Error::from(&e), &format!("{e}"));
+        gobject_ffi::g_object_unref(task as *mut _);
+        return;
+    }
+
+    let stream = gio::InputStream::from_glib_none(stream);
+    let cancellable: Option<gio::Cancellable> = from_glib_none(cancellable);
+
+    // The task reference is released when the read is done.
+    read_stream_async_chunk(rhandle, stream, cancellable, task);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_read_stream_finish(
+    handle: *const RsvgHandle,
+    result: *mut gio::ffi::GAsyncResult,
+    error: *mut *mut glib::ffi::GError,
+) -> glib::ffi::gboolean {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_read_stream_finish => false.into_glib();
+
+        is_rsvg_handle(handle),
+        from_glib::<_, bool>(gio::ffi::g_task_is_valid(result, handle as *mut _)),
+        error.is_null() || (*error).is_null(),
+    }
+
+    gio::ffi::g_task_propagate_boolean(result as *mut gio::ffi::GTask, error)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_write(
     handle: *const RsvgHandle,
@@ -1221,10 +1994,10 @@ pub unsafe extern "C" fn rsvg_handle_write(
     }
 
     let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
     let buffer = slice::from_raw_parts(buf, count);
-    rhandle.write(buffer);
 
-    true.into_glib()
+    rhandle.write(buffer).into_gerror(&session, error)
 }
 
 #[no_mangle]
@@ -1266,6 +2039,44 @@ pub unsafe extern "C" fn rsvg_handle_has_sub(
     rhandle.has_sub(&id).unwrap_or(false).into_glib()
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_element_ids(
+    handle: *const RsvgHandle,
+) -> *mut *mut libc::c_char {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_element_ids => ptr::null_mut();
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    let ids = rhandle.get_element_ids().unwrap_or_default();
+    glib::StrV::from(ids).into_raw()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_links(
+    handle: *const RsvgHandle,
+) -> *mut glib::ffi::GPtrArray {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_links => ptr::null_mut();
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    let array = glib::ffi::g_ptr_array_new_with_free_func(Some(glib::ffi::g_free));
+
+    for link in rhandle.get_links().unwrap_or_default() {
+        let link: *mut libc::c_char = link.to_glib_full();
+        glib::ffi::g_ptr_array_add(array, link as gpointer);
+    }
+
+    array
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_render_cairo(
     handle: *const RsvgHandle,
@@ -1353,6 +2164,111 @@ pub unsafe extern "C" fn rsvg_handle_get_pixbuf_sub(
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_pixbuf_for_size(
+    handle: *const RsvgHandle,
+    width: libc::c_int,
+    height: libc::c_int,
+    preserve_aspect_ratio: glib::ffi::gboolean,
+    error: *mut *mut glib::ffi::GError,
+) -> *mut gdk_pixbuf::ffi::GdkPixbuf {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_pixbuf_for_size => ptr::null_mut();
+
+        is_rsvg_handle(handle),
+        width > 0 && height > 0,
+        error.is_null() || (*error).is_null(),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    match rhandle.get_pixbuf_for_size(width, height, from_glib(preserve_aspect_ratio)) {
+        Ok(pixbuf) => pixbuf.to_glib_full(),
+        Err(e) => {
+            let session = &rhandle.imp().session;
+            set_gerror(session, error, Error::from(&e), &format!("{e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Fails `task` with an `RsvgError` that has the specified code and message.
+unsafe fn return_task_error(task: *mut gio::ffi::GTask, session: &Session, code: Error, msg: &str) {
+    let mut error = ptr::null_mut();
+    set_gerror(session, &mut error, code, msg);
+    gio::ffi::g_task_return_error(task, error);
+}
+
+unsafe extern "C" fn unref_pixbuf(pixbuf: gpointer) {
+    gobject_ffi::g_object_unref(pixbuf as *mut _);
+}
+
+unsafe extern "C" fn get_pixbuf_thread(
+    task: *mut gio::ffi::GTask,
+    source_object: *mut gobject_ffi::GObject,
+    _task_data: gpointer,
+    _cancellable: *mut gio::ffi::GCancellable,
+) {
+    if from_glib::<_, bool>(gio::ffi::g_task_return_error_if_cancelled(task)) {
+        return;
+    }
+
+    let rhandle = get_rust_handle(source_object as *const RsvgHandle);
+    let session = rhandle.imp().session.clone();
+
+    match rhandle.get_pixbuf_sub(None) {
+        Ok(pixbuf) => {
+            let pixbuf: *mut gdk_pixbuf::ffi::GdkPixbuf = pixbuf.to_glib_full();
+            gio::ffi::g_task_return_pointer(task, pixbuf as gpointer, Some(unref_pixbuf))
+        }
+
+        Err(e) => return_task_error(
+            task,
+            &session,
+            Error::from(&e),
+            &format!("could not render: {e}"),
+        ),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_pixbuf_async(
+    handle: *const RsvgHandle,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: gio::ffi::GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_get_pixbuf_async;
+
+        is_rsvg_handle(handle),
+        cancellable.is_null() || is_cancellable(cancellable),
+    }
+
+    let task = gio::ffi::g_task_new(handle as *mut _, cancellable, callback, user_data);
+    gio::ffi::g_task_set_source_tag(task, rsvg_handle_get_pixbuf_async as gpointer);
+    gio::ffi::g_task_run_in_thread(task, Some(get_pixbuf_thread));
+    gobject_ffi::g_object_unref(task as *mut _);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_pixbuf_finish(
+    handle: *const RsvgHandle,
+    result: *mut gio::ffi::GAsyncResult,
+    error: *mut *mut glib::ffi::GError,
+) -> *mut gdk_pixbuf::ffi::GdkPixbuf {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_pixbuf_finish => ptr::null_mut();
+
+        is_rsvg_handle(handle),
+        from_glib::<_, bool>(gio::ffi::g_task_is_valid(result, handle as *mut _)),
+        error.is_null() || (*error).is_null(),
+    }
+
+    gio::ffi::g_task_propagate_pointer(result as *mut gio::ffi::GTask, error)
+        as *mut gdk_pixbuf::ffi::GdkPixbuf
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_get_dimensions(
     handle: *const RsvgHandle,
@@ -1466,7 +2382,7 @@ pub unsafe extern "C" fn rsvg_handle_new_from_file(
             // API calls, so that we can log the call to rsvg_handle_new_from_file() and
             // then pass *that* session to rsvg_handle_new_from_gfile_sync() below.
             let session = Session::default();
-            set_gerror(&session, error, 0, &s);
+            set_gerror(&session, error, Error::InvalidReference, &s);
             return ptr::null_mut();
         }
     };
@@ -1508,7 +2424,7 @@ pub unsafe extern "C" fn rsvg_handle_new_from_gfile_sync(
         Ok(()) => raw_handle,
 
         Err(e) => {
-            set_gerror(&session, error, 0, &format!("{e}"));
+            set_gerror(&session, error, Error::from(&e), &format!("{e}"));
             gobject_ffi::g_object_unref(raw_handle as *mut _);
             ptr::null_mut()
         }
@@ -1549,7 +2465,7 @@ pub unsafe extern "C" fn rsvg_handle_new_from_stream_sync(
         Ok(()) => raw_handle,
 
         Err(e) => {
-            set_gerror(&session, error, 0, &format!("{e}"));
+            set_gerror(&session, error, Error::from(&e), &format!("{e}"));
             gobject_ffi::g_object_unref(raw_handle as *mut _);
             ptr::null_mut()
         }
@@ -1597,6 +2513,35 @@ pub unsafe extern "C" fn rsvg_handle_new_from_data(
     ret
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_new_from_bytes(
+    bytes: *mut glib::ffi::GBytes,
+    flags: RsvgHandleFlags,
+    base_file: *mut gio::ffi::GFile,
+    error: *mut *mut glib::ffi::GError,
+) -> *const RsvgHandle {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_new_from_bytes => ptr::null();
+
+        !bytes.is_null(),
+        base_file.is_null() || is_gfile(base_file),
+        error.is_null() || (*error).is_null(),
+    }
+
+    // The MemoryInputStream just takes a reference to the GBytes, so the
+    // buffer is never copied.
+    let bytes: glib::Bytes = from_glib_none(bytes);
+    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+
+    rsvg_handle_new_from_stream_sync(
+        stream.upcast_ref::<gio::InputStream>().to_glib_none().0,
+        base_file,
+        flags,
+        ptr::null_mut(), // cancellable
+        error,
+    )
+}
+
 unsafe fn set_out_param<T: Copy>(
     out_has_param: *mut glib::ffi::gboolean,
     out_param: *mut T,
@@ -1647,7 +2592,12 @@ pub unsafe extern "C" fn rsvg_handle_set_stylesheet(
             match str::from_utf8(s) {
                 Ok(s) => s,
                 Err(e) => {
-                    set_gerror(&session, error, 0, &format!("CSS is not valid UTF-8: {e}"));
+                    set_gerror(
+                        &session,
+                        error,
+                        Error::Css,
+                        &format!("CSS is not valid UTF-8: {e}"),
+                    );
                     return false.into_glib();
                 }
             }
@@ -1657,6 +2607,74 @@ pub unsafe extern "C" fn rsvg_handle_set_stylesheet(
     rhandle.set_stylesheet(css).into_gerror(&session, error)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_set_stylesheet_file(
+    handle: *const RsvgHandle,
+    file: *mut gio::ffi::GFile,
+    error: *mut *mut glib::ffi::GError,
+) -> glib::ffi::gboolean {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_set_stylesheet_file => false.into_glib();
+
+        is_rsvg_handle(handle),
+        is_gfile(file),
+        error.is_null() || (*error).is_null(),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
+
+    let file = gio::File::from_glib_none(file);
+
+    rhandle
+        .set_stylesheet_file(&file)
+        .into_gerror(&session, error)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_add_font_file(
+    handle: *const RsvgHandle,
+    filename: *const libc::c_char,
+    error: *mut *mut glib::ffi::GError,
+) -> glib::ffi::gboolean {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_add_font_file => false.into_glib();
+
+        is_rsvg_handle(handle),
+        !filename.is_null(),
+        error.is_null() || (*error).is_null(),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
+
+    let path = PathBuf::from_glib_none(filename);
+
+    rhandle.add_font_file(&path).into_gerror(&session, error)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_add_font_bytes(
+    handle: *const RsvgHandle,
+    bytes: *mut glib::ffi::GBytes,
+    error: *mut *mut glib::ffi::GError,
+) -> glib::ffi::gboolean {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_add_font_bytes => false.into_glib();
+
+        is_rsvg_handle(handle),
+        !bytes.is_null(),
+        error.is_null() || (*error).is_null(),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
+
+    let bytes = glib::Bytes::from_glib_none(bytes);
+
+    rhandle.add_font_bytes(&bytes).into_gerror(&session, error)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_get_intrinsic_dimensions(
     handle: *const RsvgHandle,
@@ -1688,6 +2706,52 @@ pub unsafe extern "C" fn rsvg_handle_get_intrinsic_dimensions(
     set_out_param(out_has_viewbox, out_viewbox, &r);
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_intrinsic_dimensions_full(
+    handle: *const RsvgHandle,
+    out_width_kind: *mut IntrinsicLengthKind,
+    out_width: *mut Length,
+    out_height_kind: *mut IntrinsicLengthKind,
+    out_height: *mut Length,
+    out_has_viewbox: *mut glib::ffi::gboolean,
+    out_viewbox: *mut RsvgRectangle,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_get_intrinsic_dimensions_full;
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    let d = rhandle
+        .get_intrinsic_dimensions()
+        .unwrap_or_else(|_| panic!("API called out of order"));
+
+    let (width_kind, height_kind) = rhandle
+        .get_intrinsic_length_kinds()
+        .unwrap_or_else(|_| panic!("API called out of order"));
+
+    if !out_width_kind.is_null() {
+        *out_width_kind = width_kind;
+    }
+
+    if !out_width.is_null() {
+        *out_width = d.width;
+    }
+
+    if !out_height_kind.is_null() {
+        *out_height_kind = height_kind;
+    }
+
+    if !out_height.is_null() {
+        *out_height = d.height;
+    }
+
+    let r = d.vbox.map(RsvgRectangle::from);
+    set_out_param(out_has_viewbox, out_viewbox, &r);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_get_intrinsic_size_in_pixels(
     handle: *const RsvgHandle,
@@ -1743,6 +2807,88 @@ pub unsafe extern "C" fn rsvg_handle_render_document(
         .into_gerror(&session, error)
 }
 
+/// Arguments for the thread in `rsvg_handle_render_document_async()`.
+struct RenderDocumentData {
+    cr: *mut cairo::ffi::cairo_t,
+    viewport: cairo::Rectangle,
+}
+
+unsafe extern "C" fn free_render_document_data(data: gpointer) {
+    let data = Box::from_raw(data as *mut RenderDocumentData);
+    cairo::ffi::cairo_destroy(data.cr);
+}
+
+unsafe extern "C" fn render_document_thread(
+    task: *mut gio::ffi::GTask,
+    source_object: *mut gobject_ffi::GObject,
+    task_data: gpointer,
+    _cancellable: *mut gio::ffi::GCancellable,
+) {
+    if from_glib::<_, bool>(gio::ffi::g_task_return_error_if_cancelled(task)) {
+        return;
+    }
+
+    let rhandle = get_rust_handle(source_object as *const RsvgHandle);
+    let session = rhandle.imp().session.clone();
+    let data = &*(task_data as *const RenderDocumentData);
+
+    match rhandle.render_document(data.cr, &data.viewport) {
+        Ok(()) => gio::ffi::g_task_return_boolean(task, true.into_glib()),
+        Err(e) => return_task_error(task, &session, Error::from(&e), &format!("{e}")),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_render_document_async(
+    handle: *const RsvgHandle,
+    cr: *mut cairo::ffi::cairo_t,
+    viewport: *const RsvgRectangle,
+    cancellable: *mut gio::ffi::GCancellable,
+    callback: gio::ffi::GAsyncReadyCallback,
+    user_data: gpointer,
+) {
+    rsvg_return_if_fail! {
+        rsvg_handle_render_document_async;
+
+        is_rsvg_handle(handle),
+        !cr.is_null(),
+        !viewport.is_null(),
+        cancellable.is_null() || is_cancellable(cancellable),
+    }
+
+    let data = Box::new(RenderDocumentData {
+        cr: cairo::ffi::cairo_reference(cr),
+        viewport: (*viewport).into(),
+    });
+
+    let task = gio::ffi::g_task_new(handle as *mut _, cancellable, callback, user_data);
+    gio::ffi::g_task_set_source_tag(task, rsvg_handle_render_document_async as gpointer);
+    gio::ffi::g_task_set_task_data(
+        task,
+        Box::into_raw(data) as gpointer,
+        Some(free_render_document_data),
+    );
+    gio::ffi::g_task_run_in_thread(task, Some(render_document_thread));
+    gobject_ffi::g_object_unref(task as *mut _);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_render_document_finish(
+    handle: *const RsvgHandle,
+    result: *mut gio::ffi::GAsyncResult,
+    error: *mut *mut glib::ffi::GError,
+) -> glib::ffi::gboolean {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_render_document_finish => false.into_glib();
+
+        is_rsvg_handle(handle),
+        from_glib::<_, bool>(gio::ffi::g_task_is_valid(result, handle as *mut _)),
+        error.is_null() || (*error).is_null(),
+    }
+
+    gio::ffi::g_task_propagate_boolean(result as *mut gio::ffi::GTask, error)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_get_geometry_for_layer(
     handle: *mut RsvgHandle,
@@ -1779,6 +2925,80 @@ pub unsafe extern "C" fn rsvg_handle_get_geometry_for_layer(
         .into_gerror(&session, error)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_geometries(
+    handle: *mut RsvgHandle,
+    ids: *const *const libc::c_char,
+    viewport: *const RsvgRectangle,
+    error: *mut *mut glib::ffi::GError,
+) -> *mut glib::ffi::GArray {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_geometries => ptr::null_mut();
+
+        is_rsvg_handle(handle),
+        !ids.is_null(),
+        !viewport.is_null(),
+        error.is_null() || (*error).is_null(),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
+
+    let ids: Vec<String> = FromGlibPtrContainer::from_glib_none(ids);
+    let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+    match rhandle.get_geometries(&ids, &(*viewport).into()) {
+        Ok(geometries) => {
+            let array = glib::ffi::g_array_sized_new(
+                false.into_glib(),
+                false.into_glib(),
+                mem::size_of::<RsvgElementGeometry>() as libc::c_uint,
+                geometries.len() as libc::c_uint,
+            );
+            glib::ffi::g_array_append_vals(
+                array,
+                geometries.as_ptr() as glib::ffi::gconstpointer,
+                geometries.len() as libc::c_uint,
+            );
+            array
+        }
+
+        Err(e) => {
+            set_gerror(&session, error, Error::from(&e), &format!("{e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_element_at_point(
+    handle: *mut RsvgHandle,
+    viewport: *const RsvgRectangle,
+    x: f64,
+    y: f64,
+    error: *mut *mut glib::ffi::GError,
+) -> *mut libc::c_char {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_element_at_point => ptr::null_mut();
+
+        is_rsvg_handle(handle),
+        !viewport.is_null(),
+        error.is_null() || (*error).is_null(),
+    }
+
+    let rhandle = get_rust_handle(handle);
+    let session = rhandle.imp().session.clone();
+
+    match rhandle.get_element_at_point(&(*viewport).into(), x, y) {
+        Ok(id) => id.to_glib_full(),
+
+        Err(e) => {
+            set_gerror(&session, error, Error::from(&e), &format!("{e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_handle_render_layer(
     handle: *const RsvgHandle,
@@ -1868,36 +3088,85 @@ pub unsafe extern "C" fn rsvg_handle_render_element(
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rsvg_handle_get_desc(handle: *const RsvgHandle) -> *mut libc::c_char {
+pub unsafe extern "C" fn rsvg_handle_render_element_to_pixbuf(
+    handle: *const RsvgHandle,
+    id: *const libc::c_char,
+    error: *mut *mut glib::ffi::GError,
+) -> *mut gdk_pixbuf::ffi::GdkPixbuf {
     rsvg_return_val_if_fail! {
-        rsvg_handle_get_desc => ptr::null_mut();
+        rsvg_handle_render_element_to_pixbuf => ptr::null_mut();
 
         is_rsvg_handle(handle),
+        error.is_null() || (*error).is_null(),
     }
 
-    ptr::null_mut()
+    let rhandle = get_rust_handle(handle);
+    let id: Option<String> = from_glib_none(id);
+
+    match rhandle.render_element_to_pixbuf(id.as_deref()) {
+        Ok(pixbuf) => pixbuf.to_glib_full(),
+        Err(e) => {
+            let session = &rhandle.imp().session;
+            set_gerror(session, error, Error::from(&e), &format!("{e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Fills `cache` with the result of `f` if it is empty, and returns a pointer to its contents.
+///
+/// The C API returns some strings as `const char *` owned by the handle; they must not
+/// change once the caller has them.
+fn cached_cstring_ptr<F>(cache: &mut Option<CString>, f: F) -> *const libc::c_char
+where
+    F: FnOnce() -> Option<String>,
+{
+    if cache.is_none() {
+        *cache = f().and_then(|s| CString::new(s).ok());
+    }
+
+    cache.as_ref().map_or(ptr::null(), |s| s.as_ptr())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_handle_get_desc(handle: *const RsvgHandle) -> *const libc::c_char {
+    rsvg_return_val_if_fail! {
+        rsvg_handle_get_desc => ptr::null();
+
+        is_rsvg_handle(handle),
+    }
+
+    let rhandle = get_rust_handle(handle);
+
+    rhandle.get_desc_ptr()
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rsvg_handle_get_metadata(handle: *const RsvgHandle) -> *mut libc::c_char {
+pub unsafe extern "C" fn rsvg_handle_get_metadata(
+    handle: *const RsvgHandle,
+) -> *const libc::c_char {
     rsvg_return_val_if_fail! {
-        rsvg_handle_get_metadata => ptr::null_mut();
+        rsvg_handle_get_metadata => ptr::null();
 
         is_rsvg_handle(handle),
     }
 
-    ptr::null_mut()
+    let rhandle = get_rust_handle(handle);
+
+    rhandle.get_metadata_ptr()
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn rsvg_handle_get_title(handle: *const RsvgHandle) -> *mut libc::c_char {
+pub unsafe extern "C" fn rsvg_handle_get_title(handle: *const RsvgHandle) -> *const libc::c_char {
     rsvg_return_val_if_fail! {
-        rsvg_handle_get_title => ptr::null_mut();
+        rsvg_handle_get_title => ptr::null();
 
         is_rsvg_handle(handle),
     }
 
-    ptr::null_mut()
+    let rhandle = get_rust_handle(handle);
+
+    rhandle.get_title_ptr()
 }
 
 #[no_mangle]
@@ -1909,6 +3178,31 @@ pub unsafe extern "C" fn rsvg_term() {}
 #[no_mangle]
 pub unsafe extern "C" fn rsvg_cleanup() {}
 
+#[no_mangle]
+pub unsafe extern "C" fn rsvg_get_features() -> *mut *mut libc::c_char {
+    let features = rsvg::features();
+
+    let mut keywords = Vec::new();
+
+    if features.filters {
+        keywords.push(String::from("filters"));
+    }
+
+    if features.text {
+        keywords.push(String::from("text"));
+    }
+
+    if features.animation {
+        keywords.push(String::from("animation"));
+    }
+
+    keywords.extend(features.image_formats.iter().map(|f| f.to_string()));
+    keywords.extend(features.backends.iter().map(|b| format!("backend:{b}")));
+    keywords.extend(features.capabilities.iter().map(|c| c.to_string()));
+
+    glib::StrV::from(keywords).into_raw()
+}
+
 /// Detects whether a `*const libc::c_char` is a path or a URI
 ///
 /// `rsvg_handle_new_from_file()` takes a `filename` argument, and advertises
@@ -2008,13 +3302,10 @@ fn check_cairo_context(cr: *mut cairo::ffi::cairo_t) -> Result<cairo::Context, R
 pub(crate) fn set_gerror(
     session: &Session,
     err: *mut *mut glib::ffi::GError,
-    code: u32,
+    code: Error,
     msg: &str,
 ) {
     unsafe {
-        // this is RSVG_ERROR_FAILED, the only error code available in RsvgError
-        assert!(code == 0);
-
         // Log this, in case the calling program passes a NULL GError, so we can at least
         // diagnose things by asking for RSVG_LOG.
         //
@@ -2031,21 +3322,74 @@ pub(crate) fn set_gerror(
     }
 }
 
+// Keep in sync with rsvg.h:RsvgError
 #[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum)]
 #[repr(u32)]
 #[enum_type(name = "RsvgError")]
-enum Error {
+pub(crate) enum Error {
     #[enum_value(name = "RSVG_ERROR_FAILED", nick = "failed")]
-    // Keep in sync with rsvg.h:RsvgError
     Failed = 0,
+
+    #[enum_value(name = "RSVG_ERROR_XML_PARSE", nick = "xml-parse")]
+    XmlParse = 1,
+
+    #[enum_value(name = "RSVG_ERROR_CSS", nick = "css")]
+    Css = 2,
+
+    #[enum_value(name = "RSVG_ERROR_INVALID_REFERENCE", nick = "invalid-reference")]
+    InvalidReference = 3,
+
+    #[enum_value(name = "RSVG_ERROR_LIMIT_EXCEEDED", nick = "limit-exceeded")]
+    LimitExceeded = 4,
+
+    #[enum_value(name = "RSVG_ERROR_CAIRO", nick = "cairo")]
+    Cairo = 5,
+
+    #[enum_value(name = "RSVG_ERROR_INVALID", nick = "invalid")]
+    Invalid = 6,
+}
+
+impl From<&LoadingError> for Error {
+    fn from(e: &LoadingError) -> Error {
+        match *e {
+            LoadingError::XmlParseError(_) | LoadingError::NoSvgRoot => Error::XmlParse,
+            LoadingError::BadCss => Error::Css,
+            LoadingError::BadUrl => Error::InvalidReference,
+            LoadingError::LimitExceeded(_) => Error::LimitExceeded,
+            LoadingError::Invalid(_) => Error::Invalid,
+            _ => Error::Failed,
+        }
+    }
+}
+
+impl From<&rsvg::RenderingError> for Error {
+    fn from(e: &rsvg::RenderingError) -> Error {
+        match *e {
+            rsvg::RenderingError::Rendering(_) => Error::Cairo,
+            rsvg::RenderingError::LimitExceeded(_) => Error::LimitExceeded,
+            rsvg::RenderingError::IdNotFound | rsvg::RenderingError::InvalidId(_) => {
+                Error::InvalidReference
+            }
+            _ => Error::Failed,
+        }
+    }
+}
+
+impl From<&RenderingError> for Error {
+    fn from(e: &RenderingError) -> Error {
+        match *e {
+            RenderingError::RenderingError(ref e) => Error::from(e),
+            RenderingError::HandleIsNotLoaded => Error::Failed,
+        }
+    }
 }
 
 /// Used as a generic error to translate to glib::Error
 ///
 /// This type implements `glib::error::ErrorDomain`, so it can be used
-/// to obtain the error code while calling `glib::Error::new()`.  Unfortunately
-/// the public librsvg API does not have detailed error codes yet, so we use
-/// this single value as the only possible error code to return.
+/// to obtain the error code while calling `glib::Error::new()`.  It does not know
+/// about the cause of the error, so it always uses `RSVG_ERROR_FAILED`; use
+/// [`set_gerror`] to report a specific error code.
 #[derive(Copy, Clone)]
 struct RsvgError;
 
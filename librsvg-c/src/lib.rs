@@ -8,33 +8,56 @@
 #[rustfmt::skip]
 pub use handle::{
     rsvg_error_get_type,
+    rsvg_get_features,
+    rsvg_handle_add_font_bytes,
+    rsvg_handle_add_font_file,
     rsvg_handle_close,
     rsvg_handle_flags_get_type,
     rsvg_handle_get_base_uri,
     rsvg_handle_get_dimensions,
     rsvg_handle_get_dimensions_sub,
+    rsvg_handle_get_element_at_point,
+    rsvg_handle_get_element_ids,
+    rsvg_handle_get_geometries,
     rsvg_handle_get_geometry_for_element,
     rsvg_handle_get_geometry_for_layer,
     rsvg_handle_get_intrinsic_dimensions,
+    rsvg_handle_get_intrinsic_dimensions_full,
     rsvg_handle_get_intrinsic_size_in_pixels,
+    rsvg_handle_get_links,
+    rsvg_handle_get_pixbuf_async,
+    rsvg_handle_get_pixbuf_finish,
+    rsvg_handle_get_pixbuf_for_size,
     rsvg_handle_get_pixbuf_sub,
     rsvg_handle_get_position_sub,
     rsvg_handle_has_sub,
     rsvg_handle_internal_set_testing,
+    rsvg_handle_new_from_bytes,
     rsvg_handle_new_from_data,
     rsvg_handle_new_from_file,
     rsvg_handle_new_from_gfile_sync,
     rsvg_handle_new_from_stream_sync,
     rsvg_handle_new_with_flags,
+    rsvg_handle_read_stream_async,
+    rsvg_handle_read_stream_finish,
     rsvg_handle_read_stream_sync,
     rsvg_handle_render_cairo_sub,
     rsvg_handle_render_element,
+    rsvg_handle_render_element_to_pixbuf,
     rsvg_handle_render_document,
+    rsvg_handle_render_document_async,
+    rsvg_handle_render_document_finish,
     rsvg_handle_render_layer,
     rsvg_handle_set_base_gfile,
     rsvg_handle_set_base_uri,
     rsvg_handle_set_dpi_x_y,
+    rsvg_handle_set_font_options,
+    rsvg_handle_set_pixbuf_matte_checkerboard,
+    rsvg_handle_set_pixbuf_matte_color,
+    rsvg_handle_set_physical_units_dpi,
+    rsvg_handle_set_resource_loader,
     rsvg_handle_set_size_callback,
+    rsvg_handle_set_stylesheet_file,
     rsvg_handle_write,
 };
 
@@ -52,7 +75,9 @@ pub use pixbuf_utils::{
 #[macro_use]
 mod messages;
 
+mod abi;
 mod dpi;
 pub mod handle;
 pub mod pixbuf_utils;
+mod progress;
 pub mod sizing;
@@ -9,7 +9,7 @@ use gdk_pixbuf::{Colorspace, Pixbuf};
 use glib::translate::*;
 
 use super::dpi::Dpi;
-use super::handle::{checked_i32, set_gerror};
+use super::handle::{checked_i32, set_gerror, Error};
 use super::sizing::LegacySize;
 
 use rsvg::c_api_only::{Session, SharedImageSurface, SurfaceType};
@@ -154,7 +154,7 @@ unsafe fn pixbuf_from_file_with_size_mode(
     let handle = match Loader::new_with_session(session.clone()).read_path(path) {
         Ok(handle) => handle,
         Err(e) => {
-            set_gerror(&session, error, 0, &format!("{e}"));
+            set_gerror(&session, error, Error::from(&e), &format!("{e}"));
             return ptr::null_mut();
         }
     };
@@ -165,7 +165,7 @@ unsafe fn pixbuf_from_file_with_size_mode(
     let (document_width, document_height) = match renderer.legacy_document_size() {
         Ok(dim) => dim,
         Err(e) => {
-            set_gerror(&session, error, 0, &format!("{e}"));
+            set_gerror(&session, error, Error::from(&e), &format!("{e}"));
             return ptr::null_mut();
         }
     };
@@ -182,7 +182,7 @@ unsafe fn pixbuf_from_file_with_size_mode(
     )
     .map(|pixbuf| pixbuf.to_glib_full())
     .unwrap_or_else(|e| {
-        set_gerror(&session, error, 0, &format!("{e}"));
+        set_gerror(&session, error, Error::from(&e), &format!("{e}"));
         ptr::null_mut()
     })
 }
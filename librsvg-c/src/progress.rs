@@ -0,0 +1,81 @@
+//! An input stream that reports how much has been read from it.
+//!
+//! `rsvg_handle_read_stream_sync()` wraps the caller's stream with this, so that it can
+//! emit the "load-progress" signal while the document gets parsed from the stream.
+
+use std::cell::{Cell, RefCell};
+
+use gio::prelude::*;
+use gio::subclass::prelude::*;
+use glib::Error;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default)]
+    pub struct ProgressInputStream {
+        pub base: RefCell<Option<gio::InputStream>>,
+        pub progress: RefCell<Option<Box<dyn Fn(usize)>>>,
+        pub bytes_read: Cell<usize>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for ProgressInputStream {
+        const NAME: &'static str = "RsvgProgressInputStream";
+
+        type Type = super::ProgressInputStream;
+        type ParentType = gio::InputStream;
+    }
+
+    impl ObjectImpl for ProgressInputStream {}
+
+    impl InputStreamImpl for ProgressInputStream {
+        fn read(
+            &self,
+            buffer: &mut [u8],
+            cancellable: Option<&gio::Cancellable>,
+        ) -> Result<usize, Error> {
+            let base = self
+                .base
+                .borrow()
+                .clone()
+                .expect("stream was created with a base");
+            let n = base.read(buffer, cancellable)?;
+
+            if n > 0 {
+                let bytes_read = self.bytes_read.get() + n;
+                self.bytes_read.set(bytes_read);
+
+                if let Some(ref progress) = *self.progress.borrow() {
+                    progress(bytes_read);
+                }
+            }
+
+            Ok(n)
+        }
+    }
+}
+
+glib::wrapper! {
+    pub struct ProgressInputStream(ObjectSubclass<imp::ProgressInputStream>)
+        @extends gio::InputStream;
+}
+
+impl ProgressInputStream {
+    /// Creates a stream that reads from `base`, and calls `progress` with the total number
+    /// of bytes read so far after each read.
+    ///
+    /// The `base` stream does not get closed along with this one; it belongs to the caller.
+    pub fn new<F>(base: &gio::InputStream, progress: F) -> ProgressInputStream
+    where
+        F: Fn(usize) + 'static,
+    {
+        let stream: ProgressInputStream = glib::Object::new();
+
+        let imp = stream.imp();
+        *imp.base.borrow_mut() = Some(base.clone());
+        *imp.progress.borrow_mut() = Some(Box::new(progress));
+
+        stream
+    }
+}
@@ -25,6 +25,36 @@
   rsvg_handle_internal_set_testing
 */
 
+/* Layout of the public structs.  Keep these in sync with librsvg-c/src/abi.rs, which
+ * has the same assertions for the Rust side.
+ */
+G_STATIC_ASSERT (sizeof (RsvgHandle) == sizeof (GObject) + 16 * sizeof (gpointer));
+G_STATIC_ASSERT (sizeof (RsvgHandleClass) == sizeof (GObjectClass) + 15 * sizeof (gpointer));
+
+G_STATIC_ASSERT (sizeof (RsvgRectangle) == 4 * sizeof (double));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgRectangle, x) == 0);
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgRectangle, y) == sizeof (double));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgRectangle, width) == 2 * sizeof (double));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgRectangle, height) == 3 * sizeof (double));
+
+G_STATIC_ASSERT (sizeof (RsvgElementGeometry) == 2 * sizeof (RsvgRectangle));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgElementGeometry, ink_rect) == 0);
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgElementGeometry, logical_rect) == sizeof (RsvgRectangle));
+
+G_STATIC_ASSERT (sizeof (RsvgPositionData) == 2 * sizeof (int));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgPositionData, x) == 0);
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgPositionData, y) == sizeof (int));
+
+G_STATIC_ASSERT (sizeof (RsvgDimensionData) == 2 * sizeof (int) + 2 * sizeof (double));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgDimensionData, width) == 0);
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgDimensionData, height) == sizeof (int));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgDimensionData, em) == 2 * sizeof (int));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgDimensionData, ex) == 2 * sizeof (int) + sizeof (double));
+
+G_STATIC_ASSERT (sizeof (RsvgUnit) == sizeof (int));
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgLength, length) == 0);
+G_STATIC_ASSERT (G_STRUCT_OFFSET (RsvgLength, unit) == sizeof (double));
+
 static void
 handle_has_correct_type_info (void)
 {
@@ -81,7 +111,7 @@ flags_registration (void)
     g_assert (G_FLAGS_CLASS_TYPE (type_class) == ty);
 
     flags_class = G_FLAGS_CLASS (type_class);
-    g_assert_cmpint (flags_class->n_values, ==, 3);
+    g_assert_cmpint (flags_class->n_values, ==, 5);
 
     assert_flags_value_matches(&flags_class->values[0],
                                RSVG_HANDLE_FLAGS_NONE,
@@ -98,6 +128,16 @@ flags_registration (void)
                                "RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA",
                                "flag-keep-image-data");
 
+    assert_flags_value_matches(&flags_class->values[3],
+                               RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES,
+                               "RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES",
+                               "flag-no-external-resources");
+
+    assert_flags_value_matches(&flags_class->values[4],
+                               RSVG_HANDLE_FLAG_STRICT,
+                               "RSVG_HANDLE_FLAG_STRICT",
+                               "flag-strict");
+
     g_type_class_unref (type_class);
 }
 
@@ -136,13 +176,43 @@ error_registration (void)
     g_assert (G_ENUM_CLASS_TYPE (type_class) == ty);
 
     enum_class = G_ENUM_CLASS (type_class);
-    g_assert_cmpint (enum_class->n_values, ==, 1);
+    g_assert_cmpint (enum_class->n_values, ==, 7);
 
     assert_enum_value_matches (&enum_class->values[0],
                                RSVG_ERROR_FAILED,
                                "RSVG_ERROR_FAILED",
                                "failed");
 
+    assert_enum_value_matches (&enum_class->values[1],
+                               RSVG_ERROR_XML_PARSE,
+                               "RSVG_ERROR_XML_PARSE",
+                               "xml-parse");
+
+    assert_enum_value_matches (&enum_class->values[2],
+                               RSVG_ERROR_CSS,
+                               "RSVG_ERROR_CSS",
+                               "css");
+
+    assert_enum_value_matches (&enum_class->values[3],
+                               RSVG_ERROR_INVALID_REFERENCE,
+                               "RSVG_ERROR_INVALID_REFERENCE",
+                               "invalid-reference");
+
+    assert_enum_value_matches (&enum_class->values[4],
+                               RSVG_ERROR_LIMIT_EXCEEDED,
+                               "RSVG_ERROR_LIMIT_EXCEEDED",
+                               "limit-exceeded");
+
+    assert_enum_value_matches (&enum_class->values[5],
+                               RSVG_ERROR_CAIRO,
+                               "RSVG_ERROR_CAIRO",
+                               "cairo");
+
+    assert_enum_value_matches (&enum_class->values[6],
+                               RSVG_ERROR_INVALID,
+                               "RSVG_ERROR_INVALID",
+                               "invalid");
+
     g_type_class_unref (type_class);
 }
 
@@ -296,7 +366,7 @@ pixbuf_overflow (void)
     GError *error = NULL;
 
     g_assert (!rsvg_pixbuf_from_file_at_zoom (filename, 1000000.0, 1000000.0, &error));
-    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_FAILED);
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_CAIRO);
     g_error_free (error);
     g_free (filename);
 }
@@ -322,6 +392,51 @@ noops_return_null (void)
     g_object_unref (handle);
 }
 
+static void
+title_and_desc (void)
+{
+    const char *data =
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\n"
+        "  <title>  Document title </title>\n"
+        "  <desc>Document <tspan>description</tspan></desc>\n"
+        "  <g><title>Not the document title</title></g>\n"
+        "  <metadata>\n"
+        "    <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"/>\n"
+        "  </metadata>\n"
+        "</svg>\n";
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new_from_data ((const guint8 *) data, strlen (data), &error);
+    g_assert_nonnull (handle);
+    g_assert_no_error (error);
+
+    const char *title = rsvg_handle_get_title (handle);
+    g_assert_cmpstr (title, ==, "Document title");
+    g_assert_true (rsvg_handle_get_title (handle) == title);
+
+    g_assert_cmpstr (rsvg_handle_get_desc (handle), ==, "Document description");
+
+    const char *expected_metadata = "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"/>";
+    g_assert_cmpstr (rsvg_handle_get_metadata (handle), ==, expected_metadata);
+
+    char *title_prop = NULL;
+    char *desc_prop = NULL;
+    char *metadata_prop = NULL;
+    g_object_get (handle,
+                  "title", &title_prop,
+                  "desc", &desc_prop,
+                  "metadata", &metadata_prop,
+                  NULL);
+    g_assert_cmpstr (title_prop, ==, "Document title");
+    g_assert_cmpstr (desc_prop, ==, "Document description");
+    g_assert_cmpstr (metadata_prop, ==, expected_metadata);
+    g_free (title_prop);
+    g_free (desc_prop);
+    g_free (metadata_prop);
+
+    g_object_unref (handle);
+}
+
 static void
 set_dpi (void)
 {
@@ -351,6 +466,44 @@ set_dpi (void)
     g_object_unref (handle);
 }
 
+static void
+set_physical_units_dpi (void)
+{
+    static const char pixels[] = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"100\" height=\"400\"/>";
+    RsvgHandle *handle;
+    GError *error = NULL;
+    gdouble width, height;
+
+    /* dpi.svg is 1in by 4in */
+    handle = load_test_document ("dpi.svg");
+    rsvg_handle_set_dpi (handle, 96.0);
+
+    rsvg_handle_set_physical_units_dpi (handle, 300.0, 150.0);
+    g_assert_true (rsvg_handle_get_intrinsic_size_in_pixels (handle, &width, &height));
+    g_assert_cmpfloat (width, ==, 300.0);
+    g_assert_cmpfloat (height, ==, 600.0);
+
+    /* Turn it off */
+    rsvg_handle_set_physical_units_dpi (handle, 0.0, 0.0);
+    g_assert_true (rsvg_handle_get_intrinsic_size_in_pixels (handle, &width, &height));
+    g_assert_cmpfloat (width, ==, 96.0);
+    g_assert_cmpfloat (height, ==, 384.0);
+
+    g_object_unref (handle);
+
+    /* Documents sized in pixels are not affected */
+    handle = rsvg_handle_new_from_data ((const guint8 *) pixels, strlen (pixels), &error);
+    g_assert_nonnull (handle);
+    g_assert_no_error (error);
+
+    rsvg_handle_set_physical_units_dpi (handle, 300.0, 300.0);
+    g_assert_true (rsvg_handle_get_intrinsic_size_in_pixels (handle, &width, &height));
+    g_assert_cmpfloat (width, ==, 100.0);
+    g_assert_cmpfloat (height, ==, 400.0);
+
+    g_object_unref (handle);
+}
+
 static void
 base_uri (void)
 {
@@ -469,6 +622,36 @@ handle_new_from_data (void)
     g_free (data);
 }
 
+static void
+handle_new_from_bytes (void)
+{
+    char *filename = get_test_filename ("dpi.svg");
+    char *data;
+    gsize length;
+    GError *error = NULL;
+
+    g_assert (g_file_get_contents (filename, &data, &length, &error));
+    g_assert_no_error (error);
+
+    GFile *file = g_file_new_for_path (filename);
+    g_free (filename);
+
+    GBytes *bytes = g_bytes_new_take (data, length);
+
+    RsvgHandle *handle = rsvg_handle_new_from_bytes (bytes, RSVG_HANDLE_FLAGS_NONE, file, &error);
+    g_assert_nonnull (handle);
+    g_assert_no_error (error);
+
+    g_bytes_unref (bytes);
+
+    RsvgDimensionData dim;
+    rsvg_handle_get_dimensions (handle, &dim);
+    g_assert_cmpint (dim.width, >, 0);
+
+    g_object_unref (handle);
+    g_object_unref (file);
+}
+
 static void
 handle_new_from_gfile_sync (void)
 {
@@ -555,6 +738,54 @@ handle_has_sub (void)
     g_object_unref (handle);
 }
 
+static void
+handle_get_element_ids_and_links (void)
+{
+    const char *data =
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">\n"
+        "  <a id=\"first\" href=\"https://example.com/\"><rect width=\"10\" height=\"10\"/></a>\n"
+        "  <g id=\"group\">\n"
+        "    <a xlink:href=\"other.svg\"><circle id=\"circle\" r=\"5\"/></a>\n"
+        "  </g>\n"
+        "</svg>\n";
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new_from_data ((const guint8 *) data, strlen (data), &error);
+    g_assert_nonnull (handle);
+    g_assert_no_error (error);
+
+    char **ids = rsvg_handle_get_element_ids (handle);
+    g_assert_nonnull (ids);
+    g_assert_cmpuint (g_strv_length (ids), ==, 3);
+    g_assert_cmpstr (ids[0], ==, "first");
+    g_assert_cmpstr (ids[1], ==, "group");
+    g_assert_cmpstr (ids[2], ==, "circle");
+
+    cairo_surface_t *surface = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 10, 10);
+    cairo_t *cr = cairo_create (surface);
+    RsvgRectangle viewport = { 0.0, 0.0, 10.0, 10.0 };
+
+    for (char **id = ids; *id; id++) {
+        char *fragment = g_strconcat ("#", *id, NULL);
+        g_assert (rsvg_handle_render_element (handle, cr, fragment, &viewport, &error));
+        g_assert_no_error (error);
+        g_free (fragment);
+    }
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (surface);
+    g_strfreev (ids);
+
+    GPtrArray *links = rsvg_handle_get_links (handle);
+    g_assert_nonnull (links);
+    g_assert_cmpuint (links->len, ==, 2);
+    g_assert_cmpstr (g_ptr_array_index (links, 0), ==, "https://example.com/");
+    g_assert_cmpstr (g_ptr_array_index (links, 1), ==, "other.svg");
+    g_ptr_array_unref (links);
+
+    g_object_unref (handle);
+}
+
 static void
 test_get_pixbuf (gboolean sub)
 {
@@ -619,6 +850,153 @@ handle_get_pixbuf_sub (void)
     test_get_pixbuf (TRUE);
 }
 
+static void
+handle_get_pixbuf_for_size (void)
+{
+    RsvgHandle *handle = load_test_document ("example.svg");
+    GError *error = NULL;
+
+    /* Stretched to the exact requested size */
+    GdkPixbuf *pixbuf = rsvg_handle_get_pixbuf_for_size (handle, 50, 50, FALSE, &error);
+    g_assert_no_error (error);
+    g_assert_nonnull (pixbuf);
+    g_assert_cmpint (gdk_pixbuf_get_width (pixbuf), ==, 50);
+    g_assert_cmpint (gdk_pixbuf_get_height (pixbuf), ==, 50);
+    g_object_unref (pixbuf);
+
+    /* Fitted within the requested size, keeping the 1:4 aspect ratio */
+    pixbuf = rsvg_handle_get_pixbuf_for_size (handle, 50, 400, TRUE, &error);
+    g_assert_no_error (error);
+    g_assert_nonnull (pixbuf);
+    g_assert_cmpint (gdk_pixbuf_get_width (pixbuf), ==, 50);
+    g_assert_cmpint (gdk_pixbuf_get_height (pixbuf), ==, 200);
+    g_object_unref (pixbuf);
+
+    /* Scaled up; rendering happens at the final size */
+    pixbuf = rsvg_handle_get_pixbuf_for_size (handle, EXAMPLE_WIDTH * 2, EXAMPLE_HEIGHT * 2, TRUE, &error);
+    g_assert_no_error (error);
+    g_assert_nonnull (pixbuf);
+    g_assert_cmpint (gdk_pixbuf_get_width (pixbuf), ==, EXAMPLE_WIDTH * 2);
+    g_assert_cmpint (gdk_pixbuf_get_height (pixbuf), ==, EXAMPLE_HEIGHT * 2);
+    g_object_unref (pixbuf);
+
+    g_object_unref (handle);
+}
+
+static void
+store_async_result (GObject *source_object, GAsyncResult *res, gpointer user_data)
+{
+    GAsyncResult **result = user_data;
+    *result = g_object_ref (res);
+}
+
+static GAsyncResult *
+wait_for_async_result (GAsyncResult **result)
+{
+    while (*result == NULL) {
+        g_main_context_iteration (NULL, TRUE);
+    }
+
+    return *result;
+}
+
+static void
+handle_read_stream_async (void)
+{
+    char *filename = get_test_filename ("example.svg");
+    GError *error = NULL;
+    GFile *file = g_file_new_for_path (filename);
+    g_assert_nonnull (file);
+
+    g_free (filename);
+
+    GFileInputStream *stream = g_file_read (file, NULL, &error);
+    g_assert_nonnull (stream);
+    g_assert_no_error (error);
+
+    RsvgHandle *handle = rsvg_handle_new ();
+    GAsyncResult *result = NULL;
+
+    rsvg_handle_read_stream_async (handle, G_INPUT_STREAM (stream), NULL, store_async_result, &result);
+    g_assert_true (rsvg_handle_read_stream_finish (handle, wait_for_async_result (&result), &error));
+    g_assert_no_error (error);
+
+    g_assert_true (rsvg_handle_has_sub (handle, EXAMPLE_ONE_ID));
+
+    g_object_unref (result);
+    g_object_unref (handle);
+    g_object_unref (file);
+    g_object_unref (stream);
+}
+
+static void
+handle_read_stream_async_cancelled (void)
+{
+    static const char data[] = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\"/>";
+    GInputStream *stream = g_memory_input_stream_new_from_data (data, strlen (data), NULL);
+    GCancellable *cancellable = g_cancellable_new ();
+    RsvgHandle *handle = rsvg_handle_new ();
+    GAsyncResult *result = NULL;
+    GError *error = NULL;
+    gboolean loaded;
+
+    g_cancellable_cancel (cancellable);
+    rsvg_handle_read_stream_async (handle, stream, cancellable, store_async_result, &result);
+    g_assert_false (rsvg_handle_read_stream_finish (handle, wait_for_async_result (&result), &error));
+    g_assert_error (error, G_IO_ERROR, G_IO_ERROR_CANCELLED);
+
+    g_object_get (handle, "loaded", &loaded, NULL);
+    g_assert_false (loaded);
+
+    g_error_free (error);
+    g_object_unref (result);
+    g_object_unref (handle);
+    g_object_unref (cancellable);
+    g_object_unref (stream);
+}
+
+static void
+handle_get_pixbuf_async (void)
+{
+    RsvgHandle *handle = load_test_document ("example.svg");
+    GAsyncResult *result = NULL;
+
+    rsvg_handle_get_pixbuf_async (handle, NULL, store_async_result, &result);
+
+    GError *error = NULL;
+    GdkPixbuf *pixbuf = rsvg_handle_get_pixbuf_finish (handle, wait_for_async_result (&result), &error);
+    g_assert_no_error (error);
+    g_assert_nonnull (pixbuf);
+
+    g_assert_cmpint (gdk_pixbuf_get_width (pixbuf), ==, EXAMPLE_WIDTH);
+    g_assert_cmpint (gdk_pixbuf_get_height (pixbuf), ==, EXAMPLE_HEIGHT);
+
+    g_object_unref (pixbuf);
+    g_object_unref (result);
+    g_object_unref (handle);
+}
+
+static void
+handle_get_pixbuf_async_cancelled (void)
+{
+    RsvgHandle *handle = load_test_document ("example.svg");
+    GCancellable *cancellable = g_cancellable_new ();
+    GAsyncResult *result = NULL;
+
+    g_cancellable_cancel (cancellable);
+    rsvg_handle_get_pixbuf_async (handle, cancellable, store_async_result, &result);
+
+    GError *error = NULL;
+    GdkPixbuf *pixbuf = rsvg_handle_get_pixbuf_finish (handle, wait_for_async_result (&result), &error);
+    g_assert_error (error, G_IO_ERROR, G_IO_ERROR_CANCELLED);
+    g_assert_null (pixbuf);
+
+    g_error_free (error);
+    g_object_unref (result);
+    g_object_unref (cancellable);
+    g_object_unref (handle);
+}
+
 /* Test that calling rsvg_handle_get_pixbuf() will produce a g_warning if there is a rendering error.
  * This is for the benefit of the C-based gdk-pixbuf loader, which uses rsvg_handle_get_pixbuf() --- with
  * the warning, calling code will at least have a clue that something went wrong, since that function
@@ -973,6 +1351,54 @@ get_intrinsic_dimensions_missing_values (void)
     g_object_unref (handle);
 }
 
+static void
+get_intrinsic_dimensions_full (void)
+{
+    const char *data =
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"50%\" viewBox=\"0 0 100 400\"/>\n";
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new_from_data ((const guint8 *) data, strlen (data), &error);
+    g_assert_nonnull (handle);
+    g_assert_no_error (error);
+
+    RsvgLengthKind width_kind;
+    RsvgLength width;
+    RsvgLengthKind height_kind;
+    RsvgLength height;
+    gboolean has_viewbox;
+    RsvgRectangle viewbox;
+
+    /* Test optional parameters */
+    rsvg_handle_get_intrinsic_dimensions_full (handle, NULL, NULL, NULL, NULL, NULL, NULL);
+
+    rsvg_handle_get_intrinsic_dimensions_full (handle,
+                                               &width_kind, &width,
+                                               &height_kind, &height,
+                                               &has_viewbox, &viewbox);
+
+    g_assert (width_kind == RSVG_LENGTH_KIND_PERCENTAGE);
+    g_assert_cmpfloat (width.length, ==, 0.5);
+    g_assert (width.unit == RSVG_UNIT_PERCENT);
+
+    g_assert (height_kind == RSVG_LENGTH_KIND_MISSING);
+    g_assert_cmpfloat (height.length, ==, 1.0);
+    g_assert (height.unit == RSVG_UNIT_PERCENT);
+
+    g_assert_true (has_viewbox);
+    g_assert_cmpfloat (viewbox.height, ==, 400.0);
+
+    g_object_unref (handle);
+
+    handle = load_test_document ("example.svg");
+
+    rsvg_handle_get_intrinsic_dimensions_full (handle, &width_kind, NULL, &height_kind, NULL, NULL, NULL);
+    g_assert (width_kind == RSVG_LENGTH_KIND_ABSOLUTE);
+    g_assert (height_kind == RSVG_LENGTH_KIND_ABSOLUTE);
+
+    g_object_unref (handle);
+}
+
 static void
 get_intrinsic_size_in_pixels_yes (void)
 {
@@ -1051,54 +1477,390 @@ set_stylesheet (void)
 }
 
 static void
-render_document (void)
+set_stylesheet_file (void)
 {
-    RsvgHandle *handle = load_test_document ("document.svg");
-
-    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
-    cairo_t *cr = cairo_create (output);
+    RsvgHandle *handle = load_test_document ("stylesheet-file.svg");
 
-    RsvgRectangle viewport = { 50.0, 50.0, 50.0, 50.0 };
+    char *filename = get_test_filename ("stylesheet-file/style.css");
+    GFile *file = g_file_new_for_path (filename);
+    g_free (filename);
 
     GError *error = NULL;
-    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert (rsvg_handle_set_stylesheet_file (handle, file, &error));
     g_assert_no_error (error);
+    g_object_unref (file);
 
-    cairo_destroy (cr);
-
-    cairo_surface_t *expected = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
-    cr = cairo_create (expected);
+    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 100, 100);
+    cairo_t *cr = cairo_create (output);
 
-    cairo_translate (cr, 50.0, 50.0);
-    cairo_rectangle (cr, 10.0, 10.0, 30.0, 30.0);
-    cairo_set_source_rgba (cr, 0.0, 0.0, 1.0, 0.5);
-    cairo_fill (cr);
-    cairo_destroy (cr);
+    RsvgRectangle viewport = { 0.0, 0.0, 100.0, 100.0 };
+    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert_no_error (error);
+    cairo_surface_flush (output);
 
-    cairo_surface_t *diff = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+    /* One rect gets its fill from an @import, the other from a relative url(); both are lime. */
+    guchar *data = cairo_image_surface_get_data (output);
+    int stride = cairo_image_surface_get_stride (output);
+    g_assert_cmphex (*(guint32 *) (data + 50 * stride + 25 * 4), ==, 0xff00ff00);
+    g_assert_cmphex (*(guint32 *) (data + 50 * stride + 75 * 4), ==, 0xff00ff00);
 
-    TestUtilsBufferDiffResult result = {0, 0};
-    test_utils_compare_surfaces (output, expected, diff, &result);
+    cairo_destroy (cr);
+    cairo_surface_destroy (output);
 
-    if (result.pixels_changed && result.max_diff > 0) {
-        g_test_fail ();
-    }
+    file = g_file_new_for_path ("/nonexistent/style.css");
+    g_assert (!rsvg_handle_set_stylesheet_file (handle, file, &error));
+    g_assert_nonnull (error);
+    g_clear_error (&error);
+    g_object_unref (file);
 
-    cairo_surface_destroy (diff);
-    cairo_surface_destroy (expected);
-    cairo_surface_destroy (output);
     g_object_unref (handle);
 }
 
 static void
-get_geometry_for_layer (void)
+check_ahem_text_geometry (RsvgHandle *handle)
 {
-    RsvgHandle *handle = load_test_document ("geometry.svg");
-
-    RsvgRectangle viewport = { 0.0, 0.0, 100.0, 400.0 };
     RsvgRectangle ink_rect;
     RsvgRectangle logical_rect;
-
+    GError *error = NULL;
+
+    g_assert (rsvg_handle_get_geometry_for_element (handle, "#text",
+                                                    &ink_rect, &logical_rect, &error));
+    g_assert_no_error (error);
+
+    /* Ahem's glyphs are squares as big as the font size; the ascent is 4/5 of it. */
+    g_assert_cmpfloat (ink_rect.x, ==, 10.0);
+    g_assert_cmpfloat (ink_rect.y, ==, 18.0);
+    g_assert_cmpfloat (ink_rect.width, ==, 40.0);
+    g_assert_cmpfloat (ink_rect.height, ==, 40.0);
+}
+
+static void
+add_font_file (void)
+{
+    RsvgHandle *handle = load_test_document ("font-file.svg");
+    rsvg_handle_internal_set_testing (handle, TRUE);
+
+    char *filename = g_build_filename (test_utils_get_test_data_path (),
+                                       "../resources/Ahem.ttf",
+                                       NULL);
+
+    GError *error = NULL;
+    g_assert (rsvg_handle_add_font_file (handle, filename, &error));
+    g_assert_no_error (error);
+
+    check_ahem_text_geometry (handle);
+
+    g_assert (!rsvg_handle_add_font_file (handle, "/nonexistent/font.ttf", &error));
+    g_assert_nonnull (error);
+    g_clear_error (&error);
+
+    g_free (filename);
+    g_object_unref (handle);
+}
+
+static void
+add_font_bytes (void)
+{
+    RsvgHandle *handle = load_test_document ("font-file.svg");
+    rsvg_handle_internal_set_testing (handle, TRUE);
+
+    char *filename = g_build_filename (test_utils_get_test_data_path (),
+                                       "../resources/Ahem.ttf",
+                                       NULL);
+    char *data;
+    gsize length;
+    GError *error = NULL;
+
+    g_assert (g_file_get_contents (filename, &data, &length, &error));
+    g_assert_no_error (error);
+
+    GBytes *bytes = g_bytes_new_take (data, length);
+
+    g_assert (rsvg_handle_add_font_bytes (handle, bytes, &error));
+    g_assert_no_error (error);
+
+    check_ahem_text_geometry (handle);
+
+    g_bytes_unref (bytes);
+    g_free (filename);
+    g_object_unref (handle);
+}
+
+/* Returns whether the surface has pixels that are neither transparent nor opaque. */
+static gboolean
+has_partial_alpha (cairo_surface_t *surface)
+{
+    cairo_surface_flush (surface);
+
+    guchar *data = cairo_image_surface_get_data (surface);
+    int width = cairo_image_surface_get_width (surface);
+    int height = cairo_image_surface_get_height (surface);
+    int stride = cairo_image_surface_get_stride (surface);
+
+    for (int y = 0; y < height; y++) {
+        for (int x = 0; x < width; x++) {
+            guint32 alpha = *(guint32 *) (data + y * stride + x * 4) >> 24;
+
+            if (alpha != 0 && alpha != 0xff) {
+                return TRUE;
+            }
+        }
+    }
+
+    return FALSE;
+}
+
+static cairo_surface_t *
+render_font_file (RsvgHandle *handle)
+{
+    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 100, 100);
+    cairo_t *cr = cairo_create (output);
+
+    /* Offset by half a pixel, so that the edges of the glyph are antialiased. */
+    RsvgRectangle viewport = { 0.5, 0.5, 100.0, 100.0 };
+    GError *error = NULL;
+
+    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert_no_error (error);
+
+    cairo_destroy (cr);
+
+    return output;
+}
+
+static void
+set_font_options (void)
+{
+    RsvgHandle *handle = load_test_document ("font-file.svg");
+
+    char *filename = g_build_filename (test_utils_get_test_data_path (),
+                                       "../resources/Ahem.ttf",
+                                       NULL);
+    GError *error = NULL;
+
+    g_assert (rsvg_handle_add_font_file (handle, filename, &error));
+    g_assert_no_error (error);
+    g_free (filename);
+
+    cairo_surface_t *output = render_font_file (handle);
+    g_assert_true (has_partial_alpha (output));
+    cairo_surface_destroy (output);
+
+    cairo_font_options_t *options = cairo_font_options_create ();
+    cairo_font_options_set_antialias (options, CAIRO_ANTIALIAS_NONE);
+    rsvg_handle_set_font_options (handle, options);
+    cairo_font_options_destroy (options);
+
+    output = render_font_file (handle);
+    g_assert_false (has_partial_alpha (output));
+    cairo_surface_destroy (output);
+
+    rsvg_handle_set_font_options (handle, NULL);
+
+    output = render_font_file (handle);
+    g_assert_true (has_partial_alpha (output));
+    cairo_surface_destroy (output);
+
+    g_object_unref (handle);
+}
+
+static GBytes *
+serve_stylesheet (const char *url, gpointer user_data, GError **error)
+{
+    int *num_calls = user_data;
+    (*num_calls)++;
+
+    if (g_str_has_suffix (url, "/style.css")) {
+        const char *css = "rect { fill: #00ff00; }";
+        return g_bytes_new_static (css, strlen (css));
+    } else {
+        g_set_error (error, G_IO_ERROR, G_IO_ERROR_PERMISSION_DENIED, "access denied to %s", url);
+        return NULL;
+    }
+}
+
+static void
+destroy_num_calls (gpointer data)
+{
+    int *num_calls = data;
+    *num_calls = -1;
+}
+
+static void
+set_resource_loader (void)
+{
+    const char *data =
+        "<?xml-stylesheet href=\"style.css\" type=\"text/css\"?>\n"
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\n"
+        "  <rect width=\"10\" height=\"10\" fill=\"red\"/>\n"
+        "  <image href=\"secret.png\" width=\"10\" height=\"10\"/>\n"
+        "</svg>\n";
+    int num_calls = 0;
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new ();
+    rsvg_handle_set_base_uri (handle, "resource:///org/gnome/librsvg/test/document.svg");
+    rsvg_handle_set_resource_loader (handle, serve_stylesheet, &num_calls, destroy_num_calls);
+
+    g_assert (rsvg_handle_write (handle, (const guchar *) data, strlen (data), &error));
+    g_assert_no_error (error);
+    g_assert (rsvg_handle_close (handle, &error));
+    g_assert_no_error (error);
+
+    g_assert_cmpint (num_calls, ==, 1);
+
+    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 10, 10);
+    cairo_t *cr = cairo_create (output);
+
+    RsvgRectangle viewport = { 0.0, 0.0, 10.0, 10.0 };
+
+    /* The denied image is skipped, so the stylesheet's fill is what gets drawn. */
+    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert_no_error (error);
+    g_assert_cmpint (num_calls, ==, 2);
+
+    cairo_surface_flush (output);
+    guint32 *pixel = (guint32 *) cairo_image_surface_get_data (output);
+    g_assert_cmphex (pixel[0], ==, 0xff00ff00);
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (output);
+
+    g_object_unref (handle);
+    g_assert_cmpint (num_calls, ==, -1);
+}
+
+static void
+no_external_resources (void)
+{
+    const char *data =
+        "<?xml-stylesheet href=\"style.css\" type=\"text/css\"?>\n"
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\n"
+        "  <rect width=\"10\" height=\"10\" fill=\"red\"/>\n"
+        "  <image href=\"secret.png\" width=\"10\" height=\"10\"/>\n"
+        "</svg>\n";
+    int num_calls = 0;
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new_with_flags (RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES);
+    rsvg_handle_set_base_uri (handle, "resource:///org/gnome/librsvg/test/document.svg");
+    rsvg_handle_set_resource_loader (handle, serve_stylesheet, &num_calls, NULL);
+
+    g_assert (rsvg_handle_write (handle, (const guchar *) data, strlen (data), &error));
+    g_assert_no_error (error);
+    g_assert (rsvg_handle_close (handle, &error));
+    g_assert_no_error (error);
+
+    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 10, 10);
+    cairo_t *cr = cairo_create (output);
+
+    RsvgRectangle viewport = { 0.0, 0.0, 10.0, 10.0 };
+
+    /* Neither the stylesheet nor the image get requested. */
+    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert_no_error (error);
+    g_assert_cmpint (num_calls, ==, 0);
+
+    cairo_surface_flush (output);
+    guint32 *pixel = (guint32 *) cairo_image_surface_get_data (output);
+    g_assert_cmphex (pixel[0], ==, 0xffff0000);
+
+    cairo_destroy (cr);
+    cairo_surface_destroy (output);
+
+    g_object_unref (handle);
+}
+
+static void
+render_document (void)
+{
+    RsvgHandle *handle = load_test_document ("document.svg");
+
+    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+    cairo_t *cr = cairo_create (output);
+
+    RsvgRectangle viewport = { 50.0, 50.0, 50.0, 50.0 };
+
+    GError *error = NULL;
+    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert_no_error (error);
+
+    cairo_destroy (cr);
+
+    cairo_surface_t *expected = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+    cr = cairo_create (expected);
+
+    cairo_translate (cr, 50.0, 50.0);
+    cairo_rectangle (cr, 10.0, 10.0, 30.0, 30.0);
+    cairo_set_source_rgba (cr, 0.0, 0.0, 1.0, 0.5);
+    cairo_fill (cr);
+    cairo_destroy (cr);
+
+    cairo_surface_t *diff = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+
+    TestUtilsBufferDiffResult result = {0, 0};
+    test_utils_compare_surfaces (output, expected, diff, &result);
+
+    if (result.pixels_changed && result.max_diff > 0) {
+        g_test_fail ();
+    }
+
+    cairo_surface_destroy (diff);
+    cairo_surface_destroy (expected);
+    cairo_surface_destroy (output);
+    g_object_unref (handle);
+}
+
+static void
+render_document_async (void)
+{
+    RsvgHandle *handle = load_test_document ("document.svg");
+
+    cairo_surface_t *output = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+    cairo_t *cr = cairo_create (output);
+
+    RsvgRectangle viewport = { 50.0, 50.0, 50.0, 50.0 };
+    GAsyncResult *result = NULL;
+
+    /* The handle keeps its own reference to the cairo_t until it is done */
+    rsvg_handle_render_document_async (handle, cr, &viewport, NULL, store_async_result, &result);
+    cairo_destroy (cr);
+
+    GError *error = NULL;
+    g_assert (rsvg_handle_render_document_finish (handle, wait_for_async_result (&result), &error));
+    g_assert_no_error (error);
+    g_object_unref (result);
+
+    cairo_surface_t *expected = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+    cr = cairo_create (expected);
+    g_assert (rsvg_handle_render_document (handle, cr, &viewport, &error));
+    g_assert_no_error (error);
+    cairo_destroy (cr);
+
+    cairo_surface_t *diff = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 150, 150);
+
+    TestUtilsBufferDiffResult diff_result = {0, 0};
+    test_utils_compare_surfaces (output, expected, diff, &diff_result);
+
+    if (diff_result.pixels_changed && diff_result.max_diff > 0) {
+        g_test_fail ();
+    }
+
+    cairo_surface_destroy (diff);
+    cairo_surface_destroy (expected);
+    cairo_surface_destroy (output);
+    g_object_unref (handle);
+}
+
+static void
+get_geometry_for_layer (void)
+{
+    RsvgHandle *handle = load_test_document ("geometry.svg");
+
+    RsvgRectangle viewport = { 0.0, 0.0, 100.0, 400.0 };
+    RsvgRectangle ink_rect;
+    RsvgRectangle logical_rect;
+
     GError *error = NULL;
 
     g_assert_false (rsvg_handle_get_geometry_for_layer (handle, "#nonexistent", &viewport,
@@ -1124,6 +1886,73 @@ get_geometry_for_layer (void)
     g_object_unref (handle);
 }
 
+static void
+get_geometries (void)
+{
+    RsvgHandle *handle = load_test_document ("geometry.svg");
+
+    RsvgRectangle viewport = { 0.0, 0.0, 100.0, 400.0 };
+    const char *ids[] = { "#two", "#one", NULL };
+    const char *bad_ids[] = { "#one", "#nonexistent", NULL };
+
+    GError *error = NULL;
+
+    g_assert_null (rsvg_handle_get_geometries (handle, bad_ids, &viewport, &error));
+    g_assert_nonnull (error);
+
+    g_clear_error (&error);
+
+    GArray *geometries = rsvg_handle_get_geometries (handle, ids, &viewport, &error);
+    g_assert_nonnull (geometries);
+    g_assert_no_error (error);
+
+    g_assert_cmpuint (geometries->len, ==, 2);
+
+    RsvgElementGeometry *two = &g_array_index (geometries, RsvgElementGeometry, 0);
+    g_assert_cmpfloat (two->ink_rect.x, ==, 5.0);
+    g_assert_cmpfloat (two->ink_rect.y, ==, 195.0);
+    g_assert_cmpfloat (two->ink_rect.width, ==, 90.0);
+    g_assert_cmpfloat (two->ink_rect.height, ==, 110.0);
+
+    g_assert_cmpfloat (two->logical_rect.x, ==, 10.0);
+    g_assert_cmpfloat (two->logical_rect.y, ==, 200.0);
+    g_assert_cmpfloat (two->logical_rect.width, ==, 80.0);
+    g_assert_cmpfloat (two->logical_rect.height, ==, 100.0);
+
+    RsvgElementGeometry *one = &g_array_index (geometries, RsvgElementGeometry, 1);
+    g_assert_cmpfloat (one->logical_rect.width, ==, 100.0);
+    g_assert_cmpfloat (one->logical_rect.height, ==, 200.0);
+
+    g_array_unref (geometries);
+    g_object_unref (handle);
+}
+
+static void
+get_element_at_point (void)
+{
+    RsvgHandle *handle = load_test_document ("geometry.svg");
+
+    RsvgRectangle viewport = { 0.0, 0.0, 100.0, 400.0 };
+    GError *error = NULL;
+
+    char *id = rsvg_handle_get_element_at_point (handle, &viewport, 50.0, 100.0, &error);
+    g_assert_no_error (error);
+    g_assert_cmpstr (id, ==, "one");
+    g_free (id);
+
+    /* The stroke of #two is drawn over #one */
+    id = rsvg_handle_get_element_at_point (handle, &viewport, 50.0, 198.0, &error);
+    g_assert_no_error (error);
+    g_assert_cmpstr (id, ==, "two");
+    g_free (id);
+
+    id = rsvg_handle_get_element_at_point (handle, &viewport, 2.0, 350.0, &error);
+    g_assert_no_error (error);
+    g_assert_null (id);
+
+    g_object_unref (handle);
+}
+
 static void
 render_layer (void)
 {
@@ -1234,6 +2063,108 @@ untransformed_element (void)
     g_object_unref (handle);
 }
 
+static const guchar *
+pixbuf_pixel (GdkPixbuf *pixbuf, int x, int y)
+{
+    g_assert_cmpint (gdk_pixbuf_get_n_channels (pixbuf), ==, 4);
+
+    return gdk_pixbuf_get_pixels (pixbuf) + y * gdk_pixbuf_get_rowstride (pixbuf) + x * 4;
+}
+
+static void
+pixbuf_matte (void)
+{
+    static const char data[] = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"4\" height=\"4\"/>";
+
+    GError *error = NULL;
+    RsvgHandle *handle = rsvg_handle_new_from_data ((const guint8 *) data, sizeof (data) - 1, &error);
+    g_assert_no_error (error);
+
+    rsvg_handle_set_pixbuf_matte_checkerboard (handle, 0xffffffff, 0x000000ff, 2);
+
+    GdkPixbuf *pixbuf = rsvg_handle_get_pixbuf (handle);
+    g_assert_nonnull (pixbuf);
+
+    g_assert_cmpint (pixbuf_pixel (pixbuf, 1, 1)[0], ==, 255);
+    g_assert_cmpint (pixbuf_pixel (pixbuf, 2, 1)[0], ==, 0);
+    g_assert_cmpint (pixbuf_pixel (pixbuf, 1, 2)[0], ==, 0);
+    g_assert_cmpint (pixbuf_pixel (pixbuf, 3, 3)[0], ==, 255);
+    g_assert_cmpint (pixbuf_pixel (pixbuf, 2, 1)[3], ==, 255);
+    g_object_unref (pixbuf);
+
+    rsvg_handle_set_pixbuf_matte_color (handle, 0xff0000ff);
+
+    pixbuf = rsvg_handle_get_pixbuf_for_size (handle, 8, 8, TRUE, &error);
+    g_assert_no_error (error);
+    g_assert_nonnull (pixbuf);
+
+    const guchar *pixel = pixbuf_pixel (pixbuf, 5, 5);
+    g_assert_cmpint (pixel[0], ==, 255);
+    g_assert_cmpint (pixel[1], ==, 0);
+    g_assert_cmpint (pixel[3], ==, 255);
+    g_object_unref (pixbuf);
+
+    /* A transparent color removes the matte */
+    rsvg_handle_set_pixbuf_matte_color (handle, 0);
+
+    pixbuf = rsvg_handle_get_pixbuf (handle);
+    g_assert_nonnull (pixbuf);
+    g_assert_cmpint (pixbuf_pixel (pixbuf, 0, 0)[3], ==, 0);
+    g_object_unref (pixbuf);
+
+    g_object_unref (handle);
+}
+
+static void
+render_element_to_pixbuf (void)
+{
+    RsvgHandle *handle = load_test_document ("geometry-element.svg");
+    GError *error = NULL;
+
+    GdkPixbuf *pixbuf = rsvg_handle_render_element_to_pixbuf (handle, "#nonexistent", &error);
+    g_assert_null (pixbuf);
+    g_assert_nonnull (error);
+    g_clear_error (&error);
+
+    /* The pixbuf has the size of the element's ink rectangle, including the stroke */
+    pixbuf = rsvg_handle_render_element_to_pixbuf (handle, "#foo", &error);
+    g_assert_no_error (error);
+    g_assert_nonnull (pixbuf);
+
+    g_assert_cmpint (gdk_pixbuf_get_width (pixbuf), ==, 40);
+    g_assert_cmpint (gdk_pixbuf_get_height (pixbuf), ==, 50);
+
+    cairo_surface_t *output = test_utils_cairo_surface_from_pixbuf (pixbuf);
+    g_object_unref (pixbuf);
+
+    cairo_surface_t *expected = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 40, 50);
+    cairo_t *cr = cairo_create (expected);
+
+    cairo_rectangle (cr, 5.0, 5.0, 30.0, 40.0);
+    cairo_set_source_rgba (cr, 0.0, 0.0, 1.0, 1.0);
+    cairo_fill_preserve (cr);
+
+    cairo_set_line_width (cr, 10.0);
+    cairo_set_source_rgba (cr, 0.0, 0.0, 0.0, 1.0);
+    cairo_stroke (cr);
+
+    cairo_destroy (cr);
+
+    cairo_surface_t *diff = cairo_image_surface_create (CAIRO_FORMAT_ARGB32, 40, 50);
+
+    TestUtilsBufferDiffResult result = {0, 0};
+    test_utils_compare_surfaces (output, expected, diff, &result);
+
+    if (result.pixels_changed && result.max_diff > 0) {
+        g_test_fail ();
+    }
+
+    cairo_surface_destroy (diff);
+    cairo_surface_destroy (expected);
+    cairo_surface_destroy (output);
+    g_object_unref (handle);
+}
+
 /* https://gitlab.gnome.org/GNOME/librsvg/issues/385 */
 static void
 no_write_before_close (void)
@@ -1242,7 +2173,7 @@ no_write_before_close (void)
     GError *error = NULL;
 
     g_assert_false (rsvg_handle_close (handle, &error));
-    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_FAILED);
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_XML_PARSE);
     g_error_free (error);
     error = NULL;
 
@@ -1253,6 +2184,109 @@ no_write_before_close (void)
     g_object_unref (handle);
 }
 
+static void
+error_codes (void)
+{
+    const char *bad_xml = "<svg xmlns=\"http://www.w3.org/2000/svg\"><rect></svg>";
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new_from_data ((const guint8 *) bad_xml, strlen (bad_xml), &error);
+    g_assert_null (handle);
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_XML_PARSE);
+    g_clear_error (&error);
+
+    handle = load_test_document ("geometry-element.svg");
+
+    RsvgRectangle ink_rect;
+    RsvgRectangle logical_rect;
+
+    g_assert (!rsvg_handle_get_geometry_for_element (handle, "#nonexistent",
+                                                     &ink_rect, &logical_rect, &error));
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_INVALID_REFERENCE);
+    g_clear_error (&error);
+
+    const guint8 bad_css[] = { 0xff, 0xfe };
+    g_assert (!rsvg_handle_set_stylesheet (handle, bad_css, sizeof (bad_css), &error));
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_CSS);
+    g_clear_error (&error);
+
+    g_object_unref (handle);
+}
+
+static void
+strict_flag (void)
+{
+    const char *data =
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\n"
+        "  <rect width=\"10\" height=\"10\" fill=\"url(#missing)\"/>\n"
+        "</svg>\n";
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new_with_flags (RSVG_HANDLE_FLAG_STRICT);
+
+    g_assert (rsvg_handle_write (handle, (const guchar *) data, strlen (data), &error));
+    g_assert_no_error (error);
+    g_assert_false (rsvg_handle_close (handle, &error));
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_INVALID);
+    g_assert_nonnull (strstr (error->message, " 2:"));
+    g_clear_error (&error);
+
+    g_object_unref (handle);
+
+    /* Without the flag, the same document loads fine */
+    handle = rsvg_handle_new_from_data ((const guint8 *) data, strlen (data), &error);
+    g_assert_nonnull (handle);
+    g_assert_no_error (error);
+
+    g_object_unref (handle);
+}
+
+static void
+write_in_chunks (void)
+{
+    char *filename = get_test_filename ("example.svg");
+    char *data;
+    gsize length;
+    GError *error = NULL;
+
+    g_assert_true (g_file_get_contents (filename, &data, &length, &error));
+    g_assert_no_error (error);
+    g_free (filename);
+
+    RsvgHandle *handle = rsvg_handle_new ();
+
+    /* One byte at a time, to go through the detection of compressed data */
+    for (gsize i = 0; i < length; i++) {
+        g_assert_true (rsvg_handle_write (handle, (const guchar *) data + i, 1, &error));
+        g_assert_no_error (error);
+    }
+
+    g_assert_true (rsvg_handle_close (handle, &error));
+    g_assert_no_error (error);
+
+    g_assert_true (rsvg_handle_has_sub (handle, "#one"));
+
+    g_free (data);
+    g_object_unref (handle);
+}
+
+static void
+write_reports_errors_early (void)
+{
+    const char *data =
+        "<svg xmlns=\"http://www.w3.org/2000/svg\"><g></svg>\n"
+        "<!-- more data -->\n";
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new ();
+
+    g_assert_false (rsvg_handle_write (handle, (const guchar *) data, strlen (data), &error));
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_XML_PARSE);
+    g_clear_error (&error);
+
+    g_object_unref (handle);
+}
+
 static void
 empty_write_close (void)
 {
@@ -1264,7 +2298,7 @@ empty_write_close (void)
     g_assert_no_error (error);
 
     g_assert_false (rsvg_handle_close (handle, &error));
-    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_FAILED);
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_XML_PARSE);
 
     g_error_free (error);
 
@@ -1332,7 +2366,7 @@ rendering_after_close_error (void)
 
         /* Close the handle */
         g_assert_false (rsvg_handle_close (handle, &error));
-        g_assert_error (error, RSVG_ERROR, RSVG_ERROR_FAILED);
+        g_assert_error (error, RSVG_ERROR, RSVG_ERROR_XML_PARSE);
 
         g_error_free (error);
 
@@ -1402,6 +2436,8 @@ property_flags (void)
     test_flags (RSVG_HANDLE_FLAG_UNLIMITED);
     test_flags (RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA);
     test_flags (RSVG_HANDLE_FLAG_UNLIMITED | RSVG_HANDLE_FLAG_KEEP_IMAGE_DATA);
+    test_flags (RSVG_HANDLE_FLAG_NO_EXTERNAL_RESOURCES);
+    test_flags (RSVG_HANDLE_FLAG_STRICT);
 }
 
 static void
@@ -1468,6 +2504,50 @@ property_dimensions (void)
     g_object_unref (handle);
 }
 
+static void
+property_intrinsic_size (void)
+{
+    RsvgHandle *handle = load_test_document ("example.svg");
+
+    double width;
+    double height;
+    GVariant *viewbox;
+
+    g_object_get (handle,
+                  "intrinsic-width", &width,
+                  "intrinsic-height", &height,
+                  "viewbox", &viewbox,
+                  NULL);
+
+    g_assert_cmpfloat (width, ==, (double) EXAMPLE_WIDTH);
+    g_assert_cmpfloat (height, ==, (double) EXAMPLE_HEIGHT);
+
+    g_assert_nonnull (viewbox);
+
+    double x, y, vbox_width, vbox_height;
+    g_variant_get (viewbox, "(dddd)", &x, &y, &vbox_width, &vbox_height);
+    g_assert_cmpfloat (x, ==, 0.0);
+    g_assert_cmpfloat (y, ==, 0.0);
+    g_assert_cmpfloat (vbox_width, ==, (double) EXAMPLE_WIDTH);
+    g_assert_cmpfloat (vbox_height, ==, (double) EXAMPLE_HEIGHT);
+
+    g_variant_unref (viewbox);
+    g_object_unref (handle);
+
+    /* An unloaded handle has no size */
+    handle = rsvg_handle_new ();
+
+    g_object_get (handle,
+                  "intrinsic-width", &width,
+                  "viewbox", &viewbox,
+                  NULL);
+
+    g_assert_cmpfloat (width, ==, 0.0);
+    g_assert_null (viewbox);
+
+    g_object_unref (handle);
+}
+
 static void
 property_deprecated (void)
 {
@@ -1490,6 +2570,110 @@ property_deprecated (void)
     g_object_unref (handle);
 }
 
+static void
+count_loaded_notify (GObject *object, GParamSpec *pspec, gpointer user_data)
+{
+    int *count = user_data;
+    gboolean loaded;
+
+    /* The handle must be usable from the signal handler */
+    g_object_get (object, "loaded", &loaded, NULL);
+    g_assert_true (loaded);
+
+    (*count)++;
+}
+
+static void
+property_loaded (void)
+{
+    static const char data[] = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\"/>";
+    RsvgHandle *handle = rsvg_handle_new ();
+    GError *error = NULL;
+    gboolean loaded;
+    int notify_count = 0;
+
+    g_signal_connect (handle, "notify::loaded", G_CALLBACK (count_loaded_notify), &notify_count);
+
+    g_object_get (handle, "loaded", &loaded, NULL);
+    g_assert_false (loaded);
+
+    g_assert_true (rsvg_handle_write (handle, (const guchar *) data, strlen (data), &error));
+    g_assert_no_error (error);
+
+    g_object_get (handle, "loaded", &loaded, NULL);
+    g_assert_false (loaded);
+    g_assert_cmpint (notify_count, ==, 0);
+
+    g_assert_true (rsvg_handle_close (handle, &error));
+    g_assert_no_error (error);
+
+    g_object_get (handle, "loaded", &loaded, NULL);
+    g_assert_true (loaded);
+    g_assert_cmpint (notify_count, ==, 1);
+
+    /* Closing is idempotent, and does not notify again */
+    g_assert_true (rsvg_handle_close (handle, &error));
+    g_assert_cmpint (notify_count, ==, 1);
+
+    g_object_unref (handle);
+
+    handle = rsvg_handle_new ();
+    g_assert_false (rsvg_handle_close (handle, &error));
+    g_assert_error (error, RSVG_ERROR, RSVG_ERROR_XML_PARSE);
+    g_clear_error (&error);
+
+    g_object_get (handle, "loaded", &loaded, NULL);
+    g_assert_false (loaded);
+
+    g_object_unref (handle);
+}
+
+static void
+record_load_progress (RsvgHandle *handle, guint64 bytes_consumed, gpointer user_data)
+{
+    GArray *progress = user_data;
+
+    g_array_append_val (progress, bytes_consumed);
+}
+
+static void
+load_progress (void)
+{
+    static const char data[] = "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\"/>";
+    gsize len = strlen (data);
+    GArray *progress = g_array_new (FALSE, FALSE, sizeof (guint64));
+    GError *error = NULL;
+
+    RsvgHandle *handle = rsvg_handle_new ();
+    g_signal_connect (handle, "load-progress", G_CALLBACK (record_load_progress), progress);
+
+    g_assert_true (rsvg_handle_write (handle, (const guchar *) data, 10, &error));
+    g_assert_true (rsvg_handle_write (handle, (const guchar *) data + 10, len - 10, &error));
+    g_assert_true (rsvg_handle_close (handle, &error));
+    g_assert_no_error (error);
+
+    g_assert_cmpuint (progress->len, ==, 2);
+    g_assert_cmpuint (g_array_index (progress, guint64, 0), ==, 10);
+    g_assert_cmpuint (g_array_index (progress, guint64, 1), ==, len);
+
+    g_object_unref (handle);
+    g_array_set_size (progress, 0);
+
+    GInputStream *stream = g_memory_input_stream_new_from_data (data, len, NULL);
+    handle = rsvg_handle_new ();
+    g_signal_connect (handle, "load-progress", G_CALLBACK (record_load_progress), progress);
+
+    g_assert_true (rsvg_handle_read_stream_sync (handle, stream, NULL, &error));
+    g_assert_no_error (error);
+
+    g_assert_cmpuint (progress->len, ==, 1);
+    g_assert_cmpuint (g_array_index (progress, guint64, 0), ==, len);
+
+    g_object_unref (stream);
+    g_object_unref (handle);
+    g_array_unref (progress);
+}
+
 static void
 return_if_fail (void)
 {
@@ -1567,6 +2751,20 @@ library_version_constants (void)
     g_assert_cmpuint (rsvg_micro_version, ==, LIBRSVG_MICRO_VERSION);
 }
 
+static void
+get_features (void)
+{
+    gchar **features = rsvg_get_features ();
+
+    g_assert_nonnull (features);
+    g_assert_true (g_strv_contains ((const gchar * const *) features, "filters"));
+    g_assert_true (g_strv_contains ((const gchar * const *) features, "image/png"));
+    g_assert_true (g_strv_contains ((const gchar * const *) features, "backend:pdf"));
+    g_assert_false (g_strv_contains ((const gchar * const *) features, "pdf"));
+
+    g_strfreev (features);
+}
+
 typedef struct
 {
     const gchar *test_name;
@@ -1741,18 +2939,27 @@ add_api_tests (void)
     g_test_add_func ("/api/error_registration", error_registration);
     g_test_add_func ("/api/noops", noops);
     g_test_add_func ("/api/noops_return_null", noops_return_null);
+    g_test_add_func ("/api/title_and_desc", title_and_desc);
     g_test_add_func ("/api/set_dpi", set_dpi);
+    g_test_add_func ("/api/set_physical_units_dpi", set_physical_units_dpi);
     g_test_add_func ("/api/base_uri", base_uri);
     g_test_add_func ("/api/base_gfile", base_gfile);
     g_test_add_func ("/api/handle_write_close_free", handle_write_close_free);
     g_test_add_func ("/api/handle_new_from_file", handle_new_from_file);
     g_test_add_func ("/api/handle_new_from_data", handle_new_from_data);
+    g_test_add_func ("/api/handle_new_from_bytes", handle_new_from_bytes);
     g_test_add_func ("/api/handle_new_from_gfile_sync", handle_new_from_gfile_sync);
     g_test_add_func ("/api/handle_new_from_stream_sync", handle_new_from_stream_sync);
     g_test_add_func ("/api/handle_read_stream_sync", handle_read_stream_sync);
+    g_test_add_func ("/api/handle_read_stream_async", handle_read_stream_async);
+    g_test_add_func ("/api/handle_read_stream_async_cancelled", handle_read_stream_async_cancelled);
     g_test_add_func ("/api/handle_has_sub", handle_has_sub);
+    g_test_add_func ("/api/handle_get_element_ids_and_links", handle_get_element_ids_and_links);
     g_test_add_func ("/api/handle_get_pixbuf", handle_get_pixbuf);
     g_test_add_func ("/api/handle_get_pixbuf_sub", handle_get_pixbuf_sub);
+    g_test_add_func ("/api/handle_get_pixbuf_for_size", handle_get_pixbuf_for_size);
+    g_test_add_func ("/api/handle_get_pixbuf_async", handle_get_pixbuf_async);
+    g_test_add_func ("/api/handle_get_pixbuf_async_cancelled", handle_get_pixbuf_async_cancelled);
     g_test_add_func ("/api/handle_get_pixbuf_produces_g_warning", handle_get_pixbuf_produces_g_warning);
     g_test_add_func ("/api/dimensions_and_position", dimensions_and_position);
     g_test_add_func ("/api/set_size_callback", set_size_callback);
@@ -1764,14 +2971,30 @@ add_api_tests (void)
     g_test_add_func ("/api/render_cairo_sub", render_cairo_sub);
     g_test_add_func ("/api/get_intrinsic_dimensions", get_intrinsic_dimensions);
     g_test_add_func ("/api/get_intrinsic_dimensions_missing_values", get_intrinsic_dimensions_missing_values);
+    g_test_add_func ("/api/get_intrinsic_dimensions_full", get_intrinsic_dimensions_full);
     g_test_add_func ("/api/get_intrinsic_size_in_pixels/yes", get_intrinsic_size_in_pixels_yes);
     g_test_add_func ("/api/get_intrinsic_size_in_pixels/no", get_intrinsic_size_in_pixels_no);
     g_test_add_func ("/api/set_stylesheet", set_stylesheet);
+    g_test_add_func ("/api/set_stylesheet_file", set_stylesheet_file);
+    g_test_add_func ("/api/error_codes", error_codes);
+    g_test_add_func ("/api/strict_flag", strict_flag);
+    g_test_add_func ("/api/add_font_file", add_font_file);
+    g_test_add_func ("/api/add_font_bytes", add_font_bytes);
+    g_test_add_func ("/api/set_font_options", set_font_options);
+    g_test_add_func ("/api/set_resource_loader", set_resource_loader);
+    g_test_add_func ("/api/no_external_resources", no_external_resources);
     g_test_add_func ("/api/render_document", render_document);
+    g_test_add_func ("/api/render_document_async", render_document_async);
     g_test_add_func ("/api/get_geometry_for_layer", get_geometry_for_layer);
+    g_test_add_func ("/api/get_geometries", get_geometries);
+    g_test_add_func ("/api/get_element_at_point", get_element_at_point);
     g_test_add_func ("/api/render_layer", render_layer);
     g_test_add_func ("/api/untransformed_element", untransformed_element);
+    g_test_add_func ("/api/render_element_to_pixbuf", render_element_to_pixbuf);
+    g_test_add_func ("/api/pixbuf_matte", pixbuf_matte);
     g_test_add_func ("/api/no_write_before_close", no_write_before_close);
+    g_test_add_func ("/api/write_in_chunks", write_in_chunks);
+    g_test_add_func ("/api/write_reports_errors_early", write_reports_errors_early);
     g_test_add_func ("/api/empty_write_close", empty_write_close);
     g_test_add_func ("/api/ordering_render_before_load", ordering_render_before_load);
     g_test_add_func ("/api/ordering_render_while_loading", ordering_render_while_loading);
@@ -1782,13 +3005,17 @@ add_api_tests (void)
     g_test_add_func ("/api/property_dpi", property_dpi);
     g_test_add_func ("/api/property_base_uri", property_base_uri);
     g_test_add_func ("/api/property_dimensions", property_dimensions);
+    g_test_add_func ("/api/property_intrinsic_size", property_intrinsic_size);
     g_test_add_func ("/api/property_deprecated", property_deprecated);
+    g_test_add_func ("/api/property_loaded", property_loaded);
+    g_test_add_func ("/api/load_progress", load_progress);
     g_test_add_func ("/api/return_if_fail", return_if_fail);
     g_test_add_func ("/api/return_if_fail_null_check", return_if_fail_null_check);
     g_test_add_func ("/api/return_if_fail_type_check", return_if_fail_type_check);
     g_test_add_func ("/api/library_version_defines", library_version_defines);
     g_test_add_func ("/api/library_version_check", library_version_check);
     g_test_add_func ("/api/library_version_constants", library_version_constants);
+    g_test_add_func ("/api/get_features", get_features);
 }
 
 /* Tests for the deprecated APIs to get geometries */
@@ -0,0 +1,1070 @@
+//! Timing and sampling of SMIL animation elements.
+//!
+//! Librsvg does not run animations by itself; it renders documents in their static state.
+//! However, programs that turn animated SVGs into frames, like image loaders or video tools,
+//! need to know how long an animation lasts and which elements it affects, and what the
+//! document looks like at a given time.  This module reads the timing attributes of
+//! `<animate>`, `<set>`, `<animateMotion>`, `<animateColor>`, and `<animateTransform>`
+//! elements to compute that.
+//!
+//! Offset values are supported for `begin` and `end`, like `begin="2s"`.  Event-based `begin`
+//! values, like `begin="click"` or `begin="button.click+1s"`, are resolved against the
+//! events that the calling program dispatches with [`crate::SvgHandle::dispatch_event`];
+//! librsvg does not do hit-testing by itself.  Syncbase values, like `begin="other.end"`,
+//! are not resolved.
+//!
+//! When sampling, values are interpolated by matching up the numbers in them, so `10px`
+//! and `20px`, or two paths with the same commands, interpolate smoothly.  Values that
+//! don't have the same shape, like color keywords, change discretely.  `<animateMotion>`,
+//! `keySplines`, and `accumulate` are not supported, and only `<animateTransform>` can be
+//! additive.
+//!
+//! SMIL timing: <https://www.w3.org/TR/SMIL3/smil-timing.html>
+//!
+//! SMIL animation values: <https://www.w3.org/TR/SMIL3/smil-animation.html>
+
+use markup5ever::{expanded_name, local_name, namespace_url, ns, LocalName, QualName};
+
+use crate::document::Document;
+use crate::node::{Node, NodeBorrow};
+
+/// Kinds of user interface events that can start animations.
+///
+/// These correspond to the event names in event-based `begin` values of SMIL animations,
+/// like `begin="click"` or `begin="button.mouseover"`.  Pass them to
+/// [`crate::SvgHandle::dispatch_event`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EventKind {
+    /// The element was clicked; `click` in SMIL.
+    Click,
+
+    /// A pointer button was pressed over the element; `mousedown` in SMIL.
+    MouseDown,
+
+    /// A pointer button was released over the element; `mouseup` in SMIL.
+    MouseUp,
+
+    /// The pointer moved into the element; `mouseover` in SMIL.
+    MouseOver,
+
+    /// The pointer moved out of the element; `mouseout` in SMIL.
+    MouseOut,
+}
+
+impl EventKind {
+    fn from_name(name: &str) -> Option<EventKind> {
+        match name {
+            "click" => Some(EventKind::Click),
+            "mousedown" => Some(EventKind::MouseDown),
+            "mouseup" => Some(EventKind::MouseUp),
+            "mouseover" => Some(EventKind::MouseOver),
+            "mouseout" => Some(EventKind::MouseOut),
+            _ => None,
+        }
+    }
+}
+
+/// An event that was dispatched to an element at a certain time of the timeline.
+#[derive(Clone)]
+pub struct Event {
+    /// The element that received the event.
+    ///
+    /// Events bubble up, so animations that wait for an event on one of this element's
+    /// ancestors begin as well.
+    pub target: Node,
+
+    pub kind: EventKind,
+
+    /// Time in seconds at which the event happened.
+    pub time: f64,
+}
+
+/// Summary of the animations in a document.
+#[derive(Default)]
+pub struct Timeline {
+    /// Time in seconds at which the last animation ends, or `None` if there are no
+    /// animations with a resolved start time.
+    pub duration: Option<f64>,
+
+    /// Whether any animation repeats indefinitely.
+    pub loops: bool,
+
+    /// Elements affected by animations, in the order in which the animations appear.
+    pub targets: Vec<Node>,
+}
+
+/// Computes the timeline of all the animation elements in `document`.
+pub fn timeline(document: &Document) -> Timeline {
+    let mut timeline = Timeline::default();
+
+    for node in document.root().descendants().filter(is_animation_element) {
+        if let Some(target) = animation_target(document, &node) {
+            if !timeline.targets.contains(&target) {
+                timeline.targets.push(target);
+            }
+        }
+
+        let timing = Timing::from_node(&node);
+
+        if timing.repeats_indefinitely() {
+            timeline.loops = true;
+        }
+
+        if let Some(end) = timing.end_of_first_iteration() {
+            timeline.duration = Some(timeline.duration.map_or(end, |d| d.max(end)));
+        }
+    }
+
+    timeline
+}
+
+/// Values that the animations give to the attributes of one element at a certain time.
+pub struct AnimatedAttributes {
+    /// The animated element.
+    pub target: Node,
+
+    /// Attributes and their animated values, which replace the ones in the document.
+    pub values: Vec<(QualName, String)>,
+}
+
+/// Computes the values of the animated attributes in `document` at `time` seconds.
+///
+/// Animations with event-based `begin` values start at the times of the matching `events`.
+///
+/// All the elements that are targets of animations are returned, even if no animation
+/// affects them at `time`; in that case their `values` are empty, and they should be shown
+/// in their static state.
+pub fn sample(document: &Document, time: f64, events: &[Event]) -> Vec<AnimatedAttributes> {
+    let mut result: Vec<AnimatedAttributes> = Vec::new();
+
+    for node in document.root().descendants().filter(is_animation_element) {
+        let Some(target) = animation_target(document, &node) else {
+            continue;
+        };
+
+        let index = match result.iter().position(|a| a.target == target) {
+            Some(i) => i,
+            None => {
+                result.push(AnimatedAttributes {
+                    target: target.clone(),
+                    values: Vec::new(),
+                });
+                result.len() - 1
+            }
+        };
+
+        let Some(name) = attribute_name(&node) else {
+            continue;
+        };
+
+        let mut timing = Timing::from_node(&node);
+        timing.resolve_events(document, &target, events);
+
+        let Some(progress) = timing.progress(time) else {
+            continue;
+        };
+
+        let values = &mut result[index].values;
+
+        // Later animations are applied on top of earlier ones, so the underlying value is
+        // the one that previous animations computed, if any.
+        let base = values
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.clone())
+            .or_else(|| attribute(&target, &name.ns, &name.local));
+
+        if let Some(value) = animated_value(&node, base.as_deref(), progress) {
+            values.retain(|(n, _)| *n != name);
+            values.push((name, value));
+        }
+    }
+
+    result
+}
+
+fn is_animation_element(node: &Node) -> bool {
+    if !node.is_element() {
+        return false;
+    }
+
+    let element = node.borrow_element();
+
+    matches!(
+        element.element_name().expanded(),
+        expanded_name!(svg "animate")
+            | expanded_name!(svg "set")
+            | expanded_name!(svg "animateMotion")
+            | expanded_name!(svg "animateColor")
+            | expanded_name!(svg "animateTransform")
+    )
+}
+
+/// The element referenced by the animation's `href`, or its parent element otherwise.
+fn animation_target(document: &Document, node: &Node) -> Option<Node> {
+    let href = attribute(node, "", "href")
+        .or_else(|| attribute(node, "http://www.w3.org/1999/xlink", "href"));
+
+    match href {
+        Some(href) => href
+            .strip_prefix('#')
+            .and_then(|id| document.lookup_internal_node(id)),
+
+        None => node.parent().filter(|p| p.is_element()),
+    }
+}
+
+fn attribute(node: &Node, namespace: &str, name: &str) -> Option<String> {
+    node.borrow_element()
+        .get_attributes()
+        .iter()
+        .find(|(attr, _)| &*attr.ns == namespace && &*attr.local == name)
+        .map(|(_, value)| value.to_string())
+}
+
+/// The attribute that an animation modifies, from its `attributeName`.
+fn attribute_name(node: &Node) -> Option<QualName> {
+    if is_element_named(node, "animateMotion") {
+        return None;
+    }
+
+    let name = attribute(node, "", "attributeName")?;
+
+    let qual_name = match name.trim().split_once(':') {
+        Some(("xlink", local)) => QualName::new(None, ns!(xlink), LocalName::from(local)),
+        Some(_) => return None,
+        None => QualName::new(None, ns!(), LocalName::from(name.trim())),
+    };
+
+    // These are indexed when the element is created, and can't change afterwards.
+    if qual_name.expanded() == expanded_name!("", "id")
+        || qual_name.expanded() == expanded_name!("", "class")
+    {
+        return None;
+    }
+
+    Some(qual_name)
+}
+
+fn is_element_named(node: &Node, name: &str) -> bool {
+    let element = node.borrow_element();
+    let element_name = element.element_name();
+    element_name.ns == ns!(svg) && &*element_name.local == name
+}
+
+/// Computes the value of an animation when it is at `progress` through its simple
+/// duration, between 0.0 and 1.0.
+///
+/// The `base` is the underlying value of the attribute, which is used by animations that
+/// only have a `to` or a `by`, and by additive transforms.
+fn animated_value(node: &Node, base: Option<&str>, progress: f64) -> Option<String> {
+    let attr = |name| attribute(node, "", name);
+
+    if is_element_named(node, "set") {
+        return attr("to");
+    }
+
+    let keyframes = keyframes(node, base)?;
+
+    let key_times = attr("keyTimes")
+        .map(|s| {
+            s.split(';')
+                .map(|t| t.trim().parse::<f64>().ok())
+                .collect::<Option<Vec<f64>>>()
+        })
+        .and_then(|times| times.filter(|t| t.len() == keyframes.len()));
+
+    let discrete = attr("calcMode").as_deref() == Some("discrete");
+
+    let value = if discrete {
+        discrete_value(&keyframes, key_times.as_deref(), progress)
+    } else {
+        interpolated_value(&keyframes, key_times.as_deref(), progress)
+    };
+
+    if is_element_named(node, "animateTransform") {
+        let kind = attr("type").unwrap_or_else(|| String::from("translate"));
+        let transform = format!("{}({value})", kind.trim());
+
+        match base {
+            Some(base) if attr("additive").as_deref() == Some("sum") => {
+                Some(format!("{base} {transform}"))
+            }
+            _ => Some(transform),
+        }
+    } else {
+        Some(value)
+    }
+}
+
+/// The list of values that an animation goes through, from `values` or from
+/// `from`/`to`/`by`.
+fn keyframes(node: &Node, base: Option<&str>) -> Option<Vec<String>> {
+    // Opacities can be numbers or percentages; write them all as numbers so that values
+    // like `0` and `50%` can be interpolated.
+    let is_alpha = attribute_name(node).is_some_and(|n| is_alpha_value_property(&n));
+    let normalize = |v: &str| {
+        if is_alpha {
+            alpha_value_as_number(v)
+        } else {
+            v.to_string()
+        }
+    };
+
+    let attr = |name| attribute(node, "", name).map(|v| normalize(&v));
+
+    if let Some(values) = attr("values") {
+        let values: Vec<String> = values
+            .split(';')
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(normalize)
+            .collect();
+
+        return if values.is_empty() {
+            None
+        } else {
+            Some(values)
+        };
+    }
+
+    let from = attr("from").or_else(|| base.map(normalize));
+
+    match (from, attr("to"), attr("by")) {
+        (Some(from), Some(to), _) => Some(vec![from, to]),
+        (Some(from), None, Some(by)) => {
+            let to = combine(&from, &by, |a, b| a + b)?;
+            Some(vec![from, to])
+        }
+        (None, Some(to), _) => Some(vec![to]),
+        _ => None,
+    }
+}
+
+/// Whether the attribute is a property whose value is an `<alpha-value>`.
+fn is_alpha_value_property(name: &QualName) -> bool {
+    name.ns == ns!()
+        && matches!(
+            &*name.local,
+            "opacity" | "fill-opacity" | "stroke-opacity" | "stop-opacity" | "flood-opacity"
+        )
+}
+
+/// Writes a percentage like `50%` as a number like `0.5`; other values are unchanged.
+fn alpha_value_as_number(value: &str) -> String {
+    match value
+        .trim()
+        .strip_suffix('%')
+        .and_then(|p| p.parse::<f64>().ok())
+    {
+        Some(percent) => (percent / 100.0).to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn discrete_value(keyframes: &[String], key_times: Option<&[f64]>, progress: f64) -> String {
+    let index = match key_times {
+        Some(times) => times.iter().rposition(|&t| t <= progress).unwrap_or(0),
+        None => (progress * keyframes.len() as f64) as usize,
+    };
+
+    keyframes[index.min(keyframes.len() - 1)].clone()
+}
+
+fn interpolated_value(keyframes: &[String], key_times: Option<&[f64]>, progress: f64) -> String {
+    let n = keyframes.len();
+
+    if n == 1 {
+        return keyframes[0].clone();
+    }
+
+    let time_at = |i: usize| match key_times {
+        Some(times) => times[i],
+        None => i as f64 / (n - 1) as f64,
+    };
+
+    let segment = (0..n - 1)
+        .find(|&i| progress < time_at(i + 1))
+        .unwrap_or(n - 2);
+
+    let (start, end) = (time_at(segment), time_at(segment + 1));
+
+    let t = if end > start {
+        ((progress - start) / (end - start)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+
+    let (from, to) = (&keyframes[segment], &keyframes[segment + 1]);
+
+    // Values that can't be interpolated switch halfway through the segment.
+    combine(from, to, |a, b| a + (b - a) * t).unwrap_or_else(|| {
+        if t < 0.5 {
+            from.clone()
+        } else {
+            to.clone()
+        }
+    })
+}
+
+/// Combines the numbers in two values that have the same shape, that is, the same text
+/// between their numbers, like `translate(10, 20)` and `translate(30, 40)`.
+fn combine(a: &str, b: &str, f: impl Fn(f64, f64) -> f64) -> Option<String> {
+    let (a_text, a_numbers) = split_numbers(a);
+    let (b_text, b_numbers) = split_numbers(b);
+
+    if a_text != b_text || a_numbers.is_empty() {
+        return None;
+    }
+
+    let mut result = String::from(a_text[0]);
+
+    for (i, (x, y)) in a_numbers.iter().zip(b_numbers.iter()).enumerate() {
+        result.push_str(&f(*x, *y).to_string());
+        result.push_str(a_text[i + 1]);
+    }
+
+    Some(result)
+}
+
+/// Splits a value into the numbers in it and the text around them.
+///
+/// There is always one more piece of text than numbers; for example, `M 10,20` turns into
+/// `["M ", ",", ""]` and `[10.0, 20.0]`.  Hex colors like `#102030` are kept as text.
+fn split_numbers(s: &str) -> (Vec<&str>, Vec<f64>) {
+    let bytes = s.as_bytes();
+    let mut text = Vec::new();
+    let mut numbers = Vec::new();
+
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'#' {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            continue;
+        }
+
+        match number_length(&bytes[i..]) {
+            Some(len) => {
+                text.push(&s[text_start..i]);
+                numbers.push(s[i..i + len].parse().unwrap());
+                i += len;
+                text_start = i;
+            }
+
+            None => i += 1,
+        }
+    }
+
+    text.push(&s[text_start..]);
+
+    (text, numbers)
+}
+
+/// Returns the length of the number at the start of `bytes`, like `-1.5e3`, if there is one.
+fn number_length(bytes: &[u8]) -> Option<usize> {
+    let digits = |start: usize| {
+        bytes[start.min(bytes.len())..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+
+    let mut len = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+
+    let whole = digits(len);
+    len += whole;
+
+    let mut fraction = 0;
+    if bytes.get(len) == Some(&b'.') {
+        fraction = digits(len + 1);
+        if fraction > 0 {
+            len += 1 + fraction;
+        }
+    }
+
+    if whole == 0 && fraction == 0 {
+        return None;
+    }
+
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let exponent = digits(len + 1 + sign);
+        if exponent > 0 {
+            len += 1 + sign + exponent;
+        }
+    }
+
+    Some(len)
+}
+
+/// A duration that may be indefinite.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Duration {
+    Seconds(f64),
+    Indefinite,
+}
+
+/// An event-based `begin` value, like `click` or `button.click+1s`.
+#[derive(Debug, PartialEq)]
+struct EventValue {
+    /// Id of the element that receives the event, or `None` for the animation's target.
+    element: Option<String>,
+
+    kind: EventKind,
+
+    /// Offset in seconds from the time of the event.
+    offset: f64,
+}
+
+/// The timing attributes of one animation element.
+#[derive(Debug, PartialEq)]
+struct Timing {
+    begin: Vec<f64>,
+
+    /// Event-based `begin` values, which become part of `begin` once their events happen.
+    begin_events: Vec<EventValue>,
+
+    end: Vec<f64>,
+    dur: Option<Duration>,
+    repeat_count: Option<RepeatCount>,
+    repeat_dur: Option<Duration>,
+
+    /// Whether the animation keeps its last value after it ends, with `fill="freeze"`.
+    freeze: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum RepeatCount {
+    Count(f64),
+    Indefinite,
+}
+
+impl Timing {
+    fn from_node(node: &Node) -> Timing {
+        let attr = |name| attribute(node, "", name);
+
+        let (begin, begin_events) = match attr("begin") {
+            Some(s) => (parse_offsets(&s), parse_event_values(&s)),
+            None => (vec![0.0], Vec::new()),
+        };
+
+        let end = attr("end").map(|s| parse_offsets(&s)).unwrap_or_default();
+
+        Timing {
+            begin,
+            begin_events,
+            end,
+            dur: attr("dur").and_then(|s| parse_duration(&s)),
+            repeat_count: attr("repeatCount").and_then(|s| parse_repeat_count(&s)),
+            repeat_dur: attr("repeatDur").and_then(|s| parse_duration(&s)),
+            freeze: attr("fill").as_deref() == Some("freeze"),
+        }
+    }
+
+    /// Adds a begin time for each of the `events` that one of the event-based `begin` values
+    /// is waiting for.  Those without an element id wait for events on `target`.
+    fn resolve_events(&mut self, document: &Document, target: &Node, events: &[Event]) {
+        for value in &self.begin_events {
+            let base = match value.element {
+                Some(ref id) => document.lookup_internal_node(id),
+                None => Some(target.clone()),
+            };
+
+            let Some(base) = base else {
+                continue;
+            };
+
+            self.begin.extend(
+                events
+                    .iter()
+                    .filter(|e| e.kind == value.kind && e.target.ancestors().any(|n| n == base))
+                    .map(|e| e.time + value.offset),
+            );
+        }
+    }
+
+    /// Computes how far through its simple duration the animation is at `time`, as a
+    /// number between 0.0 and 1.0, or `None` if the animation does not apply at that time.
+    ///
+    /// Animations without a simple duration stay at their start once they begin.
+    fn progress(&self, time: f64) -> Option<f64> {
+        let begin = self
+            .begin
+            .iter()
+            .copied()
+            .filter(|&b| b <= time)
+            .reduce(f64::max)?;
+
+        let end = self.active_end(begin);
+
+        let elapsed = if time < end {
+            time - begin
+        } else if self.freeze {
+            end - begin
+        } else {
+            return None;
+        };
+
+        match self.dur {
+            Some(Duration::Seconds(dur)) if dur > 0.0 => {
+                let iterations = elapsed / dur;
+                let fraction = iterations.fract();
+
+                // A frozen animation that ends at the end of an iteration keeps the last
+                // value of that iteration, not the first value of the next one.
+                if fraction == 0.0 && iterations > 0.0 && time >= end {
+                    Some(1.0)
+                } else {
+                    Some(fraction)
+                }
+            }
+
+            _ => Some(0.0),
+        }
+    }
+
+    /// Computes the end of the active duration for an animation that starts at `begin`.
+    fn active_end(&self, begin: f64) -> f64 {
+        let active_duration = match self.dur {
+            Some(Duration::Seconds(dur)) if dur > 0.0 => {
+                let by_count = match self.repeat_count {
+                    Some(RepeatCount::Count(n)) => Some(dur * n),
+                    Some(RepeatCount::Indefinite) => Some(f64::INFINITY),
+                    None => None,
+                };
+
+                let by_dur = match self.repeat_dur {
+                    Some(Duration::Seconds(d)) => Some(d),
+                    Some(Duration::Indefinite) => Some(f64::INFINITY),
+                    None => None,
+                };
+
+                match (by_count, by_dur) {
+                    (Some(a), Some(b)) => a.min(b),
+                    (Some(a), None) | (None, Some(a)) => a,
+                    (None, None) => dur,
+                }
+            }
+
+            _ => f64::INFINITY,
+        };
+
+        let end = begin + active_duration;
+
+        let explicit_end = self
+            .end
+            .iter()
+            .copied()
+            .filter(|&e| e >= begin)
+            .reduce(f64::min);
+
+        explicit_end.map_or(end, |e| e.min(end))
+    }
+
+    fn repeats_indefinitely(&self) -> bool {
+        let has_simple_duration = matches!(self.dur, Some(Duration::Seconds(d)) if d > 0.0);
+
+        self.end.is_empty()
+            && has_simple_duration
+            && (self.repeat_count == Some(RepeatCount::Indefinite)
+                || self.repeat_dur == Some(Duration::Indefinite))
+    }
+
+    /// Computes when the animation ends, or when its first iteration ends if it repeats
+    /// indefinitely.
+    ///
+    /// Animations without a simple duration, like `<set>` without `dur`, just change
+    /// their target when they begin and keep it that way, so they end at that point.
+    fn end_of_first_iteration(&self) -> Option<f64> {
+        let begin = self.begin.iter().copied().reduce(f64::max)?;
+
+        let active_duration = match self.dur {
+            Some(Duration::Seconds(dur)) => {
+                let by_count = match self.repeat_count {
+                    Some(RepeatCount::Count(n)) => Some(dur * n),
+                    Some(RepeatCount::Indefinite) => Some(dur),
+                    None => None,
+                };
+
+                let by_dur = match self.repeat_dur {
+                    Some(Duration::Seconds(d)) => Some(d),
+                    Some(Duration::Indefinite) => Some(dur),
+                    None => None,
+                };
+
+                match (by_count, by_dur) {
+                    (Some(a), Some(b)) => a.min(b),
+                    (Some(a), None) | (None, Some(a)) => a,
+                    (None, None) => dur,
+                }
+            }
+
+            Some(Duration::Indefinite) | None => 0.0,
+        };
+
+        let end = begin + active_duration;
+
+        // An explicit end cuts the animation short.
+        let explicit_end = self
+            .end
+            .iter()
+            .copied()
+            .filter(|&e| e >= begin)
+            .reduce(f64::min);
+
+        Some(explicit_end.map_or(end, |e| e.min(end)))
+    }
+}
+
+/// Parses a `begin` or `end` list, and keeps only the offset values.
+fn parse_offsets(s: &str) -> Vec<f64> {
+    s.split(';')
+        .filter_map(|v| parse_offset(v.trim()))
+        .collect()
+}
+
+/// Parses a `begin` list, and keeps only the event-based values.
+fn parse_event_values(s: &str) -> Vec<EventValue> {
+    s.split(';')
+        .filter_map(|v| parse_event_value(v.trim()))
+        .collect()
+}
+
+/// Parses `(id ".")? event-name (("+" | "-") clock-value)?`.
+fn parse_event_value(s: &str) -> Option<EventValue> {
+    // Ids may contain '-', so only take it as the start of the offset if a clock value
+    // follows it.
+    let (spec, offset) = match s.rfind(['+', '-']) {
+        Some(i) if parse_clock_value(s[i + 1..].trim()).is_some() => {
+            (s[..i].trim_end(), parse_offset(&s[i..])?)
+        }
+        _ => (s, 0.0),
+    };
+
+    let (element, name) = match spec.rsplit_once('.') {
+        Some((id, name)) if !id.is_empty() => (Some(id.to_string()), name),
+        Some(_) => return None,
+        None => (None, spec),
+    };
+
+    Some(EventValue {
+        element,
+        kind: EventKind::from_name(name)?,
+        offset,
+    })
+}
+
+fn parse_offset(s: &str) -> Option<f64> {
+    if let Some(rest) = s.strip_prefix('-') {
+        parse_clock_value(rest.trim_start()).map(|v| -v)
+    } else if let Some(rest) = s.strip_prefix('+') {
+        parse_clock_value(rest.trim_start())
+    } else {
+        parse_clock_value(s)
+    }
+}
+
+fn parse_duration(s: &str) -> Option<Duration> {
+    match s.trim() {
+        "indefinite" => Some(Duration::Indefinite),
+        s => parse_clock_value(s)
+            .filter(|&d| d >= 0.0)
+            .map(Duration::Seconds),
+    }
+}
+
+fn parse_repeat_count(s: &str) -> Option<RepeatCount> {
+    match s.trim() {
+        "indefinite" => Some(RepeatCount::Indefinite),
+        s => s
+            .parse::<f64>()
+            .ok()
+            .filter(|&n| n.is_finite() && n > 0.0)
+            .map(RepeatCount::Count),
+    }
+}
+
+/// Parses a SMIL clock value, and returns it in seconds.
+///
+/// This can be a full clock value like `01:02:03.5`, a partial one like `02:03.5`, or a
+/// timecount value like `3.5s`, `100ms`, `2min`, or `1h`.  Timecount values without a
+/// unit are in seconds.  Values that are too big to represent, like a number of hours
+/// with hundreds of digits, are rejected.
+fn parse_clock_value(s: &str) -> Option<f64> {
+    let parts: Vec<&str> = s.split(':').collect();
+
+    let seconds = match parts[..] {
+        [hours, minutes, seconds] => {
+            let h = parse_digits(hours)?;
+            let m = parse_two_digits(minutes)?;
+            let s = parse_seconds(seconds)?;
+            Some(h * 3600.0 + m * 60.0 + s)
+        }
+
+        [minutes, seconds] => {
+            let m = parse_two_digits(minutes)?;
+            let s = parse_seconds(seconds)?;
+            Some(m * 60.0 + s)
+        }
+
+        [timecount] => {
+            if let Some(n) = timecount.strip_suffix("ms") {
+                parse_decimal(n).map(|n| n / 1000.0)
+            } else if let Some(n) = timecount.strip_suffix("min") {
+                parse_decimal(n).map(|n| n * 60.0)
+            } else if let Some(n) = timecount.strip_suffix('h') {
+                parse_decimal(n).map(|n| n * 3600.0)
+            } else if let Some(n) = timecount.strip_suffix('s') {
+                parse_decimal(n)
+            } else {
+                parse_decimal(timecount)
+            }
+        }
+
+        _ => None,
+    };
+
+    seconds.filter(|s| s.is_finite())
+}
+
+fn parse_digits(s: &str) -> Option<f64> {
+    if !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Minutes and seconds in clock values have two digits, and are less than 60.
+fn parse_two_digits(s: &str) -> Option<f64> {
+    parse_digits(s).filter(|&v| s.len() == 2 && v < 60.0)
+}
+
+fn parse_seconds(s: &str) -> Option<f64> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    parse_two_digits(whole)?;
+
+    if !fraction.is_empty() {
+        parse_digits(fraction)?;
+    }
+
+    s.parse().ok()
+}
+
+/// Parses `digits ("." digits)?`, without signs or exponents.
+fn parse_decimal(s: &str) -> Option<f64> {
+    let valid = match s.split_once('.') {
+        Some((whole, fraction)) => {
+            (whole.is_empty() || parse_digits(whole).is_some()) && parse_digits(fraction).is_some()
+        }
+        None => parse_digits(s).is_some(),
+    };
+
+    if valid {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_clock_values() {
+        assert_eq!(parse_clock_value("02:30:03"), Some(9003.0));
+        assert_eq!(parse_clock_value("50:00:10.25"), Some(180010.25));
+        assert_eq!(parse_clock_value("02:33"), Some(153.0));
+        assert_eq!(parse_clock_value("00:10.5"), Some(10.5));
+        assert_eq!(parse_clock_value("1.5h"), Some(5400.0));
+        assert_eq!(parse_clock_value("45min"), Some(2700.0));
+        assert_eq!(parse_clock_value("30s"), Some(30.0));
+        assert_eq!(parse_clock_value("5ms"), Some(0.005));
+        assert_eq!(parse_clock_value("12.467"), Some(12.467));
+        assert_eq!(parse_clock_value(".5s"), Some(0.5));
+
+        assert_eq!(parse_clock_value(""), None);
+        assert_eq!(parse_clock_value("s"), None);
+        assert_eq!(parse_clock_value("1:2"), None);
+        assert_eq!(parse_clock_value("00:60"), None);
+        assert_eq!(parse_clock_value("1e3s"), None);
+        assert_eq!(parse_clock_value("-1s"), None);
+        assert_eq!(parse_clock_value("click"), None);
+
+        assert_eq!(
+            parse_clock_value("100000000000000000000s"),
+            Some(100000000000000000000.0)
+        );
+        assert_eq!(parse_clock_value(&format!("{}h", "9".repeat(400))), None);
+    }
+
+    #[test]
+    fn splits_numbers_from_text() {
+        assert_eq!(
+            split_numbers("M 10,-2.5e1L.5 3"),
+            (vec!["M ", ",", "L", " ", ""], vec![10.0, -25.0, 0.5, 3.0])
+        );
+        assert_eq!(split_numbers("12px"), (vec!["", "px"], vec![12.0]));
+        assert_eq!(split_numbers("1em"), (vec!["", "em"], vec![1.0]));
+        assert_eq!(split_numbers("#102030"), (vec!["#102030"], vec![]));
+        assert_eq!(split_numbers("url(#a1)"), (vec!["url(#a1)"], vec![]));
+    }
+
+    #[test]
+    fn interpolates_values_with_the_same_shape() {
+        let frames = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let f = frames(&["0px", "100px"]);
+        assert_eq!(interpolated_value(&f, None, 0.25), "25px");
+        assert_eq!(interpolated_value(&f, None, 1.0), "100px");
+
+        let f = frames(&["rotate(0 5 5)", "rotate(90 5 5)", "rotate(0 5 5)"]);
+        assert_eq!(interpolated_value(&f, None, 0.25), "rotate(45 5 5)");
+        assert_eq!(interpolated_value(&f, None, 0.75), "rotate(45 5 5)");
+
+        let f = frames(&["0", "10", "20"]);
+        assert_eq!(interpolated_value(&f, Some(&[0.0, 0.8, 1.0]), 0.4), "5");
+
+        let f = frames(&["red", "blue"]);
+        assert_eq!(interpolated_value(&f, None, 0.4), "red");
+        assert_eq!(interpolated_value(&f, None, 0.6), "blue");
+
+        let f = frames(&["a", "b", "c", "d"]);
+        assert_eq!(discrete_value(&f, None, 0.3), "b");
+        assert_eq!(discrete_value(&f, None, 1.0), "d");
+        assert_eq!(discrete_value(&f, Some(&[0.0, 0.1, 0.2, 0.9]), 0.5), "c");
+    }
+
+    #[test]
+    fn writes_alpha_percentages_as_numbers() {
+        assert_eq!(alpha_value_as_number("50%"), "0.5");
+        assert_eq!(alpha_value_as_number(" 100% "), "1");
+        assert_eq!(alpha_value_as_number("0.25"), "0.25");
+        assert_eq!(alpha_value_as_number("inherit"), "inherit");
+
+        let frames = vec![alpha_value_as_number("0"), alpha_value_as_number("50%")];
+        assert_eq!(interpolated_value(&frames, None, 0.5), "0.25");
+    }
+
+    #[test]
+    fn computes_progress() {
+        let t = timing(&[1.0], Some(Duration::Seconds(2.0)));
+        assert_eq!(t.progress(0.5), None);
+        assert_eq!(t.progress(1.0), Some(0.0));
+        assert_eq!(t.progress(2.5), Some(0.75));
+        assert_eq!(t.progress(3.0), None);
+
+        let t = Timing {
+            freeze: true,
+            repeat_count: Some(RepeatCount::Count(1.5)),
+            ..timing(&[0.0], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.progress(2.5), Some(0.25));
+        assert_eq!(t.progress(10.0), Some(0.5));
+
+        let t = Timing {
+            freeze: true,
+            ..timing(&[0.0], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.progress(5.0), Some(1.0));
+
+        let t = Timing {
+            repeat_count: Some(RepeatCount::Indefinite),
+            ..timing(&[0.0], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.progress(101.0), Some(0.5));
+
+        // <set begin="4s"/> without a dur stays on
+        let t = timing(&[4.0], None);
+        assert_eq!(t.progress(3.0), None);
+        assert_eq!(t.progress(100.0), Some(0.0));
+    }
+
+    #[test]
+    fn parses_offset_lists() {
+        assert_eq!(parse_offsets("1s; -0.5s;click; + 2s"), vec![1.0, -0.5, 2.0]);
+        assert_eq!(parse_offsets("other.end"), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn parses_event_values() {
+        let event = |element: Option<&str>, kind, offset| EventValue {
+            element: element.map(String::from),
+            kind,
+            offset,
+        };
+
+        assert_eq!(
+            parse_event_values("click; 1s; my-button.mouseover + 0.5s;foo.mouseout-1s"),
+            vec![
+                event(None, EventKind::Click, 0.0),
+                event(Some("my-button"), EventKind::MouseOver, 0.5),
+                event(Some("foo"), EventKind::MouseOut, -1.0),
+            ]
+        );
+
+        assert_eq!(parse_event_value("other.end"), None);
+        assert_eq!(parse_event_value("keypress"), None);
+        assert_eq!(parse_event_value(".click"), None);
+        assert_eq!(parse_event_value("click+"), None);
+    }
+
+    fn timing(begin: &[f64], dur: Option<Duration>) -> Timing {
+        Timing {
+            begin: begin.to_vec(),
+            begin_events: Vec::new(),
+            end: Vec::new(),
+            dur,
+            repeat_count: None,
+            repeat_dur: None,
+            freeze: false,
+        }
+    }
+
+    #[test]
+    fn computes_active_end() {
+        let t = timing(&[1.0], Some(Duration::Seconds(2.0)));
+        assert_eq!(t.end_of_first_iteration(), Some(3.0));
+        assert!(!t.repeats_indefinitely());
+
+        let t = Timing {
+            repeat_count: Some(RepeatCount::Count(2.5)),
+            ..timing(&[0.0], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.end_of_first_iteration(), Some(5.0));
+
+        let t = Timing {
+            repeat_count: Some(RepeatCount::Count(10.0)),
+            repeat_dur: Some(Duration::Seconds(3.0)),
+            ..timing(&[0.0], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.end_of_first_iteration(), Some(3.0));
+
+        let t = Timing {
+            repeat_count: Some(RepeatCount::Indefinite),
+            ..timing(&[0.5], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.end_of_first_iteration(), Some(2.5));
+        assert!(t.repeats_indefinitely());
+
+        let t = Timing {
+            end: vec![1.5],
+            ..timing(&[0.0], Some(Duration::Seconds(2.0)))
+        };
+        assert_eq!(t.end_of_first_iteration(), Some(1.5));
+
+        // <set begin="4s"/> without a dur
+        let t = timing(&[4.0], None);
+        assert_eq!(t.end_of_first_iteration(), Some(4.0));
+
+        // Only event-based begin values
+        let t = timing(&[], Some(Duration::Seconds(2.0)));
+        assert_eq!(t.end_of_first_iteration(), None);
+    }
+}
@@ -9,30 +9,50 @@ use std::fmt;
 // Here we only re-export stuff in the public API.
 pub use crate::{
     accept_language::{AcceptLanguage, Language},
-    drawing_ctx::Viewport,
-    error::{DefsLookupErrorKind, ImplementationLimit, LoadingError},
+    animation::EventKind,
+    contrast::TextContrast,
+    cost::RenderCost,
+    cvd::ColorVisionDeficiency,
+    drawing_ctx::{RenderingQuality, SpotColor, Viewport},
+    error::{DefsLookupErrorKind, ImplementationLimit, LoadingError, ReferenceChain},
+    geometry::BoundingBox,
+    io::ResourceLoader,
     length::{LengthUnit, RsvgLength as Length},
+    log::{BrokenPaintReference, DebugConfig, LoadWarning, Location, LogLevel, LogSink},
 };
 
+#[cfg(feature = "render-observers")]
+pub use crate::observer::{ObservedElement, RenderObserver};
+
 // Don't merge these in the "pub use" above!  They are not part of the public API!
 use crate::{
     accept_language::{LanguageTags, UserLanguage},
-    css::{Origin, Stylesheet},
-    document::{Document, LoadOptions, NodeId},
+    animation, cost,
+    css::{self, Origin, Stylesheet},
+    document::{supported_image_formats, Document, Features, LoadOptions, NodeId},
     dpi::Dpi,
-    drawing_ctx::SvgNesting,
+    drawing_ctx::{set_source_color_on_cairo, SvgNesting},
     error::InternalRenderingError,
     length::NormalizeParams,
-    node::{CascadedValues, Node},
+    limits::LoadLimits,
+    node::{CascadedValues, Node, NodeBorrow},
+    rect::{IRect, Rect},
+    render_cache::RenderCache,
     rsvg_log,
     session::Session,
+    surface_utils::shared_surface::{SharedImageSurface, SurfaceType},
+    transform::{Transform, ValidTransform},
     url_resolver::UrlResolver,
+    xml::{write_document, write_element_contents, XmlPushLoader},
 };
 
+use markup5ever::{LocalName, Namespace, Prefix, QualName};
 use url::Url;
 
+use std::io::{Read, Write};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use gio::prelude::*; // Re-exposes glib's prelude as well
 use gio::Cancellable;
@@ -57,6 +77,9 @@ pub enum RenderingError {
 
     /// Not enough memory was available for rendering.
     OutOfMemory(String),
+
+    /// Tried to modify an attribute in a namespace that librsvg interprets.
+    InvalidNamespace(String),
 }
 
 impl std::error::Error for RenderingError {}
@@ -94,6 +117,9 @@ impl fmt::Display for RenderingError {
             RenderingError::IdNotFound => write!(f, "element id not found"),
             RenderingError::InvalidId(ref s) => write!(f, "invalid id: {s:?}"),
             RenderingError::OutOfMemory(ref s) => write!(f, "out of memory: {s}"),
+            RenderingError::InvalidNamespace(ref s) => {
+                write!(f, "cannot set attributes in namespace {s:?}")
+            }
         }
     }
 }
@@ -109,6 +135,11 @@ impl fmt::Display for RenderingError {
 pub struct Loader {
     unlimited_size: bool,
     keep_image_data: bool,
+    limits: LoadLimits,
+    strict: bool,
+    features: Features,
+    external_resources: bool,
+    resource_loader: Option<Arc<dyn ResourceLoader>>,
     session: Session,
 }
 
@@ -123,6 +154,11 @@ impl Loader {
     /// surfaces that support including image data in compressed
     /// formats, like PDF.
     ///
+    /// * The [maximum number of elements](#method.with_max_elements) defaults to one
+    ///   million.  There are no other limits by default on the size of documents and the
+    ///   images they reference, besides the ones in libxml2 which are turned off with
+    ///   `unlimited_size`.
+    ///
     /// # Example:
     ///
     /// ```
@@ -137,6 +173,11 @@ impl Loader {
         Self {
             unlimited_size: false,
             keep_image_data: false,
+            limits: LoadLimits::default(),
+            strict: false,
+            features: Features::default(),
+            external_resources: true,
+            resource_loader: None,
             session: Session::default(),
         }
     }
@@ -150,6 +191,11 @@ impl Loader {
         Self {
             unlimited_size: false,
             keep_image_data: false,
+            limits: LoadLimits::default(),
+            strict: false,
+            features: Features::default(),
+            external_resources: true,
+            resource_loader: None,
             session,
         }
     }
@@ -215,6 +261,250 @@ impl Loader {
         self
     }
 
+    /// Sets the maximum number of elements that a document can have, not counting the
+    /// toplevel one.
+    ///
+    /// Documents with more elements than this fail to load with
+    /// [`ImplementationLimit::TooManyLoadedElements`].  The default is one million.
+    ///
+    /// # Example:
+    /// ```
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .with_max_elements(10_000)
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    /// ```
+    pub fn with_max_elements(mut self, max: usize) -> Self {
+        self.limits.max_elements = max;
+        self
+    }
+
+    /// Sets the maximum number of pixels in each raster image referenced by the document.
+    ///
+    /// The dimensions of images are checked before decoding them, so a small file that
+    /// declares huge dimensions does not get to allocate memory for them.  Images that
+    /// exceed the limit fail to load with [`ImplementationLimit::TooManyImagePixels`]; they
+    /// are not rendered, but the rest of the document still is.  There is no limit by
+    /// default.
+    pub fn with_max_image_pixels(mut self, max: u64) -> Self {
+        self.limits.max_image_pixels = Some(max);
+        self
+    }
+
+    /// Sets the maximum level of nesting of XML elements.
+    ///
+    /// The toplevel element has a depth of 1.  Documents with elements nested deeper than
+    /// this fail to load with [`ImplementationLimit::TooDeeplyNested`].  There is no limit by
+    /// default, besides the one in libxml2 which is turned off with
+    /// [`with_unlimited_size`](#method.with_unlimited_size).
+    pub fn with_max_nesting_depth(mut self, max: usize) -> Self {
+        self.limits.max_nesting_depth = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of bytes of XML to read for a document.
+    ///
+    /// This counts the bytes after decompressing `.svgz` files, so it also protects against
+    /// compressed files that expand to huge sizes.  Files included with `<xi:include>`
+    /// count towards the limit.  Documents that exceed it fail to load with
+    /// [`ImplementationLimit::DocumentTooLarge`].  There is no limit by default.
+    pub fn with_max_decompressed_size(mut self, max: usize) -> Self {
+        self.limits.max_decompressed_size = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of times that elements can be referenced while rendering.
+    ///
+    /// Each `<use>` element, and each reference to a pattern, gradient, clipping path,
+    /// mask, marker, or filter counts towards this limit every time it is drawn.  Renderings
+    /// that exceed it fail with [`ImplementationLimit::TooManyReferencedElements`].  The
+    /// default is half a million.
+    pub fn with_max_referenced_elements(mut self, max: usize) -> Self {
+        self.limits.max_referenced_elements = max;
+        self
+    }
+
+    /// Sets the maximum length of each chain of references between elements.
+    ///
+    /// For example, with a maximum of 10, a `<use>` element can reference a group with
+    /// another `<use>` element, and so on, up to 10 levels deep.  See [`ReferenceChain`]
+    /// for the kinds of chains that are limited.  Renderings that exceed the limit fail
+    /// with [`ImplementationLimit::ReferenceChainTooLong`], and the ids of the elements in
+    /// the chain get logged as a warning.  The default is 256.
+    ///
+    /// # Example:
+    /// ```
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .with_max_reference_chain(16)
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    /// ```
+    pub fn with_max_reference_chain(mut self, max: usize) -> Self {
+        self.limits.max_reference_chain = max;
+        self
+    }
+
+    /// Makes loading fail on invalid values instead of ignoring them.
+    ///
+    /// Per the SVG spec, librsvg ignores attributes with invalid values and renders the
+    /// rest of the document.  In strict mode, these make loading fail with
+    /// [`LoadingError::Invalid`] instead:
+    ///
+    /// * Invalid values in attributes, including lengths and presentation attributes like
+    ///   `fill` or `stroke-width`.
+    ///
+    /// * Errors in path data, in the `d` attribute of `<path>`.
+    ///
+    /// * Malformed transform lists, in the `transform` attribute.
+    ///
+    /// * Invalid declarations in `style` attributes and stylesheets, including unknown
+    ///   properties.
+    ///
+    /// * References to elements that don't exist, like `fill="url(#missing)"`.
+    ///
+    /// The [`LoadWarning`] in the error has the position of the problem in the document,
+    /// except for problems in stylesheets loaded from separate files.  CSS rules with
+    /// unsupported selectors are still only reported in [`SvgHandle::warnings`].  This is
+    /// useful to validate files, for example in a CI pipeline for a set of icons.  The
+    /// default is `false`.
+    ///
+    /// # Example:
+    /// ```
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .strict(true)
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    /// ```
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Controls whether filters get applied when rendering.
+    ///
+    /// With `false`, the `filter` property is ignored and elements are rendered as if
+    /// they did not have a filter.  Filters can be expensive, so thumbnailers and
+    /// previewers may want to turn them off.  The default is `true`.
+    pub fn enable_filters(mut self, enable: bool) -> Self {
+        self.features.filters = enable;
+        self
+    }
+
+    /// Controls whether `<image>` elements can load external files.
+    ///
+    /// With `false`, only images embedded in the document with `data:` URLs get
+    /// rendered, and no files are read for `<image>` elements.  The default is `true`.
+    pub fn enable_external_images(mut self, enable: bool) -> Self {
+        self.features.external_images = enable;
+        self
+    }
+
+    /// Controls whether the document can load anything outside of itself.
+    ///
+    /// With `false`, every reference to another file or URL is refused, as if the file
+    /// did not exist: images, stylesheets from `xml-stylesheet` instructions and
+    /// `@import` rules, XInclude, and references to elements in other documents like
+    /// `<use href="other.svg#foo"/>`.  Only `data:` URLs can be used.  This is the
+    /// safest option for rendering untrusted documents.  The default is `true`.
+    ///
+    /// This also overrides the [`ResourceLoader`] given with
+    /// [`with_resource_loader`](#method.with_resource_loader), which is not called.
+    pub fn enable_external_resources(mut self, enable: bool) -> Self {
+        self.external_resources = enable;
+        self
+    }
+
+    /// Controls whether text gets rendered.
+    ///
+    /// With `false`, `<text>` elements are skipped entirely, which avoids the cost of
+    /// text layout and font loading.  The default is `true`.
+    ///
+    /// # Example:
+    /// ```
+    /// // A fast render for a thumbnail
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .enable_filters(false)
+    ///     .enable_external_images(false)
+    ///     .enable_text(false)
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    /// ```
+    pub fn enable_text(mut self, enable: bool) -> Self {
+        self.features.text = enable;
+        self
+    }
+
+    /// Sends the messages that librsvg logs to `sink`.
+    ///
+    /// Librsvg logs messages about things it ignores in documents, like invalid attribute
+    /// values or references to missing elements, and optionally traces of what it does
+    /// while rendering.  Normally these are printed to stdout if the `RSVG_LOG` environment
+    /// variable is set.  With a sink, they are always passed to it instead, so that
+    /// applications can route them to their own logging framework.  The sink gets used for
+    /// both loading and rendering the resulting [`SvgHandle`].
+    ///
+    /// The `sink` can be a closure that takes a [`LogLevel`] and the message, or a type that
+    /// implements [`LogSink`] if it needs to filter messages by level.
+    ///
+    /// # Example:
+    /// ```
+    /// use rsvg::LogLevel;
+    ///
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .with_log_sink(|level: LogLevel, message: &str| {
+    ///         if level == LogLevel::Warning {
+    ///             eprintln!("librsvg: {message}");
+    ///         }
+    ///     })
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    /// ```
+    pub fn with_log_sink<S: LogSink + 'static>(mut self, sink: S) -> Self {
+        self.session = self.session.with_log_sink(Arc::new(sink));
+        self
+    }
+
+    /// Sets the debugging options for loading, and for rendering the resulting
+    /// [`SvgHandle`].
+    ///
+    /// Without this, the options come from the `RSVG_LOG` environment variable; see
+    /// [`DebugConfig::from_env`].  A sink set with [`with_log_sink`](#method.with_log_sink)
+    /// still receives the messages, filtered by the configuration's categories.
+    pub fn with_debug_config(mut self, config: DebugConfig) -> Self {
+        self.session = self.session.with_debug_config(config);
+        self
+    }
+
+    /// Uses `loader` to supply the contents of files referenced by the document.
+    ///
+    /// Normally librsvg reads referenced images, stylesheets, and XInclude files itself,
+    /// subject to the rules in "Security and locations of referenced files" in the [crate
+    /// documentation](crate#security-and-locations-of-referenced-files).  With a loader,
+    /// applications can serve those files from elsewhere, like their own resource bundles
+    /// or a network cache, or deny access to them.  The loader is also used for files that
+    /// are referenced while rendering the resulting [`SvgHandle`].
+    ///
+    /// The `loader` can be a closure that takes a URL and returns its contents; see
+    /// [`ResourceLoader::load`] for details.
+    ///
+    /// # Example:
+    /// ```
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .with_resource_loader(|url: &str| -> Option<Result<Vec<u8>, glib::Error>> {
+    ///         if url.ends_with(".css") {
+    ///             Some(Ok(b"rect { fill: lime; }".to_vec()))
+    ///         } else {
+    ///             None
+    ///         }
+    ///     })
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    /// ```
+    pub fn with_resource_loader<L: ResourceLoader + 'static>(mut self, loader: L) -> Self {
+        self.resource_loader = Some(Arc::new(loader));
+        self
+    }
+
     /// Reads an SVG document from `path`.
     ///
     /// # Example:
@@ -287,20 +577,182 @@ impl Loader {
             None
         };
 
-        let load_options = LoadOptions::new(UrlResolver::new(base_url))
-            .with_unlimited_size(self.unlimited_size)
-            .keep_image_data(self.keep_image_data);
-
-        Ok(SvgHandle {
-            document: Document::load_from_stream(
-                self.session.clone(),
-                Arc::new(load_options),
-                stream.as_ref(),
-                cancellable.map(|c| c.as_ref()),
-            )?,
-            session: self.session,
+        self.read_stream_with_base_url(stream.as_ref(), base_url, cancellable.map(|c| c.as_ref()))
+    }
+
+    /// Reads an SVG document from anything that implements [`std::io::Read`].
+    ///
+    /// This is useful for loading from a file handle, a network stream, or a decompressor
+    /// without constructing a GIO stream first.  Compressed `.svgz` data is detected and
+    /// decompressed automatically, just like in the other loading functions.
+    ///
+    /// The `base_url`, if it is not `None`, is used as the
+    /// [base URL][crate#the-base-file-and-resolving-references-to-external-files] for resolving
+    /// references to external files.  It must be an absolute URL like
+    /// `file:///home/user/image.svg`, or loading fails with [`LoadingError::BadUrl`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let file = std::fs::File::open("example.svg").unwrap();
+    ///
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .read(file, None)
+    ///     .unwrap();
+    /// ```
+    pub fn read<R: Read + Send + 'static>(
+        self,
+        reader: R,
+        base_url: Option<&str>,
+    ) -> Result<SvgHandle, LoadingError> {
+        let base_url = base_url
+            .map(|url| Url::parse(url).map_err(|_| LoadingError::BadUrl))
+            .transpose()?;
+
+        let stream = gio::ReadInputStream::new(reader);
+
+        self.read_stream_with_base_url(stream.upcast_ref(), base_url, None)
+    }
+
+    /// Reads an SVG document directly from a byte slice.
+    ///
+    /// Unlike wrapping the data in a [`gio::MemoryInputStream`] and calling
+    /// [`read_stream`], this parses the bytes in place without copying them.  Since
+    /// [`glib::Bytes`] dereferences to `&[u8]`, you can pass one of those as well.
+    /// Compressed `.svgz` data is detected and decompressed automatically.
+    ///
+    /// The `base_url` works as in [`read`].
+    ///
+    /// [`read_stream`]: #method.read_stream
+    /// [`read`]: #method.read
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let data = std::fs::read("example.svg").unwrap();
+    ///
+    /// let svg_handle = rsvg::Loader::new()
+    ///     .read_from_bytes(&data, None)
+    ///     .unwrap();
+    /// ```
+    pub fn read_from_bytes(
+        self,
+        bytes: &[u8],
+        base_url: Option<&str>,
+    ) -> Result<SvgHandle, LoadingError> {
+        let base_url = base_url
+            .map(|url| Url::parse(url).map_err(|_| LoadingError::BadUrl))
+            .transpose()?;
+
+        self.load_document(base_url, |session, load_options| {
+            Document::load_from_data(session, load_options, bytes)
+        })
+    }
+
+    fn read_stream_with_base_url(
+        self,
+        stream: &gio::InputStream,
+        base_url: Option<Url>,
+        cancellable: Option<&Cancellable>,
+    ) -> Result<SvgHandle, LoadingError> {
+        self.load_document(base_url, |session, load_options| {
+            Document::load_from_stream(session, load_options, stream, cancellable)
+        })
+    }
+
+    /// Starts loading an SVG document from data that will be supplied in chunks.
+    ///
+    /// This is for programs that get a document in pieces, for example from the network,
+    /// and want to parse it as the data arrives instead of keeping all of it in memory.
+    /// Feed the data to [`IncrementalLoader::write`], and call
+    /// [`IncrementalLoader::close`] at the end to get the [`SvgHandle`].  Errors in the XML
+    /// are reported by the first call to `write` that finds them.
+    ///
+    /// Compressed `.svgz` data is detected automatically, but it only gets decompressed
+    /// and parsed when the loader is closed.
+    ///
+    /// The `base_file` works as in [`read_stream`](#method.read_stream).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let data = std::fs::read("example.svg").unwrap();
+    ///
+    /// let mut loader = rsvg::Loader::new()
+    ///     .begin_incremental(None::<&gio::File>)
+    ///     .unwrap();
+    ///
+    /// for chunk in data.chunks(1024) {
+    ///     loader.write(chunk).unwrap();
+    /// }
+    ///
+    /// let svg_handle = loader.close().unwrap();
+    /// ```
+    pub fn begin_incremental<F: IsA<gio::File>>(
+        self,
+        base_file: Option<&F>,
+    ) -> Result<IncrementalLoader, LoadingError> {
+        let base_url = base_file.map(|f| url_from_file(f.as_ref())).transpose()?;
+
+        let (session, load_options) = self.prepare_loading(base_url);
+
+        Ok(IncrementalLoader {
+            loader: Document::load_incrementally(session.clone(), load_options),
+            session,
         })
     }
+
+    fn load_document<F>(self, base_url: Option<Url>, load: F) -> Result<SvgHandle, LoadingError>
+    where
+        F: FnOnce(Session, Arc<LoadOptions>) -> Result<Document, LoadingError>,
+    {
+        let (session, load_options) = self.prepare_loading(base_url);
+
+        let document = load(session.clone(), load_options)?;
+
+        Ok(SvgHandle::new(session, document))
+    }
+
+    fn prepare_loading(self, base_url: Option<Url>) -> (Session, Arc<LoadOptions>) {
+        let url_resolver = UrlResolver::new(base_url)
+            .with_resource_loader(self.resource_loader)
+            .with_external_resources(self.external_resources);
+
+        let load_options = LoadOptions::new(url_resolver)
+            .with_unlimited_size(self.unlimited_size)
+            .keep_image_data(self.keep_image_data)
+            .with_limits(self.limits)
+            .strict(self.strict)
+            .with_features(self.features);
+
+        (self.session.for_loading(), Arc::new(load_options))
+    }
+}
+
+/// Loads an SVG document from data that is supplied in chunks.
+///
+/// You can create this with [`Loader::begin_incremental`].
+pub struct IncrementalLoader {
+    session: Session,
+    loader: XmlPushLoader,
+}
+
+impl IncrementalLoader {
+    /// Parses the next chunk of the document.
+    ///
+    /// If the data so far is not a well-formed XML document, or it goes over one of the
+    /// loader's limits, this returns an error.  After that, the loader cannot be used
+    /// anymore, and [`IncrementalLoader::close`] will also return an error.
+    pub fn write(&mut self, buf: &[u8]) -> Result<(), LoadingError> {
+        self.loader.write(buf)
+    }
+
+    /// Finishes loading the document after all of its data has been written.
+    pub fn close(self) -> Result<SvgHandle, LoadingError> {
+        let document = self.loader.close()?;
+
+        Ok(SvgHandle::new(self.session, document))
+    }
 }
 
 fn url_from_file(file: &gio::File) -> Result<Url, LoadingError> {
@@ -314,6 +766,16 @@ fn url_from_file(file: &gio::File) -> Result<Url, LoadingError> {
 pub struct SvgHandle {
     session: Session,
     pub(crate) document: Document,
+
+    /// Stylesheet from [`SvgHandle::set_stylesheet`] or
+    /// [`SvgHandle::set_stylesheet_from_file`], kept around for [`SvgHandle::write_svg`].
+    stylesheet: Option<String>,
+
+    /// The parsed form of `stylesheet`, to run the cascade again in
+    /// [`SvgHandle::set_animation_time`].
+    user_stylesheet: Option<Stylesheet>,
+
+    warnings: Vec<LoadWarning>,
 }
 
 // Public API goes here
@@ -337,6 +799,128 @@ impl SvgHandle {
         }
     }
 
+    /// Returns the non-fatal problems that were found while loading the document.
+    ///
+    /// Per the SVG spec, librsvg ignores the parts of a document that it cannot use
+    /// and renders the rest.  This lists those parts, for example unknown or invalid
+    /// attribute values, invalid CSS declarations, and references to elements that do
+    /// not exist, along with their line and column in the file when they are known.
+    ///
+    /// ```
+    /// let handle = rsvg::Loader::new()
+    ///     .read_path("example.svg")
+    ///     .unwrap();
+    ///
+    /// for warning in handle.warnings() {
+    ///     eprintln!("example.svg:{warning}");
+    /// }
+    /// ```
+    pub fn warnings(&self) -> &[LoadWarning] {
+        &self.warnings
+    }
+
+    /// Returns the references to paint servers that could not be used while rendering.
+    ///
+    /// A `fill` or `stroke` like `url(#gradient) red` gets painted with its fallback
+    /// color when `#gradient` does not exist, is not a gradient or pattern, or has a
+    /// circular reference.  Since this is found out while rendering, this lists the
+    /// broken references from all the renders of this handle so far, each one once.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let handle = rsvg::Loader::new().read_path("example.svg")?;
+    ///
+    /// let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 100, 100)?;
+    /// let cr = cairo::Context::new(&surface)?;
+    /// rsvg::CairoRenderer::new(&handle).render_document(
+    ///     &cr,
+    ///     &cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0),
+    /// )?;
+    ///
+    /// for broken in handle.broken_paint_references() {
+    ///     eprintln!("example.svg: {broken}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn broken_paint_references(&self) -> Vec<BrokenPaintReference> {
+        self.session.broken_paint_references()
+    }
+
+    /// Returns the URLs of the external resources that the document references.
+    ///
+    /// This lists every file or URL that rendering the document may fetch: raster images
+    /// and SVG documents from `<image>` and `<feImage>`, external elements referenced by
+    /// `<use>` or by properties like `fill`, `clip-path`, `mask`, `marker`, and `filter`, plus
+    /// the stylesheets from `@import` rules and `xml-stylesheet` processing instructions, which
+    /// were already fetched while loading.  Librsvg does not fetch fonts; it uses the ones
+    /// installed in the system.
+    ///
+    /// URLs are absolute and listed once, in the order in which they appear in the document.
+    /// Only URLs that the loader's security rules would allow are included, and `data:`
+    /// URLs are left out since they do not need to be fetched.  Note that external SVG
+    /// documents may in turn reference other resources, which are not listed here.
+    ///
+    /// Embedders can use this to inspect and approve the resources before rendering.
+    pub fn external_references(&self) -> Vec<String> {
+        self.document.external_references()
+    }
+
+    /// Returns the font families that the document's text asks for.
+    ///
+    /// This looks at the computed `font-family` of every piece of text in the document,
+    /// and lists each family once, in the order in which they appear.  Generic families
+    /// like `sans-serif` are included.  Note that these are the families the document
+    /// requests; which fonts get used for rendering depends on the fonts installed in
+    /// the system, and the ones registered with [`SvgHandle::add_font_file`].
+    pub fn font_families(&self) -> Vec<String> {
+        self.current_document().font_families()
+    }
+
+    /// Returns the text of the document's `<title>`, if it has one.
+    ///
+    /// This is the first `<title>` child of the toplevel `<svg>` element.  Leading and
+    /// trailing whitespace is removed.
+    pub fn document_title(&self) -> Option<String> {
+        child_element_text(&self.document.root(), "title")
+    }
+
+    /// Returns the text of the document's `<desc>`, if it has one.
+    ///
+    /// This is the first `<desc>` child of the toplevel `<svg>` element.  Leading and
+    /// trailing whitespace is removed.
+    pub fn document_description(&self) -> Option<String> {
+        child_element_text(&self.document.root(), "desc")
+    }
+
+    /// Returns the contents of the document's `<metadata>` element as XML, if it has one.
+    ///
+    /// This is the first `<metadata>` child of the toplevel `<svg>` element, which usually
+    /// contains RDF with Dublin Core or Creative Commons licensing information.  The
+    /// element's children are serialized as an XML fragment, with leading and trailing
+    /// whitespace removed; each top-level element declares the namespaces it uses.
+    pub fn document_metadata(&self) -> Option<String> {
+        let metadata = svg_child_element(&self.document.root(), "metadata")?;
+
+        let mut output = Vec::new();
+        write_element_contents(&metadata, &mut output).ok()?;
+
+        String::from_utf8(output).ok().map(|s| s.trim().to_string())
+    }
+
+    /// Returns the text of the `<title>` of the element whose `id` attribute is `id`.
+    ///
+    /// Like with [`element_by_id`], this takes the plain `id`, without a leading `#`
+    /// character.  Returns `None` if there is no such element, or if it has no `<title>`
+    /// child.
+    ///
+    /// [`element_by_id`]: #method.element_by_id
+    pub fn element_title(&self, id: &str) -> Option<String> {
+        self.document
+            .lookup_internal_node(id)
+            .and_then(|node| child_element_text(&node, "title"))
+    }
+
     /// Sets a CSS stylesheet to use for an SVG document.
     ///
     /// During the CSS cascade, the specified stylesheet will be used
@@ -352,26 +936,661 @@ impl SvgHandle {
             Origin::User,
             self.session.clone(),
         )?;
-        self.document.cascade(&[stylesheet], &self.session);
+        self.document
+            .cascade(std::slice::from_ref(&stylesheet), &self.session);
+        self.stylesheet = Some(css.to_string());
+        self.user_stylesheet = Some(stylesheet);
         Ok(())
     }
-}
 
-// Private methods go here
-impl SvgHandle {
-    fn get_node_id_or_root(&self, id: Option<&str>) -> Result<Option<NodeId>, RenderingError> {
-        match id {
-            None => Ok(None),
-            Some(s) => Ok(Some(self.get_node_id(s)?)),
-        }
+    /// Sets a CSS stylesheet to use for an SVG document, loading it from a file.
+    ///
+    /// This is like [`SvgHandle::set_stylesheet`], but `@import` rules and relative
+    /// references in `url()` values are resolved with respect to the stylesheet's own
+    /// location.  The files that they refer to are subject to the same restrictions as
+    /// the stylesheet itself; see "Security and locations of referenced files" in the
+    /// [crate documentation](index.html#security-and-locations-of-referenced-files).
+    ///
+    /// The [`ResourceLoader`] given to the [`Loader`], if any, is used to fetch the
+    /// stylesheet.
+    pub fn set_stylesheet_from_file<F: IsA<gio::File>>(
+        &mut self,
+        file: &F,
+    ) -> Result<(), LoadingError> {
+        let url = url_from_file(file.as_ref())?;
+        let url_resolver = UrlResolver::new(Some(url.clone()))
+            .with_resource_loader(self.document.url_resolver().resource_loader.clone());
+        let aurl = url_resolver
+            .resolve_href(url.as_str())
+            .map_err(|_| LoadingError::BadUrl)?;
+
+        let stylesheet =
+            Stylesheet::from_href(&aurl, &url_resolver, Origin::User, self.session.clone())?;
+        self.document
+            .cascade(std::slice::from_ref(&stylesheet), &self.session);
+        self.stylesheet = Some(format!("@import url(\"{url}\");"));
+        self.user_stylesheet = Some(stylesheet);
+        Ok(())
     }
 
-    fn get_node_id(&self, id: &str) -> Result<NodeId, RenderingError> {
-        let node_id = NodeId::parse(id).map_err(|_| RenderingError::InvalidId(id.to_string()))?;
+    /// Registers a TrueType or OpenType font file to use for the document's text.
+    ///
+    /// Normally librsvg uses the fonts installed in the system.  With this function, a
+    /// program can supply fonts that are not installed, for example from a sandboxed
+    /// application or a test suite that needs predictable rendering.  The families in the
+    /// font file become available to the document's `font-family` properties, in addition
+    /// to the system's fonts.
+    ///
+    /// The file is read when it is registered; it must remain in place while the
+    /// document is being rendered.
+    ///
+    /// This is only supported on platforms where Pango uses Fontconfig; elsewhere it
+    /// returns [`LoadingError::Other`].  Returns [`LoadingError::Io`] if the file cannot
+    /// be read or is not a font.
+    pub fn add_font_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), LoadingError> {
+        self.document.add_font_file(path.as_ref())
+    }
 
-        // The public APIs to get geometries of individual elements, or to render
-        // them, should only allow referencing elements within the main handle's
-        // SVG file; that is, only plain "#foo" fragment IDs are allowed here.
+    /// Registers a font from the contents of a TrueType or OpenType file.
+    ///
+    /// This is like [`SvgHandle::add_font_file`], but for fonts that are already in memory.
+    pub fn add_font_data(&mut self, data: &[u8]) -> Result<(), LoadingError> {
+        self.document.add_font_data(data)
+    }
+
+    /// Sets the Cairo font options to use for the document's text.
+    ///
+    /// By default librsvg turns off font hinting, so that text looks the same at any
+    /// scale, and uses Cairo's defaults for everything else.  The options that are set
+    /// in `options`, like the antialiasing mode, hint style, hint metrics, or subpixel
+    /// order, override those choices; the ones that are left at their default values
+    /// don't.  Pass `None` to go back to librsvg's defaults.
+    ///
+    /// The options are used when laying out text for rendering and for measuring
+    /// geometries, in every [`CairoRenderer`] for this handle.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), rsvg::LoadingError> {
+    /// let mut handle = rsvg::Loader::new().read_path("example.svg")?;
+    ///
+    /// let mut options = cairo::FontOptions::new().unwrap();
+    /// options.set_antialias(cairo::Antialias::Subpixel);
+    /// options.set_subpixel_order(cairo::SubpixelOrder::Rgb);
+    /// options.set_hint_style(cairo::HintStyle::Slight);
+    ///
+    /// handle.set_font_options(Some(&options));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_font_options(&mut self, options: Option<&cairo::FontOptions>) {
+        self.document.set_font_options(options);
+    }
+
+    /// Writes the loaded document back out as SVG.
+    ///
+    /// The output is a well-formed XML document with the elements, attributes, and text
+    /// that librsvg parsed.  Anything that librsvg discards while loading is not written
+    /// out; this includes comments, processing instructions, entity declarations, and
+    /// `<xi:include>` elements, which get replaced with the content they include.
+    ///
+    /// If a stylesheet was set with [`SvgHandle::set_stylesheet`], it is written as a
+    /// `<style>` element at the start of the root element.  One set with
+    /// [`SvgHandle::set_stylesheet_from_file`] is written as an `@import` of its URL.
+    /// Note that in the written document it will have the same priority as the document's
+    /// own stylesheets, instead of the "User" origin it had here.
+    ///
+    /// This is useful to pass SVG documents through librsvg's parser before handing them
+    /// to other programs.
+    pub fn write_svg<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        write_document(
+            &self.document.root(),
+            self.stylesheet.as_deref(),
+            &mut writer,
+        )
+    }
+
+    /// Marks an element as static, so that its rendering gets cached between renders.
+    ///
+    /// Interactive viewers that re-render a document many times, while only changing a few
+    /// elements, can mark the elements that do not change as static.  The first time such
+    /// an element gets rendered, librsvg renders it and its children into an offscreen
+    /// image and keeps it; later renders just composite that image, as long as they use the
+    /// same transformation, viewport, DPI, and [`RenderingQuality`].
+    ///
+    /// Pass `false` in `is_static` to unmark the element and drop its cached image.
+    /// [`set_stylesheet`](#method.set_stylesheet) drops all cached images, since they may
+    /// no longer match the document's styles.
+    ///
+    /// Cached images are only used when rendering the whole document onto image surfaces.
+    /// Rendering individual elements, or rendering to vector surfaces like PDF, draws static
+    /// elements as usual.
+    ///
+    /// The `id` must be a plain fragment identifier like `#foo`, as for
+    /// [`has_element_with_id`](#method.has_element_with_id).
+    pub fn set_element_static(&mut self, id: &str, is_static: bool) -> Result<(), RenderingError> {
+        let node_id = self.get_node_id(id)?;
+        let node = self.lookup_node(&node_id)?;
+
+        self.document.set_static(&node, is_static);
+        Ok(())
+    }
+
+    /// Renders a gradient or pattern into a new image surface of `width` by `height`
+    /// pixels.
+    ///
+    /// The paint server is drawn as if it were the `fill` of a rectangle that covers the
+    /// whole surface, so gradients with `objectBoundingBox` units stretch across it.  This
+    /// is useful to show swatches of the document's paint servers in an editor.
+    ///
+    /// The `id` must be a plain fragment identifier like `#foo`, as for
+    /// [`has_element_with_id`](#method.has_element_with_id).  Returns
+    /// [`RenderingError::InvalidId`] if the element is not a `<linearGradient>`,
+    /// `<radialGradient>`, or `<pattern>`.
+    pub fn render_paint_server(
+        &self,
+        id: &str,
+        width: i32,
+        height: i32,
+    ) -> Result<cairo::ImageSurface, RenderingError> {
+        let node_id = self.get_node_id(id)?;
+        let node = self.lookup_node(&node_id)?;
+
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+        let cr = cairo::Context::new(&surface)?;
+
+        self.current_document().render_paint_server(
+            &self.session,
+            &cr,
+            &node,
+            &node_id,
+            f64::from(width),
+            f64::from(height),
+            &UserLanguage::new(&Language::FromEnvironment, &self.session),
+            Dpi::new(DEFAULT_DPI_X, DEFAULT_DPI_Y),
+            false,
+        )?;
+
+        drop(cr);
+        Ok(surface)
+    }
+
+    /// Sets an attribute in a namespace that librsvg does not interpret, like
+    /// `inkscape:label`, on the element with the specified `id`.
+    ///
+    /// This works like `setAttributeNS()` in the DOM: `name` can be a qualified name with
+    /// a prefix, which gets used when the document is written out with
+    /// [`write_svg`](#method.write_svg) and the namespace is not declared yet.  An
+    /// existing attribute with the same namespace and local name gets replaced.  Since
+    /// these attributes do not affect rendering, the document does not need to be
+    /// reloaded.
+    ///
+    /// The `id` must be a plain fragment identifier like `#foo`, as for
+    /// [`has_element_with_id`](#method.has_element_with_id).
+    ///
+    /// Returns [`RenderingError::InvalidNamespace`] if `namespace` is empty or one of the
+    /// SVG, XLink, or XML namespaces, since changing those attributes would require
+    /// reprocessing the document.
+    pub fn set_foreign_attribute(
+        &mut self,
+        id: &str,
+        namespace: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<(), RenderingError> {
+        if !is_foreign_namespace(namespace) {
+            return Err(RenderingError::InvalidNamespace(namespace.to_string()));
+        }
+
+        let node_id = self.get_node_id(id)?;
+        let mut node = self.lookup_node(&node_id)?;
+
+        let (prefix, local) = match name.split_once(':') {
+            Some((prefix, local)) => (Some(Prefix::from(prefix)), local),
+            None => (None, name),
+        };
+
+        let name = QualName::new(prefix, Namespace::from(namespace), LocalName::from(local));
+
+        node.borrow_element_mut()
+            .set_unrendered_attribute(name, value);
+
+        Ok(())
+    }
+
+    /// Returns the toplevel element of the SVG document.
+    ///
+    /// This can be used to inspect the parsed tree of elements, for example to find
+    /// references to external resources, without having to parse the SVG again.
+    pub fn root_element(&self) -> SvgElement {
+        SvgElement {
+            node: self.document.root(),
+        }
+    }
+
+    /// Returns the pages of a document that is laid out as a sequence of pages.
+    ///
+    /// Documents like slide decks can mark each of their pages with a convention: a page
+    /// is a `<g>` element that is a direct child of the toplevel `<svg>`, has an `id`,
+    /// and has `page` among its classes:
+    ///
+    /// ```xml
+    /// <svg xmlns="http://www.w3.org/2000/svg" width="800" height="600">
+    ///   <g id="title" class="page">...</g>
+    ///   <g id="agenda" class="page">...</g>
+    /// </svg>
+    /// ```
+    ///
+    /// The pages are returned in document order.  Use
+    /// [`CairoRenderer::render_pages_to_pdf`] to render each of them to a separate page.
+    pub fn pages(&self) -> Vec<SvgElement> {
+        self.root_element()
+            .children()
+            .filter(|e| {
+                let elt = e.node.borrow_element();
+
+                elt.element_name().local.as_ref() == "g"
+                    && elt.get_id().is_some()
+                    && elt
+                        .get_class()
+                        .map(|c| c.split_whitespace().any(|class| class == "page"))
+                        .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Returns a summary of the SMIL animations in the document.
+    ///
+    /// Librsvg renders documents in their static state and does not run animations, but
+    /// programs that turn animated SVGs into a sequence of frames can use this to know
+    /// how long the animation lasts, and which elements change during it.  Use
+    /// [`SvgHandle::set_animation_time`] to render each frame.
+    ///
+    /// See [`AnimationInfo`] for the details.
+    pub fn animation_info(&self) -> AnimationInfo {
+        let timeline = animation::timeline(&self.document);
+
+        AnimationInfo {
+            // Durations too long for a Duration are left out, as if they were indefinite.
+            duration: timeline
+                .duration
+                .and_then(|d| Duration::try_from_secs_f64(d.max(0.0)).ok()),
+            loops: timeline.loops,
+            animated_elements: timeline
+                .targets
+                .into_iter()
+                .map(|node| SvgElement { node })
+                .collect(),
+        }
+    }
+
+    /// Makes subsequent renders show the document as it is `time` after its animations
+    /// start.
+    ///
+    /// The SMIL animations in the document are sampled at `time`, and the attributes
+    /// they animate take the resulting values; then the CSS cascade is run again.  Animated
+    /// values replace the attributes in the XML, so they have the same precedence as
+    /// presentation attributes with respect to CSS.
+    ///
+    /// A newly-loaded document is in its static state, which ignores animations.  See the
+    /// limitations in [`AnimationInfo`]; besides those, `<animateMotion>` is not supported,
+    /// and values that don't have the same sequence of numbers, like named colors, change
+    /// discretely instead of being interpolated.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), rsvg::LoadingError> {
+    /// let mut handle = rsvg::Loader::new().read_path("example.svg")?;
+    /// let info = handle.animation_info();
+    ///
+    /// if let Some(duration) = info.duration {
+    ///     handle.set_animation_time(duration / 2);
+    ///     // render the frame in the middle of the animation
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_animation_time(&mut self, time: Duration) {
+        let extra = self
+            .user_stylesheet
+            .as_ref()
+            .map(std::slice::from_ref)
+            .unwrap_or_default();
+
+        self.document
+            .set_animation_time(time.as_secs_f64(), extra, &self.session);
+    }
+
+    /// Sends a user interface event to an element, to begin the animations that wait for
+    /// it.
+    ///
+    /// SMIL animations can begin on events, like `begin="click"` for a click on the
+    /// animation's target, or `begin="button.click+1s"` for one second after a click on the
+    /// element with `id="button"`.  Librsvg does not process input by itself, so a viewer
+    /// that supports interactive documents needs to find out which element is under the
+    /// pointer and call this function.  Events bubble up, so an event on an element also
+    /// begins the animations that wait for that event on its ancestors.
+    ///
+    /// The event happens at the time of the last call to [`SvgHandle::set_animation_time`],
+    /// or at the start of the timeline if it was not called.  The animations are sampled
+    /// again at that time, so subsequent renders show their effect, and later calls to
+    /// [`SvgHandle::set_animation_time`] keep taking the event into account.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), rsvg::LoadingError> {
+    /// let mut handle = rsvg::Loader::new().read_path("example.svg")?;
+    ///
+    /// handle.set_animation_time(Duration::from_secs(2));
+    ///
+    /// if let Some(button) = handle.element_by_id("button") {
+    ///     handle.dispatch_event(&button, rsvg::EventKind::Click);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dispatch_event(&mut self, element: &SvgElement, event: EventKind) {
+        let extra = self
+            .user_stylesheet
+            .as_ref()
+            .map(std::slice::from_ref)
+            .unwrap_or_default();
+
+        self.document
+            .dispatch_event(&element.node, event, extra, &self.session);
+    }
+
+    /// Returns the element whose `id` attribute is `id`, like `getElementById()` in the
+    /// DOM.
+    ///
+    /// Unlike [`has_element_with_id`], this takes the plain `id`, without a leading
+    /// `#` character.
+    ///
+    /// [`has_element_with_id`]: #method.has_element_with_id
+    pub fn element_by_id(&self, id: &str) -> Option<SvgElement> {
+        self.document
+            .lookup_internal_node(id)
+            .map(|node| SvgElement { node })
+    }
+
+    /// Returns the elements that match a CSS selector list, like `querySelectorAll()` in
+    /// the DOM, in document order.
+    ///
+    /// The selectors are the same ones that librsvg supports in stylesheets, so
+    /// `".icon, #logo"` returns the elements with an `icon` class and the element whose
+    /// `id` is `logo`.
+    ///
+    /// Returns [`LoadingError::BadCss`] if `selectors` cannot be parsed.
+    pub fn query_selector_all(&self, selectors: &str) -> Result<Vec<SvgElement>, LoadingError> {
+        let nodes = css::select_all(&self.document.root(), selectors, &self.session)?;
+
+        Ok(nodes.into_iter().map(|node| SvgElement { node }).collect())
+    }
+
+    /// Creates a handle for the same document that has its own stylesheet.
+    ///
+    /// The new handle shares the parsed tree of elements with this one, so it is much
+    /// cheaper than loading the document again.  Everything else is independent: the
+    /// new handle gets `css` as its stylesheet, like with [`SvgHandle::set_stylesheet`],
+    /// and it has its own caches of images and static elements, and its own animation
+    /// time.  It starts with the same font options, but without the fonts registered with
+    /// [`SvgHandle::add_font_file`] or [`SvgHandle::add_font_data`].
+    ///
+    /// This is useful to render the same document with different styles, for example
+    /// to theme an icon differently in each window of an application.
+    ///
+    /// Styles are stored in the shared elements, so switching between handles that
+    /// share a document makes the next render run the CSS cascade again.  The shared
+    /// elements are also why changes made with [`SvgHandle::set_foreign_attribute`] are
+    /// visible from all the handles.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), rsvg::LoadingError> {
+    /// let handle = rsvg::Loader::new().read_path("example.svg")?;
+    ///
+    /// let dark = handle.clone_with_overrides(Some("rect { fill: black; }"))?;
+    /// let light = handle.clone_with_overrides(Some("rect { fill: white; }"))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn clone_with_overrides(&self, css: Option<&str>) -> Result<SvgHandle, LoadingError> {
+        let mut handle = SvgHandle {
+            session: self.session.clone(),
+            document: self.document.share_tree(),
+            stylesheet: None,
+            user_stylesheet: None,
+            warnings: self.warnings.clone(),
+        };
+
+        if let Some(css) = css {
+            handle.set_stylesheet(css)?;
+        }
+
+        Ok(handle)
+    }
+}
+
+/// Summary of the animations in a document, from [`SvgHandle::animation_info`].
+///
+/// This looks at the timing attributes of the `<animate>`, `<set>`, `<animateMotion>`,
+/// `<animateColor>`, and `<animateTransform>` elements.  Only offset values for `begin`
+/// and `end`, like `begin="2s"`, are taken into account; animations that start on events
+/// like `begin="click"`, or relative to other animations, are ignored for the duration.
+/// See [`SvgHandle::dispatch_event`] to start animations that wait for events.
+pub struct AnimationInfo {
+    /// Time at which all the animations are finished, or `None` if there are no
+    /// animations that start at a known time.
+    ///
+    /// Animations that repeat indefinitely are counted with a single iteration, so this
+    /// is the length of one loop when [`loops`](#structfield.loops) is true.
+    pub duration: Option<Duration>,
+
+    /// Whether any animation repeats indefinitely, with
+    /// `repeatCount="indefinite"` or `repeatDur="indefinite"`.
+    pub loops: bool,
+
+    /// Elements that animations modify, in the order in which the animations appear in
+    /// the document.  This is the element referenced by each animation's `href`, or its
+    /// parent element.
+    pub animated_elements: Vec<SvgElement>,
+}
+
+/// Read-only view of an element in a loaded SVG document.
+///
+/// Get the toplevel one with [`SvgHandle::root_element`] or [`SvgHandle::element_by_id`],
+/// and walk the tree with [`children`] or [`descendants`].  Only elements are exposed;
+/// text content and other kinds of XML nodes are skipped.
+///
+/// All the attributes from the XML are kept, including the ones librsvg does not
+/// understand, like `data-*` attributes or attributes and elements in other namespaces.
+/// Applications can use them to bind their own data to parts of the document.
+///
+/// [`children`]: #method.children
+/// [`descendants`]: #method.descendants
+#[derive(Clone)]
+pub struct SvgElement {
+    node: Node,
+}
+
+/// An attribute of an [`SvgElement`], as it appeared in the XML.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgAttribute {
+    /// Namespace URI of the attribute; empty for attributes without a namespace prefix.
+    pub namespace: String,
+
+    /// Namespace prefix with which the attribute was written, like `inkscape` for
+    /// `inkscape:label`.
+    pub prefix: Option<String>,
+
+    /// Local name of the attribute, without a namespace prefix.
+    pub name: String,
+
+    /// The attribute's value.
+    pub value: String,
+}
+
+impl SvgElement {
+    /// Local name of the element, like `"rect"`.
+    pub fn name(&self) -> String {
+        self.node.borrow_element().element_name().local.to_string()
+    }
+
+    /// Namespace URI of the element, like `"http://www.w3.org/2000/svg"`.
+    pub fn namespace(&self) -> String {
+        self.node.borrow_element().element_name().ns.to_string()
+    }
+
+    /// The element's attributes, in the order in which they appear in the XML.
+    pub fn attributes(&self) -> Vec<SvgAttribute> {
+        self.node
+            .borrow_element()
+            .get_attributes()
+            .iter()
+            .map(|(name, value)| SvgAttribute {
+                namespace: name.ns.to_string(),
+                prefix: name.prefix.as_ref().map(|p| p.to_string()),
+                name: name.local.to_string(),
+                value: value.to_string(),
+            })
+            .collect()
+    }
+
+    /// The attributes in namespaces that librsvg does not interpret, like Inkscape's
+    /// or Sodipodi's metadata, in the order in which they appear in the XML.
+    ///
+    /// These are the attributes that [`SvgHandle::set_foreign_attribute`] can modify.
+    pub fn foreign_attributes(&self) -> Vec<SvgAttribute> {
+        self.attributes()
+            .into_iter()
+            .filter(|a| is_foreign_namespace(&a.namespace))
+            .collect()
+    }
+
+    /// The `id` attribute of the element, if it has one.
+    pub fn id(&self) -> Option<String> {
+        self.node.borrow_element().get_id().map(String::from)
+    }
+
+    /// The value of the attribute with the given namespace URI and local name.
+    ///
+    /// Use an empty `namespace` for attributes without a namespace prefix.
+    pub fn attribute(&self, namespace: &str, name: &str) -> Option<String> {
+        self.node
+            .borrow_element()
+            .get_attributes()
+            .iter()
+            .find(|(n, _)| &*n.ns == namespace && &*n.local == name)
+            .map(|(_, value)| value.to_string())
+    }
+
+    /// The element's `data-*` attributes, as pairs of the name without the `data-`
+    /// prefix, and the value.
+    pub fn data_attributes(&self) -> Vec<(String, String)> {
+        self.node
+            .borrow_element()
+            .get_attributes()
+            .iter()
+            .filter(|(n, _)| n.ns.is_empty())
+            .filter_map(|(n, value)| {
+                n.local
+                    .strip_prefix("data-")
+                    .map(|name| (name.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// The element's child elements, in document order.
+    pub fn children(&self) -> impl Iterator<Item = SvgElement> {
+        self.node
+            .children()
+            .filter(|c| c.is_element())
+            .map(|node| SvgElement { node })
+    }
+
+    /// This element and all the elements inside it, in document order.
+    pub fn descendants(&self) -> impl Iterator<Item = SvgElement> {
+        self.node
+            .descendants()
+            .filter(|c| c.is_element())
+            .map(|node| SvgElement { node })
+    }
+}
+
+/// Returns the first child of `node` that is an SVG element called `name`.
+fn svg_child_element(node: &Node, name: &str) -> Option<Node> {
+    node.children()
+        .filter(|child| child.is_element())
+        .find(|child| {
+            let elt = child.borrow_element();
+            let element_name = elt.element_name();
+            &*element_name.ns == "http://www.w3.org/2000/svg" && &*element_name.local == name
+        })
+}
+
+/// Returns the text of the first child of `node` that is an SVG element called `name`.
+fn child_element_text(node: &Node, name: &str) -> Option<String> {
+    svg_child_element(node, name).map(|child| {
+        let text: String = child
+            .descendants()
+            .filter(|n| n.is_chars())
+            .map(|n| n.borrow_chars().get_string())
+            .collect();
+
+        text.trim().to_string()
+    })
+}
+
+/// Whether `namespace` is foreign to SVG, so librsvg does not interpret attributes in it.
+fn is_foreign_namespace(namespace: &str) -> bool {
+    ![
+        "",
+        "http://www.w3.org/2000/svg",
+        "http://www.w3.org/1999/xlink",
+        "http://www.w3.org/XML/1998/namespace",
+    ]
+    .contains(&namespace)
+}
+
+// Private methods go here
+impl SvgHandle {
+    fn new(session: Session, document: Document) -> SvgHandle {
+        let warnings = session.take_warnings();
+
+        SvgHandle {
+            document,
+            session,
+            stylesheet: None,
+            user_stylesheet: None,
+            warnings,
+        }
+    }
+
+    /// Returns the document, with this handle's styles applied to it.
+    ///
+    /// See [`SvgHandle::clone_with_overrides`] for why they may not be.
+    fn current_document(&self) -> &Document {
+        let extra = self
+            .user_stylesheet
+            .as_ref()
+            .map(std::slice::from_ref)
+            .unwrap_or_default();
+
+        self.document.make_current(extra, &self.session);
+        &self.document
+    }
+
+    fn get_node_id_or_root(&self, id: Option<&str>) -> Result<Option<NodeId>, RenderingError> {
+        match id {
+            None => Ok(None),
+            Some(s) => Ok(Some(self.get_node_id(s)?)),
+        }
+    }
+
+    fn get_node_id(&self, id: &str) -> Result<NodeId, RenderingError> {
+        let node_id = NodeId::parse(id).map_err(|_| RenderingError::InvalidId(id.to_string()))?;
+
+        // The public APIs to get geometries of individual elements, or to render
+        // them, should only allow referencing elements within the main handle's
+        // SVG file; that is, only plain "#foo" fragment IDs are allowed here.
         // Otherwise, a calling program could request "another-file#foo" and cause
         // another-file to be loaded, even if it is not part of the set of
         // resources that the main SVG actually references.  In the future we may
@@ -425,8 +1644,20 @@ impl SvgHandle {
 /// Can render an `SvgHandle` to a Cairo context.
 pub struct CairoRenderer<'a> {
     pub(crate) handle: &'a SvgHandle,
+    session: Session,
     pub(crate) dpi: Dpi,
+    physical_units_dpi: Option<Dpi>,
     user_language: UserLanguage,
+    matte: Option<Matte>,
+    supersampling: u32,
+    quality: RenderingQuality,
+    path_simplification: Option<f64>,
+    error_placeholders: bool,
+    transform: Option<cairo::Matrix>,
+    opacity: f64,
+    spot_colors: Vec<SpotColor>,
+    linear_light: bool,
+    color_vision: Option<ColorVisionDeficiency>,
     is_testing: bool,
 }
 
@@ -434,6 +1665,124 @@ pub struct CairoRenderer<'a> {
 const DEFAULT_DPI_X: f64 = 96.0;
 const DEFAULT_DPI_Y: f64 = 96.0;
 
+/// Largest factor accepted by [`CairoRenderer::with_supersampling`].
+const MAX_SUPERSAMPLING: u32 = 16;
+
+/// Renders again with `render` into a recording surface, and writes it as a cairo-script.
+///
+/// The recording starts with the same transformation as `cr`, so the script draws at the
+/// same place as the actual rendering.
+#[cfg(feature = "cairo-script")]
+fn dump_cairo_script<F>(path: &Path, cr: &cairo::Context, render: F) -> Result<(), RenderingError>
+where
+    F: FnOnce(&cairo::Context) -> Result<(), RenderingError>,
+{
+    let recording = cairo::RecordingSurface::create(cairo::Content::ColorAlpha, None)?;
+
+    {
+        let recording_cr = cairo::Context::new(&recording)?;
+        recording_cr.set_matrix(cr.matrix());
+        render(&recording_cr)?;
+    }
+
+    let script = cairo::Device::create(path).ok_or_else(|| {
+        RenderingError::Rendering(format!(
+            "could not create cairo-script file {}",
+            path.display()
+        ))
+    })?;
+
+    script.from_recording_surface(&recording)?;
+    script.finish();
+
+    Ok(())
+}
+
+/// Background over which to composite a rendering.
+///
+/// Viewers usually show images with transparent areas over a matte, so that it is clear
+/// which parts are transparent.  See [`CairoRenderer::with_matte`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Matte {
+    /// A solid color.
+    Solid(cssparser::RGBA),
+
+    /// A checkerboard of alternating squares.
+    Checkerboard {
+        /// Color of the square at the origin of the device space, and of every other one.
+        light: cssparser::RGBA,
+
+        /// Color of the rest of the squares.
+        dark: cssparser::RGBA,
+
+        /// Size of each square in device units, for example pixels.  Sizes smaller than
+        /// 1 are treated as 1.
+        square_size: u32,
+    },
+}
+
+impl Matte {
+    /// Paints the matte over the whole clip region of `cr`.
+    ///
+    /// The squares of a checkerboard are aligned to the device space of the `cr`, so
+    /// they keep the same size regardless of its current transformation.
+    pub fn paint(&self, cr: &cairo::Context) -> Result<(), RenderingError> {
+        cr.status()?;
+
+        cr.save()?;
+        let res = self.set_source(cr).and_then(|()| cr.paint());
+        cr.restore()?;
+
+        Ok(res?)
+    }
+
+    fn set_source(&self, cr: &cairo::Context) -> Result<(), cairo::Error> {
+        match *self {
+            Matte::Solid(color) => set_source_color_on_cairo(cr, &cssparser::Color::Rgba(color)),
+
+            Matte::Checkerboard {
+                light,
+                dark,
+                square_size,
+            } => {
+                let size = square_size.max(1);
+                let tile_size = size
+                    .checked_mul(2)
+                    .and_then(|s| i32::try_from(s).ok())
+                    .ok_or(cairo::Error::InvalidSize)?;
+
+                let tile =
+                    cairo::ImageSurface::create(cairo::Format::ARgb32, tile_size, tile_size)?;
+
+                {
+                    let tile_cr = cairo::Context::new(&tile)?;
+                    tile_cr.set_operator(cairo::Operator::Source);
+
+                    set_source_color_on_cairo(&tile_cr, &cssparser::Color::Rgba(light));
+                    tile_cr.paint()?;
+
+                    let size = f64::from(size);
+                    set_source_color_on_cairo(&tile_cr, &cssparser::Color::Rgba(dark));
+                    tile_cr.rectangle(size, 0.0, size, size);
+                    tile_cr.rectangle(0.0, size, size, size);
+                    tile_cr.fill()?;
+                }
+
+                let pattern = cairo::SurfacePattern::create(&tile);
+                pattern.set_extend(cairo::Extend::Repeat);
+                pattern.set_filter(cairo::Filter::Nearest);
+
+                // Map user space to device space, so the squares are aligned to pixels.
+                pattern.set_matrix(cr.matrix());
+
+                cr.set_source(&pattern)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 /// Contains the computed values of the `<svg>` element's `width`, `height`, and `viewBox`.
 ///
@@ -469,6 +1818,41 @@ pub struct IntrinsicDimensions {
     pub vbox: Option<cairo::Rectangle>,
 }
 
+/// Kind of value of the `width` or `height` of the toplevel `<svg>` element.
+///
+/// [`IntrinsicDimensions`] always has a `width` and `height`, since they default to 100%
+/// when they are not specified.  Use [`CairoRenderer::intrinsic_length_kinds`] to find
+/// out whether they were actually specified, and whether they need a viewport to be
+/// resolved.
+// This needs to be kept in sync with `rsvg.h:RsvgLengthKind`.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IntrinsicLengthKind {
+    /// The property is not specified, so its computed value is 100%.
+    Missing,
+
+    /// The property is a percentage, which needs a viewport to be resolved.
+    Percentage,
+
+    /// The property is in any other unit, which can be resolved without a viewport.
+    ///
+    /// This includes the font-relative units `em` and `ex`, which get resolved against
+    /// the `font-size` of the toplevel `<svg>`.
+    Absolute,
+}
+
+impl IntrinsicLengthKind {
+    fn new(specified: bool, length: &Length) -> IntrinsicLengthKind {
+        if !specified {
+            IntrinsicLengthKind::Missing
+        } else if length.unit == LengthUnit::Percent {
+            IntrinsicLengthKind::Percentage
+        } else {
+            IntrinsicLengthKind::Absolute
+        }
+    }
+}
+
 /// Gets the user's preferred locale from the environment and
 /// translates it to a `Locale` with `LanguageRange` fallbacks.
 ///
@@ -519,12 +1903,50 @@ impl<'a> CairoRenderer<'a> {
 
         CairoRenderer {
             handle,
+            session: session.clone(),
             dpi: Dpi::new(DEFAULT_DPI_X, DEFAULT_DPI_Y),
+            physical_units_dpi: None,
             user_language: UserLanguage::new(&Language::FromEnvironment, session),
+            matte: None,
+            supersampling: 1,
+            quality: RenderingQuality::default(),
+            path_simplification: None,
+            error_placeholders: false,
+            transform: None,
+            opacity: 1.0,
+            spot_colors: Vec::new(),
+            linear_light: false,
+            color_vision: None,
             is_testing: false,
         }
     }
 
+    /// Sets the debugging options for this renderer.
+    ///
+    /// By default, the renderer uses the options that were given to the [`Loader`] with
+    /// [`Loader::with_debug_config`], or the ones from the `RSVG_LOG` environment variable.
+    pub fn with_debug_config(self, config: DebugConfig) -> Self {
+        CairoRenderer {
+            session: self.session.with_debug_config(config),
+            ..self
+        }
+    }
+
+    /// Calls `observer` before and after each element gets rendered.
+    ///
+    /// This lets external profilers, coverage tools for reference tests, or code that adds
+    /// watermarks see each element as it is drawn, with its id, its extents, and how long
+    /// it took to render.  See [`RenderObserver`] for details.
+    ///
+    /// This is only available with the `render-observers` Cargo feature.
+    #[cfg(feature = "render-observers")]
+    pub fn with_render_observer<O: RenderObserver + 'static>(self, observer: O) -> Self {
+        CairoRenderer {
+            session: self.session.with_render_observer(Arc::new(observer)),
+            ..self
+        }
+    }
+
     /// Configures the dots-per-inch for resolving physical lengths.
     ///
     /// If an SVG document has physical units like `5cm`, they must be resolved
@@ -540,6 +1962,56 @@ impl<'a> CairoRenderer<'a> {
         }
     }
 
+    /// Configures the dots-per-inch to use for documents whose size is in physical units.
+    ///
+    /// If the `width` and `height` of the toplevel `<svg>` are both in physical units,
+    /// like `width="210mm" height="297mm"`, this DPI is used instead of the one from
+    /// [`with_dpi`], so that the document's size in pixels is the size that it would
+    /// have when printed at `dpi_x` by `dpi_y`.  Documents sized in pixels, font-based
+    /// units, or percentages keep using the DPI from [`with_dpi`].
+    ///
+    /// This is useful for print previews, which can render any document at a target
+    /// resolution without having to look at its units first.
+    ///
+    /// [`with_dpi`]: #method.with_dpi
+    pub fn with_physical_units_dpi(self, dpi_x: f64, dpi_y: f64) -> Self {
+        assert!(dpi_x > 0.0);
+        assert!(dpi_y > 0.0);
+
+        CairoRenderer {
+            physical_units_dpi: Some(Dpi::new(dpi_x, dpi_y)),
+            ..self
+        }
+    }
+
+    /// Returns the DPI to use for this renderer's document.
+    ///
+    /// See [`with_physical_units_dpi`] for details.
+    ///
+    /// [`with_physical_units_dpi`]: #method.with_physical_units_dpi
+    fn effective_dpi(&self) -> Dpi {
+        let is_physical = |unit| {
+            matches!(
+                unit,
+                LengthUnit::In | LengthUnit::Cm | LengthUnit::Mm | LengthUnit::Pt | LengthUnit::Pc
+            )
+        };
+
+        match self.physical_units_dpi {
+            Some(dpi) => {
+                let dim = self.intrinsic_dimensions();
+
+                if is_physical(dim.width.unit) && is_physical(dim.height.unit) {
+                    dpi
+                } else {
+                    self.dpi
+                }
+            }
+
+            None => self.dpi,
+        }
+    }
+
     /// Configures the set of languages used for rendering.
     ///
     /// SVG documents can use the `<switch>` element, whose children have a
@@ -551,7 +2023,7 @@ impl<'a> CairoRenderer<'a> {
     /// be obtained from the program's environment.  To set an explicit list of languages,
     /// you can use `Language::AcceptLanguage` instead.
     pub fn with_language(self, language: &Language) -> Self {
-        let user_language = UserLanguage::new(language, &self.handle.session);
+        let user_language = UserLanguage::new(language, &self.session);
 
         CairoRenderer {
             user_language,
@@ -559,39 +2031,270 @@ impl<'a> CairoRenderer<'a> {
         }
     }
 
-    /// Queries the `width`, `height`, and `viewBox` attributes in an SVG document.
+    /// Configures a color to fill the viewport before rendering.
     ///
-    /// If you are calling this function to compute a scaling factor to render the SVG,
-    /// consider simply using [`render_document`] instead; it will do the scaling
-    /// computations automatically.
+    /// By default no background is drawn, so areas of the viewport that the SVG does not
+    /// paint are left untouched in the target surface.  If a background color is set,
+    /// [`render_document`] and [`render_element`] will fill their viewport rectangle with
+    /// it, and then draw the SVG on top.  Only the viewport is filled, not the whole
+    /// surface, and the fill is subject to the Cairo context's current transformation and
+    /// clip, just like the SVG's contents.
     ///
-    /// See also [`intrinsic_size_in_pixels`], which does the conversion to pixels if
-    /// possible.
+    /// [`render_layer`] does not paint a background, since it is meant to draw on top of
+    /// a previously-rendered document.
     ///
     /// [`render_document`]: #method.render_document
-    /// [`intrinsic_size_in_pixels`]: #method.intrinsic_size_in_pixels
-    pub fn intrinsic_dimensions(&self) -> IntrinsicDimensions {
-        let d = self.handle.document.get_intrinsic_dimensions();
+    /// [`render_element`]: #method.render_element
+    /// [`render_layer`]: #method.render_layer
+    pub fn with_background_color(self, color: cssparser::RGBA) -> Self {
+        self.with_matte(Matte::Solid(color))
+    }
 
-        IntrinsicDimensions {
-            width: Into::into(d.width),
-            height: Into::into(d.height),
-            vbox: d.vbox.map(|v| cairo::Rectangle::from(*v)),
+    /// Configures a matte to fill the viewport before rendering.
+    ///
+    /// This works like [`with_background_color`], but the matte can also be a
+    /// checkerboard, which viewers commonly use to show which parts of an image are
+    /// transparent.
+    ///
+    /// [`with_background_color`]: #method.with_background_color
+    pub fn with_matte(self, matte: Matte) -> Self {
+        CairoRenderer {
+            matte: Some(matte),
+            ..self
         }
     }
 
-    /// Converts the SVG document's intrinsic dimensions to pixels, if possible.
+    /// Configures supersampled rendering.
     ///
-    /// Returns `Some(width, height)` in pixel units if the SVG document has `width` and
-    /// `height` attributes with physical dimensions (CSS pixels, cm, in, etc.) or
-    /// font-based dimensions (em, ex).
+    /// Small renderings of documents with hairline strokes or dense filter effects can alias
+    /// badly.  With a `factor` greater than 1, [`render_document`] and [`render_element`]
+    /// will draw the SVG to an offscreen image that is `factor` times larger than the area of
+    /// the target surface covered by the viewport, and then downscale it in linear RGB before
+    /// compositing the result onto the target.  The downscaling uses a box filter, or a
+    /// sharper Lanczos filter if [`with_quality`] is set to [`RenderingQuality::High`].
     ///
-    /// Note that the dimensions are floating-point numbers, so your application can know
-    /// the exact size of an SVG document.  To get integer dimensions, you should use
-    /// [`f64::ceil()`] to round up to the nearest integer (just using [`f64::round()`],
-    /// may may chop off pixels with fractional coverage).
+    /// The default is 1, which means that no supersampling is done.  Since the result is
+    /// always a raster image, this is only useful when rendering to image surfaces.
     ///
-    /// If the SVG document has percentage-based `width` and `height` attributes, or if
+    /// The `factor` must be between 1 and 16; otherwise, rendering will fail with
+    /// [`RenderingError::Rendering`].
+    ///
+    /// [`render_document`]: #method.render_document
+    /// [`render_element`]: #method.render_element
+    /// [`with_quality`]: #method.with_quality
+    pub fn with_supersampling(self, factor: u32) -> Self {
+        CairoRenderer {
+            supersampling: factor,
+            ..self
+        }
+    }
+
+    /// Configures the trade-off between rendering quality and compatibility.
+    ///
+    /// The default is [`RenderingQuality::Compatible`], which renders SVG documents like
+    /// librsvg always has, closely matching what web browsers do.  With
+    /// [`RenderingQuality::High`], librsvg uses slower or less common algorithms where it
+    /// has them.  Currently this means:
+    ///
+    /// * Gradients honor the `color-interpolation` property of the gradient element,
+    ///   including the `oklab` and `oklch` values which librsvg supports as an extension.
+    ///
+    /// * Luminance masks with `color-interpolation="linearRGB"` compute the luminance of
+    ///   the mask's contents in linear RGB.
+    ///
+    /// * Raster images with `preserveAspectRatio="none"` that get shrunk a lot are
+    ///   resampled with a Lanczos filter instead of Cairo's bilinear one.
+    ///
+    /// * Supersampled renderings (see [`with_supersampling`]) are downscaled with a
+    ///   Lanczos filter instead of a box filter.
+    ///
+    /// [`with_supersampling`]: #method.with_supersampling
+    pub fn with_quality(self, quality: RenderingQuality) -> Self {
+        CairoRenderer { quality, ..self }
+    }
+
+    /// Simplifies straight-line runs in paths before sending them to Cairo.
+    ///
+    /// Documents generated by tracing bitmaps tend to have paths with a great number of
+    /// tiny line segments.  With a `tolerance`, consecutive line segments are simplified
+    /// with the [Ramer–Douglas–Peucker
+    /// algorithm](https://en.wikipedia.org/wiki/Ramer%E2%80%93Douglas%E2%80%93Peucker_algorithm),
+    /// removing points that deviate less than `tolerance` device units from the
+    /// simplified outline.  This is mostly useful when rendering to vector surfaces
+    /// like PDF or SVG, as it reduces the size of the output.
+    ///
+    /// Curves and arcs are left untouched.  The default is `None`, which disables
+    /// simplification.
+    pub fn with_path_simplification(self, tolerance: Option<f64>) -> Self {
+        let path_simplification = tolerance.filter(|t| t.is_finite() && *t > 0.0);
+
+        CairoRenderer {
+            path_simplification,
+            ..self
+        }
+    }
+
+    /// Draws a placeholder where an element could not be rendered.
+    ///
+    /// Normally, an `<image>` whose file cannot be loaded, or an element whose filter
+    /// fails, is simply left out of the rendering, which makes it hard to notice that
+    /// something went wrong.  With this option enabled, librsvg draws a checkerboard with
+    /// a red cross over the area that the failed element would have occupied.
+    ///
+    /// The default is `false`.
+    pub fn with_error_placeholders(self, error_placeholders: bool) -> Self {
+        CairoRenderer {
+            error_placeholders,
+            ..self
+        }
+    }
+
+    /// Transforms the rendered document, around the center of the viewport.
+    ///
+    /// The document gets fitted into the viewport as usual, and then `transform` gets
+    /// applied with its origin at the viewport's center.  This lets you rotate, flip, or
+    /// scale a document without adjusting the Cairo context's matrix and the viewport
+    /// yourself; for example, `cairo::Matrix::new(-1.0, 0.0, 0.0, 1.0, 0.0, 0.0)` mirrors
+    /// the document horizontally in place.  The background color from
+    /// [`with_background_color`] is not transformed.
+    ///
+    /// The transform applies to [`render_document`], [`render_layer`], and
+    /// [`render_element`]; the functions that compute geometries ignore it.  Rendering
+    /// fails if the matrix is not invertible.
+    ///
+    /// [`with_background_color`]: #method.with_background_color
+    /// [`render_document`]: #method.render_document
+    /// [`render_layer`]: #method.render_layer
+    /// [`render_element`]: #method.render_element
+    pub fn with_transform(self, transform: cairo::Matrix) -> Self {
+        CairoRenderer {
+            transform: Some(transform),
+            ..self
+        }
+    }
+
+    /// Renders the whole document with the specified group opacity.
+    ///
+    /// This is like setting the `opacity` property on the root element: the document is
+    /// rendered into a temporary group which is then composited with `opacity`, so
+    /// overlapping elements do not show through each other.  The value gets clamped to
+    /// the range 0.0 to 1.0.  The default is 1.0, which means fully opaque.
+    pub fn with_opacity(self, opacity: f64) -> Self {
+        CairoRenderer {
+            opacity: opacity.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Renders a preview that simulates overprinting for the given spot inks.
+    ///
+    /// Print designers often use solid colors in their artwork as stand-ins for spot inks.
+    /// Each [`SpotColor`] maps one of those colors to the color of its ink.  Fills and
+    /// strokes that use one of the colors are painted with the ink's color, and are
+    /// multiplied with whatever is underneath them, as if the ink were overprinted.  All
+    /// other paints knock out what is underneath, as usual.
+    ///
+    /// Only solid colors are matched, not gradients or patterns.  The default is an empty
+    /// list, which renders normally.
+    pub fn with_overprint_preview(self, spot_colors: &[SpotColor]) -> Self {
+        CairoRenderer {
+            spot_colors: spot_colors.to_vec(),
+            ..self
+        }
+    }
+
+    /// Scales raster images and composites groups in linear light.
+    ///
+    /// Cairo, like web browsers, blends colors using their sRGB values, which are not
+    /// proportional to the amount of light.  This makes downscaled images show dark halos
+    /// around light details, and makes translucent groups look darker than they should.
+    /// With `true`, librsvg does these operations in linear RGB instead:
+    ///
+    /// * Raster images that get shrunk are resampled in linear RGB, with a Lanczos filter,
+    ///   instead of with Cairo's bilinear filter.
+    ///
+    /// * Groups that get composited with `opacity`, masks, or clipping paths are blended
+    ///   onto what is beneath them in linear RGB, as long as they use the normal blend mode
+    ///   and the target is an image surface.
+    ///
+    /// The default is `false`, since the results differ from other renderers, and blending
+    /// in linear RGB is done pixel by pixel over the whole target and so is slower.
+    pub fn with_linear_light(self, linear_light: bool) -> Self {
+        CairoRenderer {
+            linear_light,
+            ..self
+        }
+    }
+
+    /// Renders as seen by a person with a color vision deficiency.
+    ///
+    /// [`render_document`] and [`render_element`] draw the SVG to an offscreen image, and
+    /// convert it with a simulation of the given deficiency before compositing it onto the
+    /// target.  This is useful to check that icons and charts are still legible to people
+    /// with color blindness.  The conversion happens in linear RGB, after supersampling.
+    ///
+    /// The default is `None`, which renders normally.  Since the result is always a raster
+    /// image, this is only useful when rendering to image surfaces.
+    ///
+    /// [`render_document`]: #method.render_document
+    /// [`render_element`]: #method.render_element
+    pub fn with_color_vision_simulation(self, deficiency: Option<ColorVisionDeficiency>) -> Self {
+        CairoRenderer {
+            color_vision: deficiency,
+            ..self
+        }
+    }
+
+    /// Queries the `width`, `height`, and `viewBox` attributes in an SVG document.
+    ///
+    /// If you are calling this function to compute a scaling factor to render the SVG,
+    /// consider simply using [`render_document`] instead; it will do the scaling
+    /// computations automatically.
+    ///
+    /// See also [`intrinsic_size_in_pixels`], which does the conversion to pixels if
+    /// possible.
+    ///
+    /// [`render_document`]: #method.render_document
+    /// [`intrinsic_size_in_pixels`]: #method.intrinsic_size_in_pixels
+    pub fn intrinsic_dimensions(&self) -> IntrinsicDimensions {
+        let d = self.handle.current_document().get_intrinsic_dimensions();
+
+        IntrinsicDimensions {
+            width: Into::into(d.width),
+            height: Into::into(d.height),
+            vbox: d.vbox.map(|v| cairo::Rectangle::from(*v)),
+        }
+    }
+
+    /// Returns what kind of values the `width` and `height` of the toplevel `<svg>` are.
+    ///
+    /// The values themselves are returned by [`intrinsic_dimensions`]; this tells
+    /// whether they were specified at all, and whether they are percentages.  A caller
+    /// that negotiates the size of the SVG with its surrounding layout, as CSS does for
+    /// replaced elements, needs to know this.
+    ///
+    /// [`intrinsic_dimensions`]: #method.intrinsic_dimensions
+    pub fn intrinsic_length_kinds(&self) -> (IntrinsicLengthKind, IntrinsicLengthKind) {
+        let d = self.handle.current_document().get_intrinsic_dimensions();
+
+        (
+            IntrinsicLengthKind::new(d.has_width, &Into::into(d.width)),
+            IntrinsicLengthKind::new(d.has_height, &Into::into(d.height)),
+        )
+    }
+
+    /// Converts the SVG document's intrinsic dimensions to pixels, if possible.
+    ///
+    /// Returns `Some(width, height)` in pixel units if the SVG document has `width` and
+    /// `height` attributes with physical dimensions (CSS pixels, cm, in, etc.) or
+    /// font-based dimensions (em, ex).
+    ///
+    /// Note that the dimensions are floating-point numbers, so your application can know
+    /// the exact size of an SVG document.  To get integer dimensions, you should use
+    /// [`f64::ceil()`] to round up to the nearest integer (just using [`f64::round()`],
+    /// may may chop off pixels with fractional coverage).
+    ///
+    /// If the SVG document has percentage-based `width` and `height` attributes, or if
     /// either of those attributes are not present, returns `None`.  Dimensions of that
     /// kind require more information to be resolved to pixels; for example, the calling
     /// application can use a viewport size to scale percentage-based dimensions.
@@ -604,7 +2307,30 @@ impl<'a> CairoRenderer<'a> {
             return None;
         }
 
-        Some(self.width_height_to_user(self.dpi))
+        Some(self.width_height_to_user(self.effective_dpi()))
+    }
+
+    /// Estimates the cost of rendering the whole document to a viewport, without rendering.
+    ///
+    /// This walks the document like [`render_document`] would, and predicts the memory
+    /// for the intermediate surfaces it needs, and how many drawing operations it does.
+    /// Servers that render untrusted documents can use it to reject or queue expensive
+    /// ones before spending the time on them.
+    ///
+    /// The `viewport` is taken to be in pixels, with no further scaling; the
+    /// supersampling factor from [`with_supersampling`] is taken into account.  The
+    /// result is an estimate: the actual rendering may need less memory when groups are
+    /// small, since intermediate surfaces are assumed to be as big as the viewport.
+    ///
+    /// [`render_document`]: #method.render_document
+    /// [`with_supersampling`]: #method.with_supersampling
+    pub fn estimate_cost(&self, viewport: &cairo::Rectangle) -> RenderCost {
+        cost::estimate(
+            self.handle.current_document(),
+            &self.session,
+            viewport,
+            self.supersampling,
+        )
     }
 
     /// Renders the whole SVG document fitted to a viewport
@@ -620,15 +2346,58 @@ impl<'a> CairoRenderer<'a> {
         cr: &cairo::Context,
         viewport: &cairo::Rectangle,
     ) -> Result<(), RenderingError> {
-        Ok(self.handle.document.render_document(
-            &self.handle.session,
-            cr,
-            viewport,
-            &self.user_language,
-            self.dpi,
-            SvgNesting::Standalone,
-            self.is_testing,
-        )?)
+        self.render_document_with_cache(cr, viewport, None)
+    }
+
+    fn render_document_with_cache(
+        &self,
+        cr: &cairo::Context,
+        viewport: &cairo::Rectangle,
+        render_cache: Option<&RenderCache>,
+    ) -> Result<(), RenderingError> {
+        let render = |cr: &cairo::Context| {
+            self.supersample(cr, viewport, |cr| {
+                self.paint_background(cr, viewport)?;
+
+                self.with_overrides(cr, viewport, |cr| {
+                    Ok(self.handle.current_document().render_document(
+                        &self.session,
+                        cr,
+                        viewport,
+                        &self.user_language,
+                        self.effective_dpi(),
+                        SvgNesting::Standalone,
+                        self.quality,
+                        self.path_simplification,
+                        self.error_placeholders,
+                        render_cache,
+                        &self.spot_colors,
+                        self.linear_light,
+                        self.is_testing,
+                    )?)
+                })
+            })
+        };
+
+        render(cr)?;
+
+        #[cfg(feature = "cairo-script")]
+        if let Some(path) = self.session.debug_config().cairo_script() {
+            dump_cairo_script(path, cr, render)?;
+        }
+
+        Ok(())
+    }
+
+    /// Prepares this renderer to render its document many times.
+    ///
+    /// The returned [`RenderPlan`] renders with the same options as this renderer, but it
+    /// keeps the results of resolving the document around between renders.
+    pub fn prepare(self) -> RenderPlan<'a> {
+        RenderPlan {
+            renderer: self,
+            render_cache: RenderCache::default(),
+        }
     }
 
     /// Computes the (ink_rect, logical_rect) of an SVG element, as if
@@ -663,12 +2432,120 @@ impl<'a> CairoRenderer<'a> {
         let node_id = self.handle.get_node_id_or_root(id)?;
         let node = self.handle.get_node_or_root(&node_id)?;
 
-        Ok(self.handle.document.get_geometry_for_layer(
-            &self.handle.session,
+        Ok(self.handle.current_document().get_geometry_for_layer(
+            &self.session,
             node,
             viewport,
             &self.user_language,
-            self.dpi,
+            self.effective_dpi(),
+            self.is_testing,
+        )?)
+    }
+
+    /// Computes the (ink_rect, logical_rect) of several SVG elements at once
+    ///
+    /// This returns the same kind of rectangles as [`geometry_for_layer`], one pair for each
+    /// element in `ids`, in the same order.  Instead of walking the document once per
+    /// element, the whole SVG is measured a single time, so this is much faster when you
+    /// need the geometry of many elements.
+    ///
+    /// The rectangles only include each element's own contents.  Unlike with
+    /// [`geometry_for_layer`], effects from the element's ancestors, like filters, are not
+    /// taken into account.  Elements which are not rendered in their place in the
+    /// document, for example because they are inside a `<defs>`, get empty rectangles.
+    ///
+    /// Element IDs should look like URL fragment identifiers, like `"#foo"`.  If any of
+    /// them does not exist, this returns [`RenderingError::IdNotFound`].
+    ///
+    /// [`geometry_for_layer`]: #method.geometry_for_layer
+    pub fn geometry_for_elements(
+        &self,
+        ids: &[&str],
+        viewport: &cairo::Rectangle,
+    ) -> Result<Vec<(cairo::Rectangle, cairo::Rectangle)>, RenderingError> {
+        let nodes = ids
+            .iter()
+            .map(|id| {
+                let node_id = self.handle.get_node_id(id)?;
+                Ok(self.handle.lookup_node(&node_id)?)
+            })
+            .collect::<Result<Vec<Node>, RenderingError>>()?;
+
+        Ok(self.handle.current_document().get_geometries_for_layers(
+            &self.session,
+            &nodes,
+            viewport,
+            &self.user_language,
+            self.effective_dpi(),
+            self.is_testing,
+        )?)
+    }
+
+    /// Finds the topmost element with an `id` that is painted at a point
+    ///
+    /// The `viewport` gives the position and size at which the whole SVG document would
+    /// be rendered, as for [`render_document`], and `(x, y)` is in the same coordinates
+    /// as the `viewport`.
+    ///
+    /// Shapes are hit where their fill or stroke gets painted, and text and images
+    /// anywhere within their bounding box.  Content drawn through a `<use>` counts as the
+    /// `<use>` element.  If the element under the point does not have an `id`, this
+    /// returns its closest ancestor that has one.  Clipping paths, masks, and filters are
+    /// not taken into account.
+    ///
+    /// Returns `None` if nothing with an `id` is painted at the point.
+    ///
+    /// This operation is not constant-time, as it involves going through all
+    /// the elements in the document.
+    ///
+    /// [`render_document`]: #method.render_document
+    pub fn element_at_point(
+        &self,
+        viewport: &cairo::Rectangle,
+        x: f64,
+        y: f64,
+    ) -> Result<Option<SvgElement>, RenderingError> {
+        let hit = self.handle.current_document().get_element_at_point(
+            &self.session,
+            viewport,
+            x,
+            y,
+            &self.user_language,
+            self.effective_dpi(),
+            self.is_testing,
+        )?;
+
+        Ok(hit.and_then(|node| {
+            node.ancestors()
+                .find(|n| n.is_element() && n.borrow_element().get_id().is_some())
+                .map(|node| SvgElement { node })
+        }))
+    }
+
+    /// Computes the contrast between each run of text and what gets rendered beneath it
+    ///
+    /// The `viewport` gives the position and size at which the whole SVG document would
+    /// be rendered, as for [`render_document`].  The document is rendered without its
+    /// text to an image that covers the viewport, and the background of each run of text
+    /// is the average of the pixels beneath its ink extents.  Everything else in the
+    /// document counts as background, even if it is drawn above the text, and transparent
+    /// areas count as white.
+    ///
+    /// Only text with a solid fill color is reported; text filled with gradients or
+    /// patterns is skipped.  The runs are returned in the order in which they get drawn.
+    /// Accessibility tools can compare the [`TextContrast::contrast_ratio`] of each run
+    /// with the levels that WCAG asks for.
+    ///
+    /// [`render_document`]: #method.render_document
+    pub fn audit_text_contrast(
+        &self,
+        viewport: &cairo::Rectangle,
+    ) -> Result<Vec<TextContrast>, RenderingError> {
+        Ok(self.handle.current_document().get_text_contrast(
+            &self.session,
+            viewport,
+            &self.user_language,
+            self.effective_dpi(),
             self.is_testing,
         )?)
     }
@@ -697,20 +2574,38 @@ impl<'a> CairoRenderer<'a> {
         cr: &cairo::Context,
         id: Option<&str>,
         viewport: &cairo::Rectangle,
+    ) -> Result<(), RenderingError> {
+        self.render_layer_with_cache(cr, id, viewport, None)
+    }
+
+    fn render_layer_with_cache(
+        &self,
+        cr: &cairo::Context,
+        id: Option<&str>,
+        viewport: &cairo::Rectangle,
+        render_cache: Option<&RenderCache>,
     ) -> Result<(), RenderingError> {
         let node_id = self.handle.get_node_id_or_root(id)?;
         let node = self.handle.get_node_or_root(&node_id)?;
 
-        Ok(self.handle.document.render_layer(
-            &self.handle.session,
-            cr,
-            node,
-            viewport,
-            &self.user_language,
-            self.dpi,
-            SvgNesting::Standalone,
-            self.is_testing,
-        )?)
+        self.with_overrides(cr, viewport, |cr| {
+            Ok(self.handle.current_document().render_layer(
+                &self.session,
+                cr,
+                node,
+                viewport,
+                &self.user_language,
+                self.effective_dpi(),
+                SvgNesting::Standalone,
+                self.quality,
+                self.path_simplification,
+                self.error_placeholders,
+                render_cache,
+                &self.spot_colors,
+                self.linear_light,
+                self.is_testing,
+            )?)
+        })
     }
 
     /// Computes the (ink_rect, logical_rect) of a single SVG element
@@ -735,6 +2630,9 @@ impl<'a> CairoRenderer<'a> {
     /// Note that these bounds are not minimum bounds; for example,
     /// clipping paths are not taken into account.
     ///
+    /// Elements that are not rendered because of `display: none`, either on
+    /// themselves or on one of their ancestors, have empty rectangles.
+    ///
     /// You can pass `None` for the `id` if you want to measure all
     /// the elements in the SVG, i.e. to measure everything from the
     /// root element.
@@ -752,12 +2650,12 @@ impl<'a> CairoRenderer<'a> {
 
         Ok(self
             .handle
-            .document
+            .current_document()
             .get_geometry_for_element(
-                &self.handle.session,
+                &self.session,
                 node,
                 &self.user_language,
-                self.dpi,
+                self.effective_dpi(),
                 self.is_testing,
             )
             .map(|(i, l)| (i, l))?)
@@ -784,25 +2682,352 @@ impl<'a> CairoRenderer<'a> {
         cr: &cairo::Context,
         id: Option<&str>,
         element_viewport: &cairo::Rectangle,
+    ) -> Result<(), RenderingError> {
+        self.render_element_with_cache(cr, id, element_viewport, None)
+    }
+
+    fn render_element_with_cache(
+        &self,
+        cr: &cairo::Context,
+        id: Option<&str>,
+        element_viewport: &cairo::Rectangle,
+        render_cache: Option<&RenderCache>,
     ) -> Result<(), RenderingError> {
         let node_id = self.handle.get_node_id_or_root(id)?;
         let node = self.handle.get_node_or_root(&node_id)?;
 
-        Ok(self.handle.document.render_element(
-            &self.handle.session,
-            cr,
-            node,
-            element_viewport,
-            &self.user_language,
-            self.dpi,
-            self.is_testing,
-        )?)
+        self.supersample(cr, element_viewport, |cr| {
+            self.paint_background(cr, element_viewport)?;
+
+            self.with_overrides(cr, element_viewport, |cr| {
+                Ok(self.handle.current_document().render_element(
+                    &self.session,
+                    cr,
+                    node,
+                    element_viewport,
+                    &self.user_language,
+                    self.effective_dpi(),
+                    self.quality,
+                    self.path_simplification,
+                    self.error_placeholders,
+                    render_cache,
+                    &self.spot_colors,
+                    self.linear_light,
+                    self.is_testing,
+                )?)
+            })
+        })
+    }
+
+    /// Renders the SVG to a PDF document written to `writer`, and returns the writer.
+    ///
+    /// Each item in `pages` produces one page: `None` renders the whole document, and
+    /// `Some(id)` renders the element with that id, as with [`render_element`].  An empty
+    /// slice renders the whole document in a single page.
+    ///
+    /// Each page is sized to its contents, in points, taking into account the renderer's
+    /// DPI (see [`with_dpi`]), so that physical units in the SVG map to the same physical
+    /// size in the PDF.
+    ///
+    /// [`render_element`]: #method.render_element
+    /// [`with_dpi`]: #method.with_dpi
+    pub fn render_to_pdf<W: Write + 'static>(
+        &self,
+        writer: W,
+        pages: &[Option<&str>],
+    ) -> Result<W, RenderingError> {
+        let pages = if pages.is_empty() { &[None] } else { pages };
+
+        let (width, height) = self.page_size_in_points(pages[0])?;
+        let surface = cairo::PdfSurface::for_stream(width, height, writer)?;
+
+        for (i, id) in pages.iter().enumerate() {
+            let (width, height) = if i == 0 {
+                (width, height)
+            } else {
+                self.page_size_in_points(*id)?
+            };
+            surface.set_size(width, height)?;
+
+            let cr = cairo::Context::new(&surface)?;
+            self.render_page(&cr, *id, width, height)?;
+            cr.show_page()?;
+        }
+
+        finish_output_stream(&surface)
+    }
+
+    /// Renders each of the document's pages to a separate page of a PDF document.
+    ///
+    /// The pages are the ones from [`SvgHandle::pages`], and each one is rendered like with
+    /// [`render_to_pdf`].  If the document does not have any pages, this renders the whole
+    /// document in a single page.
+    ///
+    /// [`render_to_pdf`]: #method.render_to_pdf
+    pub fn render_pages_to_pdf<W: Write + 'static>(&self, writer: W) -> Result<W, RenderingError> {
+        let ids: Vec<String> = self
+            .handle
+            .pages()
+            .iter()
+            .filter_map(|page| page.id())
+            .map(|id| format!("#{id}"))
+            .collect();
+
+        let pages: Vec<Option<&str>> = ids.iter().map(|id| Some(id.as_str())).collect();
+
+        self.render_to_pdf(writer, &pages)
+    }
+
+    /// Renders the whole SVG document to a PostScript document written to `writer`, and
+    /// returns the writer.
+    ///
+    /// The page is sized in points, as with [`render_to_pdf`].
+    ///
+    /// [`render_to_pdf`]: #method.render_to_pdf
+    pub fn render_to_ps<W: Write + 'static>(&self, writer: W) -> Result<W, RenderingError> {
+        let (width, height) = self.page_size_in_points(None)?;
+        let surface = cairo::PsSurface::for_stream(width, height, writer)?;
+
+        let cr = cairo::Context::new(&surface)?;
+        self.render_page(&cr, None, width, height)?;
+        cr.show_page()?;
+
+        finish_output_stream(&surface)
+    }
+
+    /// Renders the whole SVG document to a new SVG document written to `writer`, and
+    /// returns the writer.
+    ///
+    /// The output's `width` and `height` are given in points, as with [`render_to_pdf`].
+    ///
+    /// [`render_to_pdf`]: #method.render_to_pdf
+    pub fn render_to_svg<W: Write + 'static>(&self, writer: W) -> Result<W, RenderingError> {
+        let (width, height) = self.page_size_in_points(None)?;
+        let mut surface = cairo::SvgSurface::for_stream(width, height, writer)?;
+        surface.set_document_unit(cairo::SvgUnit::Pt);
+
+        let cr = cairo::Context::new(&surface)?;
+        self.render_page(&cr, None, width, height)?;
+        drop(cr);
+
+        finish_output_stream(&surface)
+    }
+
+    /// Computes the size in points of a page for the whole document or for an element.
+    fn page_size_in_points(&self, id: Option<&str>) -> Result<(f64, f64), RenderingError> {
+        let (width, height) = match id {
+            None => match self.intrinsic_size_in_pixels() {
+                Some(size) => size,
+                None => {
+                    // Percentage-based sizes; use the extents of the drawing instead
+                    let unit_rect = cairo::Rectangle::new(0.0, 0.0, 1.0, 1.0);
+                    let (ink_r, _) = self.geometry_for_layer(None, &unit_rect)?;
+                    (ink_r.width(), ink_r.height())
+                }
+            },
+
+            Some(id) => {
+                let (_, logical_r) = self.geometry_for_element(Some(id))?;
+                (logical_r.width(), logical_r.height())
+            }
+        };
+
+        let dpi = self.effective_dpi();
+        Ok((width * 72.0 / dpi.x, height * 72.0 / dpi.y))
+    }
+
+    fn render_page(
+        &self,
+        cr: &cairo::Context,
+        id: Option<&str>,
+        width: f64,
+        height: f64,
+    ) -> Result<(), RenderingError> {
+        let viewport = cairo::Rectangle::new(0.0, 0.0, width, height);
+
+        match id {
+            None => self.render_document(cr, &viewport),
+            Some(id) => self.render_element(cr, Some(id), &viewport),
+        }
+    }
+
+    /// Calls `draw` with `cr`, or with an offscreen context if supersampling or a color vision
+    /// simulation are enabled.
+    ///
+    /// In the latter case, the offscreen image covers the device-space extents of the
+    /// `viewport`, enlarged by the supersampling factor.  After drawing, it gets downscaled
+    /// in linear RGB, converted with the color vision simulation, and composited onto `cr`.
+    /// Without supersampling, the image stays in sRGB, since the simulation linearizes each
+    /// pixel by itself.
+    fn supersample<F>(
+        &self,
+        cr: &cairo::Context,
+        viewport: &cairo::Rectangle,
+        draw: F,
+    ) -> Result<(), RenderingError>
+    where
+        F: FnOnce(&cairo::Context) -> Result<(), RenderingError>,
+    {
+        let factor = self.supersampling;
+
+        if factor == 0 || factor > MAX_SUPERSAMPLING {
+            return Err(RenderingError::Rendering(format!(
+                "invalid supersampling factor {factor}"
+            )));
+        }
+
+        if factor == 1 && self.color_vision.is_none() {
+            return draw(cr);
+        }
+
+        cr.status()?;
+
+        let transform = Transform::from(cr.matrix());
+        let device_rect = IRect::from(transform.transform_rect(&Rect::from(*viewport)));
+        if device_rect.is_empty() {
+            return Ok(());
+        }
+
+        let (width, height) = device_rect.size();
+
+        let too_big = || RenderingError::Rendering("supersampled image is too big".to_string());
+        let surface_width = width.checked_mul(factor as i32).ok_or_else(too_big)?;
+        let surface_height = height.checked_mul(factor as i32).ok_or_else(too_big)?;
+
+        let surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, surface_width, surface_height)?;
+
+        {
+            let offscreen_cr = cairo::Context::new(&surface)?;
+            offscreen_cr.scale(f64::from(factor), f64::from(factor));
+            offscreen_cr.translate(-f64::from(device_rect.x0), -f64::from(device_rect.y0));
+            offscreen_cr.transform(cr.matrix());
+
+            draw(&offscreen_cr)?;
+        }
+
+        let surface = SharedImageSurface::wrap(surface, SurfaceType::SRgb)?;
+
+        let surface = if factor == 1 {
+            surface
+        } else {
+            let bounds = IRect::from_size(surface.width(), surface.height());
+            let linear = surface.to_linear_rgb(bounds)?;
+
+            let downscaled = match self.quality {
+                RenderingQuality::Compatible => linear.downsample(factor)?,
+                RenderingQuality::High => linear.resample(width, height)?,
+            };
+
+            downscaled.to_srgb(IRect::from_size(width, height))?
+        };
+
+        let surface = match self.color_vision {
+            Some(deficiency) => deficiency.simulate(&surface)?,
+            None => surface,
+        };
+
+        let surface = surface.into_image_surface()?;
+
+        cr.save()?;
+        cr.identity_matrix();
+        cr.set_source_surface(
+            &surface,
+            f64::from(device_rect.x0),
+            f64::from(device_rect.y0),
+        )?;
+        cr.paint()?;
+        cr.restore()?;
+
+        Ok(())
+    }
+
+    /// Calls `draw` with the transform from [`with_transform`] and the opacity from
+    /// [`with_opacity`] applied to `cr`.
+    ///
+    /// [`with_transform`]: #method.with_transform
+    /// [`with_opacity`]: #method.with_opacity
+    fn with_overrides<F>(
+        &self,
+        cr: &cairo::Context,
+        viewport: &cairo::Rectangle,
+        draw: F,
+    ) -> Result<(), RenderingError>
+    where
+        F: FnOnce(&cairo::Context) -> Result<(), RenderingError>,
+    {
+        if self.transform.is_none() && self.opacity == 1.0 {
+            return draw(cr);
+        }
+
+        cr.status()?;
+
+        let transform = if let Some(matrix) = self.transform {
+            let (cx, cy) = (
+                viewport.x() + viewport.width() / 2.0,
+                viewport.y() + viewport.height() / 2.0,
+            );
+
+            Transform::from(cr.matrix())
+                .pre_translate(cx, cy)
+                .pre_transform(&Transform::from(matrix))
+                .pre_translate(-cx, -cy)
+        } else {
+            Transform::from(cr.matrix())
+        };
+
+        let transform =
+            ValidTransform::try_from(transform).map_err(InternalRenderingError::from)?;
+
+        cr.save()?;
+        cr.set_matrix(transform.into());
+
+        let res = if self.opacity < 1.0 {
+            cr.push_group();
+            let res = draw(cr);
+            cr.pop_group_to_source()?;
+            cr.paint_with_alpha(self.opacity)?;
+            res
+        } else {
+            draw(cr)
+        };
+
+        cr.restore()?;
+
+        res
+    }
+
+    /// Fills the `viewport` with the matte, if there is one.
+    fn paint_background(
+        &self,
+        cr: &cairo::Context,
+        viewport: &cairo::Rectangle,
+    ) -> Result<(), RenderingError> {
+        if let Some(ref matte) = self.matte {
+            cr.status()?;
+
+            cr.save()?;
+            cr.new_path();
+            cr.rectangle(
+                viewport.x(),
+                viewport.y(),
+                viewport.width(),
+                viewport.height(),
+            );
+            cr.clip();
+            let res = matte.paint(cr);
+            cr.restore()?;
+
+            res?;
+        }
+
+        Ok(())
     }
 
     #[doc(hidden)]
     #[cfg(feature = "c-api")]
     pub fn dpi(&self) -> Dpi {
-        self.dpi
+        self.effective_dpi()
     }
 
     /// Normalizes the svg's width/height properties with a 0-sized viewport
@@ -813,13 +3038,13 @@ impl<'a> CairoRenderer<'a> {
     #[doc(hidden)]
     #[cfg(feature = "c-api")]
     pub fn width_height_to_user(&self, dpi: Dpi) -> (f64, f64) {
-        let dimensions = self.handle.document.get_intrinsic_dimensions();
+        let dimensions = self.handle.current_document().get_intrinsic_dimensions();
 
         let width = dimensions.width;
         let height = dimensions.height;
 
         let view_params = Viewport::new(dpi, 0.0, 0.0);
-        let root = self.handle.document.root();
+        let root = self.handle.current_document().root();
         let cascaded = CascadedValues::new_from_node(&root);
         let values = cascaded.get();
 
@@ -828,9 +3053,173 @@ impl<'a> CairoRenderer<'a> {
         (width.to_user(&params), height.to_user(&params))
     }
 
+    /// Returns the transform from the user space of the toplevel `<svg>` to a viewport of
+    /// `width` by `height`, or `None` if nothing would be rendered in it.
+    #[doc(hidden)]
+    #[cfg(feature = "c-api")]
+    pub fn toplevel_viewbox_transform(&self, width: f64, height: f64) -> Option<cairo::Matrix> {
+        self.handle
+            .current_document()
+            .toplevel_viewbox_transform(&Rect::from_size(width, height))
+            .map(cairo::Matrix::from)
+    }
+
     #[doc(hidden)]
     #[cfg(feature = "c-api")]
     pub fn test_mode(self, is_testing: bool) -> Self {
         CairoRenderer { is_testing, ..self }
     }
 }
+
+/// Renders an `SvgHandle` many times, reusing work between renders.
+///
+/// Rendering the same document repeatedly, for example at 16, 32, 64 and 128 pixels to
+/// generate icons, normally resolves the document's gradients and patterns and lays out
+/// all of its text once per render.  A `RenderPlan` keeps those results and reuses them in
+/// later renders.  (The CSS cascade is always done only once, when the document is loaded.)
+///
+/// Create a `RenderPlan` with [`CairoRenderer::prepare`]; it renders with the options of
+/// the renderer it was created from.
+///
+/// Text layouts are reused when the text is in the same coordinate system as in a previous
+/// render.  For documents with a `viewBox` this is the case at any size; otherwise text is
+/// laid out again for each new size.
+///
+/// ```
+/// let svg_handle = rsvg::Loader::new()
+///     .read_path("example.svg")
+///     .unwrap();
+///
+/// let plan = rsvg::CairoRenderer::new(&svg_handle).prepare();
+///
+/// for size in [16, 32, 64, 128] {
+///     let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, size, size)?;
+///     let cr = cairo::Context::new(&surface)?;
+///     let viewport = cairo::Rectangle::new(0.0, 0.0, f64::from(size), f64::from(size));
+///
+///     plan.render_document(&cr, &viewport)?;
+/// }
+/// # Ok::<(), rsvg::RenderingError>(())
+/// ```
+pub struct RenderPlan<'a> {
+    renderer: CairoRenderer<'a>,
+    render_cache: RenderCache,
+}
+
+impl<'a> RenderPlan<'a> {
+    /// Renders the whole SVG document fitted to a viewport.
+    ///
+    /// See [`CairoRenderer::render_document`] for details.
+    pub fn render_document(
+        &self,
+        cr: &cairo::Context,
+        viewport: &cairo::Rectangle,
+    ) -> Result<(), RenderingError> {
+        self.renderer
+            .render_document_with_cache(cr, viewport, Some(&self.render_cache))
+    }
+
+    /// Renders a single SVG element in the same place as for a whole SVG document.
+    ///
+    /// See [`CairoRenderer::render_layer`] for details.
+    pub fn render_layer(
+        &self,
+        cr: &cairo::Context,
+        id: Option<&str>,
+        viewport: &cairo::Rectangle,
+    ) -> Result<(), RenderingError> {
+        self.renderer
+            .render_layer_with_cache(cr, id, viewport, Some(&self.render_cache))
+    }
+
+    /// Renders a single SVG element to a given viewport.
+    ///
+    /// See [`CairoRenderer::render_element`] for details.
+    pub fn render_element(
+        &self,
+        cr: &cairo::Context,
+        id: Option<&str>,
+        element_viewport: &cairo::Rectangle,
+    ) -> Result<(), RenderingError> {
+        self.renderer
+            .render_element_with_cache(cr, id, element_viewport, Some(&self.render_cache))
+    }
+}
+
+/// Finishes a surface created with `for_stream()` and gives back its writer.
+fn finish_output_stream<W: Write + 'static>(surface: &cairo::Surface) -> Result<W, RenderingError> {
+    let stream = surface
+        .finish_output_stream()
+        .map_err(|e| RenderingError::Rendering(format!("could not write output: {}", e.error)))?;
+
+    Ok(*stream
+        .downcast::<W>()
+        .expect("surface stream has the writer's type"))
+}
+
+/// Capabilities of the librsvg library that is being used.
+///
+/// This is returned by [`features`].  Applications that can be linked against different
+/// builds or versions of librsvg can use it to adapt their user interface or processing
+/// pipelines at run-time, for example, to offer PDF output only if it is available.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryFeatures {
+    /// Version of librsvg, like `"2.57.0"`.
+    pub version: &'static str,
+
+    /// Whether the `filter` property and filter elements are supported.
+    pub filters: bool,
+
+    /// Whether text elements get rendered.
+    pub text: bool,
+
+    /// Whether SMIL animation elements like `<animate>` are supported.
+    pub animation: bool,
+
+    /// MIME types of the raster image formats that can be used in `<image>` elements.
+    pub image_formats: Vec<&'static str>,
+
+    /// Kinds of Cairo surfaces that librsvg can produce output for, like `"pdf"`.
+    pub backends: Vec<&'static str>,
+
+    /// Keywords for optional parts of the SVG and CSS specifications that are supported.
+    ///
+    /// These are `"svgz"` for gzip-compressed documents, `"xinclude"`,
+    /// `"filter-functions"` for CSS functions like `blur()` in the `filter` property,
+    /// `"paint-order"`, `"mix-blend-mode"`, `"context-paint"` for the
+    /// `context-fill` and `context-stroke` paints, and `"geometry-properties"` for
+    /// SVG2's `width`/`height`/`x`/`y` and friends as CSS properties.
+    pub capabilities: Vec<&'static str>,
+}
+
+/// Returns the capabilities of the librsvg library that is being used.
+///
+/// # Example:
+///
+/// ```
+/// let features = rsvg::features();
+///
+/// if features.image_formats.contains(&"image/webp") {
+///     println!("WebP images in SVG documents are supported");
+/// }
+/// ```
+pub fn features() -> LibraryFeatures {
+    LibraryFeatures {
+        version: env!("CARGO_PKG_VERSION"),
+        filters: true,
+        text: true,
+        animation: true,
+        image_formats: supported_image_formats().collect(),
+        backends: vec!["image", "pdf", "ps", "svg"],
+        capabilities: vec![
+            "svgz",
+            "xinclude",
+            "filter-functions",
+            "paint-order",
+            "mix-blend-mode",
+            "context-paint",
+            "geometry-properties",
+        ],
+    }
+}
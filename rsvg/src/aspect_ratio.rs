@@ -101,6 +101,11 @@ impl AspectRatio {
         }
     }
 
+    /// Whether this is `preserveAspectRatio="none"`, which allows non-uniform scaling.
+    pub fn is_none(&self) -> bool {
+        self.align.is_none()
+    }
+
     pub fn is_slice(&self) -> bool {
         matches!(
             self.align,
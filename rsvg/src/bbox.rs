@@ -3,22 +3,44 @@
 use crate::rect::Rect;
 use crate::transform::Transform;
 
+/// A pair of rectangles for the extents of an object, in a particular coordinate space.
+///
+/// The logical rectangle `rect` covers the object's geometry without stroke, like the
+/// `objectBoundingBox` in SVG.  The ink rectangle `ink_rect` covers everything that gets
+/// painted, including strokes and markers.  Either may be `None` if the object has no
+/// extents of that kind.
+///
+/// Both rectangles are in the coordinate space given by the bounding box's transform.
+/// When a bounding box is inserted into another one, or clipped by it, the rectangles get
+/// transformed into the destination's coordinate space first.
+///
+/// The public API wraps this in [`crate::geometry::BoundingBox`], which makes sure that
+/// the transform is invertible.
 #[derive(Debug, Default, Copy, Clone)]
 pub struct BoundingBox {
     transform: Transform,
-    pub rect: Option<Rect>,     // without stroke
-    pub ink_rect: Option<Rect>, // with stroke
+
+    /// Extents of the geometry, without stroke.
+    pub rect: Option<Rect>,
+
+    /// Extents of everything that gets painted, with stroke and markers.
+    pub ink_rect: Option<Rect>,
 }
 
 impl BoundingBox {
+    /// Creates an empty bounding box in the identity coordinate space.
     pub fn new() -> BoundingBox {
         Default::default()
     }
 
+    /// Returns the bounding box with its coordinate space changed to `transform`.
+    ///
+    /// This does not transform the rectangles; it says which space they are in.
     pub fn with_transform(self, transform: Transform) -> BoundingBox {
         BoundingBox { transform, ..self }
     }
 
+    /// Returns the bounding box with its logical rectangle set to `rect`.
     pub fn with_rect(self, rect: Rect) -> BoundingBox {
         BoundingBox {
             rect: Some(rect),
@@ -26,6 +48,7 @@ impl BoundingBox {
         }
     }
 
+    /// Returns the bounding box with its ink rectangle set to `ink_rect`.
     pub fn with_ink_rect(self, ink_rect: Rect) -> BoundingBox {
         BoundingBox {
             ink_rect: Some(ink_rect),
@@ -33,7 +56,13 @@ impl BoundingBox {
         }
     }
 
-    pub fn clear(mut self) {
+    /// The transform for the coordinate space of the rectangles.
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Removes both rectangles, keeping the coordinate space.
+    pub fn clear(&mut self) {
         self.rect = None;
         self.ink_rect = None;
     }
@@ -43,21 +72,45 @@ impl BoundingBox {
             return;
         }
 
-        // this will panic!() if it's not invertible... should we check on our own?
-        let transform = self
-            .transform
-            .invert()
-            .unwrap()
-            .pre_transform(&src.transform);
+        let transform = self.transform_from(src);
 
         self.rect = combine_rects(self.rect, src.rect, &transform, clip);
         self.ink_rect = combine_rects(self.ink_rect, src.ink_rect, &transform, clip);
     }
 
+    fn transform_from(&self, src: &BoundingBox) -> Transform {
+        // this will panic!() if it's not invertible... should we check on our own?
+        self.transform
+            .invert()
+            .unwrap()
+            .pre_transform(&src.transform)
+    }
+
+    /// Extends the bounding box to include `src`, like a group includes its children.
+    ///
+    /// Panics if this bounding box's transform is not invertible.
     pub fn insert(&mut self, src: &BoundingBox) {
         self.combine(src, false);
     }
 
+    /// Adds only the ink rectangle of `src`, for things like markers that are painted
+    /// as part of an element but don't contribute to its geometry.
+    pub fn insert_ink(&mut self, src: &BoundingBox) {
+        if src.ink_rect.is_none() {
+            return;
+        }
+
+        let transform = self.transform_from(src);
+        self.ink_rect = combine_rects(self.ink_rect, src.ink_rect, &transform, false);
+    }
+
+    /// Intersects the bounding box with `src`, like a clipping region does.
+    ///
+    /// If the rectangles don't intersect, the result is an empty rectangle at the origin,
+    /// so that the clipped object still counts as present but with no extents.  If this
+    /// bounding box does not have a rectangle yet, it takes the one from `src`.
+    ///
+    /// Panics if this bounding box's transform is not invertible.
     pub fn clip(&mut self, src: &BoundingBox) {
         self.combine(src, true);
     }
@@ -84,6 +137,8 @@ fn combine_rects(
 mod tests {
     use super::*;
 
+    use crate::angle::Angle;
+
     #[test]
     fn combine() {
         let r1 = Rect::new(1.0, 2.0, 3.0, 4.0);
@@ -118,4 +173,55 @@ mod tests {
         let res = combine_rects(Some(r1), Some(r2), &t, false);
         assert_eq!(res, Some(Rect::new(1.0, 2.0, 4.0, 5.0)));
     }
+
+    #[test]
+    fn insert_transforms_to_destination_space() {
+        let mut dest = BoundingBox::new().with_transform(Transform::new_scale(2.0, 2.0));
+
+        let src = BoundingBox::new()
+            .with_transform(Transform::new_translate(10.0, 0.0))
+            .with_rect(Rect::new(0.0, 0.0, 10.0, 10.0))
+            .with_ink_rect(Rect::new(-1.0, -1.0, 11.0, 11.0));
+
+        dest.insert(&src);
+        assert_eq!(dest.rect, Some(Rect::new(5.0, 0.0, 10.0, 5.0)));
+        assert_eq!(dest.ink_rect, Some(Rect::new(4.5, -0.5, 10.5, 5.5)));
+
+        // Rotated rectangles contribute their axis-aligned bounds
+        let mut dest = BoundingBox::new();
+        let src = BoundingBox::new()
+            .with_transform(Transform::new_rotate(Angle::from_degrees(90.0)))
+            .with_rect(Rect::new(0.0, 0.0, 10.0, 20.0));
+
+        dest.insert(&src);
+        assert!(dest
+            .rect
+            .unwrap()
+            .approx_eq(&Rect::new(-20.0, 0.0, 0.0, 10.0)));
+    }
+
+    #[test]
+    fn clip_intersects_or_empties() {
+        let mut bbox = BoundingBox::new().with_rect(Rect::new(0.0, 0.0, 10.0, 10.0));
+        bbox.clip(&BoundingBox::new().with_rect(Rect::new(5.0, 5.0, 20.0, 20.0)));
+        assert_eq!(bbox.rect, Some(Rect::new(5.0, 5.0, 10.0, 10.0)));
+
+        bbox.clip(&BoundingBox::new().with_rect(Rect::new(50.0, 50.0, 60.0, 60.0)));
+        assert_eq!(bbox.rect, Some(Rect::default()));
+    }
+
+    #[test]
+    fn insert_ink_leaves_logical_rect_alone() {
+        let mut bbox = BoundingBox::new()
+            .with_rect(Rect::new(0.0, 0.0, 10.0, 10.0))
+            .with_ink_rect(Rect::new(0.0, 0.0, 10.0, 10.0));
+
+        let marker = BoundingBox::new()
+            .with_rect(Rect::new(8.0, 8.0, 12.0, 12.0))
+            .with_ink_rect(Rect::new(8.0, 8.0, 12.0, 12.0));
+
+        bbox.insert_ink(&marker);
+        assert_eq!(bbox.rect, Some(Rect::new(0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(bbox.ink_rect, Some(Rect::new(0.0, 0.0, 12.0, 12.0)));
+    }
 }
@@ -0,0 +1,172 @@
+//! Auditing the contrast between text and what is rendered beneath it.
+//!
+//! The document gets rendered to an image without its text, and the position and fill of
+//! each run of text gets recorded instead.  The background of a run is then the average
+//! of the pixels beneath its bounding box.  Contrast ratios are computed as in
+//! [WCAG 2](https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio).
+
+use cssparser::RGBA;
+
+use crate::rect::IRect;
+use crate::surface_utils::{
+    iterators::Pixels, shared_surface::SharedImageSurface, srgb::linearize_f64,
+};
+
+/// Contrast between a run of text and the rendering beneath it.
+///
+/// Returned by [`crate::CairoRenderer::audit_text_contrast`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextContrast {
+    /// The `id` of the text element, or of its closest ancestor that has one.
+    pub element_id: Option<String>,
+
+    /// The characters in the run of text.
+    pub text: String,
+
+    /// Extents of the run of text, in the same coordinates as the viewport.
+    pub bounds: cairo::Rectangle,
+
+    /// Opaque color of the text, after compositing its fill over the background.
+    pub foreground: RGBA,
+
+    /// Average color beneath the text, composited over white.
+    pub background: RGBA,
+
+    /// Ratio of the relative luminances of the foreground and background, from 1 to 21.
+    ///
+    /// WCAG asks for at least 4.5 for normal text, and 3 for large text.
+    pub contrast_ratio: f64,
+}
+
+/// Averages the pixels of `surface` within `bounds`, composited over white.
+///
+/// Returns white if `bounds` is empty.
+pub fn average_background(surface: &SharedImageSurface, bounds: IRect) -> RGBA {
+    let mut sums = [0u64; 4];
+    let mut count = 0u64;
+
+    for (_x, _y, pixel) in Pixels::within(surface, bounds) {
+        sums[0] += u64::from(pixel.r);
+        sums[1] += u64::from(pixel.g);
+        sums[2] += u64::from(pixel.b);
+        sums[3] += u64::from(pixel.a);
+        count += 1;
+    }
+
+    if count == 0 {
+        return RGBA::new(Some(255), Some(255), Some(255), Some(1.0));
+    }
+
+    // The pixels are premultiplied, so compositing over white just adds the
+    // uncovered fraction of white.
+    let uncovered = 255 - sums[3] / count;
+    let channel = |sum: u64| (sum / count + uncovered).min(255) as u8;
+
+    RGBA::new(
+        Some(channel(sums[0])),
+        Some(channel(sums[1])),
+        Some(channel(sums[2])),
+        Some(1.0),
+    )
+}
+
+/// Composites `color` over an opaque `background`.
+pub fn composite_over(color: RGBA, background: RGBA) -> RGBA {
+    let alpha = f64::from(color.alpha.unwrap_or(0.0));
+
+    let channel = |c: Option<u8>, b: Option<u8>| {
+        let c = f64::from(c.unwrap_or(0));
+        let b = f64::from(b.unwrap_or(0));
+        (c * alpha + b * (1.0 - alpha)).round() as u8
+    };
+
+    RGBA::new(
+        Some(channel(color.red, background.red)),
+        Some(channel(color.green, background.green)),
+        Some(channel(color.blue, background.blue)),
+        Some(1.0),
+    )
+}
+
+/// Relative luminance of an sRGB color, ignoring its alpha.
+///
+/// <https://www.w3.org/TR/WCAG21/#dfn-relative-luminance>
+fn relative_luminance(color: RGBA) -> f64 {
+    let linear = |c: Option<u8>| linearize_f64(f64::from(c.unwrap_or(0)) / 255.0);
+
+    0.2126 * linear(color.red) + 0.7152 * linear(color.green) + 0.0722 * linear(color.blue)
+}
+
+/// Contrast ratio between two opaque colors, from 1 to 21.
+pub fn contrast_ratio(a: RGBA, b: RGBA) -> f64 {
+    let la = relative_luminance(a);
+    let lb = relative_luminance(b);
+
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::surface_utils::shared_surface::{ExclusiveImageSurface, SurfaceType};
+    use crate::surface_utils::{ImageSurfaceDataExt, Pixel};
+
+    fn rgb(r: u8, g: u8, b: u8) -> RGBA {
+        RGBA::new(Some(r), Some(g), Some(b), Some(1.0))
+    }
+
+    #[test]
+    fn black_on_white_is_21() {
+        assert!((contrast_ratio(rgb(0, 0, 0), rgb(255, 255, 255)) - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio(rgb(255, 255, 255), rgb(0, 0, 0)) - 21.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn same_colors_are_1() {
+        assert_eq!(contrast_ratio(rgb(12, 34, 56), rgb(12, 34, 56)), 1.0);
+    }
+
+    #[test]
+    fn gray_on_white() {
+        // A commonly quoted value: #767676 on white is just above 4.5.
+        let ratio = contrast_ratio(rgb(0x76, 0x76, 0x76), rgb(255, 255, 255));
+        assert!(ratio > 4.5 && ratio < 4.6);
+    }
+
+    #[test]
+    fn composites_translucent_color() {
+        let half_black = RGBA::new(Some(0), Some(0), Some(0), Some(0.5));
+        assert_eq!(
+            composite_over(half_black, rgb(255, 255, 255)),
+            rgb(128, 128, 128)
+        );
+    }
+
+    #[test]
+    fn averages_over_white() {
+        let mut surface = ExclusiveImageSurface::new(2, 1, SurfaceType::SRgb).unwrap();
+        surface.modify(&mut |data, stride| {
+            let opaque_black = Pixel {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            };
+            data.set_pixel(stride, opaque_black, 0, 0);
+        });
+        let surface = surface.share().unwrap();
+
+        // One black and one transparent pixel average to a gray.
+        assert_eq!(
+            average_background(&surface, IRect::from_size(2, 1)),
+            rgb(128, 128, 128)
+        );
+
+        // Just the transparent pixel is white.
+        assert_eq!(
+            average_background(&surface, IRect::new(1, 0, 2, 1)),
+            rgb(255, 255, 255)
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Estimating the cost of a rendering before doing it.
+//!
+//! The estimate walks the element tree like the renderer would, without drawing anything.
+//! It instances `<use>` elements, skips elements with `display: none`, only visits the
+//! contents of those with `display: contents`, and decides which elements need an
+//! offscreen layer with the same criteria as [`StackingContext`].
+//!
+//! Each offscreen layer is assumed to be as big as the viewport, which is what the
+//! renderer allocates for groups, and filters keep the result of each of their primitives
+//! until they finish.  The numbers are an upper bound for typical documents, not an exact
+//! prediction.
+
+use crate::document::{AcquiredNodes, Document};
+use crate::element::{Element, ElementData};
+use crate::filter::FilterValue;
+use crate::layout::StackingContext;
+use crate::node::{Node, NodeBorrow};
+use crate::session::Session;
+use crate::transform::Transform;
+
+/// Predicted cost of rendering a document.
+///
+/// Returned by [`crate::CairoRenderer::estimate_cost`].
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RenderCost {
+    /// Largest number of bytes of intermediate surfaces that are alive at the same time.
+    ///
+    /// This does not include the surface that the caller renders to.
+    pub surface_bytes: u64,
+
+    /// Number of drawing operations: shapes, text spans, images, filter primitives, and
+    /// compositing of offscreen layers.
+    pub operations: u64,
+}
+
+struct Estimator<'a> {
+    session: &'a Session,
+    acquired_nodes: AcquiredNodes<'a>,
+
+    /// Size in bytes of a surface as big as the viewport.
+    layer_bytes: u64,
+
+    /// Bytes of the surfaces that are alive at the current point of the walk.
+    live_bytes: u64,
+
+    cost: RenderCost,
+}
+
+/// Estimates the cost of rendering `document` to a viewport of the given size in pixels.
+pub fn estimate(
+    document: &Document,
+    session: &Session,
+    viewport: &cairo::Rectangle,
+    supersampling: u32,
+) -> RenderCost {
+    let scale = f64::from(supersampling.max(1));
+    let width = (viewport.width() * scale).ceil().max(0.0) as u64;
+    let height = (viewport.height() * scale).ceil().max(0.0) as u64;
+
+    let mut estimator = Estimator {
+        session,
+        acquired_nodes: AcquiredNodes::new(document),
+        layer_bytes: width.saturating_mul(height).saturating_mul(4),
+        live_bytes: 0,
+        cost: RenderCost::default(),
+    };
+
+    // Supersampled renderings go through a surface of the enlarged size.
+    if supersampling > 1 {
+        estimator.push_surfaces(1);
+        estimator.cost.operations += 1;
+    }
+
+    estimator.visit(&document.root());
+
+    estimator.cost
+}
+
+impl<'a> Estimator<'a> {
+    fn push_surfaces(&mut self, count: u64) {
+        self.live_bytes = self
+            .live_bytes
+            .saturating_add(self.layer_bytes.saturating_mul(count));
+        self.cost.surface_bytes = self.cost.surface_bytes.max(self.live_bytes);
+    }
+
+    fn pop_surfaces(&mut self, count: u64) {
+        self.live_bytes = self
+            .live_bytes
+            .saturating_sub(self.layer_bytes.saturating_mul(count));
+    }
+
+    fn add_operations(&mut self, count: u64) {
+        self.cost.operations = self.cost.operations.saturating_add(count);
+    }
+
+    fn visit(&mut self, node: &Node) {
+        if !node.is_element() {
+            return;
+        }
+
+        let element = node.borrow_element();
+        let values = element.get_computed_values();
+
+        if element.is_hidden_by_display(node) || !renders_itself(&element) {
+            return;
+        }
+
+        // With display: contents, only the element's contents get rendered.
+        if !values.is_displayed() {
+            self.visit_contents(node, &element);
+            return;
+        }
+
+        let stacking_ctx = StackingContext::new(
+            self.session,
+            &mut self.acquired_nodes,
+            &element,
+            Transform::identity(),
+            None,
+            values,
+        );
+
+        // An isolated layer, plus one for rendering the mask, plus the results of the
+        // filter primitives which are kept until the filter is done.
+        let mut surfaces = 0;
+
+        if stacking_ctx.should_isolate() {
+            surfaces += 1;
+            self.add_operations(1);
+        }
+
+        if stacking_ctx.mask.is_some() {
+            surfaces += 1;
+            self.add_operations(1);
+        }
+
+        if let Some(ref filter) = stacking_ctx.filter {
+            let primitives = filter
+                .filter_list
+                .iter()
+                .map(|value| self.count_primitives(value))
+                .sum::<u64>();
+
+            surfaces += primitives;
+            self.add_operations(primitives);
+        }
+
+        self.push_surfaces(surfaces);
+        self.visit_contents(node, &element);
+        self.pop_surfaces(surfaces);
+    }
+
+    fn visit_contents(&mut self, node: &Node, element: &Element) {
+        match element.element_data {
+            ElementData::Use(ref u) => {
+                if let Some(link) = u.link() {
+                    // Errors here are circular or too many references; the renderer
+                    // would stop at them as well.
+                    if let Ok(acquired) = self.acquired_nodes.acquire(link) {
+                        self.visit_use_target(acquired.get());
+                    }
+                }
+            }
+
+            ElementData::Circle(_)
+            | ElementData::Ellipse(_)
+            | ElementData::Image(_)
+            | ElementData::Line(_)
+            | ElementData::Path(_)
+            | ElementData::Polygon(_)
+            | ElementData::Polyline(_)
+            | ElementData::Rect(_) => self.add_operations(1),
+
+            ElementData::Text(_) | ElementData::TSpan(_) | ElementData::TRef(_) => {
+                self.add_operations(1);
+                self.visit_children(node);
+            }
+
+            _ => self.visit_children(node),
+        }
+    }
+
+    fn visit_children(&mut self, node: &Node) {
+        for child in node.children() {
+            self.visit(&child);
+        }
+    }
+
+    /// Visits the element referenced by a `<use>`.
+    ///
+    /// A `<symbol>` is not rendered by itself, but it is when it is instanced.
+    fn visit_use_target(&mut self, target: &Node) {
+        if target.is_element() && matches!(*target.borrow_element_data(), ElementData::Symbol(_)) {
+            self.visit_children(target);
+        } else {
+            self.visit(target);
+        }
+    }
+
+    /// Returns the number of primitives that one item in a `filter` property runs.
+    fn count_primitives(&mut self, value: &FilterValue) -> u64 {
+        match value {
+            FilterValue::Function(_) => 1,
+
+            FilterValue::Url(node_id) => match self.acquired_nodes.acquire(node_id) {
+                Ok(acquired) => acquired
+                    .get()
+                    .children()
+                    .filter(|child| child.is_element())
+                    .count() as u64,
+
+                Err(_) => 0,
+            },
+        }
+    }
+}
+
+/// Whether an element gets rendered when it is found in the tree, as opposed to only
+/// when something references it.
+fn renders_itself(element: &Element) -> bool {
+    use ElementData::*;
+
+    matches!(
+        element.element_data,
+        Circle(_)
+            | Ellipse(_)
+            | Group(_)
+            | Image(_)
+            | Line(_)
+            | Link(_)
+            | Path(_)
+            | Polygon(_)
+            | Polyline(_)
+            | Rect(_)
+            | Svg(_)
+            | Switch(_)
+            | Text(_)
+            | TRef(_)
+            | TSpan(_)
+            | Use(_)
+    )
+}
@@ -92,12 +92,13 @@ use std::cmp::Ordering;
 use std::fmt;
 use std::str;
 use std::str::FromStr;
+use url::Url;
 
 use crate::error::*;
 use crate::io::{self, BinaryData};
 use crate::node::{Node, NodeBorrow, NodeCascade};
 use crate::properties::{parse_value, ComputedValues, ParseAs, ParsedProperty};
-use crate::rsvg_log;
+use crate::rsvg_warn;
 use crate::session::Session;
 use crate::url_resolver::{AllowedUrl, UrlResolver};
 
@@ -182,6 +183,12 @@ impl<'i> RuleBodyItemParser<'i, RuleBodyItem, ValueErrorKind> for DeclParser {
 /// Struct to implement cssparser::QualifiedRuleParser and cssparser::AtRuleParser
 pub struct RuleParser {
     session: Session,
+
+    /// Base for relative `url()` references in property values.
+    ///
+    /// This is only set for stylesheets loaded from a URL; relative references in them
+    /// point to resources next to the stylesheet, not next to the SVG document.
+    base_url: Option<Url>,
 }
 
 /// Errors from the CSS parsing process
@@ -319,10 +326,20 @@ impl<'i> QualifiedRuleParser<'i> for RuleParser {
     ) -> Result<Self::QualifiedRule, cssparser::ParseError<'i, Self::Error>> {
         let declarations = RuleBodyParser::<_, _, Self::Error>::new(input, &mut DeclParser)
             .filter_map(|r| match r {
-                Ok(RuleBodyItem::Decl(decl)) => Some(decl),
+                Ok(RuleBodyItem::Decl(mut decl)) => {
+                    if let Some(ref base_url) = self.base_url {
+                        decl.property.make_urls_absolute(base_url);
+                    }
+                    Some(decl)
+                }
                 Ok(RuleBodyItem::Rule(_)) => None,
                 Err(e) => {
-                    rsvg_log!(self.session, "Invalid declaration; ignoring: {:?}", e);
+                    rsvg_warn!(
+                        self.session,
+                        strict,
+                        "Invalid declaration; ignoring: {:?}",
+                        e
+                    );
                     None
                 }
             })
@@ -797,10 +814,57 @@ pub enum Origin {
     Author,
 }
 
+impl Origin {
+    /// Precedence of a declaration from this origin during the cascade.
+    ///
+    /// Per CSS2.2, normal declarations go in the order user agent, user, author;
+    /// `!important` declarations come after all of those, but with the order of the origins
+    /// reversed, so that user agent and user important declarations have the last word.
+    fn precedence(self, important: bool) -> u8 {
+        match (important, self) {
+            (false, Origin::UserAgent) => 0,
+            (false, Origin::User) => 1,
+            (false, Origin::Author) => 2,
+            (true, Origin::Author) => 3,
+            (true, Origin::User) => 4,
+            (true, Origin::UserAgent) => 5,
+        }
+    }
+}
+
 /// A parsed CSS stylesheet.
 pub struct Stylesheet {
     origin: Origin,
     qualified_rules: Vec<QualifiedRule>,
+
+    /// URLs that were fetched to build this stylesheet, from `@import` rules or the
+    /// stylesheet's own `href`.
+    loaded_urls: Vec<AllowedUrl>,
+}
+
+/// Where a [`Match`] came from.
+///
+/// Declarations from an element's `style` attribute have a higher specificity than any
+/// selector, so they sort after all of the stylesheets' matches with the same precedence.
+#[derive(Copy, Clone, Eq, Ord, PartialEq, PartialOrd)]
+enum MatchSource {
+    Selector(u32),
+    StyleAttribute,
+}
+
+/// A stylesheet whose rules only apply to an element and its descendants.
+///
+/// This is used for `<style scoped>`, and for `<style>` elements inside a `<symbol>`, so
+/// that reusable content does not leak its selectors into the rest of the document.
+pub struct ScopedStylesheet {
+    pub scope: Node,
+    pub stylesheet: Stylesheet,
+}
+
+impl ScopedStylesheet {
+    fn applies_to(&self, node: &Node) -> bool {
+        node.ancestors().any(|n| n == self.scope)
+    }
 }
 
 /// A match during the selector matching process
@@ -810,18 +874,24 @@ pub struct Stylesheet {
 /// certain `specificity`.  The stylesheet's `origin` is also given here.
 ///
 /// This type implements [`Ord`] so a list of `Match` can be sorted.
-/// That implementation does ordering based on origin and specificity
+/// That implementation does ordering based on origin, importance and specificity
 /// as per <https://www.w3.org/TR/CSS22/cascade.html#cascading-order>.
 struct Match<'a> {
-    specificity: u32,
+    source: MatchSource,
     origin: Origin,
     declaration: &'a Declaration,
 }
 
+impl<'a> Match<'a> {
+    fn precedence(&self) -> u8 {
+        self.origin.precedence(self.declaration.important)
+    }
+}
+
 impl<'a> Ord for Match<'a> {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.origin.cmp(&other.origin) {
-            Ordering::Equal => self.specificity.cmp(&other.specificity),
+        match self.precedence().cmp(&other.precedence()) {
+            Ordering::Equal => self.source.cmp(&other.source),
             o => o,
         }
     }
@@ -835,7 +905,7 @@ impl<'a> PartialOrd for Match<'a> {
 
 impl<'a> PartialEq for Match<'a> {
     fn eq(&self, other: &Self) -> bool {
-        self.origin == other.origin && self.specificity == other.specificity
+        self.precedence() == other.precedence() && self.source == other.source
     }
 }
 
@@ -846,9 +916,16 @@ impl Stylesheet {
         Stylesheet {
             origin,
             qualified_rules: Vec::new(),
+            loaded_urls: Vec::new(),
         }
     }
 
+    /// Returns the URLs that were fetched to build this stylesheet, in the order in which
+    /// they were loaded.
+    pub fn loaded_urls(&self) -> &[AllowedUrl] {
+        &self.loaded_urls
+    }
+
     /// Parses a new stylesheet from CSS data in a string.
     ///
     /// The `url_resolver_url` is required for `@import` rules, so that librsvg can determine if
@@ -860,18 +937,22 @@ impl Stylesheet {
         session: Session,
     ) -> Result<Self, LoadingError> {
         let mut stylesheet = Stylesheet::empty(origin);
-        stylesheet.add_rules_from_string(buf, url_resolver, session)?;
+        stylesheet.add_rules_from_string(buf, url_resolver, None, session)?;
         Ok(stylesheet)
     }
 
     /// Parses a new stylesheet by loading CSS data from a URL.
+    ///
+    /// The `url_resolver` is the one that allowed `aurl`; its resource loader, if any, is
+    /// used to fetch the stylesheet and the ones it imports.
     pub fn from_href(
         aurl: &AllowedUrl,
+        url_resolver: &UrlResolver,
         origin: Origin,
         session: Session,
     ) -> Result<Self, LoadingError> {
         let mut stylesheet = Stylesheet::empty(origin);
-        stylesheet.load(aurl, session)?;
+        stylesheet.load(aurl, url_resolver, session)?;
         Ok(stylesheet)
     }
 
@@ -882,23 +963,28 @@ impl Stylesheet {
     ///
     /// If there is an `@import` rule, its rules will be recursively added into the
     /// stylesheet, in the order in which they appear.
+    ///
+    /// If `base_url` is given, relative `url()` references in property values get resolved
+    /// against it.
     fn add_rules_from_string(
         &mut self,
         buf: &str,
         url_resolver: &UrlResolver,
+        base_url: Option<Url>,
         session: Session,
     ) -> Result<(), LoadingError> {
         let mut input = ParserInput::new(buf);
         let mut parser = Parser::new(&mut input);
         let mut rule_parser = RuleParser {
             session: session.clone(),
+            base_url,
         };
 
         StyleSheetParser::new(&mut parser, &mut rule_parser)
             .filter_map(|r| match r {
                 Ok(rule) => Some(rule),
                 Err(e) => {
-                    rsvg_log!(session, "Invalid rule; ignoring: {:?}", e);
+                    rsvg_warn!(session, "Invalid rule; ignoring: {:?}", e);
                     None
                 }
             })
@@ -906,11 +992,11 @@ impl Stylesheet {
                 Rule::AtRule(AtRule::Import(url)) => match url_resolver.resolve_href(&url) {
                     Ok(aurl) => {
                         // ignore invalid imports
-                        let _ = self.load(&aurl, session.clone());
+                        let _ = self.load(&aurl, url_resolver, session.clone());
                     }
 
                     Err(e) => {
-                        rsvg_log!(session, "Not loading stylesheet from \"{}\": {}", url, e);
+                        rsvg_warn!(session, "Not loading stylesheet from \"{}\": {}", url, e);
                     }
                 },
 
@@ -921,8 +1007,15 @@ impl Stylesheet {
     }
 
     /// Parses a stylesheet referenced by an URL
-    fn load(&mut self, aurl: &AllowedUrl, session: Session) -> Result<(), LoadingError> {
-        io::acquire_data(aurl, None)
+    fn load(
+        &mut self,
+        aurl: &AllowedUrl,
+        url_resolver: &UrlResolver,
+        session: Session,
+    ) -> Result<(), LoadingError> {
+        self.loaded_urls.push(aurl.clone());
+
+        io::acquire_data(aurl, url_resolver.resource_loader(), None)
             .map_err(LoadingError::from)
             .and_then(|data| {
                 let BinaryData {
@@ -933,13 +1026,13 @@ impl Stylesheet {
                 if is_text_css(&mime_type) {
                     Ok(bytes)
                 } else {
-                    rsvg_log!(session, "\"{}\" is not of type text/css; ignoring", aurl);
+                    rsvg_warn!(session, "\"{}\" is not of type text/css; ignoring", aurl);
                     Err(LoadingError::BadCss)
                 }
             })
             .and_then(|bytes| {
                 String::from_utf8(bytes).map_err(|_| {
-                    rsvg_log!(
+                    rsvg_warn!(
                         session,
                         "\"{}\" does not contain valid UTF-8 CSS data; ignoring",
                         aurl
@@ -948,8 +1041,9 @@ impl Stylesheet {
                 })
             })
             .and_then(|utf8| {
-                let url = (**aurl).clone();
-                self.add_rules_from_string(&utf8, &UrlResolver::new(Some(url)), session)
+                let mut url_resolver = url_resolver.clone();
+                url_resolver.base_url = Some((**aurl).clone());
+                self.add_rules_from_string(&utf8, &url_resolver, Some((**aurl).clone()), session)
             })
     }
 
@@ -975,7 +1069,7 @@ impl Stylesheet {
                     for decl in rule.declarations.iter() {
                         acc.push(Match {
                             declaration: decl,
-                            specificity: selector.specificity(),
+                            source: MatchSource::Selector(selector.specificity()),
                             origin: self.origin,
                         });
                     }
@@ -989,11 +1083,78 @@ fn is_text_css(mime_type: &Mime) -> bool {
     mime_type.type_ == "text" && mime_type.subtype == "css"
 }
 
+/// Parses a list of declarations like the ones in a `style` attribute.
+///
+/// Invalid declarations are logged and ignored.
+pub fn parse_declarations(declarations: &str, session: &Session) -> Vec<Declaration> {
+    let mut input = ParserInput::new(declarations);
+    let mut parser = Parser::new(&mut input);
+
+    RuleBodyParser::new(&mut parser, &mut DeclParser)
+        .filter_map(|r| match r {
+            Ok(RuleBodyItem::Decl(decl)) => Some(decl),
+            Ok(RuleBodyItem::Rule(_)) => None,
+            Err(e) => {
+                rsvg_warn!(session, strict, "Invalid declaration; ignoring: {:?}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Returns the elements in the subtree of `root`, including itself, that match a selector
+/// list like `.icon, #logo`, in document order.
+///
+/// Returns an error if `selectors` cannot be parsed.
+pub fn select_all(
+    root: &Node,
+    selectors: &str,
+    session: &Session,
+) -> Result<Vec<Node>, LoadingError> {
+    let rule_parser = RuleParser {
+        session: session.clone(),
+        base_url: None,
+    };
+
+    let mut input = ParserInput::new(selectors);
+    let mut parser = Parser::new(&mut input);
+
+    let selector_list = parser
+        .parse_entirely(|p| SelectorList::parse(&rule_parser, p, ParseRelative::No))
+        .map_err(|_| LoadingError::BadCss)?;
+
+    let mut cache = NthIndexCache::default();
+    let mut match_ctx = MatchingContext::new(
+        MatchingMode::Normal,
+        None,
+        &mut cache,
+        QuirksMode::NoQuirks,
+        NeedsSelectorFlags::No,
+        IgnoreNthChildForInvalidation::No,
+    );
+
+    Ok(root
+        .descendants()
+        .filter(|n| n.is_element())
+        .filter(|n| {
+            selectors::matching::matches_selector_list(
+                &selector_list,
+                &RsvgElement(n.clone()),
+                &mut match_ctx,
+            )
+        })
+        .collect())
+}
+
 /// Runs the CSS cascade on the specified tree from all the stylesheets
+///
+/// Scoped stylesheets have the author origin; their matches are gathered after the ones
+/// from the document-wide author stylesheets, so they win for equal specificity.
 pub fn cascade(
     root: &mut Node,
     ua_stylesheets: &[Stylesheet],
     author_stylesheets: &[Stylesheet],
+    scoped_stylesheets: &[ScopedStylesheet],
     user_stylesheets: &[Stylesheet],
     session: &Session,
 ) {
@@ -1020,11 +1181,29 @@ pub fn cascade(
         for s in ua_stylesheets
             .iter()
             .chain(author_stylesheets)
+            .chain(
+                scoped_stylesheets
+                    .iter()
+                    .filter(|s| s.applies_to(&node))
+                    .map(|s| &s.stylesheet),
+            )
             .chain(user_stylesheets)
         {
             s.get_matches(&node, &mut match_ctx, &mut matches);
         }
 
+        let style_declarations = node.borrow_element().get_style_declarations(session);
+
+        for decl in &style_declarations {
+            matches.push(Match {
+                declaration: decl,
+                source: MatchSource::StyleAttribute,
+                origin: Origin::Author,
+            });
+        }
+
+        // This is a stable sort, so matches with the same precedence and specificity
+        // remain in document order.
         matches.as_mut_slice().sort();
 
         let mut element = node.borrow_element_mut();
@@ -1032,8 +1211,6 @@ pub fn cascade(
         for m in matches {
             element.apply_style_declaration(m.declaration, m.origin);
         }
-
-        element.set_style_attribute(session);
     }
 
     let values = ComputedValues::default();
@@ -1047,6 +1224,17 @@ mod tests {
 
     use crate::document::Document;
     use crate::is_element_of_type;
+    use crate::parsers::Parse;
+    use crate::property_defs::Opacity;
+
+    fn opacity_of(document: &Document, id: &str) -> Opacity {
+        document
+            .lookup_internal_node(id)
+            .unwrap()
+            .borrow_element()
+            .get_computed_values()
+            .opacity()
+    }
 
     #[test]
     fn xml_lang() {
@@ -1158,4 +1346,128 @@ mod tests {
         assert!(d.is_empty());
         assert!(!a.is_empty());
     }
+
+    #[test]
+    fn important_and_style_attribute_cascade() {
+        let document = Document::load_from_bytes(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+  <style>
+    #a { opacity: 0.1; }
+    #b { opacity: 0.2 !important; }
+    #c { opacity: 0.3 !important; }
+    rect { opacity: 0.4 !important; }
+  </style>
+  <rect id="a" style="opacity: 0.5"/>
+  <rect id="b" style="opacity: 0.6"/>
+  <rect id="c" style="opacity: 0.7 !important"/>
+  <circle id="d" opacity="0.8" style="opacity: 0.9"/>
+</svg>
+"#,
+        );
+
+        // style attribute beats a normal declaration from a stylesheet
+        assert_eq!(
+            opacity_of(&document, "a"),
+            Opacity::parse_str("0.5").unwrap()
+        );
+
+        // !important in a stylesheet beats a normal declaration in a style attribute
+        assert_eq!(
+            opacity_of(&document, "b"),
+            Opacity::parse_str("0.2").unwrap()
+        );
+
+        // !important in a style attribute beats !important from a stylesheet, even if
+        // there is a less specific !important rule after it
+        assert_eq!(
+            opacity_of(&document, "c"),
+            Opacity::parse_str("0.7").unwrap()
+        );
+
+        // style attribute beats presentation attribute
+        assert_eq!(
+            opacity_of(&document, "d"),
+            Opacity::parse_str("0.9").unwrap()
+        );
+    }
+
+    #[test]
+    fn user_stylesheet_cascade() {
+        let mut document = Document::load_from_bytes(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+  <style>
+    #a { opacity: 0.1; }
+    #b { opacity: 0.2 !important; }
+  </style>
+  <rect id="a"/>
+  <rect id="b"/>
+  <rect id="c" style="opacity: 0.3 !important"/>
+  <rect id="d" style="opacity: 0.4"/>
+</svg>
+"#,
+        );
+
+        let session = Session::new_for_test_suite();
+        let user = Stylesheet::from_data(
+            "#a, #b, #d { opacity: 0.5; } #c { opacity: 0.6 !important; }",
+            &UrlResolver::new(None),
+            Origin::User,
+            session.clone(),
+        )
+        .unwrap();
+
+        document.cascade(&[user], &session);
+
+        // normal author declarations beat normal user declarations
+        assert_eq!(
+            opacity_of(&document, "a"),
+            Opacity::parse_str("0.1").unwrap()
+        );
+        assert_eq!(
+            opacity_of(&document, "b"),
+            Opacity::parse_str("0.2").unwrap()
+        );
+        assert_eq!(
+            opacity_of(&document, "d"),
+            Opacity::parse_str("0.4").unwrap()
+        );
+
+        // !important user declarations beat everything from the author, including the
+        // style attribute
+        assert_eq!(
+            opacity_of(&document, "c"),
+            Opacity::parse_str("0.6").unwrap()
+        );
+    }
+
+    #[test]
+    fn scoped_stylesheets_do_not_leak() {
+        let document = Document::load_from_bytes(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+  <symbol id="sym">
+    <style>rect { opacity: 0.5; }</style>
+    <rect id="a"/>
+  </symbol>
+  <g id="g">
+    <style scoped="scoped">rect { opacity: 0.6; }</style>
+    <rect id="b"/>
+  </g>
+  <rect id="c"/>
+</svg>
+"#,
+        );
+
+        assert_eq!(
+            opacity_of(&document, "a"),
+            Opacity::parse_str("0.5").unwrap()
+        );
+        assert_eq!(
+            opacity_of(&document, "b"),
+            Opacity::parse_str("0.6").unwrap()
+        );
+        assert_eq!(opacity_of(&document, "c"), Opacity::default());
+    }
 }
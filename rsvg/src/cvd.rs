@@ -0,0 +1,170 @@
+//! Simulation of color vision deficiencies on rendered images.
+//!
+//! The simulation uses the matrices from Machado, Oliveira and Fernandes, "A
+//! Physiologically-based Model for Simulation of Color Vision Deficiency" (IEEE TVCG,
+//! 2009), for the most severe form of each deficiency.  The matrices operate on linear
+//! RGB, so each pixel gets linearized in floating point before applying them.
+
+use crate::rect::IRect;
+use crate::surface_utils::{
+    iterators::Pixels,
+    shared_surface::{ExclusiveImageSurface, SharedImageSurface, SurfaceType},
+    srgb::{linearize_f64, unlinearize_f64},
+    ImageSurfaceDataExt, Pixel,
+};
+
+/// A kind of color blindness to simulate.
+///
+/// See [`crate::CairoRenderer::with_color_vision_simulation`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColorVisionDeficiency {
+    /// Missing long-wavelength (red) cones.
+    Protanopia,
+
+    /// Missing medium-wavelength (green) cones.
+    Deuteranopia,
+
+    /// Missing short-wavelength (blue) cones.
+    Tritanopia,
+}
+
+impl ColorVisionDeficiency {
+    #[rustfmt::skip]
+    fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            ColorVisionDeficiency::Protanopia => [
+                [ 0.152286,  1.052583, -0.204868],
+                [ 0.114503,  0.786281,  0.099216],
+                [-0.003882, -0.048116,  1.051998],
+            ],
+
+            ColorVisionDeficiency::Deuteranopia => [
+                [ 0.367322,  0.860646, -0.227968],
+                [ 0.280085,  0.672501,  0.047413],
+                [-0.011820,  0.042940,  0.968881],
+            ],
+
+            ColorVisionDeficiency::Tritanopia => [
+                [ 1.255528, -0.076749, -0.178779],
+                [-0.078411,  0.930809,  0.147602],
+                [ 0.004733,  0.691367,  0.303900],
+            ],
+        }
+    }
+
+    /// Returns a copy of an sRGB `surface` as seen with this deficiency.
+    ///
+    /// Each pixel is unpremultiplied and converted to linear RGB in floating point, so
+    /// that dark colors don't lose precision as they would in an 8-bit linear surface.
+    pub fn simulate(
+        self,
+        surface: &SharedImageSurface,
+    ) -> Result<SharedImageSurface, cairo::Error> {
+        assert_eq!(surface.surface_type(), SurfaceType::SRgb);
+
+        let m = self.matrix();
+        let bounds = IRect::from_size(surface.width(), surface.height());
+
+        let mut output =
+            ExclusiveImageSurface::new(surface.width(), surface.height(), SurfaceType::SRgb)?;
+
+        output.modify(&mut |data, stride| {
+            for (x, y, pixel) in Pixels::within(surface, bounds) {
+                if pixel.a == 0 {
+                    data.set_pixel(stride, pixel, x, y);
+                    continue;
+                }
+
+                let alpha = f64::from(pixel.a);
+                let linear = |c: u8| linearize_f64((f64::from(c) / alpha).min(1.0));
+                let rgb = [linear(pixel.r), linear(pixel.g), linear(pixel.b)];
+
+                let channel = |row: [f64; 3]| {
+                    let v = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+                    (unlinearize_f64(v.clamp(0.0, 1.0)) * alpha + 0.5) as u8
+                };
+
+                let output_pixel = Pixel {
+                    r: channel(m[0]),
+                    g: channel(m[1]),
+                    b: channel(m[2]),
+                    a: pixel.a,
+                };
+
+                data.set_pixel(stride, output_pixel, x, y);
+            }
+        });
+
+        output.share()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulate_pixel(cvd: ColorVisionDeficiency, pixel: Pixel) -> Pixel {
+        let mut surface = ExclusiveImageSurface::new(1, 1, SurfaceType::SRgb).unwrap();
+        surface.modify(&mut |data, stride| data.set_pixel(stride, pixel, 0, 0));
+
+        let result = cvd.simulate(&surface.share().unwrap()).unwrap();
+        result.get_pixel(0, 0)
+    }
+
+    #[test]
+    fn preserves_grays() {
+        for cvd in [
+            ColorVisionDeficiency::Protanopia,
+            ColorVisionDeficiency::Deuteranopia,
+            ColorVisionDeficiency::Tritanopia,
+        ] {
+            for v in [0, 3, 128, 255] {
+                let gray = Pixel {
+                    r: v,
+                    g: v,
+                    b: v,
+                    a: 255,
+                };
+
+                let result = simulate_pixel(cvd, gray);
+                assert!(result.r.abs_diff(v) <= 1);
+                assert!(result.g.abs_diff(v) <= 1);
+                assert!(result.b.abs_diff(v) <= 1);
+                assert_eq!(result.a, 255);
+            }
+        }
+    }
+
+    #[test]
+    fn protanopia_confuses_red_and_green() {
+        let red = simulate_pixel(
+            ColorVisionDeficiency::Protanopia,
+            Pixel {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+        );
+
+        // Pure red loses much of its red component, and turns about as green as it is red.
+        assert!(red.r < 128);
+        assert!(red.g > 64);
+    }
+
+    #[test]
+    fn keeps_premultiplied_pixels_valid() {
+        let result = simulate_pixel(
+            ColorVisionDeficiency::Tritanopia,
+            Pixel {
+                r: 0,
+                g: 100,
+                b: 0,
+                a: 100,
+            },
+        );
+
+        assert_eq!(result.a, 100);
+        assert!(result.r <= 100 && result.g <= 100 && result.b <= 100);
+    }
+}
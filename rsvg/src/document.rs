@@ -1,36 +1,58 @@
 //! Main SVG document structure.
 
+use cssparser::{Parser, ParserInput, Token};
 use data_url::mime::Mime;
 use glib::prelude::*;
-use markup5ever::QualName;
+use markup5ever::{local_name, namespace_url, ns, QualName};
 use once_cell::sync::Lazy;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::include_str;
 use std::io::Cursor;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use url::Url;
 
 use crate::accept_language::UserLanguage;
+use crate::animation;
 use crate::bbox::BoundingBox;
 use crate::borrow_element_as;
-use crate::css::{self, Origin, Stylesheet};
+use crate::contrast::{self, TextContrast};
+use crate::css::{self, Origin, ScopedStylesheet, Stylesheet};
 use crate::dpi::Dpi;
-use crate::drawing_ctx::{draw_tree, with_saved_cr, DrawingMode, SvgNesting};
-use crate::error::{AcquireError, InternalRenderingError, LoadingError, NodeIdError};
+use crate::drawing_ctx::{
+    audit_text_contrast, draw_paint_server, draw_tree, element_at_point, measure_elements,
+    with_saved_cr, DrawingMode, RenderingQuality, SpotColor, StaticRasters, SvgNesting,
+};
+use crate::error::{
+    AcquireError, ImplementationLimit, InternalRenderingError, LoadingError, NodeIdError,
+    ParseError, ReferenceChain,
+};
+use crate::filter::FilterValue;
+use crate::fonts::DocumentFonts;
+use crate::href::is_href;
 use crate::io::{self, BinaryData};
 use crate::is_element_of_type;
-use crate::limits;
+use crate::limits::LoadLimits;
+use crate::log::{LoadWarning, Location};
 use crate::node::{CascadedValues, Node, NodeBorrow, NodeData};
-use crate::rect::Rect;
+use crate::paint_server::PaintServer;
+use crate::properties::Filter;
+use crate::rect::{IRect, Rect};
+use crate::render_cache::RenderCache;
+use crate::rsvg_log;
 use crate::session::Session;
 use crate::structure::IntrinsicDimensions;
-use crate::surface_utils::shared_surface::SharedImageSurface;
+use crate::surface_utils::shared_surface::{SharedImageSurface, SurfaceType};
+use crate::transform::ValidTransform;
 use crate::url_resolver::{AllowedUrl, UrlResolver};
-use crate::xml::{xml_load_from_possibly_compressed_stream, Attributes};
+use crate::xml::{
+    xml_load_from_bytes, xml_load_from_possibly_compressed_stream, Attributes, XmlPushLoader,
+};
 
 static UA_STYLESHEETS: Lazy<Vec<Stylesheet>> = Lazy::new(|| {
     vec![Stylesheet::from_data(
@@ -67,6 +89,16 @@ impl NodeId {
             _ => Err(NodeIdError::NodeIdRequired),
         }
     }
+
+    /// Turns the URL of an external reference into an absolute one by resolving it
+    /// against `base_url`.
+    pub fn make_absolute(&mut self, base_url: &Url) {
+        if let NodeId::External(ref mut url, _) = *self {
+            if let Ok(joined) = base_url.join(url) {
+                *url = joined.into();
+            }
+        }
+    }
 }
 
 impl fmt::Display for NodeId {
@@ -78,6 +110,32 @@ impl fmt::Display for NodeId {
     }
 }
 
+/// Subsystems that can be turned off through [`crate::Loader`].
+///
+/// Thumbnailers and previewers can disable the expensive or risky parts of rendering
+/// to get a degraded but faster render.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Features {
+    /// Whether the `filter` property has any effect.
+    pub filters: bool,
+
+    /// Whether `<image>` elements can load files other than `data:` URLs.
+    pub external_images: bool,
+
+    /// Whether text elements get laid out and drawn.
+    pub text: bool,
+}
+
+impl Default for Features {
+    fn default() -> Features {
+        Features {
+            filters: true,
+            external_images: true,
+            text: true,
+        }
+    }
+}
+
 /// Loading options for SVG documents.
 pub struct LoadOptions {
     /// Load url resolver; all references will be resolved with respect to this.
@@ -88,6 +146,15 @@ pub struct LoadOptions {
 
     /// Whether to keep original (undecoded) image data to embed in Cairo PDF surfaces.
     pub keep_image_data: bool,
+
+    /// Limits on the size of the document and the resources it references.
+    pub limits: LoadLimits,
+
+    /// Whether invalid values make loading fail instead of being ignored.
+    pub strict: bool,
+
+    /// Which subsystems are enabled for rendering.
+    pub features: Features,
 }
 
 impl LoadOptions {
@@ -97,6 +164,9 @@ impl LoadOptions {
             url_resolver,
             unlimited_size: false,
             keep_image_data: false,
+            limits: LoadLimits::default(),
+            strict: false,
+            features: Features::default(),
         }
     }
 
@@ -118,6 +188,24 @@ impl LoadOptions {
         self
     }
 
+    /// Sets the limits for loading the document and its resources.
+    pub fn with_limits(mut self, limits: LoadLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets whether invalid values make loading fail; see [`crate::Loader::strict`].
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets which subsystems are enabled for rendering.
+    pub fn with_features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
     /// Creates a new `LoadOptions` with a different `url resolver`.
     ///
     /// This is used when loading a referenced file that may in turn cause other files
@@ -130,6 +218,9 @@ impl LoadOptions {
             url_resolver,
             unlimited_size: self.unlimited_size,
             keep_image_data: self.keep_image_data,
+            limits: self.limits.clone(),
+            strict: self.strict,
+            features: self.features.clone(),
         }
     }
 }
@@ -156,7 +247,44 @@ pub struct Document {
     load_options: Arc<LoadOptions>,
 
     /// Stylesheets defined in the document.
-    stylesheets: Vec<Stylesheet>,
+    stylesheets: Rc<Vec<Stylesheet>>,
+
+    /// Stylesheets that only apply to a subtree of the document.
+    scoped_stylesheets: Rc<Vec<ScopedStylesheet>>,
+
+    /// Cached rasters of the elements that are marked as static.
+    static_rasters: RefCell<StaticRasters>,
+
+    /// Font files registered with the document, to use in addition to the system's fonts.
+    fonts: DocumentFonts,
+
+    /// Font options for text, which override librsvg's defaults.
+    font_options: Option<cairo::FontOptions>,
+
+    /// Time in seconds from the last call to [`Document::set_animation_time`].
+    animation_time: f64,
+
+    /// Events dispatched to the document's elements, for event-based animations.
+    animation_events: Vec<animation::Event>,
+
+    /// Whether [`Document::set_animation_time`] has been called; until then, elements
+    /// have their non-animated attributes.
+    animation_sampled: bool,
+
+    /// Identifier of this document, to know if it was the last one to apply its styles to
+    /// a tree that is shared with other documents.
+    id: usize,
+
+    /// The `id` of the document that last applied its animated attributes and styles to
+    /// `tree`.  This is shared among the documents created with [`Document::share_tree`].
+    tree_owner: Rc<Cell<usize>>,
+}
+
+/// Returns a new identifier for [`Document::id`].
+fn next_document_id() -> usize {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
 }
 
 impl Document {
@@ -176,6 +304,29 @@ impl Document {
         )
     }
 
+    /// Loads a document from a byte slice, parsing it in place if it is not compressed.
+    pub fn load_from_data(
+        session: Session,
+        load_options: Arc<LoadOptions>,
+        bytes: &[u8],
+    ) -> Result<Document, LoadingError> {
+        xml_load_from_bytes(
+            session.clone(),
+            DocumentBuilder::new(session, load_options.clone()),
+            load_options,
+            bytes,
+        )
+    }
+
+    /// Starts loading a document from data that will be supplied in chunks.
+    pub fn load_incrementally(session: Session, load_options: Arc<LoadOptions>) -> XmlPushLoader {
+        XmlPushLoader::new(
+            session.clone(),
+            DocumentBuilder::new(session, load_options.clone()),
+            load_options,
+        )
+    }
+
     /// Utility function to load a document from a static string in tests.
     #[cfg(test)]
     pub fn load_from_bytes(input: &'static [u8]) -> Document {
@@ -213,14 +364,40 @@ impl Document {
         self.ids.get(id).map(|n| (*n).clone())
     }
 
-    /// Loads an image by URL, or returns a pre-loaded one.
-    fn lookup_image(&self, url: &str) -> Result<SharedImageSurface, LoadingError> {
+    /// Which subsystems are enabled for rendering this document.
+    pub fn features(&self) -> &Features {
+        &self.load_options.features
+    }
+
+    /// The resolver that decides which URLs this document may load.
+    pub fn url_resolver(&self) -> &UrlResolver {
+        &self.load_options.url_resolver
+    }
+
+    /// Resolves the URL of an image, checking that it is allowed to be loaded.
+    fn resolve_image_url(&self, url: &str) -> Result<AllowedUrl, LoadingError> {
         let aurl = self
             .load_options
             .url_resolver
             .resolve_href(url)
             .map_err(|_| LoadingError::BadUrl)?;
 
+        if !self.load_options.features.external_images && aurl.scheme() != "data" {
+            rsvg_log!(
+                self.session,
+                "not loading {} since external images are disabled",
+                aurl
+            );
+            return Err(LoadingError::BadUrl);
+        }
+
+        Ok(aurl)
+    }
+
+    /// Loads an image by URL, or returns a pre-loaded one.
+    fn lookup_image(&self, url: &str) -> Result<SharedImageSurface, LoadingError> {
+        let aurl = self.resolve_image_url(url)?;
+
         self.resources
             .borrow_mut()
             .lookup_image(&self.session, &self.load_options, &aurl)
@@ -228,11 +405,7 @@ impl Document {
 
     /// Loads a resource by URL, or returns a pre-loaded one.
     fn lookup_resource(&self, url: &str) -> Result<Resource, LoadingError> {
-        let aurl = self
-            .load_options
-            .url_resolver
-            .resolve_href(url)
-            .map_err(|_| LoadingError::BadUrl)?;
+        let aurl = self.resolve_image_url(url)?;
 
         // FIXME: pass a cancellable to this.  This function is called
         // at rendering time, so probably the cancellable should come
@@ -247,13 +420,259 @@ impl Document {
     /// This uses the default UserAgent stylesheet, the document's internal stylesheets,
     /// plus an extra set of stylesheets supplied by the caller.
     pub fn cascade(&mut self, extra: &[Stylesheet], session: &Session) {
+        if self.tree_owner.get() != self.id {
+            self.apply_animated_attributes(session);
+        }
+
+        self.cascade_styles(extra, session);
+
+        // The styles may have changed, so the cached rasters are stale.
+        self.static_rasters.get_mut().clear();
+    }
+
+    /// Creates a document that shares this one's tree of nodes and stylesheets, but has
+    /// its own resources, cached rasters, and fonts.
+    ///
+    /// The computed values live in the nodes, so only one of the documents that share
+    /// a tree can have its styles applied at a time.  Call [`Document::make_current`]
+    /// before using the computed values.
+    pub fn share_tree(&self) -> Document {
+        Document {
+            tree: self.tree.clone(),
+            session: self.session.clone(),
+            ids: self.ids.clone(),
+            resources: RefCell::new(Resources::new()),
+            load_options: self.load_options.clone(),
+            stylesheets: self.stylesheets.clone(),
+            scoped_stylesheets: self.scoped_stylesheets.clone(),
+            static_rasters: RefCell::new(StaticRasters::default()),
+            fonts: DocumentFonts::default(),
+            font_options: self.font_options.clone(),
+            animation_time: self.animation_time,
+            animation_events: self.animation_events.clone(),
+            animation_sampled: self.animation_sampled,
+            id: next_document_id(),
+            tree_owner: self.tree_owner.clone(),
+        }
+    }
+
+    /// Applies this document's animated attributes and styles to the tree again, if
+    /// another document that shares the tree changed them.
+    ///
+    /// The `extra` stylesheets must be the same ones that were last passed to
+    /// [`Document::cascade`].
+    pub fn make_current(&self, extra: &[Stylesheet], session: &Session) {
+        if self.tree_owner.get() != self.id {
+            self.apply_animated_attributes(session);
+            self.cascade_styles(extra, session);
+        }
+    }
+
+    fn cascade_styles(&self, extra: &[Stylesheet], session: &Session) {
+        let mut root = self.tree.clone();
+
         css::cascade(
-            &mut self.tree,
+            &mut root,
             &UA_STYLESHEETS,
             &self.stylesheets,
+            &self.scoped_stylesheets,
             extra,
             session,
         );
+
+        self.tree_owner.set(self.id);
+    }
+
+    /// Sets the animated attributes to their values at the current animation time, or
+    /// resets them if the animations have not been sampled yet.
+    fn apply_animated_attributes(&self, session: &Session) {
+        for mut animated in animation::sample(self, self.animation_time, &self.animation_events) {
+            let values: &[_] = if self.animation_sampled {
+                &animated.values
+            } else {
+                &[]
+            };
+
+            animated
+                .target
+                .borrow_element_mut()
+                .set_animated_attributes(session, values);
+        }
+    }
+
+    /// Registers a font file for the document's text.
+    ///
+    /// See [`crate::SvgHandle::add_font_file`] for details.
+    pub fn add_font_file(&mut self, path: &std::path::Path) -> Result<(), LoadingError> {
+        self.fonts.add_file(path)?;
+
+        // Text may be drawn with a different font now, so the cached rasters are stale.
+        self.static_rasters.get_mut().clear();
+        Ok(())
+    }
+
+    /// Registers a font for the document's text from the contents of a font file.
+    ///
+    /// See [`crate::SvgHandle::add_font_data`] for details.
+    pub fn add_font_data(&mut self, data: &[u8]) -> Result<(), LoadingError> {
+        self.fonts.add_data(data)?;
+
+        self.static_rasters.get_mut().clear();
+        Ok(())
+    }
+
+    /// Sets the font options to use for text.
+    ///
+    /// See [`crate::SvgHandle::set_font_options`] for details.
+    pub fn set_font_options(&mut self, options: Option<&cairo::FontOptions>) {
+        self.font_options = options.cloned();
+
+        self.static_rasters.get_mut().clear();
+    }
+
+    /// Sets the animated attributes to their values at `time` seconds, and runs the CSS
+    /// cascade again.
+    ///
+    /// The `extra` stylesheets are the same as for [`Document::cascade`].
+    pub fn set_animation_time(&mut self, time: f64, extra: &[Stylesheet], session: &Session) {
+        self.animation_time = time;
+        self.animation_sampled = true;
+
+        self.apply_animated_attributes(session);
+        self.cascade_styles(extra, session);
+
+        self.static_rasters.get_mut().clear();
+    }
+
+    /// Records an event on `target` at the current animation time, and samples the
+    /// animations again so that the ones that wait for it begin.
+    ///
+    /// See [`crate::SvgHandle::dispatch_event`] for details.
+    pub fn dispatch_event(
+        &mut self,
+        target: &Node,
+        kind: animation::EventKind,
+        extra: &[Stylesheet],
+        session: &Session,
+    ) {
+        self.animation_events.push(animation::Event {
+            target: target.clone(),
+            kind,
+            time: self.animation_time,
+        });
+
+        self.set_animation_time(self.animation_time, extra, session);
+    }
+
+    /// Returns the URLs of the external resources that the document references.
+    ///
+    /// See [`crate::SvgHandle::external_references`] for details.
+    pub fn external_references(&self) -> Vec<String> {
+        let mut urls = Vec::new();
+
+        let stylesheets = self
+            .stylesheets
+            .iter()
+            .chain(self.scoped_stylesheets.iter().map(|s| &s.stylesheet));
+
+        for stylesheet in stylesheets {
+            urls.extend(stylesheet.loaded_urls().iter().cloned());
+        }
+
+        for node in self.tree.descendants().filter(|n| n.is_element()) {
+            let element = node.borrow_element();
+
+            if !is_element_of_type!(node, Link) {
+                for (attr, value) in element.get_attributes().iter() {
+                    if is_href(&attr.expanded()) {
+                        // Strip the fragment from "file.svg#id"; a plain "#id" is internal.
+                        let url = value.split('#').next().unwrap();
+                        if !url.is_empty() {
+                            urls.extend(self.load_options.url_resolver.resolve_href(url).ok());
+                        }
+                    }
+                }
+            }
+
+            let values = element.get_computed_values();
+
+            let mut node_ids = vec![
+                values.clip_path().0.get().cloned(),
+                values.mask().0.get().cloned(),
+                values.marker_start().0.get().cloned(),
+                values.marker_mid().0.get().cloned(),
+                values.marker_end().0.get().cloned(),
+            ];
+
+            for paint in [values.fill().0, values.stroke().0] {
+                if let PaintServer::Iri { iri, .. } = paint {
+                    node_ids.push(Some(*iri));
+                }
+            }
+
+            if let Filter::List(filters) = values.filter() {
+                node_ids.extend(filters.iter().map(|f| match f {
+                    FilterValue::Url(node_id) => Some(node_id.clone()),
+                    FilterValue::Function(_) => None,
+                }));
+            }
+
+            for node_id in node_ids.into_iter().flatten() {
+                if let NodeId::External(url, _) = node_id {
+                    urls.extend(self.load_options.url_resolver.resolve_href(&url).ok());
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+
+        urls.into_iter()
+            .filter(|aurl| aurl.scheme() != "data")
+            .map(|aurl| aurl.to_string())
+            .filter(|url| seen.insert(url.clone()))
+            .collect()
+    }
+
+    /// Returns the font families that the document's text asks for.
+    ///
+    /// See [`crate::SvgHandle::font_families`] for details.
+    pub fn font_families(&self) -> Vec<String> {
+        let mut families: Vec<String> = Vec::new();
+
+        for node in self.root().descendants() {
+            let text_node = if node.is_chars() {
+                if node.borrow_chars().get_string().trim().is_empty() {
+                    continue;
+                }
+
+                match node.parent() {
+                    Some(p) if is_element_of_type!(p, Text) || is_element_of_type!(p, TSpan) => p,
+                    _ => continue,
+                }
+            } else if node.is_element() && is_element_of_type!(node, TRef) {
+                node
+            } else {
+                continue;
+            };
+
+            let elt = text_node.borrow_element();
+            let font_family = elt.get_computed_values().font_family();
+
+            for family in font_family.as_str().split(',') {
+                if !families.iter().any(|f| f == family) {
+                    families.push(family.to_string());
+                }
+            }
+        }
+
+        families
+    }
+
+    /// Marks or unmarks an element as static, so that its rendering gets cached.
+    ///
+    /// See [`StaticRasters`] for details.
+    pub fn set_static(&self, node: &Node, is_static: bool) {
+        self.static_rasters.borrow_mut().set_static(node, is_static);
     }
 
     pub fn get_intrinsic_dimensions(&self) -> IntrinsicDimensions {
@@ -263,6 +682,21 @@ impl Document {
         borrow_element_as!(self.root(), Svg).get_intrinsic_dimensions(values)
     }
 
+    /// Returns the transform from the user space of the toplevel `<svg>` to `viewport`,
+    /// as given by its `viewBox` and `preserveAspectRatio`.
+    ///
+    /// Returns `None` if nothing would be rendered, because the viewport or the `viewBox`
+    /// is empty.
+    pub fn toplevel_viewbox_transform(&self, viewport: &Rect) -> Option<ValidTransform> {
+        let root = self.root();
+        let svg = borrow_element_as!(root, Svg);
+
+        svg.get_preserve_aspect_ratio()
+            .viewport_to_viewbox_transform(svg.get_viewbox(), viewport)
+            .ok()
+            .flatten()
+    }
+
     pub fn render_document(
         &self,
         session: &Session,
@@ -271,6 +705,12 @@ impl Document {
         user_language: &UserLanguage,
         dpi: Dpi,
         svg_nesting: SvgNesting,
+        quality: RenderingQuality,
+        path_simplification: Option<f64>,
+        error_placeholders: bool,
+        render_cache: Option<&RenderCache>,
+        spot_colors: &[SpotColor],
+        linear_light: bool,
         is_testing: bool,
     ) -> Result<(), InternalRenderingError> {
         let root = self.root();
@@ -282,6 +722,12 @@ impl Document {
             user_language,
             dpi,
             svg_nesting,
+            quality,
+            path_simplification,
+            error_placeholders,
+            render_cache,
+            spot_colors,
+            linear_light,
             is_testing,
         )
     }
@@ -295,6 +741,12 @@ impl Document {
         user_language: &UserLanguage,
         dpi: Dpi,
         svg_nesting: SvgNesting,
+        quality: RenderingQuality,
+        path_simplification: Option<f64>,
+        error_placeholders: bool,
+        render_cache: Option<&RenderCache>,
+        spot_colors: &[SpotColor],
+        linear_light: bool,
         is_testing: bool,
     ) -> Result<(), InternalRenderingError> {
         cr.status()?;
@@ -312,9 +764,15 @@ impl Document {
                 user_language,
                 dpi,
                 svg_nesting,
+                quality,
+                path_simplification,
+                error_placeholders,
                 false,
                 is_testing,
-                &mut AcquiredNodes::new(self),
+                &mut AcquiredNodes::new(self)
+                    .with_render_cache(render_cache)
+                    .with_spot_colors(spot_colors)
+                    .with_linear_light(linear_light),
             )
             .map(|_bbox| ())
         })
@@ -331,8 +789,7 @@ impl Document {
     ) -> Result<(Rect, Rect), InternalRenderingError> {
         let root = self.root();
 
-        let target = cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1)?;
-        let cr = cairo::Context::new(&target)?;
+        let cr = measuring_context()?;
 
         let bbox = draw_tree(
             session.clone(),
@@ -342,6 +799,9 @@ impl Document {
             user_language,
             dpi,
             SvgNesting::Standalone,
+            RenderingQuality::default(),
+            None,
+            false,
             true,
             is_testing,
             &mut AcquiredNodes::new(self),
@@ -373,6 +833,179 @@ impl Document {
         ))
     }
 
+    /// Returns (ink_rect, logical_rect) for each of the `nodes`, measured with a single
+    /// pass over the tree.
+    pub fn get_geometries_for_layers(
+        &self,
+        session: &Session,
+        nodes: &[Node],
+        viewport: &cairo::Rectangle,
+        user_language: &UserLanguage,
+        dpi: Dpi,
+        is_testing: bool,
+    ) -> Result<Vec<(cairo::Rectangle, cairo::Rectangle)>, InternalRenderingError> {
+        let cr = measuring_context()?;
+
+        let bboxes = measure_elements(
+            session.clone(),
+            self.root(),
+            nodes,
+            &cr,
+            Rect::from(*viewport),
+            user_language,
+            dpi,
+            is_testing,
+            &mut AcquiredNodes::new(self),
+        )?;
+
+        Ok(bboxes
+            .into_iter()
+            .map(|bbox| {
+                (
+                    cairo::Rectangle::from(bbox.ink_rect.unwrap_or_default()),
+                    cairo::Rectangle::from(bbox.rect.unwrap_or_default()),
+                )
+            })
+            .collect())
+    }
+
+    /// Returns the topmost element painted at `(x, y)`, in the coordinates of the `viewport`.
+    pub fn get_element_at_point(
+        &self,
+        session: &Session,
+        viewport: &cairo::Rectangle,
+        x: f64,
+        y: f64,
+        user_language: &UserLanguage,
+        dpi: Dpi,
+        is_testing: bool,
+    ) -> Result<Option<Node>, InternalRenderingError> {
+        let cr = measuring_context()?;
+
+        element_at_point(
+            session.clone(),
+            self.root(),
+            &cr,
+            Rect::from(*viewport),
+            (x, y),
+            user_language,
+            dpi,
+            is_testing,
+            &mut AcquiredNodes::new(self),
+        )
+    }
+
+    /// Computes the contrast of each run of text against what is rendered beneath it,
+    /// when the document is rendered to the `viewport`.
+    pub fn get_text_contrast(
+        &self,
+        session: &Session,
+        viewport: &cairo::Rectangle,
+        user_language: &UserLanguage,
+        dpi: Dpi,
+        is_testing: bool,
+    ) -> Result<Vec<TextContrast>, InternalRenderingError> {
+        let width = viewport.width().ceil();
+        let height = viewport.height().ceil();
+
+        if !(width >= 1.0 && height >= 1.0) {
+            return Ok(Vec::new());
+        }
+
+        // Cairo fails with sizes that are too big, including the saturated ones.
+        let surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, width as i32, height as i32)?;
+
+        let runs = {
+            // The surface starts at the viewport's origin.
+            let cr = cairo::Context::new(&surface)?;
+            cr.translate(-viewport.x(), -viewport.y());
+
+            audit_text_contrast(
+                session.clone(),
+                self.root(),
+                &cr,
+                Rect::from(*viewport),
+                user_language,
+                dpi,
+                is_testing,
+                &mut AcquiredNodes::new(self),
+            )?
+        };
+
+        let surface = SharedImageSurface::wrap(surface, SurfaceType::SRgb)?;
+        let surface_rect = IRect::from_size(surface.width(), surface.height());
+
+        Ok(runs
+            .into_iter()
+            .map(|run| {
+                let bounds = IRect::from(run.device_rect)
+                    .intersection(&surface_rect)
+                    .unwrap_or_default();
+
+                let background = contrast::average_background(&surface, bounds);
+                let foreground = contrast::composite_over(run.fill, background);
+
+                let element_id = run.node.and_then(|node| {
+                    node.ancestors()
+                        .filter(|n| n.is_element())
+                        .find_map(|n| n.borrow_element().get_id().map(String::from))
+                });
+
+                TextContrast {
+                    element_id,
+                    text: run.text,
+                    bounds: cairo::Rectangle::from(
+                        run.device_rect.translate((viewport.x(), viewport.y())),
+                    ),
+                    foreground,
+                    background,
+                    contrast_ratio: contrast::contrast_ratio(foreground, background),
+                }
+            })
+            .collect())
+    }
+
+    /// Paints the gradient or pattern in `node` over a `width` by `height` rectangle.
+    pub fn render_paint_server(
+        &self,
+        session: &Session,
+        cr: &cairo::Context,
+        node: &Node,
+        iri: &NodeId,
+        width: f64,
+        height: f64,
+        user_language: &UserLanguage,
+        dpi: Dpi,
+        is_testing: bool,
+    ) -> Result<(), InternalRenderingError> {
+        cr.status()?;
+
+        let is_paint_server = is_element_of_type!(node, LinearGradient)
+            || is_element_of_type!(node, RadialGradient)
+            || is_element_of_type!(node, Pattern);
+
+        if !is_paint_server {
+            return Err(InternalRenderingError::InvalidId(format!(
+                "{iri} is not a gradient or pattern"
+            )));
+        }
+
+        with_saved_cr(cr, || {
+            draw_paint_server(
+                session.clone(),
+                cr,
+                node,
+                iri,
+                (width, height),
+                user_language,
+                dpi,
+                is_testing,
+                &mut AcquiredNodes::new(self),
+            )
+        })
+    }
+
     fn get_bbox_for_element(
         &self,
         session: &Session,
@@ -381,8 +1014,18 @@ impl Document {
         dpi: Dpi,
         is_testing: bool,
     ) -> Result<BoundingBox, InternalRenderingError> {
-        let target = cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1)?;
-        let cr = cairo::Context::new(&target)?;
+        // The element is drawn by itself, but it does not get rendered at all if one of its
+        // ancestors has display: none.
+        let is_hidden = node
+            .ancestors()
+            .skip(1)
+            .any(|a| a.is_element() && a.borrow_element().is_hidden_by_display(&a));
+
+        if is_hidden {
+            return Ok(BoundingBox::new());
+        }
+
+        let cr = measuring_context()?;
 
         let node = node.clone();
 
@@ -394,6 +1037,9 @@ impl Document {
             user_language,
             dpi,
             SvgNesting::Standalone,
+            RenderingQuality::default(),
+            None,
+            false,
             true,
             is_testing,
             &mut AcquiredNodes::new(self),
@@ -431,6 +1077,12 @@ impl Document {
         element_viewport: &cairo::Rectangle,
         user_language: &UserLanguage,
         dpi: Dpi,
+        quality: RenderingQuality,
+        path_simplification: Option<f64>,
+        error_placeholders: bool,
+        render_cache: Option<&RenderCache>,
+        spot_colors: &[SpotColor],
+        linear_light: bool,
         is_testing: bool,
     ) -> Result<(), InternalRenderingError> {
         cr.status()?;
@@ -466,9 +1118,15 @@ impl Document {
                 user_language,
                 dpi,
                 SvgNesting::Standalone,
+                quality,
+                path_simplification,
+                error_placeholders,
                 false,
                 is_testing,
-                &mut AcquiredNodes::new(self),
+                &mut AcquiredNodes::new(self)
+                    .with_render_cache(render_cache)
+                    .with_spot_colors(spot_colors)
+                    .with_linear_light(linear_light),
             )
             .map(|_bbox| ())
         })
@@ -479,6 +1137,22 @@ fn unit_rectangle() -> Rect {
     Rect::from_size(1.0, 1.0)
 }
 
+/// Creates a cairo context for geometry queries.
+///
+/// Nothing gets painted while measuring, so all the measuring contexts in a thread share a
+/// single tiny target surface instead of creating one for each query.
+fn measuring_context() -> Result<cairo::Context, InternalRenderingError> {
+    thread_local! {
+        static MEASURING_TARGET: Option<cairo::ImageSurface> =
+            cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1).ok();
+    }
+
+    MEASURING_TARGET.with(|target| match target {
+        Some(target) => Ok(cairo::Context::new(target)?),
+        None => Err(InternalRenderingError::from(cairo::Error::NoMemory)),
+    })
+}
+
 /// Any kind of resource loaded while processing an SVG document: images, or SVGs themselves.
 #[derive(Clone)]
 pub enum Resource {
@@ -577,7 +1251,8 @@ fn load_resource(
     aurl: &AllowedUrl,
     cancellable: Option<&gio::Cancellable>,
 ) -> Result<Resource, LoadingError> {
-    let data = io::acquire_data(aurl, cancellable)?;
+    let resource_loader = load_options.url_resolver.resource_loader();
+    let data = io::acquire_data(aurl, resource_loader, cancellable)?;
 
     let svg_mime_type = Mime::from_str("image/svg+xml").unwrap();
 
@@ -632,16 +1307,25 @@ fn load_image_resource_from_bytes(
     load_image_with_image_rs(aurl, bytes, content_type, load_options)
 }
 
+/// Raster image formats that can be used in `<image>` elements, by MIME type.
+const IMAGE_FORMATS: &[(&str, image::ImageFormat)] = &[
+    ("image/png", image::ImageFormat::Png),
+    ("image/jpeg", image::ImageFormat::Jpeg),
+    ("image/gif", image::ImageFormat::Gif),
+    ("image/webp", image::ImageFormat::WebP),
+];
+
+/// Returns the MIME types of the raster image formats that can be loaded.
+pub fn supported_image_formats() -> impl Iterator<Item = &'static str> {
+    IMAGE_FORMATS.iter().map(|&(mime_type, _)| mime_type)
+}
+
 fn image_format(content_type: &str) -> Result<image::ImageFormat, LoadingError> {
-    match content_type {
-        "image/png" => Ok(image::ImageFormat::Png),
-        "image/jpeg" => Ok(image::ImageFormat::Jpeg),
-        "image/gif" => Ok(image::ImageFormat::Gif),
-        "image/webp" => Ok(image::ImageFormat::WebP),
-        _ => Err(LoadingError::Other(format!(
-            "unsupported image format {content_type}"
-        ))),
-    }
+    IMAGE_FORMATS
+        .iter()
+        .find(|&&(mime_type, _)| mime_type == content_type)
+        .map(|&(_, format)| format)
+        .ok_or_else(|| LoadingError::Other(format!("unsupported image format {content_type}")))
 }
 
 fn load_image_with_image_rs(
@@ -650,18 +1334,34 @@ fn load_image_with_image_rs(
     content_type: Option<String>,
     load_options: &LoadOptions,
 ) -> Result<Resource, LoadingError> {
-    let cursor = Cursor::new(&bytes);
+    let make_reader = || -> Result<image::io::Reader<Cursor<&Vec<u8>>>, LoadingError> {
+        let cursor = Cursor::new(&bytes);
 
-    let reader = if let Some(ref content_type) = content_type {
-        let format = image_format(content_type)?;
-        image::io::Reader::with_format(cursor, format)
-    } else {
-        image::io::Reader::new(cursor)
-            .with_guessed_format()
-            .map_err(|_| LoadingError::Other(String::from("unknown image format")))?
+        if let Some(ref content_type) = content_type {
+            let format = image_format(content_type)?;
+            Ok(image::io::Reader::with_format(cursor, format))
+        } else {
+            image::io::Reader::new(cursor)
+                .with_guessed_format()
+                .map_err(|_| LoadingError::Other(String::from("unknown image format")))
+        }
     };
 
-    let image = reader
+    if let Some(max_pixels) = load_options.limits.max_image_pixels {
+        // Only the image's header needs to be read to find its dimensions, so check
+        // them before decoding the whole image.
+        let (width, height) = make_reader()?
+            .into_dimensions()
+            .map_err(|e| LoadingError::Other(format!("error decoding image: {e}")))?;
+
+        if u64::from(width) * u64::from(height) > max_pixels {
+            return Err(LoadingError::LimitExceeded(
+                ImplementationLimit::TooManyImagePixels,
+            ));
+        }
+    }
+
+    let image = make_reader()?
         .decode()
         .map_err(|e| LoadingError::Other(format!("error decoding image: {e}")))?;
 
@@ -767,6 +1467,9 @@ pub struct AcquiredNodes<'i> {
     document: &'i Document,
     num_elements_acquired: usize,
     node_stack: Rc<RefCell<NodeStack>>,
+    render_cache: Option<&'i RenderCache>,
+    spot_colors: &'i [SpotColor],
+    linear_light: bool,
 }
 
 impl<'i> AcquiredNodes<'i> {
@@ -775,13 +1478,74 @@ impl<'i> AcquiredNodes<'i> {
             document,
             num_elements_acquired: 0,
             node_stack: Rc::new(RefCell::new(NodeStack::new())),
+            render_cache: None,
+            spot_colors: &[],
+            linear_light: false,
+        }
+    }
+
+    /// Uses a cache to reuse resolution results from previous renders of the document.
+    pub fn with_render_cache(self, render_cache: Option<&'i RenderCache>) -> Self {
+        AcquiredNodes {
+            render_cache,
+            ..self
+        }
+    }
+
+    pub fn render_cache(&self) -> Option<&'i RenderCache> {
+        self.render_cache
+    }
+
+    /// Simulates overprinting for the given spot inks.
+    pub fn with_spot_colors(self, spot_colors: &'i [SpotColor]) -> Self {
+        AcquiredNodes {
+            spot_colors,
+            ..self
+        }
+    }
+
+    /// Spot inks for which to simulate overprinting; empty if this is not a preview.
+    pub fn spot_colors(&self) -> &'i [SpotColor] {
+        self.spot_colors
+    }
+
+    /// Scales images and composites groups in linear light.
+    pub fn with_linear_light(self, linear_light: bool) -> Self {
+        AcquiredNodes {
+            linear_light,
+            ..self
         }
     }
 
+    /// Whether to scale images and composite groups in linear light instead of in sRGB.
+    pub fn linear_light(&self) -> bool {
+        self.linear_light
+    }
+
     pub fn lookup_image(&self, href: &str) -> Result<SharedImageSurface, LoadingError> {
         self.document.lookup_image(href)
     }
 
+    /// Which subsystems are enabled for rendering.
+    pub fn features(&self) -> &'i Features {
+        self.document.features()
+    }
+
+    /// Cached rasters of the document's static elements.
+    pub fn static_rasters(&self) -> &'i RefCell<StaticRasters> {
+        &self.document.static_rasters
+    }
+
+    /// Font map with the fonts registered with the document, if there are any.
+    pub fn font_map(&self) -> Option<&'i pangocairo::FontMap> {
+        self.document.fonts.font_map()
+    }
+
+    /// Font options that the document's user set for text, if any.
+    pub fn font_options(&self) -> Option<&'i cairo::FontOptions> {
+        self.document.font_options.as_ref()
+    }
+
     pub fn lookup_resource(&self, url: &str) -> Result<Resource, LoadingError> {
         self.document.lookup_resource(url)
     }
@@ -795,8 +1559,9 @@ impl<'i> AcquiredNodes<'i> {
 
         // This is a mitigation for SVG files that try to instance a huge number of
         // elements via <use>, recursive patterns, etc.  See limits.rs for details.
-        if self.num_elements_acquired > limits::MAX_REFERENCED_ELEMENTS {
-            return Err(AcquireError::MaxReferencesExceeded);
+        let max = self.document.load_options.limits.max_referenced_elements;
+        if self.num_elements_acquired > max {
+            return Err(AcquireError::MaxReferencesExceeded(max));
         }
 
         // FIXME: callers shouldn't have to know that get_node() can initiate a file load.
@@ -831,15 +1596,53 @@ impl<'i> AcquiredNodes<'i> {
     /// its child elements that reference other paint servers will be able to detect circular
     /// references to the pattern.
     pub fn acquire_ref(&self, node: &Node) -> Result<AcquiredNode, AcquireError> {
-        if self.node_stack.borrow().contains(node) {
-            Err(AcquireError::CircularReference(node.clone()))
-        } else {
-            self.node_stack.borrow_mut().push(node);
-            Ok(AcquiredNode {
-                stack: Some(self.node_stack.clone()),
-                node: node.clone(),
-            })
+        self.acquire_ref_with_chain(node, None)
+    }
+
+    /// Acquires a node as the next step in a chain of references of the specified kind.
+    ///
+    /// This is like [`Self::acquire_ref`], but it also checks that the chain does not get
+    /// longer than [`Self::max_reference_chain`].  For example, each `<use>` element
+    /// acquires itself with [`ReferenceChain::Use`] before drawing the element it references.
+    pub fn acquire_in_chain(
+        &self,
+        node: &Node,
+        chain: ReferenceChain,
+    ) -> Result<AcquiredNode, AcquireError> {
+        self.acquire_ref_with_chain(node, Some(chain))
+    }
+
+    fn acquire_ref_with_chain(
+        &self,
+        node: &Node,
+        chain: Option<ReferenceChain>,
+    ) -> Result<AcquiredNode, AcquireError> {
+        let mut stack = self.node_stack.borrow_mut();
+
+        if stack.contains(node) {
+            return Err(AcquireError::CircularReference(node.clone()));
         }
+
+        if let Some(chain) = chain {
+            let mut nodes = stack.chain(chain);
+
+            if nodes.len() >= self.max_reference_chain() {
+                nodes.push(node.clone());
+                return Err(AcquireError::ReferenceChainTooLong(chain, nodes));
+            }
+        }
+
+        stack.push_with_chain(node, chain);
+
+        Ok(AcquiredNode {
+            stack: Some(self.node_stack.clone()),
+            node: node.clone(),
+        })
+    }
+
+    /// Maximum length of each chain of references; see [`ReferenceChain`].
+    pub fn max_reference_chain(&self) -> usize {
+        self.document.load_options.limits.max_reference_chain
     }
 }
 
@@ -848,7 +1651,10 @@ impl<'i> AcquiredNodes<'i> {
 /// Sometimes parts of the code cannot plainly use the implicit stack of acquired
 /// nodes as maintained by DrawingCtx::acquire_node(), and they must keep their
 /// own stack of nodes to test for reference cycles.  NodeStack can be used to do that.
-pub struct NodeStack(Vec<Node>);
+///
+/// Nodes may be pushed as part of a [`ReferenceChain`], to keep track of the length of the
+/// chain.
+pub struct NodeStack(Vec<(Node, Option<ReferenceChain>)>);
 
 impl NodeStack {
     pub fn new() -> NodeStack {
@@ -856,15 +1662,37 @@ impl NodeStack {
     }
 
     pub fn push(&mut self, node: &Node) {
-        self.0.push(node.clone());
+        self.push_with_chain(node, None);
+    }
+
+    fn push_with_chain(&mut self, node: &Node, chain: Option<ReferenceChain>) {
+        self.0.push((node.clone(), chain));
     }
 
     pub fn pop(&mut self) -> Option<Node> {
-        self.0.pop()
+        self.0.pop().map(|(node, _)| node)
     }
 
     pub fn contains(&self, node: &Node) -> bool {
-        self.0.iter().any(|n| *n == *node)
+        self.0.iter().any(|(n, _)| *n == *node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns all the nodes in the stack, from the bottom to the top.
+    pub fn nodes(&self) -> Vec<Node> {
+        self.0.iter().map(|(n, _)| n.clone()).collect()
+    }
+
+    /// Returns the nodes that were pushed as part of `chain`, from the bottom to the top.
+    fn chain(&self, chain: ReferenceChain) -> Vec<Node> {
+        self.0
+            .iter()
+            .filter(|(_, c)| *c == Some(chain))
+            .map(|(n, _)| n.clone())
+            .collect()
     }
 }
 
@@ -894,6 +1722,15 @@ pub struct DocumentBuilder {
 
     /// Stylesheets defined in the document.
     stylesheets: Vec<Stylesheet>,
+
+    /// Stylesheets that only apply to a subtree of the document.
+    scoped_stylesheets: Vec<ScopedStylesheet>,
+
+    /// Ids of elements referenced from within the document, and where the references are.
+    ///
+    /// These get checked once all the elements have been created, to warn about references
+    /// to elements that don't exist.
+    references: Vec<(String, Option<Location>)>,
 }
 
 impl DocumentBuilder {
@@ -904,6 +1741,8 @@ impl DocumentBuilder {
             tree: None,
             ids: HashMap::new(),
             stylesheets: Vec::new(),
+            scoped_stylesheets: Vec::new(),
+            references: Vec::new(),
         }
     }
 
@@ -914,6 +1753,12 @@ impl DocumentBuilder {
         self.stylesheets.push(stylesheet);
     }
 
+    /// Adds a stylesheet whose rules only apply to `scope` and its descendants.
+    pub fn append_scoped_stylesheet(&mut self, scope: Node, stylesheet: Stylesheet) {
+        self.scoped_stylesheets
+            .push(ScopedStylesheet { scope, stylesheet });
+    }
+
     /// Creates an element of the specified `name` as a child of `parent`.
     ///
     /// This is the main function to create new SVG elements while parsing XML.
@@ -931,8 +1776,22 @@ impl DocumentBuilder {
         attrs: Attributes,
         parent: Option<Node>,
     ) -> Node {
+        let location = self.session.location();
+        self.references.extend(
+            internal_references(name, &attrs)
+                .into_iter()
+                .map(|id| (id, location)),
+        );
+
         let node = Node::new(NodeData::new_element(&self.session, name, attrs));
 
+        // The `style` attribute is normally parsed during the cascade, when the position
+        // of the element is no longer known.  Parse it here as well, so that invalid
+        // declarations are reported at the element in strict mode.
+        if self.load_options.strict {
+            node.borrow_element().get_style_declarations(&self.session);
+        }
+
         if let Some(id) = node.borrow_element().get_id() {
             // This is so we don't overwrite an existing id
             self.ids
@@ -972,19 +1831,52 @@ impl DocumentBuilder {
             tree,
             ids,
             stylesheets,
+            scoped_stylesheets,
+            references,
             ..
         } = self;
 
+        // Stylesheets after the last element are only checked here.
+        if load_options.strict {
+            if let Some(warning) = session.take_strict_error() {
+                return Err(LoadingError::Invalid(warning));
+            }
+        }
+
+        for (id, location) in references {
+            if !ids.contains_key(&id) {
+                let message = format!("reference to non-existent element \"#{id}\"");
+                rsvg_log!(session, "{}", message);
+
+                if load_options.strict {
+                    return Err(LoadingError::Invalid(LoadWarning { location, message }));
+                }
+
+                session.add_warning_at(location, message);
+            }
+        }
+
         match tree {
             Some(root) if root.is_element() => {
                 if is_element_of_type!(root, Svg) {
+                    let id = next_document_id();
+
                     let mut document = Document {
                         tree: root,
                         session: session.clone(),
                         ids,
                         resources: RefCell::new(Resources::new()),
                         load_options,
-                        stylesheets,
+                        stylesheets: Rc::new(stylesheets),
+                        scoped_stylesheets: Rc::new(scoped_stylesheets),
+                        static_rasters: RefCell::new(StaticRasters::default()),
+                        fonts: DocumentFonts::default(),
+                        font_options: None,
+                        animation_time: 0.0,
+                        animation_events: Vec::new(),
+                        animation_sampled: false,
+                        id,
+                        tree_owner: Rc::new(Cell::new(id)),
                     };
 
                     document.cascade(&[], &session);
@@ -999,6 +1891,64 @@ impl DocumentBuilder {
     }
 }
 
+/// Returns the ids of elements in the same document that an element refers to.
+///
+/// This looks at `href` attributes (except for those in `<a>` elements, which are
+/// hyperlinks rather than references to content), and at `url(#id)` values in the rest of
+/// the attributes, including `style`.
+fn internal_references(name: &QualName, attrs: &Attributes) -> Vec<String> {
+    let mut urls = Vec::new();
+
+    for (attr, value) in attrs.iter() {
+        if is_href(&attr.expanded()) {
+            if !(name.ns == ns!(svg) && name.local == local_name!("a")) {
+                urls.push(value.to_string());
+            }
+        } else if value.contains("url(") {
+            let mut input = ParserInput::new(value);
+            let mut parser = Parser::new(&mut input);
+
+            // Errors just mean that we stop looking; the attribute's parser will report them.
+            let _ = collect_urls(&mut parser, &mut urls);
+        }
+    }
+
+    urls.into_iter()
+        .filter_map(|url| match NodeId::parse(&url) {
+            Ok(NodeId::Internal(id)) => Some(id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Collects the contents of all the `url()` tokens in `parser`, including nested blocks.
+fn collect_urls<'i>(
+    parser: &mut Parser<'i, '_>,
+    urls: &mut Vec<String>,
+) -> Result<(), ParseError<'i>> {
+    while let Ok(token) = parser.next() {
+        match token.clone() {
+            Token::UnquotedUrl(url) => urls.push(url.to_string()),
+
+            Token::Function(ref name) if name.eq_ignore_ascii_case("url") => {
+                let url = parser.parse_nested_block(|p| Ok(p.expect_string()?.to_string()))?;
+                urls.push(url);
+            }
+
+            Token::Function(_)
+            | Token::ParenthesisBlock
+            | Token::SquareBracketBlock
+            | Token::CurlyBracketBlock => {
+                parser.parse_nested_block(|p| collect_urls(p, urls))?;
+            }
+
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1037,4 +1987,44 @@ mod tests {
             Some(String::from("image/png"))
         );
     }
+
+    #[test]
+    fn checks_image_pixel_limit_before_decoding() {
+        let mut png = Vec::new();
+        image::RgbaImage::new(10, 10)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let aurl = UrlResolver::new(None)
+            .resolve_href("data:image/png;base64,")
+            .unwrap();
+
+        let load = |max_pixels| {
+            let options = LoadOptions::new(UrlResolver::new(None)).with_limits(LoadLimits {
+                max_image_pixels: Some(max_pixels),
+                ..LoadLimits::default()
+            });
+
+            load_image_with_image_rs(&aurl, png.clone(), Some("image/png".to_string()), &options)
+        };
+
+        assert!(matches!(load(100), Ok(Resource::Image(_))));
+        assert!(matches!(
+            load(99),
+            Err(LoadingError::LimitExceeded(
+                ImplementationLimit::TooManyImagePixels
+            ))
+        ));
+    }
+
+    #[test]
+    fn collects_urls_in_nested_blocks() {
+        let mut input = ParserInput::new("url(#a) none, foo(1 url(\"#b\")) url('c.svg#d')");
+        let mut parser = Parser::new(&mut input);
+
+        let mut urls = Vec::new();
+        collect_urls(&mut parser, &mut urls).unwrap();
+
+        assert_eq!(urls, vec!["#a", "#b", "c.svg#d"]);
+    }
 }
@@ -1,6 +1,7 @@
 //! The main context structure which drives the drawing process.
 
 use float_cmp::approx_eq;
+use glib::prelude::Cast;
 use glib::translate::*;
 use once_cell::sync::Lazy;
 use pango::ffi::PangoMatrix;
@@ -9,7 +10,7 @@ use regex::{Captures, Regex};
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::convert::TryFrom;
-use std::f64::consts::*;
+use std::mem;
 use std::rc::Rc;
 
 use crate::accept_language::UserLanguage;
@@ -20,29 +21,36 @@ use crate::coord_units::CoordUnits;
 use crate::document::{AcquiredNodes, NodeId};
 use crate::dpi::Dpi;
 use crate::element::{Element, ElementData};
-use crate::error::{AcquireError, ImplementationLimit, InternalRenderingError};
-use crate::filters::{self, FilterSpec};
+use crate::error::{AcquireError, ImplementationLimit, InternalRenderingError, ReferenceChain};
+use crate::filters::{self, FilterResolveError, FilterSpec};
 use crate::float_eq_cairo::ApproxEqCairo;
 use crate::gradient::{GradientVariant, SpreadMethod, UserSpaceGradient};
 use crate::layout::{
     Filter, Image, Layer, LayerKind, Shape, StackingContext, Stroke, Text, TextSpan,
 };
 use crate::length::*;
+use crate::log::BrokenPaintReference;
 use crate::marker;
 use crate::node::{CascadedValues, Node, NodeBorrow, NodeDraw};
-use crate::paint_server::{PaintSource, UserSpacePaintSource};
+#[cfg(feature = "render-observers")]
+use crate::observer::ObservedElement;
+use crate::paint_server::{resolve_color, PaintServer, PaintSource, UserSpacePaintSource};
 use crate::path_builder::*;
 use crate::pattern::UserSpacePattern;
 use crate::properties::{
-    ClipRule, ComputedValues, FillRule, ImageRendering, MaskType, MixBlendMode, Opacity, Overflow,
-    PaintTarget, ShapeRendering, StrokeLinecap, StrokeLinejoin, TextRendering,
+    ClipRule, ColorInterpolation, ComputedValues, Display, FillRule, ImageRendering, Isolation,
+    MaskType, MixBlendMode, Opacity, Overflow, PaintTarget, ShapeRendering, StrokeLinecap,
+    StrokeLinejoin, TextRendering, VectorEffect,
 };
 use crate::rect::{rect_to_transform, IRect, Rect};
 use crate::rsvg_log;
 use crate::session::Session;
+use crate::shapes;
+use crate::stroke_extents;
 use crate::surface_utils::shared_surface::{
     ExclusiveImageSurface, Interpolation, SharedImageSurface, SurfaceType,
 };
+use crate::surface_utils::{iterators::Pixels, srgb, ImageSurfaceDataExt};
 use crate::transform::{Transform, ValidTransform};
 use crate::unit_interval::UnitInterval;
 use crate::viewbox::ViewBox;
@@ -69,6 +77,7 @@ struct PathHelper<'a> {
     transform: ValidTransform,
     path: &'a Path,
     is_square_linecap: bool,
+    simplify_tolerance: Option<f64>,
     has_path: Option<bool>,
 }
 
@@ -78,12 +87,14 @@ impl<'a> PathHelper<'a> {
         transform: ValidTransform,
         path: &'a Path,
         linecap: StrokeLinecap,
+        simplify_tolerance: Option<f64>,
     ) -> Self {
         PathHelper {
             cr,
             transform,
             path,
             is_square_linecap: linecap == StrokeLinecap::Square,
+            simplify_tolerance,
             has_path: None,
         }
     }
@@ -93,7 +104,8 @@ impl<'a> PathHelper<'a> {
             Some(false) | None => {
                 self.has_path = Some(true);
                 self.cr.set_matrix(self.transform.into());
-                self.path.to_cairo(self.cr, self.is_square_linecap)
+                self.path
+                    .to_cairo(self.cr, self.is_square_linecap, self.simplify_tolerance)
             }
             Some(true) => Ok(()),
         }
@@ -183,8 +195,24 @@ pub struct DrawingCtx {
 
     drawsub_stack: Vec<Node>,
 
+    element_boxes: Option<Rc<RefCell<ElementBoxes>>>,
+
+    clip_mask_cache: Rc<RefCell<ClipMaskCache>>,
+
+    instance_cache: Rc<RefCell<InstanceCache>>,
+
     svg_nesting: SvgNesting,
 
+    quality: RenderingQuality,
+
+    path_simplification: Option<f64>,
+
+    /// Whether to draw a placeholder where an element failed to render.
+    error_placeholders: bool,
+
+    /// Whether the elements being drawn are hidden under an opaque element drawn later.
+    occluded: bool,
+
     measuring: bool,
     testing: bool,
 }
@@ -195,6 +223,99 @@ pub enum DrawingMode {
     OnlyNode(Node),
 }
 
+/// Records the bounding boxes of a set of elements while the tree is drawn.
+///
+/// Only elements that get drawn in their place in the document tree are recorded, not the
+/// instances of them that get drawn through `<use>`, patterns, markers, clipping paths or
+/// masks.  An element is in its place if its parent is the innermost element that is
+/// being drawn in its place.
+///
+/// This can also record which element is under a point.  Content drawn through a `<use>`
+/// counts as the `<use>` element itself, since it is the innermost one drawn in its place.
+///
+/// For auditing contrast, this can record runs of text instead of letting them be painted.
+struct ElementBoxes {
+    targets: Vec<Node>,
+    bboxes: Vec<Option<BoundingBox>>,
+
+    /// Elements which are being drawn in their place, outermost first.
+    in_place: Vec<Node>,
+
+    /// Point to hit test, in device space.
+    hit_point: Option<(f64, f64)>,
+
+    /// Last element drawn over the `hit_point`, which is the topmost one.
+    hit: Option<Node>,
+
+    /// Runs of text that were skipped instead of painted, if auditing contrast.
+    text_runs: Option<Vec<TextRun>>,
+}
+
+/// A run of text with a solid fill, recorded by [`audit_text_contrast`].
+pub struct TextRun {
+    /// Innermost element being drawn in its place, as for hit testing.
+    pub node: Option<Node>,
+
+    pub text: String,
+
+    /// Ink extents of the text, in device space.
+    pub device_rect: Rect,
+
+    pub fill: cssparser::RGBA,
+}
+
+impl ElementBoxes {
+    fn new(targets: &[Node]) -> ElementBoxes {
+        ElementBoxes {
+            targets: targets.to_vec(),
+            bboxes: vec![None; targets.len()],
+            in_place: Vec::new(),
+            hit_point: None,
+            hit: None,
+            text_runs: None,
+        }
+    }
+
+    fn for_hit_point(x: f64, y: f64) -> ElementBoxes {
+        ElementBoxes {
+            hit_point: Some((x, y)),
+            ..ElementBoxes::new(&[])
+        }
+    }
+
+    fn for_text_audit() -> ElementBoxes {
+        ElementBoxes {
+            text_runs: Some(Vec::new()),
+            ..ElementBoxes::new(&[])
+        }
+    }
+
+    /// Returns whether `node` is about to be drawn in its place; if so, starts tracking it.
+    fn enter(&mut self, node: &Node) -> bool {
+        let in_place = match self.in_place.last() {
+            None => true,
+            Some(parent) => node.parent().as_ref() == Some(parent),
+        };
+
+        if in_place {
+            self.in_place.push(node.clone());
+        }
+
+        in_place
+    }
+
+    /// Stops tracking `node`, and stores its `bbox` if it is one of the targets.
+    fn leave(&mut self, node: &Node, bbox: Option<&BoundingBox>) {
+        self.in_place.pop();
+
+        for (target, slot) in self.targets.iter().zip(self.bboxes.iter_mut()) {
+            if target == node {
+                *slot = bbox.copied();
+            }
+        }
+    }
+}
+
 /// Whether an SVG document is being rendered standalone or referenced from an `<image>` element.
 ///
 /// Normally, the coordinate system used when rendering a toplevel SVG is determined from the
@@ -209,6 +330,297 @@ pub enum SvgNesting {
     ReferencedFromImageElement,
 }
 
+/// Cached rasters of elements that are marked as static.
+///
+/// Interactive viewers often re-render a document where only a few elements change between
+/// frames.  Elements which the caller marks as static get rendered to an offscreen surface
+/// the size of the toplevel viewport, and that surface is just composited in later renders
+/// that use the same transformation, DPI and rendering quality.
+///
+/// Each static element keeps only the raster from the last time it was rendered.  Rasters
+/// are only used for elements that are drawn in their place in the tree onto image
+/// surfaces; instances of them in `<use>` elements, or renderings to vector surfaces like
+/// PDF, draw the element normally.
+#[derive(Default)]
+pub struct StaticRasters {
+    entries: Vec<StaticRaster>,
+}
+
+struct StaticRaster {
+    node: Node,
+    raster: Option<(StaticRasterKey, cairo::ImageSurface, BoundingBox)>,
+}
+
+/// Parameters which must match for a cached raster to be reused.
+#[derive(PartialEq)]
+struct StaticRasterKey {
+    transform: Transform,
+    initial_transform: Transform,
+    viewport: Rect,
+    cr_stack_depth: usize,
+    dpi: Dpi,
+    quality: RenderingQuality,
+}
+
+impl StaticRasters {
+    pub fn set_static(&mut self, node: &Node, is_static: bool) {
+        self.entries.retain(|e| e.node != *node);
+
+        if is_static {
+            self.entries.push(StaticRaster {
+                node: node.clone(),
+                raster: None,
+            });
+        }
+    }
+
+    /// Drops all the cached rasters, but keeps the elements marked as static.
+    pub fn clear(&mut self) {
+        for entry in &mut self.entries {
+            entry.raster = None;
+        }
+    }
+
+    fn is_static(&self, node: &Node) -> bool {
+        self.entries.iter().any(|e| e.node == *node)
+    }
+
+    fn lookup(
+        &self,
+        node: &Node,
+        key: &StaticRasterKey,
+    ) -> Option<(cairo::ImageSurface, BoundingBox)> {
+        self.entries
+            .iter()
+            .find(|e| e.node == *node)
+            .and_then(|e| e.raster.as_ref())
+            .filter(|(k, _, _)| k == key)
+            .map(|(_, surface, bbox)| (surface.clone(), *bbox))
+    }
+
+    fn store(
+        &mut self,
+        node: &Node,
+        key: StaticRasterKey,
+        surface: cairo::ImageSurface,
+        bbox: BoundingBox,
+    ) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.node == *node) {
+            entry.raster = Some((key, surface, bbox));
+        }
+    }
+}
+
+/// Maximum number of masks kept by [`ClipMaskCache`].
+///
+/// Each mask is an image the size of the toplevel viewport, so we keep only a few of them.
+/// This is enough for the common case of many elements sharing a mask with
+/// `maskUnits="userSpaceOnUse"`.
+const MAX_CACHED_MASKS: usize = 8;
+
+/// Maximum number of clip paths kept by [`ClipMaskCache`].
+///
+/// Paths are much smaller than masks, but a map may clip thousands of elements with
+/// different bounding boxes, and the cache is searched linearly.
+const MAX_CACHED_CLIPS: usize = 64;
+
+/// Clip paths and masks computed during a single rendering.
+///
+/// Documents like maps or charts often have many elements that reference the same
+/// `clip-path` or `mask`.  Computing one of those means drawing the contents of the
+/// `<clipPath>` or `<mask>` element, so we remember the results and reuse them for
+/// other elements which would get the same one: those with the same current
+/// transformation, viewport, and bounding box (the latter only matters for
+/// `objectBoundingBox` units, but it is simpler to always compare it).
+///
+/// Clip paths are stored as paths in device space along with their fill rule, and masks
+/// as the final alpha surface that gets passed to `cairo_mask()`.  Since the cache only
+/// lives for one rendering, the contents of the referenced elements cannot change while
+/// it is in use.  Only the most recent entries are kept, to bound the memory and the
+/// time spent searching.
+#[derive(Default)]
+struct ClipMaskCache {
+    clips: Vec<(ClipMaskKey, cairo::Path, cairo::FillRule)>,
+    masks: Vec<(ClipMaskKey, cairo::ImageSurface)>,
+}
+
+#[derive(PartialEq)]
+struct ClipMaskKey {
+    node: Node,
+    transform: Transform,
+    bbox: Option<Rect>,
+    viewport: ViewBox,
+}
+
+impl ClipMaskCache {
+    fn lookup_clip(&self, key: &ClipMaskKey) -> Option<(&cairo::Path, cairo::FillRule)> {
+        self.clips
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, path, fill_rule)| (path, *fill_rule))
+    }
+
+    fn store_clip(&mut self, key: ClipMaskKey, path: cairo::Path, fill_rule: cairo::FillRule) {
+        if self.clips.len() == MAX_CACHED_CLIPS {
+            self.clips.remove(0);
+        }
+
+        self.clips.push((key, path, fill_rule));
+    }
+
+    fn lookup_mask(&self, key: &ClipMaskKey) -> Option<cairo::ImageSurface> {
+        self.masks
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, surface)| surface.clone())
+    }
+
+    fn store_mask(&mut self, key: ClipMaskKey, surface: cairo::ImageSurface) {
+        if self.masks.len() == MAX_CACHED_MASKS {
+            self.masks.remove(0);
+        }
+
+        self.masks.push((key, surface));
+    }
+}
+
+/// Number of times that an element must be drawn with the same [`InstanceKey`] before
+/// [`InstanceCache`] rasterizes it.
+const INSTANCE_THRESHOLD: usize = 3;
+
+/// Maximum number of different instances kept by [`InstanceCache`].
+const MAX_CACHED_INSTANCES: usize = 64;
+
+/// Rasters of elements that are referenced many times from `<use>` elements.
+///
+/// Documents like seat maps or floor plans can have thousands of `<use>` elements that
+/// reference the same symbol.  When an element has been drawn a few times with the same
+/// scale and the same styles, we rasterize it once and just composite the raster for the
+/// following instances, offset by a whole number of pixels.
+///
+/// The position of instances is rounded to a quarter of a pixel, so the fractional part of
+/// the translation is part of the key, too.  Elements whose subtree has filters, blend
+/// modes, or `context-fill`/`context-stroke` paint are never cached, since how they look
+/// depends on more than their own content.  Like [`ClipMaskCache`], this cache only lives
+/// for one rendering.
+#[derive(Default)]
+struct InstanceCache {
+    instances: Vec<Instance>,
+}
+
+struct Instance {
+    key: InstanceKey,
+    state: InstanceState,
+}
+
+/// Parameters which must match for an instance's raster to be reused.
+#[derive(Clone, PartialEq)]
+struct InstanceKey {
+    node: Node,
+    values: ComputedValues,
+    linear_transform: Transform,
+    subpixel_offset: (i64, i64),
+    viewport: ViewBox,
+}
+
+enum InstanceState {
+    /// The element has been drawn this many times, but not rasterized yet.
+    Counting(usize),
+
+    Rasterized(InstanceRaster),
+
+    /// The element extends beyond the temporary surface, so it must always be drawn normally.
+    Uncacheable,
+}
+
+/// The pixels of an element drawn at `position`, in quarters of a device pixel.
+///
+/// The `surface` gets composited at `rect`; it is `None` if the element has no ink.  The
+/// element's bounding box, in its own user space, is `bbox`.
+#[derive(Clone)]
+struct InstanceRaster {
+    position: (i64, i64),
+    rect: IRect,
+    surface: Option<cairo::ImageSurface>,
+    bbox: BoundingBox,
+}
+
+enum InstanceAction {
+    DrawNormally,
+    Rasterize,
+    Composite(InstanceRaster),
+}
+
+impl InstanceCache {
+    /// Counts a use of the instance with `key`, and decides how to draw it.
+    fn use_instance(&mut self, key: &InstanceKey) -> InstanceAction {
+        let Some(instance) = self.instances.iter_mut().find(|i| i.key == *key) else {
+            if self.instances.len() == MAX_CACHED_INSTANCES {
+                self.instances.remove(0);
+            }
+
+            self.instances.push(Instance {
+                key: key.clone(),
+                state: InstanceState::Counting(1),
+            });
+
+            return InstanceAction::DrawNormally;
+        };
+
+        match instance.state {
+            InstanceState::Counting(ref mut uses) => {
+                *uses += 1;
+
+                if *uses >= INSTANCE_THRESHOLD {
+                    InstanceAction::Rasterize
+                } else {
+                    InstanceAction::DrawNormally
+                }
+            }
+
+            InstanceState::Rasterized(ref raster) => InstanceAction::Composite(raster.clone()),
+
+            InstanceState::Uncacheable => InstanceAction::DrawNormally,
+        }
+    }
+
+    fn store(&mut self, key: &InstanceKey, state: InstanceState) {
+        if let Some(instance) = self.instances.iter_mut().find(|i| i.key == *key) {
+            instance.state = state;
+        }
+    }
+}
+
+/// Trade-off between rendering quality and compatibility with other renderers.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RenderingQuality {
+    /// Render like librsvg always has, which closely matches what web browsers do.
+    #[default]
+    Compatible,
+
+    /// Use slower, higher-quality algorithms where they are available, even if the output
+    /// looks different from other renderers.
+    High,
+}
+
+/// A solid color that stands for a spot ink, for overprint previews.
+///
+/// Print artwork often uses a solid color as a stand-in for a spot ink, like a varnish or
+/// a metallic ink.  In an overprint preview, paints with that `color` are shown with the
+/// `ink` color instead, and they are multiplied with what is underneath, like an
+/// overprinted ink on paper, instead of knocking it out.
+///
+/// See [`crate::CairoRenderer::with_overprint_preview`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpotColor {
+    /// Color that stands for the ink in the document.  Only its red, green, and blue
+    /// components are compared with paints; their alpha is kept.
+    pub color: cssparser::RGBA,
+
+    /// Color with which to preview the ink.
+    pub ink: cssparser::RGBA,
+}
+
 /// The toplevel drawing routine.
 ///
 /// This creates a DrawingCtx internally and starts drawing at the specified `node`.
@@ -220,9 +632,252 @@ pub fn draw_tree(
     user_language: &UserLanguage,
     dpi: Dpi,
     svg_nesting: SvgNesting,
+    quality: RenderingQuality,
+    path_simplification: Option<f64>,
+    error_placeholders: bool,
     measuring: bool,
     testing: bool,
     acquired_nodes: &mut AcquiredNodes<'_>,
+) -> Result<BoundingBox, InternalRenderingError> {
+    draw_tree_recording_boxes(
+        session,
+        mode,
+        cr,
+        viewport_rect,
+        user_language,
+        dpi,
+        svg_nesting,
+        quality,
+        path_simplification,
+        error_placeholders,
+        measuring,
+        testing,
+        acquired_nodes,
+        None,
+    )
+}
+
+/// Measures several elements with a single pass over the tree.
+///
+/// This measures the whole tree starting at `root`, and returns the bounding box of each
+/// of the `targets`, converted to the user space of the `cr`, in the same order.  Targets
+/// which do not get drawn in their place in the tree, for example because they are inside
+/// a `<defs>`, get an empty bounding box.
+pub fn measure_elements(
+    session: Session,
+    root: Node,
+    targets: &[Node],
+    cr: &cairo::Context,
+    viewport_rect: Rect,
+    user_language: &UserLanguage,
+    dpi: Dpi,
+    testing: bool,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+) -> Result<Vec<BoundingBox>, InternalRenderingError> {
+    let user_transform = Transform::from(cr.matrix());
+    let element_boxes = Rc::new(RefCell::new(ElementBoxes::new(targets)));
+
+    draw_tree_recording_boxes(
+        session,
+        DrawingMode::LimitToStack {
+            node: root.clone(),
+            root,
+        },
+        cr,
+        viewport_rect,
+        user_language,
+        dpi,
+        SvgNesting::Standalone,
+        RenderingQuality::default(),
+        None,
+        false,
+        true,
+        testing,
+        acquired_nodes,
+        Some(element_boxes.clone()),
+    )?;
+
+    let bboxes = element_boxes
+        .borrow()
+        .bboxes
+        .iter()
+        .map(|bbox| {
+            let mut user_bbox = BoundingBox::new().with_transform(user_transform);
+            if let Some(bbox) = bbox {
+                user_bbox.insert(bbox);
+            }
+            user_bbox
+        })
+        .collect();
+
+    Ok(bboxes)
+}
+
+/// Finds the topmost element that gets painted at a point.
+///
+/// The point `(x, y)` is in the user space of the `cr`.  This measures the whole tree
+/// starting at `root`, and returns the last element drawn in its place whose fill or
+/// stroke covers the point; text and images count by their bounding boxes.  Clipping
+/// paths, masks, and filters are not taken into account.
+pub fn element_at_point(
+    session: Session,
+    root: Node,
+    cr: &cairo::Context,
+    viewport_rect: Rect,
+    (x, y): (f64, f64),
+    user_language: &UserLanguage,
+    dpi: Dpi,
+    testing: bool,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+) -> Result<Option<Node>, InternalRenderingError> {
+    let (x, y) = cr.user_to_device(x, y);
+    let element_boxes = Rc::new(RefCell::new(ElementBoxes::for_hit_point(x, y)));
+
+    draw_tree_recording_boxes(
+        session,
+        DrawingMode::LimitToStack {
+            node: root.clone(),
+            root,
+        },
+        cr,
+        viewport_rect,
+        user_language,
+        dpi,
+        SvgNesting::Standalone,
+        RenderingQuality::default(),
+        None,
+        false,
+        true,
+        testing,
+        acquired_nodes,
+        Some(element_boxes.clone()),
+    )?;
+
+    let hit = element_boxes.borrow_mut().hit.take();
+    Ok(hit)
+}
+
+/// Renders the tree starting at `root` without its text, and returns the runs of text.
+///
+/// Text spans with a solid fill get recorded instead of painted; text with other fills
+/// is not painted either, so that the `cr` ends up with just what is beneath the text.
+/// The device space of the `cr` must be the same as the one of its target surface, so
+/// that the temporary surfaces for groups line up with it.
+pub fn audit_text_contrast(
+    session: Session,
+    root: Node,
+    cr: &cairo::Context,
+    viewport_rect: Rect,
+    user_language: &UserLanguage,
+    dpi: Dpi,
+    testing: bool,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+) -> Result<Vec<TextRun>, InternalRenderingError> {
+    let element_boxes = Rc::new(RefCell::new(ElementBoxes::for_text_audit()));
+
+    draw_tree_recording_boxes(
+        session,
+        DrawingMode::LimitToStack {
+            node: root.clone(),
+            root,
+        },
+        cr,
+        viewport_rect,
+        user_language,
+        dpi,
+        SvgNesting::Standalone,
+        RenderingQuality::default(),
+        None,
+        false,
+        false,
+        testing,
+        acquired_nodes,
+        Some(element_boxes.clone()),
+    )?;
+
+    let runs = element_boxes.borrow_mut().text_runs.take();
+    Ok(runs.unwrap_or_default())
+}
+
+/// Paints a gradient or pattern over a `width` by `height` rectangle at the origin.
+///
+/// The paint server is used as if it were the `fill` of a `<rect>` with that size, so its
+/// `objectBoundingBox` units are relative to the rectangle.
+pub fn draw_paint_server(
+    session: Session,
+    cr: &cairo::Context,
+    node: &Node,
+    iri: &NodeId,
+    (width, height): (f64, f64),
+    user_language: &UserLanguage,
+    dpi: Dpi,
+    testing: bool,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+) -> Result<(), InternalRenderingError> {
+    let rect = Rect::from_size(width, height);
+
+    let viewport = Viewport {
+        dpi,
+        vbox: ViewBox::from(rect),
+        transform: Transform::from(cr.matrix()),
+    };
+
+    let mut draw_ctx = DrawingCtx::new(
+        session.clone(),
+        cr,
+        &viewport,
+        user_language.clone(),
+        dpi,
+        SvgNesting::Standalone,
+        RenderingQuality::default(),
+        None,
+        false,
+        false,
+        testing,
+        Vec::new(),
+        None,
+    );
+
+    let values = node.borrow_element().get_computed_values().clone();
+
+    let paint_server = PaintServer::Iri {
+        iri: Box::new(iri.clone()),
+        alternate: None,
+    };
+
+    let paint_source = paint_server
+        .resolve(
+            acquired_nodes,
+            UnitInterval::clamp(1.0),
+            values.color().0,
+            None,
+            None,
+            &session,
+        )
+        .to_user_space(&Some(rect), &viewport, &NormalizeValues::new(&values));
+
+    draw_ctx.paint_with_source(&paint_source, acquired_nodes, |_| {
+        cr.rectangle(0.0, 0.0, width, height);
+        cr.fill()?;
+        Ok(())
+    })
+}
+
+fn draw_tree_recording_boxes(
+    session: Session,
+    mode: DrawingMode,
+    cr: &cairo::Context,
+    viewport_rect: Rect,
+    user_language: &UserLanguage,
+    dpi: Dpi,
+    svg_nesting: SvgNesting,
+    quality: RenderingQuality,
+    path_simplification: Option<f64>,
+    error_placeholders: bool,
+    measuring: bool,
+    testing: bool,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+    element_boxes: Option<Rc<RefCell<ElementBoxes>>>,
 ) -> Result<BoundingBox, InternalRenderingError> {
     let (drawsub_stack, node) = match mode {
         DrawingMode::LimitToStack { node, root } => (node.ancestors().collect(), root),
@@ -274,9 +929,13 @@ pub fn draw_tree(
         user_language.clone(),
         dpi,
         svg_nesting,
+        quality,
+        path_simplification,
+        error_placeholders,
         measuring,
         testing,
         drawsub_stack,
+        element_boxes,
     );
 
     let content_bbox = draw_ctx.draw_node_from_stack(
@@ -323,9 +982,13 @@ impl DrawingCtx {
         user_language: UserLanguage,
         dpi: Dpi,
         svg_nesting: SvgNesting,
+        quality: RenderingQuality,
+        path_simplification: Option<f64>,
+        error_placeholders: bool,
         measuring: bool,
         testing: bool,
         drawsub_stack: Vec<Node>,
+        element_boxes: Option<Rc<RefCell<ElementBoxes>>>,
     ) -> DrawingCtx {
         DrawingCtx {
             session,
@@ -335,7 +998,14 @@ impl DrawingCtx {
             cr: cr.clone(),
             user_language,
             drawsub_stack,
+            element_boxes,
+            clip_mask_cache: Rc::new(RefCell::new(ClipMaskCache::default())),
+            instance_cache: Rc::new(RefCell::new(InstanceCache::default())),
             svg_nesting,
+            quality,
+            path_simplification,
+            error_placeholders,
+            occluded: false,
             measuring,
             testing,
         }
@@ -360,7 +1030,14 @@ impl DrawingCtx {
             cr,
             user_language: self.user_language.clone(),
             drawsub_stack: self.drawsub_stack.clone(),
+            element_boxes: self.element_boxes.clone(),
+            clip_mask_cache: self.clip_mask_cache.clone(),
+            instance_cache: self.instance_cache.clone(),
             svg_nesting: self.svg_nesting,
+            quality: self.quality,
+            path_simplification: self.path_simplification,
+            error_placeholders: self.error_placeholders,
+            occluded: self.occluded,
             measuring: self.measuring,
             testing: self.testing,
         }
@@ -405,6 +1082,14 @@ impl DrawingCtx {
         self.svg_nesting
     }
 
+    pub fn quality(&self) -> RenderingQuality {
+        self.quality
+    }
+
+    pub fn error_placeholders(&self) -> bool {
+        self.error_placeholders
+    }
+
     pub fn is_measuring(&self) -> bool {
         self.measuring
     }
@@ -413,6 +1098,60 @@ impl DrawingCtx {
         self.testing
     }
 
+    /// While hit testing, records the element being drawn as hit if `contains` is true.
+    ///
+    /// The closure gets the hit point in the user space of `cr`.
+    fn hit_test<F>(&self, cr: &cairo::Context, contains: F) -> Result<(), InternalRenderingError>
+    where
+        F: FnOnce(f64, f64) -> Result<bool, cairo::Error>,
+    {
+        if let Some(ref boxes) = self.element_boxes {
+            let mut boxes = boxes.borrow_mut();
+
+            if let Some((x, y)) = boxes.hit_point {
+                let (x, y) = cr.device_to_user(x, y)?;
+
+                if contains(x, y)? {
+                    boxes.hit = boxes.in_place.last().cloned();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// While auditing contrast, records a run of text, and returns whether it was recorded.
+    ///
+    /// The caller must not paint the text in that case.
+    fn record_text_run(&self, span: &TextSpan, bbox: &BoundingBox) -> bool {
+        let Some(ref boxes) = self.element_boxes else {
+            return false;
+        };
+
+        let mut boxes = boxes.borrow_mut();
+        let node = boxes.in_place.last().cloned();
+
+        let Some(ref mut runs) = boxes.text_runs else {
+            return false;
+        };
+
+        let mut device_bbox = BoundingBox::new();
+        device_bbox.insert(bbox);
+
+        if let (UserSpacePaintSource::SolidColor(color), Some(device_rect)) =
+            (&span.fill_paint, device_bbox.ink_rect)
+        {
+            runs.push(TextRun {
+                node,
+                text: span.layout.text().to_string(),
+                device_rect,
+                fill: color_to_rgba(color),
+            });
+        }
+
+        true
+    }
+
     pub fn get_transform(&self) -> ValidTransform {
         let t = Transform::from(self.cr.matrix());
         ValidTransform::try_from(t)
@@ -423,6 +1162,54 @@ impl DrawingCtx {
         BoundingBox::new().with_transform(*self.get_transform())
     }
 
+    /// Draws an error placeholder over `rect`, if error placeholders are enabled.
+    ///
+    /// The `rect` is in the coordinate system given by `transform`, relative to the
+    /// current one.  This is used for elements that could not be rendered at all,
+    /// like an `<image>` whose file could not be loaded.
+    pub fn draw_error_placeholder(
+        &self,
+        transform: Transform,
+        rect: Rect,
+    ) -> Result<(), InternalRenderingError> {
+        if !self.error_placeholders || self.measuring {
+            return Ok(());
+        }
+
+        let transform = ValidTransform::try_from(transform)?;
+
+        with_saved_cr(&self.cr, || {
+            self.cr.transform(transform.into());
+            paint_error_placeholder(&self.cr, rect)?;
+            Ok(())
+        })
+    }
+
+    /// Draws an error placeholder over the `bbox` of an element whose filter failed.
+    ///
+    /// Returns the `surface` unchanged if error placeholders are disabled.
+    pub fn error_placeholder_surface(
+        &self,
+        surface: SharedImageSurface,
+        bbox: &BoundingBox,
+    ) -> Result<SharedImageSurface, InternalRenderingError> {
+        let rect = match bbox.rect {
+            Some(rect) if self.error_placeholders && !self.measuring => rect,
+            _ => return Ok(surface),
+        };
+
+        let surface_type = surface.surface_type();
+        let image_surface = surface.into_image_surface()?;
+
+        {
+            let cr = cairo::Context::new(&image_surface)?;
+            cr.set_matrix(ValidTransform::try_from(bbox.transform())?.into());
+            paint_error_placeholder(&cr, rect)?;
+        }
+
+        Ok(SharedImageSurface::wrap(image_surface, surface_type)?)
+    }
+
     fn size_for_temporary_surface(&self) -> (i32, i32) {
         let rect = self.toplevel_viewport();
 
@@ -516,13 +1303,51 @@ impl DrawingCtx {
         viewport: &Viewport,
         bbox: &BoundingBox,
     ) -> Result<(), InternalRenderingError> {
-        if clip_node.is_none() {
-            return Ok(());
+        if let Some(node) = clip_node {
+            if self.set_clip_path(node, acquired_nodes, viewport, bbox)? {
+                self.cr.clip();
+            }
         }
 
-        let node = clip_node.as_ref().unwrap();
+        Ok(())
+    }
+
+    /// Makes the outline of a `clipPath` element the current path of the cairo context.
+    ///
+    /// Returns `false` if the clipping path does not apply, because it uses
+    /// `objectBoundingBox` units and the element has no bounding box.
+    fn set_clip_path(
+        &mut self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        viewport: &Viewport,
+        bbox: &BoundingBox,
+    ) -> Result<bool, InternalRenderingError> {
         let units = borrow_element_as!(node, ClipPath).get_units();
 
+        let key = ClipMaskKey {
+            node: node.clone(),
+            transform: *self.get_transform(),
+            bbox: bbox.rect,
+            viewport: viewport.vbox,
+        };
+
+        {
+            let cache = self.clip_mask_cache.borrow();
+
+            if let Some((path, fill_rule)) = cache.lookup_clip(&key) {
+                // The path is in device space
+                let orig_transform = self.get_transform();
+                self.cr.identity_matrix();
+                self.cr.new_path();
+                self.cr.append_path(path);
+                self.cr.set_fill_rule(fill_rule);
+                self.cr.set_matrix(orig_transform.into());
+
+                return Ok(true);
+            }
+        }
+
         if let Ok(transform) = rect_to_transform(&bbox.rect, units) {
             let cascaded = CascadedValues::new_from_node(node);
             let values = cascaded.get();
@@ -545,12 +1370,20 @@ impl DrawingCtx {
                 )?;
             }
 
-            self.cr.clip();
+            self.cr.identity_matrix();
+            let path = self.cr.copy_path()?;
+
+            // The children set the fill rule from their clip-rule as they get drawn.
+            self.clip_mask_cache
+                .borrow_mut()
+                .store_clip(key, path, self.cr.fill_rule());
 
             self.cr.set_matrix(orig_transform.into());
-        }
 
-        Ok(())
+            Ok(true)
+        } else {
+            Ok(false)
+        }
     }
 
     fn generate_cairo_mask(
@@ -567,6 +1400,17 @@ impl DrawingCtx {
             return Ok(None);
         }
 
+        let key = ClipMaskKey {
+            node: mask_node.clone(),
+            transform,
+            bbox: bbox.rect,
+            viewport: viewport.vbox,
+        };
+
+        if let Some(mask) = self.clip_mask_cache.borrow().lookup_mask(&key) {
+            return Ok(Some(mask));
+        }
+
         let _mask_acquired = match acquired_nodes.acquire_ref(mask_node) {
             Ok(n) => n,
 
@@ -641,7 +1485,7 @@ impl DrawingCtx {
                 values,
             );
 
-            rsvg_log!(self.session, "(mask {}", mask_element);
+            rsvg_log!(self.session, level: Debug, "(mask {}", mask_element);
 
             let res = mask_draw_ctx.with_discrete_layer(
                 &stacking_ctx,
@@ -651,7 +1495,7 @@ impl DrawingCtx {
                 &mut |an, dc| mask_node.draw_children(an, &cascaded, &mask_viewport, dc, false),
             );
 
-            rsvg_log!(self.session, ")");
+            rsvg_log!(self.session, level: Debug, ")");
 
             res?;
         }
@@ -659,12 +1503,27 @@ impl DrawingCtx {
         let tmp = SharedImageSurface::wrap(mask_content_surface, SurfaceType::SRgb)?;
 
         let mask_result = match values.mask_type() {
+            // The luminance coefficients are the same for both color spaces; the
+            // mask's color-interpolation determines in which one they get applied.
+            // Librsvg has always used sRGB, so this is only done for high quality.
+            // https://www.w3.org/TR/SVG11/masking.html#Masking
+            MaskType::Luminance
+                if self.quality == RenderingQuality::High
+                    && values.color_interpolation() == ColorInterpolation::LinearRgb =>
+            {
+                let bounds = IRect::from_size(tmp.width(), tmp.height());
+                tmp.to_linear_rgb(bounds)?.to_luminance_mask()?
+            }
             MaskType::Luminance => tmp.to_luminance_mask()?,
             MaskType::Alpha => tmp.extract_alpha(IRect::from_size(tmp.width(), tmp.height()))?,
         };
 
         let mask = mask_result.into_image_surface()?;
 
+        self.clip_mask_cache
+            .borrow_mut()
+            .store_mask(key, mask.clone());
+
         Ok(Some(mask))
     }
 
@@ -686,6 +1545,8 @@ impl DrawingCtx {
 
         let res = if clipping {
             draw_fn(acquired_nodes, self)
+        } else if self.measuring {
+            self.measure_layer(stacking_ctx, acquired_nodes, viewport, draw_fn)
         } else {
             with_saved_cr(&self.cr.clone(), || {
                 if let Some(ref link_target) = stacking_ctx.link_target {
@@ -850,11 +1711,24 @@ impl DrawingCtx {
                             .set_matrix(ValidTransform::try_from(affines.compositing)?.into());
                         self.cr.set_operator(stacking_ctx.mix_blend_mode.into());
 
+                        let linear_light = acquired_nodes.linear_light()
+                            && stacking_ctx.mix_blend_mode == MixBlendMode::Normal
+                            && self.cr.group_target().type_() == cairo::SurfaceType::Image;
+
+                        if linear_light {
+                            self.cr.push_group();
+                        }
+
                         if opacity < 1.0 {
                             self.cr.paint_with_alpha(opacity)?;
                         } else {
                             self.cr.paint()?;
                         }
+
+                        if linear_light {
+                            let group = self.cr.pop_group()?;
+                            composite_in_linear_light(&self.cr, &group)?;
+                        }
                     }
 
                     self.cr.set_matrix(affine_at_start.into());
@@ -875,6 +1749,155 @@ impl DrawingCtx {
         res
     }
 
+    /// Computes the bounding box of a layer for geometry queries, without painting it.
+    ///
+    /// The ink rectangle accounts for what compositing the layer would do to its pixels:
+    /// a filter can paint anywhere in its effects region, like a drop shadow outside of the
+    /// element, and clipping paths, masks, and overflow clips limit where pixels get
+    /// painted.
+    fn measure_layer(
+        &mut self,
+        stacking_ctx: &StackingContext,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        viewport: &Viewport,
+        draw_fn: &mut dyn FnMut(
+            &mut AcquiredNodes<'_>,
+            &mut DrawingCtx,
+        ) -> Result<BoundingBox, InternalRenderingError>,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        let mut bbox = draw_fn(acquired_nodes, self)?;
+        let transform = *self.get_transform();
+
+        if let Some(ref filter) = stacking_ctx.filter {
+            if let Some(region) = self.filter_effects_region(
+                filter,
+                acquired_nodes,
+                viewport,
+                &stacking_ctx.element_name,
+                &bbox,
+            ) {
+                bbox.ink_rect = None;
+                bbox.insert_ink(
+                    &BoundingBox::new()
+                        .with_transform(transform)
+                        .with_ink_rect(region),
+                );
+            }
+        }
+
+        let mut clip_regions = Vec::new();
+
+        if let Some(rect) = stacking_ctx.clip_rect {
+            clip_regions.push(
+                BoundingBox::new()
+                    .with_transform(transform)
+                    .with_ink_rect(rect),
+            );
+        }
+
+        // As in with_discrete_layer(), clipping in user space doesn't need the bbox.
+        let clips = [
+            (&stacking_ctx.clip_in_user_space, self.empty_bbox()),
+            (&stacking_ctx.clip_in_object_space, bbox),
+        ];
+
+        for (clip_node, clip_bbox) in clips {
+            let Some(clip_node) = clip_node else {
+                continue;
+            };
+
+            self.cr.new_path();
+
+            if self.set_clip_path(clip_node, acquired_nodes, viewport, &clip_bbox)? {
+                let (x0, y0, x1, y1) = self.cr.fill_extents()?;
+                clip_regions.push(
+                    BoundingBox::new()
+                        .with_transform(transform)
+                        .with_ink_rect(Rect::new(x0, y0, x1, y1)),
+                );
+            }
+
+            self.cr.new_path();
+        }
+
+        if let (Some(mask_node), Some(bbox_rect)) = (&stacking_ctx.mask, bbox.rect) {
+            let mask = borrow_element_as!(mask_node, Mask);
+            let cascaded = CascadedValues::new_from_node(mask_node);
+            let values = cascaded.get();
+
+            let mask_units = mask.get_units();
+            let params = NormalizeParams::new(values, &viewport.with_units(mask_units));
+            let mask_rect = mask.get_rect(&params);
+
+            // Like in generate_cairo_mask(), an empty bounding box with objectBoundingBox
+            // units yields an empty mask.
+            let region = rect_to_transform(&Some(bbox_rect), mask_units)
+                .map(|t| t.transform_rect(&mask_rect))
+                .unwrap_or_default();
+
+            clip_regions.push(
+                BoundingBox::new()
+                    .with_transform(values.transform().post_transform(&transform))
+                    .with_ink_rect(region),
+            );
+        }
+
+        if bbox.ink_rect.is_some() {
+            for region in &clip_regions {
+                bbox.clip(region);
+            }
+        }
+
+        Ok(bbox)
+    }
+
+    /// Computes the rectangle where a filter chain can paint, in the current user space.
+    ///
+    /// This is the effects region of the last filter in the chain, since each filter only
+    /// produces pixels inside its own region.  Returns `None` if the chain is in error,
+    /// since then it gets ignored during rendering.
+    fn filter_effects_region(
+        &mut self,
+        filter: &Filter,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        viewport: &Viewport,
+        node_name: &str,
+        node_bbox: &BoundingBox,
+    ) -> Option<Rect> {
+        let user_space_params = NormalizeParams::from_values(
+            &filter.normalize_values,
+            &viewport.with_units(CoordUnits::UserSpaceOnUse),
+        );
+
+        let specs = self
+            .filter_specs(
+                viewport,
+                filter,
+                acquired_nodes,
+                node_name,
+                &user_space_params,
+            )
+            .ok()?;
+
+        let user_space_filter = &specs.last()?.user_space_filter;
+
+        // Like FilterContext, use an empty rectangle for elements without a bounding box.
+        let bbox_rect = node_bbox.rect.unwrap_or_default();
+        let bbox_transform = match user_space_filter.filter_units {
+            CoordUnits::UserSpaceOnUse => Transform::identity(),
+            CoordUnits::ObjectBoundingBox => Transform::new_unchecked(
+                bbox_rect.width(),
+                0.0,
+                0.0,
+                bbox_rect.height(),
+                bbox_rect.x0,
+                bbox_rect.y0,
+            ),
+        };
+
+        Some(bbox_transform.transform_rect(&user_space_filter.rect))
+    }
+
     /// Run the drawing function with the specified opacity
     fn with_alpha(
         &mut self,
@@ -908,29 +1931,24 @@ impl DrawingCtx {
         self.cr.tag_end(CAIRO_TAG_LINK);
     }
 
-    fn run_filters(
-        &mut self,
+    /// Converts each item in a filter list to a `FilterSpec`.
+    ///
+    /// The spec mentions, "If the filter references a non-existent object or the
+    /// referenced object is not a filter element, then the whole filter chain is
+    /// ignored." - <https://www.w3.org/TR/filter-effects/#FilterProperty>
+    ///
+    /// So, this returns an `Err` if any of the conversions failed.
+    fn filter_specs(
+        &self,
         viewport: &Viewport,
-        surface_to_filter: SharedImageSurface,
         filter: &Filter,
         acquired_nodes: &mut AcquiredNodes<'_>,
         node_name: &str,
         user_space_params: &NormalizeParams,
-        stroke_paint_source: Rc<UserSpacePaintSource>,
-        fill_paint_source: Rc<UserSpacePaintSource>,
-        node_bbox: BoundingBox,
-    ) -> Result<SharedImageSurface, InternalRenderingError> {
+    ) -> Result<Vec<FilterSpec>, FilterResolveError> {
         let session = self.session();
 
-        // We try to convert each item in the filter_list to a FilterSpec.
-        //
-        // However, the spec mentions, "If the filter references a non-existent object or
-        // the referenced object is not a filter element, then the whole filter chain is
-        // ignored." - https://www.w3.org/TR/filter-effects/#FilterProperty
-        //
-        // So, run through the filter_list and collect into a Result<Vec<FilterSpec>>.
-        // This will return an Err if any of the conversions failed.
-        let filter_specs = filter
+        filter
             .filter_list
             .iter()
             .map(|filter_value| {
@@ -943,7 +1961,28 @@ impl DrawingCtx {
                     node_name,
                 )
             })
-            .collect::<Result<Vec<FilterSpec>, _>>();
+            .collect()
+    }
+
+    fn run_filters(
+        &mut self,
+        viewport: &Viewport,
+        surface_to_filter: SharedImageSurface,
+        filter: &Filter,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        node_name: &str,
+        user_space_params: &NormalizeParams,
+        stroke_paint_source: Rc<UserSpacePaintSource>,
+        fill_paint_source: Rc<UserSpacePaintSource>,
+        node_bbox: BoundingBox,
+    ) -> Result<SharedImageSurface, InternalRenderingError> {
+        let filter_specs = self.filter_specs(
+            viewport,
+            filter,
+            acquired_nodes,
+            node_name,
+            user_space_params,
+        );
 
         match filter_specs {
             Ok(specs) => {
@@ -971,7 +2010,7 @@ impl DrawingCtx {
                     e
                 );
                 // just return the original surface without filtering it
-                Ok(surface_to_filter)
+                self.error_placeholder_surface(surface_to_filter, &node_bbox)
             }
         }
     }
@@ -995,7 +2034,12 @@ impl DrawingCtx {
         g.set_matrix(ValidTransform::try_from(gradient.transform)?.into());
         g.set_extend(cairo::Extend::from(gradient.spread));
 
-        for stop in &gradient.stops {
+        let stops = match self.quality {
+            RenderingQuality::Compatible => Cow::Borrowed(&gradient.stops),
+            RenderingQuality::High => Cow::Owned(gradient.stops_in_interpolation_space()),
+        };
+
+        for stop in stops.iter() {
             let UnitInterval(stop_offset) = stop.offset;
 
             let rgba = color_to_rgba(&stop.color);
@@ -1012,9 +2056,14 @@ impl DrawingCtx {
         Ok(self.cr.set_source(&g)?)
     }
 
+    /// Sets the pattern as the source of the Cairo context.
+    ///
+    /// Returns `false` if there is nothing to paint with the pattern; the caller then
+    /// uses the fallback color instead, if `has_fallback`.
     fn set_pattern(
         &mut self,
         pattern: &UserSpacePattern,
+        has_fallback: bool,
         acquired_nodes: &mut AcquiredNodes<'_>,
     ) -> Result<bool, InternalRenderingError> {
         // Bail out early if the pattern has zero size, per the spec
@@ -1026,11 +2075,26 @@ impl DrawingCtx {
         let pattern_node_acquired = match pattern.acquire_pattern_node(acquired_nodes) {
             Ok(n) => n,
 
-            Err(AcquireError::CircularReference(ref node)) => {
+            Err(ref e @ AcquireError::CircularReference(ref node)) => {
                 rsvg_log!(self.session, "circular reference in element {}", node);
+
+                self.session
+                    .add_broken_paint_reference(BrokenPaintReference {
+                        reference: pattern.reference.to_string(),
+                        reason: e.to_string(),
+                        used_fallback: has_fallback,
+                    });
+
                 return Ok(false);
             }
 
+            Err(e @ AcquireError::ReferenceChainTooLong(chain, _)) => {
+                rsvg_log!(self.session, "{}", e);
+                return Err(InternalRenderingError::LimitExceeded(
+                    ImplementationLimit::ReferenceChainTooLong(chain),
+                ));
+            }
+
             _ => unreachable!(),
         };
 
@@ -1140,29 +2204,56 @@ impl DrawingCtx {
         paint_source: &UserSpacePaintSource,
         acquired_nodes: &mut AcquiredNodes<'_>,
     ) -> Result<bool, InternalRenderingError> {
+        let spot_colors = acquired_nodes.spot_colors();
+
         match *paint_source {
             UserSpacePaintSource::Gradient(ref gradient, _c) => {
                 self.set_gradient(gradient)?;
                 Ok(true)
             }
             UserSpacePaintSource::Pattern(ref pattern, ref c) => {
-                if self.set_pattern(pattern, acquired_nodes)? {
+                if self.set_pattern(pattern, c.is_some(), acquired_nodes)? {
                     Ok(true)
                 } else if let Some(c) = c {
-                    set_source_color_on_cairo(&self.cr, c);
+                    set_source_color_with_overprint(&self.cr, c, spot_colors);
                     Ok(true)
                 } else {
                     Ok(false)
                 }
             }
             UserSpacePaintSource::SolidColor(ref c) => {
-                set_source_color_on_cairo(&self.cr, c);
+                set_source_color_with_overprint(&self.cr, c, spot_colors);
                 Ok(true)
             }
             UserSpacePaintSource::None => Ok(false),
         }
     }
 
+    /// Sets `paint_source` as the source and calls `draw` to paint with it.
+    ///
+    /// The source for a spot ink changes the operator to simulate overprint; this puts
+    /// back the previous operator after `draw`, so it does not leak into later paints.
+    /// Does nothing if there is no paint server.
+    fn paint_with_source<F>(
+        &mut self,
+        paint_source: &UserSpacePaintSource,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        draw: F,
+    ) -> Result<(), InternalRenderingError>
+    where
+        F: FnOnce(&mut DrawingCtx) -> Result<(), InternalRenderingError>,
+    {
+        let operator = self.cr.operator();
+
+        if self.set_paint_source(paint_source, acquired_nodes)? {
+            let res = draw(self);
+            self.cr.set_operator(operator);
+            res?;
+        }
+
+        Ok(())
+    }
+
     /// Computes and returns a surface corresponding to the given paint server.
     pub fn get_paint_source_surface(
         &mut self,
@@ -1178,13 +2269,10 @@ impl DrawingCtx {
 
             // FIXME: we are ignoring any error
 
-            let had_paint_server =
-                temporary_draw_ctx.set_paint_source(paint_source, acquired_nodes)?;
-            if had_paint_server {
-                temporary_draw_ctx.cr.paint()?;
-            }
-
-            Ok(())
+            temporary_draw_ctx.paint_with_source(paint_source, acquired_nodes, |dc| {
+                dc.cr.paint()?;
+                Ok(())
+            })
         })?;
 
         Ok(surface.share()?)
@@ -1196,12 +2284,10 @@ impl DrawingCtx {
         acquired_nodes: &mut AcquiredNodes<'_>,
         paint_source: &UserSpacePaintSource,
     ) -> Result<(), InternalRenderingError> {
-        let had_paint_server = self.set_paint_source(paint_source, acquired_nodes)?;
-        if had_paint_server {
+        self.paint_with_source(paint_source, acquired_nodes, |_| {
             cr.stroke_preserve()?;
-        }
-
-        Ok(())
+            Ok(())
+        })
     }
 
     fn fill(
@@ -1210,29 +2296,10 @@ impl DrawingCtx {
         acquired_nodes: &mut AcquiredNodes<'_>,
         paint_source: &UserSpacePaintSource,
     ) -> Result<(), InternalRenderingError> {
-        let had_paint_server = self.set_paint_source(paint_source, acquired_nodes)?;
-        if had_paint_server {
+        self.paint_with_source(paint_source, acquired_nodes, |_| {
             cr.fill_preserve()?;
-        }
-
-        Ok(())
-    }
-
-    pub fn compute_path_extents(
-        &self,
-        path: &Path,
-    ) -> Result<Option<Rect>, InternalRenderingError> {
-        if path.is_empty() {
-            return Ok(None);
-        }
-
-        let surface = cairo::RecordingSurface::create(cairo::Content::ColorAlpha, None)?;
-        let cr = cairo::Context::new(&surface)?;
-
-        path.to_cairo(&cr, false)?;
-        let (x0, y0, x1, y1) = cr.path_extents()?;
-
-        Ok(Some(Rect::new(x0, y0, x1, y1)))
+            Ok(())
+        })
     }
 
     pub fn draw_layer(
@@ -1288,8 +2355,13 @@ impl DrawingCtx {
                 let cr = dc.cr.clone();
 
                 let transform = dc.get_transform_for_stacking_ctx(stacking_ctx, clipping)?;
-                let mut path_helper =
-                    PathHelper::new(&cr, transform, &shape.path, shape.stroke.line_cap);
+                let mut path_helper = PathHelper::new(
+                    &cr,
+                    transform,
+                    &shape.path,
+                    shape.stroke.line_cap,
+                    dc.path_simplification,
+                );
 
                 if clipping {
                     if shape.is_visible {
@@ -1306,18 +2378,37 @@ impl DrawingCtx {
                 cr.set_fill_rule(cairo::FillRule::from(shape.fill_rule));
 
                 path_helper.set()?;
-                let bbox = compute_stroke_and_fill_box(
+                let mut bbox = compute_stroke_and_fill_box(
                     &cr,
+                    &shape.path,
                     &shape.stroke,
                     &shape.stroke_paint,
                     &dc.initial_viewport,
                 )?;
 
+                if shape.is_visible {
+                    dc.hit_test(&cr, |x, y| {
+                        let has_fill = !matches!(shape.fill_paint, UserSpacePaintSource::None);
+                        let has_stroke = !matches!(shape.stroke_paint, UserSpacePaintSource::None);
+
+                        Ok(
+                            (has_fill && cr.in_fill(x, y)?)
+                                || (has_stroke && cr.in_stroke(x, y)?),
+                        )
+                    })?;
+                }
+
+                // Markers may extend beyond the shape's ink rectangle, so they are drawn
+                // even when the shape itself is offscreen.
+                let is_offscreen = dc.is_hidden(&cr, bbox.ink_rect);
+
                 if shape.is_visible {
                     for &target in &shape.paint_order.targets {
                         // fill and stroke operations will preserve the path.
                         // markers operation will clear the path.
                         match target {
+                            PaintTarget::Fill | PaintTarget::Stroke if is_offscreen => (),
+
                             PaintTarget::Fill => {
                                 path_helper.set()?;
                                 dc.fill(&cr, an, &shape.fill_paint)?;
@@ -1343,9 +2434,10 @@ impl DrawingCtx {
 
                             PaintTarget::Markers => {
                                 path_helper.unset();
-                                marker::render_markers_for_shape(
+                                let marker_bbox = marker::render_markers_for_shape(
                                     shape, viewport, dc, an, clipping,
                                 )?;
+                                bbox.insert_ink(&marker_bbox);
                             }
                         }
                     }
@@ -1387,6 +2479,63 @@ impl DrawingCtx {
         cr.paint()
     }
 
+    /// Downscales an image with a high-quality filter, if the rendering quality asks for it.
+    ///
+    /// Cairo's bilinear filter aliases badly when an image is shrunk a lot, which is common
+    /// for thumbnails that get squeezed with `preserveAspectRatio="none"`.  With
+    /// [`RenderingQuality::High`], such images are resampled to their size in device space
+    /// before painting them.  Must be called with the image's viewport already set up.
+    ///
+    /// With `linear_light`, all images that get shrunk are resampled, in linear RGB, since
+    /// Cairo would blend their pixels in sRGB.
+    fn resample_image(
+        &self,
+        image: &Image,
+        linear_light: bool,
+    ) -> Result<Option<SharedImageSurface>, cairo::Error> {
+        let wanted =
+            linear_light || (self.quality == RenderingQuality::High && image.aspect.is_none());
+
+        if !wanted
+            || !matches!(
+                Interpolation::from(image.image_rendering),
+                Interpolation::Smooth
+            )
+        {
+            return Ok(None);
+        }
+
+        let transform = self.get_transform();
+        let scale_x = transform.xx.hypot(transform.yx);
+        let scale_y = transform.xy.hypot(transform.yy);
+
+        // Cairo does fine with small downscales, as long as it can blend in sRGB.
+        let threshold = if linear_light { 1.0 } else { 0.5 };
+        if scale_x >= threshold && scale_y >= threshold {
+            return Ok(None);
+        }
+
+        let size = |len: i32, scale: f64| {
+            let scaled = (f64::from(len) * scale.min(1.0)).ceil() as i32;
+            scaled.clamp(1, len)
+        };
+
+        let width = size(image.surface.width(), scale_x);
+        let height = size(image.surface.height(), scale_y);
+
+        if linear_light {
+            let bounds = IRect::from_size(image.surface.width(), image.surface.height());
+            let resampled = image
+                .surface
+                .to_linear_rgb(bounds)?
+                .resample(width, height)?;
+
+            Ok(Some(resampled.to_srgb(IRect::from_size(width, height))?))
+        } else {
+            Ok(Some(image.surface.resample(width, height)?))
+        }
+    }
+
     fn draw_image(
         &mut self,
         image: &Image,
@@ -1424,7 +2573,13 @@ impl DrawingCtx {
                 acquired_nodes,
                 viewport, // FIXME: should this be the push_new_viewport below?
                 clipping,
-                &mut |_an, dc| {
+                &mut |an, dc| {
+                    dc.hit_test(&dc.cr, |x, y| Ok(image.rect.contains(x, y)))?;
+
+                    if dc.is_hidden(&dc.cr, Some(image.rect)) {
+                        return Ok(bounds);
+                    }
+
                     with_saved_cr(&dc.cr.clone(), || {
                         if let Some(_params) = dc.push_new_viewport(
                             viewport,
@@ -1433,12 +2588,21 @@ impl DrawingCtx {
                             image.aspect,
                             clip_mode,
                         ) {
-                            dc.paint_surface(
-                                &image.surface,
-                                image_width,
-                                image_height,
-                                image.image_rendering,
-                            )?;
+                            if let Some(surface) = dc.resample_image(image, an.linear_light())? {
+                                // Paint the resampled surface at the size of the original one.
+                                let (width, height) =
+                                    (f64::from(surface.width()), f64::from(surface.height()));
+                                dc.cr.scale(image_width / width, image_height / height);
+
+                                dc.paint_surface(&surface, width, height, image.image_rendering)?;
+                            } else {
+                                dc.paint_surface(
+                                    &image.surface,
+                                    image_width,
+                                    image_height,
+                                    image.image_rendering,
+                                )?;
+                            }
                         }
 
                         Ok(bounds)
@@ -1469,20 +2633,30 @@ impl DrawingCtx {
         // viewers.
         let can_use_text_as_path = self.cr.target().type_() != cairo::SurfaceType::Pdf;
 
+        // The text-rendering property takes precedence; otherwise, use the antialiasing
+        // mode from the font options that the caller set on the handle, if any.
+        let antialias = match cairo::Antialias::from(span.text_rendering) {
+            cairo::Antialias::Default => acquired_nodes
+                .font_options()
+                .map(|o| o.antialias())
+                .unwrap_or(cairo::Antialias::Default),
+            a => a,
+        };
+
         with_saved_cr(&self.cr.clone(), || {
-            self.cr
-                .set_antialias(cairo::Antialias::from(span.text_rendering));
+            self.cr.set_antialias(antialias);
 
             setup_cr_for_stroke(&self.cr, &span.stroke);
 
             if clipping {
-                path.to_cairo(&self.cr, false)?;
+                path.to_cairo(&self.cr, false, None)?;
                 return Ok(self.empty_bbox());
             }
 
-            path.to_cairo(&self.cr, false)?;
+            path.to_cairo(&self.cr, false, None)?;
             let bbox = compute_stroke_and_fill_box(
                 &self.cr,
+                &path,
                 &span.stroke,
                 &span.stroke_paint,
                 &self.initial_viewport,
@@ -1490,6 +2664,16 @@ impl DrawingCtx {
             self.cr.new_path();
 
             if span.is_visible {
+                self.hit_test(&self.cr, |x, y| {
+                    Ok(bbox.ink_rect.is_some_and(|r| r.contains(x, y)))
+                })?;
+            }
+
+            if span.is_visible && !self.is_hidden(&self.cr, bbox.ink_rect) {
+                if self.record_text_run(span, &bbox) {
+                    return Ok(bbox);
+                }
+
                 if let Some(ref link_target) = span.link_target {
                     self.link_tag_begin(link_target);
                 }
@@ -1497,41 +2681,38 @@ impl DrawingCtx {
                 for &target in &span.paint_order.targets {
                     match target {
                         PaintTarget::Fill => {
-                            let had_paint_server =
-                                self.set_paint_source(&span.fill_paint, acquired_nodes)?;
-
-                            if had_paint_server {
+                            self.paint_with_source(&span.fill_paint, acquired_nodes, |dc| {
                                 if can_use_text_as_path {
-                                    path.to_cairo(&self.cr, false)?;
-                                    self.cr.fill()?;
-                                    self.cr.new_path();
+                                    path.to_cairo(&dc.cr, false, None)?;
+                                    dc.cr.fill()?;
+                                    dc.cr.new_path();
                                 } else {
-                                    self.cr.move_to(span.x, span.y);
+                                    dc.cr.move_to(span.x, span.y);
 
-                                    let matrix = self.cr.matrix();
+                                    let matrix = dc.cr.matrix();
 
                                     let rotation_from_gravity = span.gravity.to_rotation();
                                     if !rotation_from_gravity.approx_eq_cairo(0.0) {
-                                        self.cr.rotate(-rotation_from_gravity);
+                                        dc.cr.rotate(-rotation_from_gravity);
                                     }
 
-                                    pangocairo::functions::update_layout(&self.cr, &span.layout);
-                                    pangocairo::functions::show_layout(&self.cr, &span.layout);
+                                    pangocairo::functions::update_layout(&dc.cr, &span.layout);
+                                    pangocairo::functions::show_layout(&dc.cr, &span.layout);
 
-                                    self.cr.set_matrix(matrix);
+                                    dc.cr.set_matrix(matrix);
                                 }
-                            }
+
+                                Ok(())
+                            })?;
                         }
 
                         PaintTarget::Stroke => {
-                            let had_paint_server =
-                                self.set_paint_source(&span.stroke_paint, acquired_nodes)?;
-
-                            if had_paint_server {
-                                path.to_cairo(&self.cr, false)?;
-                                self.cr.stroke()?;
-                                self.cr.new_path();
-                            }
+                            self.paint_with_source(&span.stroke_paint, acquired_nodes, |dc| {
+                                path.to_cairo(&dc.cr, false, None)?;
+                                dc.cr.stroke()?;
+                                dc.cr.new_path();
+                                Ok(())
+                            })?;
                         }
 
                         PaintTarget::Markers => {}
@@ -1647,36 +2828,543 @@ impl DrawingCtx {
 
         self.cr = save_cr;
 
-        Ok(SharedImageSurface::wrap(surface, SurfaceType::SRgb)?)
+        Ok(SharedImageSurface::wrap(surface, SurfaceType::SRgb)?)
+    }
+
+    /// Tells the session's [`crate::RenderObserver`] that `node` is about to be drawn, and
+    /// returns when that happened.
+    ///
+    /// Returns `None` if there is no observer, or if the node is not really being drawn
+    /// because this is for clipping or measuring.
+    #[cfg(feature = "render-observers")]
+    fn observe_element_start(&self, node: &Node, clipping: bool) -> Option<std::time::Instant> {
+        let observer = self.session.render_observer()?;
+
+        if clipping || self.measuring || !node.is_element() {
+            return None;
+        }
+
+        let element = node.borrow_element();
+        observer.before_element(&ObservedElement {
+            id: element.get_id(),
+            name: element.element_name().local.as_ref(),
+            cr: &self.cr,
+        });
+
+        Some(std::time::Instant::now())
+    }
+
+    /// Tells the session's [`crate::RenderObserver`] that `node` was drawn, if
+    /// [`observe_element_start`](#method.observe_element_start) told it about the node.
+    #[cfg(feature = "render-observers")]
+    fn observe_element_end(
+        &self,
+        node: &Node,
+        start: Option<std::time::Instant>,
+        res: &Result<BoundingBox, InternalRenderingError>,
+    ) {
+        let (Some(start), Some(observer)) = (start, self.session.render_observer()) else {
+            return;
+        };
+
+        let elapsed = start.elapsed();
+
+        let bounds = res.as_ref().ok().and_then(|bbox| {
+            let mut device_bbox = BoundingBox::new();
+            device_bbox.insert(bbox);
+            device_bbox.ink_rect.map(cairo::Rectangle::from)
+        });
+
+        let element = node.borrow_element();
+        observer.after_element(
+            &ObservedElement {
+                id: element.get_id(),
+                name: element.element_name().local.as_ref(),
+                cr: &self.cr,
+            },
+            bounds,
+            elapsed,
+        );
+    }
+
+    pub fn draw_node_from_stack(
+        &mut self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        cascaded: &CascadedValues<'_>,
+        viewport: &Viewport,
+        clipping: bool,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        let stack_top = self.drawsub_stack.pop();
+
+        let draw = if let Some(ref top) = stack_top {
+            top == node
+        } else {
+            true
+        };
+
+        let res = if draw {
+            let recording = self
+                .element_boxes
+                .as_ref()
+                .is_some_and(|boxes| boxes.borrow_mut().enter(node));
+
+            let use_static_raster = stack_top.is_none()
+                && !clipping
+                && !self.occluded
+                && !self.measuring
+                && cascaded.is_from_node()
+                && self.cr.target().type_() == cairo::SurfaceType::Image
+                && acquired_nodes.spot_colors().is_empty()
+                && acquired_nodes.static_rasters().borrow().is_static(node);
+
+            #[cfg(feature = "render-observers")]
+            let start = self.observe_element_start(node, clipping);
+
+            let res = if use_static_raster {
+                self.draw_static_raster(node, acquired_nodes, cascaded, viewport)
+            } else {
+                node.draw(acquired_nodes, cascaded, viewport, self, clipping)
+            };
+
+            #[cfg(feature = "render-observers")]
+            self.observe_element_end(node, start, &res);
+
+            if recording {
+                if let Some(ref boxes) = self.element_boxes {
+                    boxes.borrow_mut().leave(node, res.as_ref().ok());
+                }
+            }
+
+            res
+        } else {
+            Ok(self.empty_bbox())
+        };
+
+        if let Some(top) = stack_top {
+            self.drawsub_stack.push(top);
+        }
+
+        res
+    }
+
+    /// Finds the last of `children` that hides everything drawn before it.
+    ///
+    /// This is an opaque `<rect>` that covers the whole clip region, like a background that
+    /// fills the canvas.  Returns `None` if there is no such child, or if only part of the
+    /// tree is being drawn, since then the covering element may not get drawn at all.
+    pub fn find_occluder(
+        &self,
+        children: &[Node],
+        cascaded: &CascadedValues<'_>,
+        viewport: &Viewport,
+    ) -> Option<usize> {
+        if !self.drawsub_stack.is_empty() {
+            return None;
+        }
+
+        let (x0, y0, x1, y1) = self.cr.clip_extents().ok()?;
+        let clip = Rect::new(x0, y0, x1, y1);
+
+        children.iter().rposition(|child| {
+            is_element_of_type!(child, Rect)
+                && opaque_rect_area(
+                    CascadedValues::clone_with_node(cascaded, child).get(),
+                    viewport,
+                )
+                .is_some_and(|area| area.intersection(&clip) == Some(clip))
+        })
+    }
+
+    /// Lays out `node` for painting in a [`ShapeBatch`], if it qualifies.
+    ///
+    /// Returns `None` for nodes that must be drawn on their own.  Batching is only done for
+    /// normal rendering, not while clipping, measuring, recording element boxes, or
+    /// reporting elements to a render observer, since those need to go through each
+    /// element separately.
+    pub fn layout_for_batch(
+        &self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        cascaded: &CascadedValues<'_>,
+        viewport: &Viewport,
+        clipping: bool,
+    ) -> Option<Result<Layer, InternalRenderingError>> {
+        #[cfg(feature = "render-observers")]
+        let observed = self.session.render_observer().is_some();
+        #[cfg(not(feature = "render-observers"))]
+        let observed = false;
+
+        let can_batch = !clipping
+            && !self.measuring
+            && !observed
+            && self.drawsub_stack.is_empty()
+            && self.element_boxes.is_none()
+            && is_batchable(node, cascaded.get())
+            && !acquired_nodes.static_rasters().borrow().is_static(node);
+
+        if can_batch {
+            shapes::layout_shape(node, acquired_nodes, cascaded, viewport, self)
+        } else {
+            None
+        }
+    }
+
+    /// Paints the shapes in `batch` and empties it.
+    ///
+    /// Returns the union of the shapes' bounding boxes.
+    pub fn draw_shape_batch(
+        &mut self,
+        batch: &mut ShapeBatch,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        viewport: &Viewport,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        let layers = mem::take(&mut batch.layers);
+
+        let first = match layers[..] {
+            [] => return Ok(self.empty_bbox()),
+
+            // A lone shape gets drawn as usual.
+            [ref layer] => {
+                return match self.draw_layer(layer, acquired_nodes, false, viewport) {
+                    Err(InternalRenderingError::InvalidTransform) => Ok(self.empty_bbox()),
+                    res => res,
+                };
+            }
+
+            [ref first, ..] => first,
+        };
+
+        // The shapes share a transform; if it is not invertible, none of them get displayed.
+        let Ok(transform) = ValidTransform::try_from(first.stacking_ctx.transform) else {
+            return Ok(self.empty_bbox());
+        };
+
+        let shapes: Vec<&Shape> = layers
+            .iter()
+            .filter_map(|layer| match layer.kind {
+                LayerKind::Shape(ref shape) if shape.extents.is_some() => Some(&**shape),
+                _ => None,
+            })
+            .collect();
+
+        let mut bbox = self.empty_bbox();
+
+        let cr = self.cr.clone();
+
+        with_saved_cr(&cr, || {
+            cr.transform(transform.into());
+            let transform = self.get_transform();
+
+            let Some(&first_shape) = shapes.first() else {
+                return Ok(bbox);
+            };
+
+            cr.set_antialias(cairo::Antialias::from(first_shape.shape_rendering));
+            setup_cr_for_stroke(&cr, &first_shape.stroke);
+
+            // Shapes with the same orientation don't cancel each other out with this rule.
+            cr.set_fill_rule(cairo::FillRule::Winding);
+
+            let mut visible_shapes = Vec::with_capacity(shapes.len());
+
+            for shape in shapes.iter() {
+                let mut path_helper = PathHelper::new(
+                    &cr,
+                    transform,
+                    &shape.path,
+                    shape.stroke.line_cap,
+                    self.path_simplification,
+                );
+
+                path_helper.set()?;
+                let shape_bbox = compute_stroke_and_fill_box(
+                    &cr,
+                    &shape.path,
+                    &shape.stroke,
+                    &shape.stroke_paint,
+                    &self.initial_viewport,
+                )?;
+                path_helper.unset();
+
+                if !self.is_hidden(&cr, shape_bbox.ink_rect) {
+                    visible_shapes.push(shape);
+                }
+
+                bbox.insert(&shape_bbox);
+            }
+
+            for shape in &visible_shapes {
+                shape.path.to_cairo(
+                    &cr,
+                    shape.stroke.line_cap == StrokeLinecap::Square,
+                    self.path_simplification,
+                )?;
+            }
+
+            if !visible_shapes.is_empty() {
+                if let Some(BatchPaint::Stroke(..)) = batch_paint(first_shape) {
+                    self.stroke(&cr, acquired_nodes, &first_shape.stroke_paint)?;
+                } else {
+                    self.fill(&cr, acquired_nodes, &first_shape.fill_paint)?;
+                }
+            }
+
+            cr.new_path();
+
+            Ok(bbox)
+        })
+    }
+
+    /// Runs `draw_fn` with painting turned off, for elements that are covered by an occluder.
+    ///
+    /// The elements are still laid out, so their bounding boxes are computed as usual.
+    pub fn with_occlusion(
+        &mut self,
+        draw_fn: &mut dyn FnMut(&mut DrawingCtx) -> Result<BoundingBox, InternalRenderingError>,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        let was_occluded = self.occluded;
+        self.occluded = true;
+        let res = draw_fn(self);
+        self.occluded = was_occluded;
+        res
+    }
+
+    /// Whether painting `rect` on `cr` would have no visible effect.
+    ///
+    /// While measuring, only the bounding boxes matter, so nothing gets painted at all.
+    fn is_hidden(&self, cr: &cairo::Context, rect: Option<Rect>) -> bool {
+        self.measuring || self.occluded || is_outside_clip(cr, rect)
+    }
+
+    /// Draws a static element from its cached raster, rendering the raster first if needed.
+    fn draw_static_raster(
+        &mut self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        cascaded: &CascadedValues<'_>,
+        viewport: &Viewport,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        let current = *self.get_transform();
+        let cr_stack_depth = self.cr_stack.borrow().len();
+
+        let affines =
+            CompositingAffines::new(current, self.initial_viewport.transform, cr_stack_depth);
+
+        let key = StaticRasterKey {
+            transform: current,
+            initial_transform: self.initial_viewport.transform,
+            viewport: self.toplevel_viewport(),
+            cr_stack_depth,
+            dpi: self.dpi,
+            quality: self.quality,
+        };
+
+        let cached = acquired_nodes.static_rasters().borrow().lookup(node, &key);
+
+        let (surface, bbox) = if let Some(cached) = cached {
+            cached
+        } else {
+            let surface = self.create_surface_for_toplevel_viewport()?;
+
+            let bbox = {
+                let cr = cairo::Context::new(&surface)?;
+                cr.set_matrix(ValidTransform::try_from(affines.for_temporary_surface)?.into());
+
+                let mut raster_draw_ctx = self.nested(cr);
+                node.draw(
+                    acquired_nodes,
+                    cascaded,
+                    viewport,
+                    &mut raster_draw_ctx,
+                    false,
+                )?
+            };
+
+            acquired_nodes
+                .static_rasters()
+                .borrow_mut()
+                .store(node, key, surface.clone(), bbox);
+
+            (surface, bbox)
+        };
+
+        with_saved_cr(&self.cr.clone(), || {
+            self.cr
+                .set_matrix(ValidTransform::try_from(affines.compositing)?.into());
+            self.cr.set_source_surface(&surface, 0.0, 0.0)?;
+            self.cr.paint()?;
+
+            Ok(bbox)
+        })
+    }
+
+    /// Draws the element referenced by a `<use>`, from an [`InstanceCache`] raster if possible.
+    ///
+    /// The `draw_fn` draws the element normally.  When the element gets rasterized,
+    /// `draw_fn` is called with a `DrawingCtx` for a temporary surface instead of `self`.
+    fn draw_use_target(
+        &mut self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        cascaded: &CascadedValues<'_>,
+        viewport: &Viewport,
+        clipping: bool,
+        draw_fn: &mut dyn FnMut(
+            &mut AcquiredNodes<'_>,
+            &mut DrawingCtx,
+        ) -> Result<BoundingBox, InternalRenderingError>,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        let can_use_cache = !clipping
+            && !self.occluded
+            && !self.measuring
+            && self.drawsub_stack.is_empty()
+            && self.element_boxes.is_none()
+            && self.cr.target().type_() == cairo::SurfaceType::Image
+            && acquired_nodes.spot_colors().is_empty()
+            && is_instanceable(cascaded.get());
+
+        if !can_use_cache {
+            return draw_fn(acquired_nodes, self);
+        }
+
+        let current = *self.get_transform();
+        let affines = CompositingAffines::new(
+            current,
+            self.initial_viewport.transform,
+            self.cr_stack.borrow().len(),
+        );
+        let t = affines.for_temporary_surface;
+
+        let position = ((t.x0 * 4.0).round() as i64, (t.y0 * 4.0).round() as i64);
+
+        let key = InstanceKey {
+            node: node.clone(),
+            values: cascaded.get().clone(),
+            linear_transform: Transform::new_unchecked(t.xx, t.yx, t.xy, t.yy, 0.0, 0.0),
+            subpixel_offset: (position.0.rem_euclid(4), position.1.rem_euclid(4)),
+            viewport: viewport.vbox,
+        };
+
+        let action = self.instance_cache.borrow_mut().use_instance(&key);
+
+        match action {
+            InstanceAction::DrawNormally => draw_fn(acquired_nodes, self),
+
+            InstanceAction::Composite(raster) => {
+                self.composite_instance(&raster, position, &affines)?;
+                Ok(raster.bbox.with_transform(current))
+            }
+
+            InstanceAction::Rasterize => {
+                if !node
+                    .descendants()
+                    .filter(|n| n.is_element())
+                    .all(|n| is_instanceable(n.borrow_element().get_computed_values()))
+                {
+                    self.instance_cache
+                        .borrow_mut()
+                        .store(&key, InstanceState::Uncacheable);
+                    return draw_fn(acquired_nodes, self);
+                }
+
+                let surface = self.create_surface_for_toplevel_viewport()?;
+
+                let bbox = {
+                    let cr = cairo::Context::new(&surface)?;
+                    cr.set_matrix(ValidTransform::try_from(t)?.into());
+
+                    let mut raster_draw_ctx = self.nested(cr);
+                    draw_fn(acquired_nodes, &mut raster_draw_ctx)?
+                };
+
+                let mut user_bbox = BoundingBox::new().with_transform(t);
+                user_bbox.insert(&bbox);
+
+                let mut device_bbox = BoundingBox::new();
+                device_bbox.insert(&bbox);
+
+                // Leave a pixel of margin for antialiasing.
+                let pixel_rect = device_bbox.ink_rect.map(|r| {
+                    let r = IRect::from(r);
+                    IRect::new(r.x0 - 1, r.y0 - 1, r.x1 + 1, r.y1 + 1)
+                });
+
+                let surface_rect = IRect::from_size(surface.width(), surface.height());
+
+                let state = match pixel_rect {
+                    None => InstanceState::Rasterized(InstanceRaster {
+                        position,
+                        rect: IRect::default(),
+                        surface: None,
+                        bbox: user_bbox,
+                    }),
+
+                    Some(rect) if surface_rect.intersection(&rect) == Some(rect) => {
+                        let pixels = cairo::ImageSurface::create(
+                            cairo::Format::ARgb32,
+                            rect.width(),
+                            rect.height(),
+                        )?;
+
+                        let cr = cairo::Context::new(&pixels)?;
+                        cr.set_source_surface(&surface, -f64::from(rect.x0), -f64::from(rect.y0))?;
+                        cr.paint()?;
+
+                        InstanceState::Rasterized(InstanceRaster {
+                            position,
+                            rect,
+                            surface: Some(pixels),
+                            bbox: user_bbox,
+                        })
+                    }
+
+                    Some(_) => InstanceState::Uncacheable,
+                };
+
+                // This instance is drawn from the full temporary surface, since it
+                // may not fit in the raster.
+                self.instance_cache.borrow_mut().store(&key, state);
+
+                with_saved_cr(&self.cr.clone(), || {
+                    self.cr
+                        .set_matrix(ValidTransform::try_from(affines.compositing)?.into());
+                    self.cr.set_source_surface(&surface, 0.0, 0.0)?;
+                    self.cr.paint()?;
+
+                    Ok(bbox)
+                })
+            }
+        }
     }
 
-    pub fn draw_node_from_stack(
+    /// Composites the raster of an instance that is drawn at `position`.
+    fn composite_instance(
         &mut self,
-        node: &Node,
-        acquired_nodes: &mut AcquiredNodes<'_>,
-        cascaded: &CascadedValues<'_>,
-        viewport: &Viewport,
-        clipping: bool,
-    ) -> Result<BoundingBox, InternalRenderingError> {
-        let stack_top = self.drawsub_stack.pop();
-
-        let draw = if let Some(ref top) = stack_top {
-            top == node
-        } else {
-            true
+        raster: &InstanceRaster,
+        position: (i64, i64),
+        affines: &CompositingAffines,
+    ) -> Result<(), InternalRenderingError> {
+        let Some(ref surface) = raster.surface else {
+            return Ok(());
         };
 
-        let res = if draw {
-            node.draw(acquired_nodes, cascaded, viewport, self, clipping)
-        } else {
-            Ok(self.empty_bbox())
-        };
+        // Both positions have the same subpixel offset, so this is a whole number of pixels.
+        let dx = (position.0 - raster.position.0) / 4;
+        let dy = (position.1 - raster.position.1) / 4;
 
-        if let Some(top) = stack_top {
-            self.drawsub_stack.push(top);
-        }
+        with_saved_cr(&self.cr.clone(), || {
+            self.cr
+                .set_matrix(ValidTransform::try_from(affines.compositing)?.into());
+            self.cr.set_source_surface(
+                surface,
+                f64::from(raster.rect.x0) + dx as f64,
+                f64::from(raster.rect.y0) + dy as f64,
+            )?;
+            self.cr.paint()?;
 
-        res
+            Ok(())
+        })
     }
 
     pub fn draw_from_use_node(
@@ -1698,7 +3386,7 @@ impl DrawingCtx {
         // another <use> which references the first one, etc.).  So,
         // we acquire the <use> element itself so that circular
         // references can be caught.
-        let _self_acquired = match acquired_nodes.acquire_ref(node) {
+        let _self_acquired = match acquired_nodes.acquire_in_chain(node, ReferenceChain::Use) {
             Ok(n) => n,
 
             Err(AcquireError::CircularReference(_)) => {
@@ -1706,6 +3394,13 @@ impl DrawingCtx {
                 return Ok(self.empty_bbox());
             }
 
+            Err(e @ AcquireError::ReferenceChainTooLong(chain, _)) => {
+                rsvg_log!(self.session, "{}", e);
+                return Err(InternalRenderingError::LimitExceeded(
+                    ImplementationLimit::ReferenceChainTooLong(chain),
+                ));
+            }
+
             _ => unreachable!(),
         };
 
@@ -1717,13 +3412,15 @@ impl DrawingCtx {
                 return Ok(self.empty_bbox());
             }
 
-            Err(AcquireError::MaxReferencesExceeded) => {
+            Err(AcquireError::MaxReferencesExceeded(max)) => {
                 return Err(InternalRenderingError::LimitExceeded(
-                    ImplementationLimit::TooManyReferencedElements,
+                    ImplementationLimit::TooManyReferencedElements(max),
                 ));
             }
 
-            Err(AcquireError::InvalidLinkType(_)) => unreachable!(),
+            Err(AcquireError::InvalidLinkType(_) | AcquireError::ReferenceChainTooLong(..)) => {
+                unreachable!()
+            }
 
             Err(AcquireError::LinkNotFound(node_id)) => {
                 rsvg_log!(
@@ -1750,8 +3447,17 @@ impl DrawingCtx {
 
         let orig_transform = self.get_transform();
 
-        self.cr
-            .transform(ValidTransform::try_from(values.transform())?.into());
+        // With display: contents, the <use> element itself is not drawn, so its transform,
+        // its x/y offset, and its layer are left out.
+        let unboxed = values.display() == Display::Contents;
+
+        let use_rect = if unboxed {
+            Rect::from_size(use_rect.width(), use_rect.height())
+        } else {
+            self.cr
+                .transform(ValidTransform::try_from(values.transform())?.into());
+            use_rect
+        };
 
         let use_element = node.borrow_element();
 
@@ -1781,17 +3487,10 @@ impl DrawingCtx {
                 ClipMode::NoClip
             };
 
-            let stacking_ctx = StackingContext::new(
-                self.session(),
-                acquired_nodes,
+            self.with_use_layer(
                 &use_element,
-                Transform::identity(),
-                None,
                 values,
-            );
-
-            self.with_discrete_layer(
-                &stacking_ctx,
+                Transform::identity(),
                 acquired_nodes,
                 viewport, // FIXME: should this be the child_viewport from below?
                 clipping,
@@ -1803,17 +3502,22 @@ impl DrawingCtx {
                         preserve_aspect_ratio,
                         clip_mode,
                     ) {
-                        child.draw_children(
+                        let cascaded = CascadedValues::new_from_values(
+                            child,
+                            values,
+                            Some(fill_paint.clone()),
+                            Some(stroke_paint.clone()),
+                        );
+
+                        dc.draw_use_target(
+                            child,
                             an,
-                            &CascadedValues::new_from_values(
-                                child,
-                                values,
-                                Some(fill_paint.clone()),
-                                Some(stroke_paint.clone()),
-                            ),
+                            &cascaded,
                             &child_viewport,
-                            dc,
                             clipping,
+                            &mut |an, dc| {
+                                child.draw_children(an, &cascaded, &child_viewport, dc, clipping)
+                            },
                         )
                     } else {
                         Ok(dc.empty_bbox())
@@ -1823,33 +3527,24 @@ impl DrawingCtx {
         } else {
             // otherwise the referenced node is not a <symbol>; process it generically
 
-            let stacking_ctx = StackingContext::new(
-                self.session(),
-                acquired_nodes,
+            self.with_use_layer(
                 &use_element,
-                Transform::new_translate(use_rect.x0, use_rect.y0),
-                None,
                 values,
-            );
-
-            self.with_discrete_layer(
-                &stacking_ctx,
+                Transform::new_translate(use_rect.x0, use_rect.y0),
                 acquired_nodes,
                 viewport,
                 clipping,
                 &mut |an, dc| {
-                    child.draw(
-                        an,
-                        &CascadedValues::new_from_values(
-                            child,
-                            values,
-                            Some(fill_paint.clone()),
-                            Some(stroke_paint.clone()),
-                        ),
-                        viewport,
-                        dc,
-                        clipping,
-                    )
+                    let cascaded = CascadedValues::new_from_values(
+                        child,
+                        values,
+                        Some(fill_paint.clone()),
+                        Some(stroke_paint.clone()),
+                    );
+
+                    dc.draw_use_target(child, an, &cascaded, viewport, clipping, &mut |an, dc| {
+                        child.draw(an, &cascaded, viewport, dc, clipping)
+                    })
                 },
             )
         };
@@ -1865,10 +3560,45 @@ impl DrawingCtx {
         }
     }
 
+    /// Draws the element referenced by a `<use>` in a layer for the `<use>` itself.
+    ///
+    /// For `display: contents` there is no such layer, and `draw_fn` gets called directly.
+    fn with_use_layer(
+        &mut self,
+        use_element: &Element,
+        values: &ComputedValues,
+        transform: Transform,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        viewport: &Viewport,
+        clipping: bool,
+        draw_fn: &mut dyn FnMut(
+            &mut AcquiredNodes<'_>,
+            &mut DrawingCtx,
+        ) -> Result<BoundingBox, InternalRenderingError>,
+    ) -> Result<BoundingBox, InternalRenderingError> {
+        if values.display() == Display::Contents {
+            return draw_fn(acquired_nodes, self);
+        }
+
+        let stacking_ctx = StackingContext::new(
+            self.session(),
+            acquired_nodes,
+            use_element,
+            transform,
+            None,
+            values,
+        );
+
+        self.with_discrete_layer(&stacking_ctx, acquired_nodes, viewport, clipping, draw_fn)
+    }
+
     /// Extracts the font options for the current state of the DrawingCtx.
     ///
+    /// The `user_options` are merged on top of librsvg's defaults; the ones that are not
+    /// set to their default value override librsvg's choices.
+    ///
     /// You can use the font options later with create_pango_context().
-    pub fn get_font_options(&self) -> FontOptions {
+    pub fn get_font_options(&self, user_options: Option<&cairo::FontOptions>) -> FontOptions {
         let mut options = cairo::FontOptions::new().unwrap();
         if self.testing {
             options.set_antialias(cairo::Antialias::Gray);
@@ -1877,6 +3607,10 @@ impl DrawingCtx {
         options.set_hint_style(cairo::HintStyle::None);
         options.set_hint_metrics(cairo::HintMetrics::Off);
 
+        if let Some(user_options) = user_options {
+            options.merge(user_options);
+        }
+
         FontOptions { options }
     }
 }
@@ -1897,8 +3631,17 @@ impl From<ImageRendering> for Interpolation {
 }
 
 /// Create a Pango context with a particular configuration.
-pub fn create_pango_context(font_options: &FontOptions, transform: &Transform) -> pango::Context {
-    let font_map = pangocairo::FontMap::default();
+///
+/// If `font_map` is `None`, this uses Pango's default font map, with the system's fonts.
+pub fn create_pango_context(
+    font_options: &FontOptions,
+    font_map: Option<&pangocairo::FontMap>,
+    transform: &Transform,
+) -> pango::Context {
+    let font_map = match font_map {
+        Some(font_map) => font_map.clone().upcast::<pango::FontMap>(),
+        None => pangocairo::FontMap::default(),
+    };
     let context = font_map.create_context();
 
     context.set_round_glyph_positions(false);
@@ -1951,6 +3694,99 @@ pub fn set_source_color_on_cairo(cr: &cairo::Context, color: &cssparser::Color)
     );
 }
 
+/// Sets a solid color as the source, simulating overprint if it stands for a spot ink.
+///
+/// A spot ink is painted with its preview color and the multiply operator; other colors
+/// are painted as usual.
+fn set_source_color_with_overprint(
+    cr: &cairo::Context,
+    color: &cssparser::Color,
+    spot_colors: &[SpotColor],
+) {
+    let rgba = color_to_rgba(color);
+
+    let spot = spot_colors.iter().find(|spot| {
+        (spot.color.red, spot.color.green, spot.color.blue) == (rgba.red, rgba.green, rgba.blue)
+    });
+
+    match spot {
+        Some(spot) => {
+            let ink = cssparser::RGBA {
+                alpha: rgba.alpha,
+                ..spot.ink
+            };
+            set_source_color_on_cairo(cr, &cssparser::Color::Rgba(ink));
+            cr.set_operator(cairo::Operator::Multiply);
+        }
+
+        None => set_source_color_on_cairo(cr, color),
+    }
+}
+
+/// Paints `group` over the current target of `cr`, blending in linear light.
+///
+/// Cairo can only blend sRGB values, so this reads back the pixels of the target, which
+/// must be an image surface, blends the group over them, and paints the result with the
+/// `Source` operator.  Pixels that the group does not cover are written back unchanged.
+fn composite_in_linear_light(
+    cr: &cairo::Context,
+    group: &cairo::Pattern,
+) -> Result<(), cairo::Error> {
+    let target = cr.group_target();
+    let (width, height) = cairo::ImageSurface::try_from(target.clone())
+        .map(|t| (t.width(), t.height()))
+        .map_err(|_| cairo::Error::SurfaceTypeMismatch)?;
+    let (x_offset, y_offset) = target.device_offset();
+
+    // Copies of the target and of the group, pixel by pixel in the target's device space.
+    let copy = |source: &cairo::Pattern, matrix: cairo::Matrix| {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+        surface.set_device_offset(x_offset, y_offset);
+
+        {
+            let copy_cr = cairo::Context::new(&surface)?;
+            copy_cr.set_matrix(matrix);
+            copy_cr.set_source(source)?;
+            copy_cr.paint()?;
+        }
+
+        SharedImageSurface::wrap(surface, SurfaceType::SRgb)
+    };
+
+    let backdrop = copy(
+        &cairo::SurfacePattern::create(&target),
+        cairo::Matrix::identity(),
+    )?;
+    let source = copy(group, cr.matrix())?;
+
+    let mut result = ExclusiveImageSurface::new(width, height, SurfaceType::SRgb)?;
+    result.modify(&mut |data, stride| {
+        for (x, y, dst) in Pixels::within(&backdrop, IRect::from_size(width, height)) {
+            let src = source.get_pixel(x, y);
+
+            let pixel = if src.a == 0 {
+                dst
+            } else {
+                srgb::composite_over_in_linear_light(src, dst)
+            };
+
+            data.set_pixel(stride, pixel, x, y);
+        }
+    });
+
+    let result = result.share()?.into_image_surface()?;
+    result.set_device_offset(x_offset, y_offset);
+
+    cr.save()?;
+    cr.identity_matrix();
+    cr.set_operator(cairo::Operator::Source);
+    cr.set_source_surface(&result, 0.0, 0.0)?;
+    let res = cr.paint();
+    cr.restore()?;
+
+    res
+}
+
 /// Converts a Pango layout to a Cairo path on the specified cr starting at (x, y).
 /// Does not clear the current path first.
 fn pango_layout_to_cairo(
@@ -2075,6 +3911,7 @@ impl CompositingAffines {
 
 fn compute_stroke_and_fill_extents(
     cr: &cairo::Context,
+    path: &Path,
     stroke: &Stroke,
     stroke_paint_source: &UserSpacePaintSource,
     initial_viewport: &Viewport,
@@ -2106,22 +3943,23 @@ fn compute_stroke_and_fill_extents(
     //
     // So, see if the stroke width is 0 and just not include the stroke in the
     // bounding box if so.
+    //
+    // Cairo's stroke extents are approximate, and can be too small for long miters, so
+    // they are computed from the path instead.  Non-scaling strokes are widened in a
+    // different coordinate space than the path's, so those are left to cairo.
 
     let stroke_extents = if !stroke.width.approx_eq_cairo(0.0)
         && !matches!(stroke_paint_source, UserSpacePaintSource::None)
     {
-        let backup_matrix = if stroke.non_scaling {
+        if stroke.non_scaling {
             let matrix = cr.matrix();
             cr.set_matrix(ValidTransform::try_from(initial_viewport.transform)?.into());
-            Some(matrix)
-        } else {
-            None
-        };
-        let (x0, y0, x1, y1) = cr.stroke_extents()?;
-        if let Some(matrix) = backup_matrix {
+            let (x0, y0, x1, y1) = cr.stroke_extents()?;
             cr.set_matrix(matrix);
+            Some(Rect::new(x0, y0, x1, y1))
+        } else {
+            stroke_extents::stroke_extents(path, stroke)
         }
-        Some(Rect::new(x0, y0, x1, y1))
     } else {
         None
     };
@@ -2144,12 +3982,13 @@ fn compute_stroke_and_fill_extents(
 
 fn compute_stroke_and_fill_box(
     cr: &cairo::Context,
+    path: &Path,
     stroke: &Stroke,
     stroke_paint_source: &UserSpacePaintSource,
     initial_viewport: &Viewport,
 ) -> Result<BoundingBox, InternalRenderingError> {
     let extents =
-        compute_stroke_and_fill_extents(cr, stroke, stroke_paint_source, initial_viewport)?;
+        compute_stroke_and_fill_extents(cr, path, stroke, stroke_paint_source, initial_viewport)?;
 
     let ink_rect = match (extents.fill, extents.stroke) {
         (None, None) => None,
@@ -2171,6 +4010,209 @@ fn compute_stroke_and_fill_box(
     Ok(bbox)
 }
 
+/// Whether a rectangle in the current user space of `cr` lies entirely outside its clip.
+///
+/// Painting such a rectangle has no visible effect, so shapes, images, and text spans use
+/// this to skip painting themselves.  This makes panning over huge documents faster,
+/// since most of their contents are offscreen.  Layers are not culled: their extents are
+/// only known after drawing their children, so they still get their temporary surfaces.
+/// A missing rectangle is never considered to be outside.
+fn is_outside_clip(cr: &cairo::Context, rect: Option<Rect>) -> bool {
+    match (rect, cr.clip_extents()) {
+        (Some(rect), Ok((x0, y0, x1, y1))) => {
+            Rect::new(x0, y0, x1, y1).intersection(&rect).is_none()
+        }
+        _ => false,
+    }
+}
+
+/// Whether an element with these values gets painted straight onto its parent's surface.
+///
+/// This is the case when nothing like opacity, masks, or blend modes makes librsvg render
+/// the element into a temporary surface first.
+fn is_composited_directly(values: &ComputedValues) -> bool {
+    let Opacity(UnitInterval(opacity)) = values.opacity();
+
+    opacity == 1.0
+        && values.filter() == crate::properties::Filter::None
+        && values.mask().0.get().is_none()
+        && values.clip_path().0.get().is_none()
+        && values.mix_blend_mode() == MixBlendMode::Normal
+}
+
+/// Whether an element with these values looks the same wherever it is drawn.
+///
+/// Elements in a subtree that gets rasterized for [`InstanceCache`] must not have filters or
+/// blend modes, which depend on what is around them, nor paint that comes from the
+/// `<use>` element that references them.
+fn is_instanceable(values: &ComputedValues) -> bool {
+    let is_context_paint =
+        |paint: PaintServer| matches!(paint, PaintServer::ContextFill | PaintServer::ContextStroke);
+
+    values.filter() == crate::properties::Filter::None
+        && values.mix_blend_mode() == MixBlendMode::Normal
+        && !is_context_paint(values.fill().0)
+        && !is_context_paint(values.stroke().0)
+}
+
+/// Whether `paint` is a solid color that is still fully opaque with the `opacity` applied.
+fn is_opaque_color(paint: &PaintServer, opacity: UnitInterval, values: &ComputedValues) -> bool {
+    match *paint {
+        PaintServer::SolidColor(ref color) => {
+            let color = resolve_color(color, opacity, &values.color().0);
+            color_to_rgba(&color).alpha.unwrap_or(0.0) >= 1.0
+        }
+        _ => false,
+    }
+}
+
+/// Whether an element with these values can be painted in a [`ShapeBatch`].
+///
+/// The element must not need a layer of its own, and it must be painted with an opaque
+/// color, so that painting it together with its siblings gives the same result as painting
+/// them one by one.  Fills are merged into a single path, so only the basic shapes that
+/// librsvg creates with the same orientation are allowed; otherwise overlapping shapes
+/// could cancel each other out.  Strokes do not have that problem.
+fn is_batchable(node: &Node, values: &ComputedValues) -> bool {
+    let is_plain = values.is_displayed()
+        && values.is_visible()
+        && is_composited_directly(values)
+        && values.isolation() == Isolation::Auto
+        && values.vector_effect() == VectorEffect::None
+        && values.marker_start().0.get().is_none()
+        && values.marker_mid().0.get().is_none()
+        && values.marker_end().0.get().is_none();
+
+    if !is_plain {
+        return false;
+    }
+
+    match (values.fill().0, values.stroke().0) {
+        (ref fill, PaintServer::None) if is_opaque_color(fill, values.fill_opacity().0, values) => {
+            is_element_of_type!(node, Rect)
+                || is_element_of_type!(node, Circle)
+                || is_element_of_type!(node, Ellipse)
+        }
+
+        (PaintServer::None, ref stroke)
+            if is_opaque_color(stroke, values.stroke_opacity().0, values) =>
+        {
+            true
+        }
+
+        _ => false,
+    }
+}
+
+/// Consecutive sibling shapes that are painted with a single fill or stroke.
+///
+/// Documents like scatter plots can have tens of thousands of tiny shapes with the same
+/// style, and painting each one separately has a lot of overhead.  Runs of such shapes are
+/// collected here while drawing a container's children, and then merged into a single
+/// Cairo path.  Only shapes for which [`DrawingCtx::layout_for_batch`] returns a layer can
+/// be added.
+#[derive(Default)]
+pub struct ShapeBatch {
+    layers: Vec<Layer>,
+}
+
+impl ShapeBatch {
+    /// Whether `layer` can be painted along with the shapes already in the batch.
+    pub fn accepts(&self, layer: &Layer) -> bool {
+        match self.layers.first() {
+            None => true,
+
+            Some(first) => {
+                first.stacking_ctx.transform == layer.stacking_ctx.transform
+                    && match (&first.kind, &layer.kind) {
+                        (LayerKind::Shape(a), LayerKind::Shape(b)) => {
+                            a.shape_rendering == b.shape_rendering
+                                && match (batch_paint(a), batch_paint(b)) {
+                                    (Some(a), Some(b)) => a == b,
+                                    _ => false,
+                                }
+                        }
+
+                        _ => false,
+                    }
+            }
+        }
+    }
+
+    pub fn push(&mut self, layer: Layer) {
+        self.layers.push(layer);
+    }
+}
+
+#[derive(PartialEq)]
+enum BatchPaint<'a> {
+    Fill(&'a cssparser::Color),
+    Stroke(&'a cssparser::Color, &'a Stroke),
+}
+
+fn batch_paint(shape: &Shape) -> Option<BatchPaint<'_>> {
+    match (&shape.fill_paint, &shape.stroke_paint) {
+        (UserSpacePaintSource::SolidColor(color), UserSpacePaintSource::None) => {
+            Some(BatchPaint::Fill(color))
+        }
+
+        (UserSpacePaintSource::None, UserSpacePaintSource::SolidColor(color)) => {
+            Some(BatchPaint::Stroke(color, &shape.stroke))
+        }
+
+        _ => None,
+    }
+}
+
+/// The area that a `<rect>` with these values covers with a fully opaque color, if any.
+///
+/// Only plain rectangles qualify: no rounded corners, a solid fill, and nothing that makes
+/// the element translucent like opacity, masks, or blend modes.  The area is in the user
+/// space of the rectangle's parent.
+fn opaque_rect_area(values: &ComputedValues, viewport: &Viewport) -> Option<Rect> {
+    if !values.is_displayed() || !values.is_visible() {
+        return None;
+    }
+
+    if !is_composited_directly(values)
+        || !is_opaque_color(&values.fill().0, values.fill_opacity().0, values)
+    {
+        return None;
+    }
+
+    fn is_square<N: Normalize>(r: LengthOrAuto<N>) -> bool {
+        match r {
+            LengthOrAuto::Auto => true,
+            LengthOrAuto::Length(l) => l.length == 0.0,
+        }
+    }
+
+    if !is_square(values.rx().0) || !is_square(values.ry().0) {
+        return None;
+    }
+
+    let transform = values.transform();
+    if transform.xy != 0.0 || transform.yx != 0.0 {
+        return None;
+    }
+
+    let params = NormalizeParams::new(values, viewport);
+    let x = values.x().0.to_user(&params);
+    let y = values.y().0.to_user(&params);
+    let (w, h) = match (values.width().0, values.height().0) {
+        (LengthOrAuto::Length(w), LengthOrAuto::Length(h)) => {
+            (w.to_user(&params), h.to_user(&params))
+        }
+        _ => return None,
+    };
+
+    if w <= 0.0 || h <= 0.0 {
+        return None;
+    }
+
+    Some(transform.transform_rect(&Rect::new(x, y, x + w, y + h)))
+}
+
 fn setup_cr_for_stroke(cr: &cairo::Context, stroke: &Stroke) {
     cr.set_line_width(stroke.width);
     cr.set_miter_limit(stroke.miter_limit.0);
@@ -2199,6 +4241,48 @@ fn escape_link_target(value: &str) -> Cow<'_, str> {
     })
 }
 
+/// Paints a checkerboard with a red cross over `rect`, to signal a rendering error.
+fn paint_error_placeholder(cr: &cairo::Context, rect: Rect) -> Result<(), cairo::Error> {
+    if rect.is_empty() {
+        return Ok(());
+    }
+
+    // About eight cells along the short side, but no more than 64 along the long one.
+    let cell = (rect.width().min(rect.height()) / 8.0).max(rect.width().max(rect.height()) / 64.0);
+    let cols = (rect.width() / cell).ceil() as i32;
+    let rows = (rect.height() / cell).ceil() as i32;
+
+    cr.save()?;
+    clip_to_rectangle(cr, &rect);
+
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    cr.paint()?;
+
+    cr.set_source_rgb(0.75, 0.75, 0.75);
+    for row in 0..rows {
+        for col in (row % 2..cols).step_by(2) {
+            cr.rectangle(
+                rect.x0 + f64::from(col) * cell,
+                rect.y0 + f64::from(row) * cell,
+                cell,
+                cell,
+            );
+        }
+    }
+    cr.fill()?;
+
+    cr.set_source_rgb(0.8, 0.0, 0.0);
+    cr.set_line_width(cell / 2.0);
+    cr.move_to(rect.x0, rect.y0);
+    cr.line_to(rect.x1, rect.y1);
+    cr.move_to(rect.x1, rect.y0);
+    cr.line_to(rect.x0, rect.y1);
+    cr.rectangle(rect.x0, rect.y0, rect.width(), rect.height());
+    cr.stroke()?;
+
+    cr.restore()
+}
+
 fn clip_to_rectangle(cr: &cairo::Context, r: &Rect) {
     cr.rectangle(r.x0, r.y0, r.width(), r.height());
     cr.clip();
@@ -2327,13 +4411,25 @@ pub struct PathExtents {
 }
 
 impl Path {
+    /// Feeds the path to `cr`.
+    ///
+    /// If `simplify_tolerance` is given, runs of consecutive line segments get simplified
+    /// so that they deviate from the original ones by at most that many device units.
     pub fn to_cairo(
         &self,
         cr: &cairo::Context,
         is_square_linecap: bool,
+        simplify_tolerance: Option<f64>,
     ) -> Result<(), InternalRenderingError> {
         assert!(!self.is_empty());
 
+        let scale = Transform::from(cr.matrix()).max_scale_factor();
+
+        // The tolerance is in device space; convert it to user space.
+        let tolerance = simplify_tolerance.map(|t| t / scale);
+        let mut polyline = Vec::new();
+        let mut current_point = (0.0, 0.0);
+
         for subpath in self.iter_subpath() {
             // If a subpath is empty and the linecap is a square, then draw a square centered on
             // the origin of the subpath. See #165.
@@ -2348,7 +4444,47 @@ impl Path {
             }
 
             for cmd in subpath.iter_commands() {
-                cmd.to_cairo(cr);
+                if let PathCommand::LineTo(x, y) = cmd {
+                    if tolerance.is_some() {
+                        if polyline.is_empty() {
+                            polyline.push(current_point);
+                        }
+                        polyline.push((x, y));
+                        current_point = (x, y);
+                        continue;
+                    }
+                }
+
+                if let Some(tolerance) = tolerance {
+                    polyline_to_cairo(cr, &mut polyline, tolerance);
+                }
+
+                match cmd {
+                    PathCommand::MoveTo(x, y) => {
+                        cr.move_to(x, y);
+                        current_point = (x, y);
+                    }
+                    PathCommand::LineTo(x, y) => {
+                        cr.line_to(x, y);
+                        current_point = (x, y);
+                    }
+                    PathCommand::CurveTo(ref curve) => {
+                        curve.to_cairo(cr);
+                        current_point = curve.to;
+                    }
+                    PathCommand::Arc(ref arc) => {
+                        self.flatten_arc(arc, scale).to_cairo(cr);
+                        current_point = arc.to;
+                    }
+                    PathCommand::ClosePath => {
+                        cr.close_path();
+                        current_point = subpath.origin();
+                    }
+                }
+            }
+
+            if let Some(tolerance) = tolerance {
+                polyline_to_cairo(cr, &mut polyline, tolerance);
             }
         }
 
@@ -2388,47 +4524,34 @@ impl Path {
     }
 }
 
+/// Emits the simplified version of a run of line segments, and clears the run.
+///
+/// The first point of `polyline` is the current point, which has already been fed to `cr`.
+fn polyline_to_cairo(cr: &cairo::Context, polyline: &mut Vec<(f64, f64)>, tolerance: f64) {
+    if polyline.len() > 1 {
+        for &(x, y) in simplify_polyline(polyline, tolerance).iter().skip(1) {
+            cr.line_to(x, y);
+        }
+    }
+
+    polyline.clear();
+}
+
 fn cairo_path_is_only_move_tos(path: &cairo::Path) -> bool {
     path.iter()
         .all(|seg| matches!(seg, cairo::PathSegment::MoveTo((_, _))))
 }
 
-impl PathCommand {
+impl FlattenedArc {
     fn to_cairo(&self, cr: &cairo::Context) {
         match *self {
-            PathCommand::MoveTo(x, y) => cr.move_to(x, y),
-            PathCommand::LineTo(x, y) => cr.line_to(x, y),
-            PathCommand::CurveTo(ref curve) => curve.to_cairo(cr),
-            PathCommand::Arc(ref arc) => arc.to_cairo(cr),
-            PathCommand::ClosePath => cr.close_path(),
-        }
-    }
-}
-
-impl EllipticalArc {
-    fn to_cairo(&self, cr: &cairo::Context) {
-        match self.center_parameterization() {
-            ArcParameterization::CenterParameters {
-                center,
-                radii,
-                theta1,
-                delta_theta,
-            } => {
-                let n_segs = (delta_theta / (PI * 0.5 + 0.001)).abs().ceil() as u32;
-                let d_theta = delta_theta / f64::from(n_segs);
-
-                let mut theta = theta1;
-                for _ in 0..n_segs {
-                    arc_segment(center, radii, self.x_axis_rotation, theta, theta + d_theta)
-                        .to_cairo(cr);
-                    theta += d_theta;
+            FlattenedArc::Curves(ref curves) => {
+                for curve in curves.iter() {
+                    curve.to_cairo(cr);
                 }
             }
-            ArcParameterization::LineTo => {
-                let (x2, y2) = self.to;
-                cr.line_to(x2, y2);
-            }
-            ArcParameterization::Omit => {}
+            FlattenedArc::LineTo((x2, y2)) => cr.line_to(x2, y2),
+            FlattenedArc::Omit => {}
         }
     }
 }
@@ -2472,4 +4595,35 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn detects_rectangles_outside_clip() {
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 100, 100).unwrap();
+        let cr = cairo::Context::new(&surface).unwrap();
+
+        assert!(!is_outside_clip(&cr, None));
+        assert!(!is_outside_clip(
+            &cr,
+            Some(Rect::new(90.0, 90.0, 110.0, 110.0))
+        ));
+        assert!(is_outside_clip(
+            &cr,
+            Some(Rect::new(100.0, 0.0, 120.0, 10.0))
+        ));
+
+        cr.translate(-200.0, 0.0);
+        assert!(!is_outside_clip(
+            &cr,
+            Some(Rect::new(250.0, 0.0, 260.0, 10.0))
+        ));
+        assert!(is_outside_clip(&cr, Some(Rect::new(50.0, 0.0, 60.0, 10.0))));
+
+        cr.identity_matrix();
+        cr.rectangle(0.0, 0.0, 10.0, 10.0);
+        cr.clip();
+        assert!(is_outside_clip(
+            &cr,
+            Some(Rect::new(20.0, 20.0, 30.0, 30.0))
+        ));
+    }
 }
@@ -2,13 +2,13 @@
 
 use markup5ever::{expanded_name, local_name, namespace_url, ns, QualName};
 use once_cell::sync::Lazy;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::fmt;
 
 use crate::accept_language::UserLanguage;
 use crate::bbox::BoundingBox;
 use crate::cond::{RequiredExtensions, RequiredFeatures, SystemLanguage};
-use crate::css::{Declaration, Origin};
+use crate::css::{self, Declaration, Origin};
 use crate::document::AcquiredNodes;
 use crate::drawing_ctx::{DrawingCtx, Viewport};
 use crate::error::*;
@@ -37,8 +37,8 @@ use crate::image::Image;
 use crate::marker::Marker;
 use crate::node::*;
 use crate::pattern::Pattern;
-use crate::properties::{ComputedValues, SpecifiedValues};
-use crate::rsvg_log;
+use crate::properties::{ComputedValues, Display, SpecifiedValues};
+use crate::rsvg_warn;
 use crate::session::Session;
 use crate::shapes::{Circle, Ellipse, Line, Path, Polygon, Polyline, Rect};
 use crate::structure::{ClipPath, Group, Link, Mask, NonRendering, Svg, Switch, Symbol, Use};
@@ -89,9 +89,12 @@ pub fn set_attribute<T>(dest: &mut T, parse_result: Result<T, ElementError>, ses
     match parse_result {
         Ok(v) => *dest = v,
         Err(e) => {
-            // FIXME: this does not provide a clue of what was the problematic element.
-            // We need tracking of the current parsing position to do that.
-            rsvg_log!(session, "ignoring attribute with invalid value: {}", e);
+            rsvg_warn!(
+                session,
+                strict,
+                "ignoring attribute with invalid value: {}",
+                e
+            );
         }
     }
 }
@@ -100,7 +103,6 @@ pub struct Element {
     element_name: QualName,
     attributes: Attributes,
     specified_values: SpecifiedValues,
-    important_styles: HashSet<QualName>,
     values: ComputedValues,
     required_extensions: Option<RequiredExtensions>,
     required_features: Option<RequiredFeatures>,
@@ -207,7 +209,6 @@ impl Element {
             element_name: name.clone(),
             attributes,
             specified_values: Default::default(),
-            important_styles: Default::default(),
             values: Default::default(),
             required_extensions: Default::default(),
             required_features: Default::default(),
@@ -229,6 +230,35 @@ impl Element {
         &self.attributes
     }
 
+    /// Sets an attribute which does not affect rendering, like one in a foreign namespace.
+    ///
+    /// The element's data and style are not recomputed.
+    pub fn set_unrendered_attribute(&mut self, name: QualName, value: &str) {
+        self.attributes.set(name, value);
+    }
+
+    /// Recreates the element's data and presentation attributes with the animated values
+    /// in `overrides` in place of its attributes.
+    ///
+    /// The element's own attributes are not modified, so calling this again with an empty
+    /// list of overrides returns the element to its static state.  The caller must run the
+    /// CSS cascade afterwards.
+    pub fn set_animated_attributes(&mut self, session: &Session, overrides: &[(QualName, String)]) {
+        let mut attributes = self.attributes.clone();
+
+        for (name, value) in overrides {
+            attributes.set(name.clone(), value);
+        }
+
+        let animated = Element::new(session, &self.element_name, attributes);
+
+        self.specified_values = animated.specified_values;
+        self.required_extensions = animated.required_extensions;
+        self.required_features = animated.required_features;
+        self.system_language = animated.system_language;
+        self.element_data = animated.element_data;
+    }
+
     pub fn get_id(&self) -> Option<&str> {
         self.attributes.get_id()
     }
@@ -299,29 +329,20 @@ impl Element {
 
     // Applies a style declaration to the node's specified_values
     pub fn apply_style_declaration(&mut self, declaration: &Declaration, origin: Origin) {
-        self.specified_values.set_property_from_declaration(
-            declaration,
-            origin,
-            &mut self.important_styles,
-        );
+        self.specified_values
+            .set_property_from_declaration(declaration, origin);
     }
 
-    /// Applies CSS styles from the "style" attribute
-    pub fn set_style_attribute(&mut self, session: &Session) {
-        let style = self
-            .attributes
+    /// Parses the CSS declarations in the "style" attribute
+    ///
+    /// These are not applied here; the caller must interleave them with the matches from
+    /// the stylesheets, according to their importance, during the cascade.
+    pub fn get_style_declarations(&self, session: &Session) -> Vec<Declaration> {
+        self.attributes
             .iter()
             .find(|(attr, _)| attr.expanded() == expanded_name!("", "style"))
-            .map(|(_, value)| value);
-
-        if let Some(style) = style {
-            self.specified_values.parse_style_declarations(
-                style,
-                Origin::Author,
-                &mut self.important_styles,
-                session,
-            );
-        }
+            .map(|(_, value)| css::parse_declarations(value, session))
+            .unwrap_or_default()
     }
 
     #[rustfmt::skip]
@@ -368,6 +389,32 @@ impl Element {
         )
     }
 
+    /// Whether `display: contents` draws the element's contents in its place.
+    ///
+    /// Per <https://drafts.csswg.org/css-display/#unbox-svg>, this is the case for `<g>`,
+    /// `<use>`, `<tspan>`, and `<svg>` elements nested in another one.  The element itself
+    /// is not drawn, so its transform, opacity, clipping, masking, and filter do not apply.
+    /// For other elements, `display: contents` works like `display: none`.
+    pub fn can_be_unboxed(&self, node: &Node) -> bool {
+        use ElementData::*;
+
+        match self.element_data {
+            Group(_) | TSpan(_) | Use(_) => true,
+            Svg(_) => node.parent().is_some(),
+            _ => false,
+        }
+    }
+
+    /// Whether the `display` property keeps the element and its descendants from being
+    /// drawn.
+    pub fn is_hidden_by_display(&self, node: &Node) -> bool {
+        match self.get_computed_values().display() {
+            Display::None => true,
+            Display::Contents => !self.can_be_unboxed(node),
+            _ => false,
+        }
+    }
+
     /// The main drawing function for elements.
     pub fn draw(
         &self,
@@ -379,11 +426,27 @@ impl Element {
         clipping: bool,
     ) -> Result<BoundingBox, InternalRenderingError> {
         let values = cascaded.get();
-        if values.is_displayed() {
-            self.element_data
-                .draw(node, acquired_nodes, cascaded, viewport, draw_ctx, clipping)
-        } else {
-            Ok(draw_ctx.empty_bbox())
+
+        match values.display() {
+            Display::None => Ok(draw_ctx.empty_bbox()),
+
+            Display::Contents if !self.can_be_unboxed(node) => Ok(draw_ctx.empty_bbox()),
+
+            // A <use> leaves out its own transform and layer by itself when drawing the
+            // element it references, and a <tspan> gets laid out by its <text>.
+            Display::Contents
+                if matches!(
+                    self.element_data,
+                    ElementData::Group(_) | ElementData::Svg(_)
+                ) =>
+            {
+                node.draw_children(acquired_nodes, cascaded, viewport, draw_ctx, clipping)
+            }
+
+            _ => {
+                self.element_data
+                    .draw(node, acquired_nodes, cascaded, viewport, draw_ctx, clipping)
+            }
         }
     }
 }
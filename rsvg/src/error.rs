@@ -12,7 +12,8 @@ use crate::RenderingError;
 use crate::document::NodeId;
 use crate::io::IoError;
 use crate::limits;
-use crate::node::Node;
+use crate::log::LoadWarning;
+use crate::node::{Node, NodeBorrow};
 
 /// A short-lived error.
 ///
@@ -210,7 +211,13 @@ pub enum AcquireError {
     ///
     /// Callers are expected to exit as early as possible and return an error to
     /// the public API.  See [`ImplementationLimit::TooManyReferencedElements`] for details.
-    MaxReferencesExceeded,
+    MaxReferencesExceeded(usize),
+
+    /// A chain of references got longer than allowed.
+    ///
+    /// This has the nodes in the chain, from the outermost one to the one that could not
+    /// be acquired.  See [`ImplementationLimit::ReferenceChainTooLong`] for details.
+    ReferenceChainTooLong(ReferenceChain, Vec<Node>),
 }
 
 impl fmt::Display for AcquireError {
@@ -226,8 +233,25 @@ impl fmt::Display for AcquireError {
                 write!(f, "circular reference in node {node}")
             }
 
-            AcquireError::MaxReferencesExceeded => {
-                write!(f, "maximum number of references exceeded")
+            AcquireError::MaxReferencesExceeded(max) => {
+                write!(f, "exceeded more than {max} referenced elements")
+            }
+
+            AcquireError::ReferenceChainTooLong(chain, ref nodes) => {
+                write!(f, "chain of {chain} is too long: ")?;
+
+                for (i, node) in nodes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " -> ")?;
+                    }
+
+                    match node.borrow_element().get_id() {
+                        Some(id) => write!(f, "#{id}")?,
+                        None => write!(f, "<{}>", node.borrow_element().element_name().local)?,
+                    }
+                }
+
+                Ok(())
             }
         }
     }
@@ -346,6 +370,9 @@ pub enum AllowedUrlError {
 
     /// Error when canonicalizing either the file path or the base file path
     CanonicalizationError,
+
+    /// Only `data:` URLs are allowed, since loading external resources is disabled
+    ExternalResourcesDisabled,
 }
 
 impl fmt::Display for AllowedUrlError {
@@ -362,6 +389,7 @@ impl fmt::Display for AllowedUrlError {
             InvalidPath => write!(f, "invalid path"),
             BaseIsRoot => write!(f, "base is root"),
             CanonicalizationError => write!(f, "canonicalization error"),
+            ExternalResourcesDisabled => write!(f, "external resources are disabled"),
         }
     }
 }
@@ -416,6 +444,11 @@ pub enum LoadingError {
     /// A particular implementation-defined limit was exceeded.
     LimitExceeded(ImplementationLimit),
 
+    /// The document has an invalid value, and it was loaded in strict mode.
+    ///
+    /// See [`crate::Loader::strict`] for the kinds of problems that cause this.
+    Invalid(LoadWarning),
+
     /// Catch-all for loading errors.
     Other(String),
 }
@@ -452,16 +485,24 @@ pub enum ImplementationLimit {
     /// examples for the `<use>` and `<pattern>` elements,
     /// respectively.
     ///
+    /// The limit can be changed with
+    /// [`Loader::with_max_referenced_elements`](crate::Loader::with_max_referenced_elements).
+    ///
+    /// This has the maximum number of references that was in effect.
+    ///
     /// [billion laughs attack]: https://bitbucket.org/tiran/defusedxml
-    TooManyReferencedElements,
+    TooManyReferencedElements(usize),
 
     /// Document exceeded the maximum number of elements that can be loaded.
     ///
     /// This is a mitigation for SVG files which create millions of
     /// elements in an attempt to exhaust memory.  Librsvg does not't
     /// allow loading more than a certain number of elements during
-    /// the initial loading process.
-    TooManyLoadedElements,
+    /// the initial loading process.  The number can be changed with
+    /// [`Loader::with_max_elements`](crate::Loader::with_max_elements).
+    ///
+    /// This has the maximum number of elements that was in effect.
+    TooManyLoadedElements(usize),
 
     /// Document exceeded the number of attributes that can be attached to
     /// an element.
@@ -471,6 +512,58 @@ pub enum ImplementationLimit {
     /// number of attributes that the SVG standard ascribes meaning to are
     /// lower than this limit.
     TooManyAttributes,
+
+    /// Document's XML elements are nested more deeply than allowed by
+    /// [`Loader::with_max_nesting_depth`](crate::Loader::with_max_nesting_depth).
+    TooDeeplyNested,
+
+    /// A raster image referenced by the document has more pixels than allowed by
+    /// [`Loader::with_max_image_pixels`](crate::Loader::with_max_image_pixels).
+    TooManyImagePixels,
+
+    /// Document has more XML data than allowed by
+    /// [`Loader::with_max_decompressed_size`](crate::Loader::with_max_decompressed_size).
+    DocumentTooLarge,
+
+    /// Document has a chain of references between elements that is longer than allowed by
+    /// [`Loader::with_max_reference_chain`](crate::Loader::with_max_reference_chain).
+    ///
+    /// The ids of the elements in the chain are logged as a warning.
+    ReferenceChainTooLong(ReferenceChain),
+}
+
+/// Kinds of chains of references between elements.
+///
+/// The length of each of these is limited separately; see
+/// [`ImplementationLimit::ReferenceChainTooLong`].
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReferenceChain {
+    /// `<use>` elements which instance content with more `<use>` elements.
+    Use,
+
+    /// Patterns whose content is painted with other patterns, or which take their
+    /// attributes or content from other patterns through their `href`.
+    Pattern,
+
+    /// Gradients which take their attributes or stops from other gradients through their
+    /// `href`.
+    GradientFallback,
+
+    /// `feImage` filter primitives which reference elements that have filters with more
+    /// `feImage` primitives.
+    FilterImage,
+}
+
+impl fmt::Display for ReferenceChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ReferenceChain::Use => write!(f, "<use> elements"),
+            ReferenceChain::Pattern => write!(f, "patterns"),
+            ReferenceChain::GradientFallback => write!(f, "gradient references"),
+            ReferenceChain::FilterImage => write!(f, "feImage references"),
+        }
+    }
 }
 
 impl error::Error for LoadingError {}
@@ -485,6 +578,7 @@ impl fmt::Display for LoadingError {
             LoadingError::NoSvgRoot => write!(f, "XML does not have <svg> root"),
             LoadingError::Io(ref s) => write!(f, "I/O error: {s}"),
             LoadingError::LimitExceeded(ref l) => write!(f, "{l}"),
+            LoadingError::Invalid(ref w) => write!(f, "invalid document: {w}"),
             LoadingError::Other(ref s) => write!(f, "{s}"),
         }
     }
@@ -510,23 +604,35 @@ impl From<IoError> for LoadingError {
 impl fmt::Display for ImplementationLimit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            ImplementationLimit::TooManyReferencedElements => write!(
-                f,
-                "exceeded more than {} referenced elements",
-                limits::MAX_REFERENCED_ELEMENTS
-            ),
+            ImplementationLimit::TooManyReferencedElements(max) => {
+                write!(f, "exceeded more than {max} referenced elements")
+            }
 
-            ImplementationLimit::TooManyLoadedElements => write!(
-                f,
-                "cannot load more than {} XML elements",
-                limits::MAX_LOADED_ELEMENTS
-            ),
+            ImplementationLimit::TooManyLoadedElements(max) => {
+                write!(f, "cannot load more than {max} XML elements")
+            }
 
             ImplementationLimit::TooManyAttributes => write!(
                 f,
                 "cannot load more than {} XML attributes",
                 limits::MAX_LOADED_ATTRIBUTES
             ),
+
+            ImplementationLimit::TooDeeplyNested => {
+                write!(f, "exceeded the maximum nesting depth of XML elements")
+            }
+
+            ImplementationLimit::TooManyImagePixels => {
+                write!(f, "exceeded the maximum number of pixels in an image")
+            }
+
+            ImplementationLimit::DocumentTooLarge => {
+                write!(f, "exceeded the maximum size of the XML document")
+            }
+
+            ImplementationLimit::ReferenceChainTooLong(chain) => {
+                write!(f, "exceeded the maximum length of a chain of {chain}")
+            }
         }
     }
 }
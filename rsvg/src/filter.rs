@@ -3,6 +3,7 @@
 use cssparser::{Color, Parser};
 use markup5ever::{expanded_name, local_name, namespace_url, ns};
 use std::slice::Iter;
+use url::Url;
 
 use crate::coord_units::CoordUnits;
 use crate::document::{AcquiredNodes, NodeId};
@@ -282,6 +283,15 @@ impl FilterValueList {
     pub fn iter(&self) -> Iter<'_, FilterValue> {
         self.0.iter()
     }
+
+    /// Resolves relative references to external filters against `base_url`.
+    pub fn make_absolute(&mut self, base_url: &Url) {
+        for value in &mut self.0 {
+            if let FilterValue::Url(ref mut node_id) = *value {
+                node_id.make_absolute(base_url);
+            }
+        }
+    }
 }
 
 impl Parse for FilterValueList {
@@ -4,6 +4,7 @@ use crate::aspect_ratio::AspectRatio;
 use crate::document::{AcquiredNodes, NodeId};
 use crate::drawing_ctx::DrawingCtx;
 use crate::element::{set_attribute, ElementTrait};
+use crate::error::{AcquireError, ImplementationLimit, InternalRenderingError, ReferenceChain};
 use crate::href::{is_href, set_href};
 use crate::node::{CascadedValues, Node};
 use crate::parsers::ParseValue;
@@ -157,19 +158,30 @@ impl Image {
             Source::None => return Err(FilterError::InvalidInput),
 
             Source::Node(node, ref name) => {
-                if let Ok(acquired) = acquired_nodes.acquire_ref(node) {
-                    rsvg_log!(draw_ctx.session(), "(feImage \"{}\"", name);
-                    let res = self.render_node(
-                        ctx,
-                        acquired_nodes,
-                        draw_ctx,
-                        bounds.clipped,
-                        acquired.get(),
-                    );
-                    rsvg_log!(draw_ctx.session(), ")");
-                    res?
-                } else {
-                    return Err(FilterError::InvalidInput);
+                match acquired_nodes.acquire_in_chain(node, ReferenceChain::FilterImage) {
+                    Ok(acquired) => {
+                        rsvg_log!(draw_ctx.session(), level: Debug, "(feImage \"{}\"", name);
+                        let res = self.render_node(
+                            ctx,
+                            acquired_nodes,
+                            draw_ctx,
+                            bounds.clipped,
+                            acquired.get(),
+                        );
+                        rsvg_log!(draw_ctx.session(), level: Debug, ")");
+                        res?
+                    }
+
+                    Err(e @ AcquireError::ReferenceChainTooLong(chain, _)) => {
+                        rsvg_log!(draw_ctx.session(), "{}", e);
+                        return Err(FilterError::Rendering(
+                            InternalRenderingError::LimitExceeded(
+                                ImplementationLimit::ReferenceChainTooLong(chain),
+                            ),
+                        ));
+                    }
+
+                    Err(_) => return Err(FilterError::InvalidInput),
                 }
             }
 
@@ -276,8 +276,7 @@ pub fn render(
     )
     .and_then(|mut filter_ctx| {
         // the message has an unclosed parenthesis; we'll close it below.
-        rsvg_log!(
-            session,
+        rsvg_log!(session, level: Debug,
             "(filter \"{}\" with effects_region={:?}",
             filter.name,
             filter_ctx.effects_region()
@@ -288,8 +287,7 @@ pub fn render(
             match render_primitive(user_space_primitive, &filter_ctx, acquired_nodes, draw_ctx) {
                 Ok(output) => {
                     let elapsed = start.elapsed();
-                    rsvg_log!(
-                        session,
+                    rsvg_log!(session, level: Debug,
                         "(rendered filter primitive {} in {} seconds)",
                         user_space_primitive.params.name(),
                         elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1e9
@@ -310,7 +308,7 @@ pub fn render(
                     );
 
                     // close the opening parenthesis from the message at the start of this function
-                    rsvg_log!(session, ")");
+                    rsvg_log!(session, level: Debug, ")");
 
                     // Exit early on Cairo errors. Continue rendering otherwise.
                     if let FilterError::CairoError(status) = err {
@@ -321,7 +319,7 @@ pub fn render(
         }
 
         // close the opening parenthesis from the message at the start of this function
-        rsvg_log!(session, ")");
+        rsvg_log!(session, level: Debug, ")");
 
         Ok(filter_ctx.into_output()?)
     })
@@ -333,11 +331,19 @@ pub fn render(
 
         _ => {
             // ignore other filter errors and just return an empty surface
-            Ok(SharedImageSurface::empty(
+            let surface_type = if draw_ctx.error_placeholders() {
+                source_surface.surface_type()
+            } else {
+                SurfaceType::AlphaOnly
+            };
+
+            let empty = SharedImageSurface::empty(
                 source_surface.width(),
                 source_surface.height(),
-                SurfaceType::AlphaOnly,
-            )?)
+                surface_type,
+            )?;
+
+            draw_ctx.error_placeholder_surface(empty, &node_bbox)
         }
     })
 }
@@ -66,15 +66,13 @@ impl Tile {
                 bounds: input_bounds,
             }) => {
                 if input_bounds.is_empty() {
-                    rsvg_log!(
-                        draw_ctx.session(),
+                    rsvg_log!(draw_ctx.session(), level: Debug,
                         "(feTile with empty input_bounds; returning just the input surface)"
                     );
 
                     input_surface
                 } else {
-                    rsvg_log!(
-                        draw_ctx.session(),
+                    rsvg_log!(draw_ctx.session(), level: Debug,
                         "(feTile bounds={:?}, input_bounds={:?})",
                         bounds,
                         input_bounds
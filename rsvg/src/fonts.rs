@@ -0,0 +1,268 @@
+//! Font files that are registered with a document.
+//!
+//! Normally librsvg uses the fonts installed in the system, through Pango's default font
+//! map.  Programs that cannot install fonts system-wide, like sandboxed applications or
+//! test suites, can register font files with a document instead.  In that case, the
+//! document gets its own Fontconfig configuration with the system's fonts plus the
+//! registered ones, and a font map that uses it.
+
+use std::path::{Path, PathBuf};
+
+use crate::error::LoadingError;
+
+/// Fonts registered with a document, in addition to the system's fonts.
+#[derive(Default)]
+pub struct DocumentFonts {
+    /// Fontconfig configuration with the system's fonts plus the registered ones.
+    ///
+    /// It is created when the first font gets registered, so that the system's fonts are
+    /// only scanned once.
+    config: Option<FontConfig>,
+
+    /// Temporary files created for fonts that were registered from memory.
+    ///
+    /// These are deleted when the document is dropped.
+    temp_files: Vec<PathBuf>,
+
+    /// Font map that uses `config`; `None` if no fonts were registered.
+    font_map: Option<pangocairo::FontMap>,
+}
+
+impl DocumentFonts {
+    /// Registers a TrueType or OpenType font file.
+    ///
+    /// Returns an error if the file cannot be read or is not a font.
+    pub fn add_file(&mut self, path: &Path) -> Result<(), LoadingError> {
+        if self.config.is_none() {
+            self.config = Some(FontConfig::new()?);
+        }
+
+        let config = self.config.as_ref().unwrap();
+        config.add_file(path)?;
+
+        match self.font_map {
+            Some(ref font_map) => config.font_map_changed(font_map),
+            None => self.font_map = Some(config.create_font_map()?),
+        }
+
+        Ok(())
+    }
+
+    /// Registers a font from the contents of a TrueType or OpenType file.
+    ///
+    /// Fontconfig can only load fonts from files, so this writes the data to a temporary
+    /// file first.
+    pub fn add_data(&mut self, data: &[u8]) -> Result<(), LoadingError> {
+        let (fd, path) = glib::file_open_tmp(Some("rsvg-font-XXXXXX.ttf"))
+            .map_err(|e| LoadingError::Io(format!("could not create temporary file: {e}")))?;
+
+        // glib::file_open_tmp() opens the file for us; wrap its descriptor so it gets closed.
+        let written = write_to_fd(fd, data);
+        self.temp_files.push(path.clone());
+
+        written.map_err(|e| LoadingError::Io(format!("could not write temporary file: {e}")))?;
+
+        self.add_file(&path)
+    }
+
+    /// Font map to use for text, or `None` to use Pango's default font map.
+    pub fn font_map(&self) -> Option<&pangocairo::FontMap> {
+        self.font_map.as_ref()
+    }
+}
+
+impl Drop for DocumentFonts {
+    fn drop(&mut self) {
+        for path in &self.temp_files {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn write_to_fd(fd: i32, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::io::FromRawFd;
+
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+    file.write_all(data)
+}
+
+#[cfg(not(unix))]
+fn write_to_fd(fd: i32, _data: &[u8]) -> std::io::Result<()> {
+    unsafe {
+        libc::close(fd);
+    }
+
+    Err(std::io::Error::from(std::io::ErrorKind::Unsupported))
+}
+
+#[cfg(all(not(windows), system_deps_have_pangoft2))]
+mod ffi {
+    extern "C" {
+        // pango_fc_font_map_set_config (PangoFcFontMap *fcfontmap,
+        //                               FcConfig       *fcconfig);
+        // This is not bound in gtk-rs, and PangoFcFontMap is not even exposed, so we'll bind it by hand.
+        pub fn pango_fc_font_map_set_config(
+            font_map: *mut libc::c_void,
+            config: *mut fontconfig_sys::FcConfig,
+        );
+
+        // pango_fc_font_map_config_changed (PangoFcFontMap *fcfontmap);
+        pub fn pango_fc_font_map_config_changed(font_map: *mut libc::c_void);
+    }
+}
+
+/// A Fontconfig configuration that fonts can be added to.
+#[cfg(all(not(windows), system_deps_have_pangoft2))]
+struct FontConfig(*mut fontconfig_sys::FcConfig);
+
+#[cfg(all(not(windows), system_deps_have_pangoft2))]
+impl FontConfig {
+    /// Creates a configuration with the system's configuration and fonts, so that the
+    /// document can still use them for families that are not among the registered files.
+    fn new() -> Result<FontConfig, LoadingError> {
+        let config = unsafe { fontconfig_sys::FcInitLoadConfigAndFonts() };
+        if config.is_null() {
+            return Err(LoadingError::Other(String::from(
+                "could not create a fontconfig configuration",
+            )));
+        }
+
+        Ok(FontConfig(config))
+    }
+
+    fn add_file(&self, path: &Path) -> Result<(), LoadingError> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+
+        let added = CString::new(path.as_os_str().as_bytes()).is_ok_and(|c| unsafe {
+            fontconfig_sys::FcConfigAppFontAddFile(self.0, c.as_ptr().cast()) != 0
+        });
+
+        if added {
+            Ok(())
+        } else {
+            Err(LoadingError::Io(format!(
+                "could not load font file {}",
+                path.display()
+            )))
+        }
+    }
+
+    fn create_font_map(&self) -> Result<pangocairo::FontMap, LoadingError> {
+        use glib::prelude::*;
+        use glib::translate::*;
+
+        let font_map =
+            pangocairo::FontMap::for_font_type(cairo::FontType::FontTypeFt).ok_or_else(|| {
+                LoadingError::Other(String::from("cairo does not support FreeType fonts"))
+            })?;
+        let raw_font_map: *mut pango::ffi::PangoFontMap = font_map.to_glib_none().0;
+
+        unsafe {
+            ffi::pango_fc_font_map_set_config(raw_font_map as *mut _, self.0);
+        }
+
+        Ok(font_map.downcast::<pangocairo::FontMap>().unwrap())
+    }
+
+    /// Tells `font_map` that fonts were added to the configuration.
+    fn font_map_changed(&self, font_map: &pangocairo::FontMap) {
+        use glib::translate::*;
+
+        let raw_font_map: *mut pango::ffi::PangoFontMap = font_map.to_glib_none().0;
+
+        unsafe {
+            ffi::pango_fc_font_map_config_changed(raw_font_map as *mut _);
+        }
+    }
+}
+
+#[cfg(all(not(windows), system_deps_have_pangoft2))]
+impl Drop for FontConfig {
+    fn drop(&mut self) {
+        // The font map keeps its own reference to the configuration.
+        unsafe {
+            fontconfig_sys::FcConfigDestroy(self.0);
+        }
+    }
+}
+
+#[cfg(any(windows, not(system_deps_have_pangoft2)))]
+struct FontConfig;
+
+#[cfg(any(windows, not(system_deps_have_pangoft2)))]
+impl FontConfig {
+    fn new() -> Result<FontConfig, LoadingError> {
+        Err(LoadingError::Other(String::from(
+            "registering font files is not supported on this platform",
+        )))
+    }
+
+    fn add_file(&self, _path: &Path) -> Result<(), LoadingError> {
+        unreachable!("FontConfig cannot be created on this platform")
+    }
+
+    fn create_font_map(&self) -> Result<pangocairo::FontMap, LoadingError> {
+        unreachable!("FontConfig cannot be created on this platform")
+    }
+
+    fn font_map_changed(&self, _font_map: &pangocairo::FontMap) {
+        unreachable!("FontConfig cannot be created on this platform")
+    }
+}
+
+#[cfg(all(test, not(windows), system_deps_have_pangoft2))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_font_files() {
+        let mut fonts = DocumentFonts::default();
+        assert!(fonts.font_map().is_none());
+
+        fonts
+            .add_file(Path::new("tests/resources/Ahem.ttf"))
+            .unwrap();
+        assert!(fonts.font_map().is_some());
+    }
+
+    #[test]
+    fn registers_several_fonts_in_the_same_font_map() {
+        let mut fonts = DocumentFonts::default();
+
+        fonts
+            .add_file(Path::new("tests/resources/Ahem.ttf"))
+            .unwrap();
+        let font_map = fonts.font_map().unwrap().clone();
+
+        let data = std::fs::read("tests/resources/Ahem.ttf").unwrap();
+        fonts.add_data(&data).unwrap();
+        assert_eq!(fonts.font_map(), Some(&font_map));
+    }
+
+    #[test]
+    fn registers_fonts_from_memory() {
+        let data = std::fs::read("tests/resources/Ahem.ttf").unwrap();
+
+        let mut fonts = DocumentFonts::default();
+        fonts.add_data(&data).unwrap();
+        assert!(fonts.font_map().is_some());
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_fonts() {
+        let mut fonts = DocumentFonts::default();
+
+        assert!(matches!(
+            fonts.add_file(Path::new("tests/fixtures/loading/bar.svg")),
+            Err(LoadingError::Io(_))
+        ));
+        assert!(matches!(
+            fonts.add_file(Path::new("this-file-does-not-exist.ttf")),
+            Err(LoadingError::Io(_))
+        ));
+        assert!(fonts.font_map().is_none());
+    }
+}
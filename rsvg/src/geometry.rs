@@ -0,0 +1,143 @@
+//! Bounding box algebra for embedders.
+//!
+//! This wraps the bounding boxes that librsvg uses internally with an API in terms of
+//! Cairo's types, so that programs which manage their own scenes of rendered SVG
+//! documents can combine extents the same way as librsvg does.
+
+use crate::bbox;
+use crate::rect::Rect;
+use crate::transform::Transform;
+
+/// A pair of rectangles for the extents of an object, in a particular coordinate space.
+///
+/// The logical rectangle covers the object's geometry without stroke, like the
+/// `objectBoundingBox` in SVG.  The ink rectangle covers everything that gets painted,
+/// including strokes and markers.  Either may be `None` if the object has no extents of
+/// that kind.
+///
+/// Both rectangles are in the coordinate space given by the bounding box's matrix, which
+/// maps them to the space in which they were measured.  When a bounding box is
+/// [inserted](#method.insert) into another one, or [clipped](#method.clip) by it, the
+/// rectangles get transformed into the destination's coordinate space first.  Since an
+/// axis-aligned rectangle may no longer be axis-aligned after a transformation, the
+/// result is the bounding rectangle of the transformed one.
+///
+/// ```
+/// # use rsvg::BoundingBox;
+/// let mut group = BoundingBox::new();
+///
+/// // A child that was measured in a coordinate space translated by (10, 20)
+/// let child = BoundingBox::new()
+///     .with_matrix(cairo::Matrix::new(1.0, 0.0, 0.0, 1.0, 10.0, 20.0))
+///     .unwrap()
+///     .with_rect(cairo::Rectangle::new(0.0, 0.0, 5.0, 5.0));
+///
+/// group.insert(&child);
+/// assert_eq!(group.rect(), Some(cairo::Rectangle::new(10.0, 20.0, 5.0, 5.0)));
+/// ```
+#[derive(Debug, Default, Copy, Clone)]
+pub struct BoundingBox(bbox::BoundingBox);
+
+impl BoundingBox {
+    /// Creates an empty bounding box in the identity coordinate space.
+    pub fn new() -> BoundingBox {
+        Default::default()
+    }
+
+    /// Returns the bounding box with its coordinate space changed to `matrix`.
+    ///
+    /// This does not transform the rectangles; it says which space they are in.  Returns
+    /// `None` if `matrix` is not invertible, since rectangles could then not be brought
+    /// into this bounding box's space.
+    pub fn with_matrix(self, matrix: cairo::Matrix) -> Option<BoundingBox> {
+        let transform = Transform::from(matrix);
+
+        if transform.is_invertible() {
+            Some(BoundingBox(self.0.with_transform(transform)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the bounding box with its logical rectangle set to `rect`.
+    pub fn with_rect(self, rect: cairo::Rectangle) -> BoundingBox {
+        BoundingBox(self.0.with_rect(Rect::from(rect)))
+    }
+
+    /// Returns the bounding box with its ink rectangle set to `ink_rect`.
+    pub fn with_ink_rect(self, ink_rect: cairo::Rectangle) -> BoundingBox {
+        BoundingBox(self.0.with_ink_rect(Rect::from(ink_rect)))
+    }
+
+    /// The matrix for the coordinate space of the rectangles.
+    pub fn matrix(&self) -> cairo::Matrix {
+        let t = self.0.transform();
+        cairo::Matrix::new(t.xx, t.yx, t.xy, t.yy, t.x0, t.y0)
+    }
+
+    /// Extents of the geometry, without stroke.
+    pub fn rect(&self) -> Option<cairo::Rectangle> {
+        self.0.rect.map(cairo::Rectangle::from)
+    }
+
+    /// Extents of everything that gets painted, with stroke and markers.
+    pub fn ink_rect(&self) -> Option<cairo::Rectangle> {
+        self.0.ink_rect.map(cairo::Rectangle::from)
+    }
+
+    /// Removes both rectangles, keeping the coordinate space.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Extends the bounding box to include `src`, like a group includes its children.
+    pub fn insert(&mut self, src: &BoundingBox) {
+        self.0.insert(&src.0);
+    }
+
+    /// Intersects the bounding box with `src`, like a clipping region does.
+    ///
+    /// If the rectangles don't intersect, the result is an empty rectangle at the origin,
+    /// so that the clipped object still counts as present but with no extents.  If this
+    /// bounding box does not have a rectangle yet, it takes the one from `src`.
+    pub fn clip(&mut self, src: &BoundingBox) {
+        self.0.clip(&src.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_non_invertible_matrix() {
+        let singular = cairo::Matrix::new(1.0, 2.0, 2.0, 4.0, 0.0, 0.0);
+        assert!(BoundingBox::new().with_matrix(singular).is_none());
+
+        let scale = cairo::Matrix::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0);
+        let bbox = BoundingBox::new().with_matrix(scale).unwrap();
+        assert_eq!(bbox.matrix(), scale);
+    }
+
+    #[test]
+    fn insert_and_clear() {
+        let mut dest = BoundingBox::new()
+            .with_matrix(cairo::Matrix::new(2.0, 0.0, 0.0, 2.0, 0.0, 0.0))
+            .unwrap();
+
+        let src = BoundingBox::new()
+            .with_rect(cairo::Rectangle::new(0.0, 0.0, 10.0, 10.0))
+            .with_ink_rect(cairo::Rectangle::new(-2.0, -2.0, 14.0, 14.0));
+
+        dest.insert(&src);
+        assert_eq!(dest.rect(), Some(cairo::Rectangle::new(0.0, 0.0, 5.0, 5.0)));
+        assert_eq!(
+            dest.ink_rect(),
+            Some(cairo::Rectangle::new(-1.0, -1.0, 7.0, 7.0))
+        );
+
+        dest.clear();
+        assert_eq!(dest.rect(), None);
+        assert_eq!(dest.ink_rect(), None);
+    }
+}
@@ -1,10 +1,12 @@
 //! Gradient paint servers; the `linearGradient` and `radialGradient` elements.
 
-use cssparser::{Color, Parser};
+use cssparser::{Color, Parser, RGBA};
 use markup5ever::{
     expanded_name, local_name, namespace_url, ns, ExpandedName, LocalName, Namespace,
 };
+use std::f64::consts::PI;
 
+use crate::color::color_to_rgba;
 use crate::coord_units;
 use crate::coord_units::CoordUnits;
 use crate::document::{AcquiredNodes, NodeId, NodeStack};
@@ -17,14 +19,16 @@ use crate::node::{CascadedValues, Node, NodeBorrow};
 use crate::paint_server::resolve_color;
 use crate::parse_identifiers;
 use crate::parsers::{Parse, ParseValue};
+use crate::properties::ColorInterpolation;
 use crate::rect::{rect_to_transform, Rect};
 use crate::session::Session;
-use crate::transform::{Transform, TransformAttribute};
+use crate::surface_utils::srgb::{linearize_f64, unlinearize_f64};
+use crate::transform::{Transform, TransformList};
 use crate::unit_interval::UnitInterval;
 use crate::xml::Attributes;
 
 /// Contents of a `<stop>` element for gradient color stops
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct ColorStop {
     /// `<stop offset="..."/>`
     pub offset: UnitInterval,
@@ -91,10 +95,10 @@ enum UnresolvedVariant {
     Radial {
         cx: Option<Length<Horizontal>>,
         cy: Option<Length<Vertical>>,
-        r: Option<Length<Both>>,
+        r: Option<ULength<Both>>,
         fx: Option<Length<Horizontal>>,
         fy: Option<Length<Vertical>>,
-        fr: Option<Length<Both>>,
+        fr: Option<ULength<Both>>,
     },
 }
 
@@ -111,10 +115,10 @@ enum ResolvedGradientVariant {
     Radial {
         cx: Length<Horizontal>,
         cy: Length<Vertical>,
-        r: Length<Both>,
+        r: ULength<Both>,
         fx: Length<Horizontal>,
         fy: Length<Vertical>,
-        fr: Length<Both>,
+        fr: ULength<Both>,
     },
 }
 
@@ -259,12 +263,12 @@ impl UnresolvedVariant {
             } => {
                 let cx = cx.or_else(|| Some(Length::<Horizontal>::parse_str("50%").unwrap()));
                 let cy = cy.or_else(|| Some(Length::<Vertical>::parse_str("50%").unwrap()));
-                let r = r.or_else(|| Some(Length::<Both>::parse_str("50%").unwrap()));
+                let r = r.or_else(|| Some(ULength::<Both>::parse_str("50%").unwrap()));
 
                 // fx and fy fall back to the presentational value of cx and cy
                 let fx = fx.or(cx);
                 let fy = fy.or(cy);
-                let fr = fr.or_else(|| Some(Length::<Both>::parse_str("0%").unwrap()));
+                let fr = fr.or_else(|| Some(ULength::<Both>::parse_str("0%").unwrap()));
 
                 UnresolvedVariant::Radial {
                     cx,
@@ -283,7 +287,7 @@ impl UnresolvedVariant {
 #[derive(Default)]
 struct Common {
     units: Option<GradientUnits>,
-    transform: Option<TransformAttribute>,
+    transform: Option<TransformList>,
     spread: Option<SpreadMethod>,
 
     fallback: Option<NodeId>,
@@ -307,10 +311,10 @@ pub struct RadialGradient {
 
     cx: Option<Length<Horizontal>>,
     cy: Option<Length<Vertical>>,
-    r: Option<Length<Both>>,
+    r: Option<ULength<Both>>,
     fx: Option<Length<Horizontal>>,
     fy: Option<Length<Vertical>>,
-    fr: Option<Length<Both>>,
+    fr: Option<ULength<Both>>,
 }
 
 /// Main structure used during gradient resolution.  For unresolved
@@ -319,7 +323,7 @@ pub struct RadialGradient {
 /// field was specified.
 struct UnresolvedGradient {
     units: Option<GradientUnits>,
-    transform: Option<TransformAttribute>,
+    transform: Option<TransformList>,
     spread: Option<SpreadMethod>,
     stops: Option<Vec<ColorStop>>,
 
@@ -330,9 +334,10 @@ struct UnresolvedGradient {
 #[derive(Clone)]
 pub struct ResolvedGradient {
     units: GradientUnits,
-    transform: TransformAttribute,
+    transform: TransformList,
     spread: SpreadMethod,
     stops: Vec<ColorStop>,
+    interpolation: ColorInterpolation,
 
     variant: ResolvedGradientVariant,
 }
@@ -342,12 +347,206 @@ pub struct UserSpaceGradient {
     pub transform: Transform,
     pub spread: SpreadMethod,
     pub stops: Vec<ColorStop>,
+    pub interpolation: ColorInterpolation,
 
     pub variant: GradientVariant,
 }
 
+/// Number of color stops to generate between each pair of stops when interpolating in a
+/// color space other than sRGB.
+const INTERPOLATION_SAMPLES: usize = 32;
+
+impl UserSpaceGradient {
+    /// Returns color stops that make Cairo render the gradient in its interpolation space.
+    ///
+    /// Cairo always interpolates colors linearly in sRGB.  For other color spaces, this
+    /// samples the gradient between each pair of stops, so that linear interpolation between
+    /// the samples approximates the interpolation in the requested space.
+    pub fn stops_in_interpolation_space(&self) -> Vec<ColorStop> {
+        let space = match self.interpolation {
+            ColorInterpolation::Auto | ColorInterpolation::Srgb => return self.stops.clone(),
+            space => space,
+        };
+
+        let mut stops = Vec::new();
+
+        for pair in self.stops.windows(2) {
+            let (start, end) = (&pair[0], &pair[1]);
+            stops.push(*start);
+
+            let UnitInterval(start_offset) = start.offset;
+            let UnitInterval(end_offset) = end.offset;
+
+            if end_offset <= start_offset {
+                continue;
+            }
+
+            let from = InterpolationColor::new(&start.color, space);
+            let to = InterpolationColor::new(&end.color, space);
+
+            for i in 1..INTERPOLATION_SAMPLES {
+                let t = i as f64 / INTERPOLATION_SAMPLES as f64;
+
+                stops.push(ColorStop {
+                    offset: UnitInterval(start_offset + (end_offset - start_offset) * t),
+                    color: Color::Rgba(from.lerp(&to, t, space).to_rgba(space)),
+                });
+            }
+        }
+
+        stops.extend(self.stops.last().copied());
+
+        stops
+    }
+}
+
+/// A color with premultiplied components in an interpolation color space.
+///
+/// For `oklch`, the components are lightness, chroma and hue in radians, and the hue is not
+/// premultiplied.
+struct InterpolationColor {
+    components: [f64; 3],
+    alpha: f64,
+}
+
+impl InterpolationColor {
+    fn new(color: &Color, space: ColorInterpolation) -> InterpolationColor {
+        let rgba = color_to_rgba(color);
+        let alpha = f64::from(rgba.alpha.unwrap_or(0.0));
+
+        let linear = [rgba.red, rgba.green, rgba.blue]
+            .map(|c| linearize_f64(f64::from(c.unwrap_or(0)) / 255.0));
+
+        let mut components = match space {
+            ColorInterpolation::LinearRgb => linear,
+            ColorInterpolation::Oklab => linear_to_oklab(linear),
+            ColorInterpolation::Oklch => {
+                let [l, a, b] = linear_to_oklab(linear);
+                [l, a.hypot(b), b.atan2(a)]
+            }
+            _ => unreachable!(),
+        };
+
+        let premultiplied = if space == ColorInterpolation::Oklch {
+            2
+        } else {
+            3
+        };
+        for c in components.iter_mut().take(premultiplied) {
+            *c *= alpha;
+        }
+
+        InterpolationColor { components, alpha }
+    }
+
+    fn lerp(
+        &self,
+        other: &InterpolationColor,
+        t: f64,
+        space: ColorInterpolation,
+    ) -> InterpolationColor {
+        let mut components = [0.0; 3];
+
+        for (c, (a, b)) in components
+            .iter_mut()
+            .zip(self.components.iter().zip(other.components.iter()))
+        {
+            *c = a + (b - a) * t;
+        }
+
+        if space == ColorInterpolation::Oklch {
+            components[2] = self.lerp_hue(other, t);
+        }
+
+        InterpolationColor {
+            components,
+            alpha: self.alpha + (other.alpha - self.alpha) * t,
+        }
+    }
+
+    /// Interpolates the hue of two `oklch` colors along the shorter arc.
+    ///
+    /// Achromatic colors have no meaningful hue, so they take the other color's.
+    fn lerp_hue(&self, other: &InterpolationColor, t: f64) -> f64 {
+        let (mut from, mut to) = (self.components[2], other.components[2]);
+
+        if self.components[1] < ACHROMATIC_CHROMA {
+            from = to;
+        } else if other.components[1] < ACHROMATIC_CHROMA {
+            to = from;
+        }
+
+        let mut delta = to - from;
+        if delta > PI {
+            delta -= 2.0 * PI;
+        } else if delta < -PI {
+            delta += 2.0 * PI;
+        }
+
+        from + delta * t
+    }
+
+    fn to_rgba(&self, space: ColorInterpolation) -> RGBA {
+        if self.alpha <= 0.0 {
+            return RGBA::new(Some(0), Some(0), Some(0), Some(0.0));
+        }
+
+        let unpremultiplied = if space == ColorInterpolation::Oklch {
+            2
+        } else {
+            3
+        };
+        let mut components = self.components;
+        for c in components.iter_mut().take(unpremultiplied) {
+            *c /= self.alpha;
+        }
+
+        let linear = match space {
+            ColorInterpolation::LinearRgb => components,
+            ColorInterpolation::Oklab => oklab_to_linear(components),
+            ColorInterpolation::Oklch => {
+                let [l, c, h] = components;
+                oklab_to_linear([l, c * h.cos(), c * h.sin()])
+            }
+            _ => unreachable!(),
+        };
+
+        let [r, g, b] = linear.map(|c| Some(unlinearize_f64(c.clamp(0.0, 1.0)) as f32));
+
+        RGBA::from_floats(r, g, b, Some(self.alpha.clamp(0.0, 1.0) as f32))
+    }
+}
+
+/// Chroma below which an `oklch` color is considered to be a shade of gray.
+const ACHROMATIC_CHROMA: f64 = 1e-4;
+
+// https://bottosson.github.io/posts/oklab/
+fn linear_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_linear([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ]
+}
+
 impl UnresolvedGradient {
-    fn into_resolved(self) -> ResolvedGradient {
+    fn into_resolved(self, interpolation: ColorInterpolation) -> ResolvedGradient {
         assert!(self.is_resolved());
 
         let UnresolvedGradient {
@@ -364,6 +563,7 @@ impl UnresolvedGradient {
                 transform: transform.unwrap(),
                 spread: spread.unwrap(),
                 stops: stops.unwrap(),
+                interpolation,
 
                 variant: variant.into_resolved(),
             },
@@ -373,6 +573,7 @@ impl UnresolvedGradient {
                 transform: transform.unwrap(),
                 spread: spread.unwrap(),
                 stops: stops.unwrap(),
+                interpolation,
 
                 variant: variant.into_resolved(),
             },
@@ -440,7 +641,10 @@ impl UnresolvedGradient {
 
     fn resolve_from_fallback(&self, fallback: &UnresolvedGradient) -> UnresolvedGradient {
         let units = self.units.or(fallback.units);
-        let transform = self.transform.or(fallback.transform);
+        let transform = self
+            .transform
+            .clone()
+            .or_else(|| fallback.transform.clone());
         let spread = self.spread.or(fallback.spread);
         let stops = self.stops.clone().or_else(|| fallback.stops.clone());
         let variant = self.variant.resolve_from_fallback(&fallback.variant);
@@ -458,7 +662,8 @@ impl UnresolvedGradient {
         let units = self.units.or_else(|| Some(GradientUnits::default()));
         let transform = self
             .transform
-            .or_else(|| Some(TransformAttribute::default()));
+            .clone()
+            .or_else(|| Some(TransformList::default()));
         let spread = self.spread.or_else(|| Some(SpreadMethod::default()));
         let stops = self.stops.clone().or_else(|| Some(Vec::<ColorStop>::new()));
         let variant = self.variant.resolve_from_defaults();
@@ -558,7 +763,7 @@ macro_rules! impl_gradient {
             fn get_unresolved(&self, node: &Node, opacity: UnitInterval) -> Unresolved {
                 let mut gradient = UnresolvedGradient {
                     units: self.common.units,
-                    transform: self.common.transform,
+                    transform: self.common.transform.clone(),
                     spread: self.common.spread,
                     stops: None,
                     variant: self.get_unresolved_variant(),
@@ -594,6 +799,16 @@ macro_rules! impl_gradient {
                             return Err(AcquireError::CircularReference(acquired_node.clone()));
                         }
 
+                        if stack.len() >= acquired_nodes.max_reference_chain() {
+                            let mut nodes = stack.nodes();
+                            nodes.insert(0, node.clone());
+                            nodes.push(acquired_node.clone());
+                            return Err(AcquireError::ReferenceChainTooLong(
+                                ReferenceChain::GradientFallback,
+                                nodes,
+                            ));
+                        }
+
                         let unresolved = match *acquired_node.borrow_element_data() {
                             ElementData::$gradient_type(ref g) => {
                                 g.get_unresolved(&acquired_node, opacity)
@@ -614,7 +829,14 @@ macro_rules! impl_gradient {
                     }
                 }
 
-                Ok(gradient.into_resolved())
+                // The interpolation space comes from the referenced gradient element, not
+                // from the ones it takes its attributes from.
+                let interpolation = node
+                    .borrow_element()
+                    .get_computed_values()
+                    .color_interpolation();
+
+                Ok(gradient.into_resolved(interpolation))
             }
         }
     };
@@ -651,6 +873,32 @@ impl ElementTrait for RadialGradient {
     }
 }
 
+/// How close to the edge of the end circle, relative to its radius, a focal point can be.
+const FOCUS_EDGE_TOLERANCE: f64 = 1.0 / 256.0;
+
+/// Moves a focal point that sits on the edge of the end circle slightly inside it.
+///
+/// SVG2 says that a focal circle outside of the end circle produces a cone, which is what
+/// Cairo does natively.  However, when the focal point is just on the edge, the cone
+/// degenerates to a half-plane, and Cairo's fixed-point computations produce seams and
+/// noise there, which are very visible with `spreadMethod="reflect"` or `"repeat"`.
+/// Browsers avoid this by nudging the focal point toward the center, and so do we.
+///
+/// Focal points which are clearly outside of the end circle are left alone.
+fn move_focus_off_edge(cx: f64, cy: f64, r: f64, fx: f64, fy: f64) -> (f64, f64) {
+    let (dx, dy) = (fx - cx, fy - cy);
+    let distance = dx.hypot(dy);
+
+    let max_distance = r * (1.0 - FOCUS_EDGE_TOLERANCE);
+
+    if distance > max_distance && distance <= r * (1.0 + FOCUS_EDGE_TOLERANCE) {
+        let scale = max_distance / distance;
+        (cx + dx * scale, cy + dy * scale)
+    } else {
+        (fx, fy)
+    }
+}
+
 impl ResolvedGradient {
     pub fn to_user_space(
         &self,
@@ -663,7 +911,14 @@ impl ResolvedGradient {
         let view_params = viewport.with_units(units);
         let params = NormalizeParams::from_values(values, &view_params);
 
-        let gradient_transform = self.transform.to_transform();
+        // Percentages in the gradientTransform refer to the bounding box, which is the
+        // unit square if the gradient is already in objectBoundingBox space.
+        let reference_box = match units {
+            CoordUnits::ObjectBoundingBox => Rect::from_size(1.0, 1.0),
+            CoordUnits::UserSpaceOnUse => object_bbox.unwrap_or(*viewport.vbox),
+        };
+
+        let gradient_transform = self.transform.to_transform(&params, &reference_box);
         let transform = transform.pre_transform(&gradient_transform).invert()?;
 
         let variant = match self.variant {
@@ -681,20 +936,29 @@ impl ResolvedGradient {
                 fx,
                 fy,
                 fr,
-            } => GradientVariant::Radial {
-                cx: cx.to_user(&params),
-                cy: cy.to_user(&params),
-                r: r.to_user(&params),
-                fx: fx.to_user(&params),
-                fy: fy.to_user(&params),
-                fr: fr.to_user(&params),
-            },
+            } => {
+                let cx = cx.to_user(&params);
+                let cy = cy.to_user(&params);
+                let r = r.to_user(&params);
+                let (fx, fy) =
+                    move_focus_off_edge(cx, cy, r, fx.to_user(&params), fy.to_user(&params));
+
+                GradientVariant::Radial {
+                    cx,
+                    cy,
+                    r,
+                    fx,
+                    fy,
+                    fr: fr.to_user(&params),
+                }
+            }
         };
 
         Some(UserSpaceGradient {
             transform,
             spread: self.spread,
             stops: self.stops.clone(),
+            interpolation: self.interpolation,
             variant,
         })
     }
@@ -707,6 +971,7 @@ mod tests {
     use markup5ever::{namespace_url, ns, QualName};
 
     use crate::borrow_element_as;
+    use crate::document::Document;
     use crate::node::{Node, NodeData};
 
     #[test]
@@ -749,4 +1014,124 @@ mod tests {
         let gradient = unresolved.gradient.resolve_from_defaults();
         assert!(gradient.is_resolved());
     }
+
+    #[test]
+    fn parses_focal_radius_and_rejects_negative_radii() {
+        let document = Document::load_from_bytes(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+  <radialGradient id="a" r="-1" fr="25%"/>
+  <radialGradient id="b" fr="-5"/>
+</svg>
+"#,
+        );
+
+        let resolve = |id| {
+            let node = document.lookup_internal_node(id).unwrap();
+            let unresolved = borrow_element_as!(node, RadialGradient)
+                .get_unresolved(&node, UnitInterval::clamp(1.0));
+            unresolved
+                .gradient
+                .resolve_from_defaults()
+                .variant
+                .into_resolved()
+        };
+
+        // Negative radii are errors, so the defaults get used instead.
+        match resolve("a") {
+            ResolvedGradientVariant::Radial { r, fr, .. } => {
+                assert_eq!(r, ULength::<Both>::parse_str("50%").unwrap());
+                assert_eq!(fr, ULength::<Both>::parse_str("25%").unwrap());
+            }
+            _ => unreachable!(),
+        }
+
+        match resolve("b") {
+            ResolvedGradientVariant::Radial { fr, .. } => {
+                assert_eq!(fr, ULength::<Both>::parse_str("0%").unwrap());
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn oklab_round_trips() {
+        for color in [
+            [0.0, 0.0, 0.0],
+            [1.0, 1.0, 1.0],
+            [0.2, 0.5, 0.8],
+            [1.0, 0.0, 0.0],
+        ] {
+            let result = oklab_to_linear(linear_to_oklab(color));
+
+            for (a, b) in color.iter().zip(result.iter()) {
+                assert!((a - b).abs() < 1e-6);
+            }
+        }
+    }
+
+    fn black_to_white(interpolation: ColorInterpolation) -> UserSpaceGradient {
+        let stop = |offset, gray| ColorStop {
+            offset: UnitInterval(offset),
+            color: Color::Rgba(RGBA::new(Some(gray), Some(gray), Some(gray), Some(1.0))),
+        };
+
+        UserSpaceGradient {
+            transform: Transform::identity(),
+            spread: SpreadMethod::Pad,
+            stops: vec![stop(0.0, 0), stop(1.0, 255)],
+            interpolation,
+            variant: GradientVariant::Linear {
+                x1: 0.0,
+                y1: 0.0,
+                x2: 1.0,
+                y2: 0.0,
+            },
+        }
+    }
+
+    #[test]
+    fn samples_stops_in_interpolation_space() {
+        let gradient = black_to_white(ColorInterpolation::Srgb);
+        assert_eq!(gradient.stops_in_interpolation_space(), gradient.stops);
+
+        let gradient = black_to_white(ColorInterpolation::LinearRgb);
+        let stops = gradient.stops_in_interpolation_space();
+        assert_eq!(stops.len(), INTERPOLATION_SAMPLES + 1);
+
+        // Halfway in linear RGB is much lighter than halfway in sRGB.
+        let middle = &stops[INTERPOLATION_SAMPLES / 2];
+        assert_eq!(middle.offset, UnitInterval(0.5));
+        assert_eq!(
+            middle.color,
+            Color::Rgba(RGBA::new(Some(188), Some(188), Some(188), Some(1.0)))
+        );
+
+        // Grays have no hue in oklch, so the samples stay gray.
+        let gradient = black_to_white(ColorInterpolation::Oklch);
+        for stop in gradient.stops_in_interpolation_space() {
+            let rgba = color_to_rgba(&stop.color);
+            assert_eq!(rgba.red, rgba.green);
+            assert_eq!(rgba.green, rgba.blue);
+        }
+    }
+
+    #[test]
+    fn moves_focus_off_the_edge_of_the_end_circle() {
+        // Inside the circle, or clearly outside of it, the focus is not changed.
+        assert_eq!(move_focus_off_edge(0.5, 0.5, 0.5, 0.6, 0.5), (0.6, 0.5));
+        assert_eq!(move_focus_off_edge(0.5, 0.5, 0.5, 2.0, 0.5), (2.0, 0.5));
+        assert_eq!(move_focus_off_edge(0.5, 0.5, 0.0, 0.5, 0.5), (0.5, 0.5));
+
+        // On the edge, the focus is moved toward the center.
+        let (fx, fy) = move_focus_off_edge(0.5, 0.5, 0.5, 1.0, 0.5);
+        assert!(fx < 1.0);
+        assert!(fx > 0.99);
+        assert_eq!(fy, 0.5);
+
+        let (fx, fy) = move_focus_off_edge(10.0, 10.0, 10.0, 10.0, 0.0);
+        assert_eq!(fx, 10.0);
+        assert!(fy > 0.0);
+        assert!(fy < 0.1);
+    }
 }
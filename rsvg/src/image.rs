@@ -111,11 +111,49 @@ impl Image {
                     url,
                     e
                 );
+
+                if !clipping && draw_ctx.error_placeholders() {
+                    self.draw_error_placeholder(cascaded, viewport, draw_ctx)?;
+                }
+
                 Ok(draw_ctx.empty_bbox())
             }
         }
     }
 
+    /// Draws a placeholder in the place of an `<image>` that could not be loaded.
+    ///
+    /// Since there is no image to get an intrinsic size from, this needs both
+    /// `width` and `height` to be specified.
+    fn draw_error_placeholder(
+        &self,
+        cascaded: &CascadedValues<'_>,
+        viewport: &Viewport,
+        draw_ctx: &mut DrawingCtx,
+    ) -> Result<(), InternalRenderingError> {
+        let values = cascaded.get();
+
+        if !values.is_visible() {
+            return Ok(());
+        }
+
+        let params = NormalizeParams::new(values, viewport);
+
+        let x = values.x().0.to_user(&params);
+        let y = values.y().0.to_user(&params);
+
+        if let (LengthOrAuto::Length(w), LengthOrAuto::Length(h)) =
+            (values.width().0, values.height().0)
+        {
+            let w = w.to_user(&params);
+            let h = h.to_user(&params);
+
+            draw_ctx.draw_error_placeholder(values.transform(), Rect::new(x, y, x + w, y + h))?;
+        }
+
+        Ok(())
+    }
+
     /// Draw an `<image>` from a raster image.
     fn draw_from_surface(
         &self,
@@ -243,6 +281,12 @@ impl Image {
                 draw_ctx.user_language(),
                 viewport.dpi,
                 SvgNesting::ReferencedFromImageElement,
+                draw_ctx.quality(),
+                None,
+                draw_ctx.error_placeholders(),
+                None,
+                acquired_nodes.spot_colors(),
+                acquired_nodes.linear_light(),
                 draw_ctx.is_testing(),
             )?;
         }
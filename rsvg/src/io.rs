@@ -28,6 +28,30 @@ impl fmt::Display for IoError {
     }
 }
 
+/// Supplies the contents of files that a document references, instead of librsvg reading them.
+///
+/// Set one with [`crate::Loader::with_resource_loader`].  This is implemented for closures
+/// with the same signature as [`ResourceLoader::load`], so you can pass one of those directly.
+pub trait ResourceLoader: Send + Sync {
+    /// Returns the contents of `url`, or `None` to let librsvg read it as usual.
+    ///
+    /// This gets called for the absolute URL of each image, stylesheet, or XInclude file
+    /// that the document references, after librsvg has checked it against its [security
+    /// policy](crate#security-and-locations-of-referenced-files).  `data:` URLs are always
+    /// decoded by librsvg.  Returning an error denies access to the file, and the document
+    /// behaves as if the file could not be read.
+    fn load(&self, url: &str) -> Option<Result<Vec<u8>, glib::Error>>;
+}
+
+impl<F> ResourceLoader for F
+where
+    F: Fn(&str) -> Option<Result<Vec<u8>, glib::Error>> + Send + Sync,
+{
+    fn load(&self, url: &str) -> Option<Result<Vec<u8>, glib::Error>> {
+        self(url)
+    }
+}
+
 pub struct BinaryData {
     pub data: Vec<u8>,
     pub mime_type: Mime,
@@ -64,13 +88,19 @@ fn decode_data_uri(uri: &str) -> Result<BinaryData, IoError> {
 }
 
 /// Creates a stream for reading.  The url can be a data: URL or a plain URI.
+///
+/// If there is a `loader`, it gets the first chance to supply the contents of plain URIs.
 pub fn acquire_stream(
     aurl: &AllowedUrl,
+    loader: Option<&dyn ResourceLoader>,
     cancellable: Option<&Cancellable>,
 ) -> Result<InputStream, IoError> {
     let uri = aurl.as_str();
 
-    if uri.starts_with("data:") {
+    if let Some(res) = load_with(loader, uri) {
+        let stream = MemoryInputStream::from_bytes(&GBytes::from_owned(res?));
+        Ok(stream.upcast::<InputStream>())
+    } else if uri.starts_with("data:") {
         let BinaryData { data, .. } = decode_data_uri(uri)?;
 
         //        {
@@ -92,31 +122,47 @@ pub fn acquire_stream(
 }
 
 /// Reads the entire contents pointed by an URL.  The url can be a data: URL or a plain URI.
+///
+/// If there is a `loader`, it gets the first chance to supply the contents of plain URIs.
 pub fn acquire_data(
     aurl: &AllowedUrl,
+    loader: Option<&dyn ResourceLoader>,
     cancellable: Option<&Cancellable>,
 ) -> Result<BinaryData, IoError> {
     let uri = aurl.as_str();
 
-    if uri.starts_with("data:") {
-        Ok(decode_data_uri(uri)?)
+    let contents = if let Some(res) = load_with(loader, uri) {
+        res?
+    } else if uri.starts_with("data:") {
+        return decode_data_uri(uri);
     } else {
         let file = GFile::for_uri(uri);
         let (contents, _etag) = file.load_contents(cancellable)?;
+        contents
+    };
 
-        let (content_type, _uncertain) = gio::content_type_guess(Some(uri), &contents);
+    let (content_type, _uncertain) = gio::content_type_guess(Some(uri), &contents);
 
-        let mime_type = if let Some(mime_type_str) = gio::content_type_get_mime_type(&content_type)
-        {
-            Mime::from_str(&mime_type_str)
-                .expect("gio::content_type_get_mime_type returned an invalid MIME-type!?")
-        } else {
-            Mime::from_str("application/octet-stream").unwrap()
-        };
+    let mime_type = if let Some(mime_type_str) = gio::content_type_get_mime_type(&content_type) {
+        Mime::from_str(&mime_type_str)
+            .expect("gio::content_type_get_mime_type returned an invalid MIME-type!?")
+    } else {
+        Mime::from_str("application/octet-stream").unwrap()
+    };
 
-        Ok(BinaryData {
-            data: contents,
-            mime_type,
-        })
+    Ok(BinaryData {
+        data: contents,
+        mime_type,
+    })
+}
+
+fn load_with(
+    loader: Option<&dyn ResourceLoader>,
+    uri: &str,
+) -> Option<Result<Vec<u8>, glib::Error>> {
+    if uri.starts_with("data:") {
+        None
+    } else {
+        loader.and_then(|l| l.load(uri))
     }
 }
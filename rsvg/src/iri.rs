@@ -1,6 +1,7 @@
 //! CSS funciri values.
 
 use cssparser::Parser;
+use url::Url;
 
 use crate::document::NodeId;
 use crate::error::*;
@@ -26,6 +27,13 @@ impl Iri {
             Iri::Resource(ref f) => Some(f),
         }
     }
+
+    /// Resolves a relative reference to an external resource against `base_url`.
+    pub fn make_absolute(&mut self, base_url: &Url) {
+        if let Iri::Resource(ref mut node_id) = *self {
+            node_id.make_absolute(base_url);
+        }
+    }
 }
 
 impl Parse for Iri {
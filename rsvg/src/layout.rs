@@ -73,6 +73,7 @@ pub enum LayerKind {
 }
 
 /// Stroke parameters in user-space coordinates.
+#[derive(PartialEq)]
 pub struct Stroke {
     pub width: f64,
     pub miter_limit: StrokeMiterlimit,
@@ -166,6 +167,10 @@ fn get_filter(
     acquired_nodes: &mut AcquiredNodes<'_>,
     session: &Session,
 ) -> Option<Filter> {
+    if !acquired_nodes.features().filters {
+        return None;
+    }
+
     match values.filter() {
         properties::Filter::None => None,
 
@@ -326,6 +331,16 @@ impl StackingContext {
         ctx
     }
 
+    /// Whether the element must be rendered as an isolated group.
+    ///
+    /// Per <https://drafts.fxtf.org/compositing/#csscompositingrules_SVG>, an element
+    /// creates an isolated group when it has opacity less than 1, a filter, a clip path, a
+    /// mask, a blend mode other than `normal`, or `isolation: isolate`.  Descendants with a
+    /// `mix-blend-mode` then blend only with the content of that group, not with what was
+    /// painted before it.
+    ///
+    /// Note that `clip_rect` does not count; it comes from the viewport of things like
+    /// `<svg>` and markers with `overflow: hidden`, not from the `clip-path` property.
     pub fn should_isolate(&self) -> bool {
         let Opacity(UnitInterval(opacity)) = self.opacity;
         match self.isolation {
@@ -335,6 +350,7 @@ impl StackingContext {
                     && self.filter.is_none()
                     && self.mask.is_none()
                     && self.mix_blend_mode == MixBlendMode::Normal
+                    && self.clip_in_user_space.is_none()
                     && self.clip_in_object_space.is_none())
             }
             Isolation::Isolate => true,
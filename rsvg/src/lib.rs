@@ -98,30 +98,32 @@
 //! include raster image data, encoded as base-64, directly in an SVG
 //! file.
 //!
-//! 2. URLs with queries ("?") or fragment identifiers ("#") are not allowed.
+//! 2. No other URLs are allowed if [`Loader::enable_external_resources`] is off.
 //!
-//! 3. All URL schemes other than data: in references require a base URL.  For
+//! 3. URLs with queries ("?") or fragment identifiers ("#") are not allowed.
+//!
+//! 4. All URL schemes other than data: in references require a base URL.  For
 //! example, this means that if you load an SVG with [`Loader::read_stream`]
 //! without providing a `base_file`, then any referenced files will not
 //! be allowed (e.g. raster images to be loaded from other files will
 //! not work).
 //!
-//! 4. If referenced URLs are absolute, rather than relative, then
+//! 5. If referenced URLs are absolute, rather than relative, then
 //! they must have the same scheme as the base URL.  For example, if
 //! the base URL has a "`file`" scheme, then all URL references inside
 //! the SVG must also have the "`file`" scheme, or be relative
 //! references which will be resolved against the base URL.
 //!
-//! 5. If referenced URLs have a "`resource`" scheme, that is, if they
+//! 6. If referenced URLs have a "`resource`" scheme, that is, if they
 //! are included into your binary program with GLib's resource
 //! mechanism, they are allowed to be loaded (provided that the base
 //! URL is also a "`resource`", per the previous rule).
 //!
-//! 6. Otherwise, non-`file` schemes are not allowed.  For example,
+//! 7. Otherwise, non-`file` schemes are not allowed.  For example,
 //! librsvg will not load `http` resources, to keep malicious SVG data
 //! from "phoning home".
 //!
-//! 7. A relative URL must resolve to the same directory as the base
+//! 8. A relative URL must resolve to the same directory as the base
 //! URL, or to one of its subdirectories.  Librsvg will canonicalize
 //! filenames, by removing "`..`" path components and resolving symbolic
 //! links, to decide whether files meet these conditions.
@@ -147,13 +149,17 @@ pub use crate::api::*;
 
 mod accept_language;
 mod angle;
+mod animation;
 mod api;
 mod aspect_ratio;
 mod bbox;
 mod color;
 mod cond;
+mod contrast;
 mod coord_units;
+mod cost;
 mod css;
+mod cvd;
 mod dasharray;
 mod document;
 mod dpi;
@@ -165,6 +171,8 @@ mod filter_func;
 mod filters;
 mod float_eq_cairo;
 mod font_props;
+mod fonts;
+mod geometry;
 mod gradient;
 mod href;
 mod image;
@@ -176,6 +184,8 @@ mod limits;
 mod log;
 mod marker;
 mod node;
+#[cfg(feature = "render-observers")]
+mod observer;
 mod paint_server;
 mod parsers;
 mod path_builder;
@@ -185,9 +195,11 @@ mod properties;
 mod property_defs;
 mod property_macros;
 mod rect;
+mod render_cache;
 mod session;
 mod shapes;
 mod space;
+mod stroke_extents;
 mod structure;
 mod style;
 mod surface_utils;
@@ -26,6 +26,17 @@
 /// [billion laughs attack]: https://bitbucket.org/tiran/defusedxml
 pub const MAX_REFERENCED_ELEMENTS: usize = 500_000;
 
+/// Maximum length of a chain of references of the same kind.
+///
+/// Elements can reference others which reference others in turn, like `<use>` elements that
+/// instance groups with more `<use>` elements in them, or gradients that take their
+/// attributes from other gradients through their `href`.  Each step of such a chain uses
+/// stack space while rendering, so we limit how long a chain can be.  Circular
+/// references are detected separately; this is about long chains of distinct elements.
+///
+/// See [`crate::error::ReferenceChain`] for the kinds of chains.
+pub const MAX_REFERENCE_CHAIN: usize = 256;
+
 /// Maximum number of elements loadable per document.
 ///
 /// This is a mitigation for SVG files which create millions of elements
@@ -48,3 +59,45 @@ pub const MAX_LOADED_ATTRIBUTES: usize = u16::MAX as usize;
 /// where the base document is included within itself, or when two
 /// documents recursively include each other.
 pub const MAX_XINCLUDE_DEPTH: usize = 20;
+
+/// Limits that apply while loading a document, configurable through [`crate::Loader`].
+///
+/// The defaults only limit the number of elements, to [`MAX_LOADED_ELEMENTS`], and the
+/// references between them while rendering, to [`MAX_REFERENCED_ELEMENTS`] and
+/// [`MAX_REFERENCE_CHAIN`]; programs that load untrusted documents on behalf of others may
+/// want to set stricter limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadLimits {
+    /// Maximum number of elements in a document.
+    pub max_elements: usize,
+
+    /// Maximum number of pixels (width × height) in each raster image referenced from
+    /// the document.
+    pub max_image_pixels: Option<u64>,
+
+    /// Maximum level of nesting of XML elements.
+    pub max_nesting_depth: Option<usize>,
+
+    /// Maximum number of bytes of XML to parse, after decompressing `.svgz` files.  This
+    /// includes the XML of files included with `<xi:include>`.
+    pub max_decompressed_size: Option<usize>,
+
+    /// Maximum number of times that elements can be referenced while rendering.
+    pub max_referenced_elements: usize,
+
+    /// Maximum length of each chain of references while rendering.
+    pub max_reference_chain: usize,
+}
+
+impl Default for LoadLimits {
+    fn default() -> LoadLimits {
+        LoadLimits {
+            max_elements: MAX_LOADED_ELEMENTS,
+            max_image_pixels: None,
+            max_nesting_depth: None,
+            max_decompressed_size: None,
+            max_referenced_elements: MAX_REFERENCED_ELEMENTS,
+            max_reference_chain: MAX_REFERENCE_CHAIN,
+        }
+    }
+}
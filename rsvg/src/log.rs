@@ -1,16 +1,310 @@
 //! Utilities for logging messages from the library.
 
+use std::fmt;
+use std::path::{Path, PathBuf};
+
 #[doc(hidden)]
 #[macro_export]
 macro_rules! rsvg_log {
     (
         $session:expr,
+        level: $level:ident,
         $($arg:tt)+
     ) => {
-        if $session.log_enabled() {
-            println!("{}", format_args!($($arg)+));
+        if $session.log_enabled($crate::LogLevel::$level, module_path!()) {
+            $session.log($crate::LogLevel::$level, format_args!($($arg)+));
         }
     };
+
+    (
+        $session:expr,
+        $($arg:tt)+
+    ) => {
+        $crate::rsvg_log!($session, level: Warning, $($arg)+)
+    };
+}
+
+/// Logs a problem with the document, and records it in the session's list of warnings.
+///
+/// Use this instead of [`rsvg_log!`] for things that the author of the SVG file may want
+/// to know about, like invalid attribute values.  The warnings that get collected while
+/// loading a document are available through `SvgHandle::warnings()`.
+///
+/// With `strict` as the second argument, the problem is one that makes loading fail when
+/// the document is loaded in strict mode.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! rsvg_warn {
+    (
+        $session:expr,
+        strict,
+        $($arg:tt)+
+    ) => {{
+        let message = format!($($arg)+);
+        $crate::rsvg_log!($session, "{}", message);
+        $session.add_strict_warning(message);
+    }};
+
+    (
+        $session:expr,
+        $($arg:tt)+
+    ) => {{
+        let message = format!($($arg)+);
+        $crate::rsvg_log!($session, "{}", message);
+        $session.add_warning(message);
+    }};
+}
+
+/// Position of something in the source XML.
+///
+/// Both numbers start at 1.  For elements, this is the position that the XML parser has
+/// reached after reading the element's start tag, so it points just past the `>` that
+/// closes the tag.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    /// Line number.
+    pub line: u32,
+
+    /// Column number within the line.
+    pub column: u32,
+}
+
+/// A non-fatal problem found while loading a document.
+///
+/// Librsvg ignores the parts of a document that it cannot use, per the SVG spec; for
+/// example, an attribute with an invalid value is treated as if it were not specified.
+/// These warnings let you tell the author of the document about those problems.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadWarning {
+    /// Where in the document the problem was found.
+    ///
+    /// This is `None` for problems that cannot be tied to a position, for example
+    /// those in stylesheets that were loaded from a separate file.
+    pub location: Option<Location>,
+
+    /// Human-readable description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(Location { line, column }) = self.location {
+            write!(f, "{line}:{column}: ")?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A `fill` or `stroke` that references a paint server which could not be used.
+///
+/// These are found while rendering, not while loading, since whether a reference works
+/// can depend on things like the element being rendered or the styles applied to the
+/// document.  Per the SVG spec, the element gets painted with the fallback color from
+/// the property, as in `fill="url(#gradient) red"`, or not painted at all if there is
+/// no fallback color.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenPaintReference {
+    /// The reference as it appears in the property, like `#gradient`.
+    pub reference: String,
+
+    /// Human-readable description of why the reference could not be used.
+    pub reason: String,
+
+    /// Whether the element was painted with the fallback color instead.
+    pub used_fallback: bool,
+}
+
+impl fmt::Display for BrokenPaintReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "could not use paint server \"{}\" ({}), {}",
+            self.reference,
+            self.reason,
+            if self.used_fallback {
+                "used the fallback color"
+            } else {
+                "no fallback color specified"
+            }
+        )
+    }
+}
+
+/// Severity of a message logged by librsvg.
+///
+/// Levels are ordered from most to least severe, so `LogLevel::Warning < LogLevel::Debug`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Something in the document could not be used, for example an invalid attribute value,
+    /// a reference to an element that does not exist, or a resource that could not be
+    /// loaded.  Librsvg ignores the offending part of the document and keeps going.
+    Warning,
+
+    /// Tracing of what librsvg does while rendering, like the start and end of each element
+    /// and filter primitive.  This is verbose and mostly useful for debugging librsvg.
+    Debug,
+}
+
+/// Receiver for the messages that librsvg logs while loading and rendering a document.
+///
+/// Set one with [`crate::Loader::with_log_sink`].  This is implemented for closures that
+/// take a [`LogLevel`] and the message, so you can pass one of those directly.
+pub trait LogSink: Send + Sync {
+    /// Called with each message at a level for which [`LogSink::enabled`] returned `true`.
+    fn log(&self, level: LogLevel, message: &str);
+
+    /// Whether messages at `level` should be passed to [`LogSink::log`].
+    ///
+    /// Librsvg does not format messages which are not enabled, so filtering here is
+    /// cheaper than doing it in `log`.  The default implementation enables all levels.
+    fn enabled(&self, _level: LogLevel) -> bool {
+        true
+    }
+}
+
+impl<F> LogSink for F
+where
+    F: Fn(LogLevel, &str) + Send + Sync,
+{
+    fn log(&self, level: LogLevel, message: &str) {
+        self(level, message)
+    }
+}
+
+/// Debugging options for loading and rendering documents.
+///
+/// Librsvg normally prints all of its log messages to standard output if the `RSVG_LOG`
+/// environment variable is set; that is what [`DebugConfig::from_env`] returns, and the
+/// default when no configuration is given.  Programs can pass a `DebugConfig` to
+/// [`crate::Loader::with_debug_config`] or [`crate::CairoRenderer::with_debug_config`]
+/// to turn on debugging without touching the environment.
+///
+/// # Example:
+/// ```no_run
+/// use rsvg::{DebugConfig, LogLevel};
+///
+/// let config = DebugConfig::new()
+///     .with_log_level(LogLevel::Debug)
+///     .with_categories(["filters"])
+///     .with_trace_file("/tmp/rsvg-trace.txt");
+///
+/// let svg_handle = rsvg::Loader::new()
+///     .with_debug_config(config)
+///     .read_path("example.svg")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DebugConfig {
+    level: Option<LogLevel>,
+    categories: Vec<String>,
+    trace_file: Option<PathBuf>,
+    #[cfg(feature = "cairo-script")]
+    cairo_script: Option<PathBuf>,
+}
+
+impl DebugConfig {
+    /// Creates a configuration with logging turned off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a configuration from the `RSVG_LOG` environment variable.
+    ///
+    /// If the variable is set, all messages are logged to standard output; otherwise
+    /// logging is turned off.
+    pub fn from_env() -> Self {
+        let config = Self::new();
+
+        if std::env::var_os("RSVG_LOG").is_some() {
+            config.with_log_level(LogLevel::Debug)
+        } else {
+            config
+        }
+    }
+
+    /// Logs messages up to `level`; for example, `LogLevel::Warning` logs only warnings.
+    pub fn with_log_level(self, level: LogLevel) -> Self {
+        Self {
+            level: Some(level),
+            ..self
+        }
+    }
+
+    /// Only logs messages from the given parts of librsvg.
+    ///
+    /// Categories are the names of librsvg's source modules, like `"filters"`, `"text"`,
+    /// or `"css"`; a category includes its submodules, so `"filters"` also logs
+    /// messages from `"filters::lighting"`.  If no categories are given, which is the
+    /// default, messages from everywhere are logged.
+    pub fn with_categories<I, S>(self, categories: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            categories: categories.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Writes log messages to a file instead of standard output.
+    ///
+    /// The file is truncated when the [`crate::Loader`] or [`crate::CairoRenderer`] that
+    /// uses this configuration is created.
+    pub fn with_trace_file<P: AsRef<Path>>(self, path: P) -> Self {
+        Self {
+            trace_file: Some(path.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    /// Writes the drawing operations of each call to
+    /// [`crate::CairoRenderer::render_document`] to a cairo-script file.
+    ///
+    /// The script can be replayed with cairo's `csi-replay` and similar tools, to see
+    /// exactly what librsvg asked Cairo to draw.
+    ///
+    /// This is only available with the `cairo-script` Cargo feature.
+    #[cfg(feature = "cairo-script")]
+    pub fn with_cairo_script<P: AsRef<Path>>(self, path: P) -> Self {
+        Self {
+            cairo_script: Some(path.as_ref().to_path_buf()),
+            ..self
+        }
+    }
+
+    pub(crate) fn trace_file(&self) -> Option<&Path> {
+        self.trace_file.as_deref()
+    }
+
+    #[cfg(feature = "cairo-script")]
+    pub(crate) fn cairo_script(&self) -> Option<&Path> {
+        self.cairo_script.as_deref()
+    }
+
+    /// Whether a message at `level`, logged from `module_path`, passes the filters.
+    pub(crate) fn enabled(&self, level: LogLevel, module_path: &str) -> bool {
+        self.level.is_some_and(|max| level <= max) && self.in_categories(module_path)
+    }
+
+    /// Whether `module_path`, as given by `module_path!()`, is in one of the categories.
+    pub(crate) fn in_categories(&self, module_path: &str) -> bool {
+        if self.categories.is_empty() {
+            return true;
+        }
+
+        // Strip the crate name, so that "rsvg::filters::lighting" becomes "filters::lighting".
+        let module = module_path
+            .split_once("::")
+            .map_or("", |(_crate_name, module)| module);
+
+        self.categories.iter().any(|category| {
+            module
+                .strip_prefix(category.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+        })
+    }
 }
 
 /// Captures the basic state of a [`cairo::Context`] for logging purposes.
@@ -43,6 +337,24 @@ impl CairoContextState {
 mod tests {
     use super::*;
 
+    #[test]
+    fn debug_config_filters_by_level_and_category() {
+        let config = DebugConfig::new();
+        assert!(!config.enabled(LogLevel::Warning, "rsvg::filters"));
+
+        let config = DebugConfig::new().with_log_level(LogLevel::Warning);
+        assert!(config.enabled(LogLevel::Warning, "rsvg::text"));
+        assert!(!config.enabled(LogLevel::Debug, "rsvg::text"));
+
+        let config = config.with_categories(["filters", "css"]);
+        assert!(config.enabled(LogLevel::Warning, "rsvg::filters"));
+        assert!(config.enabled(LogLevel::Warning, "rsvg::filters::lighting"));
+        assert!(config.enabled(LogLevel::Warning, "rsvg::css"));
+        assert!(!config.enabled(LogLevel::Warning, "rsvg::filter_func"));
+        assert!(!config.enabled(LogLevel::Warning, "rsvg::cssparser"));
+        assert!(!config.enabled(LogLevel::Warning, "rsvg"));
+    }
+
     #[test]
     fn captures_cr_state() {
         let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 10, 10).unwrap();
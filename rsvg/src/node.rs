@@ -14,7 +14,7 @@ use std::rc::Rc;
 
 use crate::bbox::BoundingBox;
 use crate::document::AcquiredNodes;
-use crate::drawing_ctx::{DrawingCtx, Viewport};
+use crate::drawing_ctx::{DrawingCtx, ShapeBatch, Viewport};
 use crate::element::*;
 use crate::error::*;
 use crate::paint_server::PaintSource;
@@ -114,6 +114,14 @@ enum CascadedInner<'a> {
 }
 
 impl<'a> CascadedValues<'a> {
+    /// Whether these are the node's own computed values from the document's cascade.
+    ///
+    /// This is not the case for elements that get re-cascaded from elsewhere, like the
+    /// target of a `<use>` element.
+    pub fn is_from_node(&self) -> bool {
+        matches!(self.inner, CascadedInner::FromNode(_))
+    }
+
     /// Creates a `CascadedValues` that has the same cascading mode as &self
     ///
     /// This is what nodes should normally use to draw their children from their `draw()` method.
@@ -325,6 +333,50 @@ pub trait NodeDraw {
     ) -> Result<BoundingBox, InternalRenderingError>;
 }
 
+fn draw_children_list(
+    children: &[Node],
+    acquired_nodes: &mut AcquiredNodes<'_>,
+    cascaded: &CascadedValues<'_>,
+    viewport: &Viewport,
+    draw_ctx: &mut DrawingCtx,
+    clipping: bool,
+) -> Result<BoundingBox, InternalRenderingError> {
+    let mut bbox = draw_ctx.empty_bbox();
+    let mut batch = ShapeBatch::default();
+
+    for child in children {
+        let child_cascaded = CascadedValues::clone_with_node(cascaded, child);
+
+        if let Some(layer) =
+            draw_ctx.layout_for_batch(child, acquired_nodes, &child_cascaded, viewport, clipping)
+        {
+            let layer = layer?;
+
+            if !batch.accepts(&layer) {
+                bbox.insert(&draw_ctx.draw_shape_batch(&mut batch, acquired_nodes, viewport)?);
+            }
+
+            batch.push(layer);
+            continue;
+        }
+
+        bbox.insert(&draw_ctx.draw_shape_batch(&mut batch, acquired_nodes, viewport)?);
+
+        let child_bbox = draw_ctx.draw_node_from_stack(
+            child,
+            acquired_nodes,
+            &child_cascaded,
+            viewport,
+            clipping,
+        )?;
+        bbox.insert(&child_bbox);
+    }
+
+    bbox.insert(&draw_ctx.draw_shape_batch(&mut batch, acquired_nodes, viewport)?);
+
+    Ok(bbox)
+}
+
 impl NodeDraw for Node {
     fn draw(
         &self,
@@ -336,7 +388,7 @@ impl NodeDraw for Node {
     ) -> Result<BoundingBox, InternalRenderingError> {
         match *self.borrow() {
             NodeData::Element(ref e) => {
-                rsvg_log!(draw_ctx.session(), "({}", e);
+                rsvg_log!(draw_ctx.session(), level: Debug, "({}", e);
                 let res = match e.draw(self, acquired_nodes, cascaded, viewport, draw_ctx, clipping)
                 {
                     Ok(bbox) => Ok(bbox),
@@ -351,7 +403,7 @@ impl NodeDraw for Node {
                     Err(e) => Err(e),
                 };
 
-                rsvg_log!(draw_ctx.session(), ")");
+                rsvg_log!(draw_ctx.session(), level: Debug, ")");
 
                 res
             }
@@ -368,18 +420,32 @@ impl NodeDraw for Node {
         draw_ctx: &mut DrawingCtx,
         clipping: bool,
     ) -> Result<BoundingBox, InternalRenderingError> {
-        let mut bbox = draw_ctx.empty_bbox();
-
-        for child in self.children().filter(|c| c.is_element()) {
-            let child_bbox = draw_ctx.draw_node_from_stack(
-                &child,
-                acquired_nodes,
-                &CascadedValues::clone_with_node(cascaded, &child),
-                viewport,
-                clipping,
-            )?;
-            bbox.insert(&child_bbox);
-        }
+        let children: Vec<Node> = self.children().filter(|c| c.is_element()).collect();
+
+        // Children drawn before an opaque background that covers everything are not
+        // visible, so they are only laid out for their bounding boxes.  This does not
+        // hold in overprint previews, where spot inks let what is underneath show through.
+        let occluder = if clipping || !acquired_nodes.spot_colors().is_empty() {
+            None
+        } else {
+            draw_ctx.find_occluder(&children, cascaded, viewport)
+        };
+
+        let (hidden, shown) = children.split_at(occluder.unwrap_or(0));
+
+        let mut bbox = draw_ctx.with_occlusion(&mut |dc| {
+            draw_children_list(hidden, acquired_nodes, cascaded, viewport, dc, clipping)
+        })?;
+
+        let shown_bbox = draw_children_list(
+            shown,
+            acquired_nodes,
+            cascaded,
+            viewport,
+            draw_ctx,
+            clipping,
+        )?;
+        bbox.insert(&shown_bbox);
 
         Ok(bbox)
     }
@@ -0,0 +1,49 @@
+//! Hooks that get called around the rendering of each element.
+//!
+//! This is only available with the `render-observers` Cargo feature.
+
+use std::time::Duration;
+
+/// An element that is being rendered, as passed to a [`RenderObserver`].
+pub struct ObservedElement<'a> {
+    /// The element's `id` attribute, if it has one.
+    pub id: Option<&'a str>,
+
+    /// The local name of the element, like `"rect"` or `"g"`.
+    pub name: &'a str,
+
+    /// The Cairo context that the element is drawn on.
+    ///
+    /// Its current transformation is the one that the element's parent established, so
+    /// an observer can draw things like watermarks in the same coordinate system as the
+    /// element.  Observers must leave the context in the same state in which they got it;
+    /// use `save()` and `restore()` around any drawing.
+    pub cr: &'a cairo::Context,
+}
+
+/// Receiver for the rendering of each element, for profilers, coverage tools, and the
+/// like.
+///
+/// Set one with [`crate::CairoRenderer::with_render_observer`].  Both methods do nothing
+/// by default.
+///
+/// Elements are reported each time they are drawn; an element that gets instanced by
+/// several `<use>`, or that is part of a pattern, is reported every time.  Elements
+/// drawn while computing clipping paths are not reported.
+pub trait RenderObserver: Send + Sync {
+    /// Called just before an element is drawn.
+    fn before_element(&self, _element: &ObservedElement<'_>) {}
+
+    /// Called just after an element is drawn.
+    ///
+    /// `bounds` are the extents of everything the element painted, in the device space of
+    /// the context given to the renderer, or `None` if it painted nothing or rendering
+    /// failed.  `elapsed` is the time it took to draw the element and its children.
+    fn after_element(
+        &self,
+        _element: &ObservedElement<'_>,
+        _bounds: Option<cairo::Rectangle>,
+        _elapsed: Duration,
+    ) {
+    }
+}
@@ -1,6 +1,7 @@
 //! SVG paint servers.
 
 use std::rc::Rc;
+use url::Url;
 
 use cssparser::{
     Color, ColorFunction, Hsl, Hwb, Lab, Lch, Oklab, Oklch, ParseErrorKind, Parser, RGBA,
@@ -12,6 +13,7 @@ use crate::element::ElementData;
 use crate::error::{AcquireError, NodeIdError, ParseError, ValueErrorKind};
 use crate::gradient::{ResolvedGradient, UserSpaceGradient};
 use crate::length::NormalizeValues;
+use crate::log::BrokenPaintReference;
 use crate::node::NodeBorrow;
 use crate::parsers::Parse;
 use crate::pattern::{ResolvedPattern, UserSpacePattern};
@@ -127,6 +129,13 @@ impl Parse for PaintServer {
 }
 
 impl PaintServer {
+    /// Resolves a relative reference to an external paint server against `base_url`.
+    pub fn make_absolute(&mut self, base_url: &Url) {
+        if let PaintServer::Iri { ref mut iri, .. } = *self {
+            iri.make_absolute(base_url);
+        }
+    }
+
     /// Resolves colors, plus node references for gradients and patterns.
     ///
     /// `opacity` depends on `strokeOpacity` or `fillOpacity` depending on whether
@@ -151,77 +160,111 @@ impl PaintServer {
             PaintServer::Iri {
                 ref iri,
                 ref alternate,
-            } => acquired_nodes
-                .acquire(iri)
-                .and_then(|acquired| {
-                    let node = acquired.get();
-                    assert!(node.is_element());
-
-                    match *node.borrow_element_data() {
-                        ElementData::LinearGradient(ref g) => {
-                            g.resolve(node, acquired_nodes, opacity).map(|g| {
-                                Rc::new(PaintSource::Gradient(
-                                    g,
-                                    alternate.map(|c| resolve_color(&c, opacity, &current_color)),
-                                ))
-                            })
+            } => {
+                // Paint servers with an alternate color depend on the current color, so
+                // we only cache the ones without it.
+                let render_cache = acquired_nodes
+                    .render_cache()
+                    .filter(|_| alternate.is_none());
+
+                if let Some(paint) = render_cache.and_then(|c| c.paint_source(iri, opacity)) {
+                    return paint;
+                }
+
+                acquired_nodes
+                    .acquire(iri)
+                    .and_then(|acquired| {
+                        let node = acquired.get();
+                        assert!(node.is_element());
+
+                        match *node.borrow_element_data() {
+                            ElementData::LinearGradient(ref g) => {
+                                g.resolve(node, acquired_nodes, opacity).map(|g| {
+                                    Rc::new(PaintSource::Gradient(
+                                        g,
+                                        alternate
+                                            .map(|c| resolve_color(&c, opacity, &current_color)),
+                                    ))
+                                })
+                            }
+                            ElementData::Pattern(ref p) => {
+                                p.resolve(node, iri, acquired_nodes, opacity, session).map(|p| {
+                                    Rc::new(PaintSource::Pattern(
+                                        p,
+                                        alternate
+                                            .map(|c| resolve_color(&c, opacity, &current_color)),
+                                    ))
+                                })
+                            }
+                            ElementData::RadialGradient(ref g) => {
+                                g.resolve(node, acquired_nodes, opacity).map(|g| {
+                                    Rc::new(PaintSource::Gradient(
+                                        g,
+                                        alternate
+                                            .map(|c| resolve_color(&c, opacity, &current_color)),
+                                    ))
+                                })
+                            }
+                            _ => Err(AcquireError::InvalidLinkType(iri.as_ref().clone())),
                         }
-                        ElementData::Pattern(ref p) => {
-                            p.resolve(node, acquired_nodes, opacity, session).map(|p| {
-                                Rc::new(PaintSource::Pattern(
-                                    p,
-                                    alternate.map(|c| resolve_color(&c, opacity, &current_color)),
-                                ))
-                            })
+                    })
+                    .map(|paint| {
+                        if let Some(render_cache) = render_cache {
+                            render_cache.store_paint_source(iri, opacity, paint.clone());
                         }
-                        ElementData::RadialGradient(ref g) => {
-                            g.resolve(node, acquired_nodes, opacity).map(|g| {
-                                Rc::new(PaintSource::Gradient(
-                                    g,
-                                    alternate.map(|c| resolve_color(&c, opacity, &current_color)),
-                                ))
-                            })
+
+                        paint
+                    })
+                    .unwrap_or_else(|e| {
+                        session.add_broken_paint_reference(BrokenPaintReference {
+                            reference: iri.to_string(),
+                            reason: e.to_string(),
+                            used_fallback: alternate.is_some(),
+                        });
+
+                        match alternate {
+                            // The following cases catch AcquireError::CircularReference,
+                            // AcquireError::ReferenceChainTooLong, and
+                            // AcquireError::MaxReferencesExceeded.
+                            //
+                            // Circular references mean that there is a pattern or gradient with a
+                            // reference cycle in its "href" attribute.  This is an invalid paint
+                            // server, and per
+                            // https://www.w3.org/TR/SVG2/painting.html#SpecifyingPaint we should
+                            // try to fall back to the alternate color.
+                            //
+                            // Exceeding the maximum number of references will get caught again
+                            // later in the drawing code, so it should be fine to translate this
+                            // condition to that for an invalid paint server.  Chains of "href"
+                            // that are too long are treated like circular ones.
+                            Some(color) => {
+                                rsvg_log!(
+                                    session,
+                                    "could not resolve paint server \"{}\" ({}), using alternate color",
+                                    iri,
+                                    e
+                                );
+
+                                Rc::new(PaintSource::SolidColor(resolve_color(
+                                    color,
+                                    opacity,
+                                    &current_color,
+                                )))
+                            }
+
+                            None => {
+                                rsvg_log!(
+                                    session,
+                                    "could not resolve paint server \"{}\" ({}), no alternate color specified",
+                                    iri,
+                                    e
+                                );
+
+                                Rc::new(PaintSource::None)
+                            }
                         }
-                        _ => Err(AcquireError::InvalidLinkType(iri.as_ref().clone())),
-                    }
-                })
-                .unwrap_or_else(|_| match alternate {
-                    // The following cases catch AcquireError::CircularReference and
-                    // AcquireError::MaxReferencesExceeded.
-                    //
-                    // Circular references mean that there is a pattern or gradient with a
-                    // reference cycle in its "href" attribute.  This is an invalid paint
-                    // server, and per
-                    // https://www.w3.org/TR/SVG2/painting.html#SpecifyingPaint we should
-                    // try to fall back to the alternate color.
-                    //
-                    // Exceeding the maximum number of references will get caught again
-                    // later in the drawing code, so it should be fine to translate this
-                    // condition to that for an invalid paint server.
-                    Some(color) => {
-                        rsvg_log!(
-                            session,
-                            "could not resolve paint server \"{}\", using alternate color",
-                            iri
-                        );
-
-                        Rc::new(PaintSource::SolidColor(resolve_color(
-                            color,
-                            opacity,
-                            &current_color,
-                        )))
-                    }
-
-                    None => {
-                        rsvg_log!(
-                            session,
-                            "could not resolve paint server \"{}\", no alternate color specified",
-                            iri
-                        );
-
-                        Rc::new(PaintSource::None)
-                    }
-                }),
+                    })
+            }
 
             PaintServer::SolidColor(color) => Rc::new(PaintSource::SolidColor(resolve_color(
                 color,
@@ -20,12 +20,16 @@
 
 use tinyvec::TinyVec;
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::f64;
 use std::f64::consts::*;
+use std::rc::Rc;
 use std::slice;
 
 use crate::float_eq_cairo::ApproxEqCairo;
 use crate::path_parser::{ParseError, PathParser};
+use crate::rect::Rect;
 use crate::util::clamp;
 
 /// Whether an arc's sweep should be >= 180 degrees, or smaller.
@@ -60,6 +64,55 @@ impl CubicBezierCurve {
         CubicBezierCurve { pt1, pt2, to }
     }
 
+    /// Returns the point at parameter `t` of the curve that starts at `from`.
+    pub(crate) fn point_at(&self, from: (f64, f64), t: f64) -> (f64, f64) {
+        let s = 1.0 - t;
+        let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+
+        (
+            a * from.0 + b * self.pt1.0 + c * self.pt2.0 + d * self.to.0,
+            a * from.1 + b * self.pt1.1 + c * self.pt2.1 + d * self.to.1,
+        )
+    }
+
+    /// Returns the parameters in `(0, 1)` where the curve that starts at `from` reaches
+    /// an extreme in x, and in y, respectively.
+    ///
+    /// These are the points where the derivative of each coordinate is zero.
+    pub(crate) fn extrema(
+        &self,
+        from: (f64, f64),
+    ) -> (impl Iterator<Item = f64>, impl Iterator<Item = f64>) {
+        let roots = |p0: f64, p1: f64, p2: f64, p3: f64| {
+            // Derivative of the cubic, divided by 3.
+            let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+            let b = 2.0 * (p0 - 2.0 * p1 + p2);
+            let c = p1 - p0;
+
+            quadratic_roots_in_unit_interval(a, b, c)
+        };
+
+        (
+            roots(from.0, self.pt1.0, self.pt2.0, self.to.0),
+            roots(from.1, self.pt1.1, self.pt2.1, self.to.1),
+        )
+    }
+
+    /// Grows `extents` to include the curve that starts at `from`.
+    ///
+    /// The curve is bounded by its endpoints plus its extrema, so this is exact, unlike
+    /// using the bounding box of the control points.
+    fn add_to_extents(&self, from: (f64, f64), extents: &mut Extents) {
+        extents.add(from);
+        extents.add(self.to);
+
+        let (x_extrema, y_extrema) = self.extrema(from);
+
+        for t in x_extrema.chain(y_extrema) {
+            extents.add(self.point_at(from, t));
+        }
+    }
+
     /// Pushes 6 coordinates to `coords` and returns `PackedCommand::CurveTo`.
     fn to_packed_and_coords(&self, coords: &mut Vec<f64>) -> PackedCommand {
         coords.push(self.pt1.0);
@@ -72,6 +125,48 @@ impl CubicBezierCurve {
     }
 }
 
+/// Solves `a*t^2 + b*t + c = 0`, and returns the roots that are within `(0, 1)`.
+fn quadratic_roots_in_unit_interval(a: f64, b: f64, c: f64) -> impl Iterator<Item = f64> {
+    let roots = if a.abs() < f64::EPSILON {
+        if b.abs() < f64::EPSILON {
+            [None, None]
+        } else {
+            [Some(-c / b), None]
+        }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+
+        if discriminant < 0.0 {
+            [None, None]
+        } else {
+            let sqrt = discriminant.sqrt();
+            [Some((-b + sqrt) / (2.0 * a)), Some((-b - sqrt) / (2.0 * a))]
+        }
+    };
+
+    roots.into_iter().flatten().filter(|t| *t > 0.0 && *t < 1.0)
+}
+
+/// Accumulates the bounding box of points.
+#[derive(Default)]
+pub(crate) struct Extents(Option<Rect>);
+
+impl Extents {
+    pub(crate) fn add(&mut self, (x, y): (f64, f64)) {
+        let point = Rect::new(x, y, x, y);
+
+        self.0 = Some(match self.0 {
+            Some(ref r) => r.union(&point),
+            None => point,
+        });
+    }
+
+    /// Returns the bounding box, or `None` if no points were added.
+    pub(crate) fn rect(&self) -> Option<Rect> {
+        self.0
+    }
+}
+
 /// Conversion from endpoint parameterization to center parameterization.
 ///
 /// SVG path data specifies elliptical arcs in terms of their endpoints, but
@@ -79,6 +174,7 @@ impl CubicBezierCurve {
 ///
 /// When attempting to compute the center parameterization of the arc,
 /// out of range parameters may see an arc omitted or treated as a line.
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum ArcParameterization {
     /// Center parameterization of the arc.
     CenterParameters {
@@ -279,6 +375,110 @@ impl EllipticalArc {
     }
 }
 
+/// Maximum distance, in device pixels, between an elliptical arc and the Bézier curves that
+/// approximate it.
+///
+/// This is the same as Cairo's default tolerance for flattening curves into lines.
+const ARC_TOLERANCE: f64 = 0.1;
+
+/// Upper bound for the number of Bézier curves used for a single arc.
+///
+/// This only matters for arcs which are absurdly large after transformation.
+const MAX_ARC_SEGMENTS: u32 = 1024;
+
+/// Maximum error of a single Bézier curve that approximates a circular arc of unit radius.
+///
+/// `angle` is the arc's angle in radians.  This is the same formula that Cairo uses.
+fn arc_error_normalized(angle: f64) -> f64 {
+    2.0 / 27.0 * (angle / 4.0).sin().powi(6) / (angle / 4.0).cos().powi(2)
+}
+
+/// Largest angle of a circular arc of unit radius that a single Bézier curve approximates
+/// with at most `error`.
+///
+/// This inverts [`arc_error_normalized`].  With `s = sin²(angle / 4)`, the error is
+/// `2/27 · s³ / (1 - s)`, so `s` is the real root of `s³ + k·s - k = 0` with
+/// `k = 27/2 · error`, which Cardano's formula gives in a numerically stable form.
+fn max_arc_angle_normalized(error: f64) -> f64 {
+    let k = 27.0 / 2.0 * error;
+    let u = (k / 2.0 + (k * k / 4.0 + k * k * k / 27.0).sqrt()).cbrt();
+    let s = u - k / (3.0 * u);
+
+    4.0 * clamp(s, 0.0, 1.0).sqrt().asin()
+}
+
+/// Returns the number of Bézier curves needed to approximate an arc.
+///
+/// Each curve spans at most 90 degrees.  Arcs that are large in device space get more
+/// curves so that the error stays below [`ARC_TOLERANCE`]; `scale` is the largest factor by
+/// which the current transformation scales distances.
+pub(crate) fn num_arc_segments(delta_theta: f64, radii: (f64, f64), scale: f64) -> u32 {
+    let radius = radii.0.abs().max(radii.1.abs()) * scale;
+    let n_segs = (delta_theta / (PI * 0.5 + 0.001)).abs().ceil();
+
+    // Small arcs are smooth enough with one curve per quarter turn.
+    if radius * arc_error_normalized(PI * 0.5) <= ARC_TOLERANCE {
+        return n_segs as u32;
+    }
+
+    let max_angle = max_arc_angle_normalized(ARC_TOLERANCE / radius);
+    let n_tolerance = (delta_theta.abs() / max_angle).ceil();
+
+    n_segs.max(n_tolerance).min(f64::from(MAX_ARC_SEGMENTS)) as u32
+}
+
+/// An elliptical arc converted to cubic Bézier curves for drawing.
+#[derive(Debug, Clone)]
+pub enum FlattenedArc {
+    /// Curves that start at the arc's start point.
+    Curves(Rc<[CubicBezierCurve]>),
+
+    /// The arc degenerates to a line to the specified end point.
+    LineTo((f64, f64)),
+
+    /// The arc is omitted.
+    Omit,
+}
+
+/// The parameters of an arc as given in a path, as a key for [`Path::flatten_arc`]'s cache.
+///
+/// Coordinates are compared by their bits, so only arcs that are exactly the same share
+/// a cache entry.
+#[derive(PartialEq, Eq, Hash)]
+struct ArcKey {
+    coords: [u64; 7],
+    large_arc: bool,
+    sweep: bool,
+}
+
+impl From<&EllipticalArc> for ArcKey {
+    fn from(arc: &EllipticalArc) -> ArcKey {
+        let coords = [
+            arc.r.0,
+            arc.r.1,
+            arc.x_axis_rotation,
+            arc.from.0,
+            arc.from.1,
+            arc.to.0,
+            arc.to.1,
+        ];
+
+        ArcKey {
+            coords: coords.map(f64::to_bits),
+            large_arc: arc.large_arc.0,
+            sweep: arc.sweep == Sweep::Positive,
+        }
+    }
+}
+
+/// Cached conversion of an arc; see [`Path::flatten_arc`].
+struct CachedArc {
+    parameterization: ArcParameterization,
+
+    /// Number of segments and the resulting curves, for the last scale that was used.
+    curves: Option<(u32, Rc<[CubicBezierCurve]>)>,
+}
+
 /// Turns an arc segment into a cubic bezier curve.
 ///
 /// Takes the center, the radii and the x-axis rotation of the ellipse,
@@ -454,6 +654,13 @@ pub struct PathBuilder {
 pub struct Path {
     commands: Box<[PackedCommand]>,
     coords: Box<[f64]>,
+
+    /// Elliptical arcs converted to Bézier curves, keyed by the arcs' parameters.
+    ///
+    /// Paths get drawn several times, for example to compute their extents and then to
+    /// render them, and converting arcs needs a fair amount of trigonometry.  This stays
+    /// empty for paths without arcs.
+    flattened_arcs: RefCell<HashMap<ArcKey, CachedArc>>,
 }
 
 /// Packed version of a `PathCommand`, used in `Path`.
@@ -516,6 +723,7 @@ impl PathBuilder {
         Path {
             commands: packed_commands.into_boxed_slice(),
             coords: coords.into_boxed_slice(),
+            flattened_arcs: RefCell::new(HashMap::new()),
         }
     }
 
@@ -720,6 +928,207 @@ impl Path {
     pub fn is_empty(&self) -> bool {
         self.commands.is_empty()
     }
+
+    /// Computes the bounding box of the path, without regard to stroke parameters.
+    ///
+    /// This is exact for lines and Bézier curves, and elliptical arcs are measured
+    /// through the same curves that are used to draw them.  Nothing is needed from cairo,
+    /// so this is cheap to call while laying out shapes.
+    ///
+    /// Like `cairo_path_extents()`, subpaths that consist of a lone `MoveTo` are not
+    /// included; a non-empty path with no segments at all has an empty rectangle at the
+    /// origin.  Returns `None` for an empty path.
+    pub fn extents(&self) -> Option<Rect> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut extents = Extents::default();
+        let mut current = (0.0, 0.0);
+        let mut subpath_start = (0.0, 0.0);
+
+        for cmd in self.iter() {
+            match cmd {
+                PathCommand::MoveTo(x, y) => {
+                    current = (x, y);
+                    subpath_start = current;
+                }
+
+                PathCommand::LineTo(x, y) => {
+                    extents.add(current);
+                    current = (x, y);
+                    extents.add(current);
+                }
+
+                PathCommand::CurveTo(curve) => {
+                    curve.add_to_extents(current, &mut extents);
+                    current = curve.to;
+                }
+
+                PathCommand::Arc(arc) => {
+                    match arc.center_parameterization() {
+                        ArcParameterization::CenterParameters {
+                            center,
+                            radii,
+                            theta1,
+                            delta_theta,
+                        } => {
+                            let n_segs = num_arc_segments(delta_theta, radii, 1.0);
+                            let d_theta = delta_theta / f64::from(n_segs);
+
+                            let mut from = current;
+
+                            for i in 0..n_segs {
+                                let theta = theta1 + f64::from(i) * d_theta;
+                                let curve = arc_segment(
+                                    center,
+                                    radii,
+                                    arc.x_axis_rotation,
+                                    theta,
+                                    theta + d_theta,
+                                );
+                                curve.add_to_extents(from, &mut extents);
+                                from = curve.to;
+                            }
+                        }
+
+                        ArcParameterization::LineTo => {
+                            extents.add(current);
+                            extents.add(arc.to);
+                        }
+
+                        ArcParameterization::Omit => (),
+                    }
+
+                    current = arc.to;
+                }
+
+                PathCommand::ClosePath => {
+                    extents.add(current);
+                    current = subpath_start;
+                    extents.add(current);
+                }
+            }
+        }
+
+        Some(extents.0.unwrap_or_default())
+    }
+
+    /// Converts an elliptical arc from this path to Bézier curves.
+    ///
+    /// The number of curves depends on `scale`, the largest factor by which the current
+    /// transformation scales distances, so that arcs stay smooth when zooming in.  The
+    /// result is cached by the arc's parameters, and gets recomputed only if the scale
+    /// requires a different number of curves.
+    pub fn flatten_arc(&self, arc: &EllipticalArc, scale: f64) -> FlattenedArc {
+        let mut cache = self.flattened_arcs.borrow_mut();
+
+        let cached = cache.entry(ArcKey::from(arc)).or_insert_with(|| CachedArc {
+            parameterization: arc.center_parameterization(),
+            curves: None,
+        });
+
+        match cached.parameterization {
+            ArcParameterization::CenterParameters {
+                center,
+                radii,
+                theta1,
+                delta_theta,
+            } => {
+                let n_segs = num_arc_segments(delta_theta, radii, scale);
+
+                match cached.curves {
+                    Some((n, ref curves)) if n == n_segs => FlattenedArc::Curves(curves.clone()),
+
+                    _ => {
+                        let d_theta = delta_theta / f64::from(n_segs);
+
+                        let curves: Rc<[CubicBezierCurve]> = (0..n_segs)
+                            .map(|i| {
+                                let theta = theta1 + f64::from(i) * d_theta;
+                                arc_segment(
+                                    center,
+                                    radii,
+                                    arc.x_axis_rotation,
+                                    theta,
+                                    theta + d_theta,
+                                )
+                            })
+                            .collect();
+
+                        cached.curves = Some((n_segs, curves.clone()));
+                        FlattenedArc::Curves(curves)
+                    }
+                }
+            }
+
+            ArcParameterization::LineTo => FlattenedArc::LineTo(arc.to),
+
+            ArcParameterization::Omit => FlattenedArc::Omit,
+        }
+    }
+}
+
+/// Simplifies a polyline with the Ramer–Douglas–Peucker algorithm.
+///
+/// Returns a subset of `points` such that no removed point is farther than `tolerance`
+/// from the simplified polyline.  The first and last points are always kept.
+pub fn simplify_polyline(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Use an explicit stack instead of recursion; traced paths can have many thousands
+    // of points.
+    let mut stack = vec![(0, points.len() - 1)];
+
+    while let Some((first, last)) = stack.pop() {
+        let farthest = (first + 1..last)
+            .map(|i| {
+                (
+                    i,
+                    distance_to_segment(points[i], points[first], points[last]),
+                )
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        if let Some((index, distance)) = farthest {
+            if distance > tolerance {
+                keep[index] = true;
+                stack.push((first, index));
+                stack.push((index, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(p, keep)| keep.then_some(*p))
+        .collect()
+}
+
+/// Distance from `p` to the line segment between `a` and `b`.
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_squared = dx * dx + dy * dy;
+
+    let t = if len_squared > 0.0 {
+        clamp(
+            ((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_squared,
+            0.0,
+            1.0,
+        )
+    } else {
+        0.0
+    };
+
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    (p.0 - x).hypot(p.1 - y)
 }
 
 fn take_one(iter: &mut slice::Iter<'_, f64>) -> f64 {
@@ -870,4 +1279,148 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn flattens_arcs_according_to_scale() {
+        let mut builder = PathBuilder::default();
+        builder.parse("M 0 50 A 50 50 0 0 1 100 50").unwrap();
+        let path = builder.into_path();
+
+        let arc = match path.iter().nth(1).unwrap() {
+            PathCommand::Arc(arc) => arc,
+            _ => unreachable!(),
+        };
+
+        let num_curves = |scale| match path.flatten_arc(&arc, scale) {
+            FlattenedArc::Curves(curves) => curves.len(),
+            _ => unreachable!(),
+        };
+
+        // A half circle needs two curves at normal sizes, and more when it gets large
+        assert_eq!(num_curves(1.0), 2);
+        assert!(num_curves(100.0) > 2);
+
+        // The curves get reused while the scale does not change the number of curves
+        let curves = |scale| match path.flatten_arc(&arc, scale) {
+            FlattenedArc::Curves(curves) => curves,
+            _ => unreachable!(),
+        };
+        assert!(Rc::ptr_eq(&curves(1.0), &curves(1.5)));
+
+        // The last curve ends at the arc's end point
+        let last = curves(100.0).last().unwrap().clone();
+        assert!((last.to.0 - 100.0).abs() < 1e-9 && (last.to.1 - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn arc_segments_are_the_fewest_within_tolerance() {
+        let delta_theta = PI * 1.5;
+
+        for radius in [1.0, 400.0, 1e4, 1e6] {
+            let n = num_arc_segments(delta_theta, (radius, radius / 2.0), 1.0);
+            let error = |n: u32| radius * arc_error_normalized(delta_theta / f64::from(n));
+
+            assert!(n >= 3);
+            assert!(error(n) <= ARC_TOLERANCE);
+            assert!(n == 3 || error(n - 1) > ARC_TOLERANCE);
+        }
+
+        assert_eq!(
+            num_arc_segments(delta_theta, (1e20, 1e20), 1.0),
+            MAX_ARC_SEGMENTS
+        );
+    }
+
+    #[test]
+    fn simplifies_polylines() {
+        // Nearly collinear points collapse to the endpoints
+        let points = [(0.0, 0.0), (1.0, 0.01), (2.0, -0.01), (3.0, 0.0)];
+        assert_eq!(
+            simplify_polyline(&points, 0.1),
+            vec![(0.0, 0.0), (3.0, 0.0)]
+        );
+
+        // Corners that deviate more than the tolerance are kept
+        let points = [
+            (0.0, 0.0),
+            (1.0, 0.01),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (2.01, 3.0),
+            (2.0, 4.0),
+        ];
+        assert_eq!(
+            simplify_polyline(&points, 0.1),
+            vec![(0.0, 0.0), (2.0, 0.0), (2.0, 4.0)]
+        );
+
+        // Closed polylines, whose endpoints coincide, keep their shape
+        let square = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)];
+        assert_eq!(simplify_polyline(&square, 0.1), square.to_vec());
+
+        assert_eq!(
+            simplify_polyline(&square, 2.0),
+            vec![(0.0, 0.0), (0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn computes_exact_extents() {
+        assert_eq!(Path::default().extents(), None);
+
+        let mut builder = PathBuilder::default();
+        builder.move_to(10.0, 10.0);
+        let path = builder.into_path();
+        assert_eq!(path.extents(), Some(Rect::default()));
+
+        // The control points are far outside the curve; only its extrema count
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.curve_to(0.0, 100.0, 100.0, 100.0, 100.0, 0.0);
+        let path = builder.into_path();
+        let extents = path.extents().unwrap();
+        assert!(extents.approx_eq(&Rect::new(0.0, 0.0, 100.0, 75.0)));
+
+        // Lone move_to subpaths are not included
+        let mut builder = PathBuilder::default();
+        builder.move_to(-50.0, -50.0);
+        builder.move_to(10.0, 20.0);
+        builder.line_to(30.0, 5.0);
+        builder.move_to(200.0, 200.0);
+        let path = builder.into_path();
+        assert_eq!(path.extents(), Some(Rect::new(10.0, 5.0, 30.0, 20.0)));
+
+        // A circle made of two arcs
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 50.0);
+        builder.arc(
+            0.0,
+            50.0,
+            50.0,
+            50.0,
+            0.0,
+            LargeArc(false),
+            Sweep::Positive,
+            100.0,
+            50.0,
+        );
+        builder.arc(
+            100.0,
+            50.0,
+            50.0,
+            50.0,
+            0.0,
+            LargeArc(false),
+            Sweep::Positive,
+            0.0,
+            50.0,
+        );
+        builder.close_path();
+        let path = builder.into_path();
+        let extents = path.extents().unwrap();
+        assert!((extents.x0 - 0.0).abs() < 0.01);
+        assert!((extents.y0 - 0.0).abs() < 0.01);
+        assert!((extents.x1 - 100.0).abs() < 0.01);
+        assert!((extents.y1 - 100.0).abs() < 0.01);
+    }
 }
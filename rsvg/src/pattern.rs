@@ -17,7 +17,7 @@ use crate::parsers::ParseValue;
 use crate::rect::Rect;
 use crate::rsvg_log;
 use crate::session::Session;
-use crate::transform::{Transform, TransformAttribute};
+use crate::transform::{Transform, TransformList};
 use crate::unit_interval::UnitInterval;
 use crate::viewbox::*;
 use crate::xml::Attributes;
@@ -35,7 +35,7 @@ struct Common {
     // In that case, the fully resolved pattern will have a .vbox=Some(None) value.
     vbox: Option<Option<ViewBox>>,
     preserve_aspect_ratio: Option<AspectRatio>,
-    transform: Option<TransformAttribute>,
+    transform: Option<TransformList>,
     x: Option<Length<Horizontal>>,
     y: Option<Length<Vertical>>,
     width: Option<ULength<Horizontal>>,
@@ -95,13 +95,16 @@ pub struct ResolvedPattern {
     content_units: PatternContentUnits,
     vbox: Option<ViewBox>,
     preserve_aspect_ratio: AspectRatio,
-    transform: TransformAttribute,
+    transform: TransformList,
     x: Length<Horizontal>,
     y: Length<Vertical>,
     width: ULength<Horizontal>,
     height: ULength<Vertical>,
     opacity: UnitInterval,
 
+    // Reference from the paint property that led to this pattern.
+    reference: NodeId,
+
     // Link to the node whose children are the pattern's resolved children.
     children: Children,
 }
@@ -115,6 +118,10 @@ pub struct UserSpacePattern {
     pub content_transform: Transform,
     pub opacity: UnitInterval,
 
+    /// The reference in the `fill` or `stroke` property that names this pattern, like
+    /// `#pattern`, for reporting problems with it.
+    pub reference: NodeId,
+
     // This one is private so the caller has to go through fn acquire_pattern_node()
     node_with_children: Node,
 }
@@ -176,7 +183,7 @@ impl ElementTrait for Pattern {
 }
 
 impl UnresolvedPattern {
-    fn into_resolved(self, opacity: UnitInterval) -> ResolvedPattern {
+    fn into_resolved(self, opacity: UnitInterval, reference: &NodeId) -> ResolvedPattern {
         assert!(self.is_resolved());
 
         ResolvedPattern {
@@ -184,12 +191,13 @@ impl UnresolvedPattern {
             content_units: self.common.content_units.unwrap(),
             vbox: self.common.vbox.unwrap(),
             preserve_aspect_ratio: self.common.preserve_aspect_ratio.unwrap(),
-            transform: self.common.transform.unwrap(),
+            transform: self.common.transform.clone().unwrap(),
             x: self.common.x.unwrap(),
             y: self.common.y.unwrap(),
             width: self.common.width.unwrap(),
             height: self.common.height.unwrap(),
             opacity,
+            reference: reference.clone(),
 
             children: self.children.to_resolved(),
         }
@@ -216,7 +224,11 @@ impl UnresolvedPattern {
             .common
             .preserve_aspect_ratio
             .or(fallback.common.preserve_aspect_ratio);
-        let transform = self.common.transform.or(fallback.common.transform);
+        let transform = self
+            .common
+            .transform
+            .clone()
+            .or_else(|| fallback.common.transform.clone());
         let x = self.common.x.or(fallback.common.x);
         let y = self.common.y.or(fallback.common.y);
         let width = self.common.width.or(fallback.common.width);
@@ -253,7 +265,8 @@ impl UnresolvedPattern {
         let transform = self
             .common
             .transform
-            .or_else(|| Some(TransformAttribute::default()));
+            .clone()
+            .or_else(|| Some(TransformList::default()));
         let x = self.common.x.or_else(|| Some(Default::default()));
         let y = self.common.y.or_else(|| Some(Default::default()));
         let width = self.common.width.or_else(|| Some(Default::default()));
@@ -386,7 +399,10 @@ impl ResolvedPattern {
             ),
         };
 
-        let pattern_transform = self.transform.to_transform();
+        // The patternTransform is always in user space, even for patternUnits="objectBoundingBox".
+        let user_params = NormalizeParams::from_values(values, viewport);
+        let reference_box = object_bbox.unwrap_or(*viewport.vbox);
+        let pattern_transform = self.transform.to_transform(&user_params, &reference_box);
 
         let coord_transform = coord_transform.post_transform(&pattern_transform);
 
@@ -420,6 +436,7 @@ impl ResolvedPattern {
             coord_transform,
             content_transform,
             opacity: self.opacity,
+            reference: self.reference.clone(),
             node_with_children,
         })
     }
@@ -434,7 +451,7 @@ impl UserSpacePattern {
         &self,
         acquired_nodes: &mut AcquiredNodes<'_>,
     ) -> Result<AcquiredNode, AcquireError> {
-        acquired_nodes.acquire_ref(&self.node_with_children)
+        acquired_nodes.acquire_in_chain(&self.node_with_children, ReferenceChain::Pattern)
     }
 }
 
@@ -451,9 +468,12 @@ impl Pattern {
         }
     }
 
+    /// Resolves the pattern at `node`, which was referenced from a paint property as
+    /// `reference`, by following its chain of fallbacks.
     pub fn resolve(
         &self,
         node: &Node,
+        reference: &NodeId,
         acquired_nodes: &mut AcquiredNodes<'_>,
         opacity: UnitInterval,
         session: &Session,
@@ -475,6 +495,16 @@ impl Pattern {
                             return Err(AcquireError::CircularReference(acquired_node.clone()));
                         }
 
+                        if stack.len() >= acquired_nodes.max_reference_chain() {
+                            let mut nodes = stack.nodes();
+                            nodes.insert(0, node.clone());
+                            nodes.push(acquired_node.clone());
+                            return Err(AcquireError::ReferenceChainTooLong(
+                                ReferenceChain::Pattern,
+                                nodes,
+                            ));
+                        }
+
                         match *acquired_node.borrow_element_data() {
                             ElementData::Pattern(ref p) => {
                                 let unresolved = p.get_unresolved(acquired_node);
@@ -487,9 +517,7 @@ impl Pattern {
                         }
                     }
 
-                    Err(AcquireError::MaxReferencesExceeded) => {
-                        return Err(AcquireError::MaxReferencesExceeded)
-                    }
+                    Err(e @ AcquireError::MaxReferencesExceeded(_)) => return Err(e),
 
                     Err(e) => {
                         rsvg_log!(session, "Stopping pattern resolution: {}", e);
@@ -503,7 +531,7 @@ impl Pattern {
             }
         }
 
-        Ok(pattern.into_resolved(opacity))
+        Ok(pattern.into_resolved(opacity, reference))
     }
 }
 
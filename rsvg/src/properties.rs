@@ -14,22 +14,20 @@
 //! * Modify the rest of librsvg wherever the computed value of the property needs to be used.
 //! This is available in methods that take an argument of type [`ComputedValues`].
 
-use cssparser::{
-    self, BasicParseErrorKind, ParseErrorKind, Parser, ParserInput, RuleBodyParser, ToCss,
-};
+use cssparser::{self, BasicParseErrorKind, ParseErrorKind, Parser, ParserInput, ToCss};
 use markup5ever::{
     expanded_name, local_name, namespace_url, ns, ExpandedName, LocalName, QualName,
 };
-use std::collections::HashSet;
+use url::Url;
 
 #[cfg(doc)]
 use crate::make_property;
 
-use crate::css::{DeclParser, Declaration, Origin, RuleBodyItem};
+use crate::css::{Declaration, Origin};
 use crate::error::*;
 use crate::parsers::{Parse, ParseValue};
 use crate::property_macros::Property;
-use crate::rsvg_log;
+use crate::rsvg_warn;
 use crate::session::Session;
 use crate::transform::{Transform, TransformAttribute, TransformProperty};
 use crate::xml::Attributes;
@@ -141,8 +139,11 @@ impl ComputedValues {
     /// Whether we should draw the element or skip both space allocation
     /// and drawing.
     /// <https://www.w3.org/TR/SVG2/render.html#VisibilityControl>
+    ///
+    /// This is `false` for `display: contents` as well, since the element itself is not
+    /// drawn; see [`crate::element::Element::can_be_unboxed`] for its contents.
     pub fn is_displayed(&self) -> bool {
-        self.display() != Display::None
+        !matches!(self.display(), Display::None | Display::Contents)
     }
 
     /// Whether we should draw the element or allocate its space but
@@ -252,7 +253,7 @@ macro_rules! make_properties {
         }
 
         /// Holds the computed values for the CSS properties of an element.
-        #[derive(Debug, Default, Clone)]
+        #[derive(Debug, Default, Clone, PartialEq)]
         pub struct ComputedValues {
             $(
                 $long_field: $long_name,
@@ -410,7 +411,7 @@ make_properties! {
         "clip-path"                   => (PresentationAttr::Yes, clip_path                   : ClipPath),
         "clip-rule"                   => (PresentationAttr::Yes, clip_rule                   : ClipRule),
         "color"                       => (PresentationAttr::Yes, color                       : Color),
-        // "color-interpolation"      => (PresentationAttr::Yes, unimplemented),
+        "color-interpolation"         => (PresentationAttr::Yes, color_interpolation         : ColorInterpolation),
         "color-interpolation-filters" => (PresentationAttr::Yes, color_interpolation_filters : ColorInterpolationFilters),
         // "cursor"                   => (PresentationAttr::Yes, unimplemented),
         "cx"                          => (PresentationAttr::Yes, cx: CX),
@@ -516,6 +517,36 @@ make_properties! {
     }
 }
 
+impl ParsedProperty {
+    /// Resolves relative references in `url()` values against `base_url`.
+    ///
+    /// Used for stylesheets loaded from a URL, where relative references point to
+    /// resources next to the stylesheet instead of next to the SVG document.
+    pub fn make_urls_absolute(&mut self, base_url: &Url) {
+        use SpecifiedValue::Specified;
+
+        match *self {
+            ParsedProperty::ClipPath(Specified(ClipPath(ref mut iri)))
+            | ParsedProperty::Marker(Specified(Marker(ref mut iri)))
+            | ParsedProperty::MarkerStart(Specified(MarkerStart(ref mut iri)))
+            | ParsedProperty::MarkerMid(Specified(MarkerMid(ref mut iri)))
+            | ParsedProperty::MarkerEnd(Specified(MarkerEnd(ref mut iri)))
+            | ParsedProperty::Mask(Specified(Mask(ref mut iri))) => iri.make_absolute(base_url),
+
+            ParsedProperty::Fill(Specified(Fill(ref mut paint)))
+            | ParsedProperty::Stroke(Specified(Stroke(ref mut paint))) => {
+                paint.make_absolute(base_url)
+            }
+
+            ParsedProperty::Filter(Specified(Filter::List(ref mut list))) => {
+                list.make_absolute(base_url)
+            }
+
+            _ => (),
+        }
+    }
+}
+
 impl SpecifiedValues {
     fn property_index(&self, id: PropertyId) -> Option<usize> {
         let v = self.indices[id.as_usize()];
@@ -703,6 +734,7 @@ impl SpecifiedValues {
         compute!(ClipPath, clip_path);
         compute!(ClipRule, clip_rule);
         compute!(Color, color);
+        compute!(ColorInterpolation, color_interpolation);
         compute!(ColorInterpolationFilters, color_interpolation_filters);
         compute!(CX, cx);
         compute!(CY, cy);
@@ -821,10 +853,11 @@ impl SpecifiedValues {
                 if parser.expect_exhausted().is_ok() {
                     self.set_parsed_property(&prop);
                 } else {
-                    rsvg_log!(
+                    rsvg_warn!(
                         session,
-                        "(ignoring invalid presentation attribute {:?}\n    value=\"{}\")\n",
-                        attr.expanded(),
+                        strict,
+                        "ignoring invalid presentation attribute {}=\"{}\"",
+                        attr.local,
                         value,
                     );
                 }
@@ -846,11 +879,11 @@ impl SpecifiedValues {
                 let mut tok = String::new();
 
                 t.to_css(&mut tok).unwrap(); // FIXME: what do we do with a fmt::Error?
-                rsvg_log!(
+                rsvg_warn!(
                     session,
-                    "(ignoring invalid presentation attribute {:?}\n    value=\"{}\"\n    \
-                     unexpected token '{}')",
-                    attr.expanded(),
+                    strict,
+                    "ignoring invalid presentation attribute {}=\"{}\": unexpected token '{}'",
+                    attr.local,
                     value,
                     tok,
                 );
@@ -860,11 +893,11 @@ impl SpecifiedValues {
                 kind: ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput),
                 ..
             }) => {
-                rsvg_log!(
+                rsvg_warn!(
                     session,
-                    "(ignoring invalid presentation attribute {:?}\n    value=\"{}\"\n    \
-                     unexpected end of input)",
-                    attr.expanded(),
+                    strict,
+                    "ignoring invalid presentation attribute {}=\"{}\": unexpected end of input",
+                    attr.local,
                     value,
                 );
             }
@@ -873,11 +906,11 @@ impl SpecifiedValues {
                 kind: ParseErrorKind::Basic(_),
                 ..
             }) => {
-                rsvg_log!(
+                rsvg_warn!(
                     session,
-                    "(ignoring invalid presentation attribute {:?}\n    value=\"{}\"\n    \
-                     unexpected error)",
-                    attr.expanded(),
+                    strict,
+                    "ignoring invalid presentation attribute {}=\"{}\": unexpected error",
+                    attr.local,
                     value,
                 );
             }
@@ -886,10 +919,11 @@ impl SpecifiedValues {
                 kind: ParseErrorKind::Custom(ref v),
                 ..
             }) => {
-                rsvg_log!(
+                rsvg_warn!(
                     session,
-                    "(ignoring invalid presentation attribute {:?}\n    value=\"{}\"\n    {})",
-                    attr.expanded(),
+                    strict,
+                    "ignoring invalid presentation attribute {}=\"{}\": {}",
+                    attr.local,
                     value,
                     v
                 );
@@ -904,8 +938,11 @@ impl SpecifiedValues {
                     // FIXME: we parse the transform attribute here because we don't yet have
                     // a better way to distinguish attributes whose values have different
                     // grammars than properties.
-                    let transform_attr = TransformAttribute::parse_str(value)
-                        .unwrap_or_else(|_| TransformAttribute::default());
+                    let transform_attr =
+                        TransformAttribute::parse_str(value).unwrap_or_else(|_| {
+                            rsvg_warn!(session, strict, "ignoring invalid transform \"{}\"", value);
+                            TransformAttribute::default()
+                        });
                     self.transform = Some(transform_attr.to_transform());
                 }
 
@@ -922,7 +959,12 @@ impl SpecifiedValues {
                         }
 
                         Err(e) => {
-                            rsvg_log!(session, "ignoring attribute with invalid value: {}", e);
+                            rsvg_warn!(
+                                session,
+                                strict,
+                                "ignoring attribute with invalid value: {}",
+                                e
+                            );
                         }
                     }
                 }
@@ -940,7 +982,12 @@ impl SpecifiedValues {
                         }
 
                         Err(e) => {
-                            rsvg_log!(session, "ignoring attribute with invalid value: {}", e);
+                            rsvg_warn!(
+                                session,
+                                strict,
+                                "ignoring attribute with invalid value: {}",
+                                e
+                            );
                         }
                     }
                 }
@@ -950,48 +997,16 @@ impl SpecifiedValues {
         }
     }
 
-    pub fn set_property_from_declaration(
-        &mut self,
-        declaration: &Declaration,
-        origin: Origin,
-        important_styles: &mut HashSet<QualName>,
-    ) {
-        if !declaration.important && important_styles.contains(&declaration.prop_name) {
-            return;
-        }
-
-        if declaration.important {
-            important_styles.insert(declaration.prop_name.clone());
-        }
-
-        if origin == Origin::UserAgent {
+    pub fn set_property_from_declaration(&mut self, declaration: &Declaration, origin: Origin) {
+        // Normal declarations from the user agent stylesheet have less priority than
+        // presentation attributes; everything else has already been sorted in cascade
+        // order by the caller, so it just replaces what was there before.
+        if origin == Origin::UserAgent && !declaration.important {
             self.set_parsed_property_user_agent(&declaration.property);
         } else {
             self.set_parsed_property(&declaration.property);
         }
     }
-
-    pub fn parse_style_declarations(
-        &mut self,
-        declarations: &str,
-        origin: Origin,
-        important_styles: &mut HashSet<QualName>,
-        session: &Session,
-    ) {
-        let mut input = ParserInput::new(declarations);
-        let mut parser = Parser::new(&mut input);
-
-        RuleBodyParser::new(&mut parser, &mut DeclParser)
-            .filter_map(|r| match r {
-                Ok(RuleBodyItem::Decl(decl)) => Some(decl),
-                Ok(RuleBodyItem::Rule(_)) => None,
-                Err(e) => {
-                    rsvg_log!(session, "Invalid declaration; ignoring: {:?}", e);
-                    None
-                }
-            })
-            .for_each(|decl| self.set_property_from_declaration(&decl, origin, important_styles));
-    }
 }
 
 // Parses the value for the type `T` of the property out of the Parser, including `inherit` values.
@@ -167,6 +167,30 @@ make_property!(
     newtype_parse: cssparser::Color,
 );
 
+make_property!(
+    /// `color-interpolation` property.
+    ///
+    /// SVG1.1: <https://www.w3.org/TR/SVG11/painting.html#ColorInterpolationProperty>
+    ///
+    /// SVG2: <https://www.w3.org/TR/SVG2/painting.html#ColorInterpolation>
+    ///
+    /// Librsvg only uses this for gradients, and for the luminance of `<mask>` contents,
+    /// and only when rendering with [`crate::drawing_ctx::RenderingQuality::High`].
+    /// Besides the standard values, it accepts `oklab` and `oklch` as an extension, to
+    /// interpolate gradients in those perceptual color spaces; masks treat these like
+    /// `sRGB`.
+    ColorInterpolation,
+    default: Srgb,
+    inherits_automatically: true,
+
+    identifiers:
+    "auto" => Auto,
+    "sRGB" => Srgb,
+    "linearRGB" => LinearRgb,
+    "oklab" => Oklab,
+    "oklch" => Oklch,
+);
+
 make_property!(
     /// `color-interpolation-filters` property.
     ///
@@ -249,6 +273,7 @@ make_property!(
     "table-column" => TableColumn,
     "table-cell" => TableCell,
     "table-caption" => TableCaption,
+    "contents" => Contents,
     "none" => None,
 );
 
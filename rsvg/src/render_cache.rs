@@ -0,0 +1,93 @@
+//! Resolution results that can be shared among several renders of a document.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::document::NodeId;
+use crate::dpi::Dpi;
+use crate::drawing_ctx::Viewport;
+use crate::node::Node;
+use crate::paint_server::PaintSource;
+use crate::rect::Rect;
+use crate::text::PositionedChunk;
+use crate::unit_interval::UnitInterval;
+
+/// Results of resolving parts of a document, kept around for later renders.
+///
+/// The CSS cascade is done once when a document is loaded, but each render normally
+/// resolves the paint servers referenced by elements and lays out text again.  Applications
+/// that render the same document many times, for example at several sizes, can keep a
+/// `RenderCache` and pass it to each render so this work is only done once.
+///
+/// Nothing here depends on the size of the output.  Paint servers are cached before their
+/// lengths are normalized to user space, and text layouts are cached along with the
+/// viewport and DPI they were made for.
+#[derive(Default)]
+pub struct RenderCache {
+    paint_sources: RefCell<Vec<CachedPaintSource>>,
+    text_layouts: RefCell<Vec<CachedTextLayout>>,
+}
+
+struct CachedPaintSource {
+    node_id: NodeId,
+    opacity: UnitInterval,
+    paint: Rc<PaintSource>,
+}
+
+struct CachedTextLayout {
+    node: Node,
+    vbox: Rect,
+    dpi: Dpi,
+    chunks: Rc<Vec<PositionedChunk>>,
+}
+
+impl RenderCache {
+    /// Looks up a paint server that was resolved with the specified opacity.
+    pub fn paint_source(&self, node_id: &NodeId, opacity: UnitInterval) -> Option<Rc<PaintSource>> {
+        self.paint_sources
+            .borrow()
+            .iter()
+            .find(|p| p.node_id == *node_id && p.opacity == opacity)
+            .map(|p| p.paint.clone())
+    }
+
+    pub fn store_paint_source(
+        &self,
+        node_id: &NodeId,
+        opacity: UnitInterval,
+        paint: Rc<PaintSource>,
+    ) {
+        self.paint_sources.borrow_mut().push(CachedPaintSource {
+            node_id: node_id.clone(),
+            opacity,
+            paint,
+        });
+    }
+
+    /// Looks up the laid-out chunks of a `<text>` element for a viewport.
+    pub fn text_layout(
+        &self,
+        node: &Node,
+        viewport: &Viewport,
+    ) -> Option<Rc<Vec<PositionedChunk>>> {
+        self.text_layouts
+            .borrow()
+            .iter()
+            .find(|t| t.node == *node && t.vbox == *viewport.vbox && t.dpi == viewport.dpi)
+            .map(|t| t.chunks.clone())
+    }
+
+    pub fn store_text_layout(
+        &self,
+        node: &Node,
+        viewport: &Viewport,
+        chunks: Rc<Vec<PositionedChunk>>,
+    ) {
+        self.text_layouts.borrow_mut().push(CachedTextLayout {
+            node: node.clone(),
+            vbox: *viewport.vbox,
+            dpi: viewport.dpi,
+            chunks,
+        });
+    }
+}
@@ -1,6 +1,14 @@
 //! Tracks metadata for a loading/rendering session.
 
-use std::sync::Arc;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::log::{BrokenPaintReference, DebugConfig, LoadWarning, Location, LogLevel, LogSink};
+#[cfg(feature = "render-observers")]
+use crate::observer::RenderObserver;
+use crate::rsvg_log;
 
 /// Metadata for a loading/rendering session.
 ///
@@ -10,35 +18,293 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct Session {
     inner: Arc<SessionInner>,
+
+    /// Called around the rendering of each element.
+    #[cfg(feature = "render-observers")]
+    render_observer: Option<Arc<dyn RenderObserver>>,
 }
 
 struct SessionInner {
-    log_enabled: bool,
+    debug: DebugConfig,
+
+    /// Open file from [`DebugConfig::with_trace_file`].
+    trace_file: Option<Arc<Mutex<File>>>,
+
+    /// Where to send log messages; if this is `None`, they go to the trace file or to
+    /// stdout, as set in `debug`.
+    log_sink: Option<Arc<dyn LogSink>>,
+
+    /// Warnings about the document being loaded; see [`Session::for_loading`].
+    warnings: Option<Mutex<WarningCollector>>,
+
+    /// Paint servers that could not be used while rendering the document being loaded;
+    /// see [`Session::add_broken_paint_reference`].
+    ///
+    /// Unlike `warnings`, this keeps getting filled after loading, and it is shared with
+    /// the sessions derived from this one for rendering.
+    broken_paint_references: Option<Arc<Mutex<Vec<BrokenPaintReference>>>>,
 }
 
-fn log_enabled_via_env_var() -> bool {
-    ::std::env::var_os("RSVG_LOG").is_some()
+#[derive(Default)]
+struct WarningCollector {
+    /// Position of the XML parser, used as the location of new warnings.
+    location: Option<Location>,
+
+    warnings: Vec<LoadWarning>,
+
+    /// First warning that is an error in strict mode; see [`Session::take_strict_error`].
+    strict_error: Option<LoadWarning>,
+
+    /// Set by [`Session::take_warnings`]; warnings after that point are not collected.
+    finished: bool,
 }
 
 impl Default for Session {
     fn default() -> Self {
-        Self {
-            inner: Arc::new(SessionInner {
-                log_enabled: log_enabled_via_env_var(),
-            }),
-        }
+        Self::new(DebugConfig::from_env(), None)
     }
 }
 
 impl Session {
     #[cfg(test)]
     pub fn new_for_test_suite() -> Self {
+        Self::new(DebugConfig::new(), None)
+    }
+
+    /// Creates a session that logs according to `debug`, or to `log_sink` if there is one.
+    ///
+    /// With a sink, the sink decides which levels it wants and the level in `debug` is
+    /// not used; its categories still apply.
+    ///
+    /// If the trace file from `debug` cannot be created, that gets logged like any other
+    /// message, to the sink or to stdout.
+    pub fn new(debug: DebugConfig, log_sink: Option<Arc<dyn LogSink>>) -> Self {
+        let mut trace_error = None;
+
+        let trace_file = debug
+            .trace_file()
+            .and_then(|path| match File::create(path) {
+                Ok(file) => Some(Arc::new(Mutex::new(file))),
+
+                Err(e) => {
+                    trace_error = Some(format!(
+                        "could not create trace file {}: {e}",
+                        path.display()
+                    ));
+                    None
+                }
+            });
+
+        let session = Self {
+            inner: Arc::new(SessionInner {
+                debug,
+                trace_file,
+                log_sink,
+                warnings: None,
+                broken_paint_references: None,
+            }),
+
+            #[cfg(feature = "render-observers")]
+            render_observer: None,
+        };
+
+        if let Some(message) = trace_error {
+            rsvg_log!(session, "{message}");
+        }
+
+        session
+    }
+
+    /// Creates a session that sends log messages to `sink` instead of stdout.
+    ///
+    /// The `RSVG_LOG` environment variable is not used in this case; the sink decides
+    /// which messages it wants.
+    pub fn new_with_log_sink(sink: Arc<dyn LogSink>) -> Self {
+        Self::new(DebugConfig::new(), Some(sink))
+    }
+
+    /// Creates a session like this one, but with a different debug configuration.
+    pub fn with_debug_config(&self, debug: DebugConfig) -> Self {
+        let mut session = self.clone();
+        session.inner = Arc::new(SessionInner {
+            broken_paint_references: self.inner.broken_paint_references.clone(),
+            ..Arc::into_inner(Self::new(debug, self.inner.log_sink.clone()).inner).unwrap()
+        });
+        session
+    }
+
+    /// Creates a session like this one, but which sends log messages to `sink`.
+    pub fn with_log_sink(&self, sink: Arc<dyn LogSink>) -> Self {
+        let mut session = self.clone();
+        session.inner = Arc::new(SessionInner {
+            debug: self.inner.debug.clone(),
+            trace_file: self.inner.trace_file.clone(),
+            log_sink: Some(sink),
+            warnings: None,
+            broken_paint_references: self.inner.broken_paint_references.clone(),
+        });
+        session
+    }
+
+    pub fn debug_config(&self) -> &DebugConfig {
+        &self.inner.debug
+    }
+
+    /// Creates a session for loading a single document, which collects warnings about it.
+    ///
+    /// The new session logs messages in the same way as `self`.  Since a `Session` may be
+    /// shared among several documents, each load gets its own one so that their warnings
+    /// don't get mixed up.
+    pub fn for_loading(&self) -> Self {
+        let mut session = self.clone();
+        session.inner = Arc::new(SessionInner {
+            debug: self.inner.debug.clone(),
+            trace_file: self.inner.trace_file.clone(),
+            log_sink: self.inner.log_sink.clone(),
+            warnings: Some(Mutex::new(WarningCollector::default())),
+            broken_paint_references: Some(Arc::new(Mutex::new(Vec::new()))),
+        });
+        session
+    }
+
+    /// Creates a session like this one, but which calls `observer` around the rendering
+    /// of each element.
+    #[cfg(feature = "render-observers")]
+    pub fn with_render_observer(&self, observer: Arc<dyn RenderObserver>) -> Self {
         Self {
-            inner: Arc::new(SessionInner { log_enabled: false }),
+            render_observer: Some(observer),
+            ..self.clone()
         }
     }
 
-    pub fn log_enabled(&self) -> bool {
-        self.inner.log_enabled
+    #[cfg(feature = "render-observers")]
+    pub fn render_observer(&self) -> Option<&Arc<dyn RenderObserver>> {
+        self.render_observer.as_ref()
+    }
+
+    /// Sets the position of the XML parser, to be used for subsequent warnings.
+    pub fn set_location(&self, location: Option<Location>) {
+        if let Some(ref warnings) = self.inner.warnings {
+            warnings.lock().unwrap().location = location;
+        }
+    }
+
+    /// Returns the position of the XML parser, if a document is being parsed.
+    pub fn location(&self) -> Option<Location> {
+        self.inner
+            .warnings
+            .as_ref()
+            .and_then(|w| w.lock().unwrap().location)
+    }
+
+    /// Records a warning at the current position of the XML parser.
+    ///
+    /// Normally you want to use the `rsvg_warn!` macro instead, which also logs the message.
+    pub fn add_warning(&self, message: String) {
+        self.add_warning_at(self.location(), message);
+    }
+
+    /// Records a warning at a specific position.
+    pub fn add_warning_at(&self, location: Option<Location>, message: String) {
+        if let Some(ref warnings) = self.inner.warnings {
+            let mut collector = warnings.lock().unwrap();
+
+            if !collector.finished {
+                collector.warnings.push(LoadWarning { location, message });
+            }
+        }
+    }
+
+    /// Records a warning which makes loading fail in strict mode.
+    ///
+    /// Normally you want to use `rsvg_warn!(session, strict, ...)` instead.
+    pub fn add_strict_warning(&self, message: String) {
+        if let Some(ref warnings) = self.inner.warnings {
+            let mut collector = warnings.lock().unwrap();
+
+            if !collector.finished {
+                let warning = LoadWarning {
+                    location: collector.location,
+                    message,
+                };
+
+                if collector.strict_error.is_none() {
+                    collector.strict_error = Some(warning.clone());
+                }
+
+                collector.warnings.push(warning);
+            }
+        }
+    }
+
+    /// Returns the first warning recorded with [`Session::add_strict_warning`], if any.
+    ///
+    /// The loader checks this after creating each element when loading in strict mode.
+    pub fn take_strict_error(&self) -> Option<LoadWarning> {
+        self.inner
+            .warnings
+            .as_ref()
+            .and_then(|w| w.lock().unwrap().strict_error.take())
+    }
+
+    /// Returns the warnings collected so far, and stops collecting new ones.
+    ///
+    /// This gets called when a document is done loading; problems found while rendering
+    /// are only logged.
+    pub fn take_warnings(&self) -> Vec<LoadWarning> {
+        match self.inner.warnings {
+            Some(ref warnings) => {
+                let mut collector = warnings.lock().unwrap();
+                collector.finished = true;
+                collector.location = None;
+                std::mem::take(&mut collector.warnings)
+            }
+
+            None => Vec::new(),
+        }
+    }
+
+    /// Records a paint server that could not be used while rendering.
+    ///
+    /// The same reference may get resolved many times while rendering, so each distinct
+    /// one is only recorded once.
+    pub fn add_broken_paint_reference(&self, broken: BrokenPaintReference) {
+        if let Some(ref references) = self.inner.broken_paint_references {
+            let mut references = references.lock().unwrap();
+
+            if !references.contains(&broken) {
+                references.push(broken);
+            }
+        }
+    }
+
+    /// Returns the paint servers recorded with [`Session::add_broken_paint_reference`].
+    pub fn broken_paint_references(&self) -> Vec<BrokenPaintReference> {
+        self.inner
+            .broken_paint_references
+            .as_ref()
+            .map(|r| r.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Whether to log a message at `level` from the module given by `module_path!()`.
+    pub fn log_enabled(&self, level: LogLevel, module_path: &str) -> bool {
+        match self.inner.log_sink {
+            Some(ref sink) => sink.enabled(level) && self.inner.debug.in_categories(module_path),
+            None => self.inner.debug.enabled(level, module_path),
+        }
+    }
+
+    pub fn log(&self, level: LogLevel, args: fmt::Arguments<'_>) {
+        match (&self.inner.log_sink, &self.inner.trace_file) {
+            (Some(sink), _) => sink.log(level, &args.to_string()),
+
+            (None, Some(file)) => {
+                // Logging is best-effort; there is nowhere to report a failed write.
+                let _ = writeln!(file.lock().unwrap(), "{args}");
+            }
+
+            (None, None) => println!("{args}"),
+        }
     }
 }
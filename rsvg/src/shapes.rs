@@ -9,7 +9,7 @@ use std::rc::Rc;
 use crate::bbox::BoundingBox;
 use crate::document::AcquiredNodes;
 use crate::drawing_ctx::{DrawingCtx, Viewport};
-use crate::element::{set_attribute, ElementTrait};
+use crate::element::{set_attribute, ElementData, ElementTrait};
 use crate::error::*;
 use crate::iri::Iri;
 use crate::is_element_of_type;
@@ -19,9 +19,9 @@ use crate::node::{CascadedValues, Node, NodeBorrow};
 use crate::parsers::{optional_comma, Parse, ParseValue};
 use crate::path_builder::{LargeArc, Path as SvgPath, PathBuilder, Sweep};
 use crate::properties::ComputedValues;
-use crate::rsvg_log;
 use crate::session::Session;
 use crate::xml::Attributes;
+use crate::{rsvg_log, rsvg_warn};
 
 #[derive(PartialEq)]
 enum Markers {
@@ -53,6 +53,59 @@ fn draw_basic_shape(
     draw_ctx: &mut DrawingCtx,
     clipping: bool,
 ) -> Result<BoundingBox, InternalRenderingError> {
+    let layer = layout_basic_shape(
+        basic_shape,
+        node,
+        acquired_nodes,
+        cascaded,
+        viewport,
+        draw_ctx,
+    )?;
+
+    draw_ctx.draw_layer(&layer, acquired_nodes, clipping, viewport)
+}
+
+/// Lays out a basic shape element into a layer, without drawing it.
+///
+/// Returns `None` if `node` is not one of the basic shapes.
+pub fn layout_shape(
+    node: &Node,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+    cascaded: &CascadedValues<'_>,
+    viewport: &Viewport,
+    draw_ctx: &DrawingCtx,
+) -> Option<Result<Layer, InternalRenderingError>> {
+    let element_data = node.borrow_element_data();
+
+    let basic_shape: &dyn BasicShape = match *element_data {
+        ElementData::Circle(ref e) => &**e,
+        ElementData::Ellipse(ref e) => &**e,
+        ElementData::Line(ref e) => &**e,
+        ElementData::Path(ref e) => &**e,
+        ElementData::Polygon(ref e) => &**e,
+        ElementData::Polyline(ref e) => &**e,
+        ElementData::Rect(ref e) => &**e,
+        _ => return None,
+    };
+
+    Some(layout_basic_shape(
+        basic_shape,
+        node,
+        acquired_nodes,
+        cascaded,
+        viewport,
+        draw_ctx,
+    ))
+}
+
+fn layout_basic_shape(
+    basic_shape: &dyn BasicShape,
+    node: &Node,
+    acquired_nodes: &mut AcquiredNodes<'_>,
+    cascaded: &CascadedValues<'_>,
+    viewport: &Viewport,
+    draw_ctx: &DrawingCtx,
+) -> Result<Layer, InternalRenderingError> {
     let values = cascaded.get();
     let params = NormalizeParams::new(values, viewport);
     let shape_def = basic_shape.make_shape(&params, values);
@@ -118,7 +171,7 @@ fn draw_basic_shape(
         context_fill: fill_paint.clone(),
     };
 
-    let extents = draw_ctx.compute_path_extents(&shape_def.path)?;
+    let extents = shape_def.path.extents();
 
     let normalize_values = NormalizeValues::new(values);
 
@@ -151,12 +204,10 @@ fn draw_basic_shape(
         values,
     );
 
-    let layer = Layer {
+    Ok(Layer {
         kind: LayerKind::Shape(shape),
         stacking_ctx,
-    };
-
-    draw_ctx.draw_layer(&layer, acquired_nodes, clipping, viewport)
+    })
 }
 
 macro_rules! impl_draw {
@@ -278,7 +329,7 @@ impl ElementTrait for Path {
                     // Creating a partial path is OK per the spec; we don't throw away the partial
                     // result in case of an error.
 
-                    rsvg_log!(session, "could not parse path: {}", e);
+                    rsvg_warn!(session, strict, "could not parse path: {}", e);
                 }
                 self.path = Rc::new(builder.into_path());
             }
@@ -0,0 +1,762 @@
+//! Extents of stroked paths, computed from the path's geometry.
+//!
+//! Cairo can compute the extents of a stroke, but only by running its stroker over the
+//! path on a cairo context.  This module computes the extents directly: each segment
+//! contributes the ends of its offset lines plus the points where it is vertical or
+//! horizontal, and then joins, caps, and dashes are added according to the stroke's
+//! parameters.
+//!
+//! For a segment with direction `d` at a point `p`, the stroke extends to `p ± w/2 * n`,
+//! where `n` is the unit normal to `d`.  The offset curves of a Bézier segment reach
+//! their extremes in x exactly where the segment itself is vertical, and in y where it
+//! is horizontal, so the extents computed here are exact and not an approximation of
+//! the control polygon.
+
+use crate::layout::Stroke;
+use crate::path_builder::{
+    arc_segment, num_arc_segments, ArcParameterization, CubicBezierCurve, Extents, Path,
+    PathCommand,
+};
+use crate::properties::{StrokeLinecap, StrokeLinejoin};
+use crate::rect::Rect;
+
+type Point = (f64, f64);
+
+/// Segments shorter than this have no direction, and they are ignored.
+const DEGENERATE_LENGTH: f64 = 1e-9;
+
+/// Number of chords used to measure the length of curves, for dashing.
+const CURVE_LENGTH_CHORDS: usize = 32;
+
+/// Directions along the axes, which are the candidates for the extremes of round joins
+/// and caps.
+const AXIS_DIRECTIONS: [Point; 4] = [(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)];
+
+/// Computes the extents of `path` when stroked with `stroke`, in the path's coordinates.
+///
+/// Returns `None` if the stroke would not paint anything.
+pub fn stroke_extents(path: &Path, stroke: &Stroke) -> Option<Rect> {
+    let mut stroker = ExtentsStroker::new(stroke);
+
+    for subpath in subpaths(path) {
+        stroker.add_subpath(&subpath);
+    }
+
+    stroker.extents.rect()
+}
+
+/// A straight or curved piece of a subpath.
+#[derive(Clone)]
+enum Piece {
+    Line(Point, Point),
+    Curve(Point, CubicBezierCurve),
+}
+
+struct Subpath {
+    origin: Point,
+    pieces: Vec<Piece>,
+    closed: bool,
+}
+
+/// Splits a path into subpaths of lines and curves; arcs are turned into curves.
+fn subpaths(path: &Path) -> Vec<Subpath> {
+    let mut subpaths = Vec::new();
+
+    for subpath in path.iter_subpath() {
+        let origin = subpath.origin();
+        let num_before = subpaths.len();
+        let mut current = Subpath::new(origin);
+        let mut point = origin;
+
+        for cmd in subpath.iter_commands().skip(1) {
+            add_command(&mut subpaths, &mut current, &mut point, cmd);
+        }
+
+        // Keep lone MoveTo commands, since they may get a dot with square caps, but not the
+        // empty subpath that follows a ClosePath.
+        if !current.pieces.is_empty() || subpaths.len() == num_before {
+            subpaths.push(current);
+        }
+    }
+
+    subpaths
+}
+
+/// Adds the pieces for a path command to the `current` subpath.
+fn add_command(
+    subpaths: &mut Vec<Subpath>,
+    current: &mut Subpath,
+    point: &mut Point,
+    cmd: PathCommand,
+) {
+    match cmd {
+        PathCommand::MoveTo(..) => unreachable!("subpaths only start with a MoveTo"),
+
+        PathCommand::LineTo(x, y) => {
+            current.pieces.push(Piece::Line(*point, (x, y)));
+            *point = (x, y);
+        }
+
+        PathCommand::CurveTo(curve) => {
+            let to = curve.to;
+            current.pieces.push(Piece::Curve(*point, curve));
+            *point = to;
+        }
+
+        PathCommand::Arc(arc) => {
+            match arc.center_parameterization() {
+                ArcParameterization::CenterParameters {
+                    center,
+                    radii,
+                    theta1,
+                    delta_theta,
+                } => {
+                    let n_segs = num_arc_segments(delta_theta, radii, 1.0);
+                    let d_theta = delta_theta / f64::from(n_segs);
+                    let mut from = *point;
+
+                    for i in 0..n_segs {
+                        let theta = theta1 + f64::from(i) * d_theta;
+                        let curve =
+                            arc_segment(center, radii, arc.x_axis_rotation, theta, theta + d_theta);
+                        let to = curve.to;
+                        current.pieces.push(Piece::Curve(from, curve));
+                        from = to;
+                    }
+                }
+
+                ArcParameterization::LineTo => {
+                    current.pieces.push(Piece::Line(*point, arc.to));
+                }
+
+                ArcParameterization::Omit => (),
+            }
+
+            *point = arc.to;
+        }
+
+        PathCommand::ClosePath => {
+            let origin = current.origin;
+            current.pieces.push(Piece::Line(*point, origin));
+            current.closed = true;
+
+            // Commands after a ClosePath start a new subpath at the same origin.
+            subpaths.push(std::mem::replace(current, Subpath::new(origin)));
+            *point = origin;
+        }
+    }
+}
+
+impl Subpath {
+    fn new(origin: Point) -> Subpath {
+        Subpath {
+            origin,
+            pieces: Vec::new(),
+            closed: false,
+        }
+    }
+
+    fn is_zero_length(&self) -> bool {
+        self.pieces.iter().all(Piece::is_degenerate)
+    }
+}
+
+impl Piece {
+    fn start(&self) -> Point {
+        match *self {
+            Piece::Line(a, _) => a,
+            Piece::Curve(from, _) => from,
+        }
+    }
+
+    fn end(&self) -> Point {
+        match *self {
+            Piece::Line(_, b) => b,
+            Piece::Curve(_, ref curve) => curve.to,
+        }
+    }
+
+    fn is_degenerate(&self) -> bool {
+        self.start_direction().is_none()
+    }
+
+    /// Unit vector in the direction in which the piece starts.
+    fn start_direction(&self) -> Option<Point> {
+        match *self {
+            Piece::Line(a, b) => direction(a, b),
+            Piece::Curve(from, ref c) => [c.pt1, c.pt2, c.to]
+                .into_iter()
+                .find_map(|p| direction(from, p)),
+        }
+    }
+
+    /// Unit vector in the direction in which the piece ends.
+    fn end_direction(&self) -> Option<Point> {
+        match *self {
+            Piece::Line(a, b) => direction(a, b),
+            Piece::Curve(from, ref c) => [c.pt2, c.pt1, from]
+                .into_iter()
+                .find_map(|p| direction(p, c.to)),
+        }
+    }
+
+    fn point_at(&self, t: f64) -> Point {
+        match *self {
+            Piece::Line(a, b) => lerp(a, b, t),
+            Piece::Curve(from, ref curve) => curve.point_at(from, t),
+        }
+    }
+
+    /// Returns cumulative lengths along the piece, at evenly spaced parameters.
+    ///
+    /// The first element is 0 and the last one is the length of the whole piece.
+    fn length_table(&self) -> Vec<f64> {
+        let n = match *self {
+            Piece::Line(..) => 1,
+            Piece::Curve(..) => CURVE_LENGTH_CHORDS,
+        };
+
+        let mut lengths = Vec::with_capacity(n + 1);
+        let mut length = 0.0;
+        let mut prev = self.start();
+        lengths.push(length);
+
+        for i in 1..=n {
+            let p = self.point_at(i as f64 / n as f64);
+            length += distance(prev, p);
+            lengths.push(length);
+            prev = p;
+        }
+
+        lengths
+    }
+
+    /// Returns the part of the piece between the parameters `t0` and `t1`.
+    fn sub_piece(&self, t0: f64, t1: f64) -> Piece {
+        match *self {
+            Piece::Line(a, b) => Piece::Line(lerp(a, b, t0), lerp(a, b, t1)),
+
+            Piece::Curve(from, ref c) => {
+                let p = [from, c.pt1, c.pt2, c.to];
+
+                // Split at t1 and keep the first part, then split that at t0 (which is
+                // relative to the first part now) and keep the second part.
+                let (head, _) = split_cubic(p, t1);
+                let t0 = if t1 > 0.0 { t0 / t1 } else { 0.0 };
+                let (_, [p0, p1, p2, p3]) = split_cubic(head, t0);
+
+                Piece::Curve(
+                    p0,
+                    CubicBezierCurve {
+                        pt1: p1,
+                        pt2: p2,
+                        to: p3,
+                    },
+                )
+            }
+        }
+    }
+
+    /// Direction of the piece at parameter `t`.
+    fn direction_at(&self, t: f64) -> Option<Point> {
+        if t < 1.0 {
+            self.sub_piece(t, 1.0).start_direction()
+        } else {
+            self.end_direction()
+        }
+    }
+}
+
+/// Converts a length along a piece to a parameter, using the piece's `length_table`.
+fn param_at_length(table: &[f64], length: f64) -> f64 {
+    let n = table.len() - 1;
+
+    for i in 0..n {
+        if length <= table[i + 1] {
+            let chord = table[i + 1] - table[i];
+            let frac = if chord > 0.0 {
+                (length - table[i]) / chord
+            } else {
+                0.0
+            };
+
+            return (i as f64 + frac) / n as f64;
+        }
+    }
+
+    1.0
+}
+
+/// Splits a cubic Bézier curve at parameter `t` with de Casteljau's algorithm.
+fn split_cubic(p: [Point; 4], t: f64) -> ([Point; 4], [Point; 4]) {
+    let p01 = lerp(p[0], p[1], t);
+    let p12 = lerp(p[1], p[2], t);
+    let p23 = lerp(p[2], p[3], t);
+    let p012 = lerp(p01, p12, t);
+    let p123 = lerp(p12, p23, t);
+    let mid = lerp(p012, p123, t);
+
+    ([p[0], p01, p012, mid], [mid, p123, p23, p[3]])
+}
+
+/// Walks the dash pattern of a stroke.
+struct Dasher<'a> {
+    dashes: &'a [f64],
+    index: usize,
+    remaining: f64,
+}
+
+impl<'a> Dasher<'a> {
+    /// Starts the pattern at `offset`, like cairo does at the start of each subpath.
+    fn new(dashes: &'a [f64], offset: f64) -> Dasher<'a> {
+        let mut dasher = Dasher {
+            dashes,
+            index: 0,
+            remaining: 0.0,
+        };
+
+        // A pattern with an odd number of lengths gets repeated to yield an even number.
+        let period = if dashes.len() % 2 == 1 { 2.0 } else { 1.0 } * dashes.iter().sum::<f64>();
+        let mut offset = offset.rem_euclid(period);
+
+        while offset >= dasher.len() {
+            offset -= dasher.len();
+            dasher.advance();
+        }
+
+        dasher.remaining = dasher.len() - offset;
+        dasher
+    }
+
+    fn len(&self) -> f64 {
+        self.dashes[self.index % self.dashes.len()]
+    }
+
+    /// Whether the current dash paints; on an odd pattern this alternates on each repeat.
+    fn is_on(&self) -> bool {
+        self.index % 2 == 0
+    }
+
+    fn advance(&mut self) {
+        self.index = (self.index + 1) % (self.dashes.len() * 2);
+        self.remaining = self.len();
+    }
+}
+
+struct ExtentsStroker<'a> {
+    stroke: &'a Stroke,
+    half_width: f64,
+    extents: Extents,
+}
+
+impl<'a> ExtentsStroker<'a> {
+    fn new(stroke: &'a Stroke) -> ExtentsStroker<'a> {
+        ExtentsStroker {
+            stroke,
+            half_width: stroke.width / 2.0,
+            extents: Extents::default(),
+        }
+    }
+
+    fn is_dashed(&self) -> bool {
+        self.stroke.dashes.iter().sum::<f64>() > 0.0
+    }
+
+    fn add_subpath(&mut self, subpath: &Subpath) {
+        if subpath.is_zero_length() {
+            // Cairo paints a dot for degenerate segments with round caps.  For square caps,
+            // librsvg feeds cairo a tiny horizontal segment even for a lone MoveTo; see
+            // Path::to_cairo().
+            let paints_dot = match self.stroke.line_cap {
+                StrokeLinecap::Butt => false,
+                StrokeLinecap::Round => !subpath.pieces.is_empty(),
+                StrokeLinecap::Square => true,
+            };
+
+            if paints_dot {
+                self.add_dot(subpath.origin, (1.0, 0.0));
+            }
+
+            return;
+        }
+
+        if self.is_dashed() {
+            self.add_dashed(subpath);
+        } else {
+            self.add_run(&subpath.pieces, subpath.closed);
+        }
+    }
+
+    fn add_dashed(&mut self, subpath: &Subpath) {
+        let mut dasher = Dasher::new(&self.stroke.dashes, self.stroke.dash_offset);
+        let starts_on = dasher.is_on();
+
+        let mut first_run: Option<Vec<Piece>> = None;
+        let mut run = Vec::new();
+
+        for piece in subpath.pieces.iter().filter(|p| !p.is_degenerate()) {
+            let table = piece.length_table();
+            let total = table[table.len() - 1];
+            let mut pos = 0.0;
+
+            while pos < total {
+                let step = dasher.remaining.min(total - pos);
+
+                if dasher.is_on() {
+                    if step > 0.0 {
+                        let t0 = param_at_length(&table, pos);
+                        let t1 = param_at_length(&table, pos + step);
+                        run.push(piece.sub_piece(t0, t1));
+                    } else {
+                        // Zero-length dashes still get caps.
+                        let t = param_at_length(&table, pos);
+                        if let Some(dir) = piece.direction_at(t) {
+                            self.add_dot(piece.point_at(t), dir);
+                        }
+                    }
+                }
+
+                pos += step;
+                dasher.remaining -= step;
+
+                if dasher.remaining <= 0.0 {
+                    if dasher.is_on() && !run.is_empty() {
+                        if starts_on && first_run.is_none() {
+                            first_run = Some(std::mem::take(&mut run));
+                        } else {
+                            self.add_run(&run, false);
+                            run.clear();
+                        }
+                    }
+
+                    dasher.advance();
+                }
+            }
+        }
+
+        // For closed subpaths, cairo joins the last dash to the first one if the
+        // pattern is "on" at both ends.
+        match first_run {
+            Some(first) if subpath.closed && !run.is_empty() => {
+                run.extend(first);
+                self.add_run(&run, false);
+            }
+
+            Some(first) => {
+                self.add_run(&first, false);
+                self.add_run(&run, false);
+            }
+
+            None => self.add_run(&run, false),
+        }
+    }
+
+    /// Adds a continuous run of pieces, with joins between them and caps at the ends if
+    /// it is not closed.
+    fn add_run(&mut self, pieces: &[Piece], closed: bool) {
+        let pieces: Vec<&Piece> = pieces.iter().filter(|p| !p.is_degenerate()).collect();
+
+        let (Some(first), Some(last)) = (pieces.first(), pieces.last()) else {
+            return;
+        };
+
+        for piece in &pieces {
+            self.add_body(piece);
+        }
+
+        for pair in pieces.windows(2) {
+            self.add_join(pair[0], pair[1]);
+        }
+
+        if closed {
+            self.add_join(last, first);
+        } else {
+            let start_dir = first.start_direction().unwrap();
+            let end_dir = last.end_direction().unwrap();
+
+            self.add_cap(first.start(), (-start_dir.0, -start_dir.1));
+            self.add_cap(last.end(), end_dir);
+        }
+    }
+
+    /// Adds the area swept by a piece, without joins or caps.
+    fn add_body(&mut self, piece: &Piece) {
+        let (Some(start_dir), Some(end_dir)) = (piece.start_direction(), piece.end_direction())
+        else {
+            return;
+        };
+
+        let hw = self.half_width;
+
+        self.add_offsets(piece.start(), start_dir);
+        self.add_offsets(piece.end(), end_dir);
+
+        if let Piece::Curve(from, ref curve) = *piece {
+            let (x_extrema, y_extrema) = curve.extrema(from);
+
+            // Where the curve is vertical, its normal is horizontal, and vice versa.
+            for t in x_extrema {
+                let (x, y) = curve.point_at(from, t);
+                self.extents.add((x - hw, y));
+                self.extents.add((x + hw, y));
+            }
+
+            for t in y_extrema {
+                let (x, y) = curve.point_at(from, t);
+                self.extents.add((x, y - hw));
+                self.extents.add((x, y + hw));
+            }
+        }
+    }
+
+    /// Adds the points at half the stroke width on either side of `p`.
+    fn add_offsets(&mut self, p: Point, dir: Point) {
+        let hw = self.half_width;
+        let n = (-dir.1, dir.0);
+
+        self.extents.add((p.0 + hw * n.0, p.1 + hw * n.1));
+        self.extents.add((p.0 - hw * n.0, p.1 - hw * n.1));
+    }
+
+    fn add_join(&mut self, incoming: &Piece, outgoing: &Piece) {
+        let v = incoming.end();
+        let d_in = incoming.end_direction().unwrap();
+        let d_out = outgoing.start_direction().unwrap();
+        let hw = self.half_width;
+
+        let cos_turn = dot(d_in, d_out);
+        let cross = d_in.0 * d_out.1 - d_in.1 * d_out.0;
+
+        if cross.abs() < DEGENERATE_LENGTH && cos_turn > 0.0 {
+            // No turn; the offset points of both pieces coincide.
+            return;
+        }
+
+        match self.stroke.line_join {
+            // The ends of the offset lines were already added with the pieces.
+            StrokeLinejoin::Bevel => (),
+
+            StrokeLinejoin::Round => {
+                // The arc of the join is on the outer side of the turn: past the end of
+                // the incoming piece, and before the start of the outgoing one.
+                for u in AXIS_DIRECTIONS {
+                    if dot(u, d_in) >= 0.0 && dot(u, d_out) <= 0.0 {
+                        self.extents.add((v.0 + hw * u.0, v.1 + hw * u.1));
+                    }
+                }
+            }
+
+            StrokeLinejoin::Miter => {
+                // Same test as cairo: the ratio of the miter length to the stroke width
+                // is 1 / sin(theta / 2), where theta is the angle between the pieces.
+                let limit = self.stroke.miter_limit.0;
+
+                if limit * limit * (1.0 + cos_turn) >= 2.0 {
+                    let n_in = outer_normal(d_in, d_out);
+                    let n_out = outer_normal(d_out, (-d_in.0, -d_in.1));
+                    let scale = hw / (1.0 + cos_turn);
+
+                    self.extents.add((
+                        v.0 + (n_in.0 + n_out.0) * scale,
+                        v.1 + (n_in.1 + n_out.1) * scale,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Adds a cap at `p`, for a piece that ends there going in direction `dir`.
+    fn add_cap(&mut self, p: Point, dir: Point) {
+        let hw = self.half_width;
+
+        match self.stroke.line_cap {
+            StrokeLinecap::Butt => (),
+
+            StrokeLinecap::Round => {
+                for u in AXIS_DIRECTIONS {
+                    if dot(u, dir) >= 0.0 {
+                        self.extents.add((p.0 + hw * u.0, p.1 + hw * u.1));
+                    }
+                }
+            }
+
+            StrokeLinecap::Square => {
+                self.add_offsets((p.0 + hw * dir.0, p.1 + hw * dir.1), dir);
+            }
+        }
+    }
+
+    /// Adds the caps at both ends of a zero-length segment going in direction `dir`.
+    fn add_dot(&mut self, p: Point, dir: Point) {
+        self.add_cap(p, dir);
+        self.add_cap(p, (-dir.0, -dir.1));
+    }
+}
+
+/// Returns the normal to `dir` that points away from `other`.
+fn outer_normal(dir: Point, other: Point) -> Point {
+    let n = (-dir.1, dir.0);
+
+    if dot(n, other) > 0.0 {
+        (-n.0, -n.1)
+    } else {
+        n
+    }
+}
+
+fn direction(from: Point, to: Point) -> Option<Point> {
+    let len = distance(from, to);
+
+    if len < DEGENERATE_LENGTH {
+        None
+    } else {
+        Some(((to.0 - from.0) / len, (to.1 - from.1) / len))
+    }
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn lerp(a: Point, b: Point, t: f64) -> Point {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::path_builder::PathBuilder;
+    use crate::properties::StrokeMiterlimit;
+
+    fn stroke(width: f64, line_cap: StrokeLinecap, line_join: StrokeLinejoin) -> Stroke {
+        Stroke {
+            width,
+            miter_limit: StrokeMiterlimit(4.0),
+            line_cap,
+            line_join,
+            dash_offset: 0.0,
+            dashes: Box::new([]),
+            non_scaling: false,
+        }
+    }
+
+    fn polyline(points: &[Point]) -> Path {
+        let mut builder = PathBuilder::default();
+        builder.move_to(points[0].0, points[0].1);
+        for &(x, y) in &points[1..] {
+            builder.line_to(x, y);
+        }
+        builder.into_path()
+    }
+
+    fn assert_rect_eq(a: Option<Rect>, b: Rect) {
+        let a = a.unwrap();
+        assert!(
+            (a.x0 - b.x0).abs() < 1e-6
+                && (a.y0 - b.y0).abs() < 1e-6
+                && (a.x1 - b.x1).abs() < 1e-6
+                && (a.y1 - b.y1).abs() < 1e-6,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn caps() {
+        let path = polyline(&[(10.0, 10.0), (30.0, 10.0)]);
+
+        let butt = stroke(4.0, StrokeLinecap::Butt, StrokeLinejoin::Miter);
+        assert_rect_eq(
+            stroke_extents(&path, &butt),
+            Rect::new(10.0, 8.0, 30.0, 12.0),
+        );
+
+        let square = stroke(4.0, StrokeLinecap::Square, StrokeLinejoin::Miter);
+        assert_rect_eq(
+            stroke_extents(&path, &square),
+            Rect::new(8.0, 8.0, 32.0, 12.0),
+        );
+
+        let round = stroke(4.0, StrokeLinecap::Round, StrokeLinejoin::Miter);
+        assert_rect_eq(
+            stroke_extents(&path, &round),
+            Rect::new(8.0, 8.0, 32.0, 12.0),
+        );
+    }
+
+    #[test]
+    fn long_miters() {
+        // A sharp spike; the miter extends far above the apex.
+        let path = polyline(&[(0.0, 100.0), (10.0, 0.0), (20.0, 100.0)]);
+
+        let mut miter = stroke(2.0, StrokeLinecap::Butt, StrokeLinejoin::Miter);
+        miter.miter_limit = StrokeMiterlimit(100.0);
+
+        // 1 / sin(theta / 2) for the angle at the apex
+        let half_angle = (10.0f64).atan2(100.0);
+        let miter_length = 1.0 / half_angle.sin();
+
+        let extents = stroke_extents(&path, &miter).unwrap();
+        assert!((extents.y0 - -miter_length).abs() < 1e-6);
+
+        // Over the miter limit, it becomes a bevel that doesn't reach that high
+        miter.miter_limit = StrokeMiterlimit(4.0);
+        let extents = stroke_extents(&path, &miter).unwrap();
+        assert!(extents.y0 > -1.0);
+    }
+
+    #[test]
+    fn curves_use_offset_extrema() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.curve_to(0.0, 100.0, 100.0, 100.0, 100.0, 0.0);
+        let path = builder.into_path();
+
+        let s = stroke(10.0, StrokeLinecap::Butt, StrokeLinejoin::Miter);
+        assert_rect_eq(stroke_extents(&path, &s), Rect::new(-5.0, 0.0, 105.0, 80.0));
+    }
+
+    #[test]
+    fn dashes_only_include_painted_parts() {
+        let path = polyline(&[(0.0, 0.0), (100.0, 0.0)]);
+
+        let mut s = stroke(2.0, StrokeLinecap::Butt, StrokeLinejoin::Miter);
+        s.dashes = Box::new([10.0, 100.0]);
+        assert_rect_eq(stroke_extents(&path, &s), Rect::new(0.0, -1.0, 10.0, 1.0));
+
+        s.dash_offset = -50.0;
+        assert_rect_eq(stroke_extents(&path, &s), Rect::new(50.0, -1.0, 60.0, 1.0));
+
+        // Zero-length dashes still get their caps
+        s.line_cap = StrokeLinecap::Square;
+        s.dashes = Box::new([0.0, 200.0]);
+        s.dash_offset = -20.0;
+        assert_rect_eq(stroke_extents(&path, &s), Rect::new(19.0, -1.0, 21.0, 1.0));
+    }
+
+    #[test]
+    fn zero_length_subpaths() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(10.0, 10.0);
+        let lone_move = builder.into_path();
+
+        let round = stroke(4.0, StrokeLinecap::Round, StrokeLinejoin::Miter);
+        let square = stroke(4.0, StrokeLinecap::Square, StrokeLinejoin::Miter);
+
+        assert_eq!(stroke_extents(&lone_move, &round), None);
+        assert_rect_eq(
+            stroke_extents(&lone_move, &square),
+            Rect::new(8.0, 8.0, 12.0, 12.0),
+        );
+
+        let dot = polyline(&[(10.0, 10.0), (10.0, 10.0)]);
+        assert_rect_eq(
+            stroke_extents(&dot, &round),
+            Rect::new(8.0, 8.0, 12.0, 12.0),
+        );
+    }
+}
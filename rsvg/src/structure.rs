@@ -18,6 +18,7 @@ use crate::parsers::{Parse, ParseValue};
 use crate::properties::ComputedValues;
 use crate::rect::Rect;
 use crate::session::Session;
+use crate::transform::Transform;
 use crate::viewbox::*;
 use crate::xml::Attributes;
 
@@ -101,11 +102,11 @@ impl ElementTrait for Switch {
                     let elt = c.borrow_element();
                     elt.get_cond(dc.user_language())
                 }) {
-                    child.draw(
+                    dc.draw_node_from_stack(
+                        &child,
                         an,
                         &CascadedValues::clone_with_node(cascaded, &child),
                         viewport,
-                        dc,
                         clipping,
                     )
                 } else {
@@ -131,6 +132,12 @@ pub struct IntrinsicDimensions {
     /// Computed value of the `height` property.
     pub height: ULength<Vertical>,
 
+    /// Whether the `width` property was specified, instead of being `auto`.
+    pub has_width: bool,
+
+    /// Whether the `height` property was specified, instead of being `auto`.
+    pub has_height: bool,
+
     /// Contents of the `viewBox` attribute.
     pub vbox: Option<ViewBox>,
 }
@@ -160,6 +167,8 @@ impl Svg {
         IntrinsicDimensions {
             width: w,
             height: h,
+            has_width: matches!(values.width().0, LengthOrAuto::Length(_)),
+            has_height: matches!(values.height().0, LengthOrAuto::Length(_)),
             vbox: self.vbox,
         }
     }
@@ -220,6 +229,38 @@ impl Svg {
         self.preserve_aspect_ratio
     }
 
+    /// Computes the transform for the outermost `<svg>` element.
+    ///
+    /// The outermost `<svg>` has a CSS layout box, so per the CSS Transforms spec its
+    /// `transform-origin` is `50% 50%` instead of the `0 0` used for other SVG elements.
+    /// That is, the transform is applied around the center of the viewport, and outside
+    /// of the viewBox-to-viewport mapping.  This is what browsers do.
+    fn outermost_transform(
+        &self,
+        values: &ComputedValues,
+        viewport: &Viewport,
+        draw_ctx: &DrawingCtx,
+    ) -> Transform {
+        let transform = values.transform();
+        if transform == Transform::identity() {
+            return transform;
+        }
+
+        let rect = if draw_ctx.is_measuring() {
+            let params = NormalizeParams::new(values, viewport);
+            self.get_viewport(&params, values, true)
+        } else {
+            draw_ctx.toplevel_viewport()
+        };
+
+        let cx = rect.x0 + rect.width() / 2.0;
+        let cy = rect.y0 + rect.height() / 2.0;
+
+        Transform::new_translate(cx, cy)
+            .pre_transform(&transform)
+            .pre_translate(-cx, -cy)
+    }
+
     fn make_svg_viewport(
         &self,
         node: &Node,
@@ -321,12 +362,18 @@ impl ElementTrait for Svg {
     ) -> Result<BoundingBox, InternalRenderingError> {
         let values = cascaded.get();
 
+        let transform = if node.parent().is_some() {
+            values.transform()
+        } else {
+            self.outermost_transform(values, viewport, draw_ctx)
+        };
+
         let elt = node.borrow_element();
         let stacking_ctx = StackingContext::new(
             draw_ctx.session(),
             acquired_nodes,
             &elt,
-            values.transform(),
+            transform,
             None,
             values,
         );
@@ -357,6 +404,10 @@ pub struct Use {
 }
 
 impl Use {
+    pub fn link(&self) -> Option<&NodeId> {
+        self.link.as_ref()
+    }
+
     fn get_rect(&self, params: &NormalizeParams) -> Rect {
         let x = self.x.to_user(params);
         let y = self.y.to_user(params);
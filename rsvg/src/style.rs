@@ -44,23 +44,39 @@ impl StyleType {
 #[derive(Default)]
 pub struct Style {
     type_: StyleType,
+    scoped: bool,
 }
 
 impl Style {
     pub fn style_type(&self) -> StyleType {
         self.type_
     }
+
+    /// Whether the `<style>` has a `scoped` attribute.
+    ///
+    /// The rules in a scoped stylesheet only apply to the `<style>`'s parent element and
+    /// its descendants.  This is the semantics of the old HTML `<style scoped>`.
+    pub fn is_scoped(&self) -> bool {
+        self.scoped
+    }
 }
 
 impl ElementTrait for Style {
     fn set_attributes(&mut self, attrs: &Attributes, session: &Session) {
         for (attr, value) in attrs.iter() {
-            if attr.expanded() == expanded_name!("", "type") {
-                set_attribute(
-                    &mut self.type_,
-                    StyleType::parse(value).attribute(attr),
-                    session,
-                );
+            match attr.expanded() {
+                expanded_name!("", "type") => {
+                    set_attribute(
+                        &mut self.type_,
+                        StyleType::parse(value).attribute(attr),
+                        session,
+                    );
+                }
+
+                // This is a boolean attribute; its presence is what matters.
+                expanded_name!("", "scoped") => self.scoped = true,
+
+                _ => (),
             }
         }
     }
@@ -214,7 +214,8 @@ impl PixelOps for Pixel {
 
     /// Returns a 'mask' pixel with only the alpha channel
     ///
-    /// Assuming, the pixel is linear RGB (not sRGB)
+    /// The coefficients are the ones for linear RGB, but they get applied to the pixel's
+    /// values as they are; convert the surface to linear RGB first if needed.
     /// y = luminance
     /// Y = 0.2126 R + 0.7152 G + 0.0722 B
     /// 1.0 opacity = 255
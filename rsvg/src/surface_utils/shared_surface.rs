@@ -1,5 +1,6 @@
 //! Shared access to Cairo image surfaces.
 use std::cmp::min;
+use std::f64::consts::PI;
 use std::marker::PhantomData;
 use std::ptr::NonNull;
 use std::slice;
@@ -173,6 +174,53 @@ pub enum AlphaOnly {}
 /// Not alpha-only.
 pub enum NotAlphaOnly {}
 
+/// Number of lobes of the Lanczos filter used by [`ImageSurface::resample`].
+const LANCZOS_LOBES: f64 = 3.0;
+
+fn lanczos(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else if x.abs() >= LANCZOS_LOBES {
+        0.0
+    } else {
+        let px = PI * x;
+        LANCZOS_LOBES * px.sin() * (px / LANCZOS_LOBES).sin() / (px * px)
+    }
+}
+
+/// Computes the filter weights to resample a row of `src_len` pixels into `dst_len` pixels.
+///
+/// For each output pixel, returns the index of the first input pixel it depends on, and
+/// the normalized weights of it and the following input pixels.
+fn lanczos_weights(src_len: i32, dst_len: i32) -> Vec<(u32, Vec<f64>)> {
+    let scale = f64::from(dst_len) / f64::from(src_len);
+
+    // When shrinking, the filter gets wider so that it covers all the input pixels.
+    let filter_scale = scale.min(1.0);
+    let support = LANCZOS_LOBES / filter_scale;
+
+    (0..dst_len)
+        .map(|i| {
+            let center = (f64::from(i) + 0.5) / scale;
+            let start = ((center - support).floor() as i32).max(0);
+            let end = ((center + support).ceil() as i32).min(src_len);
+
+            let mut weights: Vec<f64> = (start..end)
+                .map(|j| lanczos((f64::from(j) + 0.5 - center) * filter_scale))
+                .collect();
+
+            let sum: f64 = weights.iter().sum();
+            if sum != 0.0 {
+                for w in weights.iter_mut() {
+                    *w /= sum;
+                }
+            }
+
+            (start as u32, weights)
+        })
+        .collect()
+}
+
 /// Iterator over the rows of a `SharedImageSurface`.
 pub struct Rows<'a> {
     surface: &'a SharedImageSurface,
@@ -561,6 +609,128 @@ impl ImageSurface<Shared> {
         }
     }
 
+    /// Resamples the surface to `width`×`height` pixels with a Lanczos filter.
+    ///
+    /// This is much slower than letting Cairo scale the surface, but it does not alias when
+    /// the surface is shrunk by a large factor.  Filtering happens on the stored
+    /// (premultiplied) values.
+    ///
+    /// # Panics
+    /// Panics if `width` or `height` are not positive.
+    pub fn resample(&self, width: i32, height: i32) -> Result<SharedImageSurface, cairo::Error> {
+        assert!(width > 0 && height > 0);
+
+        let horizontal = lanczos_weights(self.width, width);
+        let vertical = lanczos_weights(self.height, height);
+
+        let buffer_width = width as usize;
+
+        // Horizontal pass, into a buffer that is `width` pixels wide and as tall as the input.
+        let mut buffer = vec![[0.0f64; 4]; buffer_width * self.height as usize];
+
+        for y in 0..self.height as u32 {
+            for (x, (start, weights)) in horizontal.iter().enumerate() {
+                let mut sum = [0.0; 4];
+
+                for (i, weight) in weights.iter().enumerate() {
+                    let pixel = self.get_pixel(*start + i as u32, y);
+
+                    for (s, c) in sum.iter_mut().zip([pixel.r, pixel.g, pixel.b, pixel.a]) {
+                        *s += weight * f64::from(c);
+                    }
+                }
+
+                buffer[y as usize * buffer_width + x] = sum;
+            }
+        }
+
+        // Vertical pass, into the output surface.
+        let mut output_surface = ExclusiveImageSurface::new(width, height, self.surface_type)?;
+
+        output_surface.modify(&mut |data, stride| {
+            for (y, (start, weights)) in vertical.iter().enumerate() {
+                for x in 0..buffer_width {
+                    let mut sum = [0.0; 4];
+
+                    for (i, weight) in weights.iter().enumerate() {
+                        let row = *start as usize + i;
+
+                        for (s, c) in sum.iter_mut().zip(buffer[row * buffer_width + x]) {
+                            *s += weight * c;
+                        }
+                    }
+
+                    let alpha = sum[3].round().clamp(0.0, 255.0);
+
+                    // The filter's negative lobes can make color values larger than alpha,
+                    // which is not valid for premultiplied pixels.
+                    let component = |c: f64| c.round().clamp(0.0, alpha) as u8;
+
+                    let output_pixel = Pixel {
+                        r: component(sum[0]),
+                        g: component(sum[1]),
+                        b: component(sum[2]),
+                        a: alpha as u8,
+                    };
+
+                    data.set_pixel(stride, output_pixel, x as u32, y as u32);
+                }
+            }
+        });
+
+        output_surface.share()
+    }
+
+    /// Shrinks the surface by an integer `factor` with a box filter.
+    ///
+    /// Each output pixel is the average of a `factor`×`factor` block of input pixels.  The
+    /// averaging happens on the stored (premultiplied) values, so for gamma-correct results
+    /// the surface should be converted with [`Self::to_linear_rgb`] first.
+    ///
+    /// Input pixels beyond the last whole block are ignored.
+    ///
+    /// # Panics
+    /// Panics if `factor` is zero or larger than the surface's width or height.
+    pub fn downsample(&self, factor: u32) -> Result<SharedImageSurface, cairo::Error> {
+        assert!(factor > 0);
+
+        let width = self.width / factor as i32;
+        let height = self.height / factor as i32;
+        let mut output_surface = ExclusiveImageSurface::new(width, height, self.surface_type)?;
+
+        let num_pixels = factor * factor;
+        let average = |sum: u32| ((sum + num_pixels / 2) / num_pixels) as u8;
+
+        output_surface.modify(&mut |data, stride| {
+            for y in 0..height as u32 {
+                for x in 0..width as u32 {
+                    let (mut r, mut g, mut b, mut a) = (0u32, 0u32, 0u32, 0u32);
+
+                    for dy in 0..factor {
+                        for dx in 0..factor {
+                            let pixel = self.get_pixel(x * factor + dx, y * factor + dy);
+                            r += u32::from(pixel.r);
+                            g += u32::from(pixel.g);
+                            b += u32::from(pixel.b);
+                            a += u32::from(pixel.a);
+                        }
+                    }
+
+                    let output_pixel = Pixel {
+                        r: average(r),
+                        g: average(g),
+                        b: average(b),
+                        a: average(a),
+                    };
+
+                    data.set_pixel(stride, output_pixel, x, y);
+                }
+            }
+        });
+
+        output_surface.share()
+    }
+
     /// Performs a convolution.
     ///
     /// Note that `kernel` is rotated 180 degrees.
@@ -1476,4 +1646,76 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn downsample_averages_blocks() {
+        let mut surface = ExclusiveImageSurface::new(4, 2, SurfaceType::SRgb).unwrap();
+
+        // Left block: one opaque white pixel out of four; right block: all transparent.
+        surface.modify(&mut |data, stride| {
+            data.set_pixel(
+                stride,
+                Pixel {
+                    r: 255,
+                    g: 255,
+                    b: 255,
+                    a: 255,
+                },
+                1,
+                1,
+            );
+        });
+
+        let surface = surface.share().unwrap();
+        let small = surface.downsample(2).unwrap();
+
+        assert_eq!(small.width(), 2);
+        assert_eq!(small.height(), 1);
+
+        let p = small.get_pixel(0, 0);
+        assert_eq!((p.r, p.g, p.b, p.a), (64, 64, 64, 64));
+
+        let p = small.get_pixel(1, 0);
+        assert_eq!((p.r, p.g, p.b, p.a), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn resample_keeps_solid_colors_and_premultiplication() {
+        let mut surface = ExclusiveImageSurface::new(40, 30, SurfaceType::SRgb).unwrap();
+
+        // Opaque red on the left half, transparent on the right half.
+        surface.modify(&mut |data, stride| {
+            for y in 0..30 {
+                for x in 0..20 {
+                    data.set_pixel(
+                        stride,
+                        Pixel {
+                            r: 255,
+                            g: 0,
+                            b: 0,
+                            a: 255,
+                        },
+                        x,
+                        y,
+                    );
+                }
+            }
+        });
+
+        let surface = surface.share().unwrap();
+        let small = surface.resample(8, 3).unwrap();
+
+        assert_eq!(small.width(), 8);
+        assert_eq!(small.height(), 3);
+
+        let p = small.get_pixel(0, 1);
+        assert_eq!((p.r, p.g, p.b, p.a), (255, 0, 0, 255));
+
+        let p = small.get_pixel(7, 1);
+        assert_eq!((p.r, p.g, p.b, p.a), (0, 0, 0, 0));
+
+        for (_x, _y, p) in Pixels::new(&small) {
+            assert!(p.r <= p.a && p.g <= p.a && p.b <= p.a);
+        }
+    }
 }
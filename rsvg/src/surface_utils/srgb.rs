@@ -93,3 +93,113 @@ pub fn unlinearize_surface(
 
     map_unpremultiplied_components(surface, bounds, unlinearize, SurfaceType::SRgb)
 }
+
+/// Converts an sRGB component in the range 0.0 to 1.0 to linear light.
+///
+/// Unlike [`linearize`], this does not round to 8 bits, so that dark colors keep their
+/// precision.
+pub fn linearize_f64(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light component in the range 0.0 to 1.0 to sRGB.
+pub fn unlinearize_f64(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Composites a premultiplied sRGB pixel over another one, blending in linear light.
+pub fn composite_over_in_linear_light(src: Pixel, dst: Pixel) -> Pixel {
+    let src_alpha = f64::from(src.a) / 255.0;
+    let dst_alpha = f64::from(dst.a) / 255.0;
+    let alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+
+    if alpha == 0.0 {
+        return Pixel {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        };
+    }
+
+    // Unpremultiplies a component and converts it to linear light.
+    let linear = |c: u8, a: f64| {
+        if a == 0.0 {
+            0.0
+        } else {
+            linearize_f64((f64::from(c) / 255.0 / a).min(1.0))
+        }
+    };
+
+    let blend = |s: u8, d: u8| {
+        let premultiplied =
+            linear(s, src_alpha) * src_alpha + linear(d, dst_alpha) * dst_alpha * (1.0 - src_alpha);
+        let c = unlinearize_f64(premultiplied / alpha);
+        (c * alpha * 255.0 + 0.5) as u8
+    };
+
+    Pixel {
+        r: blend(src.r, dst.r),
+        g: blend(src.g, dst.g),
+        b: blend(src.b, dst.b),
+        a: (alpha * 255.0 + 0.5) as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(v: u8, a: u8) -> Pixel {
+        Pixel {
+            r: v,
+            g: v,
+            b: v,
+            a,
+        }
+    }
+
+    #[test]
+    fn round_trips_f64_components() {
+        for i in 0..=255 {
+            let c = f64::from(i) / 255.0;
+            assert!((unlinearize_f64(linearize_f64(c)) - c).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn opaque_source_replaces_destination() {
+        let src = Pixel {
+            r: 10,
+            g: 20,
+            b: 30,
+            a: 255,
+        };
+        assert_eq!(composite_over_in_linear_light(src, gray(200, 255)), src);
+    }
+
+    #[test]
+    fn half_white_over_black_is_half_the_light() {
+        // Blending in sRGB would give 128; half the light is much brighter.
+        assert_eq!(
+            composite_over_in_linear_light(gray(128, 128), gray(0, 255)),
+            gray(188, 255)
+        );
+    }
+
+    #[test]
+    fn over_transparent_keeps_source() {
+        assert_eq!(
+            composite_over_in_linear_light(gray(64, 128), gray(0, 0)),
+            gray(64, 128)
+        );
+    }
+}
@@ -28,8 +28,21 @@ pub fn render_document<F: FnOnce(&cairo::Context)>(
     cr_transform: F,
     viewport: cairo::Rectangle,
 ) -> Result<SharedImageSurface, RenderingError> {
-    let renderer = CairoRenderer::new(svg);
+    render_document_with_renderer(
+        &CairoRenderer::new(svg),
+        surface_size,
+        cr_transform,
+        viewport,
+    )
+}
 
+/// Like [`render_document`], but with a renderer that the caller has configured.
+pub fn render_document_with_renderer<F: FnOnce(&cairo::Context)>(
+    renderer: &CairoRenderer<'_>,
+    surface_size: SurfaceSize,
+    cr_transform: F,
+    viewport: cairo::Rectangle,
+) -> Result<SharedImageSurface, RenderingError> {
     let SurfaceSize(width, height) = surface_size;
 
     let output = cairo::ImageSurface::create(cairo::Format::ARgb32, width, height).unwrap();
@@ -17,8 +17,8 @@ use crate::node::{CascadedValues, Node, NodeBorrow};
 use crate::paint_server::PaintSource;
 use crate::parsers::ParseValue;
 use crate::properties::{
-    ComputedValues, Direction, FontStretch, FontStyle, FontVariant, FontWeight, PaintOrder,
-    TextAnchor, TextRendering, UnicodeBidi, WritingMode, XmlLang, XmlSpace,
+    ComputedValues, Direction, Display, FontStretch, FontStyle, FontVariant, FontWeight,
+    PaintOrder, TextAnchor, TextRendering, UnicodeBidi, WritingMode, XmlLang, XmlSpace,
 };
 use crate::rect::Rect;
 use crate::rsvg_log;
@@ -38,6 +38,9 @@ struct LayoutContext {
     /// Font options from the DrawingCtx.
     font_options: FontOptions,
 
+    /// Font map with the document's registered fonts, or `None` to use the default one.
+    font_map: Option<pangocairo::FontMap>,
+
     /// For normalizing lengths.
     viewport: Viewport,
 
@@ -74,7 +77,7 @@ struct MeasuredChunk {
     spans: Vec<MeasuredSpan>,
 }
 
-struct PositionedChunk {
+pub struct PositionedChunk {
     next_chunk_x: f64,
     next_chunk_y: f64,
     spans: Vec<PositionedSpan>,
@@ -770,6 +773,10 @@ impl ElementTrait for Text {
         draw_ctx: &mut DrawingCtx,
         clipping: bool,
     ) -> Result<BoundingBox, InternalRenderingError> {
+        if !acquired_nodes.features().text {
+            return Ok(draw_ctx.empty_bbox());
+        }
+
         let values = cascaded.get();
         let params = NormalizeParams::new(values, viewport);
 
@@ -790,37 +797,59 @@ impl ElementTrait for Text {
             let layout_context = LayoutContext {
                 writing_mode: values.writing_mode(),
                 transform,
-                font_options: draw_ctx.get_font_options(),
+                font_options: draw_ctx.get_font_options(acquired_nodes.font_options()),
+                font_map: acquired_nodes.font_map().cloned(),
                 viewport: viewport.clone(),
                 session: draw_ctx.session().clone(),
             };
 
-            let mut x = self.x.to_user(&params);
-            let mut y = self.y.to_user(&params);
+            // Text that gets re-cascaded from a <use> element may look different in each
+            // instance, so only text with the node's own cascade can be cached.
+            let render_cache = acquired_nodes
+                .render_cache()
+                .filter(|_| cascaded.is_from_node());
 
-            let chunks = self.make_chunks(node, acquired_nodes, cascaded, &layout_context, x, y);
+            let cached_chunks = render_cache.and_then(|c| c.text_layout(node, viewport));
 
-            let mut measured_chunks = Vec::new();
-            for chunk in &chunks {
-                measured_chunks.push(MeasuredChunk::from_chunk(&layout_context, chunk));
-            }
+            let positioned_chunks = if let Some(chunks) = cached_chunks {
+                chunks
+            } else {
+                let mut x = self.x.to_user(&params);
+                let mut y = self.y.to_user(&params);
+
+                let chunks =
+                    self.make_chunks(node, acquired_nodes, cascaded, &layout_context, x, y);
 
-            let mut positioned_chunks = Vec::new();
-            for chunk in &measured_chunks {
-                let chunk_x = chunk.x.unwrap_or(x);
-                let chunk_y = chunk.y.unwrap_or(y);
+                let mut measured_chunks = Vec::new();
+                for chunk in &chunks {
+                    measured_chunks.push(MeasuredChunk::from_chunk(&layout_context, chunk));
+                }
 
-                let positioned =
-                    PositionedChunk::from_measured(&layout_context, chunk, chunk_x, chunk_y);
+                let mut positioned_chunks = Vec::new();
+                for chunk in &measured_chunks {
+                    let chunk_x = chunk.x.unwrap_or(x);
+                    let chunk_y = chunk.y.unwrap_or(y);
 
-                x = positioned.next_chunk_x;
-                y = positioned.next_chunk_y;
+                    let positioned =
+                        PositionedChunk::from_measured(&layout_context, chunk, chunk_x, chunk_y);
 
-                positioned_chunks.push(positioned);
-            }
+                    x = positioned.next_chunk_x;
+                    y = positioned.next_chunk_y;
+
+                    positioned_chunks.push(positioned);
+                }
+
+                let positioned_chunks = Rc::new(positioned_chunks);
+
+                if let Some(render_cache) = render_cache {
+                    render_cache.store_text_layout(node, viewport, positioned_chunks.clone());
+                }
+
+                positioned_chunks
+            };
 
             let mut layout_spans = Vec::new();
-            for chunk in &positioned_chunks {
+            for chunk in positioned_chunks.iter() {
                 for span in &chunk.spans {
                     layout_spans.push(span.layout(&layout_context, acquired_nodes));
                 }
@@ -974,17 +1003,27 @@ impl TSpan {
         link: Option<String>,
     ) {
         let values = cascaded.get();
-        if !values.is_displayed() {
-            return;
-        }
 
-        let params = NormalizeParams::new(values, &layout_context.viewport);
+        // With display: contents, the text of the span is laid out as if it were directly
+        // in the parent, so the span's own positioning does not apply.
+        let unboxed = match values.display() {
+            Display::None => return,
+            Display::Contents => true,
+            _ => false,
+        };
 
-        let x = self.x.map(|l| l.to_user(&params));
-        let y = self.y.map(|l| l.to_user(&params));
+        let params = NormalizeParams::new(values, &layout_context.viewport);
 
-        let span_dx = dx + self.dx.to_user(&params);
-        let span_dy = dy + self.dy.to_user(&params);
+        let (x, y, span_dx, span_dy) = if unboxed {
+            (None, None, dx, dy)
+        } else {
+            (
+                self.x.map(|l| l.to_user(&params)),
+                self.y.map(|l| l.to_user(&params)),
+                dx + self.dx.to_user(&params),
+                dy + self.dy.to_user(&params),
+            )
+        };
 
         if x.is_some() || y.is_some() {
             chunks.push(Chunk::new(values, x, y));
@@ -1206,8 +1245,11 @@ fn create_pango_layout(
     props: &FontProperties,
     text: &str,
 ) -> Option<pango::Layout> {
-    let pango_context =
-        create_pango_context(&layout_context.font_options, &layout_context.transform);
+    let pango_context = create_pango_context(
+        &layout_context.font_options,
+        layout_context.font_map.as_ref(),
+        &layout_context.transform,
+    );
 
     if let XmlLang(Some(ref lang)) = props.xml_lang {
         pango_context.set_language(Some(&pango::Language::from_string(lang.as_str())));
@@ -9,6 +9,8 @@
 //! * [`Transform`] also handles the [`transform` attribute][attr] in SVG1.1, which has a different
 //! grammar than the `transform` property from SVG2.
 //!
+//! * [`TransformList`] for `gradientTransform` and `patternTransform`, which accept both grammars.
+//!
 //! [prop]: https://www.w3.org/TR/css-transforms-1/#transform-property
 //! [attr]: https://www.w3.org/TR/SVG11/coords.html#TransformAttribute
 
@@ -102,34 +104,50 @@ impl TransformProperty {
         match self {
             TransformProperty::None => Transform::identity(),
 
-            TransformProperty::List(l) => {
-                let mut final_transform = Transform::identity();
-
-                for f in l.iter() {
-                    use TransformFunction::*;
-
-                    let transform_matrix = match f {
-                        Matrix(trans_matrix) => *trans_matrix,
-                        Translate(h, v) => Transform::new_translate(h.length, v.length),
-                        TranslateX(h) => Transform::new_translate(h.length, 0.0),
-                        TranslateY(v) => Transform::new_translate(0.0, v.length),
-                        Scale(x, y) => Transform::new_scale(*x, *y),
-                        ScaleX(x) => Transform::new_scale(*x, 1.0),
-                        ScaleY(y) => Transform::new_scale(1.0, *y),
-                        Rotate(a) => Transform::new_rotate(*a),
-                        Skew(ax, ay) => Transform::new_skew(*ax, *ay),
-                        SkewX(ax) => Transform::new_skew(*ax, Angle::new(0.0)),
-                        SkewY(ay) => Transform::new_skew(Angle::new(0.0), *ay),
-                    };
-                    final_transform = transform_matrix.post_transform(&final_transform);
-                }
-
-                final_transform
-            }
+            // The parser only allows translations in pixels for the property, so we can
+            // use the lengths directly.
+            TransformProperty::List(l) => functions_to_transform(l, |h| h.length, |v| v.length),
         }
     }
 }
 
+/// Multiplies a list of transform functions from left to right.
+///
+/// The `horizontal` and `vertical` functions are used to turn the lengths in translations
+/// into user-space values.
+fn functions_to_transform<H, V>(
+    functions: &[TransformFunction],
+    horizontal: H,
+    vertical: V,
+) -> Transform
+where
+    H: Fn(&Length<Horizontal>) -> f64,
+    V: Fn(&Length<Vertical>) -> f64,
+{
+    let mut final_transform = Transform::identity();
+
+    for f in functions.iter() {
+        use TransformFunction::*;
+
+        let transform_matrix = match f {
+            Matrix(trans_matrix) => *trans_matrix,
+            Translate(h, v) => Transform::new_translate(horizontal(h), vertical(v)),
+            TranslateX(h) => Transform::new_translate(horizontal(h), 0.0),
+            TranslateY(v) => Transform::new_translate(0.0, vertical(v)),
+            Scale(x, y) => Transform::new_scale(*x, *y),
+            ScaleX(x) => Transform::new_scale(*x, 1.0),
+            ScaleY(y) => Transform::new_scale(1.0, *y),
+            Rotate(a) => Transform::new_rotate(*a),
+            Skew(ax, ay) => Transform::new_skew(*ax, *ay),
+            SkewX(ax) => Transform::new_skew(*ax, Angle::new(0.0)),
+            SkewY(ay) => Transform::new_skew(Angle::new(0.0), *ay),
+        };
+        final_transform = transform_matrix.post_transform(&final_transform);
+    }
+
+    final_transform
+}
+
 // https://www.w3.org/TR/css-transforms-1/#typedef-transform-function
 #[derive(Debug, Clone, PartialEq)]
 pub enum TransformFunction {
@@ -146,6 +164,17 @@ pub enum TransformFunction {
     SkewY(Angle),
 }
 
+impl TransformFunction {
+    fn is_translation_in_pixels(&self) -> bool {
+        match self {
+            TransformFunction::Translate(h, v) => length_is_in_pixels(h) && length_is_in_pixels(v),
+            TransformFunction::TranslateX(h) => length_is_in_pixels(h),
+            TransformFunction::TranslateY(v) => length_is_in_pixels(v),
+            _ => true,
+        }
+    }
+}
+
 impl Parse for TransformProperty {
     fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TransformProperty, ParseError<'i>> {
         if parser
@@ -154,13 +183,83 @@ impl Parse for TransformProperty {
         {
             Ok(TransformProperty::None)
         } else {
+            let loc = parser.current_source_location();
+
             let t = parse_transform_prop_function_list(parser)?;
 
+            if !t.iter().all(TransformFunction::is_translation_in_pixels) {
+                return Err(only_pixels_error(loc));
+            }
+
             Ok(TransformProperty::List(t))
         }
     }
 }
 
+/// The `gradientTransform` and `patternTransform` attributes.
+///
+/// In SVG1.1 these have the same grammar as the `transform` attribute.  SVG2 turns them
+/// into presentation attributes for the `transform` property, so they may also contain CSS
+/// transform functions with lengths in any units, like `translate(1cm, 50%)`.  Those can
+/// only be turned into a matrix once the lengths can be normalized and the reference box
+/// for percentages is known; see [`TransformList::to_transform`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransformList {
+    /// Plain SVG1.1 transform list, already converted to a matrix.
+    Attribute(TransformAttribute),
+
+    /// CSS transform functions which may need to be resolved.
+    Functions(Vec<TransformFunction>),
+}
+
+impl Default for TransformList {
+    fn default() -> TransformList {
+        TransformList::Attribute(TransformAttribute::default())
+    }
+}
+
+impl Parse for TransformList {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TransformList, ParseError<'i>> {
+        if let Ok(t) = parser.try_parse(TransformAttribute::parse) {
+            Ok(TransformList::Attribute(t))
+        } else {
+            Ok(TransformList::Functions(
+                parse_transform_prop_function_list(parser)?,
+            ))
+        }
+    }
+}
+
+impl TransformList {
+    /// Converts the transform list to a matrix.
+    ///
+    /// Lengths in translations get normalized with `params`, except for percentages,
+    /// which refer to the size of the `reference_box`, per the CSS Transforms spec.
+    pub fn to_transform(&self, params: &NormalizeParams, reference_box: &Rect) -> Transform {
+        match self {
+            TransformList::Attribute(t) => t.to_transform(),
+
+            TransformList::Functions(l) => functions_to_transform(
+                l,
+                |h| {
+                    if h.unit == LengthUnit::Percent {
+                        h.length * reference_box.width()
+                    } else {
+                        h.to_user(params)
+                    }
+                },
+                |v| {
+                    if v.unit == LengthUnit::Percent {
+                        v.length * reference_box.height()
+                    } else {
+                        v.to_user(params)
+                    }
+                },
+            ),
+        }
+    }
+}
+
 fn parse_transform_prop_function_list<'i>(
     parser: &mut Parser<'i, '_>,
 ) -> Result<Vec<TransformFunction>, ParseError<'i>> {
@@ -248,8 +347,6 @@ fn parse_prop_translate_args<'i>(
     parser: &mut Parser<'i, '_>,
 ) -> Result<TransformFunction, ParseError<'i>> {
     parser.parse_nested_block(|p| {
-        let loc = p.current_source_location();
-
         let tx: Length<Horizontal> = Length::parse(p)?;
 
         let ty: Length<Vertical> = if p.try_parse(|p| p.expect_comma()).is_ok() {
@@ -258,10 +355,6 @@ fn parse_prop_translate_args<'i>(
             Length::new(0.0, LengthUnit::Px)
         };
 
-        if !(length_is_in_pixels(&tx) && length_is_in_pixels(&ty)) {
-            return Err(only_pixels_error(loc));
-        }
-
         Ok(TransformFunction::Translate(tx, ty))
     })
 }
@@ -270,14 +363,8 @@ fn parse_prop_translate_x_args<'i>(
     parser: &mut Parser<'i, '_>,
 ) -> Result<TransformFunction, ParseError<'i>> {
     parser.parse_nested_block(|p| {
-        let loc = p.current_source_location();
-
         let tx: Length<Horizontal> = Length::parse(p)?;
 
-        if !length_is_in_pixels(&tx) {
-            return Err(only_pixels_error(loc));
-        }
-
         Ok(TransformFunction::TranslateX(tx))
     })
 }
@@ -286,14 +373,8 @@ fn parse_prop_translate_y_args<'i>(
     parser: &mut Parser<'i, '_>,
 ) -> Result<TransformFunction, ParseError<'i>> {
     parser.parse_nested_block(|p| {
-        let loc = p.current_source_location();
-
         let ty: Length<Vertical> = Length::parse(p)?;
 
-        if !length_is_in_pixels(&ty) {
-            return Err(only_pixels_error(loc));
-        }
-
         Ok(TransformFunction::TranslateY(ty))
     })
 }
@@ -513,6 +594,18 @@ impl Transform {
         (x + self.x0, y + self.y0)
     }
 
+    /// Returns the largest factor by which this transform scales distances.
+    ///
+    /// This is the largest singular value of the matrix, ignoring the translation.
+    pub fn max_scale_factor(&self) -> f64 {
+        let sum_squares =
+            self.xx * self.xx + self.xy * self.xy + self.yx * self.yx + self.yy * self.yy;
+        let det = self.xx * self.yy - self.xy * self.yx;
+        let discriminant = (sum_squares * sum_squares - 4.0 * det * det).max(0.0);
+
+        ((sum_squares + discriminant.sqrt()) / 2.0).sqrt()
+    }
+
     pub fn transform_rect(&self, rect: &Rect) -> Rect {
         let points = [
             self.transform_point(rect.x0, rect.y0),
@@ -796,6 +889,15 @@ mod tests {
         assert_eq!((2.0, 1.0), t.transform_distance(1.0, 1.0));
     }
 
+    #[test]
+    fn computes_max_scale_factor() {
+        assert_eq!(Transform::identity().max_scale_factor(), 1.0);
+        assert_eq!(Transform::new_scale(2.0, -3.0).max_scale_factor(), 3.0);
+
+        let t = Transform::new_rotate(Angle::from_degrees(30.0)).post_scale(4.0, 4.0);
+        assert!((t.max_scale_factor() - 4.0).abs() < 1e-9);
+    }
+
     #[test]
     fn parses_valid_transform() {
         let t = Transform::new_unchecked(1.0, 0.0, 0.0, 1.0, 20.0, 30.0);
@@ -1031,6 +1133,51 @@ mod tests {
         assert!(parse_transform_prop("translateY(1cm)").is_err());
     }
 
+    #[test]
+    fn parses_transform_list_with_both_grammars() {
+        assert_eq!(
+            TransformList::parse_str("translate(10 20) scale(2)").unwrap(),
+            TransformList::Attribute(TransformAttribute(
+                parse_transform("translate(10 20) scale(2)").unwrap()
+            ))
+        );
+
+        assert_eq!(
+            TransformList::parse_str("translate(1in, 50%) rotate(0.25turn)").unwrap(),
+            TransformList::Functions(vec![
+                TransformFunction::Translate(
+                    Length::new(1.0, LengthUnit::In),
+                    Length::new(0.5, LengthUnit::Percent)
+                ),
+                TransformFunction::Rotate(Angle::from_degrees(90.0)),
+            ])
+        );
+
+        assert!(TransformList::parse_str("translate(1in, foo)").is_err());
+    }
+
+    #[test]
+    fn resolves_transform_list_units() {
+        use crate::dpi::Dpi;
+        use crate::drawing_ctx::Viewport;
+
+        let viewport = Viewport::new(Dpi::new(96.0, 96.0), 100.0, 100.0);
+        let params = NormalizeParams::new(&ComputedValues::default(), &viewport);
+        let reference_box = Rect::new(10.0, 10.0, 50.0, 30.0);
+
+        let t = TransformList::parse_str("translate(1in, 50%)").unwrap();
+        assert_transform_eq(
+            &t.to_transform(&params, &reference_box),
+            &Transform::new_translate(96.0, 10.0),
+        );
+
+        let t = TransformList::parse_str("translateX(25%) translateY(0.5in)").unwrap();
+        assert_transform_eq(
+            &t.to_transform(&params, &reference_box),
+            &Transform::new_translate(10.0, 48.0),
+        );
+    }
+
     #[test]
     fn test_parse_transform_property_scale() {
         let tps = TransformProperty::List(vec![TransformFunction::Scale(1.0, 10.0)]);
@@ -3,8 +3,7 @@
 use cssparser::Parser;
 
 use crate::error::*;
-use crate::length::*;
-use crate::parsers::Parse;
+use crate::parsers::{NumberOrPercentage, Parse};
 use crate::util;
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, PartialOrd)]
@@ -17,15 +16,12 @@ impl UnitInterval {
 }
 
 impl Parse for UnitInterval {
+    /// Parses an `<alpha-value>`, which is a number or a percentage, and clamps it.
+    ///
+    /// CSS Color 4: <https://www.w3.org/TR/css-color-4/#typedef-alpha-value>
     fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<UnitInterval, ParseError<'i>> {
-        let loc = parser.current_source_location();
-        let l: Length<Both> = Parse::parse(parser)?;
-        match l.unit {
-            LengthUnit::Px | LengthUnit::Percent => Ok(UnitInterval::clamp(l.length)),
-            _ => Err(loc.new_custom_error(ValueErrorKind::value_error(
-                "<unit-interval> must be in default or percent units",
-            ))),
-        }
+        let NumberOrPercentage { value } = Parse::parse(parser)?;
+        Ok(UnitInterval::clamp(value))
     }
 }
 
@@ -85,6 +81,12 @@ mod tests {
         assert!(UnitInterval::parse_str("%").is_err());
     }
 
+    #[test]
+    fn errors_on_dimensions() {
+        assert!(UnitInterval::parse_str("0.5px").is_err());
+        assert!(UnitInterval::parse_str("1em").is_err());
+    }
+
     #[test]
     fn convert() {
         assert_eq!(u8::from(UnitInterval(0.0)), 0);
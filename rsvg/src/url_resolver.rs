@@ -2,9 +2,11 @@
 
 use std::fmt;
 use std::ops::Deref;
+use std::sync::Arc;
 use url::Url;
 
 use crate::error::AllowedUrlError;
+use crate::io::ResourceLoader;
 
 /// Decides which URLs are allowed to be loaded.
 ///
@@ -21,12 +23,39 @@ use crate::error::AllowedUrlError;
 pub struct UrlResolver {
     /// Base URL; all relative references will be resolved with respect to this.
     pub base_url: Option<Url>,
+
+    /// Supplies the contents of allowed URLs before librsvg tries to read them.
+    pub resource_loader: Option<Arc<dyn ResourceLoader>>,
+
+    /// Whether URLs other than `data:` ones are allowed at all.
+    pub external_resources: bool,
 }
 
 impl UrlResolver {
     /// Creates a `UrlResolver` with defaults, and sets the `base_url`.
     pub fn new(base_url: Option<Url>) -> Self {
-        UrlResolver { base_url }
+        UrlResolver {
+            base_url,
+            resource_loader: None,
+            external_resources: true,
+        }
+    }
+
+    /// Sets the loader that supplies the contents of allowed URLs.
+    pub fn with_resource_loader(mut self, loader: Option<Arc<dyn ResourceLoader>>) -> Self {
+        self.resource_loader = loader;
+        self
+    }
+
+    /// Sets whether URLs other than `data:` ones are allowed to be loaded.
+    pub fn with_external_resources(mut self, enable: bool) -> Self {
+        self.external_resources = enable;
+        self
+    }
+
+    /// Returns the loader to pass to the functions in [`crate::io`].
+    pub fn resource_loader(&self) -> Option<&dyn ResourceLoader> {
+        self.resource_loader.as_deref()
     }
 
     /// Decides which URLs are allowed to be loaded based on the presence of a base URL.
@@ -45,6 +74,10 @@ impl UrlResolver {
             return Ok(AllowedUrl(url));
         }
 
+        if !self.external_resources {
+            return Err(AllowedUrlError::ExternalResourcesDisabled);
+        }
+
         // Queries are not allowed.
         if url.query().is_some() {
             return Err(AllowedUrlError::NoQueriesAllowed);
@@ -192,6 +225,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn disallows_everything_but_data_without_external_resources() {
+        let url_resolver = UrlResolver::new(Some(
+            Url::parse(&make_file_uri("/example/bar.svg")).unwrap(),
+        ))
+        .with_external_resources(false);
+
+        assert!(matches!(
+            url_resolver.resolve_href("foo.svg"),
+            Err(AllowedUrlError::ExternalResourcesDisabled)
+        ));
+
+        assert!(url_resolver
+            .resolve_href("data:image/jpeg;base64,xxyyzz")
+            .is_ok());
+    }
+
     #[test]
     fn disallows_base_is_root() {
         let url_resolver = UrlResolver::new(Some(Url::parse(&make_file_uri("/")).unwrap()));
@@ -155,6 +155,29 @@ impl Attributes {
     pub fn clear_class(&mut self) {
         self.class_idx = None;
     }
+
+    /// Sets the value of an attribute, replacing an existing one with the same namespace
+    /// and local name, or appending it otherwise.
+    ///
+    /// This does not update the `id` or `class` indices, so it must not be used for
+    /// those attributes.
+    pub fn set(&mut self, name: QualName, value: &str) {
+        let value = AttributeValue::from(value);
+
+        match self
+            .attrs
+            .iter_mut()
+            .find(|(n, _)| n.ns == name.ns && n.local == name.local)
+        {
+            Some((_, v)) => *v = value,
+
+            None => {
+                let mut attrs = std::mem::take(&mut self.attrs).into_vec();
+                attrs.push((name, value));
+                self.attrs = attrs.into();
+            }
+        }
+    }
 }
 
 impl<'a> Iterator for AttributesIter<'a> {
@@ -3,13 +3,13 @@
 use encoding_rs::Encoding;
 use gio::{
     prelude::BufferedInputStreamExt, BufferedInputStream, Cancellable, ConverterInputStream,
-    InputStream, ZlibCompressorFormat, ZlibDecompressor,
+    InputStream, MemoryInputStream, ZlibCompressorFormat, ZlibDecompressor,
 };
 use glib::Cast;
 use markup5ever::{
     expanded_name, local_name, namespace_url, ns, ExpandedName, LocalName, Namespace, QualName,
 };
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::str;
@@ -24,9 +24,10 @@ use crate::css::{Origin, Stylesheet};
 use crate::document::{Document, DocumentBuilder, LoadOptions};
 use crate::error::{ImplementationLimit, LoadingError};
 use crate::io::{self, IoError};
-use crate::limits::{MAX_LOADED_ELEMENTS, MAX_XINCLUDE_DEPTH};
+use crate::is_element_of_type;
+use crate::limits::MAX_XINCLUDE_DEPTH;
 use crate::node::{Node, NodeBorrow};
-use crate::rsvg_log;
+use crate::rsvg_warn;
 use crate::session::Session;
 use crate::style::StyleType;
 use crate::url_resolver::AllowedUrl;
@@ -34,10 +35,12 @@ use crate::url_resolver::AllowedUrl;
 use xml2_load::Xml2Parser;
 
 mod attributes;
+mod serialize;
 mod xml2;
 mod xml2_load;
 
 pub use attributes::Attributes;
+pub use serialize::{write_document, write_element_contents};
 
 #[derive(Clone)]
 enum Context {
@@ -114,6 +117,7 @@ struct XmlStateInner {
     //
     // An XmlState is finally consumed in XmlState::build_document(), and that
     // function is responsible for freeing all the XmlEntityPtr from this field.
+    // An XmlPushLoader that gets dropped before it is closed frees them itself.
     //
     // (The structs cannot impl Drop because build_document()
     // destructures and consumes them at the same time.)
@@ -125,6 +129,10 @@ pub struct XmlState {
 
     session: Session,
     load_options: Arc<LoadOptions>,
+
+    // Number of bytes of XML that have been read so far, including from xi:include.
+    // This is updated from the I/O callbacks for libxml2, so it lives outside of `inner`.
+    num_bytes_read: Rc<Cell<usize>>,
 }
 
 /// Errors returned from XmlState::acquire()
@@ -164,6 +172,7 @@ impl XmlState {
 
             session,
             load_options,
+            num_bytes_read: Rc::new(Cell::new(0)),
         }
     }
 
@@ -177,10 +186,27 @@ impl XmlState {
     }
 
     fn check_limits(&self) -> Result<(), ()> {
-        if self.inner.borrow().num_loaded_elements > MAX_LOADED_ELEMENTS {
-            self.error(LoadingError::LimitExceeded(
-                ImplementationLimit::TooManyLoadedElements,
-            ));
+        let limits = &self.load_options.limits;
+
+        let exceeded = {
+            let inner = self.inner.borrow();
+
+            // The context stack has one entry per open element, plus the initial one.
+            let depth = inner.context_stack.len();
+
+            if inner.num_loaded_elements > limits.max_elements {
+                Some(ImplementationLimit::TooManyLoadedElements(
+                    limits.max_elements,
+                ))
+            } else if limits.max_nesting_depth.is_some_and(|max| depth > max) {
+                Some(ImplementationLimit::TooDeeplyNested)
+            } else {
+                None
+            }
+        };
+
+        if let Some(limit) = exceeded {
+            self.error(LoadingError::LimitExceeded(limit));
             Err(())
         } else {
             Ok(())
@@ -216,6 +242,13 @@ impl XmlState {
 
         self.inner.borrow_mut().context_stack.push(new_context);
 
+        if self.load_options.strict {
+            if let Some(warning) = self.session.take_strict_error() {
+                self.error(LoadingError::Invalid(warning));
+                return Err(());
+            }
+        }
+
         Ok(())
     }
 
@@ -288,7 +321,7 @@ impl XmlState {
             if type_.as_deref() != Some("text/css")
                 || (alternate.is_some() && alternate.as_deref() != Some("no"))
             {
-                rsvg_log!(
+                rsvg_warn!(
                     self.session,
                     "invalid parameters in XML processing instruction for stylesheet",
                 );
@@ -297,28 +330,31 @@ impl XmlState {
 
             if let Some(href) = href {
                 if let Ok(aurl) = self.load_options.url_resolver.resolve_href(&href) {
-                    if let Ok(stylesheet) =
-                        Stylesheet::from_href(&aurl, Origin::Author, self.session.clone())
-                    {
+                    if let Ok(stylesheet) = Stylesheet::from_href(
+                        &aurl,
+                        &self.load_options.url_resolver,
+                        Origin::Author,
+                        self.session.clone(),
+                    ) {
                         inner.document_builder.append_stylesheet(stylesheet);
                     } else {
                         // FIXME: https://www.w3.org/TR/xml-stylesheet/ does not seem to specify
                         // what to do if the stylesheet cannot be loaded, so here we ignore the error.
-                        rsvg_log!(
+                        rsvg_warn!(
                             self.session,
                             "could not create stylesheet from {} in XML processing instruction",
                             href
                         );
                     }
                 } else {
-                    rsvg_log!(
+                    rsvg_warn!(
                         self.session,
                         "{} not allowed for xml-stylesheet in XML processing instruction",
                         href
                     );
                 }
             } else {
-                rsvg_log!(
+                rsvg_warn!(
                     self.session,
                     "xml-stylesheet processing instruction does not have href; ignoring"
                 );
@@ -394,6 +430,7 @@ impl XmlState {
         let current_node = inner.current_node.as_ref().unwrap();
 
         let style_type = borrow_element_as!(current_node, Style).style_type();
+        let scope = style_scope(current_node);
 
         if style_type == StyleType::TextCss {
             let stylesheet_text = current_node
@@ -412,9 +449,15 @@ impl XmlState {
                 Origin::Author,
                 self.session.clone(),
             ) {
-                inner.document_builder.append_stylesheet(stylesheet);
+                if let Some(scope) = scope {
+                    inner
+                        .document_builder
+                        .append_scoped_stylesheet(scope, stylesheet);
+                } else {
+                    inner.document_builder.append_stylesheet(stylesheet);
+                }
             } else {
-                rsvg_log!(self.session, "invalid inline stylesheet");
+                rsvg_warn!(self.session, "invalid inline stylesheet");
             }
         }
     }
@@ -521,7 +564,7 @@ impl XmlState {
                 .map_err(|e| {
                     // FIXME: should AlloweUrlError::UrlParseError be a fatal error,
                     // not a resource error?
-                    rsvg_log!(self.session, "could not acquire \"{}\": {}", href, e);
+                    rsvg_warn!(self.session, "could not acquire \"{}\": {}", href, e);
                     AcquireError::ResourceError
                 })?;
 
@@ -578,8 +621,9 @@ impl XmlState {
     }
 
     fn acquire_text(&self, aurl: &AllowedUrl, encoding: Option<&str>) -> Result<(), AcquireError> {
-        let binary = io::acquire_data(aurl, None).map_err(|e| {
-            rsvg_log!(self.session, "could not acquire \"{}\": {}", aurl, e);
+        let url_resolver = &self.load_options.url_resolver;
+        let binary = io::acquire_data(aurl, url_resolver.resource_loader(), None).map_err(|e| {
+            rsvg_warn!(self.session, "could not acquire \"{}\": {}", aurl, e);
             AcquireError::ResourceError
         })?;
 
@@ -602,15 +646,21 @@ impl XmlState {
     fn acquire_xml(&self, aurl: &AllowedUrl) -> Result<(), AcquireError> {
         // FIXME: distinguish between "file not found" and "invalid XML"
 
-        let stream = io::acquire_stream(aurl, None).map_err(|e| match e {
-            IoError::BadDataUrl => AcquireError::FatalError(String::from("malformed data: URL")),
-            _ => AcquireError::ResourceError,
-        })?;
+        let url_resolver = &self.load_options.url_resolver;
+        let stream = io::acquire_stream(aurl, url_resolver.resource_loader(), None).map_err(
+            |e| match e {
+                IoError::BadDataUrl => {
+                    AcquireError::FatalError(String::from("malformed data: URL"))
+                }
+                _ => AcquireError::ResourceError,
+            },
+        )?;
 
         // FIXME: pass a cancellable
         self.parse_from_stream(&stream, None).map_err(|e| match e {
             LoadingError::Io(_) => AcquireError::ResourceError,
             LoadingError::XmlParseError(s) => AcquireError::FatalError(s),
+            LoadingError::LimitExceeded(l) => AcquireError::FatalError(format!("{l}")),
             _ => AcquireError::FatalError(String::from("unknown error")),
         })
     }
@@ -624,7 +674,14 @@ impl XmlState {
         stream: &gio::InputStream,
         cancellable: Option<&gio::Cancellable>,
     ) -> Result<(), LoadingError> {
-        Xml2Parser::from_stream(self, self.load_options.unlimited_size, stream, cancellable)
+        Xml2Parser::from_stream(self, stream, cancellable)
+            .and_then(|parser| parser.parse())
+            .and_then(|_: ()| self.check_last_error())
+    }
+
+    // Parses XML from a byte slice into an XmlState.
+    fn parse_from_bytes(&self, bytes: &[u8]) -> Result<(), LoadingError> {
+        Xml2Parser::from_bytes(self, bytes)
             .and_then(|parser| parser.parse())
             .and_then(|_: ()| self.check_last_error())
     }
@@ -633,31 +690,53 @@ impl XmlState {
         Context::UnsupportedXIncludeChild
     }
 
-    fn build_document(
-        self,
-        stream: &gio::InputStream,
-        cancellable: Option<&gio::Cancellable>,
-    ) -> Result<Document, LoadingError> {
-        self.parse_from_stream(stream, cancellable)?;
+    fn build_document<F>(self, parse: F) -> Result<Document, LoadingError>
+    where
+        F: FnOnce(&XmlState) -> Result<(), LoadingError>,
+    {
+        parse(&self)?;
+
+        // Warnings from here on don't come from a particular place in the XML
+        self.session.set_location(None);
+
+        // Free the hash of XmlEntityPtr.  We cannot do this in Drop because we will
+        // consume inner by destructuring it below.
+        self.free_entities();
 
         // consume self, then consume inner, then consume document_builder by calling .build()
 
         let XmlState { inner, .. } = self;
-        let mut inner = inner.into_inner();
 
-        // Free the hash of XmlEntityPtr.  We cannot do this in Drop because we will
-        // consume inner by destructuring it after the for() loop.
-        for (_key, entity) in inner.entities.drain() {
+        let XmlStateInner {
+            document_builder, ..
+        } = inner.into_inner();
+        document_builder.build()
+    }
+
+    fn free_entities(&self) {
+        for (_key, entity) in self.inner.borrow_mut().entities.drain() {
             unsafe {
                 xmlFreeNode(entity);
             }
         }
+    }
+}
 
-        let XmlStateInner {
-            document_builder, ..
-        } = inner;
-        document_builder.build()
+/// Finds the element to whose subtree the rules in a `<style>` element should be limited.
+///
+/// A `<style scoped>` applies to its parent element.  A `<style>` inside a `<symbol>` is
+/// scoped to the symbol automatically, so that sprites can carry their own styles without
+/// affecting the document that uses them.  Other `<style>` elements apply to the whole
+/// document, and this returns `None` for them.
+fn style_scope(style_node: &Node) -> Option<Node> {
+    if borrow_element_as!(style_node, Style).is_scoped() {
+        return style_node.parent();
     }
+
+    style_node
+        .ancestors()
+        .skip(1)
+        .find(|n| is_element_of_type!(n, Symbol))
 }
 
 /// Temporary holding space for data in an XML processing instruction
@@ -734,7 +813,213 @@ pub fn xml_load_from_possibly_compressed_stream(
 
     let stream = get_input_stream_for_loading(stream, cancellable)?;
 
-    state.build_document(&stream, cancellable)
+    state.build_document(|state| state.parse_from_stream(&stream, cancellable))
+}
+
+/// Loads an SVG document from a byte slice.
+///
+/// Uncompressed data gets parsed in place.  Gzipped data (svgz) needs to be decompressed
+/// anyway, so it goes through the same path as streams.
+pub fn xml_load_from_bytes(
+    session: Session,
+    document_builder: DocumentBuilder,
+    load_options: Arc<LoadOptions>,
+    bytes: &[u8],
+) -> Result<Document, LoadingError> {
+    if bytes.len() < 2 {
+        return Err(LoadingError::XmlParseError(String::from(
+            "Input file is too short",
+        )));
+    }
+
+    if bytes[0..2] == [GZ_MAGIC_0, GZ_MAGIC_1] {
+        let stream = MemoryInputStream::from_bytes(&glib::Bytes::from(bytes));
+
+        return xml_load_from_possibly_compressed_stream(
+            session,
+            document_builder,
+            load_options,
+            stream.upcast_ref(),
+            None,
+        );
+    }
+
+    let state = XmlState::new(session, document_builder, load_options);
+
+    state.build_document(|state| state.parse_from_bytes(bytes))
+}
+
+/// Loads an SVG document from data that arrives in chunks.
+///
+/// Uncompressed XML gets parsed as each chunk arrives, so the data does not need to be
+/// kept in memory and errors are found as soon as possible.  Gzipped data (svgz) gets
+/// buffered, and it is decompressed and parsed when the loader is closed.
+pub struct XmlPushLoader {
+    state: PushState,
+}
+
+/// What an [`XmlPushLoader`] needs to create its [`XmlState`].
+struct PendingLoad {
+    session: Session,
+    document_builder: DocumentBuilder,
+    load_options: Arc<LoadOptions>,
+}
+
+enum PushState {
+    /// Waiting for the first two bytes, to find out if the data is gzipped.
+    Start(PendingLoad, Vec<u8>),
+
+    /// Parsing uncompressed XML.
+    ///
+    /// The parser refers to the `XmlState`, so the state is owned through a pointer from
+    /// `Box::into_raw()` instead of a `Box`, which would claim unique access to it whenever
+    /// the loader moves.  It gets freed after the parser, in `close()` or `discard()`.
+    Parsing(Box<Xml2Parser<'static>>, *mut XmlState),
+
+    /// Buffering gzipped data until the loader is closed.
+    Compressed(PendingLoad, Vec<u8>),
+
+    /// Loading failed, or the loader was closed.
+    Done,
+}
+
+impl XmlPushLoader {
+    pub fn new(
+        session: Session,
+        document_builder: DocumentBuilder,
+        load_options: Arc<LoadOptions>,
+    ) -> XmlPushLoader {
+        let pending = PendingLoad {
+            session,
+            document_builder,
+            load_options,
+        };
+
+        XmlPushLoader {
+            state: PushState::Start(pending, Vec::new()),
+        }
+    }
+
+    /// Feeds the next chunk of data to the loader.
+    ///
+    /// Once this returns an error, the loader cannot be used anymore.
+    pub fn write(&mut self, buf: &[u8]) -> Result<(), LoadingError> {
+        let result = self.write_chunk(buf);
+
+        if result.is_err() {
+            self.discard();
+        }
+
+        result
+    }
+
+    /// Finishes parsing the data and builds the document.
+    pub fn close(mut self) -> Result<Document, LoadingError> {
+        match std::mem::replace(&mut self.state, PushState::Done) {
+            PushState::Start(pending, buffer) | PushState::Compressed(pending, buffer) => {
+                xml_load_from_bytes(
+                    pending.session,
+                    pending.document_builder,
+                    pending.load_options,
+                    &buffer,
+                )
+            }
+
+            PushState::Parsing(parser, state) => {
+                // SAFETY: the state is only freed below, once the parser is gone.
+                let result = parser
+                    .parse_chunk(&[], true)
+                    .and_then(|_: ()| unsafe { &*state }.check_last_error());
+                drop(parser);
+
+                let state = *unsafe { Box::from_raw(state) };
+                state.build_document(|_| result)
+            }
+
+            PushState::Done => Err(LoadingError::Other(String::from(
+                "the document could not be loaded",
+            ))),
+        }
+    }
+
+    fn write_chunk(&mut self, buf: &[u8]) -> Result<(), LoadingError> {
+        match self.state {
+            PushState::Start(_, ref mut buffer) => {
+                buffer.extend_from_slice(buf);
+
+                if buffer.len() < 2 {
+                    return Ok(());
+                }
+
+                let PushState::Start(pending, buffer) =
+                    std::mem::replace(&mut self.state, PushState::Done)
+                else {
+                    unreachable!();
+                };
+
+                if buffer[0..2] == [GZ_MAGIC_0, GZ_MAGIC_1] {
+                    self.state = PushState::Compressed(pending, buffer);
+                    return Ok(());
+                }
+
+                let state = Box::into_raw(Box::new(XmlState::new(
+                    pending.session,
+                    pending.document_builder,
+                    pending.load_options,
+                )));
+
+                // SAFETY: the state is only freed after the parser, and nothing takes a
+                // mutable reference to it while the parser exists.
+                let parser = match Xml2Parser::for_push(unsafe { &*state }) {
+                    Ok(parser) => parser,
+                    Err(e) => {
+                        drop(unsafe { Box::from_raw(state) });
+                        return Err(e);
+                    }
+                };
+
+                let result = parser
+                    .parse_chunk(&buffer, false)
+                    .and_then(|_: ()| unsafe { &*state }.check_last_error());
+
+                self.state = PushState::Parsing(parser, state);
+                result
+            }
+
+            // SAFETY: see PushState::Parsing.
+            PushState::Parsing(ref parser, state) => parser
+                .parse_chunk(buf, false)
+                .and_then(|_: ()| unsafe { &*state }.check_last_error()),
+
+            PushState::Compressed(_, ref mut buffer) => {
+                buffer.extend_from_slice(buf);
+                Ok(())
+            }
+
+            PushState::Done => Err(LoadingError::Other(String::from(
+                "the document could not be loaded",
+            ))),
+        }
+    }
+
+    /// Frees the parser and the partially-loaded document.
+    fn discard(&mut self) {
+        if let PushState::Parsing(parser, state) =
+            std::mem::replace(&mut self.state, PushState::Done)
+        {
+            drop(parser);
+
+            // SAFETY: the parser, which referred to the state, is gone.
+            let state = unsafe { Box::from_raw(state) };
+            state.free_entities();
+        }
+    }
+}
+
+impl Drop for XmlPushLoader {
+    fn drop(&mut self) {
+        self.discard();
+    }
 }
 
 // Header of a gzip data stream
@@ -0,0 +1,401 @@
+//! Serialization of a loaded document back to XML.
+//!
+//! This writes out the tree of nodes as librsvg parsed it: elements with their attributes
+//! in their original order, and text content.  Things which are not part of the tree, like
+//! comments, processing instructions, DTD declarations, and `<xi:include>` elements (which
+//! get replaced with the included content at load time), are not emitted.
+
+use markup5ever::{namespace_url, ns, Namespace, QualName};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::node::{Node, NodeBorrow};
+
+/// Writes `root` and all its descendants as a standalone XML document.
+///
+/// If `stylesheet` is present, it is written as a `<style>` element at the beginning of the
+/// root element, so that it applies to the whole document when it is loaded again.
+pub fn write_document<W: Write>(
+    root: &Node,
+    stylesheet: Option<&str>,
+    writer: &mut W,
+) -> io::Result<()> {
+    let prefixes = Prefixes::for_tree(root);
+
+    writer.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n")?;
+
+    Serializer { writer, prefixes }.write_root(root, stylesheet)
+}
+
+/// Writes the children of `node` as an XML fragment, without `node`'s own tags.
+///
+/// Each top-level element in the fragment declares the namespaces used in the fragment,
+/// so that it can be parsed on its own.
+pub fn write_element_contents<W: Write>(node: &Node, writer: &mut W) -> io::Result<()> {
+    let mut prefixes = Prefixes::for_tree(node);
+
+    // The node itself is not written, so its namespace doesn't need a declaration.
+    let name = node.borrow_element().element_name().clone();
+    if name.ns != ns!(svg)
+        && !node
+            .descendants()
+            .skip(1)
+            .any(|n| uses_namespace(&n, &name.ns))
+    {
+        prefixes.remove(&name.ns);
+    }
+
+    let mut serializer = Serializer { writer, prefixes };
+
+    for child in node.children() {
+        if child.is_chars() {
+            serializer.write_node(&child)?;
+        } else {
+            serializer.write_fragment_root(&child)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn uses_namespace(node: &Node, namespace: &Namespace) -> bool {
+    if !node.is_element() {
+        return false;
+    }
+
+    let element = node.borrow_element();
+
+    element.element_name().ns == *namespace
+        || element
+            .get_attributes()
+            .iter()
+            .any(|(attr, _)| attr.ns == *namespace)
+}
+
+/// Namespace prefixes to use in the output.
+///
+/// Elements in the SVG namespace are written without a prefix, since it is declared as
+/// the default namespace in the root element.  All other namespaces get a prefix which
+/// is declared in the root element as well.
+struct Prefixes {
+    /// Namespaces that need an `xmlns:prefix` declaration, in the order in which they
+    /// were found in the document.
+    declared: Vec<(String, Namespace)>,
+
+    by_namespace: HashMap<Namespace, String>,
+}
+
+impl Prefixes {
+    fn for_tree(root: &Node) -> Prefixes {
+        let mut prefixes = Prefixes {
+            declared: Vec::new(),
+            by_namespace: HashMap::new(),
+        };
+
+        for node in root.descendants().filter(|n| n.is_element()) {
+            let element = node.borrow_element();
+
+            let name = element.element_name();
+            if name.ns != ns!(svg) {
+                prefixes.add(name);
+            }
+
+            for (attr, _) in element.get_attributes().iter() {
+                if attr.ns != ns!() && attr.ns != ns!(xml) {
+                    prefixes.add(&attr);
+                }
+            }
+        }
+
+        prefixes
+    }
+
+    fn add(&mut self, name: &QualName) {
+        if self.by_namespace.contains_key(&name.ns) {
+            return;
+        }
+
+        let preferred = if name.ns == ns!(xlink) {
+            Some("xlink".to_string())
+        } else {
+            name.prefix.as_ref().map(|p| p.to_string())
+        };
+
+        let prefix = preferred
+            .filter(|p| p != "xml" && p != "xmlns" && !self.is_taken(p))
+            .unwrap_or_else(|| {
+                (1..)
+                    .map(|i| format!("ns{i}"))
+                    .find(|p| !self.is_taken(p))
+                    .unwrap()
+            });
+
+        self.declared.push((prefix.clone(), name.ns.clone()));
+        self.by_namespace.insert(name.ns.clone(), prefix);
+    }
+
+    fn remove(&mut self, namespace: &Namespace) {
+        self.declared.retain(|(_, ns)| ns != namespace);
+        self.by_namespace.remove(namespace);
+    }
+
+    fn is_taken(&self, prefix: &str) -> bool {
+        self.declared.iter().any(|(p, _)| p == prefix)
+    }
+
+    fn element_name(&self, name: &QualName) -> String {
+        if name.ns == ns!(svg) {
+            name.local.to_string()
+        } else {
+            self.prefixed(name)
+        }
+    }
+
+    fn attribute_name(&self, name: &QualName) -> String {
+        if name.ns == ns!() {
+            name.local.to_string()
+        } else if name.ns == ns!(xml) {
+            format!("xml:{}", name.local)
+        } else {
+            self.prefixed(name)
+        }
+    }
+
+    fn prefixed(&self, name: &QualName) -> String {
+        format!("{}:{}", self.by_namespace[&name.ns], name.local)
+    }
+}
+
+struct Serializer<'a, W: Write> {
+    writer: &'a mut W,
+    prefixes: Prefixes,
+}
+
+impl<'a, W: Write> Serializer<'a, W> {
+    fn write_root(&mut self, root: &Node, stylesheet: Option<&str>) -> io::Result<()> {
+        let name = self.write_start_tag(root)?;
+
+        write!(self.writer, " xmlns=\"{}\"", ns!(svg))?;
+        self.write_prefix_declarations()?;
+
+        if stylesheet.is_none() && root.first_child().is_none() {
+            return write!(self.writer, "/>");
+        }
+
+        write!(self.writer, ">")?;
+
+        if let Some(css) = stylesheet {
+            write!(self.writer, "<style type=\"text/css\">")?;
+            write_escaped(self.writer, css, false)?;
+            write!(self.writer, "</style>")?;
+        }
+
+        self.write_children(root)?;
+
+        write!(self.writer, "</{}>", name)
+    }
+
+    /// Writes a top-level element of a fragment, declaring the namespaces it may use.
+    fn write_fragment_root(&mut self, node: &Node) -> io::Result<()> {
+        let name = self.write_start_tag(node)?;
+
+        if node
+            .descendants()
+            .filter(|n| n.is_element())
+            .any(|n| n.borrow_element().element_name().ns == ns!(svg))
+        {
+            write!(self.writer, " xmlns=\"{}\"", ns!(svg))?;
+        }
+
+        self.write_prefix_declarations()?;
+
+        if node.first_child().is_none() {
+            return write!(self.writer, "/>");
+        }
+
+        write!(self.writer, ">")?;
+        self.write_children(node)?;
+        write!(self.writer, "</{}>", name)
+    }
+
+    fn write_prefix_declarations(&mut self) -> io::Result<()> {
+        for (prefix, namespace) in &self.prefixes.declared {
+            write!(self.writer, " xmlns:{}=\"", prefix)?;
+            write_escaped(self.writer, namespace, true)?;
+            write!(self.writer, "\"")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_node(&mut self, node: &Node) -> io::Result<()> {
+        if node.is_chars() {
+            return write_escaped(self.writer, &node.borrow_chars().get_string(), false);
+        }
+
+        let name = self.write_start_tag(node)?;
+
+        if node.first_child().is_none() {
+            return write!(self.writer, "/>");
+        }
+
+        write!(self.writer, ">")?;
+        self.write_children(node)?;
+        write!(self.writer, "</{}>", name)
+    }
+
+    fn write_children(&mut self, node: &Node) -> io::Result<()> {
+        for child in node.children() {
+            self.write_node(&child)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the opening `<name attr="value" ...` of an element, without closing the tag.
+    ///
+    /// Returns the element's name so the caller can write the end tag.
+    fn write_start_tag(&mut self, node: &Node) -> io::Result<String> {
+        let element = node.borrow_element();
+        let name = self.prefixes.element_name(element.element_name());
+
+        write!(self.writer, "<{}", name)?;
+
+        for (attr, value) in element.get_attributes().iter() {
+            write!(self.writer, " {}=\"", self.prefixes.attribute_name(&attr))?;
+            write_escaped(self.writer, value, true)?;
+            write!(self.writer, "\"")?;
+        }
+
+        Ok(name)
+    }
+}
+
+/// Writes `s` with the characters that are special in XML replaced by references.
+///
+/// For attribute values, whitespace other than plain spaces is escaped as well, so that
+/// it does not get normalized to spaces when the document is parsed again.
+fn write_escaped<W: Write>(writer: &mut W, s: &str, in_attribute: bool) -> io::Result<()> {
+    let mut rest = s;
+
+    while let Some(pos) = rest.find(|c| needs_escaping(c, in_attribute)) {
+        let (plain, special) = rest.split_at(pos);
+        writer.write_all(plain.as_bytes())?;
+
+        let c = special.chars().next().unwrap();
+        let escaped = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            '\t' => "&#9;",
+            '\n' => "&#10;",
+            '\r' => "&#13;",
+            _ => unreachable!(),
+        };
+        writer.write_all(escaped.as_bytes())?;
+
+        rest = &special[c.len_utf8()..];
+    }
+
+    writer.write_all(rest.as_bytes())
+}
+
+fn needs_escaping(c: char, in_attribute: bool) -> bool {
+    match c {
+        '&' | '<' | '>' => true,
+        '"' | '\t' | '\n' => in_attribute,
+        '\r' => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::document::Document;
+
+    fn serialize(input: &'static [u8], stylesheet: Option<&str>) -> String {
+        let document = Document::load_from_bytes(input);
+
+        let mut output = Vec::new();
+        write_document(&document.root(), stylesheet, &mut output).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        let mut output = Vec::new();
+        write_escaped(&mut output, "a<b & \"c\"\n", true).unwrap();
+        assert_eq!(output, b"a&lt;b &amp; &quot;c&quot;&#10;");
+
+        let mut output = Vec::new();
+        write_escaped(&mut output, "a > \"b\"\n", false).unwrap();
+        assert_eq!(output, b"a &gt; \"b\"\n");
+    }
+
+    #[test]
+    fn writes_elements_attributes_and_text() {
+        let output = serialize(
+            br##"<?xml version="1.0" encoding="UTF-8"?>
+<!-- a comment -->
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="10" height="10"><rect id="a" width="5" height="5"/><use xlink:href="#a" x="5"/><text xml:space="preserve">1 &lt; 2</text></svg>"##,
+            None,
+        );
+
+        assert_eq!(
+            output,
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg width="10" height="10" xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><rect id="a" width="5" height="5"/><use xlink:href="#a" x="5"/><text xml:space="preserve">1 &lt; 2</text></svg>"##
+        );
+    }
+
+    #[test]
+    fn declares_foreign_namespaces() {
+        let output = serialize(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:foo="http://example.com/foo"><foo:bar foo:baz="1"/></svg>"#,
+            None,
+        );
+
+        assert_eq!(
+            output,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:foo="http://example.com/foo"><foo:bar foo:baz="1"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn writes_element_contents_as_fragment() {
+        let document = Document::load_from_bytes(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<metadata id="m"> <rdf:RDF><rdf:Description dc:title="A &amp; B"/></rdf:RDF> </metadata>
+</svg>"#,
+        );
+
+        let metadata = document.lookup_internal_node("m").unwrap();
+
+        let mut output = Vec::new();
+        write_element_contents(&metadata, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            r#" <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:dc="http://purl.org/dc/elements/1.1/"><rdf:Description dc:title="A &amp; B"/></rdf:RDF> "#
+        );
+    }
+
+    #[test]
+    fn writes_stylesheet_as_first_child() {
+        let output = serialize(
+            br#"<svg xmlns="http://www.w3.org/2000/svg"/>"#,
+            Some("svg > rect { fill: blue; }"),
+        );
+
+        assert_eq!(
+            output,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg"><style type="text/css">svg &gt; rect { fill: blue; }</style></svg>"#
+        );
+    }
+}
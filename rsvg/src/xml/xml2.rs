@@ -189,6 +189,26 @@ extern "C" {
         enc: xmlCharEncoding,
     ) -> xmlParserCtxtPtr;
 
+    pub fn xmlCreateMemoryParserCtxt(
+        buffer: *const libc::c_char,
+        size: libc::c_int,
+    ) -> xmlParserCtxtPtr;
+
+    pub fn xmlCreatePushParserCtxt(
+        sax: xmlSAXHandlerPtr,
+        user_data: *mut libc::c_void,
+        chunk: *const libc::c_char,
+        size: libc::c_int,
+        filename: *const libc::c_char,
+    ) -> xmlParserCtxtPtr;
+
+    pub fn xmlParseChunk(
+        ctxt: xmlParserCtxtPtr,
+        chunk: *const libc::c_char,
+        size: libc::c_int,
+        terminate: libc::c_int,
+    ) -> libc::c_int;
+
     pub fn xmlStopParser(ctxt: xmlParserCtxtPtr);
 
     pub fn xmlParseDocument(ctxt: xmlParserCtxtPtr) -> libc::c_int;
@@ -199,6 +219,10 @@ extern "C" {
 
     pub fn xmlCtxtGetLastError(ctxt: *mut libc::c_void) -> xmlErrorPtr;
 
+    pub fn xmlSAX2GetLineNumber(ctx: *mut libc::c_void) -> libc::c_int;
+
+    pub fn xmlSAX2GetColumnNumber(ctx: *mut libc::c_void) -> libc::c_int;
+
     pub fn xmlCtxtUseOptions(ctxt: xmlParserCtxtPtr, options: libc::c_int) -> libc::c_int;
 
     pub fn xmlNewEntity(
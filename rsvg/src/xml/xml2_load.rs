@@ -1,7 +1,8 @@
 //! Glue between the libxml2 API and our xml parser module.
 //!
 //! This file provides functions to create a libxml2 xmlParserCtxtPtr, configured
-//! to read from a gio::InputStream, and to maintain its loading data in an XmlState.
+//! to read from a gio::InputStream or from a byte slice, and to maintain its loading
+//! data in an XmlState.
 
 use gio::prelude::*;
 use std::borrow::Cow;
@@ -15,7 +16,8 @@ use std::sync::Once;
 use glib::translate::*;
 use markup5ever::{namespace_url, ns, LocalName, Namespace, Prefix, QualName};
 
-use crate::error::LoadingError;
+use crate::error::{ImplementationLimit, LoadingError};
+use crate::log::Location;
 use crate::util::{cstr, opt_utf8_cstr, utf8_cstr};
 
 use super::xml2::*;
@@ -226,6 +228,8 @@ unsafe extern "C" fn sax_start_element_ns_cb(
             }
         };
 
+    xml2_parser.update_location();
+
     // This clippy::let_unit_value is for the "let _: () = e" guard below.
     #[allow(clippy::let_unit_value)]
     if let Err(e) = xml2_parser.state.start_element(qual_name, attrs) {
@@ -252,6 +256,7 @@ unsafe extern "C" fn sax_end_element_ns_cb(
 
     let qual_name = make_qual_name(prefix, uri, localname);
 
+    xml2_parser.update_location();
     xml2_parser.state.end_element(qual_name);
 }
 
@@ -285,6 +290,7 @@ unsafe extern "C" fn sax_processing_instruction_cb(
 
     let data = if data.is_null() { "" } else { utf8_cstr(data) };
 
+    xml2_parser.update_location();
     xml2_parser.state.processing_instruction(target, data);
 }
 
@@ -326,10 +332,16 @@ fn set_xml_parse_options(parser: xmlParserCtxtPtr, unlimited_size: bool) {
 // is set; if it is, it means that there was an I/O error.  Otherwise,
 // there were no I/O errors but the caller must then ask libxml2 for
 // XML parsing errors.
+//
+// Similarly, the size_exceeded field gets set if the number of bytes
+// read goes over the limit from LoadLimits::max_decompressed_size.
 struct StreamCtx {
     stream: gio::InputStream,
     cancellable: Option<gio::Cancellable>,
     gio_error: Rc<RefCell<Option<glib::Error>>>,
+    num_bytes_read: Rc<Cell<usize>>,
+    max_bytes: Option<usize>,
+    size_exceeded: Rc<Cell<bool>>,
 }
 
 // read() callback from xmlCreateIOParserCtxt()
@@ -350,7 +362,17 @@ unsafe extern "C" fn stream_ctx_read(
     let buf: &mut [u8] = slice::from_raw_parts_mut(buffer.cast::<u8>(), len as usize);
 
     match ctx.stream.read(buf, ctx.cancellable.as_ref()) {
-        Ok(size) => size as libc::c_int,
+        Ok(size) => {
+            let total = ctx.num_bytes_read.get() + size;
+            ctx.num_bytes_read.set(total);
+
+            if ctx.max_bytes.is_some_and(|max| total > max) {
+                ctx.size_exceeded.set(true);
+                -1
+            } else {
+                size as libc::c_int
+            }
+        }
 
         Err(e) => {
             // Just store the first I/O error we get; ignore subsequent ones.
@@ -396,12 +418,12 @@ pub struct Xml2Parser<'a> {
     parser: Cell<xmlParserCtxtPtr>,
     state: &'a XmlState,
     gio_error: Rc<RefCell<Option<glib::Error>>>,
+    size_exceeded: Rc<Cell<bool>>,
 }
 
 impl<'a> Xml2Parser<'a> {
     pub fn from_stream(
         state: &'a XmlState,
-        unlimited_size: bool,
         stream: &gio::InputStream,
         cancellable: Option<&gio::Cancellable>,
     ) -> Result<Box<Xml2Parser<'a>>, LoadingError> {
@@ -415,11 +437,15 @@ impl<'a> Xml2Parser<'a> {
         // xmlCreateIOParserCtxt() in case that fails, since on
         // failure that function frees the StreamCtx.
         let gio_error = Rc::new(RefCell::new(None));
+        let size_exceeded = Rc::new(Cell::new(false));
 
         let ctx = Box::new(StreamCtx {
             stream: stream.clone(),
             cancellable: cancellable.cloned(),
             gio_error: gio_error.clone(),
+            num_bytes_read: state.num_bytes_read.clone(),
+            max_bytes: state.load_options.limits.max_decompressed_size,
+            size_exceeded: size_exceeded.clone(),
         });
 
         let mut sax_handler = get_xml2_sax_handler();
@@ -428,6 +454,7 @@ impl<'a> Xml2Parser<'a> {
             parser: Cell::new(ptr::null_mut()),
             state,
             gio_error,
+            size_exceeded,
         });
 
         unsafe {
@@ -450,13 +477,148 @@ impl<'a> Xml2Parser<'a> {
             } else {
                 xml2_parser.parser.set(parser);
 
-                set_xml_parse_options(parser, unlimited_size);
+                set_xml_parse_options(parser, state.load_options.unlimited_size);
 
                 Ok(xml2_parser)
             }
         }
     }
 
+    /// Creates a parser that reads directly from `bytes`, without going through a stream.
+    pub fn from_bytes(
+        state: &'a XmlState,
+        bytes: &'a [u8],
+    ) -> Result<Box<Xml2Parser<'a>>, LoadingError> {
+        init_libxml2();
+
+        let too_large = || LoadingError::LimitExceeded(ImplementationLimit::DocumentTooLarge);
+
+        let size = libc::c_int::try_from(bytes.len()).map_err(|_| too_large())?;
+
+        let total = state.num_bytes_read.get() + bytes.len();
+        state.num_bytes_read.set(total);
+
+        if state
+            .load_options
+            .limits
+            .max_decompressed_size
+            .is_some_and(|max| total > max)
+        {
+            return Err(too_large());
+        }
+
+        let mut xml2_parser = Box::new(Xml2Parser {
+            parser: Cell::new(ptr::null_mut()),
+            state,
+            gio_error: Rc::new(RefCell::new(None)),
+            size_exceeded: Rc::new(Cell::new(false)),
+        });
+
+        unsafe {
+            let parser = xmlCreateMemoryParserCtxt(bytes.as_ptr().cast(), size);
+
+            if parser.is_null() {
+                return Err(LoadingError::OutOfMemory(String::from(
+                    "could not create XML parser",
+                )));
+            }
+
+            // Install our SAX callbacks, like xmlCreateIOParserCtxt() does with the
+            // handler it gets passed.
+            *((*parser).sax as xmlSAXHandlerPtr) = get_xml2_sax_handler();
+
+            let xml2_parser_ptr: *mut Xml2Parser<'a> = xml2_parser.as_mut();
+            (*parser).userData = xml2_parser_ptr as *mut _;
+
+            xml2_parser.parser.set(parser);
+
+            set_xml_parse_options(parser, state.load_options.unlimited_size);
+        }
+
+        Ok(xml2_parser)
+    }
+
+    /// Creates a parser that gets fed data in chunks with [`Xml2Parser::parse_chunk`].
+    pub fn for_push(state: &'a XmlState) -> Result<Box<Xml2Parser<'a>>, LoadingError> {
+        init_libxml2();
+
+        let mut sax_handler = get_xml2_sax_handler();
+
+        let mut xml2_parser = Box::new(Xml2Parser {
+            parser: Cell::new(ptr::null_mut()),
+            state,
+            gio_error: Rc::new(RefCell::new(None)),
+            size_exceeded: Rc::new(Cell::new(false)),
+        });
+
+        unsafe {
+            let xml2_parser_ptr: *mut Xml2Parser<'a> = xml2_parser.as_mut();
+            let parser = xmlCreatePushParserCtxt(
+                &mut sax_handler,
+                xml2_parser_ptr as *mut _,
+                ptr::null(),
+                0,
+                ptr::null(),
+            );
+
+            if parser.is_null() {
+                return Err(LoadingError::OutOfMemory(String::from(
+                    "could not create XML parser",
+                )));
+            }
+
+            xml2_parser.parser.set(parser);
+
+            set_xml_parse_options(parser, state.load_options.unlimited_size);
+        }
+
+        Ok(xml2_parser)
+    }
+
+    /// Parses the next chunk of data for a parser created with [`Xml2Parser::for_push`].
+    ///
+    /// The `terminate` argument must be `true` for the last chunk, to let libxml2 know
+    /// that the document is complete.
+    pub fn parse_chunk(&self, chunk: &[u8], terminate: bool) -> Result<(), LoadingError> {
+        let too_large = || LoadingError::LimitExceeded(ImplementationLimit::DocumentTooLarge);
+
+        let size = libc::c_int::try_from(chunk.len()).map_err(|_| too_large())?;
+
+        let total = self.state.num_bytes_read.get() + chunk.len();
+        self.state.num_bytes_read.set(total);
+
+        if self
+            .state
+            .load_options
+            .limits
+            .max_decompressed_size
+            .is_some_and(|max| total > max)
+        {
+            return Err(too_large());
+        }
+
+        unsafe {
+            let parser = self.parser.get();
+
+            xmlParseChunk(
+                parser,
+                chunk.as_ptr().cast(),
+                size,
+                libc::c_int::from(terminate),
+            );
+
+            // Like xmlParseDocument(), only fail for errors that make the document
+            // not well-formed, and not for namespace errors and such.
+            if (*parser).wellFormed == 0 {
+                let xerr = xmlCtxtGetLastError(parser as *mut _);
+                let msg = xml2_error_to_string(xerr);
+                Err(LoadingError::XmlParseError(msg))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
     pub fn parse(&self) -> Result<(), LoadingError> {
         unsafe {
             let parser = self.parser.get();
@@ -467,7 +629,11 @@ impl<'a> Xml2Parser<'a> {
 
             let io_error = err_ref.take();
 
-            if let Some(io_error) = io_error {
+            if self.size_exceeded.get() {
+                Err(LoadingError::LimitExceeded(
+                    ImplementationLimit::DocumentTooLarge,
+                ))
+            } else if let Some(io_error) = io_error {
                 Err(LoadingError::from(io_error))
             } else if !xml_parse_success {
                 let xerr = xmlCtxtGetLastError(parser as *mut _);
@@ -478,6 +644,25 @@ impl<'a> Xml2Parser<'a> {
             }
         }
     }
+
+    /// Tells the session where the parser is, so that warnings can refer to that position.
+    fn update_location(&self) {
+        let parser = self.parser.get() as *mut libc::c_void;
+
+        let (line, column) =
+            unsafe { (xmlSAX2GetLineNumber(parser), xmlSAX2GetColumnNumber(parser)) };
+
+        let location = if line > 0 {
+            Some(Location {
+                line: line as u32,
+                column: column.max(1) as u32,
+            })
+        } else {
+            None
+        };
+
+        self.state.session.set_location(location);
+    }
 }
 
 impl<'a> Drop for Xml2Parser<'a> {
@@ -1,8 +1,15 @@
+use gio::prelude::*;
 use rsvg::tests_only::{SharedImageSurface, SurfaceType};
-use rsvg::{CairoRenderer, RenderingError};
+use rsvg::{
+    CairoRenderer, ColorVisionDeficiency, DebugConfig, EventKind, ImplementationLimit, Loader,
+    LoadingError, LogLevel, LogSink, Matte, ReferenceChain, RenderingError, RenderingQuality,
+    SpotColor, SvgAttribute, SvgHandle,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use rsvg::test_utils::load_svg;
 use rsvg::test_utils::reference_utils::{Compare, Evaluate, Reference};
+use rsvg::test_utils::{load_svg, render_document, render_document_with_renderer, SurfaceSize};
 
 #[test]
 fn has_element_with_id_works() {
@@ -213,3 +220,2085 @@ fn text_doesnt_leave_points_in_current_path() {
 
     assert!(!cr.has_current_point().unwrap());
 }
+
+#[test]
+fn background_color_fills_only_the_viewport() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect x="10" y="20" width="30" height="40" fill="#0000ff"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg).with_background_color(cssparser::RGBA::new(
+        Some(0),
+        Some(255),
+        Some(0),
+        Some(1.0),
+    ));
+
+    let output = cairo::ImageSurface::create(cairo::Format::ARgb32, 300, 300).unwrap();
+
+    let res = {
+        let cr = cairo::Context::new(&output).expect("Failed to create cairo context");
+
+        // Clip to part of the viewport; the background must not go outside the clip.
+        cr.rectangle(100.0, 100.0, 50.0, 100.0);
+        cr.clip();
+
+        let viewport = cairo::Rectangle::new(100.0, 100.0, 100.0, 100.0);
+
+        renderer.render_document(&cr, &viewport)
+    };
+
+    let output_surf = res
+        .map(|_| SharedImageSurface::wrap(output, SurfaceType::SRgb).unwrap())
+        .unwrap();
+
+    let reference_surf = cairo::ImageSurface::create(cairo::Format::ARgb32, 300, 300).unwrap();
+
+    {
+        let cr = cairo::Context::new(&reference_surf).expect("Failed to create a cairo context");
+
+        cr.rectangle(100.0, 100.0, 50.0, 100.0);
+        cr.set_source_rgba(0.0, 1.0, 0.0, 1.0);
+        cr.fill().unwrap();
+
+        cr.translate(100.0, 100.0);
+
+        cr.rectangle(10.0, 20.0, 30.0, 40.0);
+        cr.set_source_rgba(0.0, 0.0, 1.0, 1.0);
+        cr.fill().unwrap();
+    }
+
+    Reference::from_surface(reference_surf)
+        .compare(&output_surf)
+        .evaluate(&output_surf, "background_color_fills_only_the_viewport");
+}
+
+#[test]
+fn supersampling_preserves_pixel_aligned_shapes() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect x="10" y="20" width="30" height="40" fill="#0000ff"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg).with_supersampling(4);
+
+    let output = cairo::ImageSurface::create(cairo::Format::ARgb32, 200, 200).unwrap();
+
+    let res = {
+        let cr = cairo::Context::new(&output).expect("Failed to create cairo context");
+        cr.translate(50.0, 50.0);
+
+        let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+        renderer.render_document(&cr, &viewport)
+    };
+
+    let output_surf = res
+        .map(|_| SharedImageSurface::wrap(output, SurfaceType::SRgb).unwrap())
+        .unwrap();
+
+    let reference_surf = cairo::ImageSurface::create(cairo::Format::ARgb32, 200, 200).unwrap();
+
+    {
+        let cr = cairo::Context::new(&reference_surf).expect("Failed to create a cairo context");
+
+        cr.rectangle(60.0, 70.0, 30.0, 40.0);
+        cr.set_source_rgba(0.0, 0.0, 1.0, 1.0);
+        cr.fill().unwrap();
+    }
+
+    Reference::from_surface(reference_surf)
+        .compare(&output_surf)
+        .evaluate(&output_surf, "supersampling_preserves_pixel_aligned_shapes");
+}
+
+#[test]
+fn invalid_supersampling_factor_is_an_error() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100"/>
+"#,
+    )
+    .unwrap();
+
+    for factor in [0, 1000] {
+        let renderer = CairoRenderer::new(&svg).with_supersampling(factor);
+
+        let output = cairo::ImageSurface::create(cairo::Format::ARgb32, 100, 100).unwrap();
+        let cr = cairo::Context::new(&output).expect("Failed to create cairo context");
+        let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+        assert!(matches!(
+            renderer.render_document(&cr, &viewport),
+            Err(RenderingError::Rendering(_))
+        ));
+    }
+}
+
+#[test]
+fn walks_the_element_tree() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+  <g id="layer">
+    <image xlink:href="external.png"/>
+    text is skipped
+    <script>alert("hi")</script>
+  </g>
+  <script/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let root = svg.root_element();
+    assert_eq!(root.name(), "svg");
+    assert_eq!(root.namespace(), "http://www.w3.org/2000/svg");
+
+    let children: Vec<String> = root.children().map(|c| c.name()).collect();
+    assert_eq!(children, vec!["g", "script"]);
+
+    let names: Vec<String> = root.descendants().map(|c| c.name()).collect();
+    assert_eq!(names, vec!["svg", "g", "image", "script", "script"]);
+
+    let image = root.descendants().find(|e| e.name() == "image").unwrap();
+    assert_eq!(
+        image.attributes(),
+        vec![SvgAttribute {
+            namespace: "http://www.w3.org/1999/xlink".to_string(),
+            prefix: Some("xlink".to_string()),
+            name: "href".to_string(),
+            value: "external.png".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn element_query_keeps_custom_attributes() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:mapsvg="http://mapsvg.com">
+  <metadata>
+    <mapsvg:geoViewBox>-10 60 30 35</mapsvg:geoViewBox>
+  </metadata>
+  <path id="FI" data-name="Finland" data-population="5541000" mapsvg:code="246" d="M0,0 L1,1"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let finland = svg.element_by_id("FI").unwrap();
+    assert_eq!(finland.name(), "path");
+    assert_eq!(finland.id().as_deref(), Some("FI"));
+    assert_eq!(
+        finland.data_attributes(),
+        vec![
+            ("name".to_string(), "Finland".to_string()),
+            ("population".to_string(), "5541000".to_string()),
+        ]
+    );
+    assert_eq!(
+        finland.attribute("http://mapsvg.com", "code").as_deref(),
+        Some("246")
+    );
+    assert_eq!(finland.attribute("", "code"), None);
+
+    assert!(svg.element_by_id("#FI").is_none());
+    assert!(svg.element_by_id("SE").is_none());
+
+    let geo = svg
+        .root_element()
+        .descendants()
+        .find(|e| e.name() == "geoViewBox")
+        .unwrap();
+    assert_eq!(geo.namespace(), "http://mapsvg.com");
+}
+
+#[test]
+fn query_selector_all_returns_elements_in_document_order() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+  <g id="logo"/>
+  <g id="home" class="icon"/>
+  <g>
+    <rect id="search" class="big icon"/>
+  </g>
+  <rect id="plain"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let ids = |selectors: &str| {
+        svg.query_selector_all(selectors)
+            .unwrap()
+            .iter()
+            .map(|e| e.id().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(ids(".icon, #logo"), vec!["logo", "home", "search"]);
+    assert_eq!(ids("g > rect.icon"), vec!["search"]);
+    assert!(ids(".missing").is_empty());
+
+    assert!(matches!(
+        svg.query_selector_all(".icon {"),
+        Err(LoadingError::BadCss)
+    ));
+}
+
+fn load_with_limits(loader: Loader, input: &'static [u8]) -> Result<SvgHandle, LoadingError> {
+    let bytes = glib::Bytes::from_static(input);
+    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+
+    loader.read_stream(&stream, None::<&gio::File>, None::<&gio::Cancellable>)
+}
+
+const NESTED_GROUPS: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <g><g><rect width="10" height="10"/></g></g>
+</svg>
+"#;
+
+#[test]
+fn loader_limits_number_of_elements() {
+    assert!(load_with_limits(Loader::new().with_max_elements(3), NESTED_GROUPS).is_ok());
+
+    assert!(matches!(
+        load_with_limits(Loader::new().with_max_elements(2), NESTED_GROUPS),
+        Err(LoadingError::LimitExceeded(
+            ImplementationLimit::TooManyLoadedElements(2)
+        ))
+    ));
+}
+
+#[test]
+fn loader_limits_nesting_depth() {
+    assert!(load_with_limits(Loader::new().with_max_nesting_depth(4), NESTED_GROUPS).is_ok());
+
+    assert!(matches!(
+        load_with_limits(Loader::new().with_max_nesting_depth(3), NESTED_GROUPS),
+        Err(LoadingError::LimitExceeded(
+            ImplementationLimit::TooDeeplyNested
+        ))
+    ));
+}
+
+#[test]
+fn loader_limits_decompressed_size() {
+    let size = NESTED_GROUPS.len();
+
+    assert!(load_with_limits(
+        Loader::new().with_max_decompressed_size(size),
+        NESTED_GROUPS
+    )
+    .is_ok());
+
+    assert!(matches!(
+        load_with_limits(
+            Loader::new().with_max_decompressed_size(size / 2),
+            NESTED_GROUPS
+        ),
+        Err(LoadingError::LimitExceeded(
+            ImplementationLimit::DocumentTooLarge
+        ))
+    ));
+}
+
+const NESTED_USES: &[u8] = br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <defs>
+    <rect id="r" width="10" height="10"/>
+    <g id="g1"><use id="u1" href="#r"/></g>
+    <g id="g2"><use id="u2" href="#g1"/></g>
+  </defs>
+  <use id="u3" href="#g2"/>
+</svg>
+"##;
+
+fn render_with_reference_chain(
+    max: usize,
+    messages: Arc<Mutex<Vec<String>>>,
+) -> Result<(), RenderingError> {
+    let loader = Loader::new().with_max_reference_chain(max).with_log_sink(
+        move |_level: LogLevel, message: &str| {
+            messages.lock().unwrap().push(message.to_string());
+        },
+    );
+
+    let handle = load_with_limits(loader, NESTED_USES).unwrap();
+
+    let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 10, 10).unwrap();
+    let cr = cairo::Context::new(&surface).unwrap();
+
+    CairoRenderer::new(&handle).render_document(&cr, &cairo::Rectangle::new(0.0, 0.0, 10.0, 10.0))
+}
+
+#[test]
+fn renderer_limits_reference_chains() {
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    assert!(render_with_reference_chain(3, messages).is_ok());
+
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    assert!(matches!(
+        render_with_reference_chain(2, messages.clone()),
+        Err(RenderingError::LimitExceeded(
+            ImplementationLimit::ReferenceChainTooLong(ReferenceChain::Use)
+        ))
+    ));
+
+    assert!(messages
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|m| m.contains("#u3 -> #u2 -> #u1")));
+}
+
+const INVALID_ATTRIBUTE_AND_FILTER: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <filter id="f">
+    <feFlood flood-color="lime"/>
+  </filter>
+  <rect width="foo" height="10"/>
+  <rect width="10" height="10" filter="url(#f)"/>
+</svg>
+"#;
+
+fn render_to_image(handle: &SvgHandle) {
+    render_document(
+        handle,
+        SurfaceSize(10, 10),
+        |_| (),
+        cairo::Rectangle::new(0.0, 0.0, 10.0, 10.0),
+    )
+    .unwrap();
+}
+
+#[test]
+fn log_sink_gets_messages_from_loading_and_rendering() {
+    let messages = Arc::new(Mutex::new(Vec::new()));
+
+    let loader = {
+        let messages = messages.clone();
+        Loader::new().with_log_sink(move |level: LogLevel, message: &str| {
+            messages.lock().unwrap().push((level, message.to_string()));
+        })
+    };
+
+    let handle = load_with_limits(loader, INVALID_ATTRIBUTE_AND_FILTER).unwrap();
+    render_to_image(&handle);
+
+    let messages = messages.lock().unwrap();
+
+    assert!(messages
+        .iter()
+        .any(|(level, m)| *level == LogLevel::Warning && m.contains("invalid value")));
+    assert!(messages
+        .iter()
+        .any(|(level, m)| *level == LogLevel::Debug && m.contains("feFlood")));
+}
+
+#[test]
+fn log_sink_can_filter_by_level() {
+    struct WarningsOnly(Arc<Mutex<Vec<LogLevel>>>);
+
+    impl LogSink for WarningsOnly {
+        fn log(&self, level: LogLevel, _message: &str) {
+            self.0.lock().unwrap().push(level);
+        }
+
+        fn enabled(&self, level: LogLevel) -> bool {
+            level <= LogLevel::Warning
+        }
+    }
+
+    let levels = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = load_with_limits(
+        Loader::new().with_log_sink(WarningsOnly(levels.clone())),
+        INVALID_ATTRIBUTE_AND_FILTER,
+    )
+    .unwrap();
+    render_to_image(&handle);
+
+    let levels = levels.lock().unwrap();
+    assert!(!levels.is_empty());
+    assert!(levels.iter().all(|l| *l == LogLevel::Warning));
+}
+
+#[test]
+fn debug_config_writes_trace_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let trace = dir.path().join("trace.log");
+
+    let handle = load_with_limits(
+        Loader::new().with_debug_config(
+            DebugConfig::new()
+                .with_log_level(LogLevel::Warning)
+                .with_trace_file(&trace),
+        ),
+        INVALID_ATTRIBUTE_AND_FILTER,
+    )
+    .unwrap();
+    render_to_image(&handle);
+
+    let contents = std::fs::read_to_string(&trace).unwrap();
+    assert!(contents.contains("invalid value"));
+    assert!(!contents.contains("feFlood"));
+}
+
+#[test]
+fn estimate_cost_counts_layers_and_operations() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="100" height="100">
+  <defs>
+    <filter id="f">
+      <feFlood flood-color="lime"/>
+      <feGaussianBlur stdDeviation="2"/>
+    </filter>
+    <rect id="r" width="10" height="10"/>
+  </defs>
+  <rect width="10" height="10"/>
+  <g opacity="0.5">
+    <use xlink:href="#r"/>
+    <use xlink:href="#r" x="20"/>
+  </g>
+  <rect width="10" height="10" filter="url(#f)" style="display: none"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+    let renderer = CairoRenderer::new(&svg);
+
+    let cost = renderer.estimate_cost(&viewport);
+    assert_eq!(cost.operations, 4);
+    assert_eq!(cost.surface_bytes, 100 * 100 * 4);
+
+    let cost = renderer.with_supersampling(2).estimate_cost(&viewport);
+    assert_eq!(cost.operations, 5);
+    assert_eq!(cost.surface_bytes, 2 * 200 * 200 * 4);
+}
+
+#[test]
+fn estimate_cost_includes_filter_primitives() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <filter id="f">
+    <feFlood flood-color="lime"/>
+    <feGaussianBlur stdDeviation="2"/>
+  </filter>
+  <rect width="10" height="10" filter="url(#f)"/>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    let cost = CairoRenderer::new(&svg).estimate_cost(&cairo::Rectangle::new(0.0, 0.0, 50.0, 50.0));
+
+    // The isolated layer, plus the result of each primitive.
+    assert_eq!(cost.surface_bytes, 3 * 50 * 50 * 4);
+    assert_eq!(cost.operations, 4);
+}
+
+#[cfg(feature = "render-observers")]
+#[test]
+fn render_observer_sees_each_element() {
+    use rsvg::{ObservedElement, RenderObserver};
+
+    type Events = Arc<Mutex<Vec<(String, Option<String>, Option<cairo::Rectangle>)>>>;
+
+    struct Recorder(Events);
+
+    impl RenderObserver for Recorder {
+        fn before_element(&self, element: &ObservedElement<'_>) {
+            self.0.lock().unwrap().push((
+                format!("<{}", element.name),
+                element.id.map(String::from),
+                None,
+            ));
+        }
+
+        fn after_element(
+            &self,
+            element: &ObservedElement<'_>,
+            bounds: Option<cairo::Rectangle>,
+            _elapsed: Duration,
+        ) {
+            self.0.lock().unwrap().push((
+                format!("{}>", element.name),
+                element.id.map(String::from),
+                bounds,
+            ));
+        }
+    }
+
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <g id="group">
+    <rect id="square" x="10" y="20" width="30" height="40" fill="blue"/>
+    <rect id="sibling" x="50" y="20" width="30" height="40" fill="blue"/>
+  </g>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    let events = Events::default();
+
+    let output = cairo::ImageSurface::create(cairo::Format::ARgb32, 100, 100).unwrap();
+    let cr = cairo::Context::new(&output).unwrap();
+    CairoRenderer::new(&svg)
+        .with_render_observer(Recorder(events.clone()))
+        .render_document(&cr, &cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0))
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    let names: Vec<_> = events.iter().map(|(name, _, _)| name.as_str()).collect();
+    // Identically styled siblings could be painted together, but each one gets reported.
+    assert_eq!(
+        names,
+        ["<svg", "<g", "<rect", "rect>", "<rect", "rect>", "g>", "svg>"]
+    );
+
+    let (_, id, bounds) = &events[3];
+    assert_eq!(id.as_deref(), Some("square"));
+    assert_eq!(*bounds, Some(cairo::Rectangle::new(10.0, 20.0, 30.0, 40.0)));
+
+    let (_, id, bounds) = &events[5];
+    assert_eq!(id.as_deref(), Some("sibling"));
+    assert_eq!(*bounds, Some(cairo::Rectangle::new(50.0, 20.0, 30.0, 40.0)));
+}
+
+fn render_to_shared_surface(handle: &SvgHandle) -> SharedImageSurface {
+    render_document(
+        handle,
+        SurfaceSize(100, 100),
+        |cr| cr.translate(10.0, 10.0),
+        cairo::Rectangle::new(0.0, 0.0, 80.0, 80.0),
+    )
+    .unwrap()
+}
+
+const STATIC_ELEMENTS: &[u8] = br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <g id="background" opacity="0.5">
+    <rect x="10" y="10" width="50" height="50" fill="#0000ff"/>
+  </g>
+  <circle id="foreground" cx="60" cy="60" r="30" fill="#ff0000"/>
+</svg>
+"##;
+
+#[test]
+fn static_elements_render_like_normal_ones() {
+    let mut svg = load_svg(STATIC_ELEMENTS).unwrap();
+    let reference = render_to_shared_surface(&load_svg(STATIC_ELEMENTS).unwrap());
+
+    svg.set_element_static("#background", true).unwrap();
+    svg.set_element_static("#foreground", true).unwrap();
+
+    // The first render creates the cached rasters, the second one uses them.
+    for _ in 0..2 {
+        let output = render_to_shared_surface(&svg);
+
+        Reference::from_surface(reference.clone().into_image_surface().unwrap())
+            .compare(&output)
+            .evaluate(&output, "static_elements_render_like_normal_ones");
+    }
+
+    assert!(svg.set_element_static("#nonexistent", true).is_err());
+}
+
+#[test]
+fn set_stylesheet_invalidates_static_elements() {
+    let mut svg = load_svg(STATIC_ELEMENTS).unwrap();
+    svg.set_element_static("#foreground", true).unwrap();
+    let _ = render_to_shared_surface(&svg);
+
+    let css = "circle { fill: lime; }";
+    svg.set_stylesheet(css).unwrap();
+    let output = render_to_shared_surface(&svg);
+
+    let mut reference_svg = load_svg(STATIC_ELEMENTS).unwrap();
+    reference_svg.set_stylesheet(css).unwrap();
+    let reference = render_to_shared_surface(&reference_svg);
+
+    Reference::from_surface(reference.into_image_surface().unwrap())
+        .compare(&output)
+        .evaluate(&output, "set_stylesheet_invalidates_static_elements");
+}
+
+#[test]
+fn set_stylesheet_from_file_resolves_relative_references() {
+    let mut svg = Loader::new()
+        .read_path("tests/fixtures/api/stylesheet-file.svg")
+        .unwrap();
+    svg.set_stylesheet_from_file(&gio::File::for_path(
+        "tests/fixtures/api/stylesheet-file/style.css",
+    ))
+    .unwrap();
+    let output = render_to_shared_surface(&svg);
+
+    let reference_svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect x="10" y="10" width="30" height="80" fill="lime"/>
+  <rect x="60" y="10" width="30" height="80" fill="lime"/>
+</svg>
+"#,
+    )
+    .unwrap();
+    let reference = render_to_shared_surface(&reference_svg);
+
+    Reference::from_surface(reference.into_image_surface().unwrap())
+        .compare(&output)
+        .evaluate(
+            &output,
+            "set_stylesheet_from_file_resolves_relative_references",
+        );
+
+    let mut svg = load_svg(b"<svg xmlns='http://www.w3.org/2000/svg'/>").unwrap();
+    assert!(matches!(
+        svg.set_stylesheet_from_file(&gio::File::for_path(
+            "tests/fixtures/api/stylesheet-file/nonexistent.css"
+        )),
+        Err(LoadingError::BadUrl)
+    ));
+}
+
+#[test]
+fn collects_warnings_with_locations() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10" fill="bogus"/>
+  <use href="#nonexistent"/>
+  <rect id="r" width="10" height="10" style="stroke: url(#missing); opacity: nope"/>
+  <use href="#r"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let warnings = svg.warnings();
+
+    let lines_with = |text: &str| {
+        warnings
+            .iter()
+            .filter(|w| w.message.contains(text))
+            .map(|w| w.location.unwrap().line)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(lines_with("fill"), vec![3]);
+    assert_eq!(lines_with("#nonexistent"), vec![4]);
+    assert_eq!(lines_with("#missing"), vec![5]);
+    assert_eq!(lines_with("Invalid declaration"), vec![5]);
+    assert_eq!(warnings.len(), 4);
+}
+
+#[test]
+fn valid_document_has_no_warnings() {
+    let svg = load_svg(STATIC_ELEMENTS).unwrap();
+    assert!(svg.warnings().is_empty());
+}
+
+fn load_strict(input: &'static [u8]) -> Result<SvgHandle, LoadingError> {
+    load_with_limits(Loader::new().strict(true), input)
+}
+
+#[test]
+fn strict_mode_rejects_invalid_values() {
+    let invalid_line = |input| match load_strict(input) {
+        Err(LoadingError::Invalid(warning)) => warning.location.unwrap().line,
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("document should not have loaded in strict mode"),
+    };
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <path d="M 0 0 L 10 x"/>
+</svg>"#
+        ),
+        2
+    );
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <g>
+    <rect width="10" height="10" transform="translate(1, 2"/>
+  </g>
+</svg>"#
+        ),
+        3
+    );
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <circle cx="5" cy="5" r="5foo"/>
+</svg>"#
+        ),
+        2
+    );
+}
+
+#[test]
+fn strict_mode_rejects_css_problems_and_missing_references() {
+    let invalid_line = |input| match load_strict(input) {
+        Err(LoadingError::Invalid(warning)) => warning.location.unwrap().line,
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("document should not have loaded in strict mode"),
+    };
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10"/>
+  <rect width="10" height="10" style="fill: bogus"/>
+</svg>"#
+        ),
+        3
+    );
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10" style="fill-colour: red"/>
+</svg>"#
+        ),
+        2
+    );
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10"/>
+  <style>rect { stroke-width: wide; }</style>
+</svg>"#
+        ),
+        3
+    );
+
+    assert_eq!(
+        invalid_line(
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10" fill="url(#missing)"/>
+  <linearGradient id="present"/>
+</svg>"#
+        ),
+        2
+    );
+}
+
+#[test]
+fn strict_mode_accepts_valid_documents() {
+    assert!(load_strict(STATIC_ELEMENTS).is_ok());
+
+    let svg = load_strict(
+        br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10" fill="url(#gradient)" style="stroke: blue"/>
+  <linearGradient id="gradient"/>
+</svg>"#,
+    )
+    .unwrap();
+    assert!(svg.warnings().is_empty());
+
+    let bad_path = br#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <path d="M 0 0 L 10 x"/>
+</svg>"#;
+    assert!(load_svg(bad_path).is_ok());
+}
+
+#[test]
+fn lists_external_references() {
+    let svg = Loader::new()
+        .read_path("tests/fixtures/api/external-references.svg")
+        .unwrap();
+
+    let references = svg.external_references();
+    let file_names = references
+        .iter()
+        .map(|url| {
+            assert!(url.starts_with("file://"));
+            url.rsplit('/').next().unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        file_names,
+        vec![
+            "external-references.css",
+            "example.svg",
+            "geometry.svg",
+            "layers.svg"
+        ]
+    );
+}
+
+#[test]
+fn loads_from_std_io_read() {
+    let path = "tests/fixtures/api/external-references.svg";
+    let base_url = gio::File::for_path(path).uri();
+
+    let file = std::fs::File::open(path).unwrap();
+    let svg = Loader::new().read(file, Some(&base_url)).unwrap();
+    assert!(svg.has_element_with_id("#internal").unwrap());
+
+    // References get resolved with respect to the base URL
+    assert_eq!(svg.external_references().len(), 4);
+
+    let reader = std::io::Cursor::new(STATIC_ELEMENTS.to_vec());
+    let svg = Loader::new().read(reader, None).unwrap();
+    assert!(svg.has_element_with_id("#foreground").unwrap());
+
+    let reader = std::io::Cursor::new(STATIC_ELEMENTS.to_vec());
+    assert!(matches!(
+        Loader::new().read(reader, Some("not a url")),
+        Err(LoadingError::BadUrl)
+    ));
+}
+
+#[test]
+fn loads_from_bytes() {
+    let svg = Loader::new()
+        .read_from_bytes(STATIC_ELEMENTS, None)
+        .unwrap();
+    assert!(svg.has_element_with_id("#foreground").unwrap());
+
+    let bytes = glib::Bytes::from_static(STATIC_ELEMENTS);
+    let svg = Loader::new().read_from_bytes(&bytes, None).unwrap();
+    assert!(svg.has_element_with_id("#foreground").unwrap());
+
+    let svgz = std::fs::read("tests/fixtures/loading/gnome-cool.svgz").unwrap();
+    assert!(Loader::new().read_from_bytes(&svgz, None).is_ok());
+
+    assert!(matches!(
+        Loader::new().read_from_bytes(b"<", None),
+        Err(LoadingError::XmlParseError(_))
+    ));
+
+    assert!(matches!(
+        Loader::new().read_from_bytes(b"<svg", None),
+        Err(LoadingError::XmlParseError(_))
+    ));
+
+    assert!(matches!(
+        Loader::new()
+            .with_max_decompressed_size(NESTED_GROUPS.len() / 2)
+            .read_from_bytes(NESTED_GROUPS, None),
+        Err(LoadingError::LimitExceeded(
+            ImplementationLimit::DocumentTooLarge
+        ))
+    ));
+
+    assert!(matches!(
+        Loader::new().read_from_bytes(STATIC_ELEMENTS, Some("not a url")),
+        Err(LoadingError::BadUrl)
+    ));
+}
+
+#[test]
+fn features_can_be_disabled() {
+    let path = "tests/fixtures/api/features.svg";
+
+    let svg = Loader::new().read_path(path).unwrap();
+    let output = render_to_shared_surface(&svg);
+    assert_eq!(output.get_pixel(15, 15).r, 255);
+    assert_eq!(output.get_pixel(55, 15).b, 255);
+
+    let renderer = CairoRenderer::new(&svg);
+    let (ink_r, _) = renderer.geometry_for_element(Some("#text")).unwrap();
+    assert!(ink_r.width() > 0.0);
+
+    let svg = Loader::new()
+        .enable_filters(false)
+        .enable_external_images(false)
+        .enable_text(false)
+        .read_path(path)
+        .unwrap();
+    let output = render_to_shared_surface(&svg);
+
+    // The rect is drawn without its filter
+    let pixel = output.get_pixel(15, 15);
+    assert_eq!((pixel.r, pixel.g), (0, 255));
+
+    // External images are not loaded, but embedded ones are
+    assert_eq!(output.get_pixel(55, 15).a, 0);
+    assert_eq!(output.get_pixel(55, 55).b, 255);
+
+    let renderer = CairoRenderer::new(&svg);
+    let (ink_r, _) = renderer.geometry_for_element(Some("#text")).unwrap();
+    assert_eq!(ink_r.width(), 0.0);
+}
+
+const INKSCAPE_LAYERS: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg"
+     xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape"
+     xmlns:sodipodi="http://sodipodi.sourceforge.net/DTD/sodipodi-0.dtd"
+     width="10" height="10">
+  <g id="layer1" inkscape:label="Background" inkscape:groupmode="layer" sodipodi:insensitive="true">
+    <rect width="10" height="10"/>
+  </g>
+</svg>
+"#;
+
+#[test]
+fn lists_and_sets_foreign_attributes() {
+    let mut svg = load_svg(INKSCAPE_LAYERS).unwrap();
+
+    let inkscape = "http://www.inkscape.org/namespaces/inkscape";
+
+    let layer = svg.element_by_id("layer1").unwrap();
+    let foreign: Vec<(Option<String>, String)> = layer
+        .foreign_attributes()
+        .into_iter()
+        .map(|a| (a.prefix, a.name))
+        .collect();
+    assert_eq!(
+        foreign,
+        vec![
+            (Some("inkscape".to_string()), "label".to_string()),
+            (Some("inkscape".to_string()), "groupmode".to_string()),
+            (Some("sodipodi".to_string()), "insensitive".to_string()),
+        ]
+    );
+
+    // Attributes are addressed by namespace, not by prefix
+    assert_eq!(
+        layer.attribute(inkscape, "label").as_deref(),
+        Some("Background")
+    );
+    assert_eq!(layer.attribute("", "label"), None);
+
+    svg.set_foreign_attribute("#layer1", inkscape, "inkscape:label", "Sky")
+        .unwrap();
+    svg.set_foreign_attribute("#layer1", "http://example.com/tool", "tool:locked", "yes")
+        .unwrap();
+
+    let layer = svg.element_by_id("layer1").unwrap();
+    assert_eq!(layer.attribute(inkscape, "label").as_deref(), Some("Sky"));
+    assert_eq!(layer.foreign_attributes().len(), 4);
+
+    let mut output = Vec::new();
+    svg.write_svg(&mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains(r#"inkscape:label="Sky""#));
+    assert!(output.contains(r#"xmlns:tool="http://example.com/tool""#));
+    assert!(output.contains(r#"tool:locked="yes""#));
+
+    assert!(matches!(
+        svg.set_foreign_attribute("#nonexistent", inkscape, "label", "x"),
+        Err(RenderingError::IdNotFound)
+    ));
+}
+
+#[test]
+fn cannot_set_svg_attributes_as_foreign() {
+    let mut svg = load_svg(INKSCAPE_LAYERS).unwrap();
+    assert!(matches!(
+        svg.set_foreign_attribute("#layer1", "", "fill", "red"),
+        Err(RenderingError::InvalidNamespace(_))
+    ));
+}
+
+const LEFT_HALF: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="50" height="100" fill="lime"/>
+</svg>
+"#;
+
+fn render_with<F>(handle: &SvgHandle, configure: F) -> SharedImageSurface
+where
+    F: FnOnce(CairoRenderer) -> CairoRenderer,
+{
+    render_document_with_renderer(
+        &configure(CairoRenderer::new(handle)),
+        SurfaceSize(100, 100),
+        |_| (),
+        cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0),
+    )
+    .unwrap()
+}
+
+#[test]
+fn renderer_transform_is_applied_around_viewport_center() {
+    let svg = load_svg(LEFT_HALF).unwrap();
+
+    let output = render_with(&svg, |r| r);
+    assert_eq!(output.get_pixel(25, 50).a, 255);
+    assert_eq!(output.get_pixel(75, 50).a, 0);
+
+    let flip = cairo::Matrix::new(-1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+    let output = render_with(&svg, |r| r.with_transform(flip));
+    assert_eq!(output.get_pixel(25, 50).a, 0);
+    assert_eq!(output.get_pixel(75, 50).a, 255);
+
+    let singular = cairo::Matrix::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    let output = cairo::ImageSurface::create(cairo::Format::ARgb32, 100, 100).unwrap();
+    let cr = cairo::Context::new(&output).unwrap();
+    assert!(CairoRenderer::new(&svg)
+        .with_transform(singular)
+        .render_document(&cr, &cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0))
+        .is_err());
+}
+
+#[test]
+fn renderer_opacity_fades_document() {
+    let svg = load_svg(LEFT_HALF).unwrap();
+
+    let output = render_with(&svg, |r| r.with_opacity(0.5));
+    let alpha = output.get_pixel(25, 50).a;
+    assert!((127..=128).contains(&alpha));
+    assert_eq!(output.get_pixel(75, 50).a, 0);
+
+    let output = render_with(&svg, |r| r.with_opacity(-1.0));
+    assert_eq!(output.get_pixel(25, 50).a, 0);
+}
+
+#[test]
+fn checkerboard_matte_is_aligned_to_pixels() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"/>
+"#,
+    )
+    .unwrap();
+
+    let matte = Matte::Checkerboard {
+        light: cssparser::RGBA::new(Some(255), Some(255), Some(255), Some(1.0)),
+        dark: cssparser::RGBA::new(Some(0), Some(0), Some(0), Some(1.0)),
+        square_size: 8,
+    };
+
+    // The document is scaled up to the 100x100 viewport, but the squares are not.
+    let output = render_with(&svg, |r| r.with_matte(matte));
+
+    assert_eq!(output.get_pixel(7, 7).r, 255);
+    assert_eq!(output.get_pixel(8, 7).r, 0);
+    assert_eq!(output.get_pixel(7, 8).r, 0);
+    assert_eq!(output.get_pixel(15, 15).r, 255);
+    assert_eq!(output.get_pixel(8, 7).a, 255);
+}
+
+#[test]
+fn overprint_preview_multiplies_spot_inks() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="100" height="100" fill="yellow"/>
+  <rect x="50" width="50" height="100" fill="magenta"/>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    let pixel = output.get_pixel(75, 50);
+    assert_eq!((pixel.r, pixel.g, pixel.b), (255, 0, 255));
+
+    let spot = SpotColor {
+        color: cssparser::RGBA::new(Some(255), Some(0), Some(255), Some(1.0)),
+        ink: cssparser::RGBA::new(Some(0), Some(255), Some(255), Some(1.0)),
+    };
+
+    let output = render_with(&svg, |r| r.with_overprint_preview(&[spot]));
+
+    // The cyan ink is multiplied with the yellow underneath.
+    let pixel = output.get_pixel(75, 50);
+    assert_eq!((pixel.r, pixel.g, pixel.b), (0, 255, 0));
+
+    let pixel = output.get_pixel(25, 50);
+    assert_eq!((pixel.r, pixel.g, pixel.b), (255, 255, 0));
+}
+
+#[test]
+fn renders_to_vector_formats_in_points() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="1in" height="0.5in">
+  <rect id="rect" width="48" height="24" fill="blue"/>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg).with_dpi(96.0, 96.0);
+
+    let pdf = renderer
+        .render_to_pdf(Vec::new(), &[None, Some("#rect")])
+        .unwrap();
+    assert!(pdf.starts_with(b"%PDF"));
+
+    let ps = renderer.render_to_ps(Vec::new()).unwrap();
+    assert!(ps.starts_with(b"%!PS"));
+
+    let svg_output = String::from_utf8(renderer.render_to_svg(Vec::new()).unwrap()).unwrap();
+    assert!(svg_output.contains(r#"width="72pt""#));
+    assert!(svg_output.contains(r#"height="36pt""#));
+
+    assert!(matches!(
+        renderer.render_to_pdf(Vec::new(), &[Some("#nonexistent")]),
+        Err(RenderingError::IdNotFound)
+    ));
+}
+
+#[test]
+fn pages_of_slide_deck() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="300" height="100">
+  <g id="first" class="page"><rect width="100" height="100"/></g>
+  <g class="page"><rect x="100" width="100" height="100"/></g>
+  <g id="decoration"><circle r="10"/></g>
+  <g id="second" class="slide page">
+    <g id="nested" class="page"/>
+    <rect x="200" width="50" height="100"/>
+  </g>
+  <rect id="not-a-group" class="page"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let ids: Vec<_> = svg.pages().iter().filter_map(|p| p.id()).collect();
+    assert_eq!(ids, ["first", "second"]);
+
+    let pdf = CairoRenderer::new(&svg)
+        .render_pages_to_pdf(Vec::new())
+        .unwrap();
+    assert!(pdf.starts_with(b"%PDF"));
+}
+
+#[test]
+fn render_plan_matches_renderer_at_several_sizes() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100">
+  <defs>
+    <linearGradient id="base">
+      <stop offset="0" stop-color="red"/>
+      <stop offset="1" stop-color="blue"/>
+    </linearGradient>
+    <linearGradient id="gradient" href="#base" x2="0" y2="1"/>
+  </defs>
+  <rect x="10" y="10" width="80" height="40" fill="url(#gradient)"/>
+  <circle cx="50" cy="70" r="20" fill="url(#gradient)" fill-opacity="0.5"/>
+  <text x="10" y="95" font-size="20" fill="url(#gradient)">Hello</text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let plan = CairoRenderer::new(&svg).prepare();
+
+    for size in [32, 64, 100] {
+        let viewport = cairo::Rectangle::new(0.0, 0.0, f64::from(size), f64::from(size));
+
+        let output = cairo::ImageSurface::create(cairo::Format::ARgb32, size, size).unwrap();
+        {
+            let cr = cairo::Context::new(&output).unwrap();
+            plan.render_document(&cr, &viewport).unwrap();
+        }
+        let output_surf = SharedImageSurface::wrap(output, SurfaceType::SRgb).unwrap();
+
+        let reference = cairo::ImageSurface::create(cairo::Format::ARgb32, size, size).unwrap();
+        {
+            let cr = cairo::Context::new(&reference).unwrap();
+            CairoRenderer::new(&svg)
+                .render_document(&cr, &viewport)
+                .unwrap();
+        }
+
+        Reference::from_surface(reference)
+            .compare(&output_surf)
+            .evaluate(
+                &output_surf,
+                "render_plan_matches_renderer_at_several_sizes",
+            );
+    }
+}
+
+#[test]
+fn title_and_description() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <title>
+    Document title
+  </title>
+  <desc>A <tspan>longer</tspan> description</desc>
+  <g id="group">
+    <title>Group title</title>
+    <rect id="rect" width="10" height="10"/>
+  </g>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(svg.document_title().as_deref(), Some("Document title"));
+    assert_eq!(
+        svg.document_description().as_deref(),
+        Some("A longer description")
+    );
+    assert_eq!(svg.element_title("group").as_deref(), Some("Group title"));
+    assert_eq!(svg.element_title("rect"), None);
+    assert_eq!(svg.element_title("nonexistent"), None);
+
+    let untitled = load_svg(STATIC_ELEMENTS).unwrap();
+    assert_eq!(untitled.document_title(), None);
+    assert_eq!(untitled.document_description(), None);
+    assert_eq!(untitled.document_metadata(), None);
+}
+
+#[test]
+fn document_metadata() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"
+     xmlns:cc="http://creativecommons.org/ns#" width="100" height="100">
+  <metadata>
+    <rdf:RDF>
+      <cc:Work rdf:about=""><cc:license rdf:resource="http://creativecommons.org/licenses/by-sa/4.0/"/></cc:Work>
+    </rdf:RDF>
+  </metadata>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        svg.document_metadata().as_deref(),
+        Some(
+            r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns:cc="http://creativecommons.org/ns#">
+      <cc:Work rdf:about=""><cc:license rdf:resource="http://creativecommons.org/licenses/by-sa/4.0/"/></cc:Work>
+    </rdf:RDF>"#
+        )
+    );
+}
+
+#[test]
+fn content_under_opaque_background_is_covered() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <circle id="hidden" cx="50" cy="50" r="40" fill="red"/>
+  <rect width="100" height="100" fill="lime"/>
+  <rect x="0" y="0" width="50" height="50" fill="blue" fill-opacity="0.5"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    let pixel = output.get_pixel(75, 75);
+    assert_eq!((pixel.r, pixel.g, pixel.b, pixel.a), (0, 255, 0, 255));
+    let pixel = output.get_pixel(25, 25);
+    assert_eq!((pixel.r, pixel.a), (0, 255));
+    assert!(pixel.b > 0 && pixel.g > 0);
+
+    // Covered elements are still measured.
+    let (ink_rect, _) = CairoRenderer::new(&svg)
+        .geometry_for_layer(
+            Some("#hidden"),
+            &cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0),
+        )
+        .unwrap();
+    assert_eq!(ink_rect, cairo::Rectangle::new(10.0, 10.0, 80.0, 80.0));
+
+    // A translucent background does not hide what is below it.
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="100" height="100" fill="red"/>
+  <rect width="100" height="100" fill="lime" opacity="0.5"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    assert!(output.get_pixel(50, 50).r > 0);
+}
+
+#[test]
+fn resource_loader_serves_and_denies_files() {
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let loader_requested = requested.clone();
+
+    let svg = Loader::new()
+        .with_resource_loader(move |url: &str| -> Option<Result<Vec<u8>, glib::Error>> {
+            loader_requested.lock().unwrap().push(url.to_string());
+
+            if url.ends_with("/style.css") {
+                Some(Ok(b"rect { fill: lime; }".to_vec()))
+            } else {
+                Some(Err(glib::Error::new(
+                    gio::IOErrorEnum::PermissionDenied,
+                    "access denied",
+                )))
+            }
+        })
+        .read_from_bytes(
+            br#"<?xml-stylesheet href="style.css" type="text/css"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="100" height="100" fill="red"/>
+  <image href="secret.png" width="100" height="100"/>
+</svg>
+"#,
+            Some("resource:///org/gnome/librsvg/test/document.svg"),
+        )
+        .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    let pixel = output.get_pixel(50, 50);
+    assert_eq!((pixel.r, pixel.g, pixel.b, pixel.a), (0, 255, 0, 255));
+
+    assert_eq!(
+        *requested.lock().unwrap(),
+        vec![
+            "resource:///org/gnome/librsvg/test/style.css",
+            "resource:///org/gnome/librsvg/test/secret.png",
+        ]
+    );
+}
+
+#[test]
+fn disabled_external_resources_are_not_requested() {
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let loader_requested = requested.clone();
+
+    let svg = Loader::new()
+        .enable_external_resources(false)
+        .with_resource_loader(move |url: &str| -> Option<Result<Vec<u8>, glib::Error>> {
+            loader_requested.lock().unwrap().push(url.to_string());
+            Some(Ok(b"rect { fill: lime; }".to_vec()))
+        })
+        .read_from_bytes(
+            br#"<?xml-stylesheet href="style.css" type="text/css"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="100" height="100" fill="red"/>
+  <image href="secret.png" width="100" height="100"/>
+  <use href="other.svg#foo"/>
+</svg>
+"#,
+            Some("resource:///org/gnome/librsvg/test/document.svg"),
+        )
+        .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    let pixel = output.get_pixel(50, 50);
+    assert_eq!((pixel.r, pixel.g, pixel.b, pixel.a), (255, 0, 0, 255));
+
+    assert!(requested.lock().unwrap().is_empty());
+}
+
+fn scatter_plot(separate: bool) -> String {
+    // Isolating each element keeps it from being painted in a batch with its siblings.
+    let style = if separate {
+        r#" style="isolation: isolate""#
+    } else {
+        ""
+    };
+
+    let mut svg =
+        String::from(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">"#);
+
+    for i in 0..50 {
+        let (x, y) = (5 + (i % 10) * 10, 5 + (i / 10) * 10);
+        svg.push_str(&format!(
+            r#"<circle cx="{x}" cy="{y}" r="3" fill="blue"{style}/>"#
+        ));
+    }
+
+    for i in 0..10 {
+        let x = 5 + i * 10;
+        svg.push_str(&format!(
+            r#"<line x1="{x}" y1="60" x2="{x}" y2="95" stroke="black" stroke-width="2"{style}/>"#
+        ));
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+#[test]
+fn batched_shapes_render_like_separate_ones() {
+    let svg = Loader::new()
+        .read_from_bytes(scatter_plot(false).as_bytes(), None)
+        .unwrap();
+    let reference = Loader::new()
+        .read_from_bytes(scatter_plot(true).as_bytes(), None)
+        .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    let reference = render_with(&reference, |r| r);
+
+    Reference::from_surface(reference.into_image_surface().unwrap())
+        .compare(&output)
+        .evaluate(&output, "batched_shapes_render_like_separate_ones");
+}
+
+fn seat_map(instanced: bool) -> String {
+    let seat =
+        r#"<rect x="1" y="1" width="6" height="5" rx="1" stroke="black" stroke-width="0.5"/>"#;
+
+    let mut svg =
+        String::from(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">"#);
+
+    svg.push_str(&format!(
+        r#"<defs><symbol id="seat">{seat}</symbol></defs>"#
+    ));
+
+    for i in 0..100 {
+        let (x, y) = (f64::from(i % 10) * 9.5, f64::from(i / 10) * 9.5 + 0.25);
+        let fill = if i % 3 == 0 { "red" } else { "green" };
+
+        if instanced {
+            svg.push_str(&format!(
+                r##"<use href="#seat" x="{x}" y="{y}" width="8" height="8" fill="{fill}"/>"##
+            ));
+        } else {
+            svg.push_str(&format!(
+                r#"<g transform="translate({x} {y})" fill="{fill}">{seat}</g>"#
+            ));
+        }
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+#[test]
+fn repeated_use_elements_render_like_separate_elements() {
+    let svg = Loader::new()
+        .read_from_bytes(seat_map(true).as_bytes(), None)
+        .unwrap();
+    let reference = Loader::new()
+        .read_from_bytes(seat_map(false).as_bytes(), None)
+        .unwrap();
+
+    let output = render_with(&svg, |r| r);
+    let reference = render_with(&reference, |r| r);
+
+    Reference::from_surface(reference.into_image_surface().unwrap())
+        .compare(&output)
+        .evaluate(
+            &output,
+            "repeated_use_elements_render_like_separate_elements",
+        );
+}
+
+#[test]
+fn geometry_includes_long_miters_and_markers() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="200">
+  <defs>
+    <marker id="square" markerUnits="userSpaceOnUse" markerWidth="20" markerHeight="20" refX="10" refY="10">
+      <rect width="20" height="20" fill="blue"/>
+    </marker>
+  </defs>
+  <polyline id="spike" points="0,100 10,0 20,100" fill="none" stroke="black" stroke-width="2" stroke-miterlimit="100"/>
+  <line id="marked" x1="10" y1="150" x2="80" y2="150" stroke="black" marker-end="url(#square)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+
+    // The miter at the top extends 1 / sin(atan(0.1)) units above the vertex.
+    let (ink_r, logical_r) = renderer.geometry_for_element(Some("#spike")).unwrap();
+    let miter_length = 1.0 / 0.1f64.atan().sin();
+    assert!((logical_r.y() - miter_length).abs() < 1e-3);
+    assert!(ink_r.height() > 100.0 + miter_length);
+
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 200.0);
+    let (ink_r, _) = renderer
+        .geometry_for_layer(Some("#marked"), &viewport)
+        .unwrap();
+    assert_eq!(ink_r, cairo::Rectangle::new(10.0, 140.0, 80.0, 20.0));
+}
+
+#[test]
+fn geometry_includes_filter_regions_clips_and_masks() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs>
+    <filter id="big" x="-50%" y="-50%" width="200%" height="200%">
+      <feOffset dx="10" dy="10"/>
+    </filter>
+    <clipPath id="clip">
+      <rect x="10" y="10" width="30" height="30"/>
+    </clipPath>
+    <mask id="mask" maskContentUnits="objectBoundingBox" x="0" y="0" width="0.5" height="1">
+      <rect width="1" height="1" fill="white"/>
+    </mask>
+  </defs>
+  <rect id="filtered" x="20" y="20" width="40" height="40" filter="url(#big)"/>
+  <rect id="shadow" x="20" y="20" width="40" height="40" filter="drop-shadow(5px 5px 2px black)"/>
+  <rect id="clipped" width="100" height="100" clip-path="url(#clip)"/>
+  <rect id="masked" width="50" height="50" mask="url(#mask)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+    let check = |id: &str, expected: cairo::Rectangle| {
+        let (ink_r, _) = renderer.geometry_for_layer(Some(id), &viewport).unwrap();
+        assert_eq!(ink_r, expected, "ink rectangle of {id}");
+    };
+
+    check("#filtered", cairo::Rectangle::new(0.0, 0.0, 80.0, 80.0));
+    check("#shadow", cairo::Rectangle::new(16.0, 16.0, 48.0, 48.0));
+    check("#clipped", cairo::Rectangle::new(10.0, 10.0, 30.0, 30.0));
+    check("#masked", cairo::Rectangle::new(0.0, 0.0, 25.0, 50.0));
+}
+
+#[test]
+fn element_at_point_finds_topmost_element_with_id() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs>
+    <rect id="template" width="20" height="20" fill="blue"/>
+  </defs>
+  <rect id="background" width="100" height="100" fill="white"/>
+  <circle id="ring" cx="50" cy="50" r="30" fill="none" stroke="black" stroke-width="4"/>
+  <g id="group">
+    <rect x="10" y="10" width="20" height="20" fill="red"/>
+  </g>
+  <use id="instance" href="#template" x="70" y="70"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+    let id_at = |x, y| {
+        renderer
+            .element_at_point(&viewport, x, y)
+            .unwrap()
+            .and_then(|element| element.id())
+    };
+
+    // Only the stroke of the ring is painted, so its center hits the background.
+    assert_eq!(id_at(50.0, 50.0).as_deref(), Some("background"));
+    assert_eq!(id_at(80.0, 50.0).as_deref(), Some("ring"));
+
+    // Elements without an id report their closest ancestor with one.
+    assert_eq!(id_at(15.0, 15.0).as_deref(), Some("group"));
+
+    assert_eq!(id_at(75.0, 75.0).as_deref(), Some("instance"));
+
+    assert_eq!(id_at(150.0, 50.0), None);
+}
+
+#[test]
+fn audit_text_contrast_samples_background_beneath_text() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+  <defs>
+    <linearGradient id="gradient">
+      <stop offset="0" stop-color="red"/>
+      <stop offset="1" stop-color="blue"/>
+    </linearGradient>
+  </defs>
+  <rect x="100" width="100" height="100" fill="black"/>
+  <g id="labels" font-family="sans-serif" font-size="20">
+    <text id="dark" x="10" y="50" fill="black">Dark</text>
+    <text x="110" y="50" fill="#333333">Dim</text>
+    <text x="10" y="90" fill="url(#gradient)">Skipped</text>
+  </g>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 200.0, 100.0);
+
+    let runs = renderer.audit_text_contrast(&viewport).unwrap();
+    assert_eq!(runs.len(), 2);
+
+    let white = cssparser::RGBA::new(Some(255), Some(255), Some(255), Some(1.0));
+    let black = cssparser::RGBA::new(Some(0), Some(0), Some(0), Some(1.0));
+
+    assert_eq!(runs[0].element_id.as_deref(), Some("dark"));
+    assert_eq!(runs[0].text, "Dark");
+    assert_eq!(runs[0].background, white);
+    assert!((runs[0].contrast_ratio - 21.0).abs() < 1e-6);
+    assert!(runs[0].bounds.x() >= 10.0 && runs[0].bounds.x() + runs[0].bounds.width() <= 100.0);
+
+    // Elements without an id report their closest ancestor with one.
+    assert_eq!(runs[1].element_id.as_deref(), Some("labels"));
+    assert_eq!(runs[1].background, black);
+    assert!(runs[1].contrast_ratio < 2.0);
+}
+
+#[test]
+fn animation_info() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="100" height="100">
+  <rect id="box" width="10" height="10">
+    <animate attributeName="x" from="0" to="90" begin="1s" dur="2s" repeatCount="2"/>
+  </rect>
+  <circle id="dot" r="5"/>
+  <animateTransform xlink:href="#dot" attributeName="transform" type="rotate" from="0" to="360" dur="500ms" repeatCount="indefinite"/>
+  <set href="#box" attributeName="fill" to="red" begin="click"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let info = svg.animation_info();
+    assert_eq!(info.duration, Some(Duration::from_secs(5)));
+    assert!(info.loops);
+
+    let ids: Vec<_> = info
+        .animated_elements
+        .iter()
+        .map(|e| e.id().unwrap())
+        .collect();
+    assert_eq!(ids, ["box", "dot"]);
+
+    let info = load_svg(STATIC_ELEMENTS).unwrap().animation_info();
+    assert_eq!(info.duration, None);
+    assert!(!info.loops);
+    assert!(info.animated_elements.is_empty());
+}
+
+#[test]
+fn animation_info_with_huge_duration() {
+    let info = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="10" height="10">
+    <animate attributeName="x" from="0" to="90" dur="100000000000000000000s"/>
+  </rect>
+</svg>
+"##,
+    )
+    .unwrap()
+    .animation_info();
+
+    assert_eq!(info.duration, None);
+    assert_eq!(info.animated_elements.len(), 1);
+}
+
+#[test]
+fn set_animation_time() {
+    let mut svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect id="box" width="10" height="10">
+    <animate attributeName="width" from="10" to="50" dur="4s" fill="freeze"/>
+    <set attributeName="height" to="30" begin="2s" end="3s"/>
+  </rect>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+    let mut check = |seconds: f64, width: f64, height: f64| {
+        svg.set_animation_time(Duration::from_secs_f64(seconds));
+
+        let renderer = CairoRenderer::new(&svg);
+        let (ink_r, _) = renderer
+            .geometry_for_layer(Some("#box"), &viewport)
+            .unwrap();
+        assert_eq!(
+            ink_r,
+            cairo::Rectangle::new(0.0, 0.0, width, height),
+            "box at {seconds}s"
+        );
+    };
+
+    check(0.0, 10.0, 10.0);
+    check(1.0, 20.0, 10.0);
+    check(2.5, 35.0, 30.0);
+    check(10.0, 50.0, 10.0);
+}
+
+#[test]
+fn dispatch_event() {
+    let mut svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <g id="button">
+    <rect id="label" width="10" height="10"/>
+  </g>
+  <rect id="box" x="20" width="10" height="10">
+    <animate attributeName="width" from="10" to="50" begin="button.click" dur="4s" fill="freeze"/>
+    <set attributeName="height" to="30" begin="click+1s"/>
+  </rect>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+    let check = |svg: &SvgHandle, width: f64, height: f64| {
+        let renderer = CairoRenderer::new(svg);
+        let (ink_r, _) = renderer
+            .geometry_for_layer(Some("#box"), &viewport)
+            .unwrap();
+        assert_eq!(ink_r, cairo::Rectangle::new(20.0, 0.0, width, height));
+    };
+
+    svg.set_animation_time(Duration::from_secs(2));
+    check(&svg, 10.0, 10.0);
+
+    // The click on the label bubbles up to the button.
+    let label = svg.element_by_id("label").unwrap();
+    svg.dispatch_event(&label, EventKind::Click);
+    check(&svg, 10.0, 10.0);
+
+    svg.set_animation_time(Duration::from_secs(4));
+    check(&svg, 30.0, 10.0);
+
+    // Events of a different kind don't begin the animations.
+    svg.dispatch_event(&label, EventKind::MouseOver);
+
+    svg.set_animation_time(Duration::from_secs(6));
+    check(&svg, 50.0, 10.0);
+
+    // A click on the box begins its own animation, but not the one for the button.
+    let rect = svg.element_by_id("box").unwrap();
+    svg.dispatch_event(&rect, EventKind::Click);
+    check(&svg, 50.0, 10.0);
+
+    svg.set_animation_time(Duration::from_secs(7));
+    check(&svg, 50.0, 30.0);
+}
+
+const FAILED_ELEMENTS: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <image href="nonexistent.png" x="0" y="0" width="50" height="50"/>
+  <rect x="50" y="50" width="50" height="50" fill="lime" filter="url(#nonexistent)"/>
+</svg>
+"#;
+
+#[test]
+fn error_placeholders_are_drawn_for_failed_elements() {
+    let svg = load_svg(FAILED_ELEMENTS).unwrap();
+
+    let output = render_with(&svg, |r| r);
+    assert_eq!(output.get_pixel(25, 25).a, 0);
+    let pixel = output.get_pixel(75, 75);
+    assert_eq!((pixel.r, pixel.g), (0, 255));
+
+    let output = render_with(&svg, |r| r.with_error_placeholders(true));
+
+    // The red cross goes through the center of each placeholder
+    let pixel = output.get_pixel(25, 25);
+    assert_eq!((pixel.r, pixel.g, pixel.a), (204, 0, 255));
+    let pixel = output.get_pixel(75, 75);
+    assert_eq!((pixel.r, pixel.g, pixel.a), (204, 0, 255));
+
+    // The rest is a checkerboard
+    assert_eq!(output.get_pixel(2, 25).a, 255);
+}
+
+#[test]
+fn features_lists_compiled_in_support() {
+    let features = rsvg::features();
+
+    assert!(!features.version.is_empty());
+    assert!(features.filters);
+    assert!(features.image_formats.contains(&"image/png"));
+    assert!(features.backends.contains(&"pdf"));
+}
+
+#[test]
+fn cloned_handles_have_their_own_stylesheets() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect width="100" height="100" fill="blue"/>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    let red = svg
+        .clone_with_overrides(Some("rect { fill: red; }"))
+        .unwrap();
+    let plain = svg.clone_with_overrides(None).unwrap();
+
+    // Interleave the renders so that each one has to apply its own styles again
+    for _ in 0..2 {
+        let pixel = render_with(&red, |r| r).get_pixel(50, 50);
+        assert_eq!((pixel.r, pixel.b), (255, 0));
+
+        let pixel = render_with(&svg, |r| r).get_pixel(50, 50);
+        assert_eq!((pixel.r, pixel.b), (0, 255));
+
+        let pixel = render_with(&plain, |r| r).get_pixel(50, 50);
+        assert_eq!((pixel.r, pixel.b), (0, 255));
+    }
+}
+
+#[test]
+fn incremental_loader_parses_chunks() {
+    let mut loader = Loader::new().begin_incremental(None::<&gio::File>).unwrap();
+
+    // One byte at a time, to go through the detection of gzipped data
+    for chunk in NESTED_GROUPS.chunks(1) {
+        loader.write(chunk).unwrap();
+    }
+
+    let svg = loader.close().unwrap();
+    let num_rects = svg
+        .root_element()
+        .descendants()
+        .filter(|e| e.name() == "rect")
+        .count();
+    assert_eq!(num_rects, 1);
+}
+
+#[test]
+fn incremental_loader_reports_errors_before_closing() {
+    let mut loader = Loader::new().begin_incremental(None::<&gio::File>).unwrap();
+
+    assert!(matches!(
+        loader.write(b"<svg xmlns=\"http://www.w3.org/2000/svg\"><g></svg>\n<!-- rest -->"),
+        Err(LoadingError::XmlParseError(_))
+    ));
+    assert!(loader.write(b"<!-- more -->").is_err());
+    assert!(loader.close().is_err());
+}
+
+#[test]
+fn render_paint_server_draws_swatch() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs>
+    <linearGradient id="gradient">
+      <stop offset="0.5" stop-color="red"/>
+      <stop offset="0.5" stop-color="blue"/>
+    </linearGradient>
+  </defs>
+  <rect id="rect" width="10" height="10" fill="url(#gradient)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let surface = svg.render_paint_server("#gradient", 40, 10).unwrap();
+    assert_eq!((surface.width(), surface.height()), (40, 10));
+
+    // The gradient's bounding box units cover the whole swatch.
+    let output = SharedImageSurface::wrap(surface, SurfaceType::SRgb).unwrap();
+    let left = output.get_pixel(10, 5);
+    let right = output.get_pixel(30, 5);
+    assert_eq!((left.r, left.b, left.a), (255, 0, 255));
+    assert_eq!((right.r, right.b, right.a), (0, 255, 255));
+
+    assert!(matches!(
+        svg.render_paint_server("#rect", 40, 10),
+        Err(RenderingError::InvalidId(_))
+    ));
+    assert!(matches!(
+        svg.render_paint_server("#nonexistent", 40, 10),
+        Err(RenderingError::IdNotFound)
+    ));
+}
+
+#[test]
+fn broken_paint_references_use_fallback_color() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect id="not-a-paint-server" x="50" width="50" height="100" fill="url(#missing)"/>
+  <rect width="50" height="100" fill="url(#not-a-paint-server) #00ff00"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    assert!(svg.broken_paint_references().is_empty());
+
+    // Each broken reference gets listed once, no matter how many times it is rendered.
+    for _ in 0..2 {
+        let output = render_with(&svg, |r| r);
+
+        let left = output.get_pixel(25, 50);
+        assert_eq!((left.r, left.g, left.b, left.a), (0, 255, 0, 255));
+        assert_eq!(output.get_pixel(75, 50).a, 0);
+    }
+
+    let broken = svg.broken_paint_references();
+    assert_eq!(broken.len(), 2);
+
+    assert_eq!(broken[0].reference, "#missing");
+    assert!(!broken[0].used_fallback);
+
+    assert_eq!(broken[1].reference, "#not-a-paint-server");
+    assert!(broken[1].used_fallback);
+}
+
+#[test]
+fn display_contents_draws_children_in_place_of_element() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="100" height="100">
+  <defs>
+    <rect id="r" width="25" height="50" fill="#00ff00"/>
+  </defs>
+  <g transform="translate(50, 0)" opacity="0.5" style="display: contents">
+    <rect width="25" height="50" fill="#00ff00"/>
+  </g>
+  <use xlink:href="#r" x="50" y="50" opacity="0.5" style="display: contents"/>
+  <rect x="50" width="50" height="100" fill="#00ff00" style="display: contents"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let output = render_with(&svg, |r| r);
+
+    let group_child = output.get_pixel(12, 25);
+    assert_eq!((group_child.g, group_child.a), (255, 255));
+
+    // The <use> has no x/y offset either; its target ends up over the group's child.
+    assert_eq!(output.get_pixel(12, 75).a, 0);
+
+    // Elements other than containers are not drawn at all.
+    assert_eq!(output.get_pixel(75, 25).a, 0);
+    assert_eq!(output.get_pixel(75, 75).a, 0);
+}
+
+#[test]
+fn elements_inside_display_none_have_no_geometry() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <g display="none">
+    <rect id="hidden" width="10" height="10"/>
+  </g>
+  <g style="display: contents">
+    <rect id="shown" width="10" height="10"/>
+  </g>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 100.0);
+
+    let (ink, _) = renderer.geometry_for_element(Some("#hidden")).unwrap();
+    assert_eq!((ink.width(), ink.height()), (0.0, 0.0));
+
+    let (ink, _) = renderer
+        .geometry_for_layer(Some("#hidden"), &viewport)
+        .unwrap();
+    assert_eq!((ink.width(), ink.height()), (0.0, 0.0));
+
+    let (ink, _) = renderer.geometry_for_element(Some("#shown")).unwrap();
+    assert_eq!((ink.width(), ink.height()), (10.0, 10.0));
+
+    let (ink, _) = renderer
+        .geometry_for_layer(Some("#shown"), &viewport)
+        .unwrap();
+    assert_eq!((ink.width(), ink.height()), (10.0, 10.0));
+}
+
+#[test]
+fn luminance_mask_uses_linear_rgb_only_with_high_quality() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <mask id="mask" maskUnits="userSpaceOnUse" x="0" y="0" width="100" height="100"
+        color-interpolation="linearRGB">
+    <rect width="100" height="100" fill="#808080"/>
+  </mask>
+  <rect width="100" height="100" fill="black" mask="url(#mask)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    // The luminance of #808080 is about 0.5 in sRGB, but 0.22 in linear RGB.
+    let output = render_with(&svg, |r| r);
+    assert!((127..=129).contains(&output.get_pixel(50, 50).a));
+
+    let output = render_with(&svg, |r| r.with_quality(RenderingQuality::High));
+    assert!((53..=57).contains(&output.get_pixel(50, 50).a));
+}
+
+#[test]
+fn broken_pattern_reference_names_the_property_iri() {
+    // The cycle goes through "b", which "a" inherits its children from; the report
+    // should name the reference from the fill property, not the node in the cycle.
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+     width="100" height="100">
+  <pattern id="b" width="10" height="10" patternUnits="userSpaceOnUse">
+    <rect width="10" height="10" fill="url(#a)"/>
+  </pattern>
+  <pattern id="a" xlink:href="#b"/>
+  <rect width="100" height="100" fill="url(#a)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let _output = render_with(&svg, |r| r);
+
+    let broken = svg.broken_paint_references();
+    assert_eq!(broken.len(), 1);
+    assert_eq!(broken[0].reference, "#a");
+    assert!(!broken[0].used_fallback);
+}
+
+#[test]
+fn color_vision_simulation_keeps_dark_grays() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+  <rect width="10" height="10" fill="rgb(3, 3, 3)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let output = render_with(&svg, |r| {
+        r.with_color_vision_simulation(Some(ColorVisionDeficiency::Deuteranopia))
+    });
+
+    let pixel = output.get_pixel(5, 5);
+    assert_eq!((pixel.r, pixel.g, pixel.b, pixel.a), (3, 3, 3, 255));
+}
@@ -20,7 +20,7 @@ fn too_many_elements() {
     assert!(matches!(
         Loader::new().read_path(name),
         Err(LoadingError::LimitExceeded(
-            ImplementationLimit::TooManyLoadedElements
+            ImplementationLimit::TooManyLoadedElements(_)
         ))
     ));
 }
@@ -40,7 +40,7 @@ fn rendering_instancing_limit(name: &str) {
         .render_document(&cr, &cairo::Rectangle::new(0.0, 0.0, 500.0, 500.0))
     {
         Ok(_) => (),
-        Err(RenderingError::LimitExceeded(ImplementationLimit::TooManyReferencedElements)) => (),
+        Err(RenderingError::LimitExceeded(ImplementationLimit::TooManyReferencedElements(_))) => (),
         _ => panic!("unexpected error code"),
     }
 }
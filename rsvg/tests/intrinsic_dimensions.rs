@@ -1,4 +1,6 @@
-use rsvg::{CairoRenderer, IntrinsicDimensions, Length, LengthUnit, RenderingError};
+use rsvg::{
+    CairoRenderer, IntrinsicDimensions, IntrinsicLengthKind, Length, LengthUnit, RenderingError,
+};
 
 use rsvg::test_utils::reference_utils::{Compare, Evaluate, Reference};
 use rsvg::test_utils::{load_svg, render_document, SurfaceSize};
@@ -41,6 +43,36 @@ fn has_intrinsic_dimensions() {
     );
 }
 
+#[test]
+fn intrinsic_length_kinds() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg"/>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        CairoRenderer::new(&svg).intrinsic_length_kinds(),
+        (IntrinsicLengthKind::Missing, IntrinsicLengthKind::Missing)
+    );
+
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100%" height="2em"/>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        CairoRenderer::new(&svg).intrinsic_length_kinds(),
+        (
+            IntrinsicLengthKind::Percentage,
+            IntrinsicLengthKind::Absolute
+        )
+    );
+}
+
 #[test]
 fn intrinsic_size_in_pixels() {
     let svg = load_svg(
@@ -56,6 +88,42 @@ fn intrinsic_size_in_pixels() {
     );
 }
 
+#[test]
+fn physical_units_dpi_applies_to_physical_documents() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="2in" height="5.08cm"/>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        CairoRenderer::new(&svg)
+            .with_dpi(96.0, 96.0)
+            .with_physical_units_dpi(300.0, 150.0)
+            .intrinsic_size_in_pixels(),
+        Some((600.0, 300.0)),
+    );
+}
+
+#[test]
+fn physical_units_dpi_does_not_apply_to_mixed_units() {
+    let svg = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="2in" height="100"/>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        CairoRenderer::new(&svg)
+            .with_dpi(96.0, 96.0)
+            .with_physical_units_dpi(300.0, 300.0)
+            .intrinsic_size_in_pixels(),
+        Some((192.0, 100.0)),
+    );
+}
+
 #[test]
 fn no_intrinsic_size_in_pixels_with_percent_dimensions() {
     let svg = load_svg(
@@ -325,6 +393,51 @@ fn layer_geometry_for_invalid_id() {
     ));
 }
 
+#[test]
+fn geometry_for_several_elements() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="400" viewBox="0 0 100 400">
+  <defs>
+    <rect id="hidden" x="0" y="0" width="10" height="10"/>
+  </defs>
+  <rect id="one" x="0" y="0" width="100" height="200" fill="rgb(0,255,0)"/>
+  <g transform="translate(0, 200)">
+    <rect id="two" x="0" y="0" width="100" height="200" fill="rgb(0,0,255)"/>
+  </g>
+  <use href="#two" transform="translate(50, 0)"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+
+    let viewport = cairo::Rectangle::new(0.0, 0.0, 100.0, 400.0);
+
+    let geometries = renderer
+        .geometry_for_elements(&["#two", "#one", "#hidden"], &viewport)
+        .unwrap();
+
+    let one = cairo::Rectangle::new(0.0, 0.0, 100.0, 200.0);
+    let two = cairo::Rectangle::new(0.0, 200.0, 100.0, 200.0);
+    let empty = cairo::Rectangle::new(0.0, 0.0, 0.0, 0.0);
+
+    assert_eq!(geometries, vec![(two, two), (one, one), (empty, empty)]);
+
+    assert_eq!(
+        geometries[0],
+        renderer
+            .geometry_for_layer(Some("#two"), &viewport)
+            .unwrap()
+    );
+
+    assert!(matches!(
+        renderer.geometry_for_elements(&["#one", "#foo"], &viewport),
+        Err(RenderingError::IdNotFound)
+    ));
+}
+
 #[test]
 fn render_to_viewport_with_different_size() {
     let svg = load_svg(
@@ -1108,6 +1108,18 @@ test_svg_reference!(
     "tests/fixtures/reftests/svg2-reftests/isolation-ref.svg"
 );
 
+test_svg_reference!(
+    isolation_clip_path,
+    "tests/fixtures/reftests/svg2-reftests/isolation-clip-path.svg",
+    "tests/fixtures/reftests/svg2-reftests/isolation-clip-path-ref.svg"
+);
+
+test_svg_reference!(
+    shared_clip_path_and_mask,
+    "tests/fixtures/reftests/svg2-reftests/shared-clip-path-and-mask.svg",
+    "tests/fixtures/reftests/svg2-reftests/shared-clip-path-and-mask-ref.svg"
+);
+
 test_svg_reference!(
     mask_and_opacity,
     "tests/fixtures/reftests/svg2-reftests/mask-and-opacity.svg",
@@ -1198,3 +1210,66 @@ test_svg_reference!(
     "tests/fixtures/reftests/markers-arc-segments.svg",
     "tests/fixtures/reftests/markers-arc-segments-ref.svg"
 );
+
+// The transform on the outermost <svg> is applied around the center of the viewport,
+// outside of the viewBox mapping, like browsers do.
+test_compare_render_output!(
+    root_svg_transform_with_viewbox,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100" viewBox="0 0 50 50"
+         transform="rotate(90)">
+      <rect x="0" y="0" width="25" height="50" fill="lime"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="0" y="0" width="100" height="50" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    root_svg_translate_is_in_viewport_units,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100" viewBox="0 0 50 50"
+         transform="translate(10 20)">
+      <rect x="0" y="0" width="10" height="10" fill="lime"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="10" y="20" width="20" height="20" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    root_svg_scale_around_center,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100" viewBox="0 0 10 10"
+         transform="scale(0.5)">
+      <rect x="0" y="0" width="10" height="10" fill="lime"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="25" y="25" width="50" height="50" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    nested_svg_transform_uses_origin,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <svg width="50" height="50" viewBox="0 0 10 10" transform="scale(2)">
+        <rect x="0" y="0" width="5" height="5" fill="lime"/>
+      </svg>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="0" y="0" width="50" height="50" fill="lime"/>
+    </svg>"##,
+);
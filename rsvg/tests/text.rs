@@ -1,7 +1,7 @@
 use float_cmp::approx_eq;
 use rsvg::{CairoRenderer, Loader};
 
-use rsvg::test_utils::setup_font_map;
+use rsvg::test_utils::{load_svg, setup_font_map};
 use rsvg::{test_compare_render_output, test_svg_reference};
 
 // From https://www.w3.org/Style/CSS/Test/Fonts/Ahem/
@@ -123,3 +123,34 @@ fn test_text_layer_geometry() {
         );
     }
 }
+
+// Test that fonts registered with the handle get used for text, instead of the system's.
+#[test]
+fn registered_font_file() {
+    let mut handle = load_svg(
+        br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="200">
+  <text id="a" x="50" y="100" font-family="Ahem" font-size="50">XX</text>
+</svg>
+"#,
+    )
+    .unwrap();
+
+    handle.add_font_file("tests/resources/Ahem.ttf").unwrap();
+
+    let renderer = CairoRenderer::new(&handle).test_mode(true);
+    let viewport = rect(0.0, 0.0, 200.0, 200.0);
+
+    let (ink_rect, _) = renderer.geometry_for_layer(Some("#a"), &viewport).unwrap();
+    let expected_ink_rect = rect(50.0, 60.0, 100.0, 50.0);
+    assert!(
+        rectangle_approx_eq(&ink_rect, &expected_ink_rect),
+        "ink_rect: {:?}, expected: {:?}",
+        ink_rect,
+        expected_ink_rect
+    );
+
+    assert!(handle
+        .add_font_file("tests/fixtures/text/bounds.svg")
+        .is_err());
+}
@@ -0,0 +1,399 @@
+//! Animated output formats.
+//!
+//! Animated SVGs are rendered by sampling their animations at a fixed frame rate; each
+//! frame is rendered to an image surface, and kept in memory until all of them can be
+//! written out with one of the encoders.  Each encoder is behind a Cargo feature with the
+//! same name as the output format.
+
+use std::io::Write;
+use std::time::Duration;
+
+use rsvg::AnimationInfo;
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+    WebpAnim,
+}
+
+/// Range of frame rates accepted by `--frame-rate`, in frames per second.
+pub const FRAME_RATES: std::ops::RangeInclusive<f64> = 0.01..=1000.0;
+
+/// Maximum number of frames to render for a single animation.
+const MAX_FRAMES: f64 = 600.0;
+
+/// Computes the times at which to render the frames of an animation, and how long each
+/// frame is shown.
+///
+/// Animations that loop are sampled for one iteration, since the output loops as well.
+/// Otherwise, the last frame shows the final state of the animation.  Long animations
+/// are sampled at a lower rate, so that they produce at most `MAX_FRAMES` frames.
+pub fn frame_times(info: &AnimationInfo, frame_rate: f64) -> Vec<(Duration, Duration)> {
+    let Some(duration) = info.duration else {
+        return vec![(Duration::ZERO, Duration::from_secs_f64(1.0 / frame_rate))];
+    };
+
+    let rate = frame_rate.min(MAX_FRAMES / duration.as_secs_f64());
+    let delay = Duration::from_secs_f64(1.0 / rate);
+    let count = (duration.as_secs_f64() * rate).ceil() as usize;
+
+    let mut frames: Vec<_> = (0..count)
+        .map(|i| {
+            let time = Duration::try_from_secs_f64(i as f64 / rate).unwrap_or(duration);
+            (time.min(duration), delay)
+        })
+        .collect();
+
+    if !info.loops || frames.is_empty() {
+        frames.push((duration, delay));
+    }
+
+    frames
+}
+
+struct Frame {
+    /// Non-premultiplied RGBA pixels.
+    rgba: Vec<u8>,
+    delay: Duration,
+}
+
+/// Frames of an animation that have been rendered so far.
+pub struct Frames {
+    format: AnimationFormat,
+    width: u32,
+    height: u32,
+    loops: bool,
+    frames: Vec<Frame>,
+}
+
+impl Frames {
+    pub fn new(format: AnimationFormat, width: i32, height: i32) -> Frames {
+        Frames {
+            format,
+            width: width as u32,
+            height: height as u32,
+            loops: false,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Sets whether the output should play in a loop, or only once.
+    pub fn set_loops(&mut self, loops: bool) {
+        self.loops = loops;
+    }
+
+    /// Adds the contents of `surface` as the next frame, to be shown for `delay`.
+    pub fn push(&mut self, surface: &cairo::ImageSurface, delay: Duration) -> Result<(), Error> {
+        let rgba = rgba_pixels(surface)?;
+        self.frames.push(Frame { rgba, delay });
+        Ok(())
+    }
+
+    pub fn encode<W: Write>(self, w: W) -> Result<(), Error> {
+        match self.format {
+            AnimationFormat::Gif => self.encode_gif(w),
+            AnimationFormat::Apng => self.encode_apng(w),
+            AnimationFormat::WebpAnim => self.encode_webp_anim(w),
+        }
+    }
+
+    #[cfg(feature = "gif")]
+    fn encode_gif<W: Write>(self, w: W) -> Result<(), Error> {
+        let too_big = || Error(String::from("The image is too large for GIF output."));
+
+        let width = u16::try_from(self.width).map_err(|_| too_big())?;
+        let height = u16::try_from(self.height).map_err(|_| too_big())?;
+
+        let mut encoder = gif::Encoder::new(w, width, height, &[])?;
+
+        if self.loops {
+            encoder.set_repeat(gif::Repeat::Infinite)?;
+        }
+
+        for mut frame in self.frames {
+            let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut frame.rgba, 10);
+
+            // GIF delays are in hundredths of a second.
+            gif_frame.delay = (frame.delay.as_secs_f64() * 100.0).round() as u16;
+
+            // Clear each frame before drawing the next one, so that transparent areas
+            // don't show the previous frame.
+            gif_frame.dispose = gif::DisposalMethod::Background;
+
+            encoder.write_frame(&gif_frame)?;
+        }
+
+        encoder.into_inner().map_err(|e| Error(format!("{e}")))?;
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "gif"))]
+    fn encode_gif<W: Write>(self, _w: W) -> Result<(), Error> {
+        Err(Error("unsupported format".to_string()))
+    }
+
+    #[cfg(feature = "apng")]
+    fn encode_apng<W: Write>(self, w: W) -> Result<(), Error> {
+        let mut encoder = png::Encoder::new(w, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        // A play count of zero means to loop forever.
+        let num_plays = if self.loops { 0 } else { 1 };
+        encoder.set_animated(self.frames.len() as u32, num_plays)?;
+
+        let mut writer = encoder.write_header()?;
+
+        for frame in self.frames {
+            let millis = u16::try_from(frame.delay.as_millis()).unwrap_or(u16::MAX);
+            writer.set_frame_delay(millis, 1000)?;
+            writer.write_image_data(&frame.rgba)?;
+        }
+
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "apng"))]
+    fn encode_apng<W: Write>(self, _w: W) -> Result<(), Error> {
+        Err(Error("unsupported format".to_string()))
+    }
+
+    /// Writes an animated WebP.
+    ///
+    /// The encoder only produces still images, so each frame gets encoded losslessly as
+    /// one of them, and its `VP8L` chunk is wrapped into an `ANMF` chunk of the animated
+    /// container, as described in <https://developers.google.com/speed/webp/docs/riff_container>.
+    #[cfg(feature = "webp-anim")]
+    fn encode_webp_anim<W: Write>(self, mut w: W) -> Result<(), Error> {
+        // Sizes and durations are stored in 24 bits.
+        const MAX_24: u32 = (1 << 24) - 1;
+
+        if self.width == 0 || self.height == 0 {
+            return Err(Error(String::from("WebP output cannot be empty.")));
+        }
+
+        if self.width > MAX_24 || self.height > MAX_24 {
+            return Err(Error(String::from(
+                "The image is too large for WebP output.",
+            )));
+        }
+
+        let mut chunks = Vec::new();
+
+        // Animation and alpha flags, then the canvas size.
+        let mut vp8x = vec![0x02 | 0x10, 0, 0, 0];
+        vp8x.extend_from_slice(&u24(self.width - 1));
+        vp8x.extend_from_slice(&u24(self.height - 1));
+        write_chunk(&mut chunks, b"VP8X", &vp8x);
+
+        // Transparent background, and a loop count of zero to loop forever.
+        let loop_count: u16 = if self.loops { 0 } else { 1 };
+        let mut anim = vec![0, 0, 0, 0];
+        anim.extend_from_slice(&loop_count.to_le_bytes());
+        write_chunk(&mut chunks, b"ANIM", &anim);
+
+        for frame in self.frames {
+            let mut still = Vec::new();
+            image_webp::WebPEncoder::new(&mut still).encode(
+                &frame.rgba,
+                self.width,
+                self.height,
+                image_webp::ColorType::Rgba8,
+            )?;
+
+            let bitstream = find_chunk(&still, b"VP8L").ok_or_else(|| {
+                Error(String::from(
+                    "The WebP encoder did not produce a VP8L chunk.",
+                ))
+            })?;
+
+            let duration = frame.delay.as_millis().min(u128::from(MAX_24)) as u32;
+
+            // Each frame covers the whole canvas at (0, 0), and replaces the previous one
+            // without blending with it.
+            let mut anmf = vec![0; 6];
+            anmf.extend_from_slice(&u24(self.width - 1));
+            anmf.extend_from_slice(&u24(self.height - 1));
+            anmf.extend_from_slice(&u24(duration));
+            anmf.push(0x02);
+            write_chunk(&mut anmf, b"VP8L", bitstream);
+
+            write_chunk(&mut chunks, b"ANMF", &anmf);
+        }
+
+        let riff_size = u32::try_from(chunks.len() + 4)
+            .map_err(|_| Error(String::from("The animation is too large for WebP output.")))?;
+
+        let mut header = Vec::with_capacity(12);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&riff_size.to_le_bytes());
+        header.extend_from_slice(b"WEBP");
+
+        w.write_all(&header)
+            .and_then(|_| w.write_all(&chunks))
+            .map_err(|e| Error(format!("{e}")))
+    }
+
+    #[cfg(not(feature = "webp-anim"))]
+    fn encode_webp_anim<W: Write>(self, _w: W) -> Result<(), Error> {
+        Err(Error("unsupported format".to_string()))
+    }
+}
+
+/// Little-endian bytes of a 24-bit value, as used by the WebP container.
+#[cfg(feature = "webp-anim")]
+fn u24(v: u32) -> [u8; 3] {
+    let [a, b, c, _] = v.to_le_bytes();
+    [a, b, c]
+}
+
+/// Appends a RIFF chunk to `out`, padded to an even size.
+#[cfg(feature = "webp-anim")]
+fn write_chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], payload: &[u8]) {
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+
+    if payload.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Finds the payload of the chunk named `fourcc` in a WebP file.
+#[cfg(feature = "webp-anim")]
+fn find_chunk<'a>(webp: &'a [u8], fourcc: &[u8; 4]) -> Option<&'a [u8]> {
+    // Skip the "RIFF", size, and "WEBP" header.
+    let mut rest = webp.get(12..)?;
+
+    while rest.len() >= 8 {
+        let size = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let payload = rest.get(8..8 + size)?;
+
+        if &rest[..4] == fourcc {
+            return Some(payload);
+        }
+
+        rest = rest.get(8 + size + size % 2..).unwrap_or_default();
+    }
+
+    None
+}
+
+#[cfg(feature = "gif")]
+impl From<gif::EncodingError> for Error {
+    fn from(e: gif::EncodingError) -> Self {
+        Self(format!("{e}"))
+    }
+}
+
+#[cfg(feature = "apng")]
+impl From<png::EncodingError> for Error {
+    fn from(e: png::EncodingError) -> Self {
+        Self(format!("{e}"))
+    }
+}
+
+#[cfg(feature = "webp-anim")]
+impl From<image_webp::EncodingError> for Error {
+    fn from(e: image_webp::EncodingError) -> Self {
+        Self(format!("{e}"))
+    }
+}
+
+/// Converts the premultiplied ARGB pixels of a Cairo surface to straight RGBA.
+pub fn rgba_pixels(surface: &cairo::ImageSurface) -> Result<Vec<u8>, Error> {
+    surface.flush();
+
+    let width = surface.width() as usize;
+    let height = surface.height() as usize;
+    let stride = surface.stride() as usize;
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+
+    surface
+        .with_data(|data| {
+            for row in data.chunks(stride).take(height) {
+                for pixel in row[..width * 4].chunks_exact(4) {
+                    let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+
+                    let a = (argb >> 24) as u8;
+                    let unpremultiply = |c: u32| {
+                        let c = c & 0xff;
+                        if a == 0 {
+                            0
+                        } else {
+                            ((c * 255 + u32::from(a) / 2) / u32::from(a)) as u8
+                        }
+                    };
+
+                    rgba.extend_from_slice(&[
+                        unpremultiply(argb >> 16),
+                        unpremultiply(argb >> 8),
+                        unpremultiply(argb),
+                        a,
+                    ]);
+                }
+            }
+        })
+        .map_err(|e| Error(format!("{e}")))?;
+
+    Ok(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(duration: Option<f64>, loops: bool) -> AnimationInfo {
+        AnimationInfo {
+            duration: duration.map(Duration::from_secs_f64),
+            loops,
+            animated_elements: Vec::new(),
+        }
+    }
+
+    fn seconds(frames: &[(Duration, Duration)]) -> Vec<f64> {
+        frames.iter().map(|(t, _)| t.as_secs_f64()).collect()
+    }
+
+    #[test]
+    fn samples_one_iteration_of_looping_animations() {
+        let frames = frame_times(&info(Some(1.0), true), 4.0);
+        assert_eq!(seconds(&frames), [0.0, 0.25, 0.5, 0.75]);
+        assert!(frames
+            .iter()
+            .all(|(_, delay)| *delay == Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn ends_with_the_final_state() {
+        let frames = frame_times(&info(Some(0.5), false), 4.0);
+        assert_eq!(seconds(&frames), [0.0, 0.25, 0.5]);
+    }
+
+    #[test]
+    fn static_documents_have_one_frame() {
+        let frames = frame_times(&info(None, false), 10.0);
+        assert_eq!(seconds(&frames), [0.0]);
+
+        let frames = frame_times(&info(Some(0.0), false), 10.0);
+        assert_eq!(seconds(&frames), [0.0]);
+    }
+
+    #[test]
+    fn long_animations_have_a_bounded_number_of_frames() {
+        let frames = frame_times(&info(Some(100_000.0), false), 10.0);
+        assert_eq!(frames.len(), 601);
+        assert!((frames[1].0.as_secs_f64() - 100_000.0 / 600.0).abs() < 1e-6);
+        assert_eq!(frames[600].0.as_secs_f64(), 100_000.0);
+
+        let frames = frame_times(&info(Some(1.0e19), true), 10.0);
+        assert_eq!(frames.len(), 600);
+    }
+}
@@ -0,0 +1,150 @@
+//! Icon container output.
+//!
+//! The document is rendered once for each icon size, and each rendering is stored as a PNG
+//! image inside an ICO or ICNS container.  Both formats allow PNG payloads, so no other
+//! encoder is needed.
+
+use std::io::Write;
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IconFormat {
+    Ico,
+    Icns,
+}
+
+impl IconFormat {
+    /// Sizes in pixels that are rendered when none are requested.
+    pub fn default_sizes(self) -> &'static [u32] {
+        match self {
+            IconFormat::Ico => &[16, 24, 32, 48, 64, 128, 256],
+            IconFormat::Icns => &[16, 32, 64, 128, 256, 512, 1024],
+        }
+    }
+
+    /// Checks that the container can store an icon of `size` by `size` pixels.
+    pub fn check_size(self, size: u32) -> Result<(), Error> {
+        match self {
+            IconFormat::Ico if (1..=256).contains(&size) => Ok(()),
+            IconFormat::Ico => Err(Error(format!(
+                "ICO icons must be between 1 and 256 pixels; {size} is not supported."
+            ))),
+
+            IconFormat::Icns if icns_type(size).is_some() => Ok(()),
+            IconFormat::Icns => Err(Error(format!(
+                "ICNS icons must be 16, 32, 64, 128, 256, 512, or 1024 pixels; \
+                 {size} is not supported."
+            ))),
+        }
+    }
+}
+
+/// Returns the ICNS element type for PNG data of a given size.
+fn icns_type(size: u32) -> Option<&'static [u8; 4]> {
+    match size {
+        16 => Some(b"icp4"),
+        32 => Some(b"icp5"),
+        64 => Some(b"icp6"),
+        128 => Some(b"ic07"),
+        256 => Some(b"ic08"),
+        512 => Some(b"ic09"),
+        1024 => Some(b"ic10"),
+        _ => None,
+    }
+}
+
+struct Icon {
+    size: u32,
+    png: Vec<u8>,
+}
+
+/// Icons that have been rendered so far.
+pub struct Icons {
+    format: IconFormat,
+    icons: Vec<Icon>,
+}
+
+impl Icons {
+    pub fn new(format: IconFormat) -> Icons {
+        Icons {
+            format,
+            icons: Vec::new(),
+        }
+    }
+
+    /// Adds the contents of `surface`, which must be square, as the next icon.
+    pub fn push(&mut self, surface: &cairo::ImageSurface) -> Result<(), Error> {
+        let mut png = Vec::new();
+        surface.write_to_png(&mut png)?;
+
+        self.icons.push(Icon {
+            size: surface.width() as u32,
+            png,
+        });
+
+        Ok(())
+    }
+
+    pub fn encode<W: Write>(self, w: W) -> Result<(), Error> {
+        match self.format {
+            IconFormat::Ico => self.encode_ico(w),
+            IconFormat::Icns => self.encode_icns(w),
+        }
+        .map_err(|e| Error(format!("{e}")))
+    }
+
+    fn encode_ico<W: Write>(self, mut w: W) -> std::io::Result<()> {
+        const HEADER_LEN: usize = 6;
+        const ENTRY_LEN: usize = 16;
+
+        w.write_all(&0u16.to_le_bytes())?; // reserved
+        w.write_all(&1u16.to_le_bytes())?; // type: icon
+        w.write_all(&(self.icons.len() as u16).to_le_bytes())?;
+
+        let mut offset = HEADER_LEN + ENTRY_LEN * self.icons.len();
+
+        for icon in &self.icons {
+            // A dimension of 0 means 256 pixels.
+            let dimension = if icon.size == 256 { 0 } else { icon.size as u8 };
+
+            w.write_all(&[dimension, dimension, 0, 0])?; // width, height, palette, reserved
+            w.write_all(&1u16.to_le_bytes())?; // color planes
+            w.write_all(&32u16.to_le_bytes())?; // bits per pixel
+            w.write_all(&(icon.png.len() as u32).to_le_bytes())?;
+            w.write_all(&(offset as u32).to_le_bytes())?;
+
+            offset += icon.png.len();
+        }
+
+        for icon in &self.icons {
+            w.write_all(&icon.png)?;
+        }
+
+        Ok(())
+    }
+
+    fn encode_icns<W: Write>(self, mut w: W) -> std::io::Result<()> {
+        const HEADER_LEN: usize = 8;
+
+        let total_len = HEADER_LEN
+            + self
+                .icons
+                .iter()
+                .map(|icon| HEADER_LEN + icon.png.len())
+                .sum::<usize>();
+
+        w.write_all(b"icns")?;
+        w.write_all(&(total_len as u32).to_be_bytes())?;
+
+        for icon in &self.icons {
+            let icon_type = icns_type(icon.size).expect("sizes are checked when parsing options");
+
+            w.write_all(icon_type)?;
+            w.write_all(&((HEADER_LEN + icon.png.len()) as u32).to_be_bytes())?;
+            w.write_all(&icon.png)?;
+        }
+
+        Ok(())
+    }
+}
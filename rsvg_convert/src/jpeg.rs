@@ -0,0 +1,70 @@
+//! JPEG output.
+//!
+//! JPEG has no alpha channel, so the rendered image is composited over opaque white
+//! before encoding.  A background color given with `--background-color` has already
+//! been painted under the document by then.
+
+use std::io::Write;
+
+use crate::Error;
+
+#[cfg(feature = "jpeg")]
+pub fn encode<W: Write>(surface: &cairo::ImageSurface, quality: u8, w: W) -> Result<(), Error> {
+    let rgb = flattened_rgb_pixels(surface)?;
+
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(w, quality);
+    encoder.encode(
+        &rgb,
+        surface.width() as u32,
+        surface.height() as u32,
+        image::ColorType::Rgb8,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "jpeg"))]
+pub fn encode<W: Write>(_surface: &cairo::ImageSurface, _quality: u8, _w: W) -> Result<(), Error> {
+    Err(Error("unsupported format".to_string()))
+}
+
+#[cfg(feature = "jpeg")]
+impl From<image::ImageError> for Error {
+    fn from(e: image::ImageError) -> Self {
+        Self(format!("{e}"))
+    }
+}
+
+/// Composites a surface over white, and returns its pixels as packed RGB.
+#[cfg(feature = "jpeg")]
+fn flattened_rgb_pixels(surface: &cairo::ImageSurface) -> Result<Vec<u8>, Error> {
+    let width = surface.width();
+    let height = surface.height();
+
+    let mut flat = cairo::ImageSurface::create(cairo::Format::Rgb24, width, height)?;
+
+    {
+        let cr = cairo::Context::new(&flat)?;
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.paint()?;
+        cr.set_source_surface(surface, 0.0, 0.0)?;
+        cr.paint()?;
+    }
+
+    let width = width as usize;
+    let height = height as usize;
+    let stride = flat.stride() as usize;
+
+    let mut rgb = Vec::with_capacity(width * height * 3);
+
+    let data = flat.data().map_err(|e| Error(format!("{e}")))?;
+
+    for row in data.chunks(stride).take(height) {
+        for pixel in row[..width * 4].chunks_exact(4) {
+            let xrgb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            rgb.extend_from_slice(&[(xrgb >> 16) as u8, (xrgb >> 8) as u8, xrgb as u8]);
+        }
+    }
+
+    Ok(rgb)
+}
@@ -26,12 +26,39 @@ use rsvg::rsvg_convert_only::{
     set_source_color_on_cairo, AspectRatio, CssLength, Dpi, Horizontal, Length, Normalize,
     NormalizeParams, Parse, Rect, Signed, ULength, Unsigned, Validate, Vertical, ViewBox,
 };
-use rsvg::{AcceptLanguage, CairoRenderer, Language, LengthUnit, Loader, RenderingError};
+use rsvg::{
+    AcceptLanguage, CairoRenderer, ColorVisionDeficiency, Language, LengthUnit, Loader,
+    RenderingError, RenderingQuality, SvgHandle,
+};
 
+use std::borrow::BorrowMut;
+use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+mod animation;
+use animation::{AnimationFormat, Frames};
+
+mod icon;
+use icon::{IconFormat, Icons};
+
+mod jpeg;
+
+mod sprite;
+
+mod template;
+use template::{OutputTemplate, TemplateValues};
+
+mod tiff;
+use self::tiff::{Pages, TiffCompression};
+
+mod watch;
+use watch::Watcher;
 
 #[derive(Debug)]
 pub struct Error(String);
@@ -220,6 +247,10 @@ impl ResizeStrategy {
 
 enum Surface {
     Png(cairo::ImageSurface, OutputStream),
+    Jpeg(cairo::ImageSurface, u8, OutputStream),
+    Tiff(cairo::ImageSurface, Pages, OutputStream),
+    Icon(cairo::ImageSurface, Icons, OutputStream),
+    Animation(cairo::ImageSurface, Frames, OutputStream),
     #[cfg(system_deps_have_cairo_pdf)]
     Pdf(cairo::PdfSurface, Size),
     #[cfg(system_deps_have_cairo_ps)]
@@ -234,6 +265,10 @@ impl Deref for Surface {
     fn deref(&self) -> &cairo::Surface {
         match self {
             Self::Png(surface, _) => surface,
+            Self::Jpeg(surface, _, _) => surface,
+            Self::Tiff(surface, _, _) => surface,
+            Self::Icon(surface, _, _) => surface,
+            Self::Animation(surface, _, _) => surface,
             #[cfg(system_deps_have_cairo_pdf)]
             Self::Pdf(surface, _) => surface,
             #[cfg(system_deps_have_cairo_ps)]
@@ -256,26 +291,74 @@ impl Surface {
         size: Size,
         stream: OutputStream,
         unit: LengthUnit,
+        jpeg_quality: u8,
+        tiff_compression: TiffCompression,
+        pdf_info: &metadata::PdfInfo,
+        dpi: Dpi,
     ) -> Result<Self, Error> {
         match format {
             Format::Png => Self::new_for_png(size, stream),
-            Format::Pdf => Self::new_for_pdf(size, stream, None),
-            Format::Pdf1_7 => Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_7)),
-            Format::Pdf1_6 => Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_6)),
-            Format::Pdf1_5 => Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_5)),
-            Format::Pdf1_4 => Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_4)),
+            Format::Jpeg => Self::new_for_jpeg(size, stream, jpeg_quality),
+            Format::Tiff => Self::new_for_tiff(size, stream, tiff_compression, dpi),
+            Format::Ico => Self::new_for_icon(size, stream, IconFormat::Ico),
+            Format::Icns => Self::new_for_icon(size, stream, IconFormat::Icns),
+            Format::Pdf => Self::new_for_pdf(size, stream, None, pdf_info),
+            Format::Pdf1_7 => {
+                Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_7), pdf_info)
+            }
+            Format::Pdf1_6 => {
+                Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_6), pdf_info)
+            }
+            Format::Pdf1_5 => {
+                Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_5), pdf_info)
+            }
+            Format::Pdf1_4 => {
+                Self::new_for_pdf(size, stream, Some(cairo::PdfVersion::_1_4), pdf_info)
+            }
             Format::Ps => Self::new_for_ps(size, stream, false),
             Format::Eps => Self::new_for_ps(size, stream, true),
             Format::Svg => Self::new_for_svg(size, stream, unit),
+            Format::Gif => Self::new_for_animation(size, stream, AnimationFormat::Gif),
+            Format::Apng => Self::new_for_animation(size, stream, AnimationFormat::Apng),
+            Format::WebpAnim => Self::new_for_animation(size, stream, AnimationFormat::WebpAnim),
         }
     }
 
     fn new_for_png(size: Size, stream: OutputStream) -> Result<Self, Error> {
-        // We use ceil() to avoid chopping off the last pixel if it is partially covered.
+        let surface = new_image_surface(size)?;
+        Ok(Self::Png(surface, stream))
+    }
+
+    fn new_for_jpeg(size: Size, stream: OutputStream, quality: u8) -> Result<Self, Error> {
+        let surface = new_image_surface(size)?;
+        Ok(Self::Jpeg(surface, quality, stream))
+    }
+
+    fn new_for_tiff(
+        size: Size,
+        stream: OutputStream,
+        compression: TiffCompression,
+        dpi: Dpi,
+    ) -> Result<Self, Error> {
+        let surface = new_image_surface(size)?;
+        let pages = Pages::new(compression, dpi.x, dpi.y);
+        Ok(Self::Tiff(surface, pages, stream))
+    }
+
+    fn new_for_icon(size: Size, stream: OutputStream, format: IconFormat) -> Result<Self, Error> {
+        let surface = new_image_surface(size)?;
+        Ok(Self::Icon(surface, Icons::new(format), stream))
+    }
+
+    fn new_for_animation(
+        size: Size,
+        stream: OutputStream,
+        format: AnimationFormat,
+    ) -> Result<Self, Error> {
         let w = checked_i32(size.w.ceil())?;
         let h = checked_i32(size.h.ceil())?;
         let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, w, h)?;
-        Ok(Self::Png(surface, stream))
+        Ok(Self::Animation(surface, Frames::new(format, w, h), stream))
     }
 
     #[cfg(system_deps_have_cairo_pdf)]
@@ -283,14 +366,33 @@ impl Surface {
         size: Size,
         stream: OutputStream,
         version: Option<cairo::PdfVersion>,
+        info: &metadata::PdfInfo,
     ) -> Result<Self, Error> {
         let surface = cairo::PdfSurface::for_stream(size.w, size.h, stream.into_write())?;
         if let Some(ver) = version {
             surface.restrict(ver)?;
         }
-        if let Some(date) = metadata::creation_date()? {
+
+        let date = match info.creation_date {
+            Some(ref date) => Some(date.clone()),
+            None => metadata::creation_date()?,
+        };
+        if let Some(date) = date {
             surface.set_metadata(cairo::PdfMetadata::CreateDate, &date)?;
         }
+
+        let fields = [
+            (cairo::PdfMetadata::Title, &info.title),
+            (cairo::PdfMetadata::Author, &info.author),
+            (cairo::PdfMetadata::Subject, &info.subject),
+            (cairo::PdfMetadata::Keywords, &info.keywords),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                surface.set_metadata(field, value)?;
+            }
+        }
+
         Ok(Self::Pdf(surface, size))
     }
 
@@ -341,6 +443,7 @@ impl Surface {
         top: f64,
         final_size: Size,
         geometry: cairo::Rectangle,
+        viewport: cairo::Rectangle,
         background_color: Option<Color>,
         id: Option<&str>,
     ) -> Result<(), Error> {
@@ -365,23 +468,59 @@ impl Surface {
 
         cr.scale(scale.x, scale.y);
 
-        let viewport = cairo::Rectangle::new(0.0, 0.0, geometry.width(), geometry.height());
-
         match id {
             None => renderer.render_document(&cr, &viewport)?,
             Some(_) => renderer.render_element(&cr, id, &viewport)?,
         }
 
-        if !matches!(self, Self::Png(_, _)) {
+        if !matches!(
+            self,
+            Self::Png(_, _)
+                | Self::Jpeg(_, _, _)
+                | Self::Tiff(_, _, _)
+                | Self::Icon(_, _, _)
+                | Self::Animation(_, _, _)
+        ) {
             cr.show_page()?;
         }
 
         Ok(())
     }
 
+    /// For animated formats, adds what has been rendered so far as a frame, and clears the
+    /// surface for the next one.
+    pub fn push_frame(&mut self, delay: Duration) -> Result<(), Error> {
+        if let Self::Animation(surface, frames, _) = self {
+            frames.push(surface, delay)?;
+
+            let cr = cairo::Context::new(&*surface)?;
+            cr.set_operator(cairo::Operator::Clear);
+            cr.paint()?;
+        }
+
+        Ok(())
+    }
+
+    /// For multi-page raster formats, adds what has been rendered so far as a page.
+    pub fn end_page(&mut self) -> Result<(), Error> {
+        match self {
+            Self::Tiff(surface, pages, _) => pages.push(surface)?,
+            Self::Icon(surface, icons, _) => icons.push(surface)?,
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     pub fn finish(self) -> Result<(), Error> {
         match self {
             Self::Png(surface, stream) => surface.write_to_png(&mut stream.into_write())?,
+            Self::Jpeg(surface, quality, stream) => {
+                jpeg::encode(&surface, quality, stream.into_write())?
+            }
+            Self::Tiff(_, pages, stream) => pages.encode(stream.into_write())?,
+            Self::Icon(_, icons, stream) => icons.encode(stream.into_write())?,
+            Self::Animation(_, frames, stream) => frames.encode(stream.into_write())?,
             _ => self.finish_output_stream().map(|_| ())?,
         }
 
@@ -389,6 +528,14 @@ impl Surface {
     }
 }
 
+/// Creates an image surface for a page of raster output.
+fn new_image_surface(size: Size) -> Result<cairo::ImageSurface, Error> {
+    // We use ceil() to avoid chopping off the last pixel if it is partially covered.
+    let w = checked_i32(size.w.ceil())?;
+    let h = checked_i32(size.h.ceil())?;
+    Ok(cairo::ImageSurface::create(cairo::Format::ARgb32, w, h)?)
+}
+
 fn checked_i32(x: f64) -> Result<i32, cairo::Error> {
     cast::i32(x).map_err(|_| cairo::Error::InvalidSize)
 }
@@ -414,6 +561,54 @@ mod metadata {
             )),
         }
     }
+
+    /// Document information for PDF output, from the `--pdf-*` options.
+    #[derive(Clone, Debug, Default)]
+    pub struct PdfInfo {
+        pub title: Option<String>,
+        pub author: Option<String>,
+        pub subject: Option<String>,
+        pub keywords: Option<String>,
+
+        /// In RFC 3339 format; this takes precedence over `$SOURCE_DATE_EPOCH`.
+        pub creation_date: Option<String>,
+    }
+
+    impl PdfInfo {
+        pub fn is_empty(&self) -> bool {
+            self.title.is_none()
+                && self.author.is_none()
+                && self.subject.is_none()
+                && self.keywords.is_none()
+                && self.creation_date.is_none()
+        }
+    }
+
+    /// Parses the value of `--pdf-creation-date`.
+    ///
+    /// This takes a date and time in RFC 3339 format, a date like `2024-05-17` for midnight
+    /// UTC, or a number of seconds since the Unix epoch like `$SOURCE_DATE_EPOCH`.
+    pub fn parse_date(v: &str) -> Result<String, String> {
+        if let Ok(datetime) = DateTime::parse_from_rfc3339(v) {
+            return Ok(datetime.to_rfc3339());
+        }
+
+        if let Ok(date) = NaiveDate::parse_from_str(v, "%Y-%m-%d") {
+            let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+            return Ok(Utc.from_utc_datetime(&midnight).to_rfc3339());
+        }
+
+        i64::from_str(v)
+            .ok()
+            .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+            .map(|datetime| datetime.to_rfc3339())
+            .ok_or_else(|| {
+                String::from(
+                    "Expected a date like 2024-05-17, a date and time like \
+                     2024-05-17T12:00:00Z, or seconds since the Unix epoch",
+                )
+            })
+    }
 }
 
 struct Stdin;
@@ -470,6 +665,9 @@ impl std::fmt::Display for Input {
 enum Output {
     Stdout,
     Path(PathBuf),
+
+    /// Each input file goes to its own output file, named after the template.
+    Template(OutputTemplate),
 }
 
 impl std::fmt::Display for Output {
@@ -477,14 +675,100 @@ impl std::fmt::Display for Output {
         match self {
             Output::Stdout => "stdout".fmt(f),
             Output::Path(p) => p.display().fmt(f),
+            Output::Template(t) => t.fmt(f),
         }
     }
 }
 
+/// A conversion to run for an output template.
+enum Job<'a> {
+    /// Converts an input, or its object with the given id, to an output file.
+    Convert(&'a Input, Option<String>, Output),
+
+    /// Converts each object of an input that matches `--select` to its own output file.
+    /// Has the name and index of the input for expanding the template.
+    Select(&'a Input, PathBuf, usize),
+}
+
+/// The output files from a template, with what gets written to each one.
+struct TemplateOutputs<'a> {
+    template: &'a OutputTemplate,
+    zoom: f64,
+    sources: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl<'a> TemplateOutputs<'a> {
+    fn new(template: &'a OutputTemplate, zoom: f64) -> TemplateOutputs<'a> {
+        TemplateOutputs {
+            template,
+            zoom,
+            sources: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.sources.lock().unwrap().is_empty()
+    }
+
+    /// Expands the template for an input, or its object with `export_id`, and creates
+    /// the directory for the output file.
+    ///
+    /// Two conversions that write to the same file would clobber each other, or worse,
+    /// write to it at the same time with --jobs, so this fails if another conversion
+    /// already has the file.
+    fn claim(
+        &self,
+        input: &Input,
+        name: &Path,
+        index: usize,
+        export_id: Option<&str>,
+    ) -> Result<Output, Error> {
+        let path = self.template.expand(&TemplateValues {
+            name,
+            index,
+            zoom: self.zoom,
+            id: export_id,
+        })?;
+
+        let source = match export_id {
+            Some(id) => format!("{id} of {input}"),
+            None => input.to_string(),
+        };
+
+        if let Some(other) = self
+            .sources
+            .lock()
+            .unwrap()
+            .insert(path.clone(), source.clone())
+        {
+            return Err(error!(
+                "Both {} and {} would be written to \"{}\".",
+                other,
+                source,
+                path.display()
+            ));
+        }
+
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                error!(
+                    "Error creating output directory \"{}\": {}",
+                    dir.display(),
+                    e
+                )
+            })?;
+        }
+
+        Ok(Output::Path(path))
+    }
+}
+
 // Keep this enum in sync with supported_formats in parse_args()
 #[derive(Clone, Copy, Debug)]
 enum Format {
     Png,
+    Jpeg,
+    Tiff,
     Pdf,
     Pdf1_7,
     Pdf1_6,
@@ -493,6 +777,25 @@ enum Format {
     Ps,
     Eps,
     Svg,
+    Gif,
+    Apng,
+    WebpAnim,
+    Ico,
+    Icns,
+}
+
+impl Format {
+    fn is_animated(self) -> bool {
+        matches!(self, Format::Gif | Format::Apng | Format::WebpAnim)
+    }
+
+    fn icon_format(self) -> Option<IconFormat> {
+        match self {
+            Format::Ico => Some(IconFormat::Ico),
+            Format::Icns => Some(IconFormat::Icns),
+            _ => None,
+        }
+    }
 }
 
 struct Converter {
@@ -505,20 +808,82 @@ struct Converter {
     pub top: Option<Length<Vertical>>,
     pub page_size: Option<(ULength<Horizontal>, ULength<Vertical>)>,
     pub format: Format,
-    pub export_id: Option<String>,
+    pub export_ids: Vec<String>,
+    pub select: Option<String>,
+    pub sprite_sheet: Option<PathBuf>,
+    pub export_area: Option<Rect>,
+    pub pages: bool,
     pub keep_aspect_ratio: bool,
     pub background_color: Option<Color>,
+    pub supersampling: u32,
+    pub simulate_cvd: Option<ColorVisionDeficiency>,
+    pub linear_light: bool,
+    pub quality: RenderingQuality,
+    pub jpeg_quality: u8,
+    pub tiff_compression: TiffCompression,
+    pub pdf_info: metadata::PdfInfo,
+    pub simplify_paths: Option<f64>,
+    pub error_placeholders: bool,
+    pub frame_rate: f64,
+    pub icon_sizes: Vec<u32>,
     pub stylesheet: Option<PathBuf>,
     pub language: Language,
     pub unlimited: bool,
     pub keep_image_data: bool,
     pub input: Vec<Input>,
     pub output: Output,
+    pub listing: Option<Listing>,
+    pub jobs: Option<usize>,
+    pub watch: bool,
     pub testing: bool,
 }
 
+/// Information to print about the input files instead of converting them.
+#[derive(Copy, Clone)]
+enum Listing {
+    /// Element ids and the names of their elements.
+    Ids,
+
+    /// Font families requested by the text.
+    Fonts,
+}
+
 impl Converter {
-    pub fn convert(self) -> Result<(), Error> {
+    /// Converts the inputs, and then converts them again whenever they or the stylesheet
+    /// change, until the process is killed.
+    ///
+    /// Errors in each conversion are printed instead of returned, so that saving a broken
+    /// file while editing it does not stop the watching.
+    pub fn watch(&self) -> Result<(), Error> {
+        let local_path = |input: &Input| match input {
+            Input::Named(p) => p
+                .get_gfile()
+                .path()
+                .ok_or_else(|| error!("Cannot watch {} since it is not a local file.", input)),
+            Input::Stdin => Err(error!("Cannot watch stdin.")),
+        };
+
+        let mut paths = self
+            .input
+            .iter()
+            .map(local_path)
+            .collect::<Result<Vec<PathBuf>, Error>>()?;
+
+        paths.extend(self.stylesheet.iter().cloned());
+
+        let mut watcher = Watcher::new(paths);
+
+        loop {
+            match self.convert() {
+                Ok(()) => std::eprintln!("Wrote {}", self.output),
+                Err(e) => std::eprintln!("{e}"),
+            }
+
+            watcher.wait_for_change();
+        }
+    }
+
+    pub fn convert(&self) -> Result<(), Error> {
         let stylesheet = match self.stylesheet {
             Some(ref p) => std::fs::read_to_string(p)
                 .map(Some)
@@ -526,6 +891,178 @@ impl Converter {
             None => None,
         };
 
+        if let Some(ref map) = self.sprite_sheet {
+            return self.convert_sprite_sheet(stylesheet.as_deref(), &self.input[0], map);
+        }
+
+        let Output::Template(ref template) = self.output else {
+            let export_id = self.export_ids.first().map(String::as_str);
+            return self.convert_inputs(
+                stylesheet.as_deref(),
+                &self.input,
+                export_id,
+                &self.output,
+            );
+        };
+
+        let outputs = TemplateOutputs::new(template, self.zoom.x);
+        let mut jobs = Vec::with_capacity(self.input.len());
+
+        for (i, input) in self.input.iter().enumerate() {
+            let name = match input {
+                Input::Named(p) => p.get_gfile().basename(),
+                Input::Stdin => None,
+            }
+            .ok_or_else(|| error!("Cannot name the output for {} with a template.", input))?;
+
+            // The elements that match --select can only be known by loading the input,
+            // so the job does that, and then converts them all from the same document.
+            if self.select.is_some() {
+                jobs.push(Job::Select(input, name, i + 1));
+                continue;
+            }
+
+            // With several ids, each object of each input goes to its own output file.
+            let export_ids: Vec<Option<String>> =
+                if self.export_ids.is_empty() || self.listing.is_some() {
+                    vec![None]
+                } else {
+                    self.export_ids.iter().cloned().map(Some).collect()
+                };
+
+            for export_id in export_ids {
+                let output = outputs.claim(input, &name, i + 1, export_id.as_deref())?;
+                jobs.push(Job::Convert(input, export_id, output));
+            }
+        }
+
+        let cpus = || std::thread::available_parallelism().map_or(1, |n| n.get());
+
+        // Listings get printed to stdout, so they must come out in order.
+        let threads = match self.listing {
+            Some(_) => 1,
+            None => self.jobs.unwrap_or_else(cpus),
+        };
+
+        self.run_jobs(stylesheet.as_deref(), &jobs, &outputs, threads)?;
+
+        match self.select {
+            Some(ref selectors) if outputs.is_empty() => {
+                Err(error!("No elements match --select \"{}\".", selectors))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs the conversions for an output template, with up to `threads` of them at a
+    /// time.
+    ///
+    /// After a conversion fails, no new ones are started.  The error for the first job
+    /// that failed is returned.
+    fn run_jobs(
+        &self,
+        stylesheet: Option<&str>,
+        jobs: &[Job<'_>],
+        outputs: &TemplateOutputs<'_>,
+        threads: usize,
+    ) -> Result<(), Error> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let errors = Mutex::new(Vec::new());
+
+        let worker = || loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+
+            let Some(job) = jobs.get(i) else {
+                break;
+            };
+
+            if failed.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let result = match job {
+                Job::Convert(input, export_id, output) => {
+                    let inputs = std::slice::from_ref(*input);
+                    self.convert_inputs(stylesheet, inputs, export_id.as_deref(), output)
+                }
+
+                Job::Select(input, name, index) => {
+                    self.convert_selected(stylesheet, input, name, *index, outputs)
+                }
+            };
+
+            if let Err(e) = result {
+                failed.store(true, Ordering::Relaxed);
+                errors.lock().unwrap().push((i, e));
+            }
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 1..threads.min(jobs.len()) {
+                scope.spawn(worker);
+            }
+
+            worker();
+        });
+
+        let errors = errors.into_inner().unwrap();
+
+        match errors.into_iter().min_by_key(|(i, _)| *i) {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Converts each object of `input` that matches `--select` to its own output from
+    /// the template, loading the input only once.
+    fn convert_selected(
+        &self,
+        stylesheet: Option<&str>,
+        input: &Input,
+        name: &Path,
+        index: usize,
+        outputs: &TemplateOutputs<'_>,
+    ) -> Result<(), Error> {
+        let mut handle = self.load(stylesheet, input)?;
+
+        for id in self.export_ids_for(&handle, input)? {
+            let output = outputs.claim(input, name, index, Some(id.as_str()))?;
+            let handles = std::iter::once(Ok((input, &mut handle)));
+            self.convert_handles(handles, Some(id.as_str()), &output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts `inputs`, or just their object with `export_id`, into a single `output`.
+    fn convert_inputs(
+        &self,
+        stylesheet: Option<&str>,
+        inputs: &[Input],
+        export_id: Option<&str>,
+        output: &Output,
+    ) -> Result<(), Error> {
+        // Load each input only when its turn comes, so that one document at a time is in
+        // memory.
+        let handles = inputs
+            .iter()
+            .map(|input| self.load(stylesheet, input).map(|handle| (input, handle)));
+
+        self.convert_handles(handles, export_id, output)
+    }
+
+    /// Converts the loaded documents, or just their object with `export_id`, into a
+    /// single `output`.
+    fn convert_handles<'i, H>(
+        &self,
+        handles: impl ExactSizeIterator<Item = Result<(&'i Input, H), Error>>,
+        export_id: Option<&str>,
+        output: &Output,
+    ) -> Result<(), Error>
+    where
+        H: BorrowMut<SvgHandle>,
+    {
         let mut surface: Option<Surface> = None;
 
         // Use user units per default
@@ -546,199 +1083,419 @@ impl Converter {
             }
         }
 
-        for (page_idx, input) in self.input.iter().enumerate() {
-            let (stream, basefile) = match input {
-                Input::Stdin => (Stdin::stream(), None),
-                Input::Named(p) => {
-                    let file = p.get_gfile();
-                    let stream = file
-                        .read(None::<&Cancellable>)
-                        .map_err(|e| error!("Error reading file \"{}\": {}", input, e))?;
-                    (stream.upcast::<InputStream>(), Some(file))
-                }
-            };
+        let mut page_num = 0;
+        let num_inputs = handles.len();
 
-            let mut handle = Loader::new()
-                .with_unlimited_size(self.unlimited)
-                .keep_image_data(self.keep_image_data)
-                .read_stream(&stream, basefile.as_ref(), None::<&Cancellable>)
-                .map_err(|e| error!("Error reading SVG {}: {}", input, e))?;
+        for loaded in handles {
+            let (input, mut handle) = loaded?;
+            let handle = handle.borrow_mut();
+
+            if let Some(listing) = self.listing {
+                if num_inputs > 1 {
+                    println!("{input}:");
+                }
 
-            if let Some(ref css) = stylesheet {
-                handle
-                    .set_stylesheet(css)
-                    .map_err(|e| error!("Error applying stylesheet: {}", e))?;
+                print_listing(&handle, listing);
+                continue;
             }
 
-            let renderer = CairoRenderer::new(&handle)
-                .with_dpi(self.dpi_x.0, self.dpi_y.0)
-                .with_language(&self.language)
-                .test_mode(self.testing);
+            // With --pages, each page of the document goes into a separate page of the
+            // output; otherwise, the whole document or the --export-id object does.
+            let export_ids = if self.pages {
+                page_ids(&handle)
+            } else {
+                vec![export_id.map(String::from)]
+            };
 
-            let geometry = natural_geometry(&renderer, input, self.export_id.as_deref())?;
+            for export_id in export_ids {
+                page_num += 1;
 
-            let natural_size = Size::new(geometry.width(), geometry.height());
+                let renderer = self.make_renderer(&handle);
 
-            let params = NormalizeParams::from_dpi(Dpi::new(self.dpi_x.0, self.dpi_y.0));
-
-            // Convert natural size and requested size to pixels or points, depending on the target format,
-            let (natural_size, requested_width, requested_height, page_size) = match self.format {
-                Format::Png => {
-                    // PNG surface requires units in pixels
-                    (
-                        natural_size,
-                        self.width.map(|l| l.to_user(&params)),
-                        self.height.map(|l| l.to_user(&params)),
-                        self.page_size.map(|(w, h)| Size {
-                            w: w.to_user(&params),
-                            h: h.to_user(&params),
-                        }),
-                    )
-                }
+                let (geometry, viewport) = match self.export_area {
+                    Some(ref area) => area_geometry(&renderer, input, area)?,
+                    None => {
+                        let geometry = natural_geometry(&renderer, input, export_id.as_deref())?;
+                        let viewport =
+                            cairo::Rectangle::new(0.0, 0.0, geometry.width(), geometry.height());
+                        (geometry, viewport)
+                    }
+                };
+
+                let natural_size = Size::new(geometry.width(), geometry.height());
+
+                let params = NormalizeParams::from_dpi(Dpi::new(self.dpi_x.0, self.dpi_y.0));
+
+                // Convert natural size and requested size to pixels or points, depending on the target format,
+                let (natural_size, requested_width, requested_height, page_size) = match self.format
+                {
+                    Format::Png
+                    | Format::Jpeg
+                    | Format::Tiff
+                    | Format::Gif
+                    | Format::Apng
+                    | Format::WebpAnim
+                    | Format::Ico
+                    | Format::Icns => {
+                        // Image surfaces require units in pixels
+                        (
+                            natural_size,
+                            self.width.map(|l| l.to_user(&params)),
+                            self.height.map(|l| l.to_user(&params)),
+                            self.page_size.map(|(w, h)| Size {
+                                w: w.to_user(&params),
+                                h: h.to_user(&params),
+                            }),
+                        )
+                    }
 
-                Format::Pdf
-                | Format::Pdf1_7
-                | Format::Pdf1_6
-                | Format::Pdf1_5
-                | Format::Pdf1_4
-                | Format::Ps
-                | Format::Eps => {
-                    // These surfaces require units in points
-                    unit = LengthUnit::Pt;
-
-                    (
-                        Size {
-                            w: ULength::<Horizontal>::new(natural_size.w, LengthUnit::Px)
-                                .to_points(&params),
-                            h: ULength::<Vertical>::new(natural_size.h, LengthUnit::Px)
-                                .to_points(&params),
-                        },
-                        self.width.map(|l| l.to_points(&params)),
-                        self.height.map(|l| l.to_points(&params)),
-                        self.page_size.map(|(w, h)| Size {
-                            w: w.to_points(&params),
-                            h: h.to_points(&params),
-                        }),
-                    )
-                }
+                    Format::Pdf
+                    | Format::Pdf1_7
+                    | Format::Pdf1_6
+                    | Format::Pdf1_5
+                    | Format::Pdf1_4
+                    | Format::Ps
+                    | Format::Eps => {
+                        // These surfaces require units in points
+                        unit = LengthUnit::Pt;
+
+                        (
+                            Size {
+                                w: ULength::<Horizontal>::new(natural_size.w, LengthUnit::Px)
+                                    .to_points(&params),
+                                h: ULength::<Vertical>::new(natural_size.h, LengthUnit::Px)
+                                    .to_points(&params),
+                            },
+                            self.width.map(|l| l.to_points(&params)),
+                            self.height.map(|l| l.to_points(&params)),
+                            self.page_size.map(|(w, h)| Size {
+                                w: w.to_points(&params),
+                                h: h.to_points(&params),
+                            }),
+                        )
+                    }
 
-                Format::Svg => {
-                    let (w_unit, h_unit) =
-                        (self.width.map(|l| l.unit), self.height.map(|l| l.unit));
-
-                    unit = match (w_unit, h_unit) {
-                        (None, None) => LengthUnit::Px,
-                        (None, u) | (u, None) => u.unwrap(),
-                        (u1, u2) => {
-                            if u1 == u2 {
-                                u1.unwrap()
-                            } else {
-                                LengthUnit::Px
+                    Format::Svg => {
+                        let (w_unit, h_unit) =
+                            (self.width.map(|l| l.unit), self.height.map(|l| l.unit));
+
+                        unit = match (w_unit, h_unit) {
+                            (None, None) => LengthUnit::Px,
+                            (None, u) | (u, None) => u.unwrap(),
+                            (u1, u2) => {
+                                if u1 == u2 {
+                                    u1.unwrap()
+                                } else {
+                                    LengthUnit::Px
+                                }
+                            }
+                        };
+
+                        // Supported SVG units are px, in, cm, mm, pt, pc
+                        (
+                            Size {
+                                w: set_unit(
+                                    ULength::<Horizontal>::new(natural_size.w, LengthUnit::Px),
+                                    &params,
+                                    unit,
+                                ),
+                                h: set_unit(
+                                    ULength::<Vertical>::new(natural_size.h, LengthUnit::Px),
+                                    &params,
+                                    unit,
+                                ),
+                            },
+                            self.width.map(|l| set_unit(l, &params, unit)),
+                            self.height.map(|l| set_unit(l, &params, unit)),
+                            self.page_size.map(|(w, h)| Size {
+                                w: set_unit(w, &params, unit),
+                                h: set_unit(h, &params, unit),
+                            }),
+                        )
+                    }
+                };
+
+                let strategy = match (requested_width, requested_height) {
+                    // when w and h are not specified, scale to the requested zoom (if any)
+                    (None, None) => ResizeStrategy::Scale(self.zoom),
+
+                    // when w and h are specified, but zoom is not, scale to the requested size
+                    (Some(width), Some(height)) if self.zoom.is_identity() => ResizeStrategy::Fit {
+                        size: Size::new(width, height),
+                        keep_aspect_ratio: self.keep_aspect_ratio,
+                    },
+
+                    // if only one between w and h is specified and there is no zoom, scale to the
+                    // requested w or h and use the same scaling factor for the other
+                    (Some(w), None) if self.zoom.is_identity() => ResizeStrategy::FitWidth(w),
+                    (None, Some(h)) if self.zoom.is_identity() => ResizeStrategy::FitHeight(h),
+
+                    // otherwise scale the image, but cap the zoom to match the requested size
+                    _ => ResizeStrategy::ScaleWithMaxSize {
+                        scale: self.zoom,
+                        max_width: requested_width,
+                        max_height: requested_height,
+                        keep_aspect_ratio: self.keep_aspect_ratio,
+                    },
+                };
+
+                let final_size = self.final_size(&strategy, &natural_size, input)?;
+
+                // Create the surface once on the first input,
+                // except for PDF, PS, EPS, and TIFF, which allow differently-sized pages.
+                let page_size = page_size.unwrap_or(final_size);
+                let s = match &mut surface {
+                    Some(s) => {
+                        match s {
+                            #[cfg(system_deps_have_cairo_pdf)]
+                            Surface::Pdf(pdf, size) => {
+                                pdf.set_size(page_size.w, page_size.h).map_err(|e| {
+                                    error!(
+                                        "Error setting PDF page #{} size {}: {}",
+                                        page_num, input, e
+                                    )
+                                })?;
+                                *size = page_size;
+                            }
+                            #[cfg(system_deps_have_cairo_ps)]
+                            Surface::Ps(ps, size) => {
+                                ps.set_size(page_size.w, page_size.h);
+                                *size = page_size;
                             }
+                            Surface::Tiff(image, _, _) => {
+                                *image = new_image_surface(page_size)?;
+                            }
+                            _ => {}
                         }
-                    };
-
-                    // Supported SVG units are px, in, cm, mm, pt, pc
-                    (
-                        Size {
-                            w: set_unit(
-                                ULength::<Horizontal>::new(natural_size.w, LengthUnit::Px),
-                                &params,
-                                unit,
-                            ),
-                            h: set_unit(
-                                ULength::<Vertical>::new(natural_size.h, LengthUnit::Px),
-                                &params,
-                                unit,
-                            ),
-                        },
-                        self.width.map(|l| set_unit(l, &params, unit)),
-                        self.height.map(|l| set_unit(l, &params, unit)),
-                        self.page_size.map(|(w, h)| Size {
-                            w: set_unit(w, &params, unit),
-                            h: set_unit(h, &params, unit),
-                        }),
-                    )
-                }
-            };
+                        s
+                    }
+                    surface @ None => surface.insert(self.create_surface(page_size, unit, output)?),
+                };
 
-            let strategy = match (requested_width, requested_height) {
-                // when w and h are not specified, scale to the requested zoom (if any)
-                (None, None) => ResizeStrategy::Scale(self.zoom),
-
-                // when w and h are specified, but zoom is not, scale to the requested size
-                (Some(width), Some(height)) if self.zoom.is_identity() => ResizeStrategy::Fit {
-                    size: Size::new(width, height),
-                    keep_aspect_ratio: self.keep_aspect_ratio,
-                },
-
-                // if only one between w and h is specified and there is no zoom, scale to the
-                // requested w or h and use the same scaling factor for the other
-                (Some(w), None) if self.zoom.is_identity() => ResizeStrategy::FitWidth(w),
-                (None, Some(h)) if self.zoom.is_identity() => ResizeStrategy::FitHeight(h),
-
-                // otherwise scale the image, but cap the zoom to match the requested size
-                _ => ResizeStrategy::ScaleWithMaxSize {
-                    scale: self.zoom,
-                    max_width: requested_width,
-                    max_height: requested_height,
-                    keep_aspect_ratio: self.keep_aspect_ratio,
-                },
-            };
+                let left = self.left.map(|l| set_unit(l, &params, unit)).unwrap_or(0.0);
+                let top = self.top.map(|l| set_unit(l, &params, unit)).unwrap_or(0.0);
 
-            let final_size = self.final_size(&strategy, &natural_size, input)?;
-
-            // Create the surface once on the first input,
-            // except for PDF, PS, and EPS, which allow differently-sized pages.
-            let page_size = page_size.unwrap_or(final_size);
-            let s = match &mut surface {
-                Some(s) => {
-                    match s {
-                        #[cfg(system_deps_have_cairo_pdf)]
-                        Surface::Pdf(pdf, size) => {
-                            pdf.set_size(page_size.w, page_size.h).map_err(|e| {
-                                error!(
-                                    "Error setting PDF page #{} size {}: {}",
-                                    page_idx + 1,
-                                    input,
-                                    e
-                                )
-                            })?;
-                            *size = page_size;
-                        }
-                        #[cfg(system_deps_have_cairo_ps)]
-                        Surface::Ps(ps, size) => {
-                            ps.set_size(page_size.w, page_size.h);
-                            *size = page_size;
+                if self.format.is_animated() {
+                    let info = handle.animation_info();
+
+                    if let Surface::Animation(_, frames, _) = s {
+                        frames.set_loops(info.loops);
+                    }
+
+                    for (time, delay) in animation::frame_times(&info, self.frame_rate) {
+                        handle.set_animation_time(time);
+
+                        s.render(
+                            &self.make_renderer(&handle),
+                            left,
+                            top,
+                            final_size,
+                            geometry,
+                            viewport,
+                            self.background_color,
+                            export_id.as_deref(),
+                        )
+                        .map_err(|e| error!("Error rendering SVG {}: {}", input, e))?;
+
+                        s.push_frame(delay)?;
+                    }
+                } else if self.format.icon_format().is_some() {
+                    // Each icon is a square of the requested size, with the document
+                    // scaled to fit and centered in it.
+                    for &icon_size in &self.icon_sizes {
+                        let square = Size::new(f64::from(icon_size), f64::from(icon_size));
+
+                        let strategy = ResizeStrategy::Fit {
+                            size: square,
+                            keep_aspect_ratio: true,
+                        };
+                        let icon_final_size = self.final_size(&strategy, &natural_size, input)?;
+
+                        if let Surface::Icon(image, _, _) = s {
+                            *image = new_image_surface(square)?;
                         }
-                        _ => {}
+
+                        s.render(
+                            &renderer,
+                            (square.w - icon_final_size.w) / 2.0,
+                            (square.h - icon_final_size.h) / 2.0,
+                            icon_final_size,
+                            geometry,
+                            viewport,
+                            self.background_color,
+                            export_id.as_deref(),
+                        )
+                        .map_err(|e| error!("Error rendering SVG {}: {}", input, e))?;
+
+                        s.end_page()?;
                     }
-                    s
+                } else {
+                    s.render(
+                        &renderer,
+                        left,
+                        top,
+                        final_size,
+                        geometry,
+                        viewport,
+                        self.background_color,
+                        export_id.as_deref(),
+                    )
+                    .map_err(|e| error!("Error rendering SVG {}: {}", input, e))?;
+
+                    s.end_page()?;
                 }
-                surface @ None => surface.insert(self.create_surface(page_size, unit)?),
-            };
+            }
+        }
 
-            let left = self.left.map(|l| set_unit(l, &params, unit)).unwrap_or(0.0);
-            let top = self.top.map(|l| set_unit(l, &params, unit)).unwrap_or(0.0);
+        if let Some(s) = surface.take() {
+            s.finish()
+                .map_err(|e| error!("Error saving output {}: {}", output, e))?
+        };
+
+        Ok(())
+    }
+
+    /// Renders each of the objects to export into its own rectangle of a PNG image, and
+    /// writes a JSON file with the rectangles to `map`.
+    fn convert_sprite_sheet(
+        &self,
+        stylesheet: Option<&str>,
+        input: &Input,
+        map: &Path,
+    ) -> Result<(), Error> {
+        let handle = self.load(stylesheet, input)?;
+        let renderer = self.make_renderer(&handle);
+
+        let export_ids = self.export_ids_for(&handle, input)?;
+
+        if export_ids.is_empty() {
+            return Err(error!("No elements in {} match --select.", input));
+        }
+
+        let mut sprites = Vec::with_capacity(export_ids.len());
+
+        for id in &export_ids {
+            let geometry = natural_geometry(&renderer, input, Some(id))?;
+            let natural_size = Size::new(geometry.width(), geometry.height());
+            let final_size =
+                self.final_size(&ResizeStrategy::Scale(self.zoom), &natural_size, input)?;
+
+            sprites.push((geometry, final_size));
+        }
+
+        // Partially covered pixels at the edges get their own pixel, as in other output.
+        let sizes: Vec<(u32, u32)> = sprites
+            .iter()
+            .map(|(_, size)| (size.w.ceil() as u32, size.h.ceil() as u32))
+            .collect();
+
+        let ((width, height), placements) = sprite::pack(&sizes);
+        let sheet_size = Size::new(f64::from(width), f64::from(height));
+
+        let s = self.create_surface(sheet_size, LengthUnit::Px, &self.output)?;
+
+        if let Some(color) = self.background_color {
+            let cr = cairo::Context::new(&s)?;
+            set_source_color_on_cairo(&cr, &color);
+            cr.paint()?;
+        }
+
+        for ((id, (geometry, final_size)), placement) in
+            export_ids.iter().zip(&sprites).zip(&placements)
+        {
+            let viewport = cairo::Rectangle::new(0.0, 0.0, geometry.width(), geometry.height());
 
             s.render(
                 &renderer,
-                left,
-                top,
-                final_size,
-                geometry,
-                self.background_color,
-                self.export_id.as_deref(),
+                f64::from(placement.x),
+                f64::from(placement.y),
+                *final_size,
+                *geometry,
+                viewport,
+                None,
+                Some(id),
             )
-            .map_err(|e| error!("Error rendering SVG {}: {}", input, e))?
+            .map_err(|e| error!("Error rendering SVG {}: {}", input, e))?;
         }
 
-        if let Some(s) = surface.take() {
-            s.finish()
-                .map_err(|e| error!("Error saving output {}: {}", self.output, e))?
+        s.finish()
+            .map_err(|e| error!("Error saving output {}: {}", self.output, e))?;
+
+        std::fs::write(map, sprite::json_map(&export_ids, &placements)).map_err(|e| {
+            error!(
+                "Error writing sprite sheet map \"{}\": {}",
+                map.display(),
+                e
+            )
+        })
+    }
+
+    /// Ids of the objects to export from `handle`: the ones given with `--export-id`, and
+    /// then the ones of the elements that match `--select`.
+    fn export_ids_for(&self, handle: &SvgHandle, input: &Input) -> Result<Vec<String>, Error> {
+        let mut ids = self.export_ids.clone();
+
+        if let Some(ref selectors) = self.select {
+            let elements = handle
+                .query_selector_all(selectors)
+                .map_err(|_| error!("Invalid selector \"{}\" for --select.", selectors))?;
+
+            for element in elements {
+                let id = element.id().map(|id| format!("#{id}")).ok_or_else(|| {
+                    error!(
+                        "The <{}> element in {} that matches --select has no id, so it cannot be exported.",
+                        element.name(),
+                        input
+                    )
+                })?;
+
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+
+    fn load(&self, stylesheet: Option<&str>, input: &Input) -> Result<SvgHandle, Error> {
+        let (stream, basefile) = match input {
+            Input::Stdin => (Stdin::stream(), None),
+            Input::Named(p) => {
+                let file = p.get_gfile();
+                let stream = file
+                    .read(None::<&Cancellable>)
+                    .map_err(|e| error!("Error reading file \"{}\": {}", input, e))?;
+                (stream.upcast::<InputStream>(), Some(file))
+            }
         };
 
-        Ok(())
+        let mut handle = Loader::new()
+            .with_unlimited_size(self.unlimited)
+            .keep_image_data(self.keep_image_data)
+            .read_stream(&stream, basefile.as_ref(), None::<&Cancellable>)
+            .map_err(|e| error!("Error reading SVG {}: {}", input, e))?;
+
+        if let Some(css) = stylesheet {
+            handle
+                .set_stylesheet(css)
+                .map_err(|e| error!("Error applying stylesheet: {}", e))?;
+        }
+
+        Ok(handle)
+    }
+
+    fn make_renderer<'a>(&self, handle: &'a SvgHandle) -> CairoRenderer<'a> {
+        CairoRenderer::new(handle)
+            .with_dpi(self.dpi_x.0, self.dpi_y.0)
+            .with_language(&self.language)
+            .with_supersampling(self.supersampling)
+            .with_color_vision_simulation(self.simulate_cvd)
+            .with_linear_light(self.linear_light)
+            .with_quality(self.quality)
+            .with_path_simplification(self.simplify_paths)
+            .with_error_placeholders(self.error_placeholders)
+            .test_mode(self.testing)
     }
 
     fn final_size(
@@ -752,19 +1509,68 @@ impl Converter {
             .ok_or_else(|| error!("The SVG {} has no dimensions", input))
     }
 
-    fn create_surface(&self, size: Size, unit: LengthUnit) -> Result<Surface, Error> {
-        let output_stream = match self.output {
+    fn create_surface(
+        &self,
+        size: Size,
+        unit: LengthUnit,
+        output: &Output,
+    ) -> Result<Surface, Error> {
+        let output_stream = match output {
             Output::Stdout => Stdout::stream(),
             Output::Path(ref p) => {
                 let file = gio::File::for_path(p);
                 let stream = file
                     .replace(None, false, FileCreateFlags::NONE, None::<&Cancellable>)
-                    .map_err(|e| error!("Error opening output \"{}\": {}", self.output, e))?;
+                    .map_err(|e| error!("Error opening output \"{}\": {}", output, e))?;
                 stream.upcast::<OutputStream>()
             }
+            Output::Template(_) => unreachable!("templates are expanded for each input"),
         };
 
-        Surface::new(self.format, size, output_stream, unit)
+        Surface::new(
+            self.format,
+            size,
+            output_stream,
+            unit,
+            self.jpeg_quality,
+            self.tiff_compression,
+            &self.pdf_info,
+            Dpi::new(self.dpi_x.0, self.dpi_y.0),
+        )
+    }
+}
+
+fn print_listing(handle: &SvgHandle, listing: Listing) {
+    match listing {
+        Listing::Ids => {
+            for element in handle.root_element().descendants() {
+                if let Some(id) = element.id() {
+                    println!("{}\t{}", id, element.name());
+                }
+            }
+        }
+
+        Listing::Fonts => {
+            for family in handle.font_families() {
+                println!("{family}");
+            }
+        }
+    }
+}
+
+/// Returns the fragment ids of the document's pages, or the whole document if it has none.
+fn page_ids(handle: &SvgHandle) -> Vec<Option<String>> {
+    let ids: Vec<_> = handle
+        .pages()
+        .iter()
+        .filter_map(|page| page.id())
+        .map(|id| Some(format!("#{id}")))
+        .collect();
+
+    if ids.is_empty() {
+        vec![None]
+    } else {
+        ids
     }
 }
 
@@ -788,9 +1594,40 @@ fn natural_geometry(
     })
 }
 
+/// Returns the geometry of an `--export-area` rectangle, and the viewport for the whole document.
+///
+/// The area is in the user space of the toplevel `<svg>`, so it gets mapped through the
+/// document's `viewBox` and `preserveAspectRatio`, if any.  The viewport is offset so that
+/// the area's upper-left corner ends up at the origin of the output.
+fn area_geometry(
+    renderer: &CairoRenderer,
+    input: &Input,
+    area: &Rect,
+) -> Result<(cairo::Rectangle, cairo::Rectangle), Error> {
+    let document = natural_geometry(renderer, input, None)?;
+
+    // A viewBox only scales and translates, so the corners of the area are enough.
+    let matrix = renderer
+        .toplevel_viewbox_transform(document.width(), document.height())
+        .unwrap_or_else(cairo::Matrix::identity);
+
+    let (ax, ay) = matrix.transform_point(area.x0, area.y0);
+    let (bx, by) = matrix.transform_point(area.x1, area.y1);
+    let (x0, y0) = (ax.min(bx), ay.min(by));
+
+    let geometry = cairo::Rectangle::new(x0, y0, (bx - ax).abs(), (by - ay).abs());
+    let viewport = cairo::Rectangle::new(-x0, -y0, document.width(), document.height());
+
+    Ok((geometry, viewport))
+}
+
 fn build_cli() -> clap::Command {
     let supported_formats = vec![
         "png",
+        #[cfg(feature = "jpeg")]
+        "jpeg",
+        #[cfg(feature = "tiff")]
+        "tiff",
         #[cfg(system_deps_have_cairo_pdf)]
         "pdf",
         #[cfg(system_deps_have_cairo_pdf)]
@@ -807,6 +1644,14 @@ fn build_cli() -> clap::Command {
         "eps",
         #[cfg(system_deps_have_cairo_svg)]
         "svg",
+        #[cfg(feature = "gif")]
+        "gif",
+        #[cfg(feature = "apng")]
+        "apng",
+        #[cfg(feature = "webp-anim")]
+        "webp-anim",
+        "ico",
+        "icns",
     ];
 
     clap::Command::new("rsvg-convert")
@@ -964,7 +1809,55 @@ fn build_cli() -> clap::Command {
                 .long("export-id")
                 .value_parser(clap::builder::NonEmptyStringValueParser::new())
                 .value_name("object id")
-                .help("SVG id of object to export [default is to export all objects]")
+                .help("SVG id of object to export; repeat it or separate ids with commas to export several [default is to export all objects]")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            clap::Arg::new("select")
+                .long("select")
+                .num_args(1)
+                .value_name("selectors")
+                .value_parser(clap::builder::NonEmptyStringValueParser::new())
+                .help("Export each element that matches a CSS selector list, like \".icon, #logo\"")
+                .conflicts_with_all(["pages", "export_area", "list_ids", "list_fonts"])
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("sprite_sheet")
+                .long("sprite-sheet")
+                .num_args(1)
+                .value_name("map.json")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("Pack the exported objects into a single PNG, and write their rectangles to a JSON file")
+                .conflicts_with_all([
+                    "pages",
+                    "export_area",
+                    "size_x",
+                    "size_y",
+                    "page_width",
+                    "page_height",
+                    "left",
+                    "top",
+                    "list_ids",
+                    "list_fonts",
+                ])
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("pages")
+                .long("pages")
+                .help("Export each top-level <g class=\"page\"> to a separate page (PDF and PS only)")
+                .conflicts_with("export_id")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("export_area")
+                .long("export-area")
+                .num_args(1)
+                .value_name("x0:y0:x1:y1")
+                .value_parser(parse_export_area)
+                .help("Export only this rectangle, in user space units [default is to export the whole document]")
+                .conflicts_with_all(["export_id", "pages"])
                 .action(clap::ArgAction::Set),
         )
         .arg(
@@ -994,6 +1887,134 @@ fn build_cli() -> clap::Command {
                 .help("Set the background color using a CSS color spec")
                 .action(clap::ArgAction::Set),
         )
+        .arg(
+            clap::Arg::new("supersample")
+                .long("supersample")
+                .num_args(1)
+                .value_name("factor")
+                .default_value("1")
+                .value_parser(clap::value_parser!(u32).range(1..=16))
+                .help("Render at a multiple of the size and downscale, to reduce aliasing (PNG only)")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("simulate_cvd")
+                .long("simulate-cvd")
+                .num_args(1)
+                .value_name("deficiency")
+                .value_parser(["protanopia", "deuteranopia", "tritanopia"])
+                .help("Simulate how people with a color vision deficiency see the output (raster formats only)")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("linear_light")
+                .long("linear-light")
+                .help("Scale images and composite groups in linear RGB (raster formats only)")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("frame_rate")
+                .long("frame-rate")
+                .num_args(1)
+                .value_name("fps")
+                .default_value("10")
+                .value_parser(parse_frame_rate)
+                .help("Frames per second for animated output (GIF, APNG, and WebP only)")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("icon_sizes")
+                .long("icon-sizes")
+                .value_name("sizes")
+                .value_delimiter(',')
+                .value_parser(clap::value_parser!(u32))
+                .help("Comma-separated sizes in pixels of the icons for ICO and ICNS output")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("quality")
+                .long("quality")
+                .num_args(1)
+                .value_name("quality")
+                .value_parser(["compatible", "high"])
+                .default_value("compatible")
+                .help("Rendering quality: \"compatible\" matches web browsers, \"high\" uses slower algorithms")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("jpeg_quality")
+                .long("jpeg-quality")
+                .num_args(1)
+                .value_name("quality")
+                .value_parser(clap::value_parser!(u8).range(1..=100))
+                .help("Quality of JPEG output, from 1 to 100 [default: 90]")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("tiff_compression")
+                .long("tiff-compression")
+                .num_args(1)
+                .value_name("compression")
+                .value_parser(["none", "lzw", "deflate"])
+                .help("Compression of TIFF output [default: lzw]")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("pdf_title")
+                .long("pdf-title")
+                .num_args(1)
+                .value_name("title")
+                .help("Title in the document information of PDF output")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("pdf_author")
+                .long("pdf-author")
+                .num_args(1)
+                .value_name("author")
+                .help("Author in the document information of PDF output")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("pdf_subject")
+                .long("pdf-subject")
+                .num_args(1)
+                .value_name("subject")
+                .help("Subject in the document information of PDF output")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("pdf_keywords")
+                .long("pdf-keywords")
+                .num_args(1)
+                .value_name("keywords")
+                .help("Keywords in the document information of PDF output")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("pdf_creation_date")
+                .long("pdf-creation-date")
+                .num_args(1)
+                .value_name("date")
+                .value_parser(metadata::parse_date)
+                .help("Creation date of PDF output; overrides $SOURCE_DATE_EPOCH")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("simplify_paths")
+                .long("simplify-paths")
+                .num_args(1)
+                .value_name("tolerance")
+                .value_parser(parse_tolerance)
+                .help("Simplify straight-line runs in paths, in points (vector formats only)")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("error_placeholders")
+                .long("error-placeholders")
+                .help("Draw a placeholder where an image or filter fails to render")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             clap::Arg::new("stylesheet")
                 .short('s')
@@ -1025,6 +2046,36 @@ fn build_cli() -> clap::Command {
                 .conflicts_with("keep_image_data")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            clap::Arg::new("list_ids")
+                .long("list-ids")
+                .help("Print the ids of all elements with their element names, and exit")
+                .conflicts_with("list_fonts")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("list_fonts")
+                .long("list-fonts")
+                .help("Print the font families used by the text, and exit")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            clap::Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .num_args(1)
+                .value_name("number")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .help("Number of files to convert at the same time with an output template [default: number of CPUs]")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            clap::Arg::new("watch")
+                .long("watch")
+                .help("Convert again whenever the input files change, until interrupted")
+                .conflicts_with_all(["list_ids", "list_fonts"])
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             clap::Arg::new("testing")
                 .long("testing")
@@ -1071,6 +2122,8 @@ fn parse_args() -> Result<Converter, Error> {
     let format = match_ignore_ascii_case! {
         format_str,
         "png" => Format::Png,
+        "jpeg" => Format::Jpeg,
+        "tiff" => Format::Tiff,
         "pdf" => Format::Pdf,
         "pdf1.7" => Format::Pdf1_7,
         "pdf1.6" => Format::Pdf1_6,
@@ -1079,6 +2132,11 @@ fn parse_args() -> Result<Converter, Error> {
         "ps" => Format::Ps,
         "eps" => Format::Eps,
         "svg" => Format::Svg,
+        "gif" => Format::Gif,
+        "apng" => Format::Apng,
+        "webp-anim" => Format::WebpAnim,
+        "ico" => Format::Ico,
+        "icns" => Format::Icns,
         _ => unreachable!("clap should already have the list of possible values"),
     };
 
@@ -1103,9 +2161,9 @@ fn parse_args() -> Result<Converter, Error> {
     // librsvg expects ids starting with '#', so it can lookup ids in externs like "subfile.svg#subid".
     // For the user's convenience, we prepend '#' automatically; we only support specifying ids from
     // the toplevel, and don't expect users to lookup things in externs.
-    let lookup_id = |id: &String| {
+    let lookup_id = |id: &str| {
         if id.starts_with('#') {
-            id.clone()
+            id.to_string()
         } else {
             format!("#{id}")
         }
@@ -1157,19 +2215,256 @@ fn parse_args() -> Result<Converter, Error> {
         return Err(error!("Only one input file can be read from stdin."));
     }
 
-    if input.len() > 1 && !matches!(format, Format::Ps | Format::Eps | Format::Pdf) {
+    let output = match matches.get_one::<PathBuf>("output") {
+        None => Output::Stdout,
+        Some(path) if OutputTemplate::is_template(path) => {
+            Output::Template(OutputTemplate::parse(&path.to_string_lossy())?)
+        }
+        Some(path) => Output::Path(path.clone()),
+    };
+
+    let jobs = matches.get_one::<u32>("jobs").map(|&n| n as usize);
+
+    if jobs.is_some() && !matches!(output, Output::Template(_)) {
         return Err(error!(
-            "Multiple SVG files are only allowed for PDF and (E)PS output."
+            "--jobs is only allowed with an output filename template."
         ));
     }
 
-    let export_id: Option<String> = matches.get_one::<String>("export_id").map(lookup_id);
+    let watch = matches.get_flag("watch");
 
-    let output = match matches.get_one::<PathBuf>("output") {
-        None => Output::Stdout,
-        Some(path) => Output::Path(path.clone()),
+    if watch && matches!(output, Output::Stdout) {
+        return Err(error!("--watch needs an output file given with --output."));
+    }
+
+    if watch && input.iter().any(|i| matches!(i, Input::Stdin)) {
+        return Err(error!("--watch cannot be used when reading from stdin."));
+    }
+
+    let listing = if matches.get_flag("list_ids") {
+        Some(Listing::Ids)
+    } else if matches.get_flag("list_fonts") {
+        Some(Listing::Fonts)
+    } else {
+        None
+    };
+
+    if input.len() > 1
+        && listing.is_none()
+        && !matches!(output, Output::Template(_))
+        && !matches!(
+            format,
+            Format::Ps | Format::Eps | Format::Pdf | Format::Tiff
+        )
+    {
+        return Err(error!(
+            "Multiple SVG files are only allowed for PDF, (E)PS, and TIFF output."
+        ));
+    }
+
+    let pages = matches.get_flag("pages");
+
+    if pages && !matches!(format, Format::Ps | Format::Pdf | Format::Tiff) {
+        return Err(error!(
+            "--pages is only allowed for PDF, PS, and TIFF output."
+        ));
+    }
+
+    let supersampling = *matches
+        .get_one::<u32>("supersample")
+        .expect("already provided default_value");
+
+    let is_raster = matches!(
+        format,
+        Format::Png
+            | Format::Jpeg
+            | Format::Tiff
+            | Format::Gif
+            | Format::Apng
+            | Format::WebpAnim
+            | Format::Ico
+            | Format::Icns
+    );
+
+    if supersampling > 1 && !is_raster {
+        return Err(error!(
+            "Supersampling is only supported for raster output formats."
+        ));
+    }
+
+    let simulate_cvd = match matches.get_one::<String>("simulate_cvd") {
+        None => None,
+        Some(_) if !is_raster => {
+            return Err(error!(
+                "--simulate-cvd is only supported for raster output formats."
+            ));
+        }
+        Some(s) => match s.as_str() {
+            "protanopia" => Some(ColorVisionDeficiency::Protanopia),
+            "deuteranopia" => Some(ColorVisionDeficiency::Deuteranopia),
+            "tritanopia" => Some(ColorVisionDeficiency::Tritanopia),
+            _ => unreachable!("clap should already have the list of possible values"),
+        },
+    };
+
+    let linear_light = matches.get_flag("linear_light");
+
+    if linear_light && !is_raster {
+        return Err(error!(
+            "--linear-light is only supported for raster output formats."
+        ));
+    }
+
+    let quality_str: &String = matches
+        .get_one("quality")
+        .expect("already provided default_value");
+
+    let quality = match quality_str.as_str() {
+        "compatible" => RenderingQuality::Compatible,
+        "high" => RenderingQuality::High,
+        _ => unreachable!("clap should already have the list of possible values"),
+    };
+
+    let jpeg_quality = matches.get_one::<u8>("jpeg_quality").copied();
+
+    if jpeg_quality.is_some() && !matches!(format, Format::Jpeg) {
+        return Err(error!("--jpeg-quality is only allowed for JPEG output."));
+    }
+
+    let tiff_compression = match matches.get_one::<String>("tiff_compression") {
+        None => TiffCompression::Lzw,
+        Some(_) if !matches!(format, Format::Tiff) => {
+            return Err(error!(
+                "--tiff-compression is only allowed for TIFF output."
+            ));
+        }
+        Some(s) => match s.as_str() {
+            "none" => TiffCompression::None,
+            "lzw" => TiffCompression::Lzw,
+            "deflate" => TiffCompression::Deflate,
+            _ => unreachable!("clap should already have the list of possible values"),
+        },
+    };
+
+    let pdf_info = metadata::PdfInfo {
+        title: matches.get_one::<String>("pdf_title").cloned(),
+        author: matches.get_one::<String>("pdf_author").cloned(),
+        subject: matches.get_one::<String>("pdf_subject").cloned(),
+        keywords: matches.get_one::<String>("pdf_keywords").cloned(),
+        creation_date: matches.get_one::<String>("pdf_creation_date").cloned(),
+    };
+
+    let is_pdf = matches!(
+        format,
+        Format::Pdf | Format::Pdf1_7 | Format::Pdf1_6 | Format::Pdf1_5 | Format::Pdf1_4
+    );
+
+    if !pdf_info.is_empty() && !is_pdf {
+        return Err(error!(
+            "The --pdf-* options are only allowed for PDF output."
+        ));
+    }
+
+    let simplify_paths = matches.get_one::<Tolerance>("simplify_paths").map(|t| t.0);
+
+    if simplify_paths.is_some()
+        && matches!(
+            format,
+            Format::Png
+                | Format::Jpeg
+                | Format::Tiff
+                | Format::Gif
+                | Format::Apng
+                | Format::WebpAnim
+                | Format::Ico
+                | Format::Icns
+        )
+    {
+        return Err(error!(
+            "Path simplification is only supported for vector output formats."
+        ));
+    }
+
+    let frame_rate = matches
+        .get_one::<FrameRate>("frame_rate")
+        .expect("already provided default_value")
+        .0;
+
+    let icon_sizes = match (format.icon_format(), matches.get_many::<u32>("icon_sizes")) {
+        (None, None) => Vec::new(),
+        (None, Some(_)) => {
+            return Err(error!(
+                "--icon-sizes is only allowed for ICO and ICNS output."
+            ));
+        }
+        (Some(icon_format), sizes) => {
+            if width.is_some() || height.is_some() || zoom.or(zoom_x).or(zoom_y).is_some() {
+                return Err(error!(
+                    "Use --icon-sizes instead of --width, --height, or --zoom for ICO and ICNS output."
+                ));
+            }
+
+            let mut sizes: Vec<u32> = match sizes {
+                Some(sizes) => sizes.copied().collect(),
+                None => icon_format.default_sizes().to_vec(),
+            };
+            sizes.sort_unstable();
+            sizes.dedup();
+
+            for &size in &sizes {
+                icon_format.check_size(size)?;
+            }
+
+            sizes
+        }
     };
 
+    let export_ids: Vec<String> = matches
+        .get_many::<String>("export_id")
+        .into_iter()
+        .flatten()
+        .flat_map(|ids| ids.split(','))
+        .filter(|id| !id.is_empty())
+        .map(lookup_id)
+        .collect();
+
+    let select: Option<String> = matches.get_one("select").cloned();
+    let sprite_sheet: Option<PathBuf> = matches.get_one("sprite_sheet").cloned();
+
+    if sprite_sheet.is_some() {
+        if !matches!(format, Format::Png) {
+            return Err(error!("--sprite-sheet is only allowed for PNG output."));
+        }
+
+        if export_ids.is_empty() && select.is_none() {
+            return Err(error!(
+                "--sprite-sheet needs the objects to export, given with --export-id or --select."
+            ));
+        }
+
+        if input.len() > 1 || matches!(output, Output::Template(_)) {
+            return Err(error!(
+                "--sprite-sheet needs a single input file and a single output file."
+            ));
+        }
+    } else if (export_ids.len() > 1 || select.is_some())
+        && !matches!(output, Output::Template(ref t) if t.uses_id())
+    {
+        return Err(error!(
+            "Several --export-id values or --select need an output filename template with {{id}}, or --sprite-sheet."
+        ));
+    }
+
+    if matches!(output, Output::Template(ref t) if t.uses_id())
+        && export_ids.is_empty()
+        && select.is_none()
+    {
+        return Err(error!(
+            "The {{id}} placeholder in the output template needs --export-id or --select."
+        ));
+    }
+    let export_area: Option<Rect> = matches.get_one("export_area").copied();
+
     Ok(Converter {
         dpi_x,
         dpi_y,
@@ -1183,15 +2478,33 @@ fn parse_args() -> Result<Converter, Error> {
         top,
         page_size,
         format,
-        export_id,
+        export_ids,
+        select,
+        sprite_sheet,
+        export_area,
+        pages,
         keep_aspect_ratio: matches.get_flag("keep_aspect"),
         background_color,
+        supersampling,
+        simulate_cvd,
+        linear_light,
+        quality,
+        jpeg_quality: jpeg_quality.unwrap_or(90),
+        tiff_compression,
+        pdf_info,
+        simplify_paths,
+        error_placeholders: matches.get_flag("error_placeholders"),
+        frame_rate,
+        icon_sizes,
         stylesheet: matches.get_one("stylesheet").cloned(),
         unlimited: matches.get_flag("unlimited"),
         keep_image_data,
         language,
         input,
         output,
+        listing,
+        jobs,
+        watch,
         testing: matches.get_flag("testing"),
     })
 }
@@ -1207,6 +2520,48 @@ fn parse_resolution(v: &str) -> Result<Resolution, String> {
     }
 }
 
+#[derive(Copy, Clone)]
+struct Tolerance(f64);
+
+fn parse_tolerance(v: &str) -> Result<Tolerance, String> {
+    match v.parse::<f64>() {
+        Ok(t) if t > 0.0 && t.is_finite() => Ok(Tolerance(t)),
+        Ok(_) => Err(String::from("Invalid tolerance")),
+        Err(e) => Err(format!("{e}")),
+    }
+}
+
+fn parse_export_area(v: &str) -> Result<Rect, String> {
+    let coords = v
+        .split(':')
+        .map(|c| c.trim().parse::<f64>().map_err(|e| format!("{e}")))
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    match coords[..] {
+        [x0, y0, x1, y1] if coords.iter().all(|c| c.is_finite()) && x0 < x1 && y0 < y1 => {
+            Ok(Rect::new(x0, y0, x1, y1))
+        }
+        _ => Err(String::from(
+            "Invalid area; use x0:y0:x1:y1 with x0 < x1 and y0 < y1",
+        )),
+    }
+}
+
+#[derive(Copy, Clone)]
+struct FrameRate(f64);
+
+fn parse_frame_rate(v: &str) -> Result<FrameRate, String> {
+    match v.parse::<f64>() {
+        Ok(fps) if animation::FRAME_RATES.contains(&fps) => Ok(FrameRate(fps)),
+        Ok(_) => Err(format!(
+            "Invalid frame rate; use a number between {} and {}",
+            animation::FRAME_RATES.start(),
+            animation::FRAME_RATES.end()
+        )),
+        Err(e) => Err(format!("{e}")),
+    }
+}
+
 #[derive(Copy, Clone)]
 struct ZoomFactor(f64);
 
@@ -1278,7 +2633,15 @@ fn parse_length<N: Normalize, V: Validate>(s: &str) -> Result<CssLength<N, V>, S
 }
 
 fn main() {
-    if let Err(e) = parse_args().and_then(|converter| converter.convert()) {
+    let result = parse_args().and_then(|converter| {
+        if converter.watch {
+            converter.watch()
+        } else {
+            converter.convert()
+        }
+    });
+
+    if let Err(e) = result {
         std::eprintln!("{e}");
         std::process::exit(1);
     }
@@ -0,0 +1,185 @@
+//! Sprite sheets, for `--sprite-sheet`.
+//!
+//! Each exported object gets rendered into its own rectangle of a single image, and a JSON
+//! file maps the ids of the objects to their rectangles.  The rectangles are packed in
+//! rows, tallest first, which wastes little space for the similarly-sized icons that
+//! usually go into sprite sheets.
+
+use std::fmt::Write;
+
+/// Transparent pixels between sprites, so that scaling the sheet does not bleed one
+/// sprite into its neighbors.
+const PADDING: u32 = 1;
+
+/// Where a sprite goes in the sheet, in pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Packs sprites of the given `(width, height)` sizes into a sheet.
+///
+/// Returns the size of the sheet, and the placement of each sprite in the same order as
+/// `sizes`.
+pub fn pack(sizes: &[(u32, u32)]) -> ((u32, u32), Vec<Placement>) {
+    let area: u64 = sizes
+        .iter()
+        .map(|&(w, h)| u64::from(w + PADDING) * u64::from(h + PADDING))
+        .sum();
+
+    let widest = sizes.iter().map(|&(w, _)| w).max().unwrap_or(0);
+
+    // Aim for a roughly square sheet.
+    let row_width = widest.max((area as f64).sqrt().ceil() as u32);
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut placements = vec![
+        Placement {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        };
+        sizes.len()
+    ];
+
+    let (mut x, mut y) = (0, 0);
+    let mut row_height = 0;
+    let (mut sheet_width, mut sheet_height) = (0, 0);
+
+    for i in order {
+        let (width, height) = sizes[i];
+
+        if x > 0 && x + width > row_width {
+            x = 0;
+            y += row_height + PADDING;
+            row_height = 0;
+        }
+
+        placements[i] = Placement {
+            x,
+            y,
+            width,
+            height,
+        };
+
+        sheet_width = sheet_width.max(x + width);
+        sheet_height = sheet_height.max(y + height);
+
+        x += width + PADDING;
+        row_height = row_height.max(height);
+    }
+
+    ((sheet_width.max(1), sheet_height.max(1)), placements)
+}
+
+/// Formats the JSON map from the ids of the sprites, without their `#`, to their
+/// rectangles in the sheet.
+pub fn json_map(ids: &[String], placements: &[Placement]) -> String {
+    let mut json = String::from("{\n");
+
+    for (i, (id, p)) in ids.iter().zip(placements).enumerate() {
+        let id = id.strip_prefix('#').unwrap_or(id);
+        let comma = if i + 1 < ids.len() { "," } else { "" };
+
+        writeln!(
+            json,
+            "  {}: {{\"x\": {}, \"y\": {}, \"width\": {}, \"height\": {}}}{}",
+            json_string(id),
+            p.x,
+            p.y,
+            p.width,
+            p.height,
+            comma
+        )
+        .unwrap();
+    }
+
+    json.push_str("}\n");
+    json
+}
+
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c < ' ' => write!(quoted, "\\u{:04x}", u32::from(c)).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlap(a: &Placement, b: &Placement) -> bool {
+        a.x < b.x + b.width && b.x < a.x + a.width && a.y < b.y + b.height && b.y < a.y + a.height
+    }
+
+    #[test]
+    fn packs_without_overlaps() {
+        let sizes = [(16, 16), (32, 8), (10, 40), (16, 16), (64, 2)];
+        let ((width, height), placements) = pack(&sizes);
+
+        for (i, a) in placements.iter().enumerate() {
+            assert_eq!((a.width, a.height), sizes[i]);
+            assert!(a.x + a.width <= width && a.y + a.height <= height);
+
+            for b in &placements[i + 1..] {
+                assert!(!overlap(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn packs_single_sprite_exactly() {
+        let (size, placements) = pack(&[(20, 30)]);
+        assert_eq!(size, (20, 30));
+        assert_eq!(
+            placements,
+            vec![Placement {
+                x: 0,
+                y: 0,
+                width: 20,
+                height: 30
+            }]
+        );
+    }
+
+    #[test]
+    fn formats_json_map() {
+        let ids = vec![String::from("#one"), String::from("t\"wo")];
+        let placements = [
+            Placement {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 2,
+            },
+            Placement {
+                x: 2,
+                y: 0,
+                width: 3,
+                height: 4,
+            },
+        ];
+
+        assert_eq!(
+            json_map(&ids, &placements),
+            "{\n  \"one\": {\"x\": 0, \"y\": 0, \"width\": 1, \"height\": 2},\n  \
+             \"t\\\"wo\": {\"x\": 2, \"y\": 0, \"width\": 3, \"height\": 4}\n}\n"
+        );
+    }
+}
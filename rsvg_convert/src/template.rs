@@ -0,0 +1,224 @@
+//! Output filename templates for converting many files at once.
+//!
+//! An output filename like `out/{stem}@{zoom}x.png` makes each input file get converted
+//! to its own output file, with the placeholders replaced by values for that input.
+
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+
+    /// Input file name without its extension.
+    Stem,
+
+    /// Input file name.
+    Name,
+
+    /// Position of the input file on the command line, starting at 1.
+    Index,
+
+    /// Zoom factor.
+    Zoom,
+
+    /// Id of the exported object, without the `#`.
+    Id,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputTemplate {
+    pieces: Vec<Piece>,
+}
+
+/// Values that replace the placeholders for one input file.
+pub struct TemplateValues<'a> {
+    pub name: &'a Path,
+    pub index: usize,
+    pub zoom: f64,
+    pub id: Option<&'a str>,
+}
+
+impl OutputTemplate {
+    /// Whether an output filename should be treated as a template.
+    pub fn is_template(output: &Path) -> bool {
+        output.to_string_lossy().contains('{')
+    }
+
+    /// Whether the template has an `{id}` placeholder, so that each exported object can
+    /// go to its own file.
+    pub fn uses_id(&self) -> bool {
+        self.pieces.contains(&Piece::Id)
+    }
+
+    pub fn parse(template: &str) -> Result<OutputTemplate, Error> {
+        let mut pieces = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                pieces.push(Piece::Text(rest[..start].to_string()));
+            }
+
+            let end = rest[start..].find('}').ok_or_else(|| {
+                Error(format!(
+                    "Unterminated placeholder in output template \"{template}\""
+                ))
+            })? + start;
+
+            let piece = match &rest[start + 1..end] {
+                "stem" => Piece::Stem,
+                "name" => Piece::Name,
+                "index" => Piece::Index,
+                "zoom" => Piece::Zoom,
+                "id" => Piece::Id,
+                other => {
+                    return Err(Error(format!(
+                        "Unknown placeholder {{{other}}} in output template; \
+                         use {{stem}}, {{name}}, {{index}}, {{zoom}}, or {{id}}"
+                    )))
+                }
+            };
+
+            pieces.push(piece);
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            pieces.push(Piece::Text(rest.to_string()));
+        }
+
+        Ok(OutputTemplate { pieces })
+    }
+
+    /// Expands the placeholders into a path.
+    ///
+    /// Ids may come from the input file itself, so ids that would make the output go to
+    /// another directory, or that are not valid in file names, are rejected.
+    pub fn expand(&self, values: &TemplateValues<'_>) -> Result<PathBuf, Error> {
+        let mut s = String::new();
+
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => s.push_str(text),
+                Piece::Stem => s.push_str(
+                    &values
+                        .name
+                        .file_stem()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                ),
+                Piece::Name => s.push_str(&values.name.to_string_lossy()),
+                Piece::Index => s.push_str(&values.index.to_string()),
+                Piece::Zoom => s.push_str(&values.zoom.to_string()),
+                Piece::Id => {
+                    let id = values.id.unwrap_or_default();
+                    let id = id.strip_prefix('#').unwrap_or(id);
+
+                    if id == "." || id == ".." || id.contains(['/', '\\', '\0']) {
+                        return Err(Error(format!(
+                            "The id \"{id}\" cannot be used in an output file name."
+                        )));
+                    }
+
+                    s.push_str(id);
+                }
+            }
+        }
+
+        Ok(PathBuf::from(s))
+    }
+}
+
+impl std::fmt::Display for OutputTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(text) => text.fmt(f)?,
+                Piece::Stem => "{stem}".fmt(f)?,
+                Piece::Name => "{name}".fmt(f)?,
+                Piece::Index => "{index}".fmt(f)?,
+                Piece::Zoom => "{zoom}".fmt(f)?,
+                Piece::Id => "{id}".fmt(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(template: &str, name: &str, index: usize, zoom: f64) -> PathBuf {
+        OutputTemplate::parse(template)
+            .unwrap()
+            .expand(&TemplateValues {
+                name: Path::new(name),
+                index,
+                zoom,
+                id: None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn expands_placeholders() {
+        assert_eq!(
+            expand("out/{stem}@{zoom}x.png", "icon.svg", 1, 2.0),
+            PathBuf::from("out/icon@2x.png")
+        );
+        assert_eq!(
+            expand("{index}-{name}.pdf", "a.b.svg", 3, 1.0),
+            PathBuf::from("3-a.b.svg.pdf")
+        );
+        assert_eq!(
+            expand("{stem}_{zoom}", "icon.svg", 1, 1.5),
+            PathBuf::from("icon_1.5")
+        );
+    }
+
+    #[test]
+    fn expands_id_without_hash() {
+        let template = OutputTemplate::parse("{stem}-{id}.png").unwrap();
+        assert!(template.uses_id());
+
+        assert_eq!(
+            template
+                .expand(&TemplateValues {
+                    name: Path::new("icons.svg"),
+                    index: 1,
+                    zoom: 1.0,
+                    id: Some("#star"),
+                })
+                .unwrap(),
+            PathBuf::from("icons-star.png")
+        );
+
+        assert!(!OutputTemplate::parse("{stem}.png").unwrap().uses_id());
+    }
+
+    #[test]
+    fn rejects_ids_that_are_not_file_names() {
+        let template = OutputTemplate::parse("out/{id}/icon.png").unwrap();
+
+        for id in ["#../../home", "#a/b", "#a\\b", "#..", "#.", "#a\0b"] {
+            assert!(template
+                .expand(&TemplateValues {
+                    name: Path::new("icons.svg"),
+                    index: 1,
+                    zoom: 1.0,
+                    id: Some(id),
+                })
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn detects_invalid_templates() {
+        assert!(OutputTemplate::parse("{stem").is_err());
+        assert!(OutputTemplate::parse("{size}.png").is_err());
+    }
+}
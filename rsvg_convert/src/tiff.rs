@@ -0,0 +1,133 @@
+//! TIFF output.
+//!
+//! Each page is rendered to an image surface, and kept in memory until all of them can be
+//! written out as the images of a multi-page TIFF.  Pages can have different sizes.
+
+use std::io::Write;
+
+use crate::animation::rgba_pixels;
+use crate::Error;
+
+/// Compression for the image data of TIFF output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TiffCompression {
+    None,
+    Lzw,
+    Deflate,
+}
+
+struct Page {
+    /// Non-premultiplied RGBA pixels.
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+/// Pages of a TIFF that have been rendered so far.
+pub struct Pages {
+    compression: TiffCompression,
+    dpi_x: f64,
+    dpi_y: f64,
+    pages: Vec<Page>,
+}
+
+impl Pages {
+    pub fn new(compression: TiffCompression, dpi_x: f64, dpi_y: f64) -> Pages {
+        Pages {
+            compression,
+            dpi_x,
+            dpi_y,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Adds the contents of `surface` as the next page.
+    pub fn push(&mut self, surface: &cairo::ImageSurface) -> Result<(), Error> {
+        let rgba = rgba_pixels(surface)?;
+        self.pages.push(Page {
+            rgba,
+            width: surface.width() as u32,
+            height: surface.height() as u32,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "tiff")]
+    pub fn encode<W: Write>(self, mut w: W) -> Result<(), Error> {
+        use ::tiff::encoder::compression::{Deflate, Lzw, Uncompressed};
+        use std::io::Cursor;
+
+        // The encoder needs to seek back to write the offsets of each image, and the
+        // output may be a pipe, so encode into memory first.
+        let mut buf = Cursor::new(Vec::new());
+
+        {
+            let mut encoder = ::tiff::encoder::TiffEncoder::new(&mut buf)?;
+
+            for page in &self.pages {
+                match self.compression {
+                    TiffCompression::None => self.write_page(&mut encoder, page, Uncompressed)?,
+                    TiffCompression::Lzw => self.write_page(&mut encoder, page, Lzw)?,
+                    TiffCompression::Deflate => {
+                        self.write_page(&mut encoder, page, Deflate::default())?
+                    }
+                }
+            }
+        }
+
+        w.write_all(buf.get_ref())
+            .map_err(|e| Error(format!("{e}")))?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "tiff")]
+    fn write_page<W, D>(
+        &self,
+        encoder: &mut ::tiff::encoder::TiffEncoder<W>,
+        page: &Page,
+        compression: D,
+    ) -> Result<(), Error>
+    where
+        W: Write + std::io::Seek,
+        D: ::tiff::encoder::compression::Compression,
+    {
+        use ::tiff::encoder::colortype::RGBA8;
+        use ::tiff::tags::{ResolutionUnit, Tag};
+
+        let mut image =
+            encoder.new_image_with_compression::<RGBA8, D>(page.width, page.height, compression)?;
+
+        // The alpha channel is not premultiplied.
+        image.encoder().write_tag(Tag::ExtraSamples, 2u16)?;
+
+        image.resolution_unit(ResolutionUnit::Inch);
+        image.x_resolution(resolution(self.dpi_x));
+        image.y_resolution(resolution(self.dpi_y));
+
+        image.write_data(&page.rgba)?;
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "tiff"))]
+    pub fn encode<W: Write>(self, _w: W) -> Result<(), Error> {
+        Err(Error("unsupported format".to_string()))
+    }
+}
+
+/// Converts a resolution in dots per inch to a TIFF rational, with two decimals.
+#[cfg(feature = "tiff")]
+fn resolution(dpi: f64) -> ::tiff::encoder::Rational {
+    ::tiff::encoder::Rational {
+        n: (dpi * 100.0).round() as u32,
+        d: 100,
+    }
+}
+
+#[cfg(feature = "tiff")]
+impl From<::tiff::TiffError> for Error {
+    fn from(e: ::tiff::TiffError) -> Self {
+        Self(format!("{e}"))
+    }
+}
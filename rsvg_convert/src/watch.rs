@@ -0,0 +1,88 @@
+//! Watching the input files for changes, for `--watch`.
+//!
+//! The files are polled for their modification time and size, which works the same on
+//! every platform and with editors that save by writing a new file and renaming it over
+//! the old one.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// How often to check the files for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// What is known about a file to tell whether it changed; `None` if it does not exist.
+type Stamp = Option<(SystemTime, u64)>;
+
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    stamps: Vec<Stamp>,
+}
+
+impl Watcher {
+    /// Starts watching `paths` as they are now.
+    pub fn new(paths: Vec<PathBuf>) -> Watcher {
+        let stamps = current_stamps(&paths);
+
+        Watcher { paths, stamps }
+    }
+
+    /// Blocks until any of the files gets modified, created, or removed.
+    ///
+    /// Editors may write a file in several steps, so this waits until the files stop
+    /// changing for one poll interval before returning.
+    pub fn wait_for_change(&mut self) {
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            if self.update() {
+                break;
+            }
+        }
+
+        while self.update() {
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Updates the stamps, and returns whether any of them changed.
+    fn update(&mut self) -> bool {
+        let stamps = current_stamps(&self.paths);
+        let changed = stamps != self.stamps;
+        self.stamps = stamps;
+        changed
+    }
+}
+
+fn current_stamps(paths: &[PathBuf]) -> Vec<Stamp> {
+    paths
+        .iter()
+        .map(|p| {
+            let metadata = std::fs::metadata(p).ok()?;
+            Some((metadata.modified().ok()?, metadata.len()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watched.svg");
+
+        let mut watcher = Watcher::new(vec![path.clone()]);
+        assert!(!watcher.update());
+
+        std::fs::write(&path, "<svg/>").unwrap();
+        assert!(watcher.update());
+        assert!(!watcher.update());
+
+        std::fs::write(&path, "<svg></svg>").unwrap();
+        assert!(watcher.update());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.update());
+    }
+}
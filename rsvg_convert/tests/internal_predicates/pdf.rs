@@ -44,6 +44,14 @@ impl PdfPredicate {
         }
     }
 
+    /// Checks a text field of the document information, like `Title` or `Author`.
+    pub fn with_info(self, key: &str, value: &str) -> DetailPredicate<Self> {
+        DetailPredicate::<Self> {
+            p: self,
+            d: Detail::Info(key.to_string(), value.to_string()),
+        }
+    }
+
     pub fn with_link(self, link: &str) -> DetailPredicate<Self> {
         DetailPredicate::<Self> {
             p: self,
@@ -99,6 +107,7 @@ enum Detail {
     PageCount(usize),
     PageSize(Dimensions, usize),
     CreationDate(DateTime<Utc>),
+    Info(String, String),
     Link(String),
     Text(String),
     Version(String),
@@ -161,6 +170,7 @@ trait Details {
     fn get_page_count(&self) -> usize;
     fn get_page_size(&self, idx: usize) -> Option<Dimensions>;
     fn get_creation_date(&self) -> Option<DateTime<Utc>>;
+    fn get_info(&self, key: &str) -> Option<String>;
     fn get_from_trailer<'a>(&'a self, key: &[u8]) -> lopdf::Result<&'a lopdf::Object>;
     fn get_from_page<'a>(&'a self, idx: usize, key: &[u8]) -> lopdf::Result<&'a lopdf::Object>;
 }
@@ -171,6 +181,7 @@ impl DetailPredicate<PdfPredicate> {
             Detail::PageCount(n) => doc.get_page_count() == *n,
             Detail::PageSize(d, idx) => doc.get_page_size(*idx).map_or(false, |dim| dim == *d),
             Detail::CreationDate(d) => doc.get_creation_date().map_or(false, |date| date == *d),
+            Detail::Info(key, value) => doc.get_info(key).map_or(false, |v| v == *value),
             Detail::Link(link) => document_has_link(doc, link),
             Detail::Text(text) => document_has_text(doc, text),
             Detail::Version(version) => document_has_version(doc, version),
@@ -203,6 +214,10 @@ impl DetailPredicate<PdfPredicate> {
                 "actual creation date",
                 format!("{:?}", doc.get_creation_date()),
             ),
+            Detail::Info(key, _) => Product::new(
+                "actual document information",
+                format!("{:?}", doc.get_info(key)),
+            ),
             Detail::Link(_) => Product::new(
                 "actual link contents",
                 "FIXME: who knows, but it's not what we expected".to_string(),
@@ -257,6 +272,13 @@ impl Details for lopdf::Document {
         }
     }
 
+    fn get_info(&self, key: &str) -> Option<String> {
+        self.get_from_trailer(key.as_bytes())
+            .and_then(|obj| obj.as_str())
+            .ok()
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
     fn get_from_trailer<'a>(&'a self, key: &[u8]) -> lopdf::Result<&'a lopdf::Object> {
         let id = self.trailer.get(b"Info")?.as_reference()?;
         self.get_object(id)?.as_dict()?.get(key)
@@ -303,6 +325,7 @@ impl fmt::Display for DetailPredicate<PdfPredicate> {
             Detail::PageCount(n) => write!(f, "is a PDF with {} page(s)", n),
             Detail::PageSize(d, _) => write!(f, "is a PDF sized {}", d),
             Detail::CreationDate(d) => write!(f, "is a PDF created {:?}", d),
+            Detail::Info(k, v) => write!(f, "is a PDF with {} \"{}\"", k, v),
             Detail::Link(l) => write!(f, "is a PDF with a link to {}", l),
             Detail::Text(t) => write!(f, "is a PDF with \"{}\" in its text content", t),
             Detail::Version(v) => write!(f, "is a PDF with version {}", v),
@@ -20,8 +20,9 @@ use url::Url;
 //  - command-line options that affect size (width, height, zoom, resolution) ✔
 //  - pixel dimensions of the output (should be sufficient to do that for PNG) ✔
 //  - limit on output size (32767 pixels) ✔
-//  - output formats (PNG, PDF, PS, EPS, SVG) ✔
+//  - output formats (PNG, PDF, PS, EPS, SVG, GIF, APNG, WebP) ✔
 //  - multi-page output (for PDF) ✔
+//  - pages of a single document (--pages) ✔
 //  - output file option ✔
 //  - SOURCE_DATA_EPOCH environment variable for PDF output ✔
 //  - background color option ✔
@@ -201,6 +202,304 @@ fn output_format_svg_short_option() {
         .stdout(file::is_svg());
 }
 
+#[cfg(feature = "jpeg")]
+fn decode_jpeg(data: &[u8]) -> image::RgbImage {
+    image::load_from_memory_with_format(data, image::ImageFormat::Jpeg)
+        .unwrap()
+        .to_rgb8()
+}
+
+#[cfg(feature = "jpeg")]
+#[test]
+fn output_format_jpeg_is_composited_over_white() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--format=jpeg")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let image = decode_jpeg(&output.stdout);
+    assert_eq!(image.dimensions(), (10, 10));
+    assert!(image.pixels().all(|p| p.0.iter().all(|&c| c >= 250)));
+}
+
+#[cfg(feature = "jpeg")]
+#[test]
+fn output_format_jpeg_uses_background_color() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--format=jpeg")
+        .arg("--background-color=blue")
+        .arg("--jpeg-quality=100")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let image = decode_jpeg(&output.stdout);
+    let [r, g, b] = image.get_pixel(5, 5).0;
+    assert!(r < 5 && g < 5 && b > 250);
+}
+
+#[test]
+fn jpeg_quality_is_only_for_jpeg_output() {
+    RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--format=png")
+        .arg("--jpeg-quality=50")
+        .assert()
+        .failure()
+        .stderr(contains("--jpeg-quality is only allowed for JPEG output"));
+}
+
+#[test]
+fn jpeg_quality_must_be_in_range() {
+    RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--format=jpeg")
+        .arg("--jpeg-quality=101")
+        .assert()
+        .failure();
+}
+
+#[cfg(feature = "tiff")]
+fn decode_tiff_pages(data: &[u8]) -> Vec<((u32, u32), u16)> {
+    use tiff::decoder::Decoder;
+    use tiff::tags::Tag;
+
+    let mut decoder = Decoder::new(std::io::Cursor::new(data)).unwrap();
+    let mut pages = Vec::new();
+
+    loop {
+        let compression = decoder.get_tag_u32(Tag::Compression).unwrap() as u16;
+        pages.push((decoder.dimensions().unwrap(), compression));
+
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().unwrap();
+    }
+
+    pages
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+fn output_format_tiff_has_one_page_per_input() {
+    let output = RsvgConvert::new()
+        .arg("--format=tiff")
+        .arg("tests/fixtures/empty-10x10.svg")
+        .arg("tests/fixtures/dimensions-in.svg")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let pages = decode_tiff_pages(&output.stdout);
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].0, (10, 10));
+    assert_eq!(pages[1].0, (96, 96));
+}
+
+#[cfg(feature = "tiff")]
+#[test]
+fn output_format_tiff_compression() {
+    for (name, tag) in [("none", 1), ("lzw", 5), ("deflate", 8)] {
+        let output = RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+            .arg("--format=tiff")
+            .arg(format!("--tiff-compression={name}"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let pages = decode_tiff_pages(&output.stdout);
+        assert_eq!(pages, vec![((10, 10), tag)]);
+    }
+}
+
+#[test]
+fn output_format_ico_contains_png_icons() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/dimensions-in.svg")
+        .arg("--format=ico")
+        .arg("--icon-sizes=16,256,32")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let data = output.stdout;
+    assert_eq!(&data[..6], &[0, 0, 1, 0, 3, 0]);
+
+    let entry = |i: usize| {
+        let e = &data[6 + 16 * i..6 + 16 * (i + 1)];
+        let len = u32::from_le_bytes(e[8..12].try_into().unwrap()) as usize;
+        let offset = u32::from_le_bytes(e[12..16].try_into().unwrap()) as usize;
+        (e[0], &data[offset..offset + len])
+    };
+
+    for (i, (dimension, size)) in [(16, 16), (32, 32), (0, 256)].into_iter().enumerate() {
+        let (width, png_data) = entry(i);
+        assert_eq!(width, dimension);
+
+        let decoder = png::Decoder::new(png_data);
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().size(), (size, size));
+    }
+}
+
+#[test]
+fn output_format_icns_contains_png_icons() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/dimensions-in.svg")
+        .arg("--format=icns")
+        .arg("--icon-sizes=16,1024")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let data = output.stdout;
+    assert_eq!(&data[..4], b"icns");
+    assert_eq!(
+        u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize,
+        data.len()
+    );
+
+    let mut types = Vec::new();
+    let mut pos = 8;
+    while pos < data.len() {
+        let len = u32::from_be_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        types.push(data[pos..pos + 4].to_vec());
+        pos += len;
+    }
+
+    assert_eq!(types, vec![b"icp4".to_vec(), b"ic10".to_vec()]);
+}
+
+#[test]
+fn icon_sizes_must_fit_the_container() {
+    RsvgConvert::new_with_input("tests/fixtures/dimensions-in.svg")
+        .arg("--format=ico")
+        .arg("--icon-sizes=512")
+        .assert()
+        .failure()
+        .stderr(contains("ICO icons must be between 1 and 256 pixels"));
+
+    RsvgConvert::new_with_input("tests/fixtures/dimensions-in.svg")
+        .arg("--format=icns")
+        .arg("--icon-sizes=48")
+        .assert()
+        .failure()
+        .stderr(contains("ICNS icons must be"));
+}
+
+#[test]
+fn icon_sizes_are_only_for_icon_output() {
+    RsvgConvert::new_with_input("tests/fixtures/dimensions-in.svg")
+        .arg("--icon-sizes=16")
+        .assert()
+        .failure()
+        .stderr(contains(
+            "--icon-sizes is only allowed for ICO and ICNS output",
+        ));
+}
+
+#[test]
+fn tiff_compression_is_only_for_tiff_output() {
+    RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--format=png")
+        .arg("--tiff-compression=lzw")
+        .assert()
+        .failure()
+        .stderr(contains(
+            "--tiff-compression is only allowed for TIFF output",
+        ));
+}
+
+#[cfg(feature = "gif")]
+#[test]
+fn output_format_gif() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/animated.svg")
+        .arg("--format=gif")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.starts_with(b"GIF89a"));
+}
+
+#[cfg(feature = "apng")]
+#[test]
+fn output_format_apng() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/animated.svg")
+        .arg("--format=apng")
+        .arg("--frame-rate=4")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let decoder = png::Decoder::new(output.stdout.as_slice());
+    let reader = decoder.read_info().unwrap();
+    let info = reader.info();
+
+    assert_eq!((info.width, info.height), (20, 20));
+
+    // One frame every quarter of a second, plus the final state at 1s.
+    let control = info.animation_control().unwrap();
+    assert_eq!(control.num_frames, 5);
+    assert_eq!(control.num_plays, 1);
+}
+
+#[cfg(feature = "webp-anim")]
+#[test]
+fn output_format_webp_anim() {
+    let output = RsvgConvert::new_with_input("tests/fixtures/animated.svg")
+        .arg("--format=webp-anim")
+        .arg("--frame-rate=4")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let webp = output.stdout.as_slice();
+    assert_eq!(&webp[..4], b"RIFF");
+    assert_eq!(&webp[8..12], b"WEBP");
+
+    let riff_size = u32::from_le_bytes(webp[4..8].try_into().unwrap()) as usize;
+    assert_eq!(riff_size + 8, webp.len());
+
+    let mut chunks = Vec::new();
+    let mut rest = &webp[12..];
+    while !rest.is_empty() {
+        let size = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        chunks.push((&rest[..4], &rest[8..8 + size]));
+        rest = &rest[8 + size + size % 2..];
+    }
+
+    // The canvas size is stored minus one, in 24 bits.
+    let (name, vp8x) = chunks[0];
+    assert_eq!(name, b"VP8X");
+    assert_eq!(&vp8x[4..10], &[19, 0, 0, 19, 0, 0]);
+
+    // The animation plays once.
+    let (name, anim) = chunks[1];
+    assert_eq!(name, b"ANIM");
+    assert_eq!(&anim[4..6], &[1, 0]);
+
+    // One frame every quarter of a second, plus the final state at 1s.
+    assert_eq!(chunks.len(), 2 + 5);
+    assert!(chunks[2..].iter().all(|(name, _)| *name == b"ANMF"));
+}
+
+#[test]
+fn frame_rate_must_be_positive() {
+    RsvgConvert::new_with_input("tests/fixtures/animated.svg")
+        .arg("--frame-rate=0")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn frame_rate_must_be_in_range() {
+    for fps in ["1e-300", "100000"] {
+        RsvgConvert::new_with_input("tests/fixtures/animated.svg")
+            .arg(format!("--frame-rate={fps}"))
+            .assert()
+            .failure()
+            .stderr(contains("Invalid frame rate"));
+    }
+}
+
 #[cfg(system_deps_have_cairo_svg)]
 #[test]
 fn user_specified_width_and_height() {
@@ -300,6 +599,102 @@ fn output_file_short_option() {
     std::fs::remove_file(&output).unwrap();
 }
 
+#[test]
+fn output_template_converts_each_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("out/{index}-{stem}@{zoom}x.png");
+
+    RsvgConvert::new()
+        .arg("--zoom=2")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/empty-10x10.svg")
+        .arg("tests/fixtures/dimensions-in.svg")
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    let first = std::fs::read(dir.path().join("out/1-empty-10x10@2x.png")).unwrap();
+    let second = std::fs::read(dir.path().join("out/2-dimensions-in@2x.png")).unwrap();
+
+    assert!(file::is_png().with_size(20, 20).eval(first.as_slice()));
+    assert!(file::is_png().with_size(192, 192).eval(second.as_slice()));
+}
+
+#[test]
+fn output_template_converts_inputs_in_parallel() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{index}.png");
+
+    let mut command = RsvgConvert::new();
+    command
+        .arg("--jobs=3")
+        .arg(format!("--output={}", template.display()));
+
+    for _ in 0..8 {
+        command.arg("tests/fixtures/empty-10x10.svg");
+    }
+
+    command.assert().success().stdout(is_empty());
+
+    for i in 1..=8 {
+        let output = std::fs::read(dir.path().join(format!("{i}.png"))).unwrap();
+        assert!(file::is_png().with_size(10, 10).eval(output.as_slice()));
+    }
+}
+
+#[test]
+fn output_template_reports_first_failed_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{stem}.png");
+
+    RsvgConvert::new()
+        .arg("--jobs=2")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/empty-10x10.svg")
+        .arg("tests/fixtures/does-not-exist.svg")
+        .arg("tests/fixtures/dimensions-in.svg")
+        .assert()
+        .failure()
+        .stderr(contains("does-not-exist.svg"));
+}
+
+#[test]
+fn output_template_rejects_inputs_with_the_same_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{zoom}.png");
+
+    RsvgConvert::new()
+        .arg("--jobs=2")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/empty-10x10.svg")
+        .arg("tests/fixtures/dimensions-in.svg")
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Both tests/fixtures/empty-10x10.svg and tests/fixtures/dimensions-in.svg would be written to",
+        ));
+
+    assert!(!dir.path().join("1.png").exists());
+}
+
+#[test]
+fn jobs_is_only_allowed_with_output_template() {
+    RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--jobs=2")
+        .assert()
+        .failure()
+        .stderr(contains("--jobs is only allowed with an output"));
+}
+
+#[test]
+fn output_template_rejects_unknown_placeholders() {
+    RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--output={size}.png")
+        .assert()
+        .failure()
+        .stderr(contains("Unknown placeholder {size}"));
+}
+
 #[test]
 fn overwrites_existing_output_file() {
     let output = {
@@ -349,7 +744,7 @@ fn multiple_input_files_not_allowed_for_png_output() {
         .assert()
         .failure()
         .stderr(contains(
-            "Multiple SVG files are only allowed for PDF and (E)PS output",
+            "Multiple SVG files are only allowed for PDF, (E)PS, and TIFF output",
         ));
 }
 
@@ -433,6 +828,51 @@ fn multiple_input_files_create_multi_page_pdf_output_fixed_size() {
         );
 }
 
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn pages_create_multi_page_pdf_output() {
+    let input = Path::new("tests/fixtures/pages.svg");
+    RsvgConvert::new()
+        .arg("--format=pdf")
+        .arg("--pages")
+        .arg(input)
+        .assert()
+        .success()
+        .stdout(
+            file::is_pdf()
+                .with_page_count(3)
+                .and(file::is_pdf().with_page_size(0, 150.0, 75.0))
+                .and(file::is_pdf().with_page_size(1, 150.0, 75.0))
+                .and(file::is_pdf().with_page_size(2, 75.0, 150.0)),
+        );
+}
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn pages_without_pages_render_whole_document() {
+    let input = Path::new("tests/fixtures/example.svg");
+    RsvgConvert::new()
+        .arg("--format=pdf")
+        .arg("--pages")
+        .arg(input)
+        .assert()
+        .success()
+        .stdout(file::is_pdf().with_page_count(1));
+}
+
+#[test]
+fn pages_not_allowed_for_png_output() {
+    let input = Path::new("tests/fixtures/pages.svg");
+    RsvgConvert::new()
+        .arg("--pages")
+        .arg(input)
+        .assert()
+        .failure()
+        .stderr(contains(
+            "--pages is only allowed for PDF, PS, and TIFF output",
+        ));
+}
+
 #[cfg(system_deps_have_cairo_pdf)]
 #[test]
 fn pdf_has_link() {
@@ -876,6 +1316,178 @@ fn does_not_clip_partial_coverage_pixels() {
         .stdout(file::is_png().with_size(2, 2));
 }
 
+#[test]
+fn supersample_keeps_output_size() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--supersample=4")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(96, 384));
+}
+
+#[test]
+fn supersample_with_high_quality_keeps_output_size() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--supersample=4")
+        .arg("--quality=high")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(96, 384));
+}
+
+#[test]
+fn unknown_quality_is_an_error() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--quality=ultra")
+        .assert()
+        .failure();
+}
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn supersample_is_only_for_png() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--format=pdf")
+        .arg("--supersample=2")
+        .assert()
+        .failure()
+        .stderr(contains("only supported for PNG"));
+}
+
+#[test]
+fn simulate_cvd_keeps_output_size() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--simulate-cvd=deuteranopia")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(96, 384));
+}
+
+#[test]
+fn unknown_cvd_is_an_error() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--simulate-cvd=achromatopsia")
+        .assert()
+        .failure();
+}
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn simulate_cvd_is_only_for_raster_output() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--format=pdf")
+        .arg("--simulate-cvd=protanopia")
+        .assert()
+        .failure()
+        .stderr(contains("only supported for raster output formats"));
+}
+
+#[test]
+fn linear_light_keeps_output_size() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--linear-light")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(96, 384));
+}
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn linear_light_is_only_for_raster_output() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--format=pdf")
+        .arg("--linear-light")
+        .assert()
+        .failure()
+        .stderr(contains("only supported for raster output formats"));
+}
+
+#[test]
+fn simplify_paths_for_pdf() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--format=pdf")
+        .arg("--simplify-paths=0.5")
+        .assert()
+        .success()
+        .stdout(file::is_pdf());
+}
+
+#[test]
+fn simplify_paths_is_only_for_vector_formats() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--simplify-paths=0.5")
+        .assert()
+        .failure()
+        .stderr(contains("only supported for vector output formats"));
+}
+
+#[test]
+fn simplify_paths_requires_positive_tolerance() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--format=pdf")
+        .arg("--simplify-paths=0")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn error_placeholders_option() {
+    RsvgConvert::new_with_input("tests/fixtures/example.svg")
+        .arg("--error-placeholders")
+        .assert()
+        .success()
+        .stdout(file::is_png());
+}
+
+#[test]
+fn list_ids_prints_ids_and_element_names() {
+    RsvgConvert::new_with_input("tests/fixtures/list-ids-and-fonts.svg")
+        .arg("--list-ids")
+        .assert()
+        .success()
+        .stdout("layer\tg\nbox\trect\ntitle\ttext\n");
+}
+
+#[test]
+fn list_fonts_prints_font_families() {
+    RsvgConvert::new_with_input("tests/fixtures/list-ids-and-fonts.svg")
+        .arg("--list-fonts")
+        .assert()
+        .success()
+        .stdout("DejaVu Serif\nserif\nCantarell\n");
+}
+
+#[test]
+fn list_ids_and_list_fonts_conflict() {
+    RsvgConvert::new_with_input("tests/fixtures/list-ids-and-fonts.svg")
+        .arg("--list-ids")
+        .arg("--list-fonts")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn watch_needs_an_output_file() {
+    RsvgConvert::new()
+        .arg("--watch")
+        .arg("tests/fixtures/dpi.svg")
+        .assert()
+        .failure()
+        .stderr(contains("--watch needs an output file"));
+}
+
+#[test]
+fn watch_cannot_read_from_stdin() {
+    let output = Builder::new().suffix(".png").tempfile().unwrap();
+
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--watch")
+        .arg(format!("--output={}", output.path().display()))
+        .assert()
+        .failure()
+        .stderr(contains("--watch cannot be used when reading from stdin"));
+}
+
 #[test]
 fn background_color_option_with_valid_color() {
     RsvgConvert::accepts_arg("--background-color=LimeGreen");
@@ -1089,6 +1701,203 @@ fn export_id_option_error() {
         .stderr(starts_with("File stdin does not have an object with id \""));
 }
 
+#[test]
+fn export_ids_are_expanded_in_template() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{stem}-{id}.png");
+
+    RsvgConvert::new()
+        .arg("--export-id=one")
+        .arg("-i")
+        .arg("two")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/dpi.svg")
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    for id in ["one", "two"] {
+        let output = std::fs::read(dir.path().join(format!("dpi-{id}.png"))).unwrap();
+        assert!(file::is_png().with_size(100, 200).eval(output.as_slice()));
+    }
+}
+
+#[test]
+fn several_export_ids_need_id_placeholder() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--export-id=one,two")
+        .assert()
+        .failure()
+        .stderr(contains("template with {id}"));
+}
+
+#[test]
+fn sprite_sheet_packs_export_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let map = dir.path().join("map.json");
+
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--export-id=one,two")
+        .arg(format!("--sprite-sheet={}", map.display()))
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(201, 200));
+
+    let map = std::fs::read_to_string(map).unwrap();
+    assert!(map.contains("\"one\": {\"x\": 0, \"y\": 0, \"width\": 100, \"height\": 200}"));
+    assert!(map.contains("\"two\": {\"x\": 101, \"y\": 0, \"width\": 100, \"height\": 200}"));
+}
+
+#[test]
+fn sprite_sheet_needs_export_ids() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--sprite-sheet=map.json")
+        .assert()
+        .failure()
+        .stderr(contains("--sprite-sheet needs the objects to export"));
+}
+
+#[test]
+fn select_exports_matching_elements() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{id}.png");
+
+    RsvgConvert::new()
+        .arg("--select=.icon, #logo")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/select.svg")
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    for (id, width, height) in [("logo", 40, 20), ("home", 16, 16), ("search", 24, 24)] {
+        let output = std::fs::read(dir.path().join(format!("{id}.png"))).unwrap();
+        assert!(file::is_png()
+            .with_size(width, height)
+            .eval(output.as_slice()));
+    }
+}
+
+#[test]
+fn select_needs_id_placeholder() {
+    RsvgConvert::new_with_input("tests/fixtures/select.svg")
+        .arg("--select=.icon")
+        .assert()
+        .failure()
+        .stderr(contains("template with {id}"));
+}
+
+#[test]
+fn select_elements_need_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{id}.png");
+
+    RsvgConvert::new()
+        .arg("--select=.decoration")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/select.svg")
+        .assert()
+        .failure()
+        .stderr(contains("has no id"));
+}
+
+#[test]
+fn select_rejects_ids_that_are_not_file_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("out").join("{id}.png");
+
+    RsvgConvert::new()
+        .arg("--select=.sneaky")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/select.svg")
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used in an output file name"));
+
+    assert!(!dir.path().join("escape.png").exists());
+}
+
+#[test]
+fn select_reports_invalid_selector() {
+    let dir = tempfile::tempdir().unwrap();
+    let template = dir.path().join("{id}.png");
+
+    RsvgConvert::new()
+        .arg("--select=.icon {")
+        .arg(format!("--output={}", template.display()))
+        .arg("tests/fixtures/select.svg")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid selector"));
+}
+
+#[test]
+fn select_works_with_sprite_sheet() {
+    let dir = tempfile::tempdir().unwrap();
+    let map = dir.path().join("map.json");
+
+    RsvgConvert::new_with_input("tests/fixtures/select.svg")
+        .arg("--select=.icon")
+        .arg(format!("--sprite-sheet={}", map.display()))
+        .assert()
+        .success()
+        .stdout(file::is_png());
+
+    let map = std::fs::read_to_string(map).unwrap();
+    assert!(map.contains("\"home\""));
+    assert!(map.contains("\"search\""));
+    assert!(!map.contains("\"logo\""));
+}
+
+#[test]
+fn export_area_option() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--dpi-x=100")
+        .arg("--dpi-y=100")
+        .arg("--export-area=10:20:60:120")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(50, 100));
+}
+
+#[test]
+fn export_area_uses_viewbox_units() {
+    RsvgConvert::new_with_input("tests/fixtures/bug521-with-viewbox.svg")
+        .arg("--export-area=500:600:1200:1000")
+        .arg("--zoom=2")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(140, 80));
+}
+
+#[test]
+fn export_area_uses_preserve_aspect_ratio() {
+    RsvgConvert::new_with_input("tests/fixtures/viewbox-aspect-none.svg")
+        .arg("--export-area=10:20:60:70")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(100, 50));
+}
+
+#[test]
+fn export_area_option_error() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--export-area=10:20:5:40")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid area"));
+}
+
+#[test]
+fn export_area_conflicts_with_export_id() {
+    RsvgConvert::new_with_input("tests/fixtures/dpi.svg")
+        .arg("--export-area=0:0:10:10")
+        .arg("--export-id=two")
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+}
+
 #[test]
 fn unlimited_option() {
     RsvgConvert::accepts_arg("--unlimited");
@@ -1227,3 +2036,66 @@ fn multiple_stdin_arguments_not_allowed() {
         .failure()
         .stderr(contains("Only one input file can be read from stdin"));
 }
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn pdf_document_information() {
+    RsvgConvert::new_with_input("tests/fixtures/bug521-with-viewbox.svg")
+        .arg("--format=pdf")
+        .arg("--pdf-title=Quarterly report")
+        .arg("--pdf-author=Jane Doe")
+        .arg("--pdf-subject=Sales")
+        .arg("--pdf-keywords=sales, charts")
+        .assert()
+        .success()
+        .stdout(
+            file::is_pdf()
+                .with_info("Title", "Quarterly report")
+                .and(file::is_pdf().with_info("Author", "Jane Doe"))
+                .and(file::is_pdf().with_info("Subject", "Sales"))
+                .and(file::is_pdf().with_info("Keywords", "sales, charts")),
+        );
+}
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn pdf_creation_date_overrides_source_date_epoch() {
+    RsvgConvert::new_with_input("tests/fixtures/bug521-with-viewbox.svg")
+        .env("SOURCE_DATE_EPOCH", "1581411039")
+        .arg("--format=pdf")
+        .arg("--pdf-creation-date=2024-05-17")
+        .assert()
+        .success()
+        .stdout(
+            file::is_pdf().with_creation_date(Utc.with_ymd_and_hms(2024, 5, 17, 0, 0, 0).unwrap()),
+        );
+
+    RsvgConvert::new_with_input("tests/fixtures/bug521-with-viewbox.svg")
+        .arg("--format=pdf")
+        .arg("--pdf-creation-date=2024-05-17T12:30:00+02:00")
+        .assert()
+        .success()
+        .stdout(
+            file::is_pdf()
+                .with_creation_date(Utc.with_ymd_and_hms(2024, 5, 17, 10, 30, 0).unwrap()),
+        );
+}
+
+#[test]
+fn pdf_creation_date_must_be_valid() {
+    RsvgConvert::new_with_input("tests/fixtures/bug521-with-viewbox.svg")
+        .arg("--format=pdf")
+        .arg("--pdf-creation-date=yesterday")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn pdf_options_are_only_for_pdf_output() {
+    RsvgConvert::new_with_input("tests/fixtures/empty-10x10.svg")
+        .arg("--format=png")
+        .arg("--pdf-title=Icon")
+        .assert()
+        .failure()
+        .stderr(contains("only allowed for PDF output"));
+}